@@ -0,0 +1,270 @@
+//! Encoding helpers to exchange certificates with an enrolment server
+//! following the conventions of EST ([RFC 7030](https://www.rfc-editor.org/rfc/rfc7030)).
+//!
+//! - Certificate signing requests are sent as base64 encoded DER.
+//! - Certificates are received either as a PEM file, a base64 encoded DER certificate
+//!   (`application/pkix-cert`) or a base64 encoded PKCS#7 certs-only bundle (`application/pkcs7-mime`).
+use crate::CertificateError;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use x509_parser::der_parser::asn1_rs::Any;
+use x509_parser::der_parser::asn1_rs::Class;
+use x509_parser::der_parser::asn1_rs::Tag;
+use x509_parser::prelude::FromDer;
+use x509_parser::prelude::X509Certificate;
+
+const PEM_CERTIFICATE_HEADER: &str = "-----BEGIN CERTIFICATE-----";
+const PEM_CERTIFICATE_FOOTER: &str = "-----END CERTIFICATE-----";
+
+/// Encode a DER certificate signing request as expected by an EST server
+pub fn encode_csr(csr_der: &[u8]) -> String {
+    STANDARD.encode(csr_der)
+}
+
+/// Decode the certificate returned by an enrolment server, returning it as a PEM string
+pub fn decode_certificate(body: &[u8]) -> Result<String, CertificateError> {
+    let body = std::str::from_utf8(body)
+        .map_err(|_| invalid_response("the response is not a text encoded certificate"))?
+        .trim();
+
+    if body.contains(PEM_CERTIFICATE_HEADER) {
+        return Ok(format!("{body}\n"));
+    }
+
+    let base64: String = body.split_whitespace().collect();
+    let der = STANDARD
+        .decode(base64)
+        .map_err(|err| invalid_response(&format!("invalid base64 content: {err}")))?;
+
+    if X509Certificate::from_der(&der).is_ok() {
+        return Ok(pem_from_der(&der));
+    }
+
+    let certificates = certificates_from_pkcs7(&der)?;
+    let certificate = certificates
+        .first()
+        .ok_or_else(|| invalid_response("no certificate found in the PKCS#7 bundle"))?;
+    Ok(pem_from_der(certificate))
+}
+
+/// Encode a DER certificate as PEM
+pub fn pem_from_der(der: &[u8]) -> String {
+    let base64 = STANDARD.encode(der);
+    let mut pem = String::from(PEM_CERTIFICATE_HEADER);
+    pem.push('\n');
+    for line in base64.as_bytes().chunks(64) {
+        // base64 is pure ASCII, hence chunks are valid UTF-8
+        pem.push_str(std::str::from_utf8(line).unwrap_or_default());
+        pem.push('\n');
+    }
+    pem.push_str(PEM_CERTIFICATE_FOOTER);
+    pem.push('\n');
+    pem
+}
+
+/// Extract the DER certificates of a PKCS#7 certs-only bundle
+///
+/// ```text
+/// ContentInfo ::= SEQUENCE {
+///     contentType OBJECT IDENTIFIER,
+///     content [0] EXPLICIT SignedData }
+///
+/// SignedData ::= SEQUENCE {
+///     version INTEGER,
+///     digestAlgorithms SET,
+///     encapContentInfo SEQUENCE,
+///     certificates [0] IMPLICIT SET OF Certificate OPTIONAL,
+///     ... }
+/// ```
+pub fn certificates_from_pkcs7(der: &[u8]) -> Result<Vec<Vec<u8>>, CertificateError> {
+    const SEQUENCE: (Class, Tag) = (Class::Universal, Tag::Sequence);
+    const SET: (Class, Tag) = (Class::Universal, Tag::Set);
+    const INTEGER: (Class, Tag) = (Class::Universal, Tag::Integer);
+    const OID: (Class, Tag) = (Class::Universal, Tag::Oid);
+    const CONTEXT_0: (Class, Tag) = (Class::ContextSpecific, Tag(0));
+
+    let (content_info, _) = expect_element(der, SEQUENCE)?;
+    let (_, content_info) = expect_element(content_info, OID)?;
+    let (explicit_content, _) = expect_element(content_info, CONTEXT_0)?;
+    let (signed_data, _) = expect_element(explicit_content, SEQUENCE)?;
+    let (_, signed_data) = expect_element(signed_data, INTEGER)?;
+    let (_, signed_data) = expect_element(signed_data, SET)?;
+    let (_, signed_data) = expect_element(signed_data, SEQUENCE)?;
+
+    let mut certificates = Vec::new();
+    if let Ok((mut certs, _)) = expect_element(signed_data, CONTEXT_0) {
+        while !certs.is_empty() {
+            let (_, rest) = expect_element(certs, SEQUENCE)?;
+            let encoded_len = certs.len() - rest.len();
+            certificates.push(certs[..encoded_len].to_vec());
+            certs = rest;
+        }
+    }
+
+    Ok(certificates)
+}
+
+/// Read a DER element of the expected class and tag, returning its content and the remaining bytes
+///
+/// The element header, notably its length, is checked by the DER parser of x509-parser.
+fn expect_element(
+    input: &[u8],
+    (expected_class, expected_tag): (Class, Tag),
+) -> Result<(&[u8], &[u8]), CertificateError> {
+    let (rest, element) = Any::from_der(input)
+        .map_err(|err| invalid_response(&format!("invalid DER content: {err}")))?;
+    if element.class() == expected_class && element.tag() == expected_tag {
+        Ok((element.data, rest))
+    } else {
+        Err(invalid_response("unexpected PKCS#7 structure"))
+    }
+}
+
+fn invalid_response(reason: &str) -> CertificateError {
+    CertificateError::InvalidEnrolmentResponse(reason.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyCertPair;
+    use crate::KeyKind;
+    use crate::NewCertificateConfig;
+    use crate::PemCertificate;
+
+    fn test_certificate() -> String {
+        KeyCertPair::new_selfsigned_certificate(
+            &NewCertificateConfig::default(),
+            "test-device",
+            &KeyKind::New,
+        )
+        .unwrap()
+        .certificate_pem_string()
+        .unwrap()
+    }
+
+    fn der_of(pem: &str) -> Vec<u8> {
+        x509_parser::pem::Pem::iter_from_buffer(pem.as_bytes())
+            .next()
+            .unwrap()
+            .unwrap()
+            .contents
+    }
+
+    /// Wrap a DER element into a new element with the given tag
+    fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+        let mut encoded = vec![tag];
+        let len = content.len();
+        if len < 0x80 {
+            encoded.push(len as u8);
+        } else {
+            let len_bytes: Vec<u8> = len
+                .to_be_bytes()
+                .into_iter()
+                .skip_while(|b| *b == 0)
+                .collect();
+            encoded.push(0x80 | len_bytes.len() as u8);
+            encoded.extend(len_bytes);
+        }
+        encoded.extend(content);
+        encoded
+    }
+
+    fn pkcs7_certs_only(certificates: &[Vec<u8>]) -> Vec<u8> {
+        // id-signedData: 1.2.840.113549.1.7.2
+        let signed_data_oid = [0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x07, 0x02];
+        // id-data: 1.2.840.113549.1.7.1
+        let data_oid = [0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x07, 0x01];
+
+        let signed_data = [
+            tlv(0x02, &[1]),
+            tlv(0x31, &[]),
+            tlv(0x30, &tlv(0x06, &data_oid)),
+            tlv(0xA0, &certificates.concat()),
+            tlv(0x31, &[]),
+        ]
+        .concat();
+
+        tlv(
+            0x30,
+            &[
+                tlv(0x06, &signed_data_oid),
+                tlv(0xA0, &tlv(0x30, &signed_data)),
+            ]
+            .concat(),
+        )
+    }
+
+    #[test]
+    fn decode_pem_certificate() {
+        let pem = test_certificate();
+
+        let decoded = decode_certificate(pem.as_bytes()).unwrap();
+
+        assert_eq!(decoded.trim(), pem.trim());
+    }
+
+    #[test]
+    fn decode_base64_der_certificate() {
+        let pem = test_certificate();
+        let body = STANDARD.encode(der_of(&pem));
+
+        let decoded = decode_certificate(body.as_bytes()).unwrap();
+
+        assert_eq!(der_of(&decoded), der_of(&pem));
+        assert!(PemCertificate::from_pem_string(&decoded).is_ok());
+    }
+
+    #[test]
+    fn decode_pkcs7_certificate_bundle() {
+        let pem = test_certificate();
+        let bundle = pkcs7_certs_only(&[der_of(&pem)]);
+        let body = STANDARD.encode(bundle);
+
+        let decoded = decode_certificate(body.as_bytes()).unwrap();
+
+        assert_eq!(der_of(&decoded), der_of(&pem));
+    }
+
+    #[test]
+    fn reject_garbage() {
+        assert!(decode_certificate(b"not a certificate").is_err());
+        assert!(decode_certificate(STANDARD.encode([0x30, 0x03, 0x01]).as_bytes()).is_err());
+    }
+
+    #[test]
+    fn reject_truncated_pkcs7_bundle() {
+        let pem = test_certificate();
+        let bundle = pkcs7_certs_only(&[der_of(&pem)]);
+
+        for len in [0, 1, 2, bundle.len() / 2, bundle.len() - 1] {
+            assert!(
+                certificates_from_pkcs7(&bundle[..len]).is_err(),
+                "a bundle truncated to {len} bytes must be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn reject_oversized_der_lengths() {
+        // A length larger than the remaining content
+        assert!(certificates_from_pkcs7(&[0x30, 0x82, 0xFF, 0xFF, 0x06, 0x00]).is_err());
+        // A length encoded on more bytes than a usize
+        let mut oversized = vec![0x30, 0x89];
+        oversized.extend([0xFF; 9]);
+        assert!(certificates_from_pkcs7(&oversized).is_err());
+        // An indefinite length, which is not allowed by DER
+        assert!(certificates_from_pkcs7(&[0x30, 0x80, 0x00, 0x00]).is_err());
+    }
+
+    #[test]
+    fn reject_a_certificate_bundle_with_a_truncated_certificate() {
+        let pem = test_certificate();
+        let der = der_of(&pem);
+        // The outer lengths are consistent, but not the length of the certificate
+        let truncated = der[..der.len() - 1].to_vec();
+        let bundle = pkcs7_certs_only(&[truncated]);
+
+        assert!(certificates_from_pkcs7(&bundle).is_err());
+    }
+}
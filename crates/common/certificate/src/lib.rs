@@ -4,8 +4,10 @@ use rcgen::CertificateParams;
 use rcgen::KeyPair;
 use sha1::Digest;
 use sha1::Sha1;
+use std::net::IpAddr;
 use std::path::Path;
 use std::path::PathBuf;
+use std::str::FromStr;
 use time::Duration;
 use time::OffsetDateTime;
 pub use zeroize::Zeroizing;
@@ -15,6 +17,8 @@ mod cloud_root_certificate;
 pub use cloud_root_certificate::*;
 
 pub mod device_id;
+pub mod est;
+pub mod local_ca;
 pub mod parse_root_certificate;
 pub struct PemCertificate {
     pem: x509_parser::pem::Pem,
@@ -77,6 +81,12 @@ impl PemCertificate {
             .map_err(CertificateError::X509Error)
     }
 
+    /// The date after which the certificate is no longer valid
+    pub fn expiry(&self) -> Result<OffsetDateTime, CertificateError> {
+        let x509 = PemCertificate::extract_certificate(&self.pem)?;
+        Ok(x509.tbs_certificate.validity.not_after.to_datetime())
+    }
+
     pub fn thumbprint(&self) -> Result<String, CertificateError> {
         let bytes = Sha1::digest(&self.pem.contents).as_slice().to_vec();
         let strs: Vec<String> = bytes.iter().map(|b| format!("{:02X}", b)).collect();
@@ -143,6 +153,31 @@ impl KeyCertPair {
         })
     }
 
+    /// Create a certificate signing request for a key pair stored on a PKCS#11 token
+    ///
+    /// The key pair labelled `cryptoki_config.label` is generated on the token, unless already there.
+    /// The private key never leaves the token, the request being signed by the token.
+    /// Hence, the private key of the returned pair cannot be serialized.
+    #[cfg(feature = "cryptoki")]
+    pub fn new_certificate_sign_request_cryptoki(
+        config: &NewCertificateConfig,
+        id: &str,
+        cryptoki_config: &parse_root_certificate::CryptokiConfig,
+    ) -> Result<KeyCertPair, CertificateError> {
+        let key_pair = parse_root_certificate::pkcs11::Pkcs11KeyPair::new(
+            cryptoki_config,
+            config.key_algorithm,
+        )?;
+        let key_pair = KeyPair::from_remote(Box::new(key_pair))?;
+
+        let mut params = Self::create_csr_parameters(config, id, &KeyKind::New)?;
+        params.alg = key_pair.algorithm();
+        params.key_pair = Some(key_pair);
+        Ok(KeyCertPair {
+            certificate: Zeroizing::new(Certificate::from_params(params)?),
+        })
+    }
+
     fn create_selfsigned_certificate_parameters(
         config: &NewCertificateConfig,
         id: &str,
@@ -175,14 +210,31 @@ impl KeyCertPair {
             &config.organizational_unit_name,
         );
 
+        if let Some(country_name) = &config.country_name {
+            distinguished_name.push(rcgen::DnType::CountryName, country_name);
+        }
+        if let Some(state_or_province_name) = &config.state_or_province_name {
+            distinguished_name.push(rcgen::DnType::StateOrProvinceName, state_or_province_name);
+        }
+        if let Some(locality_name) = &config.locality_name {
+            distinguished_name.push(rcgen::DnType::LocalityName, locality_name);
+        }
+
         let mut params = CertificateParams::default();
         params.distinguished_name = distinguished_name;
-
-        // ECDSA signing using the P-256 curves and SHA-256 hashing as per RFC 5758
-        params.alg = &rcgen::PKCS_ECDSA_P256_SHA256;
+        params.subject_alt_names = config
+            .subject_alt_names
+            .iter()
+            .map(SubjectAltName::to_san_type)
+            .collect();
 
         if let KeyKind::Reuse { keypair_pem } = key_kind {
-            params.key_pair = Some(KeyPair::from_pem(keypair_pem)?);
+            // The signature algorithm is given by the key, whatever the configured key algorithm
+            let key_pair = KeyPair::from_pem(keypair_pem)?;
+            params.alg = key_pair.algorithm();
+            params.key_pair = Some(key_pair);
+        } else {
+            params.alg = config.key_algorithm.signature_algorithm();
         }
 
         Ok(params)
@@ -200,6 +252,10 @@ impl KeyCertPair {
         Ok(self.certificate.serialize_request_pem()?)
     }
 
+    pub fn certificate_signing_request_der(&self) -> Result<Vec<u8>, CertificateError> {
+        Ok(self.certificate.serialize_request_der()?)
+    }
+
     fn check_identifier(id: &str, max_cn_size: usize) -> Result<(), CertificateError> {
         Ok(device_id::is_valid_device_id(id, max_cn_size)?)
     }
@@ -263,6 +319,12 @@ pub enum CertificateError {
     #[error("Failed to add the certificate to root store")]
     RootStoreAdd,
 
+    #[error("Invalid subject alternative name: {0}. Expected 'dns:<name>', 'uri:<uri>', 'ip:<address>' or 'email:<address>'")]
+    InvalidSubjectAltName(String),
+
+    #[error("Invalid certificate returned by the enrolment server: {0}")]
+    InvalidEnrolmentResponse(String),
+
     #[error(transparent)]
     CertParse(#[from] rustls::Error),
 
@@ -273,11 +335,17 @@ pub enum CertificateError {
     Other(#[from] anyhow::Error),
 }
 
+#[derive(Debug, Clone)]
 pub struct NewCertificateConfig {
     pub max_cn_size: usize,
     pub validity_period_days: u32,
     pub organization_name: String,
     pub organizational_unit_name: String,
+    pub country_name: Option<String>,
+    pub state_or_province_name: Option<String>,
+    pub locality_name: Option<String>,
+    pub subject_alt_names: Vec<SubjectAltName>,
+    pub key_algorithm: KeyAlgorithm,
 }
 
 impl Default for NewCertificateConfig {
@@ -287,6 +355,96 @@ impl Default for NewCertificateConfig {
             validity_period_days: 365,
             organization_name: "Thin Edge".into(),
             organizational_unit_name: "Test Device".into(),
+            country_name: None,
+            state_or_province_name: None,
+            locality_name: None,
+            subject_alt_names: vec![],
+            key_algorithm: KeyAlgorithm::default(),
+        }
+    }
+}
+
+/// The algorithm used to generate a new private key
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum KeyAlgorithm {
+    /// ECDSA using the P-256 curve and SHA-256 hashing as per RFC 5758
+    #[default]
+    EcdsaP256,
+    /// ECDSA using the P-384 curve and SHA-384 hashing as per RFC 5758
+    EcdsaP384,
+    /// Ed25519 as per RFC 8410
+    Ed25519,
+}
+
+impl KeyAlgorithm {
+    fn signature_algorithm(&self) -> &'static rcgen::SignatureAlgorithm {
+        match self {
+            KeyAlgorithm::EcdsaP256 => &rcgen::PKCS_ECDSA_P256_SHA256,
+            KeyAlgorithm::EcdsaP384 => &rcgen::PKCS_ECDSA_P384_SHA384,
+            KeyAlgorithm::Ed25519 => &rcgen::PKCS_ED25519,
+        }
+    }
+}
+
+/// An entry of the SubjectAltName extension
+///
+/// Parsed from `dns:<name>`, `uri:<uri>`, `ip:<address>` or `email:<address>`.
+/// Without prefix, the value is taken as an IP address if valid, otherwise as a DNS name.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SubjectAltName {
+    Dns(String),
+    Uri(String),
+    Ip(IpAddr),
+    Email(String),
+}
+
+impl SubjectAltName {
+    fn to_san_type(&self) -> rcgen::SanType {
+        match self {
+            SubjectAltName::Dns(name) => rcgen::SanType::DnsName(name.clone()),
+            SubjectAltName::Uri(uri) => rcgen::SanType::URI(uri.clone()),
+            SubjectAltName::Ip(ip) => rcgen::SanType::IpAddress(*ip),
+            SubjectAltName::Email(email) => rcgen::SanType::Rfc822Name(email.clone()),
+        }
+    }
+}
+
+impl FromStr for SubjectAltName {
+    type Err = CertificateError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || CertificateError::InvalidSubjectAltName(value.to_string());
+        let san = match value.split_once(':') {
+            Some(("dns", name)) => SubjectAltName::Dns(name.to_string()),
+            Some(("uri", uri)) => SubjectAltName::Uri(uri.to_string()),
+            Some(("ip", ip)) => SubjectAltName::Ip(ip.parse().map_err(|_| invalid())?),
+            Some(("email", email)) => SubjectAltName::Email(email.to_string()),
+            _ => match value.parse() {
+                Ok(ip) => SubjectAltName::Ip(ip),
+                Err(_) if !value.contains(':') => SubjectAltName::Dns(value.to_string()),
+                Err(_) => return Err(invalid()),
+            },
+        };
+        match &san {
+            SubjectAltName::Dns(value)
+            | SubjectAltName::Uri(value)
+            | SubjectAltName::Email(value)
+                if value.is_empty() =>
+            {
+                Err(invalid())
+            }
+            _ => Ok(san),
+        }
+    }
+}
+
+impl std::fmt::Display for SubjectAltName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SubjectAltName::Dns(name) => write!(f, "dns:{name}"),
+            SubjectAltName::Uri(uri) => write!(f, "uri:{uri}"),
+            SubjectAltName::Ip(ip) => write!(f, "ip:{ip}"),
+            SubjectAltName::Email(email) => write!(f, "email:{email}"),
         }
     }
 }
@@ -298,6 +456,7 @@ mod tests {
     use std::error::Error;
     use time::macros::datetime;
     use x509_parser::der_parser::asn1_rs::FromDer;
+    use x509_parser::extensions::GeneralName;
 
     impl KeyCertPair {
         fn new_selfsigned_certificate_with_new_key(
@@ -471,6 +630,148 @@ mod tests {
         assert_eq!(subject, "CN=some-id, O=Thin Edge, OU=Test Device");
     }
 
+    #[test]
+    fn create_certificate_sign_request_with_custom_subject() {
+        let config = NewCertificateConfig {
+            organization_name: "Acme".to_owned(),
+            organizational_unit_name: "IoT".to_owned(),
+            country_name: Some("DE".to_owned()),
+            state_or_province_name: Some("Hesse".to_owned()),
+            locality_name: Some("Darmstadt".to_owned()),
+            ..Default::default()
+        };
+
+        let keypair = KeyCertPair::new_certificate_sign_request(&config, "some-id", &KeyKind::New)
+            .expect("Fail to create a CSR");
+
+        let subject = subject_of_csr(&keypair);
+        assert_eq!(
+            subject,
+            "CN=some-id, O=Acme, OU=IoT, C=DE, ST=Hesse, L=Darmstadt"
+        );
+    }
+
+    #[test]
+    fn self_signed_cert_with_subject_alt_names() {
+        let config = NewCertificateConfig {
+            subject_alt_names: vec![
+                "dns:device.local".parse().unwrap(),
+                "uri:urn:device:some-id".parse().unwrap(),
+                "10.0.0.1".parse().unwrap(),
+            ],
+            ..Default::default()
+        };
+
+        let keypair = KeyCertPair::new_selfsigned_certificate_with_new_key(&config, "some-id")
+            .expect("Fail to create a certificate");
+
+        let pem = pem_of_keypair(&keypair);
+        let x509 = PemCertificate::extract_certificate(&pem.pem).unwrap();
+        let san = x509
+            .subject_alternative_name()
+            .unwrap()
+            .expect("Missing SAN extension");
+        let names: Vec<String> = san
+            .value
+            .general_names
+            .iter()
+            .map(|name| match name {
+                GeneralName::DNSName(name) => format!("dns:{name}"),
+                GeneralName::URI(uri) => format!("uri:{uri}"),
+                GeneralName::IPAddress(ip) => format!("ip:{ip:?}"),
+                _ => "unexpected".to_string(),
+            })
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "dns:device.local",
+                "uri:urn:device:some-id",
+                "ip:[10, 0, 0, 1]",
+            ]
+        );
+    }
+
+    #[test]
+    fn create_keys_using_the_configured_algorithm() {
+        for (key_algorithm, expected_oid) in [
+            (KeyAlgorithm::EcdsaP256, "1.2.840.10045.4.3.2"),
+            (KeyAlgorithm::EcdsaP384, "1.2.840.10045.4.3.3"),
+            (KeyAlgorithm::Ed25519, "1.3.101.112"),
+        ] {
+            let config = NewCertificateConfig {
+                key_algorithm,
+                ..Default::default()
+            };
+            let keypair = KeyCertPair::new_selfsigned_certificate_with_new_key(&config, "some-id")
+                .expect("Fail to create a certificate");
+
+            let pem = pem_of_keypair(&keypair);
+            let x509 = PemCertificate::extract_certificate(&pem.pem).unwrap();
+            assert_eq!(
+                x509.signature_algorithm.algorithm.to_id_string(),
+                expected_oid
+            );
+        }
+    }
+
+    #[test]
+    fn reused_key_determines_the_signature_algorithm() {
+        let config = NewCertificateConfig {
+            key_algorithm: KeyAlgorithm::EcdsaP384,
+            ..Default::default()
+        };
+        let keypair = KeyCertPair::new_selfsigned_certificate_with_new_key(&config, "some-id")
+            .expect("Fail to create a certificate");
+        let keypair_pem = keypair.private_key_pem_string().unwrap().to_string();
+
+        // The default P-256 algorithm is ignored in favor of the existing P-384 key
+        let csr = KeyCertPair::new_certificate_sign_request(
+            &NewCertificateConfig::default(),
+            "some-id",
+            &KeyKind::Reuse { keypair_pem },
+        )
+        .expect("Fail to create a CSR");
+
+        let csr = csr
+            .certificate_signing_request_string()
+            .expect("Failed to read the CSR string");
+        let pem = x509_parser::pem::Pem::iter_from_buffer(csr.as_bytes())
+            .next()
+            .unwrap()
+            .expect("Reading PEM block failed");
+        let (_, request) =
+            x509_parser::certification_request::X509CertificationRequest::from_der(&pem.contents)
+                .unwrap();
+        assert_eq!(
+            request.signature_algorithm.algorithm,
+            x509_parser::oid_registry::OID_SIG_ECDSA_WITH_SHA384
+        );
+    }
+
+    #[test]
+    fn parse_subject_alt_names() {
+        assert_eq!(
+            "dns:device.local".parse::<SubjectAltName>().unwrap(),
+            SubjectAltName::Dns("device.local".to_owned())
+        );
+        assert_eq!(
+            "device.local".parse::<SubjectAltName>().unwrap(),
+            SubjectAltName::Dns("device.local".to_owned())
+        );
+        assert_eq!(
+            "::1".parse::<SubjectAltName>().unwrap(),
+            SubjectAltName::Ip("::1".parse().unwrap())
+        );
+        assert_eq!(
+            "email:admin@example.com".parse::<SubjectAltName>().unwrap(),
+            SubjectAltName::Email("admin@example.com".to_owned())
+        );
+        assert!("ip:not-an-ip".parse::<SubjectAltName>().is_err());
+        assert!("urn:missing-prefix".parse::<SubjectAltName>().is_err());
+        assert!("dns:".parse::<SubjectAltName>().is_err());
+    }
+
     #[test]
     fn check_certificate_thumbprint_b64_decode_sha1() {
         // Create a certificate key pair
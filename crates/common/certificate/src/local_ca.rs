@@ -0,0 +1,241 @@
+//! A local certificate authority issuing the certificates used on the device itself
+//!
+//! The local MQTT broker and its clients (the agent, the mappers, the plugins)
+//! authenticate each other with certificates signed by this CA,
+//! which is never shared with any cloud endpoint.
+use crate::CertificateError;
+use crate::KeyCertPair;
+use crate::KeyKind;
+use crate::NewCertificateConfig;
+use crate::PemCertificate;
+use rcgen::Certificate;
+use rcgen::ExtendedKeyUsagePurpose;
+use rcgen::KeyPair;
+use rcgen::KeyUsagePurpose;
+use time::Duration;
+use time::OffsetDateTime;
+use zeroize::Zeroizing;
+
+/// The role of a certificate issued by the local CA
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum CertificateUsage {
+    /// A certificate used by the local MQTT broker to authenticate itself to its clients
+    Server,
+    /// A certificate used by a local service to authenticate itself to the MQTT broker
+    Client,
+}
+
+/// A certificate issued by the local CA, along with its private key
+pub struct IssuedCertificate {
+    pub certificate_pem: String,
+    pub private_key_pem: Zeroizing<String>,
+}
+
+pub struct LocalCa {
+    certificate_pem: String,
+    signer: Zeroizing<Certificate>,
+}
+
+impl LocalCa {
+    /// Create a new self-signed CA
+    pub fn new(config: &NewCertificateConfig, name: &str) -> Result<Self, CertificateError> {
+        let mut params = KeyCertPair::create_csr_parameters(config, name, &KeyKind::New)?;
+        let not_before = OffsetDateTime::now_utc() - Duration::days(1);
+        params.not_before = not_before;
+        params.not_after = not_before + Duration::days(config.validity_period_days.into());
+        params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Constrained(0));
+        params.key_usages = vec![
+            KeyUsagePurpose::KeyCertSign,
+            KeyUsagePurpose::CrlSign,
+            KeyUsagePurpose::DigitalSignature,
+        ];
+
+        let signer = Certificate::from_params(params)?;
+        Ok(LocalCa {
+            certificate_pem: signer.serialize_pem()?,
+            signer: Zeroizing::new(signer),
+        })
+    }
+
+    /// Load a CA previously created with [LocalCa::new]
+    pub fn from_pem(
+        certificate_pem: &str,
+        private_key_pem: &str,
+    ) -> Result<Self, CertificateError> {
+        let certificate = PemCertificate::from_pem_string(certificate_pem)?;
+        let x509 = PemCertificate::extract_certificate(&certificate.pem)?;
+
+        // The issuer of the certificates signed by this CA is taken from the signer parameters:
+        // so these have to be rebuilt from the CA certificate, keeping the attribute order.
+        let mut distinguished_name = rcgen::DistinguishedName::new();
+        for attribute in x509.subject().iter_attributes() {
+            let Ok(value) = attribute.as_str() else {
+                continue;
+            };
+            let dn_type = match attribute.attr_type().to_id_string().as_str() {
+                "2.5.4.3" => rcgen::DnType::CommonName,
+                "2.5.4.6" => rcgen::DnType::CountryName,
+                "2.5.4.7" => rcgen::DnType::LocalityName,
+                "2.5.4.8" => rcgen::DnType::StateOrProvinceName,
+                "2.5.4.10" => rcgen::DnType::OrganizationName,
+                "2.5.4.11" => rcgen::DnType::OrganizationalUnitName,
+                _ => continue,
+            };
+            distinguished_name.push(dn_type, value);
+        }
+
+        let key_pair = KeyPair::from_pem(private_key_pem)?;
+        let mut params = rcgen::CertificateParams::default();
+        params.alg = key_pair.algorithm();
+        params.key_pair = Some(key_pair);
+        params.distinguished_name = distinguished_name;
+        params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Constrained(0));
+
+        Ok(LocalCa {
+            certificate_pem: certificate_pem.to_string(),
+            signer: Zeroizing::new(Certificate::from_params(params)?),
+        })
+    }
+
+    pub fn certificate_pem_string(&self) -> &str {
+        &self.certificate_pem
+    }
+
+    pub fn private_key_pem_string(&self) -> Zeroizing<String> {
+        Zeroizing::new(self.signer.serialize_private_key_pem())
+    }
+
+    /// Issue a certificate for `common_name`, along with a new private key
+    ///
+    /// The subject alternative names are taken from the `config`.
+    pub fn issue_certificate(
+        &self,
+        config: &NewCertificateConfig,
+        common_name: &str,
+        usage: CertificateUsage,
+    ) -> Result<IssuedCertificate, CertificateError> {
+        let mut params = KeyCertPair::create_csr_parameters(config, common_name, &KeyKind::New)?;
+        let not_before = OffsetDateTime::now_utc() - Duration::days(1);
+        params.not_before = not_before;
+        params.not_after = not_before + Duration::days(config.validity_period_days.into());
+        params.is_ca = rcgen::IsCa::NoCa;
+        params.use_authority_key_identifier_extension = true;
+        params.key_usages = vec![KeyUsagePurpose::DigitalSignature];
+        params.extended_key_usages = vec![match usage {
+            CertificateUsage::Server => ExtendedKeyUsagePurpose::ServerAuth,
+            CertificateUsage::Client => ExtendedKeyUsagePurpose::ClientAuth,
+        }];
+
+        let certificate = Certificate::from_params(params)?;
+        Ok(IssuedCertificate {
+            certificate_pem: certificate.serialize_pem_with_signer(&self.signer)?,
+            private_key_pem: Zeroizing::new(certificate.serialize_private_key_pem()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use x509_parser::der_parser::asn1_rs::FromDer;
+    use x509_parser::extensions::ParsedExtension;
+    use x509_parser::prelude::X509Certificate;
+
+    fn ca_config() -> NewCertificateConfig {
+        NewCertificateConfig {
+            organizational_unit_name: "Local CA".into(),
+            ..NewCertificateConfig::default()
+        }
+    }
+
+    fn parse(pem: &str, check: impl FnOnce(&X509Certificate)) {
+        let pem = PemCertificate::from_pem_string(pem).unwrap();
+        let (_, x509) = X509Certificate::from_der(&pem.pem.contents).unwrap();
+        check(&x509)
+    }
+
+    fn key_identifier(x509: &X509Certificate) -> Vec<u8> {
+        x509.extensions()
+            .iter()
+            .find_map(|ext| match ext.parsed_extension() {
+                ParsedExtension::SubjectKeyIdentifier(id) => Some(id.0.to_vec()),
+                _ => None,
+            })
+            .expect("a subject key identifier")
+    }
+
+    fn authority_key_identifier(x509: &X509Certificate) -> Vec<u8> {
+        x509.extensions()
+            .iter()
+            .find_map(|ext| match ext.parsed_extension() {
+                ParsedExtension::AuthorityKeyIdentifier(aki) => {
+                    aki.key_identifier.as_ref().map(|id| id.0.to_vec())
+                }
+                _ => None,
+            })
+            .expect("an authority key identifier")
+    }
+
+    #[test]
+    fn issued_certificates_are_signed_by_the_local_ca() {
+        let ca = LocalCa::new(&ca_config(), "tedge-local-ca").unwrap();
+        let client = ca
+            .issue_certificate(
+                &NewCertificateConfig::default(),
+                "tedge-agent",
+                CertificateUsage::Client,
+            )
+            .unwrap();
+
+        let ca_pem = PemCertificate::from_pem_string(ca.certificate_pem_string()).unwrap();
+        let (_, ca_x509) = X509Certificate::from_der(&ca_pem.pem.contents).unwrap();
+        parse(&client.certificate_pem, |x509| {
+            assert_eq!(x509.issuer(), ca_x509.subject());
+            assert_eq!(authority_key_identifier(x509), key_identifier(&ca_x509));
+            assert!(!x509.is_ca());
+            let eku = x509.extended_key_usage().unwrap().unwrap();
+            assert!(eku.value.client_auth);
+            assert!(!eku.value.server_auth);
+        });
+    }
+
+    #[test]
+    fn a_reloaded_ca_issues_certificates_verifiable_with_the_original_ca_certificate() {
+        let ca = LocalCa::new(&ca_config(), "tedge-local-ca").unwrap();
+        let ca_pem = ca.certificate_pem_string().to_string();
+        let ca_key = ca.private_key_pem_string();
+
+        let reloaded = LocalCa::from_pem(&ca_pem, &ca_key).unwrap();
+        assert_eq!(reloaded.certificate_pem_string(), ca_pem);
+
+        let config = NewCertificateConfig {
+            subject_alt_names: vec!["localhost".parse().unwrap(), "127.0.0.1".parse().unwrap()],
+            ..NewCertificateConfig::default()
+        };
+        let server = reloaded
+            .issue_certificate(&config, "localhost", CertificateUsage::Server)
+            .unwrap();
+
+        let ca_cert = PemCertificate::from_pem_string(&ca_pem).unwrap();
+        let (_, ca_x509) = X509Certificate::from_der(&ca_cert.pem.contents).unwrap();
+        parse(&server.certificate_pem, |x509| {
+            assert_eq!(x509.issuer(), ca_x509.subject());
+            assert_eq!(authority_key_identifier(x509), key_identifier(&ca_x509));
+            let has_san = x509.extensions().iter().any(|ext| {
+                matches!(ext.parsed_extension(), ParsedExtension::SubjectAlternativeName(san) if san.general_names.len() == 2)
+            });
+            assert!(has_san);
+        });
+    }
+
+    #[test]
+    fn the_ca_certificate_can_only_sign_end_entity_certificates() {
+        let ca = LocalCa::new(&ca_config(), "tedge-local-ca").unwrap();
+        parse(ca.certificate_pem_string(), |x509| {
+            assert!(x509.is_ca());
+            let constraints = x509.basic_constraints().unwrap().unwrap();
+            assert_eq!(constraints.value.path_len_constraint, Some(0));
+            assert!(x509.key_usage().unwrap().unwrap().value.key_cert_sign());
+        });
+    }
+}
@@ -15,7 +15,7 @@ use std::sync::Arc;
 use crate::CertificateError;
 
 #[cfg(feature = "cryptoki")]
-mod pkcs11;
+pub(crate) mod pkcs11;
 
 pub fn create_tls_config(
     root_certificates: impl AsRef<Path>,
@@ -67,6 +67,8 @@ pub struct CryptokiConfig {
     pub module_path: Utf8PathBuf,
     pub pin: Arc<str>,
     pub serial: Option<Arc<str>>,
+    pub slot: Option<u64>,
+    pub label: Option<Arc<str>>,
 }
 
 pub fn client_config_for_ca_certificates<P>(
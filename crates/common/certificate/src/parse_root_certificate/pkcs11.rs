@@ -5,6 +5,7 @@
 //! - PKCS#11: https://docs.oasis-open.org/pkcs11/pkcs11-base/v2.40/os/pkcs11-base-v2.40-os.html
 
 use anyhow::Context;
+use asn1_rs::FromDer;
 use asn1_rs::ToDer;
 use base64::Engine;
 use cryptoki::context::CInitializeArgs;
@@ -13,6 +14,8 @@ use cryptoki::mechanism::Mechanism;
 use cryptoki::object::Attribute;
 use cryptoki::object::AttributeType;
 use cryptoki::object::KeyType;
+use cryptoki::object::ObjectClass;
+use cryptoki::object::ObjectHandle;
 use cryptoki::session::Session;
 use cryptoki::session::UserType;
 use cryptoki::types::AuthPin;
@@ -33,6 +36,7 @@ use tracing::error;
 use tracing::warn;
 
 use super::CryptokiConfig;
+use crate::KeyAlgorithm;
 
 #[derive(Debug)]
 pub enum Pkcs11SigningKey {
@@ -44,37 +48,11 @@ impl Pkcs11SigningKey {
     pub fn from_cryptoki_config(
         cryptoki_config: CryptokiConfig,
     ) -> anyhow::Result<Pkcs11SigningKey> {
-        let CryptokiConfig {
-            module_path,
-            pin,
-            // TODO(marcel): select modules by serial if multiple are connected
-            serial: _,
-        } = cryptoki_config;
-
-        debug!(%module_path, "Loading PKCS#11 module");
-        // can fail with Pkcs11(GeneralError, GetFunctionList) if P11_KIT_SERVER_ADDRESS is wrong
-        let pkcs11client = Pkcs11::new(module_path)?;
-        pkcs11client.initialize(CInitializeArgs::OsThreads)?;
-
-        // Select first available slot. If it's not the one we want, the token
-        // used in p11-kit-server should be adjusted.
-        let slot = pkcs11client
-            .get_slots_with_token()?
-            .into_iter()
-            .next()
-            .context("Didn't find a slot to use. The device may be disconnected.")?;
-        let slot_info = pkcs11client.get_slot_info(slot)?;
-        let token_info = pkcs11client.get_token_info(slot)?;
-        debug!(?slot_info, ?token_info, "Selected slot");
-
-        debug!(%pin, "Attempting to login to PKCS#11 module");
-        let session = pkcs11client.open_ro_session(slot)?;
-        session.login(UserType::User, Some(&AuthPin::new(pin.deref().into())))?;
-        let session_info = session.get_session_info()?;
-        debug!(?session_info, "Opened a readonly session");
+        let session = open_session(&cryptoki_config, false)?;
 
         let pkcs11 = PKCS11 {
             session: Arc::new(Mutex::new(session)),
+            label: cryptoki_config.label,
         };
 
         let key_type = get_key_type(pkcs11.clone()).context("Failed to read key")?;
@@ -110,6 +88,69 @@ impl SigningKey for Pkcs11SigningKey {
 #[derive(Debug, Clone)]
 struct PKCS11 {
     session: Arc<Mutex<Session>>,
+    label: Option<Arc<str>>,
+}
+
+/// Open a session on the configured token, logged in as the user
+fn open_session(cryptoki_config: &CryptokiConfig, read_write: bool) -> anyhow::Result<Session> {
+    let CryptokiConfig {
+        module_path,
+        pin,
+        // TODO(marcel): select modules by serial if multiple are connected
+        serial: _,
+        slot: slot_id,
+        label: _,
+    } = cryptoki_config;
+
+    debug!(%module_path, "Loading PKCS#11 module");
+    // can fail with Pkcs11(GeneralError, GetFunctionList) if P11_KIT_SERVER_ADDRESS is wrong
+    let pkcs11client = Pkcs11::new(module_path)?;
+    pkcs11client.initialize(CInitializeArgs::OsThreads)?;
+
+    let slots = pkcs11client.get_slots_with_token()?;
+    let slot = match slot_id {
+        Some(slot_id) => slots
+            .into_iter()
+            .find(|slot| slot.id() == *slot_id)
+            .with_context(|| {
+                format!("Didn't find the slot {slot_id}. The device may be disconnected.")
+            })?,
+
+        // Select first available slot. If it's not the one we want, the token
+        // used in p11-kit-server should be adjusted.
+        None => slots
+            .into_iter()
+            .next()
+            .context("Didn't find a slot to use. The device may be disconnected.")?,
+    };
+    let slot_info = pkcs11client.get_slot_info(slot)?;
+    let token_info = pkcs11client.get_token_info(slot)?;
+    debug!(?slot_info, ?token_info, "Selected slot");
+
+    debug!(%pin, "Attempting to login to PKCS#11 module");
+    let session = if read_write {
+        pkcs11client.open_rw_session(slot)?
+    } else {
+        pkcs11client.open_ro_session(slot)?
+    };
+    session.login(UserType::User, Some(&AuthPin::new(pin.deref().into())))?;
+    let session_info = session.get_session_info()?;
+    debug!(?session_info, "Opened a session");
+
+    Ok(session)
+}
+
+/// The attributes of the private key used to sign, selected by label if one is configured
+fn signing_key_template(label: Option<&str>) -> Vec<Attribute> {
+    let mut key_template = vec![
+        Attribute::Token(true),
+        Attribute::Private(true),
+        Attribute::Sign(true),
+    ];
+    if let Some(label) = label {
+        key_template.push(Attribute::Label(label.as_bytes().to_vec()));
+    }
+    key_template
 }
 
 #[derive(Debug)]
@@ -141,11 +182,7 @@ impl Signer for PkcsSigner {
     fn sign(&self, message: &[u8]) -> Result<Vec<u8>, rustls::Error> {
         let session = self.pkcs11.session.lock().unwrap();
 
-        let key_template = vec![
-            Attribute::Token(true),
-            Attribute::Private(true),
-            Attribute::Sign(true),
-        ];
+        let key_template = signing_key_template(self.pkcs11.label.as_deref());
 
         let key = session
             .find_objects(&key_template)
@@ -191,10 +228,9 @@ impl Signer for PkcsSigner {
             rustls::Error::General(format!("pkcs11: Failed to sign message: {:?}", err))
         })?;
 
-        // Split raw signature into r and s values (assuming 32 bytes each)
+        // Split raw signature into r and s values of equal length
         trace!("Signature (raw) len={:?}", signature_raw.len());
-        let r_bytes = &signature_raw[0..32];
-        let s_bytes = &signature_raw[32..];
+        let (r_bytes, s_bytes) = signature_raw.split_at(signature_raw.len() / 2);
         let signature_asn1 = format_asn1_ecdsa_signature(r_bytes, s_bytes).map_err(|err| {
             rustls::Error::General(format!("pkcs11: Failed to format signature: {:?}", err))
         })?;
@@ -319,11 +355,7 @@ impl SigningKey for RSASigningKey {
 fn get_key_type(pkcs11: PKCS11) -> anyhow::Result<KeyType> {
     let session = pkcs11.session.lock().unwrap();
 
-    let key_template = vec![
-        Attribute::Token(true),
-        Attribute::Private(true),
-        Attribute::Sign(true),
-    ];
+    let key_template = signing_key_template(pkcs11.label.as_deref());
 
     trace!(?key_template, "Finding a key");
     let key = session
@@ -358,6 +390,208 @@ fn get_key_type(pkcs11: PKCS11) -> anyhow::Result<KeyType> {
     Ok(key_type)
 }
 
+/// The label of the key pair generated on a token, when `device.cryptoki.label` is not set
+pub const DEFAULT_KEY_LABEL: &str = "tedge";
+
+/// A key pair stored on a PKCS#11 token, used to sign certificate signing requests
+///
+/// The private key never leaves the token: the signatures are computed by the token.
+pub struct Pkcs11KeyPair {
+    session: Mutex<Session>,
+    private_key: ObjectHandle,
+    public_key: Vec<u8>,
+    curve: EcCurve,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum EcCurve {
+    P256,
+    P384,
+}
+
+impl EcCurve {
+    fn from_key_algorithm(key_algorithm: KeyAlgorithm) -> anyhow::Result<Self> {
+        match key_algorithm {
+            KeyAlgorithm::EcdsaP256 => Ok(EcCurve::P256),
+            KeyAlgorithm::EcdsaP384 => Ok(EcCurve::P384),
+            KeyAlgorithm::Ed25519 => {
+                anyhow::bail!("Only ECDSA keys can be generated on a PKCS#11 token, not Ed25519")
+            }
+        }
+    }
+
+    fn from_ec_params(ec_params: &[u8]) -> anyhow::Result<Self> {
+        [EcCurve::P256, EcCurve::P384]
+            .into_iter()
+            .find(|curve| curve.ec_params() == ec_params)
+            .context("Unsupported elliptic curve. Only P-256 and P-384 are supported")
+    }
+
+    /// The DER encoded OID of the curve
+    fn ec_params(self) -> &'static [u8] {
+        match self {
+            EcCurve::P256 => &[0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07],
+            EcCurve::P384 => &[0x06, 0x05, 0x2b, 0x81, 0x04, 0x00, 0x22],
+        }
+    }
+
+    /// The length of an uncompressed point on the curve
+    fn point_len(self) -> usize {
+        match self {
+            EcCurve::P256 => 65,
+            EcCurve::P384 => 97,
+        }
+    }
+
+    fn digest_mechanism(self) -> Mechanism<'static> {
+        match self {
+            EcCurve::P256 => Mechanism::Sha256,
+            EcCurve::P384 => Mechanism::Sha384,
+        }
+    }
+
+    fn signature_algorithm(self) -> &'static rcgen::SignatureAlgorithm {
+        match self {
+            EcCurve::P256 => &rcgen::PKCS_ECDSA_P256_SHA256,
+            EcCurve::P384 => &rcgen::PKCS_ECDSA_P384_SHA384,
+        }
+    }
+}
+
+impl Pkcs11KeyPair {
+    /// Use the key pair with the configured label, generating it on the token if there is none
+    ///
+    /// As for a key stored in a file, an existing key is reused whatever the requested algorithm.
+    pub fn new(
+        cryptoki_config: &CryptokiConfig,
+        key_algorithm: KeyAlgorithm,
+    ) -> anyhow::Result<Self> {
+        let session = open_session(cryptoki_config, true)?;
+        let label = cryptoki_config
+            .label
+            .as_deref()
+            .unwrap_or(DEFAULT_KEY_LABEL)
+            .as_bytes()
+            .to_vec();
+
+        let find_key = |class: ObjectClass| -> anyhow::Result<Option<ObjectHandle>> {
+            let template = [Attribute::Class(class), Attribute::Label(label.clone())];
+            Ok(session.find_objects(&template)?.into_iter().next())
+        };
+        let (public_key, private_key, curve) = match (
+            find_key(ObjectClass::PUBLIC_KEY)?,
+            find_key(ObjectClass::PRIVATE_KEY)?,
+        ) {
+            (Some(public_key), Some(private_key)) => {
+                let ec_params = session
+                    .get_attributes(public_key, &[AttributeType::EcParams])?
+                    .into_iter()
+                    .find_map(|attribute| match attribute {
+                        Attribute::EcParams(ec_params) => Some(ec_params),
+                        _ => None,
+                    })
+                    .context("The key on the token is not an EC key")?;
+                let curve = EcCurve::from_ec_params(&ec_params)?;
+                debug!(?curve, "Reusing the key pair found on the token");
+                (public_key, private_key, curve)
+            }
+            _ => {
+                let curve = EcCurve::from_key_algorithm(key_algorithm)?;
+                let public_template = [
+                    Attribute::Token(true),
+                    Attribute::Private(false),
+                    Attribute::Verify(true),
+                    Attribute::EcParams(curve.ec_params().to_vec()),
+                    Attribute::Label(label.clone()),
+                    Attribute::Id(label.clone()),
+                ];
+                let private_template = [
+                    Attribute::Token(true),
+                    Attribute::Private(true),
+                    Attribute::Sign(true),
+                    Attribute::Sensitive(true),
+                    Attribute::Extractable(false),
+                    Attribute::Label(label.clone()),
+                    Attribute::Id(label.clone()),
+                ];
+                debug!(?curve, "Generating a key pair on the token");
+                let (public_key, private_key) = session
+                    .generate_key_pair(
+                        &Mechanism::EccKeyPairGen,
+                        &public_template,
+                        &private_template,
+                    )
+                    .context("Failed to generate a key pair on the token")?;
+                (public_key, private_key, curve)
+            }
+        };
+
+        let ec_point = session
+            .get_attributes(public_key, &[AttributeType::EcPoint])?
+            .into_iter()
+            .find_map(|attribute| match attribute {
+                Attribute::EcPoint(ec_point) => Some(ec_point),
+                _ => None,
+            })
+            .context("Failed to read the public key from the token")?;
+
+        Ok(Pkcs11KeyPair {
+            session: Mutex::new(session),
+            private_key,
+            public_key: decode_ec_point(&ec_point, curve.point_len())?,
+            curve,
+        })
+    }
+}
+
+impl rcgen::RemoteKeyPair for Pkcs11KeyPair {
+    fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, rcgen::Error> {
+        let session = self.session.lock().unwrap();
+        let digest = session
+            .digest(&self.curve.digest_mechanism(), message)
+            .map_err(|err| {
+                error!("pkcs11: Failed to digest message: {err:?}");
+                rcgen::Error::RemoteKeyError
+            })?;
+        let signature_raw = session
+            .sign(&Mechanism::Ecdsa, self.private_key, &digest)
+            .map_err(|err| {
+                error!("pkcs11: Failed to sign message: {err:?}");
+                rcgen::Error::RemoteKeyError
+            })?;
+
+        let (r_bytes, s_bytes) = signature_raw.split_at(signature_raw.len() / 2);
+        format_asn1_ecdsa_signature(r_bytes, s_bytes).map_err(|_| rcgen::Error::RemoteKeyError)
+    }
+
+    fn algorithm(&self) -> &'static rcgen::SignatureAlgorithm {
+        self.curve.signature_algorithm()
+    }
+}
+
+/// Extract the uncompressed point from the CKA_EC_POINT attribute
+///
+/// PKCS#11 mandates a DER encoded octet string, but some tokens return the raw point.
+fn decode_ec_point(ec_point: &[u8], point_len: usize) -> anyhow::Result<Vec<u8>> {
+    if ec_point.len() == point_len {
+        return Ok(ec_point.to_vec());
+    }
+
+    let (_, octet_string) = asn1_rs::OctetString::from_der(ec_point)
+        .map_err(|err| anyhow::anyhow!("Invalid public key returned by the token: {err}"))?;
+    let point = octet_string.as_cow().to_vec();
+    anyhow::ensure!(
+        point.len() == point_len,
+        "Invalid public key returned by the token: unexpected length {}",
+        point.len()
+    );
+    Ok(point)
+}
+
 fn format_asn1_ecdsa_signature(r_bytes: &[u8], s_bytes: &[u8]) -> Result<Vec<u8>, anyhow::Error> {
     use base64::prelude::BASE64_STANDARD_NO_PAD;
     let mut writer = Vec::new();
@@ -385,3 +619,87 @@ fn write_asn1_integer(writer: &mut dyn std::io::Write, b: &[u8]) {
     let i = asn1_rs::Integer::new(&i);
     let _ = i.write_der(writer);
 }
+
+/// These tests require a SoftHSM token, initialized with:
+///
+/// ```sh
+/// softhsm2-util --init-token --free --label tedge-test --pin 123456 --so-pin 123456
+/// PKCS11_MODULE=/usr/lib/softhsm/libsofthsm2.so cargo test -p certificate --features cryptoki -- --ignored
+/// ```
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyCertPair;
+    use crate::NewCertificateConfig;
+    use x509_parser::certification_request::X509CertificationRequest;
+    use x509_parser::der_parser::asn1_rs::FromDer as _;
+
+    fn softhsm_config(label: &str) -> CryptokiConfig {
+        CryptokiConfig {
+            module_path: std::env::var("PKCS11_MODULE")
+                .unwrap_or("/usr/lib/softhsm/libsofthsm2.so".to_string())
+                .into(),
+            pin: "123456".into(),
+            serial: None,
+            slot: None,
+            label: Some(label.into()),
+        }
+    }
+
+    fn public_key_of_csr(csr_der: &[u8]) -> Vec<u8> {
+        let (_, csr) = X509CertificationRequest::from_der(csr_der).unwrap();
+        csr.certification_request_info
+            .subject_pki
+            .subject_public_key
+            .data
+            .to_vec()
+    }
+
+    #[test]
+    #[ignore = "requires a SoftHSM token"]
+    fn create_a_csr_with_a_key_generated_on_the_token() {
+        let label = format!("test-{}", std::process::id());
+        let cryptoki_config = softhsm_config(&label);
+        let config = NewCertificateConfig {
+            key_algorithm: KeyAlgorithm::EcdsaP384,
+            ..NewCertificateConfig::default()
+        };
+
+        let csr = KeyCertPair::new_certificate_sign_request_cryptoki(
+            &config,
+            "my-device",
+            &cryptoki_config,
+        )
+        .unwrap();
+        let csr_der = csr.certificate_signing_request_der().unwrap();
+        let (_, request) = X509CertificationRequest::from_der(&csr_der).unwrap();
+        assert_eq!(
+            request.certification_request_info.subject.to_string(),
+            "CN=my-device, O=Thin Edge, OU=Test Device"
+        );
+        assert_eq!(public_key_of_csr(&csr_der).len(), 97);
+
+        // The key pair is reused on a second request, whatever the requested algorithm
+        let second_csr = KeyCertPair::new_certificate_sign_request_cryptoki(
+            &NewCertificateConfig::default(),
+            "my-device",
+            &cryptoki_config,
+        )
+        .unwrap();
+        assert_eq!(
+            public_key_of_csr(&second_csr.certificate_signing_request_der().unwrap()),
+            public_key_of_csr(&csr_der)
+        );
+    }
+
+    #[test]
+    fn decode_der_encoded_and_raw_ec_points() {
+        let point = [0x04; 65];
+        let mut der = vec![0x04, 65];
+        der.extend_from_slice(&point);
+
+        assert_eq!(decode_ec_point(&der, 65).unwrap(), point);
+        assert_eq!(decode_ec_point(&point, 65).unwrap(), point);
+        assert!(decode_ec_point(&der, 97).is_err());
+    }
+}
@@ -59,6 +59,12 @@ pub struct Config {
     ///
     /// Default: None
     pub initial_message: Option<InitMessageFn>,
+
+    /// Notified with `true` on each connection acknowledged by the broker,
+    /// and with `false` on each connection error or disconnection
+    ///
+    /// Default: None
+    pub connection_callback: Option<ConnectionCallbackFn>,
 }
 
 #[derive(Debug, Clone)]
@@ -153,6 +159,29 @@ impl Debug for InitMessageFn {
     }
 }
 
+#[derive(Clone)]
+pub struct ConnectionCallbackFn {
+    callback: Arc<dyn Fn(bool) + Send + Sync>,
+}
+
+impl ConnectionCallbackFn {
+    pub fn new(callback: impl Fn(bool) + Sync + Send + 'static) -> ConnectionCallbackFn {
+        ConnectionCallbackFn {
+            callback: Arc::new(callback),
+        }
+    }
+
+    pub fn notify(&self, connected: bool) {
+        (self.callback)(connected)
+    }
+}
+
+impl Debug for ConnectionCallbackFn {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Connection callback function")
+    }
+}
+
 /// By default a client connects the local MQTT broker.
 impl Default for Config {
     fn default() -> Self {
@@ -169,6 +198,7 @@ impl Default for Config {
             max_packet_size: 16 * 1024 * 1024,
             last_will_message: None,
             initial_message: None,
+            connection_callback: None,
         }
     }
 }
@@ -261,6 +291,17 @@ impl Config {
         }
     }
 
+    /// Set a function to be called on each connection to or disconnection from the broker
+    pub fn with_connection_callback(
+        self,
+        connection_callback: impl Fn(bool) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            connection_callback: Some(ConnectionCallbackFn::new(connection_callback)),
+            ..self
+        }
+    }
+
     /// Adds all certificates present in `ca_file` file to the trust store.
     /// Enables server authentication.
     pub fn with_cafile(
@@ -316,6 +357,13 @@ impl Config {
         Ok(self)
     }
 
+    /// Notify the connection callback, if any, of a connection or disconnection
+    pub(crate) fn notify_connection(&self, connected: bool) {
+        if let Some(callback) = &self.connection_callback {
+            callback.notify(connected)
+        }
+    }
+
     /// Wrap this config into an internal set of options for `rumqttc`.
     pub fn rumqttc_options(&self) -> Result<rumqttc::MqttOptions, rustls::Error> {
         let id = match &self.session_name {
@@ -154,6 +154,7 @@ impl Connection {
                         return Err(err);
                     };
                     info!(target: "MQTT", "Connection established");
+                    config.notify_connection(true);
 
                     let subscriptions = config.subscriptions.filters();
 
@@ -184,6 +185,7 @@ impl Connection {
                 }
 
                 Err(err) => {
+                    config.notify_connection(false);
                     error!(target: "MQTT",
                         "Failed to connect to broker at '{host}:{port}': {err}",
                         host = config.broker.host,
@@ -256,9 +258,11 @@ impl Connection {
 
                 Ok(Event::Incoming(Packet::ConnAck(ack))) => {
                     if let Some(err) = MqttError::maybe_connection_error(&ack) {
+                        config.notify_connection(false);
                         error!(target: "MQTT", "Connection Error {err}");
                     } else {
                         info!(target: "MQTT", "Connection re-established");
+                        config.notify_connection(true);
                         if let Some(ref imsg_fn) = config.initial_message {
                             // publish the initial message on connect
                             let message = imsg_fn.new_init_message();
@@ -289,10 +293,12 @@ impl Connection {
 
                 Ok(Event::Incoming(Incoming::Disconnect))
                 | Ok(Event::Outgoing(Outgoing::Disconnect)) => {
+                    config.notify_connection(false);
                     break;
                 }
 
                 Err(err) => {
+                    config.notify_connection(false);
                     error!(target: "MQTT", "Connection error: {err}");
 
                     // Errors on send are ignored: it just means the client has closed the receiving channel.
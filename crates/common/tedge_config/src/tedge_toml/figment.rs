@@ -17,19 +17,35 @@ use crate::TEdgeConfigError;
 
 pub trait ConfigSources {
     const INCLUDE_ENVIRONMENT: bool;
+    const INCLUDE_DROP_INS: bool;
 }
 
 #[derive(Clone, Debug)]
 pub struct FileAndEnvironment;
 #[derive(Clone, Debug)]
 pub struct FileOnly;
+#[derive(Clone, Debug)]
+pub struct FileAndDropIns;
 
 impl ConfigSources for FileAndEnvironment {
     const INCLUDE_ENVIRONMENT: bool = true;
+    const INCLUDE_DROP_INS: bool = true;
 }
 
+/// The content of `tedge.toml` only, as updated by `tedge config set`
+///
+/// The drop-in files are ignored, so their settings are not copied into `tedge.toml`.
 impl ConfigSources for FileOnly {
     const INCLUDE_ENVIRONMENT: bool = false;
+    const INCLUDE_DROP_INS: bool = false;
+}
+
+/// The settings persisted on disk, i.e. `tedge.toml` and its drop-in files
+///
+/// The environment variables are ignored, so their values are not taken for persisted settings.
+impl ConfigSources for FileAndDropIns {
+    const INCLUDE_ENVIRONMENT: bool = false;
+    const INCLUDE_DROP_INS: bool = true;
 }
 
 #[derive(Default, Debug, PartialEq, Eq)]
@@ -45,17 +61,14 @@ impl UnusedValueWarnings {
 }
 
 /// Extract the configuration data from the provided TOML path and `TEDGE_` prefixed environment variables
+///
+/// The settings of the drop-in files, i.e. the `*.toml` files of the `tedge.toml.d` directory,
+/// are merged in lexical order, and are then overridden by `tedge.toml` and the environment.
 pub fn extract_data<T: DeserializeOwned, Sources: ConfigSources>(
     path: impl AsRef<Path>,
 ) -> Result<(T, UnusedValueWarnings), TEdgeConfigError> {
     let env = TEdgeEnv::default();
-    let figment = Figment::new().merge(Toml::file(path));
-
-    let figment = if Sources::INCLUDE_ENVIRONMENT {
-        figment.merge(env.provider())
-    } else {
-        figment
-    };
+    let figment = layered_figment::<Sources>(path.as_ref(), &env);
 
     let data = extract_exact(&figment, &env);
 
@@ -73,6 +86,97 @@ pub fn extract_data<T: DeserializeOwned, Sources: ConfigSources>(
     }
 }
 
+fn layered_figment<Sources: ConfigSources>(path: &Path, env: &TEdgeEnv) -> Figment {
+    let mut figment = Figment::new();
+    if Sources::INCLUDE_DROP_INS {
+        for drop_in in drop_in_files(path) {
+            figment = figment.merge(Toml::file(drop_in));
+        }
+    }
+    figment = figment.merge(Toml::file(path));
+
+    if Sources::INCLUDE_ENVIRONMENT {
+        figment.merge(env.provider())
+    } else {
+        figment
+    }
+}
+
+/// The directory of the drop-in files for the given `tedge.toml` path, i.e. `tedge.toml.d`
+fn drop_in_dir(path: &Path) -> PathBuf {
+    let mut dir = path.as_os_str().to_owned();
+    dir.push(".d");
+    PathBuf::from(dir)
+}
+
+/// The `*.toml` files of the drop-in directory, sorted by name
+fn drop_in_files(path: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(drop_in_dir(path)) else {
+        return vec![];
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml") && path.is_file())
+        .collect();
+    files.sort();
+    files
+}
+
+/// Where the value of a configuration setting comes from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConfigOrigin {
+    /// `tedge.toml` or one of the drop-in files
+    File(PathBuf),
+    /// A `TEDGE_` prefixed environment variable
+    Environment(String),
+    /// Not set, the value being the default one or derived from other settings
+    Default,
+}
+
+impl Display for ConfigOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::File(path) => write!(f, "file:{}", path.display()),
+            Self::Environment(variable) => write!(f, "env:{variable}"),
+            Self::Default => write!(f, "default"),
+        }
+    }
+}
+
+/// All the configuration layers, used to tell where each setting comes from
+pub struct ConfigOrigins {
+    figment: Figment,
+    env: TEdgeEnv,
+}
+
+impl ConfigOrigins {
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        let env = TEdgeEnv::default();
+        let figment = layered_figment::<FileAndEnvironment>(path.as_ref(), &env);
+        ConfigOrigins { figment, env }
+    }
+
+    /// Load the layers persisted on disk only, i.e. `tedge.toml` and its drop-in files
+    ///
+    /// A setting overridden by an environment variable is then attributed to its file.
+    pub fn load_persisted(path: impl AsRef<Path>) -> Self {
+        let env = TEdgeEnv::default();
+        let figment = layered_figment::<FileAndDropIns>(path.as_ref(), &env);
+        ConfigOrigins { figment, env }
+    }
+
+    /// The origin of the value of a key, e.g. `c8y.url` or `c8y.profiles.staging.url`
+    pub fn origin_of(&self, key: &str) -> ConfigOrigin {
+        let metadata = self.figment.find_metadata(key);
+        match metadata.and_then(|metadata| ConfigurationSource::infer(&self.env, key, metadata)) {
+            Some(ConfigurationSource::TomlFile(path)) => ConfigOrigin::File(path),
+            Some(ConfigurationSource::EnvVariable(variable)) => ConfigOrigin::Environment(variable),
+            Some(ConfigurationSource::Unknown(_)) | None => ConfigOrigin::Default,
+        }
+    }
+}
+
 #[cfg(feature = "test")]
 pub fn extract_from_toml_str<T: DeserializeOwned>(toml: &str) -> Result<T, TEdgeConfigError> {
     let env = TEdgeEnv::default();
@@ -381,6 +485,89 @@ mod tests {
         })
     }
 
+    #[test]
+    fn drop_in_files_are_merged_in_lexical_order_before_tedge_toml() {
+        #[derive(Deserialize)]
+        struct Config {
+            c8y: C8yConfig,
+        }
+
+        #[derive(Deserialize)]
+        struct C8yConfig {
+            url: String,
+            proxy: String,
+            timeout: String,
+        }
+
+        figment::Jail::expect_with(|jail| {
+            jail.create_dir("tedge.toml.d")?;
+            jail.create_file(
+                "tedge.toml.d/10-package.toml",
+                "c8y.url = \"package.c8y.io\"\nc8y.proxy = \"package\"\nc8y.timeout = \"package\"",
+            )?;
+            jail.create_file("tedge.toml.d/20-site.toml", "c8y.proxy = \"site\"")?;
+            jail.create_file("tedge.toml.d/30-ignored.conf", "c8y.proxy = \"ignored\"")?;
+            jail.create_file("tedge.toml", "c8y.url = \"test.c8y.io\"")?;
+
+            let config = extract_data::<Config, FileAndEnvironment>("tedge.toml")
+                .unwrap()
+                .0
+                .c8y;
+            assert_eq!(config.url, "test.c8y.io");
+            assert_eq!(config.proxy, "site");
+            assert_eq!(config.timeout, "package");
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn drop_in_files_are_ignored_in_file_only_mode() {
+        #[derive(Deserialize, Debug)]
+        struct Config {
+            value: Option<String>,
+        }
+
+        figment::Jail::expect_with(|jail| {
+            jail.create_dir("tedge.toml.d")?;
+            jail.create_file("tedge.toml.d/10-package.toml", "value = \"drop-in\"")?;
+
+            let data = extract_data::<Config, FileOnly>("tedge.toml").unwrap();
+            assert_eq!(data.0.value, None);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn origins_tell_which_layer_provides_a_value() {
+        figment::Jail::expect_with(|jail| {
+            jail.create_dir("tedge.toml.d")?;
+            jail.create_file("tedge.toml.d/10-package.toml", "az.url = \"package\"")?;
+            jail.create_file(
+                "tedge.toml",
+                "mqtt.bind.port = 1883\n[c8y.profiles.new]\nurl = \"new.c8y.io\"",
+            )?;
+            jail.set_env("TEDGE_MQTT_BIND_PORT", "2883");
+
+            let origins = ConfigOrigins::load("tedge.toml");
+            let origin_file = |origin| match origin {
+                ConfigOrigin::File(path) => path.file_name().unwrap().to_owned(),
+                origin => panic!("unexpected origin {origin}"),
+            };
+
+            assert_eq!(origin_file(origins.origin_of("az.url")), "10-package.toml");
+            assert_eq!(
+                origin_file(origins.origin_of("c8y.profiles.new.url")),
+                "tedge.toml"
+            );
+            assert_eq!(
+                origins.origin_of("mqtt.bind.port"),
+                ConfigOrigin::Environment("TEDGE_MQTT_BIND_PORT".into())
+            );
+            assert_eq!(origins.origin_of("c8y.url"), ConfigOrigin::Default);
+            Ok(())
+        })
+    }
+
     #[test]
     fn environment_variables_can_override_profiled_configurations() {
         use tedge_config_macros::*;
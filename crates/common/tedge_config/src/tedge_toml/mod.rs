@@ -1,5 +1,7 @@
 pub mod error;
 mod figment;
+pub use figment::ConfigOrigin;
+pub use figment::ConfigOrigins;
 pub mod models;
 pub mod tedge_config;
 pub use tedge_config::*;
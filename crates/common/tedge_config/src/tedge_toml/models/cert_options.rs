@@ -0,0 +1,132 @@
+use certificate::KeyAlgorithm;
+use std::convert::Infallible;
+use std::str::FromStr;
+use strum_macros::Display;
+
+/// The type of key generated for a new device certificate
+#[derive(
+    Debug, Display, Clone, Copy, Eq, PartialEq, doku::Document, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum KeyType {
+    Ecdsa,
+    Ed25519,
+}
+
+impl KeyType {
+    /// The algorithm of a new key of this type, the curve being only relevant for ECDSA
+    pub fn key_algorithm(self, curve: EcCurve) -> KeyAlgorithm {
+        match (self, curve) {
+            (KeyType::Ecdsa, EcCurve::P256) => KeyAlgorithm::EcdsaP256,
+            (KeyType::Ecdsa, EcCurve::P384) => KeyAlgorithm::EcdsaP384,
+            (KeyType::Ed25519, _) => KeyAlgorithm::Ed25519,
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("Failed to parse key type: {input}. Supported values are: 'ecdsa' or 'ed25519'")]
+pub struct InvalidKeyType {
+    input: String,
+}
+
+impl FromStr for KeyType {
+    type Err = InvalidKeyType;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "ecdsa" => Ok(KeyType::Ecdsa),
+            "ed25519" => Ok(KeyType::Ed25519),
+            _ => Err(InvalidKeyType {
+                input: input.to_string(),
+            }),
+        }
+    }
+}
+
+/// The elliptic curve used for a new ECDSA key
+#[derive(
+    Debug, Display, Clone, Copy, Eq, PartialEq, doku::Document, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum EcCurve {
+    P256,
+    P384,
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("Failed to parse elliptic curve: {input}. Supported values are: 'p256' or 'p384'")]
+pub struct InvalidEcCurve {
+    input: String,
+}
+
+impl FromStr for EcCurve {
+    type Err = InvalidEcCurve;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.to_ascii_lowercase().as_str() {
+            "p256" | "p-256" => Ok(EcCurve::P256),
+            "p384" | "p-384" => Ok(EcCurve::P384),
+            _ => Err(InvalidEcCurve {
+                input: input.to_string(),
+            }),
+        }
+    }
+}
+
+/// The subject alternative names added to a new device certificate
+///
+/// Each entry is either `dns:<name>`, `uri:<uri>`, `ip:<address>` or `email:<address>`.
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize, Eq, PartialEq)]
+#[serde(from = "FromTomlOrCli")]
+pub struct SubjectAltNames(pub Vec<String>);
+
+impl doku::Document for SubjectAltNames {
+    fn ty() -> doku::Type {
+        Vec::<String>::ty()
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum FromTomlOrCli {
+    Toml(Vec<String>),
+    Cli(String),
+}
+
+impl From<FromTomlOrCli> for SubjectAltNames {
+    fn from(value: FromTomlOrCli) -> Self {
+        match value {
+            FromTomlOrCli::Toml(entries) => Self(entries),
+            FromTomlOrCli::Cli(entries) => Self::from(entries.as_str()),
+        }
+    }
+}
+
+impl<'a> From<&'a str> for SubjectAltNames {
+    fn from(value: &'a str) -> Self {
+        Self(
+            value
+                .split(',')
+                .map(|s| s.trim().to_owned())
+                .filter(|s| !s.is_empty())
+                .collect(),
+        )
+    }
+}
+
+impl FromStr for SubjectAltNames {
+    type Err = Infallible;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Ok(Self::from(value))
+    }
+}
+
+impl std::fmt::Display for SubjectAltNames {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.join(","))
+    }
+}
@@ -2,6 +2,7 @@ pub mod apt_config;
 pub mod auth_method;
 pub mod auto;
 pub mod c8y_software_management;
+pub mod cert_options;
 pub mod connect_url;
 pub mod flag;
 pub mod host_port;
@@ -24,6 +25,7 @@ pub const MQTT_TLS_PORT: u16 = 8883;
 pub use self::apt_config::*;
 pub use self::auto::*;
 pub use self::c8y_software_management::*;
+pub use self::cert_options::*;
 pub use self::connect_url::*;
 pub use self::flag::*;
 #[doc(inline)]
@@ -68,6 +70,116 @@ impl FromStr for AutoLogUpload {
     }
 }
 
+#[derive(
+    Debug, Display, Clone, Copy, Eq, PartialEq, doku::Document, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum CertRenewalMethod {
+    /// Enrol a new certificate with an EST server
+    Est,
+    /// Enrol a new certificate with the Cumulocity certificate authority
+    C8y,
+    /// Delegate the enrolment of a new certificate to a user-provided script
+    Script,
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("Failed to parse certificate renewal method: {input}. Supported values are: 'est', 'c8y' or 'script'")]
+pub struct InvalidCertRenewalMethod {
+    input: String,
+}
+
+impl FromStr for CertRenewalMethod {
+    type Err = InvalidCertRenewalMethod;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "est" => Ok(CertRenewalMethod::Est),
+            "c8y" => Ok(CertRenewalMethod::C8y),
+            "script" => Ok(CertRenewalMethod::Script),
+            _ => Err(InvalidCertRenewalMethod {
+                input: input.to_string(),
+            }),
+        }
+    }
+}
+
+#[derive(
+    Debug, Display, Clone, Copy, Eq, PartialEq, doku::Document, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum WatchdogMode {
+    /// Notify the systemd watchdog on behalf of the services responding to health checks
+    Systemd,
+    /// Restart the unresponsive services using the init system commands of `system.toml`
+    Generic,
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("Failed to parse watchdog mode: {input}. Supported values are: 'systemd' or 'generic'")]
+pub struct InvalidWatchdogMode {
+    input: String,
+}
+
+impl FromStr for WatchdogMode {
+    type Err = InvalidWatchdogMode;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "systemd" => Ok(WatchdogMode::Systemd),
+            "generic" => Ok(WatchdogMode::Generic),
+            _ => Err(InvalidWatchdogMode {
+                input: input.to_string(),
+            }),
+        }
+    }
+}
+
+/// The precision of the timestamps of InfluxDB line protocol points
+#[derive(
+    Debug, Display, Clone, Copy, Eq, PartialEq, doku::Document, serde::Serialize, serde::Deserialize,
+)]
+pub enum TimestampPrecision {
+    #[serde(rename = "ns")]
+    #[strum(serialize = "ns")]
+    Nanoseconds,
+    #[serde(rename = "us")]
+    #[strum(serialize = "us")]
+    Microseconds,
+    #[serde(rename = "ms")]
+    #[strum(serialize = "ms")]
+    Milliseconds,
+    #[serde(rename = "s")]
+    #[strum(serialize = "s")]
+    Seconds,
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error(
+    "Failed to parse timestamp precision: {input}. Supported values are: 'ns', 'us', 'ms' or 's'"
+)]
+pub struct InvalidTimestampPrecision {
+    input: String,
+}
+
+impl FromStr for TimestampPrecision {
+    type Err = InvalidTimestampPrecision;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "ns" => Ok(TimestampPrecision::Nanoseconds),
+            "us" => Ok(TimestampPrecision::Microseconds),
+            "ms" => Ok(TimestampPrecision::Milliseconds),
+            "s" => Ok(TimestampPrecision::Seconds),
+            _ => Err(InvalidTimestampPrecision {
+                input: input.to_string(),
+            }),
+        }
+    }
+}
+
 pub const MQTT_MAX_PAYLOAD_SIZE: u32 = 268435455;
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize, Document)]
@@ -9,13 +9,19 @@ use super::models::timestamp::TimeFormat;
 use super::models::AptConfig;
 use super::models::AutoFlag;
 use super::models::AutoLogUpload;
+use super::models::CertRenewalMethod;
 use super::models::ConnectUrl;
+use super::models::EcCurve;
 use super::models::HostPort;
+use super::models::KeyType;
 use super::models::MqttPayloadLimit;
 use super::models::SecondsOrHumanTime;
 use super::models::SoftwareManagementApiFlag;
+use super::models::SubjectAltNames;
 use super::models::TemplatesSet;
+use super::models::TimestampPrecision;
 use super::models::TopicPrefix;
+use super::models::WatchdogMode;
 use super::models::HTTPS_PORT;
 use super::models::MQTT_TLS_PORT;
 use super::tedge_config_location::TEdgeConfigLocation;
@@ -29,6 +35,7 @@ use certificate::parse_root_certificate::create_tls_config_without_client_cert;
 use certificate::read_trust_store;
 use certificate::CertificateError;
 use certificate::CloudRootCerts;
+use certificate::NewCertificateConfig;
 use certificate::PemCertificate;
 use doku::Document;
 use once_cell::sync::Lazy;
@@ -48,9 +55,11 @@ use tedge_config_macros::*;
 use tracing::error;
 
 mod mqtt_config;
+pub use mqtt_config::LocalCaLayout;
 pub use mqtt_config::MqttAuthClientConfig;
 pub use mqtt_config::MqttAuthConfig;
 pub use mqtt_config::MqttAuthConfigCloudBroker;
+pub use mqtt_config::LOCAL_CA_DEFAULT_CLIENT;
 
 const DEFAULT_ROOT_CERT_PATH: &str = "/etc/ssl/certs";
 
@@ -131,6 +140,44 @@ define_tedge_config! {
         #[doku(as = "PathBuf")]
         csr_path: Utf8PathBuf,
 
+        cert: {
+            /// The type of key generated for a new device certificate
+            #[tedge_config(example = "ecdsa", example = "ed25519", default(variable = "KeyType::Ecdsa"))]
+            key_type: KeyType,
+
+            /// The elliptic curve used to generate a new ECDSA key
+            #[tedge_config(example = "p256", example = "p384", default(variable = "EcCurve::P256"))]
+            curve: EcCurve,
+
+            /// The number of days a new self-signed device certificate is valid
+            #[tedge_config(example = "365", default(value = 365u32))]
+            validity_days: u32,
+
+            /// The organization name of the subject of a new device certificate
+            #[tedge_config(example = "Acme Corp.", default(value = "Thin Edge"))]
+            organization: String,
+
+            /// The organizational unit name of the subject of a new device certificate
+            #[tedge_config(example = "IoT", default(value = "Test Device"))]
+            organizational_unit: String,
+
+            /// The two-letter country code of the subject of a new device certificate
+            #[tedge_config(example = "DE")]
+            country: String,
+
+            /// The state or province of the subject of a new device certificate
+            #[tedge_config(example = "Hesse")]
+            state: String,
+
+            /// The locality of the subject of a new device certificate
+            #[tedge_config(example = "Darmstadt")]
+            locality: String,
+
+            /// The subject alternative names added to a new device certificate
+            #[tedge_config(example = "dns:my-device.local,uri:urn:device:my-device,ip:10.0.0.1", default(function = "SubjectAltNames::default"))]
+            subject_alt_names: SubjectAltNames,
+        },
+
         cryptoki: {
             /// Use a Hardware Security Module for authenticating the MQTT connection with the cloud.
             ///
@@ -153,6 +200,19 @@ define_tedge_config! {
             /// Necessary if two or more modules are connected.
             #[tedge_config(example = "123456789")]
             serial: Arc<str>,
+
+            /// The identifier of the PKCS#11 slot holding the token to be used.
+            ///
+            /// If not set, the first slot with a token is used.
+            #[tedge_config(example = "0")]
+            slot: u64,
+
+            /// The label of the private key on the token.
+            ///
+            /// Set on the keys generated by `tedge cert create-csr --cryptoki`,
+            /// and used to select the key signing the TLS handshakes.
+            #[tedge_config(example = "tedge")]
+            label: Arc<str>,
         },
 
         /// The default device type
@@ -239,7 +299,7 @@ define_tedge_config! {
 
         /// Set of MQTT topics the Cumulocity mapper should subscribe to
         #[tedge_config(example = "te/+/+/+/+/a/+,te/+/+/+/+/m/+,te/+/+/+/+/e/+")]
-        #[tedge_config(default(value = "te/+/+/+/+,te/+/+/+/+/twin/+,te/+/+/+/+/m/+,te/+/+/+/+/e/+,te/+/+/+/+/a/+,te/+/+/+/+/status/health"))]
+        #[tedge_config(default(value = "te/+/+/+/+,te/+/+/+/+/twin/+,te/+/+/+/+/m/+,te/+/+/+/+/e/+,te/+/+/+/+/a/+,te/+/+/+/+/a/+/status,te/+/+/+/+/status/health"))]
         topics: TemplatesSet,
 
         enable: {
@@ -262,6 +322,14 @@ define_tedge_config! {
             /// Enable device_profile feature
             #[tedge_config(example = "true", default(value = true))]
             device_profile: bool,
+
+            /// Enable the mapping of c8y_Command operations to command operations
+            #[tedge_config(example = "true", default(value = true))]
+            command: bool,
+
+            /// Enable the mapping of the hardware and network twin fragments published by tedge-agent to c8y_Hardware and c8y_Network
+            #[tedge_config(example = "true", default(value = false))]
+            device_details: bool,
         },
 
         mapper: {
@@ -269,7 +337,13 @@ define_tedge_config! {
                 /// The maximum message payload size that can be mapped to the cloud via MQTT
                 #[tedge_config(example = "16184", default(function = "c8y_mqtt_payload_limit"))]
                 max_payload_size: MqttPayloadLimit,
-            }
+            },
+
+            metrics: {
+                /// The port of the Cumulocity mapper Prometheus metrics endpoint. The endpoint is disabled if not set
+                #[tedge_config(example = "9103")]
+                port: u16,
+            },
         },
 
         proxy: {
@@ -427,7 +501,13 @@ define_tedge_config! {
                 /// The maximum message payload size that can be mapped to the cloud via MQTT
                 #[tedge_config(example = "262144", default(function = "az_mqtt_payload_limit"))]
                 max_payload_size: MqttPayloadLimit,
-            }
+            },
+
+            metrics: {
+                /// The port of the Azure IoT mapper Prometheus metrics endpoint. The endpoint is disabled if not set
+                #[tedge_config(example = "9104")]
+                port: u16,
+            },
         },
 
         bridge: {
@@ -501,7 +581,13 @@ define_tedge_config! {
                 /// The maximum message payload size that can be mapped to the cloud via MQTT
                 #[tedge_config(example = "131072", default(function = "aws_mqtt_payload_limit"))]
                 max_payload_size: MqttPayloadLimit,
-            }
+            },
+
+            metrics: {
+                /// The port of the AWS IoT mapper Prometheus metrics endpoint. The endpoint is disabled if not set
+                #[tedge_config(example = "9105")]
+                port: u16,
+            },
         },
 
         bridge: {
@@ -588,6 +674,22 @@ define_tedge_config! {
             }
         },
 
+        local_ca: {
+            /// Authenticate the local MQTT broker and its clients with certificates issued by a local CA
+            #[tedge_config(note = "After changing this value, run `tedge init` and `tedge reconnect <cloud>` to apply the changes")]
+            #[tedge_config(example = "true", default(value = false))]
+            enable: bool,
+
+            /// The directory holding the local CA, the MQTT broker certificate and the per-service client certificates
+            #[tedge_config(example = "/etc/tedge/device-certs/local", default(function = "default_local_ca_dir"))]
+            #[doku(as = "PathBuf")]
+            dir: Utf8PathBuf,
+
+            /// Number of days the certificates issued by the local CA are valid for
+            #[tedge_config(example = "365", default(value = 365u32))]
+            validity_days: u32,
+        },
+
         external: {
             bind: {
                 /// The port mosquitto binds to for external use
@@ -680,6 +782,13 @@ define_tedge_config! {
                 #[doku(as = "PathBuf")]
                 #[tedge_config(reader(private))]
                 key_file: Utf8PathBuf,
+
+                /// Path to a file holding the bearer token used by `tedge http` and `tedge diag`
+                /// to authenticate to the agent HTTP API, as declared in `http.access_policy_path`
+                #[tedge_config(example = "/etc/tedge/http-client-token")]
+                #[doku(as = "PathBuf")]
+                #[tedge_config(reader(private))]
+                token_file: Utf8PathBuf,
             },
         },
 
@@ -698,9 +807,123 @@ define_tedge_config! {
         #[tedge_config(example = "/etc/ssl/certs")]
         #[doku(as = "PathBuf")]
         ca_path: Utf8PathBuf,
+
+        /// Path to a TOML file listing the clients allowed to use the agent HTTP API,
+        /// along with the entity and file transfer subtree each client is restricted to
+        #[tedge_config(example = "/etc/tedge/http-access-policy.toml")]
+        #[doku(as = "PathBuf")]
+        access_policy_path: Utf8PathBuf,
+    },
+
+    metrics: {
+        bind: {
+            /// The address the Prometheus metrics endpoints of the thin-edge services bind to
+            #[tedge_config(default(variable = "Ipv4Addr::LOCALHOST"))]
+            #[tedge_config(example = "127.0.0.1", example = "0.0.0.0")]
+            address: IpAddr,
+        },
+
+        /// The file that will be used as the server certificate for the metrics endpoints
+        #[tedge_config(example = "/etc/tedge/device-certs/metrics_certificate.pem")]
+        #[doku(as = "PathBuf")]
+        cert_path: Utf8PathBuf,
+
+        /// The file that will be used as the server private key for the metrics endpoints
+        #[tedge_config(example = "/etc/tedge/device-certs/metrics_key.pem")]
+        #[doku(as = "PathBuf")]
+        key_path: Utf8PathBuf,
+
+        /// Path to a directory containing the PEM encoded CA certificates that are
+        /// trusted when checking the client certificates of the metrics scrapers
+        #[tedge_config(example = "/etc/ssl/certs")]
+        #[doku(as = "PathBuf")]
+        ca_path: Utf8PathBuf,
+    },
+
+    influx: {
+        /// Set of MQTT topics on which the InfluxDB line protocol mapper receives points
+        #[tedge_config(example = "influx/#,telegraf/#", default(value = "influx/#"))]
+        topics: TemplatesSet,
+
+        /// The precision of the timestamps of the points received by the InfluxDB line protocol mapper
+        #[tedge_config(example = "ns", example = "ms", example = "s", default(variable = "TimestampPrecision::Nanoseconds"))]
+        precision: TimestampPrecision,
+
+        /// The tag naming the child device a point is attributed to.
+        /// The points with no such tag are attributed to the main device
+        #[tedge_config(example = "device", default(value = "device"))]
+        device_tag: String,
+
+        /// The tag naming the service a point is attributed to
+        #[tedge_config(example = "service", default(value = "service"))]
+        service_tag: String,
+
+        udp: {
+            bind: {
+                /// The UDP port on which the InfluxDB line protocol mapper receives points.
+                /// No UDP socket is opened if not set
+                #[tedge_config(example = "8089")]
+                port: u16,
+
+                /// The address the UDP socket of the InfluxDB line protocol mapper binds to
+                #[tedge_config(example = "127.0.0.1", example = "0.0.0.0", default(variable = "Ipv4Addr::LOCALHOST"))]
+                address: IpAddr,
+            },
+        },
+
+        http: {
+            bind: {
+                /// The port of the InfluxDB compatible `/write` HTTP endpoint of the line protocol mapper.
+                /// No HTTP endpoint is served if not set
+                #[tedge_config(example = "8086")]
+                port: u16,
+
+                /// The address the `/write` HTTP endpoint of the InfluxDB line protocol mapper binds to
+                #[tedge_config(example = "127.0.0.1", example = "0.0.0.0", default(variable = "Ipv4Addr::LOCALHOST"))]
+                address: IpAddr,
+            },
+        },
+    },
+
+    gateway: {
+        mqttsn: {
+            bind: {
+                /// The UDP port on which the gateway accepts MQTT-SN clients.
+                /// No MQTT-SN socket is opened if not set
+                #[tedge_config(example = "1883")]
+                port: u16,
+
+                /// The address the MQTT-SN socket of the gateway binds to
+                #[tedge_config(example = "127.0.0.1", example = "0.0.0.0", default(variable = "Ipv4Addr::LOCALHOST"))]
+                address: IpAddr,
+            },
+        },
+
+        coap: {
+            bind: {
+                /// The UDP port on which the gateway accepts CoAP requests.
+                /// No CoAP socket is opened if not set
+                #[tedge_config(example = "5683")]
+                port: u16,
+
+                /// The address the CoAP socket of the gateway binds to
+                #[tedge_config(example = "127.0.0.1", example = "0.0.0.0", default(variable = "Ipv4Addr::LOCALHOST"))]
+                address: IpAddr,
+            },
+        },
+
+        /// The maximum number of commands kept for a sleeping client until it wakes up
+        #[tedge_config(example = "16", default(value = 16u32))]
+        max_pending_commands: u32,
     },
 
     agent: {
+        metrics: {
+            /// The port of the tedge-agent Prometheus metrics endpoint. The endpoint is disabled if not set
+            #[tedge_config(example = "9102")]
+            port: u16,
+        },
+
         state: {
             /// The directory where the tedge-agent persists its state across restarts
             #[tedge_config(note = "If the given directory doesn't exists, `/etc/tedge/.agent` is used as a fallback irrespective of the current setting.")]
@@ -722,6 +945,30 @@ define_tedge_config! {
             /// Determines if tedge-agent should enable log_upload operation
             #[tedge_config(example = "true", default(value = true))]
             log_upload: bool,
+
+            /// Determines if tedge-agent should enable the command operation, running the command lines sent by the cloud
+            #[tedge_config(example = "true", default(value = false))]
+            command: bool,
+        },
+
+        command: {
+            /// The programs the command operation is allowed to run, `*` allowing any program
+            ///
+            /// A program is matched as given in the command line, e.g. `ls` doesn't allow `/bin/ls`.
+            #[tedge_config(example = "df,journalctl,systemctl", example = "*", default(function = "TemplatesSet::default"))]
+            allowed: TemplatesSet,
+
+            /// The user running the commands, using sudo. The commands are run by the tedge-agent user if not set
+            #[tedge_config(example = "tedge")]
+            user: String,
+
+            /// How long a command is given to complete, before being sent a SIGTERM
+            #[tedge_config(example = "60s", default(from_str = "60s"))]
+            timeout: SecondsOrHumanTime,
+
+            /// How long a command is given to terminate on SIGTERM, before being killed with a SIGKILL
+            #[tedge_config(example = "10s", default(from_str = "10s"))]
+            kill_timeout: SecondsOrHumanTime,
         },
 
         entity_store: {
@@ -734,7 +981,79 @@ define_tedge_config! {
             clean_start: bool,
         },
 
+        cert_renewal: {
+            /// Determines if tedge-agent should renew the device certificate before it expires
+            #[tedge_config(example = "true", default(value = false))]
+            enable: bool,
+
+            /// How long before the expiry date the device certificate is renewed
+            #[tedge_config(example = "30d", default(from_str = "30d"))]
+            window: SecondsOrHumanTime,
 
+            /// How often tedge-agent checks the expiry date of the device certificate
+            #[tedge_config(example = "12h", default(from_str = "12h"))]
+            check_interval: SecondsOrHumanTime,
+
+            /// The protocol used to get a new certificate signed
+            #[tedge_config(example = "c8y", example = "est", example = "script", default(variable = "CertRenewalMethod::C8y"))]
+            method: CertRenewalMethod,
+
+            /// The URL of the EST server, when the `est` method is used
+            #[tedge_config(example = "https://est.example.com/.well-known/est")]
+            url: String,
+
+            /// The script used to get a new certificate signed, when the `script` method is used
+            ///
+            /// The script is given the path to the certificate signing request
+            /// and has to print the signed certificate on its standard output.
+            #[tedge_config(example = "/etc/tedge/renew-certificate.sh")]
+            #[doku(as = "PathBuf")]
+            script: Utf8PathBuf,
+
+            /// Determines if a new private key is generated on each renewal
+            #[tedge_config(example = "true", default(value = false))]
+            rotate_key: bool,
+
+            /// The cloud to reconnect once the certificate has been renewed
+            #[tedge_config(example = "c8y")]
+            reconnect: String,
+        },
+
+        inventory: {
+            /// Determines if tedge-agent should publish the hardware, OS, network and storage details of the device as twin data
+            #[tedge_config(example = "true", default(value = true))]
+            enable: bool,
+
+            /// How often tedge-agent collects the inventory, publishing the fragments that have changed
+            #[tedge_config(example = "1h", default(from_str = "1h"))]
+            interval: SecondsOrHumanTime,
+
+            /// How long an inventory script is given to print its fragments, before being killed
+            #[tedge_config(example = "10s", default(from_str = "10s"))]
+            script_timeout: SecondsOrHumanTime,
+        },
+
+        alarms: {
+            /// Determines if tedge-agent should manage the lifecycle of the alarms: flap suppression, escalation, acknowledgement and shelving
+            #[tedge_config(example = "true", default(value = false))]
+            enable: bool,
+
+            flapping: {
+                /// The number of raise and clear transitions within the window for an alarm to be suppressed as flapping
+                #[tedge_config(example = "5", default(value = 5u32))]
+                transitions: u32,
+
+                /// The window over which the transitions of an alarm are counted, a suppressed alarm being restored once stable over this window
+                #[tedge_config(example = "60s", default(from_str = "60s"))]
+                window: SecondsOrHumanTime,
+            },
+
+            escalation: {
+                /// How long an alarm can stay active and unacknowledged before its severity is raised by one level. Alarms are not escalated if not set
+                #[tedge_config(example = "15m")]
+                after: SecondsOrHumanTime,
+            },
+        },
     },
 
     software: {
@@ -813,6 +1132,43 @@ define_tedge_config! {
         #[tedge_config(example = "unix")]
         #[tedge_config(default(variable = "TimeFormat::Unix"))]
         timestamp_format: TimeFormat,
+
+        /// How often the thin-edge.io services publish the statistics of their actors as a measurement.
+        /// Set to 0 to disable the publication.
+        #[tedge_config(example = "60s", default(from_str = "0"))]
+        metrics_interval: SecondsOrHumanTime,
+    },
+
+    watchdog: {
+        /// How tedge-watchdog supervises the thin-edge.io services
+        ///
+        /// With `systemd`, the services are restarted by systemd when tedge-watchdog stops notifying their `WatchdogSec`.
+        /// With `generic`, tedge-watchdog restarts the unresponsive services itself, using the `init` commands of `system.toml`.
+        #[tedge_config(example = "systemd", example = "generic", default(variable = "WatchdogMode::Systemd"))]
+        mode: WatchdogMode,
+
+        /// The services monitored by the generic watchdog
+        #[tedge_config(example = "tedge-agent,tedge-mapper-c8y")]
+        #[tedge_config(default(value = "tedge-agent,tedge-mapper-c8y,tedge-mapper-az,tedge-mapper-aws,tedge-mapper-collectd,c8y-firmware-plugin"))]
+        services: TemplatesSet,
+
+        /// How often the generic watchdog sends health check requests to the services
+        #[tedge_config(example = "30s", default(from_str = "30s"))]
+        check_interval: SecondsOrHumanTime,
+
+        /// How long the generic watchdog waits for a health check response, before restarting a service
+        #[tedge_config(example = "10s", default(from_str = "10s"))]
+        timeout: SecondsOrHumanTime,
+
+        /// The maximum number of times a service is restarted within the restart window
+        ///
+        /// Once exceeded, the service is no more restarted and an alarm is raised until it responds again.
+        #[tedge_config(example = "3", default(value = 3u32))]
+        max_restarts: u32,
+
+        /// The time window over which the restarts of a service are counted
+        #[tedge_config(example = "1h", default(from_str = "1h"))]
+        restart_window: SecondsOrHumanTime,
     },
 
     apt: {
@@ -1123,6 +1479,13 @@ fn default_device_cert(location: &TEdgeConfigLocation) -> Utf8PathBuf {
         .join("tedge-certificate.pem")
 }
 
+fn default_local_ca_dir(location: &TEdgeConfigLocation) -> Utf8PathBuf {
+    location
+        .tedge_config_root_path()
+        .join("device-certs")
+        .join("local")
+}
+
 fn default_device_csr(location: &TEdgeConfigLocation) -> Utf8PathBuf {
     location
         .tedge_config_root_path()
@@ -1239,6 +1602,30 @@ impl TEdgeConfigReaderHttp {
     }
 }
 
+impl TEdgeConfigReaderDeviceCert {
+    /// The settings used to create a new device certificate or certificate signing request
+    pub fn new_certificate_config(&self) -> Result<NewCertificateConfig, CertificateError> {
+        let subject_alt_names = self
+            .subject_alt_names
+            .0
+            .iter()
+            .map(|name| name.parse())
+            .collect::<Result<_, _>>()?;
+
+        Ok(NewCertificateConfig {
+            validity_period_days: self.validity_days,
+            organization_name: self.organization.clone(),
+            organizational_unit_name: self.organizational_unit.clone(),
+            country_name: self.country.or_none().cloned(),
+            state_or_province_name: self.state.or_none().cloned(),
+            locality_name: self.locality.or_none().cloned(),
+            subject_alt_names,
+            key_algorithm: self.key_type.key_algorithm(self.curve),
+            ..NewCertificateConfig::default()
+        })
+    }
+}
+
 impl TEdgeConfigReaderHttpClientAuth {
     pub fn identity(&self) -> anyhow::Result<Option<reqwest::Identity>> {
         use ReadableKey::*;
@@ -1264,6 +1651,19 @@ impl TEdgeConfigReaderHttpClientAuth {
             None => None,
         })
     }
+
+    /// The bearer token to authenticate to the agent HTTP API, if any
+    pub fn bearer_token(&self) -> anyhow::Result<Option<String>> {
+        use ReadableKey::*;
+
+        let Some(token_file) = self.token_file.or_none() else {
+            return Ok(None);
+        };
+        let token = std::fs::read_to_string(token_file).with_context(|| {
+            format!("reading bearer token (from {HttpClientAuthTokenFile}): {token_file}")
+        })?;
+        Ok(Some(token.trim().to_string()))
+    }
 }
 
 #[cfg(test)]
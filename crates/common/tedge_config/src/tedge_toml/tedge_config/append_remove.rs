@@ -48,10 +48,16 @@ impl_append_remove_for_single_value!(
     TopicPrefix,
     SoftwareManagementApiFlag,
     AutoLogUpload,
+    CertRenewalMethod,
+    WatchdogMode,
+    TimestampPrecision,
+    KeyType,
+    EcCurve,
     TimeFormat,
     NonZeroU16,
     SecondsOrHumanTime,
     u32,
+    u64,
     AptConfig,
     MqttPayloadLimit,
     AuthMethod
@@ -88,3 +94,31 @@ impl AppendRemoveItem for TemplatesSet {
         current_value
     }
 }
+
+impl AppendRemoveItem for SubjectAltNames {
+    type Item = SubjectAltNames;
+
+    fn append(current_value: Option<Self::Item>, new_value: Self::Item) -> Option<Self::Item> {
+        let Some(mut current_value) = current_value else {
+            return Some(new_value);
+        };
+        for name in new_value.0 {
+            if !current_value.0.contains(&name) {
+                current_value.0.push(name);
+            }
+        }
+        Some(current_value)
+    }
+
+    fn remove(current_value: Option<Self::Item>, remove_value: Self::Item) -> Option<Self::Item> {
+        let mut current_value = current_value;
+
+        if let Some(ref mut current_value) = current_value {
+            current_value
+                .0
+                .retain(|name| !remove_value.0.contains(name));
+        }
+
+        current_value
+    }
+}
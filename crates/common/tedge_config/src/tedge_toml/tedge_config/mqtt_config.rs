@@ -7,9 +7,11 @@
 
 use crate::TEdgeConfig;
 use anyhow::Context;
+use camino::Utf8Path;
 use camino::Utf8PathBuf;
 use certificate::CertificateError;
 use tedge_config_macros::all_or_nothing;
+use tracing::debug;
 
 use super::TEdgeConfigReaderDeviceCryptoki;
 
@@ -141,8 +143,74 @@ impl MqttAuthConfig {
     }
 }
 
+/// The name of the local CA client certificate used by the `tedge` commands,
+/// and by any service with no certificate of its own
+pub const LOCAL_CA_DEFAULT_CLIENT: &str = "tedge";
+
+/// The files of the local CA, as laid out under `mqtt.local_ca.dir`
+///
+/// ```text
+/// ca.crt, ca.key              the local CA
+/// broker.crt, broker.key      the certificate of the local MQTT broker
+/// clients/<service>.crt/.key  the client certificate of each service
+/// ```
+#[derive(Debug, Clone)]
+pub struct LocalCaLayout {
+    dir: Utf8PathBuf,
+}
+
+impl LocalCaLayout {
+    pub fn new(dir: impl Into<Utf8PathBuf>) -> Self {
+        LocalCaLayout { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Utf8Path {
+        &self.dir
+    }
+
+    pub fn ca_cert(&self) -> Utf8PathBuf {
+        self.dir.join("ca.crt")
+    }
+
+    pub fn ca_key(&self) -> Utf8PathBuf {
+        self.dir.join("ca.key")
+    }
+
+    pub fn broker_cert(&self) -> Utf8PathBuf {
+        self.dir.join("broker.crt")
+    }
+
+    pub fn broker_key(&self) -> Utf8PathBuf {
+        self.dir.join("broker.key")
+    }
+
+    pub fn clients_dir(&self) -> Utf8PathBuf {
+        self.dir.join("clients")
+    }
+
+    pub fn client_cert(&self, service: &str) -> Utf8PathBuf {
+        self.clients_dir().join(format!("{service}.crt"))
+    }
+
+    pub fn client_key(&self, service: &str) -> Utf8PathBuf {
+        self.clients_dir().join(format!("{service}.key"))
+    }
+}
+
 impl TEdgeConfig {
+    /// Returns the MQTT configuration used by the `tedge` commands to connect the local broker
     pub fn mqtt_config(&self) -> Result<mqtt_channel::Config, CertificateError> {
+        self.service_mqtt_config(LOCAL_CA_DEFAULT_CLIENT)
+    }
+
+    /// Returns the MQTT configuration used by a service to connect the local broker
+    ///
+    /// When `mqtt.local_ca.enable` is set and no client certificate is explicitly configured,
+    /// the service authenticates with the certificate issued to it by the local CA.
+    pub fn service_mqtt_config(
+        &self,
+        service: &str,
+    ) -> Result<mqtt_channel::Config, CertificateError> {
         let host = self.mqtt.client.host.as_str();
         let port = u16::from(self.mqtt.client.port);
 
@@ -166,11 +234,41 @@ impl TEdgeConfig {
             self.mqtt.client.auth.key_file.as_ref(),
         )) {
             mqtt_config.with_client_auth(client_cert, client_key)?;
+        } else if let Some(identity) = self.local_ca_client_auth(service) {
+            mqtt_config.with_cafile(&identity.ca_file)?;
+            mqtt_config.with_client_auth(&identity.cert_file, &identity.key_file)?;
         }
 
         Ok(mqtt_config)
     }
 
+    /// The layout of the local CA directory
+    pub fn local_ca(&self) -> LocalCaLayout {
+        LocalCaLayout::new(&self.mqtt.local_ca.dir)
+    }
+
+    /// The certificate issued by the local CA to a service, if the local CA is enabled
+    ///
+    /// A service with no certificate of its own uses the certificate of the `tedge` commands.
+    fn local_ca_client_auth(&self, service: &str) -> Option<LocalCaClientAuth> {
+        if !self.mqtt.local_ca.enable || self.mqtt.client.auth.cert_file.or_none().is_some() {
+            return None;
+        }
+
+        let layout = self.local_ca();
+        let service = if layout.client_cert(service).exists() {
+            service
+        } else {
+            debug!("No local CA certificate for {service}, using {LOCAL_CA_DEFAULT_CLIENT} certificate");
+            LOCAL_CA_DEFAULT_CLIENT
+        };
+        Some(LocalCaClientAuth {
+            ca_file: layout.ca_cert(),
+            cert_file: layout.client_cert(service),
+            key_file: layout.client_key(service),
+        })
+    }
+
     /// Returns an authentication configuration for an MQTT client that will connect to the Cumulocity MQTT broker.
     #[cfg(feature = "cryptoki")]
     pub fn mqtt_auth_config_cloud_broker(
@@ -232,6 +330,11 @@ impl TEdgeConfig {
 
     /// Returns an authentication configuration for an MQTT client that will connect to the local MQTT broker.
     pub fn mqtt_client_auth_config(&self) -> MqttAuthConfig {
+        self.service_mqtt_client_auth_config(LOCAL_CA_DEFAULT_CLIENT)
+    }
+
+    /// Returns an authentication configuration for a service that will connect to the local MQTT broker.
+    pub fn service_mqtt_client_auth_config(&self, service: &str) -> MqttAuthConfig {
         let mut client_auth = MqttAuthConfig {
             ca_dir: self.mqtt.client.auth.ca_dir.or_none().cloned(),
             ca_file: self.mqtt.client.auth.ca_file.or_none().cloned(),
@@ -246,11 +349,23 @@ impl TEdgeConfig {
                 cert_file: client_cert.clone(),
                 key_file: client_key.clone(),
             })
+        } else if let Some(identity) = self.local_ca_client_auth(service) {
+            client_auth.ca_file = Some(identity.ca_file);
+            client_auth.client = Some(MqttAuthClientConfig {
+                cert_file: identity.cert_file,
+                key_file: identity.key_file,
+            })
         }
         client_auth
     }
 }
 
+struct LocalCaClientAuth {
+    ca_file: Utf8PathBuf,
+    cert_file: Utf8PathBuf,
+    key_file: Utf8PathBuf,
+}
+
 impl TEdgeConfigReaderDeviceCryptoki {
     #[cfg(feature = "cryptoki")]
     pub fn config(&self) -> Result<Option<CryptokiConfig>, anyhow::Error> {
@@ -258,10 +373,20 @@ impl TEdgeConfigReaderDeviceCryptoki {
             return Ok(None);
         }
 
-        Ok(Some(CryptokiConfig {
-            module_path: self.module_path.or_config_not_set().unwrap().clone(),
+        self.token_config().map(Some)
+    }
+
+    /// The configuration to access the PKCS#11 token, even if not yet used for authentication
+    ///
+    /// This is notably used to generate a new key on the token, before `device.cryptoki.enable` is set.
+    #[cfg(feature = "cryptoki")]
+    pub fn token_config(&self) -> Result<CryptokiConfig, anyhow::Error> {
+        Ok(CryptokiConfig {
+            module_path: self.module_path.or_config_not_set()?.clone(),
             pin: self.pin.clone(),
             serial: self.serial.or_none().cloned(),
-        }))
+            slot: self.slot.or_none().copied(),
+            label: self.label.or_none().cloned(),
+        })
     }
 }
@@ -1,6 +1,8 @@
 use std::path::Path;
 
+use crate::tedge_toml::figment::ConfigOrigins;
 use crate::tedge_toml::figment::ConfigSources;
+use crate::tedge_toml::figment::FileAndDropIns;
 use crate::tedge_toml::figment::FileAndEnvironment;
 use crate::tedge_toml::figment::FileOnly;
 use crate::tedge_toml::figment::UnusedValueWarnings;
@@ -81,6 +83,16 @@ impl TEdgeConfigLocation {
         &self.tedge_config_file_path
     }
 
+    /// Load all the configuration layers, to tell where each setting comes from
+    pub fn config_origins(&self) -> ConfigOrigins {
+        ConfigOrigins::load(self.toml_path())
+    }
+
+    /// Load the configuration layers persisted on disk, to tell from which file each setting is read
+    pub fn persisted_config_origins(&self) -> ConfigOrigins {
+        ConfigOrigins::load_persisted(self.toml_path())
+    }
+
     pub async fn update_toml(
         &self,
         update: &impl Fn(&mut TEdgeConfigDto, &TEdgeConfigReader) -> ConfigSettingResult<()>,
@@ -118,6 +130,13 @@ impl TEdgeConfigLocation {
         self.load_dto::<FileAndEnvironment>(self.toml_path()).await
     }
 
+    /// Load the settings persisted in `tedge.toml` and its drop-in files, ignoring the environment
+    pub async fn load_dto_from_toml_and_drop_ins(
+        &self,
+    ) -> Result<TEdgeConfigDto, TEdgeConfigError> {
+        self.load_dto::<FileAndDropIns>(self.toml_path()).await
+    }
+
     async fn load_dto<Sources: ConfigSources>(
         &self,
         path: &Utf8Path,
@@ -0,0 +1,44 @@
+//! The metrics exported by the thin-edge services
+use crate::Family;
+
+/// Labels: none
+pub static ALLOCATED_MEMORY: Family = Family::gauge(
+    "tedge_allocated_memory_bytes",
+    "Memory allocated by the process",
+);
+
+/// Labels: `topic_family`, as returned by [topic_family](crate::topic_family)
+pub static MQTT_MESSAGES_RECEIVED: Family = Family::counter(
+    "tedge_mqtt_messages_received",
+    "MQTT messages received from the local broker",
+);
+
+/// Labels: `topic_family`, as returned by [topic_family](crate::topic_family)
+pub static MQTT_MESSAGES_PUBLISHED: Family = Family::counter(
+    "tedge_mqtt_messages_published",
+    "MQTT messages published to the local broker",
+);
+
+/// Labels: `bridge`, the bridge service name, and `half`, either `local` or `cloud`
+pub static BRIDGE_CONNECTED: Family = Family::gauge(
+    "tedge_bridge_connected",
+    "Connection state of the built-in MQTT bridge (1 connected, 0 disconnected)",
+);
+
+/// Labels: `mapper`, either `c8y`, `az` or `aws`
+pub static CONVERSION_ERRORS: Family = Family::counter(
+    "tedge_conversion_errors",
+    "Messages which could not be converted by a mapper",
+);
+
+/// Labels: `operation` and `status`
+pub static COMMANDS: Family = Family::counter(
+    "tedge_commands",
+    "Command state transitions, per operation and status",
+);
+
+/// Labels: `operation` and `step`
+pub static WORKFLOW_STEP_DURATION: Family = Family::summary(
+    "tedge_workflow_step_duration_seconds",
+    "Time spent by commands in each workflow step",
+);
@@ -0,0 +1,343 @@
+//! Metrics collected by the thin-edge services
+//!
+//! A service updates the [metric families](Family) defined by this crate,
+//! and, when configured to, exports them in the [OpenMetrics text format](encode)
+//! so they can be scraped by Prometheus.
+//!
+//! The updates are ignored till the metrics are [enabled](enable),
+//! so the services with no metrics endpoint don't pay for the metrics.
+//!
+//! ```
+//! tedge_metrics::enable();
+//! tedge_metrics::COMMANDS.inc(&[("operation", "software_update"), ("status", "successful")]);
+//!
+//! let exported = tedge_metrics::encode();
+//! assert!(exported.contains(
+//!     r#"tedge_commands_total{operation="software_update",status="successful"} 1"#
+//! ));
+//! ```
+mod families;
+
+pub use families::*;
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::Once;
+use std::time::Duration;
+
+/// The content type of the exported metrics
+pub const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// The kind of values recorded by a metric family
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MetricKind {
+    /// A value that only increases, such as a number of messages
+    Counter,
+    /// A value that goes up and down, such as a connection state
+    Gauge,
+    /// A sum and count of observations, such as the durations of a workflow step
+    Summary,
+}
+
+/// A set of metrics sharing the same name, distinguished by their labels
+pub struct Family {
+    name: &'static str,
+    help: &'static str,
+    kind: MetricKind,
+    samples: Mutex<BTreeMap<Labels, Sample>>,
+    registered: Once,
+}
+
+type Labels = Vec<(String, String)>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Sample {
+    Value(f64),
+    Summary { sum: f64, count: u64 },
+}
+
+/// The families to be exported, plus the callbacks to be run before exporting the values
+struct Registry {
+    families: Vec<&'static Family>,
+    collectors: Vec<Arc<dyn Fn() + Send + Sync>>,
+}
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    families: Vec::new(),
+    collectors: Vec::new(),
+});
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Start recording the updates of the metric families
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed)
+}
+
+/// Return `true` if the metrics are recorded, i.e. if [enable] has been called
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+impl Family {
+    pub const fn counter(name: &'static str, help: &'static str) -> Self {
+        Family::new(name, help, MetricKind::Counter)
+    }
+
+    pub const fn gauge(name: &'static str, help: &'static str) -> Self {
+        Family::new(name, help, MetricKind::Gauge)
+    }
+
+    pub const fn summary(name: &'static str, help: &'static str) -> Self {
+        Family::new(name, help, MetricKind::Summary)
+    }
+
+    const fn new(name: &'static str, help: &'static str, kind: MetricKind) -> Self {
+        Family {
+            name,
+            help,
+            kind,
+            samples: Mutex::new(BTreeMap::new()),
+            registered: Once::new(),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Increment by one the counter with the given labels
+    pub fn inc(&'static self, labels: &[(&str, &str)]) {
+        self.add(labels, 1.0)
+    }
+
+    /// Increment the counter with the given labels
+    pub fn add(&'static self, labels: &[(&str, &str)], value: f64) {
+        self.update(labels, |sample| match sample {
+            Sample::Value(current) => *current += value,
+            Sample::Summary { .. } => {}
+        })
+    }
+
+    /// Set the value of the gauge with the given labels
+    pub fn set(&'static self, labels: &[(&str, &str)], value: f64) {
+        self.update(labels, |sample| *sample = Sample::Value(value))
+    }
+
+    /// Record an observation of the summary with the given labels
+    pub fn observe(&'static self, labels: &[(&str, &str)], duration: Duration) {
+        let value = duration.as_secs_f64();
+        self.update(labels, |sample| match sample {
+            Sample::Summary { sum, count } => {
+                *sum += value;
+                *count += 1;
+            }
+            Sample::Value(_) => {
+                *sample = Sample::Summary {
+                    sum: value,
+                    count: 1,
+                }
+            }
+        })
+    }
+
+    /// Return the current value of a counter or a gauge
+    pub fn get(&self, labels: &[(&str, &str)]) -> Option<f64> {
+        match lock(&self.samples).get(&to_labels(labels)) {
+            Some(Sample::Value(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    fn update(&'static self, labels: &[(&str, &str)], update: impl FnOnce(&mut Sample)) {
+        if !is_enabled() {
+            return;
+        }
+        self.registered
+            .call_once(|| lock(&REGISTRY).families.push(self));
+        let mut samples = lock(&self.samples);
+
+        // The families have few samples: a linear search avoids allocating the labels on each update
+        if let Some(sample) = samples.iter_mut().find_map(|(sample_labels, sample)| {
+            same_labels(sample_labels, labels).then_some(sample)
+        }) {
+            update(sample);
+            return;
+        }
+        let initial = match self.kind {
+            MetricKind::Summary => Sample::Summary { sum: 0.0, count: 0 },
+            MetricKind::Counter | MetricKind::Gauge => Sample::Value(0.0),
+        };
+        update(samples.entry(to_labels(labels)).or_insert(initial));
+    }
+
+    fn encode(&self, output: &mut String) {
+        let samples = lock(&self.samples);
+        let kind = match self.kind {
+            MetricKind::Counter => "counter",
+            MetricKind::Gauge => "gauge",
+            MetricKind::Summary => "summary",
+        };
+        let name = self.name;
+        let _ = writeln!(output, "# TYPE {name} {kind}");
+        let _ = writeln!(output, "# HELP {name} {}", self.help);
+        for (labels, sample) in samples.iter() {
+            let labels = encode_labels(labels);
+            match sample {
+                Sample::Value(value) if self.kind == MetricKind::Counter => {
+                    let _ = writeln!(output, "{name}_total{labels} {value}");
+                }
+                Sample::Value(value) => {
+                    let _ = writeln!(output, "{name}{labels} {value}");
+                }
+                Sample::Summary { sum, count } => {
+                    let _ = writeln!(output, "{name}_sum{labels} {sum}");
+                    let _ = writeln!(output, "{name}_count{labels} {count}");
+                }
+            }
+        }
+    }
+}
+
+/// Register a callback run each time the metrics are exported
+///
+/// This is used to sample values which are not updated as events occur, as the memory in use.
+pub fn on_collect(collector: impl Fn() + Send + Sync + 'static) {
+    lock(&REGISTRY).collectors.push(Arc::new(collector));
+}
+
+/// Export all the metrics updated so far in the OpenMetrics text format
+pub fn encode() -> String {
+    // The collectors are run without holding the registry lock,
+    // as they might update families not registered yet
+    let collectors = lock(&REGISTRY).collectors.clone();
+    for collector in collectors {
+        collector();
+    }
+
+    let mut families = lock(&REGISTRY).families.clone();
+    let mut output = String::new();
+    families.sort_by_key(|family| family.name);
+    for family in families {
+        family.encode(&mut output);
+    }
+    output.push_str("# EOF\n");
+    output
+}
+
+/// The family of a topic, used to label MQTT metrics without creating a metric per topic
+///
+/// - For the thin-edge topics, `te/<entity>/<channel>/...`, this is the channel category (`m`, `e`, `cmd`, ...).
+/// - For other topics, this is the first segment of the topic (`c8y`, `az`, ...).
+pub fn topic_family(topic: &str) -> &str {
+    let mut segments = topic.split('/');
+    match segments.next() {
+        Some("te") => segments.nth(4).unwrap_or("te"),
+        Some(root) => root,
+        None => "",
+    }
+}
+
+fn to_labels(labels: &[(&str, &str)]) -> Labels {
+    labels
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+fn same_labels(labels: &Labels, expected: &[(&str, &str)]) -> bool {
+    labels.len() == expected.len()
+        && labels
+            .iter()
+            .zip(expected)
+            .all(|((key, value), (expected_key, expected_value))| {
+                key == expected_key && value == expected_value
+            })
+}
+
+fn encode_labels(labels: &Labels) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|(key, value)| {
+            let value = value
+                .replace('\\', r"\\")
+                .replace('"', r#"\""#)
+                .replace('\n', r"\n");
+            format!(r#"{key}="{value}""#)
+        })
+        .collect();
+    format!("{{{}}}", labels.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static TEST_COUNTER: Family = Family::counter("test_messages", "Test messages");
+    static TEST_GAUGE: Family = Family::gauge("test_connected", "Test connection state");
+    static TEST_SUMMARY: Family = Family::summary("test_duration_seconds", "Test durations");
+
+    #[test]
+    fn encode_counters_gauges_and_summaries() {
+        enable();
+        TEST_COUNTER.inc(&[("topic", "m")]);
+        TEST_COUNTER.inc(&[("topic", "m")]);
+        TEST_COUNTER.inc(&[("topic", "e")]);
+        TEST_GAUGE.set(&[("bridge", "c8y")], 1.0);
+        TEST_SUMMARY.observe(&[], Duration::from_millis(500));
+        TEST_SUMMARY.observe(&[], Duration::from_millis(1500));
+
+        let exported = encode();
+        for expected in [
+            "# TYPE test_messages counter",
+            "# HELP test_messages Test messages",
+            r#"test_messages_total{topic="e"} 1"#,
+            r#"test_messages_total{topic="m"} 2"#,
+            "# TYPE test_connected gauge",
+            r#"test_connected{bridge="c8y"} 1"#,
+            "# TYPE test_duration_seconds summary",
+            "test_duration_seconds_sum 2",
+            "test_duration_seconds_count 2",
+        ] {
+            assert!(
+                exported.lines().any(|line| line == expected),
+                "missing {expected:?} in:\n{exported}"
+            );
+        }
+        assert!(exported.ends_with("# EOF\n"));
+    }
+
+    #[test]
+    fn label_values_are_escaped() {
+        assert_eq!(
+            encode_labels(&to_labels(&[("error", "a \"quoted\"\nvalue")])),
+            r#"{error="a \"quoted\"\nvalue"}"#
+        );
+    }
+
+    #[test]
+    fn topic_families() {
+        assert_eq!(topic_family("te/device/main///m/temperature"), "m");
+        assert_eq!(
+            topic_family("te/device/child/service/foo/status/health"),
+            "status"
+        );
+        assert_eq!(topic_family("te/device/main///cmd/restart/123"), "cmd");
+        assert_eq!(topic_family("te/device/main//"), "te");
+        assert_eq!(topic_family("c8y/s/us"), "c8y");
+    }
+}
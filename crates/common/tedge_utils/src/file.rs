@@ -314,10 +314,6 @@ impl PermissionEntry {
         match options.create_new(true).write(true).open(file).await {
             Ok(mut f) => {
                 self.clone().apply(file).await?;
-                f.sync_all().await.map_err(|from| FileError::FailedToSync {
-                    file: file.to_path_buf(),
-                    from,
-                })?;
                 if let Some(default_content) = default_content {
                     f.write_all(default_content.as_bytes())
                         .map_err(|e| FileError::WriteContentFailed {
@@ -326,6 +322,11 @@ impl PermissionEntry {
                         })
                         .await?;
                 }
+                // Also waits for the content written in the background by tokio
+                f.sync_all().await.map_err(|from| FileError::FailedToSync {
+                    file: file.to_path_buf(),
+                    from,
+                })?;
                 Ok(())
             }
 
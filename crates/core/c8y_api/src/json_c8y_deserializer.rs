@@ -40,6 +40,8 @@ pub enum C8yDeviceControlOperation {
     DownloadConfigFile(C8yDownloadConfigFile),
     Firmware(C8yFirmware),
     DeviceProfile(C8yDeviceProfile),
+    Command(C8yCommand),
+    AcknowledgeAlarm(C8yAcknowledgeAlarm),
     Custom,
 }
 
@@ -71,6 +73,12 @@ impl C8yDeviceControlOperation {
             C8yDeviceControlOperation::DeviceProfile(C8yDeviceProfile::from_json_value(
                 value.clone(),
             )?)
+        } else if let Some(value) = hashmap.get("c8y_Command") {
+            C8yDeviceControlOperation::Command(C8yCommand::from_json_value(value.clone())?)
+        } else if let Some(value) = hashmap.get("c8y_AcknowledgeAlarm") {
+            C8yDeviceControlOperation::AcknowledgeAlarm(C8yAcknowledgeAlarm::from_json_value(
+                value.clone(),
+            )?)
         } else {
             C8yDeviceControlOperation::Custom
         };
@@ -544,6 +552,41 @@ pub struct C8yDeviceProfile {
     pub configuration: Vec<C8yDownloadConfigFile>,
 }
 
+/// Representation of c8y_Command JSON object
+///
+/// ```rust
+/// use c8y_api::json_c8y_deserializer::C8yCommand;
+///
+/// // Example input from c8y
+/// let data = r#"{"text": "df -h"}"#;
+///
+/// // Parse the data
+/// let req: C8yCommand = serde_json::from_str(data).unwrap();
+/// assert_eq!(req.text, "df -h");
+/// ```
+#[derive(Debug, Deserialize, Eq, PartialEq)]
+pub struct C8yCommand {
+    pub text: String,
+}
+
+/// Representation of c8y_AcknowledgeAlarm JSON object
+///
+/// ```rust
+/// use c8y_api::json_c8y_deserializer::C8yAcknowledgeAlarm;
+///
+/// // Example input from c8y
+/// let data = r#"{"type": "temperature_high"}"#;
+///
+/// // Parse the data
+/// let req: C8yAcknowledgeAlarm = serde_json::from_str(data).unwrap();
+/// assert_eq!(req.alarm_type, "temperature_high");
+/// ```
+#[derive(Debug, Deserialize, Eq, PartialEq)]
+pub struct C8yAcknowledgeAlarm {
+    #[serde(rename = "type")]
+    pub alarm_type: String,
+}
+
 pub trait C8yDeviceControlOperationHelper {
     fn from_json_value(value: serde_json::Value) -> Result<Self, serde_json::Error>
     where
@@ -567,6 +610,10 @@ impl C8yDeviceControlOperationHelper for C8yFirmware {}
 
 impl C8yDeviceControlOperationHelper for C8yDeviceProfile {}
 
+impl C8yDeviceControlOperationHelper for C8yCommand {}
+
+impl C8yDeviceControlOperationHelper for C8yAcknowledgeAlarm {}
+
 #[derive(thiserror::Error, Debug)]
 pub enum C8yJsonOverMqttDeserializerError {
     #[error("Parameter {parameter} is not recognized. {hint}")]
@@ -14,6 +14,9 @@ use super::message_ids::*;
 
 pub type SmartRest = String;
 
+/// The maximum size in bytes of an operation failure reason, longer reasons being truncated
+pub const MAX_FAILURE_REASON_SIZE: usize = 500;
+
 pub fn request_pending_operations() -> SmartrestPayload {
     SmartrestPayload::serialize(GET_PENDING_OPERATIONS)
         .expect("shouldn't put payload over size limit")
@@ -42,13 +45,17 @@ pub fn fail_operation_with_id(op_id: &str, reason: &str) -> SmartrestPayload {
 }
 
 fn fail_operation(template_id: usize, operation: &str, reason: &str) -> SmartrestPayload {
-    // If the failure reason exceeds 500 bytes, truncate it
-    if reason.len() <= 500 {
+    // If the failure reason exceeds the maximum size, truncate it
+    if reason.len() <= MAX_FAILURE_REASON_SIZE {
         SmartrestPayload::serialize((template_id, operation, reason))
             .expect("operation name shouldn't put payload over size limit")
     } else {
-        warn!("Failure reason too long, message truncated to 500 bytes");
-        SmartrestPayload::serialize((template_id, operation, &reason[..500]))
+        warn!("Failure reason too long, message truncated to {MAX_FAILURE_REASON_SIZE} bytes");
+        let mut end = MAX_FAILURE_REASON_SIZE;
+        while !reason.is_char_boundary(end) {
+            end -= 1;
+        }
+        SmartrestPayload::serialize((template_id, operation, &reason[..end]))
             .expect("operation name shouldn't put payload over size limit")
     }
 }
@@ -189,6 +196,7 @@ pub enum CumulocitySupportedOperations {
     C8yDownloadConfigFile,
     C8yFirmware,
     C8yDeviceProfile,
+    C8yCommand,
     C8yCustom(String),
 }
 
@@ -202,6 +210,7 @@ impl CumulocitySupportedOperations {
             CumulocitySupportedOperations::C8yDownloadConfigFile => "c8y_DownloadConfigFile",
             CumulocitySupportedOperations::C8yFirmware => "c8y_Firmware",
             CumulocitySupportedOperations::C8yDeviceProfile => "c8y_DeviceProfile",
+            CumulocitySupportedOperations::C8yCommand => "c8y_Command",
             CumulocitySupportedOperations::C8yCustom(operation) => operation.as_str(),
         }
     }
@@ -567,6 +576,16 @@ mod tests {
         assert_eq!(smartrest.as_str(), "505,1234,");
     }
 
+    #[test]
+    fn serialize_smartrest_set_operation_to_failed_with_long_multibyte_reason() {
+        let reason = "é".repeat(MAX_FAILURE_REASON_SIZE);
+        let smartrest = fail_operation_with_id("1234", &reason);
+        assert_eq!(
+            smartrest.as_str(),
+            format!("505,1234,{}", "é".repeat(MAX_FAILURE_REASON_SIZE / 2))
+        );
+    }
+
     #[test]
     fn from_software_module_to_smartrest_software_module_item() {
         let software_module = SoftwareModule {
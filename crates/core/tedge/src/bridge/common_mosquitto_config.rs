@@ -15,9 +15,11 @@ pub struct ListenerConfig {
     pub bind_interface: Option<String>,
     pub allow_anonymous: bool,
     pub capath: Option<Utf8PathBuf>,
+    pub cafile: Option<Utf8PathBuf>,
     pub certfile: Option<Utf8PathBuf>,
     pub keyfile: Option<Utf8PathBuf>,
     pub require_certificate: bool,
+    pub use_identity_as_username: bool,
 }
 
 impl Default for ListenerConfig {
@@ -28,9 +30,11 @@ impl Default for ListenerConfig {
             bind_interface: None,
             allow_anonymous: false,
             capath: None,
+            cafile: None,
             certfile: None,
             keyfile: None,
             require_certificate: true,
+            use_identity_as_username: false,
         }
     }
 }
@@ -76,10 +80,17 @@ impl ListenerConfig {
                     .await?;
                 self.maybe_writeln(writer, "capath", self.capath.as_ref())
                     .await?;
+                self.maybe_writeln(writer, "cafile", self.cafile.as_ref())
+                    .await?;
                 self.maybe_writeln(writer, "certfile", self.certfile.as_ref())
                     .await?;
                 self.maybe_writeln(writer, "keyfile", self.keyfile.as_ref())
-                    .await
+                    .await?;
+                if self.use_identity_as_username {
+                    self.writeln(writer, "use_identity_as_username", true)
+                        .await?;
+                }
+                Ok(())
             }
         }
     }
@@ -153,6 +164,31 @@ impl CommonMosquittoConfig {
         }
     }
 
+    /// Require the local clients to authenticate with a certificate issued by the local CA
+    ///
+    /// The common name of the client certificate is used as the client username,
+    /// so access control lists can be defined per service.
+    pub fn with_internal_tls(
+        self,
+        cafile: Utf8PathBuf,
+        certfile: Utf8PathBuf,
+        keyfile: Utf8PathBuf,
+    ) -> Self {
+        let internal_listener = ListenerConfig {
+            cafile: Some(cafile),
+            certfile: Some(certfile),
+            keyfile: Some(keyfile),
+            allow_anonymous: false,
+            require_certificate: true,
+            use_identity_as_username: true,
+            ..self.internal_listener
+        };
+        Self {
+            internal_listener,
+            ..self
+        }
+    }
+
     pub fn with_external_opts(
         self,
         port: Option<u16>,
@@ -171,6 +207,7 @@ impl CommonMosquittoConfig {
             keyfile,
             allow_anonymous: true,
             require_certificate: false,
+            ..Default::default()
         };
 
         if external_listener.capath.is_some() {
@@ -185,7 +222,7 @@ impl CommonMosquittoConfig {
     }
 
     pub fn from_tedge_config(config: &TEdgeConfig) -> Self {
-        CommonMosquittoConfig::default()
+        let mosquitto_config = CommonMosquittoConfig::default()
             .with_internal_opts(
                 config.mqtt.bind.port.into(),
                 config.mqtt.bind.address.to_string(),
@@ -204,7 +241,18 @@ impl CommonMosquittoConfig {
                 config.mqtt.external.ca_path.or_none().cloned(),
                 config.mqtt.external.cert_file.or_none().cloned(),
                 config.mqtt.external.key_file.or_none().cloned(),
+            );
+
+        if config.mqtt.local_ca.enable {
+            let local_ca = config.local_ca();
+            mosquitto_config.with_internal_tls(
+                local_ca.ca_cert(),
+                local_ca.broker_cert(),
+                local_ca.broker_key(),
             )
+        } else {
+            mosquitto_config
+        }
     }
 
     /// Write the configuration file in a mosquitto configuration directory relative to the main
@@ -320,4 +368,33 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_serialize_with_internal_tls() -> anyhow::Result<()> {
+        let mosquitto_config = CommonMosquittoConfig::default().with_internal_tls(
+            "/etc/tedge/device-certs/local/ca.crt".into(),
+            "/etc/tedge/device-certs/local/broker.crt".into(),
+            "/etc/tedge/device-certs/local/broker.key".into(),
+        );
+
+        let mut buffer = Vec::new();
+        mosquitto_config
+            .internal_listener
+            .write(&mut buffer)
+            .await?;
+
+        let contents = String::from_utf8(buffer).unwrap();
+        let expected = concat!(
+            "listener 1883 127.0.0.1\n",
+            "allow_anonymous false\n",
+            "require_certificate true\n",
+            "cafile /etc/tedge/device-certs/local/ca.crt\n",
+            "certfile /etc/tedge/device-certs/local/broker.crt\n",
+            "keyfile /etc/tedge/device-certs/local/broker.key\n",
+            "use_identity_as_username true\n",
+        );
+        assert_eq!(contents, expected);
+
+        Ok(())
+    }
 }
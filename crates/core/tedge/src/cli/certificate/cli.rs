@@ -1,5 +1,6 @@
 use super::create::CreateCertCmd;
 use super::create_csr::CreateCsrCmd;
+use super::local_ca::CreateLocalCaCmd;
 use super::remove::RemoveCertCmd;
 use super::renew::RenewCertCmd;
 use super::show::ShowCertCmd;
@@ -7,7 +8,12 @@ use super::upload::*;
 
 use anyhow::anyhow;
 use camino::Utf8PathBuf;
+use certificate::parse_root_certificate::CryptokiConfig;
+use certificate::CertificateError;
+use certificate::NewCertificateConfig;
 use clap::ValueHint;
+use tedge_config::models::EcCurve;
+use tedge_config::models::KeyType;
 use tedge_config::tedge_toml::OptionalConfigError;
 use tedge_config::tedge_toml::ProfileName;
 use tedge_config::TEdgeConfig;
@@ -15,6 +21,7 @@ use tedge_config::TEdgeConfigLocation;
 
 use crate::cli::common::Cloud;
 use crate::cli::common::CloudArg;
+use crate::cli::output::OutputFormat;
 use crate::command::BuildCommand;
 use crate::command::Command;
 use crate::ConfigError;
@@ -27,6 +34,13 @@ pub enum TEdgeCertCli {
         #[clap(long = "device-id", global = true)]
         id: Option<String>,
 
+        /// The number of days the certificate is valid, overriding `device.cert.validity_days`
+        #[clap(long, global = true)]
+        validity_days: Option<u32>,
+
+        #[command(flatten)]
+        options: NewCertificateOptions,
+
         #[clap(subcommand)]
         cloud: Option<CloudArg>,
     },
@@ -37,10 +51,19 @@ pub enum TEdgeCertCli {
         #[clap(long = "device-id", global = true)]
         id: Option<String>,
 
+        #[command(flatten)]
+        options: NewCertificateOptions,
+
         /// Path where a Certificate signing request will be stored
         #[clap(long = "output-path", global = true, value_hint = ValueHint::FilePath)]
         output_path: Option<Utf8PathBuf>,
 
+        /// Generate the private key on the PKCS#11 token configured with `device.cryptoki`
+        ///
+        /// The key never leaves the token, which signs the certificate signing request.
+        #[clap(long, global = true)]
+        cryptoki: bool,
+
         #[clap(subcommand)]
         cloud: Option<CloudArg>,
     },
@@ -55,6 +78,9 @@ pub enum TEdgeCertCli {
     Show {
         #[clap(subcommand)]
         cloud: Option<CloudArg>,
+
+        #[clap(from_global)]
+        output: OutputFormat,
     },
 
     /// Remove the device certificate
@@ -66,6 +92,20 @@ pub enum TEdgeCertCli {
     /// Upload root certificate
     #[clap(subcommand)]
     Upload(UploadCertCli),
+
+    /// Create the local CA, along with the certificates of the local MQTT broker and services
+    ///
+    /// The files are stored in `mqtt.local_ca.dir`. The CA is created only once,
+    /// and the broker and client certificates only if missing, unless `--force` is given.
+    CreateLocalCa {
+        /// Issue a client certificate to this service, in addition to the thin-edge services
+        #[clap(long = "service")]
+        services: Vec<String>,
+
+        /// Re-issue the broker and client certificates, even if they already exist
+        #[clap(long)]
+        force: bool,
+    },
 }
 
 impl BuildCommand for TEdgeCertCli {
@@ -81,8 +121,17 @@ impl BuildCommand for TEdgeCertCli {
         };
 
         let cmd = match self {
-            TEdgeCertCli::Create { id, cloud } => {
+            TEdgeCertCli::Create {
+                id,
+                validity_days,
+                options,
+                cloud,
+            } => {
                 let cloud: Option<Cloud> = cloud.map(<_>::try_into).transpose()?;
+                let mut csr_template = options.csr_template(&config)?;
+                if let Some(validity_days) = validity_days {
+                    csr_template.validity_period_days = validity_days;
+                }
 
                 let cmd = CreateCertCmd {
                     id: get_device_id(id, &config, &cloud)?,
@@ -90,16 +139,24 @@ impl BuildCommand for TEdgeCertCli {
                     key_path: config.device_key_path(cloud.as_ref())?.to_owned(),
                     user: user.to_owned(),
                     group: group.to_owned(),
+                    csr_template,
                 };
                 cmd.into_boxed()
             }
 
             TEdgeCertCli::CreateCsr {
                 id,
+                options,
                 output_path,
+                cryptoki,
                 cloud,
             } => {
                 let cloud: Option<Cloud> = cloud.map(<_>::try_into).transpose()?;
+                let cryptoki = if cryptoki {
+                    Some(cryptoki_config(&config)?)
+                } else {
+                    None
+                };
 
                 let cmd = CreateCsrCmd {
                     id: get_device_id(id, &config, &cloud)?,
@@ -112,14 +169,17 @@ impl BuildCommand for TEdgeCertCli {
                     },
                     user: user.to_owned(),
                     group: group.to_owned(),
+                    csr_template: options.csr_template(&config)?,
+                    cryptoki,
                 };
                 cmd.into_boxed()
             }
 
-            TEdgeCertCli::Show { cloud } => {
+            TEdgeCertCli::Show { cloud, output } => {
                 let cloud: Option<Cloud> = cloud.map(<_>::try_into).transpose()?;
                 let cmd = ShowCertCmd {
                     cert_path: config.device_cert_path(cloud.as_ref())?.to_owned(),
+                    output,
                 };
                 cmd.into_boxed()
             }
@@ -154,9 +214,21 @@ impl BuildCommand for TEdgeCertCli {
                 let cmd = RenewCertCmd {
                     cert_path: config.device_cert_path(cloud.as_ref())?.to_owned(),
                     key_path: config.device_key_path(cloud.as_ref())?.to_owned(),
+                    csr_template: config.device.cert.new_certificate_config()?,
                 };
                 cmd.into_boxed()
             }
+
+            TEdgeCertCli::CreateLocalCa { services, force } => {
+                let mut cmd = CreateLocalCaCmd::from_tedge_config(&config, "tedge", "tedge")?;
+                for service in services {
+                    if !cmd.services.contains(&service) {
+                        cmd.services.push(service);
+                    }
+                }
+                cmd.force = force;
+                cmd.into_boxed()
+            }
         };
         Ok(cmd)
     }
@@ -194,6 +266,81 @@ pub enum UploadCertCli {
     },
 }
 
+/// Settings of a new certificate, overriding the `device.cert.*` configuration
+#[derive(clap::Args, Debug, Default)]
+pub struct NewCertificateOptions {
+    /// The type of the private key, if a new one is generated
+    #[clap(long, global = true)]
+    key_type: Option<KeyType>,
+
+    /// The elliptic curve of a new ECDSA private key
+    #[clap(long, global = true)]
+    curve: Option<EcCurve>,
+
+    /// The organization name of the certificate subject
+    #[clap(long, global = true)]
+    organization: Option<String>,
+
+    /// The organizational unit name of the certificate subject
+    #[clap(long, global = true)]
+    organizational_unit: Option<String>,
+
+    /// The two-letter country code of the certificate subject
+    #[clap(long, global = true)]
+    country: Option<String>,
+
+    /// The state or province of the certificate subject
+    #[clap(long, global = true)]
+    state: Option<String>,
+
+    /// The locality of the certificate subject
+    #[clap(long, global = true)]
+    locality: Option<String>,
+
+    /// A subject alternative name: `dns:<name>`, `uri:<uri>`, `ip:<address>` or `email:<address>`
+    ///
+    /// Can be repeated. When given, replaces the names set by `device.cert.subject_alt_names`.
+    #[clap(long = "san", global = true)]
+    subject_alt_names: Vec<String>,
+}
+
+impl NewCertificateOptions {
+    /// Build the settings of a new certificate from the config, overridden by the command line
+    fn csr_template(self, config: &TEdgeConfig) -> Result<NewCertificateConfig, CertificateError> {
+        let defaults = &config.device.cert;
+        let mut csr_template = defaults.new_certificate_config()?;
+
+        csr_template.key_algorithm = self
+            .key_type
+            .unwrap_or(defaults.key_type)
+            .key_algorithm(self.curve.unwrap_or(defaults.curve));
+        if let Some(organization) = self.organization {
+            csr_template.organization_name = organization;
+        }
+        if let Some(organizational_unit) = self.organizational_unit {
+            csr_template.organizational_unit_name = organizational_unit;
+        }
+        if let Some(country) = self.country {
+            csr_template.country_name = Some(country);
+        }
+        if let Some(state) = self.state {
+            csr_template.state_or_province_name = Some(state);
+        }
+        if let Some(locality) = self.locality {
+            csr_template.locality_name = Some(locality);
+        }
+        if !self.subject_alt_names.is_empty() {
+            csr_template.subject_alt_names = self
+                .subject_alt_names
+                .iter()
+                .map(|name| name.parse())
+                .collect::<Result<_, _>>()?;
+        }
+
+        Ok(csr_template)
+    }
+}
+
 /// Returns the device ID from the config if no ID is provided by CLI
 fn get_device_id(
     id: Option<String>,
@@ -209,6 +356,19 @@ fn get_device_id(
     }
 }
 
+/// Returns the configuration of the PKCS#11 token used to generate a private key
+#[cfg(feature = "cryptoki")]
+fn cryptoki_config(config: &TEdgeConfig) -> Result<CryptokiConfig, anyhow::Error> {
+    config.device.cryptoki.token_config()
+}
+
+#[cfg(not(feature = "cryptoki"))]
+fn cryptoki_config(_config: &TEdgeConfig) -> Result<CryptokiConfig, anyhow::Error> {
+    Err(anyhow!(
+        "tedge has been built without the cryptoki feature. Rebuild it with `--features cryptoki`."
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
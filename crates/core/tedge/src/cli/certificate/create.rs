@@ -1,5 +1,6 @@
 use super::error::CertError;
 use crate::cli::certificate::show::ShowCertCmd;
+use crate::cli::output::OutputFormat;
 use crate::command::Command;
 use crate::log::MaybeFancy;
 use camino::Utf8PathBuf;
@@ -29,6 +30,9 @@ pub struct CreateCertCmd {
     /// The owner of the private key
     pub user: String,
     pub group: String,
+
+    /// The key algorithm, subject and validity of the new certificate
+    pub csr_template: NewCertificateConfig,
 }
 
 #[async_trait::async_trait]
@@ -38,11 +42,11 @@ impl Command for CreateCertCmd {
     }
 
     async fn execute(&self) -> Result<(), MaybeFancy<anyhow::Error>> {
-        let config = NewCertificateConfig::default();
-        self.create_test_certificate(&config).await?;
+        self.create_test_certificate(&self.csr_template).await?;
         eprintln!("Certificate was successfully created\n");
         let show_cert_cmd = ShowCertCmd {
             cert_path: self.cert_path.clone(),
+            output: OutputFormat::Text,
         };
         show_cert_cmd.show_certificate().await?;
         Ok(())
@@ -214,6 +218,7 @@ mod tests {
             key_path: key_path.clone(),
             user: "mosquitto".to_string(),
             group: "mosquitto".to_string(),
+            csr_template: NewCertificateConfig::default(),
         };
 
         assert_matches!(
@@ -244,6 +249,7 @@ mod tests {
             key_path: key_path.clone(),
             user: "mosquitto".to_string(),
             group: "mosquitto".to_string(),
+            csr_template: NewCertificateConfig::default(),
         };
 
         assert!(cmd
@@ -268,6 +274,7 @@ mod tests {
             key_path,
             user: "mosquitto".to_string(),
             group: "mosquitto".to_string(),
+            csr_template: NewCertificateConfig::default(),
         };
 
         let cert_error = cmd
@@ -289,6 +296,7 @@ mod tests {
             key_path,
             user: "mosquitto".to_string(),
             group: "mosquitto".to_string(),
+            csr_template: NewCertificateConfig::default(),
         };
 
         let cert_error = cmd
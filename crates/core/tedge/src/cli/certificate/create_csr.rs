@@ -5,6 +5,7 @@ use crate::override_public_key;
 use crate::persist_new_private_key;
 use crate::reuse_private_key;
 use camino::Utf8PathBuf;
+use certificate::parse_root_certificate::CryptokiConfig;
 use certificate::KeyCertPair;
 use certificate::KeyKind;
 use certificate::NewCertificateConfig;
@@ -23,6 +24,12 @@ pub struct CreateCsrCmd {
     /// The owner of the private key
     pub user: String,
     pub group: String,
+
+    /// The key algorithm and subject of the certificate signing request
+    pub csr_template: NewCertificateConfig,
+
+    /// The PKCS#11 token where the key is generated, instead of the `key_path` file
+    pub cryptoki: Option<CryptokiConfig>,
 }
 
 #[async_trait::async_trait]
@@ -32,8 +39,8 @@ impl Command for CreateCsrCmd {
     }
 
     async fn execute(&self) -> Result<(), MaybeFancy<anyhow::Error>> {
-        let config = NewCertificateConfig::default();
-        self.create_certificate_signing_request(&config).await?;
+        self.create_certificate_signing_request(&self.csr_template)
+            .await?;
         eprintln!("Certificate Signing Request was successfully created.");
         Ok(())
     }
@@ -48,6 +55,14 @@ impl CreateCsrCmd {
         let csr_path = &self.csr_path;
         let key_path = &self.key_path;
 
+        if let Some(cryptoki_config) = &self.cryptoki {
+            let cert = create_csr_with_cryptoki(config, id, cryptoki_config)?;
+            override_public_key(csr_path, cert.certificate_signing_request_string()?)
+                .await
+                .map_err(|err| err.cert_context(csr_path.clone()))?;
+            return Ok(());
+        }
+
         let previous_key = reuse_private_key(key_path).await.unwrap_or(KeyKind::New);
         let cert = KeyCertPair::new_certificate_sign_request(config, id, &previous_key)?;
 
@@ -68,6 +83,28 @@ impl CreateCsrCmd {
     }
 }
 
+#[cfg(feature = "cryptoki")]
+fn create_csr_with_cryptoki(
+    config: &NewCertificateConfig,
+    id: &str,
+    cryptoki_config: &CryptokiConfig,
+) -> Result<KeyCertPair, CertError> {
+    Ok(KeyCertPair::new_certificate_sign_request_cryptoki(
+        config,
+        id,
+        cryptoki_config,
+    )?)
+}
+
+#[cfg(not(feature = "cryptoki"))]
+fn create_csr_with_cryptoki(
+    _config: &NewCertificateConfig,
+    _id: &str,
+    _cryptoki_config: &CryptokiConfig,
+) -> Result<KeyCertPair, CertError> {
+    Err(anyhow::anyhow!("tedge has been built without the cryptoki feature").into())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -89,6 +126,8 @@ mod tests {
             csr_path: csr_path.clone(),
             user: "mosquitto".to_string(),
             group: "mosquitto".to_string(),
+            csr_template: NewCertificateConfig::default(),
+            cryptoki: None,
         };
 
         assert_matches!(
@@ -115,6 +154,7 @@ mod tests {
             key_path: key_path.clone(),
             user: "mosquitto".to_string(),
             group: "mosquitto".to_string(),
+            csr_template: NewCertificateConfig::default(),
         };
 
         // create private key and public cert with standard command
@@ -135,6 +175,8 @@ mod tests {
             csr_path: csr_path.clone(),
             user: "mosquitto".to_string(),
             group: "mosquitto".to_string(),
+            csr_template: NewCertificateConfig::default(),
+            cryptoki: None,
         };
 
         // create csr using existing private key and device_id from public cert
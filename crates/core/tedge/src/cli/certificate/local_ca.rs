@@ -0,0 +1,321 @@
+use super::error::CertError;
+use crate::command::Command;
+use crate::log::MaybeFancy;
+use camino::Utf8Path;
+use certificate::local_ca::CertificateUsage;
+use certificate::local_ca::LocalCa;
+use certificate::CertificateError;
+use certificate::NewCertificateConfig;
+use certificate::SubjectAltName;
+use tedge_config::tedge_toml::LocalCaLayout;
+use tedge_config::tedge_toml::LOCAL_CA_DEFAULT_CLIENT;
+use tedge_config::TEdgeConfig;
+use tedge_utils::file::PermissionEntry;
+use tedge_utils::paths::DraftFile;
+use tokio::io::AsyncWriteExt;
+
+/// The common name of the local CA certificate
+const LOCAL_CA_NAME: &str = "tedge-local-ca";
+
+/// The common name of the local MQTT broker certificate
+const LOCAL_BROKER_NAME: &str = "localhost";
+
+/// The number of days the local CA certificate is valid
+const LOCAL_CA_VALIDITY_DAYS: u32 = 3650;
+
+/// The services which are issued a client certificate by default
+pub const LOCAL_CA_SERVICES: &[&str] = &[
+    LOCAL_CA_DEFAULT_CLIENT,
+    "tedge-agent",
+    "tedge-watchdog",
+    "tedge-mapper-c8y",
+    "tedge-mapper-az",
+    "tedge-mapper-aws",
+    "tedge-mapper-collectd",
+    "tedge-mapper-influx",
+    "tedge-mapper-modbus",
+    "tedge-mapper-opcua",
+    "tedge-mapper-gateway",
+    "tedge-mapper-bridge-c8y",
+    "tedge-mapper-bridge-az",
+    "tedge-mapper-bridge-aws",
+    "c8y-firmware-plugin",
+];
+
+/// Create the local CA, along with the certificates of the local MQTT broker and services
+///
+/// Existing certificates are left untouched, unless `force` is set.
+pub struct CreateLocalCaCmd {
+    /// Where the CA and the certificates are stored
+    pub layout: LocalCaLayout,
+
+    /// The services to be issued a client certificate
+    pub services: Vec<String>,
+
+    /// The names the local MQTT broker is reached with
+    pub broker_names: Vec<SubjectAltName>,
+
+    /// The number of days the broker and client certificates are valid
+    pub validity_days: u32,
+
+    /// The owner of the broker certificate and private key
+    pub broker_user: String,
+    pub broker_group: String,
+
+    /// The owner of the client certificates and private keys
+    pub user: String,
+    pub group: String,
+
+    /// Re-issue the broker and client certificates, even if they already exist
+    pub force: bool,
+}
+
+#[async_trait::async_trait]
+impl Command for CreateLocalCaCmd {
+    fn description(&self) -> String {
+        format!(
+            "create the local CA and certificates in {}",
+            self.layout.dir()
+        )
+    }
+
+    async fn execute(&self) -> Result<(), MaybeFancy<anyhow::Error>> {
+        self.create_local_certificates().await?;
+        eprintln!(
+            "Local CA and certificates were successfully created in {}",
+            self.layout.dir()
+        );
+        Ok(())
+    }
+}
+
+impl CreateLocalCaCmd {
+    pub fn from_tedge_config(
+        config: &TEdgeConfig,
+        user: &str,
+        group: &str,
+    ) -> Result<Self, CertificateError> {
+        let mut broker_names = vec![];
+        for name in [
+            LOCAL_BROKER_NAME,
+            "127.0.0.1",
+            config.mqtt.client.host.as_str(),
+            &config.mqtt.bind.address.to_string(),
+        ] {
+            let name: SubjectAltName = name.parse()?;
+            if !broker_names.contains(&name) {
+                broker_names.push(name);
+            }
+        }
+
+        Ok(CreateLocalCaCmd {
+            layout: config.local_ca(),
+            services: LOCAL_CA_SERVICES.iter().map(|s| s.to_string()).collect(),
+            broker_names,
+            validity_days: config.mqtt.local_ca.validity_days,
+            broker_user: crate::BROKER_USER.to_string(),
+            broker_group: crate::BROKER_GROUP.to_string(),
+            user: user.to_string(),
+            group: group.to_string(),
+            force: false,
+        })
+    }
+
+    pub async fn create_local_certificates(&self) -> Result<(), CertError> {
+        let layout = &self.layout;
+        let public = PermissionEntry::new(None, None, Some(0o755));
+        tedge_utils::file::create_directory(layout.dir(), &public).await?;
+        tedge_utils::file::create_directory(layout.clients_dir(), &public).await?;
+
+        let ca = self.load_or_create_ca().await?;
+        let config = NewCertificateConfig {
+            validity_period_days: self.validity_days,
+            organizational_unit_name: "Local Service".into(),
+            ..NewCertificateConfig::default()
+        };
+
+        if self.force || !layout.broker_cert().exists() {
+            let broker_config = NewCertificateConfig {
+                subject_alt_names: self.broker_names.clone(),
+                ..config.clone()
+            };
+            let broker =
+                ca.issue_certificate(&broker_config, LOCAL_BROKER_NAME, CertificateUsage::Server)?;
+            let (user, group) = (&self.broker_user, &self.broker_group);
+            write_file(
+                &layout.broker_key(),
+                &broker.private_key_pem,
+                0o600,
+                user,
+                group,
+            )
+            .await?;
+            write_file(
+                &layout.broker_cert(),
+                &broker.certificate_pem,
+                0o644,
+                user,
+                group,
+            )
+            .await?;
+        }
+
+        for service in &self.services {
+            if !self.force && layout.client_cert(service).exists() {
+                continue;
+            }
+            let client = ca.issue_certificate(&config, service, CertificateUsage::Client)?;
+            let (user, group) = (&self.user, &self.group);
+            write_file(
+                &layout.client_key(service),
+                &client.private_key_pem,
+                0o600,
+                user,
+                group,
+            )
+            .await?;
+            write_file(
+                &layout.client_cert(service),
+                &client.certificate_pem,
+                0o644,
+                user,
+                group,
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// The CA is created once and never re-issued, not even when `force` is set,
+    /// as this would invalidate all the certificates issued by the previous one.
+    async fn load_or_create_ca(&self) -> Result<LocalCa, CertError> {
+        let (cert_path, key_path) = (self.layout.ca_cert(), self.layout.ca_key());
+        if cert_path.exists() {
+            let certificate_pem = tokio::fs::read_to_string(&cert_path).await?;
+            let private_key_pem = tokio::fs::read_to_string(&key_path)
+                .await
+                .map_err(|err| CertError::from(err).key_context(key_path))?;
+            return Ok(LocalCa::from_pem(&certificate_pem, &private_key_pem)?);
+        }
+
+        let config = NewCertificateConfig {
+            validity_period_days: LOCAL_CA_VALIDITY_DAYS,
+            organizational_unit_name: "Local CA".into(),
+            ..NewCertificateConfig::default()
+        };
+        let ca = LocalCa::new(&config, LOCAL_CA_NAME)?;
+
+        // The CA private key is only readable by the user running this command, i.e. root
+        write_draft(&key_path, ca.private_key_pem_string().as_str(), 0o600).await?;
+        write_draft(&cert_path, ca.certificate_pem_string(), 0o644).await?;
+
+        Ok(ca)
+    }
+}
+
+async fn write_file(
+    path: &Utf8Path,
+    content: &str,
+    mode: u32,
+    user: &str,
+    group: &str,
+) -> Result<(), CertError> {
+    write_draft(path, content, mode).await?;
+
+    // Ignore errors, as for `tedge cert create`, so the command can be used by a non-root user
+    let _ = tedge_utils::file::change_user_and_group(
+        path.as_std_path().to_path_buf(),
+        user.to_string(),
+        group.to_string(),
+    )
+    .await;
+    Ok(())
+}
+
+async fn write_draft(path: &Utf8Path, content: &str, mode: u32) -> Result<(), CertError> {
+    let mut file = DraftFile::new(path).await?.with_mode(mode);
+    file.write_all(content.as_bytes()).await?;
+    file.persist().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use certificate::PemCertificate;
+    use tempfile::TempDir;
+
+    fn test_cmd(dir: &TempDir) -> CreateLocalCaCmd {
+        // Changing the owner is expected to fail when testing, and the failure is ignored
+        let user = "tedge".to_string();
+        CreateLocalCaCmd {
+            layout: LocalCaLayout::new(Utf8Path::from_path(dir.path()).unwrap().join("local")),
+            services: vec!["tedge".to_string(), "tedge-agent".to_string()],
+            broker_names: vec!["localhost".parse().unwrap(), "127.0.0.1".parse().unwrap()],
+            validity_days: 365,
+            broker_user: user.clone(),
+            broker_group: user.clone(),
+            user: user.clone(),
+            group: user,
+            force: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn create_the_local_ca_and_certificates() {
+        let dir = tempfile::tempdir().unwrap();
+        let cmd = test_cmd(&dir);
+        cmd.create_local_certificates().await.unwrap();
+
+        let layout = &cmd.layout;
+        let ca = PemCertificate::from_pem_file(layout.ca_cert()).unwrap();
+        assert_eq!(ca.subject_common_name().unwrap(), LOCAL_CA_NAME);
+
+        let broker = PemCertificate::from_pem_file(layout.broker_cert()).unwrap();
+        assert_eq!(broker.subject_common_name().unwrap(), "localhost");
+        assert_eq!(broker.issuer().unwrap(), ca.subject().unwrap());
+
+        for service in ["tedge", "tedge-agent"] {
+            let client = PemCertificate::from_pem_file(layout.client_cert(service)).unwrap();
+            assert_eq!(client.subject_common_name().unwrap(), service);
+            assert_eq!(client.issuer().unwrap(), ca.subject().unwrap());
+            assert!(layout.client_key(service).exists());
+        }
+
+        use std::os::unix::fs::PermissionsExt;
+        let key_mode = std::fs::metadata(layout.ca_key())
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(key_mode & 0o777, 0o600);
+    }
+
+    #[tokio::test]
+    async fn existing_certificates_are_kept_unless_forced() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cmd = test_cmd(&dir);
+        cmd.create_local_certificates().await.unwrap();
+
+        let layout = cmd.layout.clone();
+        let ca_cert = std::fs::read_to_string(layout.ca_cert()).unwrap();
+        let agent_cert = std::fs::read_to_string(layout.client_cert("tedge-agent")).unwrap();
+
+        cmd.create_local_certificates().await.unwrap();
+        assert_eq!(
+            std::fs::read_to_string(layout.client_cert("tedge-agent")).unwrap(),
+            agent_cert
+        );
+
+        cmd.force = true;
+        cmd.create_local_certificates().await.unwrap();
+        let reissued_cert = std::fs::read_to_string(layout.client_cert("tedge-agent")).unwrap();
+        assert_ne!(reissued_cert, agent_cert);
+
+        // The CA is never re-created, so the re-issued certificates are signed by the same CA
+        assert_eq!(std::fs::read_to_string(layout.ca_cert()).unwrap(), ca_cert);
+        let reissued = PemCertificate::from_pem_string(&reissued_cert).unwrap();
+        let ca = PemCertificate::from_pem_string(&ca_cert).unwrap();
+        assert_eq!(reissued.issuer().unwrap(), ca.subject().unwrap());
+    }
+}
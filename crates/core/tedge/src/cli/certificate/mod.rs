@@ -4,6 +4,7 @@ mod cli;
 mod create;
 mod create_csr;
 mod error;
+mod local_ca;
 mod remove;
 mod renew;
 mod show;
@@ -12,6 +13,7 @@ mod upload;
 pub use self::cli::*;
 pub use self::create::*;
 pub use self::error::*;
+pub use self::local_ca::CreateLocalCaCmd;
 
 #[cfg(test)]
 mod test_helpers {
@@ -15,6 +15,9 @@ pub struct RenewCertCmd {
 
     /// The path of the private key to re-use
     pub key_path: Utf8PathBuf,
+
+    /// The subject and validity of the renewed certificate
+    pub csr_template: NewCertificateConfig,
 }
 
 #[async_trait::async_trait]
@@ -24,8 +27,7 @@ impl Command for RenewCertCmd {
     }
 
     async fn execute(&self) -> Result<(), MaybeFancy<anyhow::Error>> {
-        let config = NewCertificateConfig::default();
-        self.renew_test_certificate(&config).await?;
+        self.renew_test_certificate(&self.csr_template).await?;
         eprintln!("Certificate was successfully renewed, for un-interrupted service, the certificate has to be uploaded to the cloud");
         Ok(())
     }
@@ -77,6 +79,7 @@ mod tests {
             key_path: key_path.clone(),
             user: "mosquitto".to_string(),
             group: "mosquitto".to_string(),
+            csr_template: NewCertificateConfig::default(),
         };
 
         // First create both cert and key
@@ -96,6 +99,7 @@ mod tests {
         let cmd = RenewCertCmd {
             cert_path: cert_path.clone(),
             key_path: key_path.clone(),
+            csr_template: NewCertificateConfig::default(),
         };
         cmd.renew_test_certificate(&NewCertificateConfig::default())
             .await
@@ -131,6 +135,7 @@ mod tests {
         let cmd = RenewCertCmd {
             cert_path,
             key_path,
+            csr_template: NewCertificateConfig::default(),
         };
 
         let cert_error = cmd
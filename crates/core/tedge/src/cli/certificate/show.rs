@@ -1,8 +1,11 @@
+use crate::cli::output::print_json;
+use crate::cli::output::OutputFormat;
 use crate::command::Command;
 use crate::log::MaybeFancy;
 use anyhow::Context;
 use camino::Utf8PathBuf;
 use certificate::PemCertificate;
+use serde::Serialize;
 use tokio::io::AsyncWriteExt;
 
 macro_rules! print_async {
@@ -14,10 +17,22 @@ macro_rules! print_async {
     );
 }
 
+/// The fields of the device certificate, as printed by `tedge cert show --output json`
+#[derive(Serialize, Debug)]
+pub struct CertificateFields {
+    pub path: String,
+    pub subject: String,
+    pub issuer: String,
+    pub not_before: String,
+    pub not_after: String,
+    pub thumbprint: String,
+}
+
 /// Show the device certificate, if any
 pub struct ShowCertCmd {
     /// The path where the device certificate will be stored
     pub cert_path: Utf8PathBuf,
+    pub output: OutputFormat,
 }
 
 #[async_trait::async_trait]
@@ -40,14 +55,26 @@ impl ShowCertCmd {
             .with_context(|| format!("reading certificate from {cert_path}"))?;
         let pem = PemCertificate::from_pem_string(&cert)
             .with_context(|| format!("decoding certificate from {cert_path}"))?;
+        let fields = CertificateFields {
+            path: cert_path.to_string(),
+            subject: pem.subject()?,
+            issuer: pem.issuer()?,
+            not_before: pem.not_before()?,
+            not_after: pem.not_after()?,
+            thumbprint: pem.thumbprint()?,
+        };
+
+        if self.output == OutputFormat::Json {
+            return print_json(&fields);
+        }
 
         let mut stdout = tokio::io::stdout();
-        print_async!(stdout, "Device certificate: {}\n", self.cert_path);
-        print_async!(stdout, "Subject: {}\n", pem.subject()?);
-        print_async!(stdout, "Issuer: {}\n", pem.issuer()?);
-        print_async!(stdout, "Valid from: {}\n", pem.not_before()?);
-        print_async!(stdout, "Valid up to: {}\n", pem.not_after()?);
-        print_async!(stdout, "Thumbprint: {}\n", pem.thumbprint()?);
+        print_async!(stdout, "Device certificate: {}\n", fields.path);
+        print_async!(stdout, "Subject: {}\n", fields.subject);
+        print_async!(stdout, "Issuer: {}\n", fields.issuer);
+        print_async!(stdout, "Valid from: {}\n", fields.not_before);
+        print_async!(stdout, "Valid up to: {}\n", fields.not_after);
+        print_async!(stdout, "Thumbprint: {}\n", fields.thumbprint);
         let _ = stdout.flush().await;
         Ok(())
     }
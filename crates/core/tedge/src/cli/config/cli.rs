@@ -1,7 +1,9 @@
 use crate::cli::common::profile_completions;
 use crate::cli::config::commands::*;
+use crate::cli::output::OutputFormat;
 use crate::command::*;
 use crate::ConfigError;
+use camino::Utf8PathBuf;
 use clap_complete::ArgValueCandidates;
 use tedge_config::tedge_toml::ProfileName;
 use tedge_config::tedge_toml::ReadableKey;
@@ -25,6 +27,14 @@ pub enum ConfigCmd {
         #[clap(long)]
         #[arg(add = ArgValueCandidates::new(profile_completions))]
         profile: Option<ProfileName>,
+
+        /// Print the origin of the value before the value itself:
+        /// `file:<path>`, `env:<variable>` or `default`
+        #[clap(long)]
+        show_origin: bool,
+
+        #[clap(from_global)]
+        output: OutputFormat,
     },
 
     /// Set or update the provided configuration key with the given value
@@ -112,6 +122,32 @@ pub enum ConfigCmd {
 
         /// Prints only the keys that contain the provided filter string
         filter: Option<String>,
+
+        #[clap(from_global)]
+        output: OutputFormat,
+    },
+
+    /// Print the content of tedge.toml, cloud profiles included, with the secrets redacted
+    ///
+    /// The settings of the tedge.toml.d drop-in files are included too,
+    /// but not those provided by environment variables.
+    Export,
+
+    /// Apply the settings of a configuration file, printing the changes before applying them
+    ///
+    /// The file is checked beforehand, all its settings having to be known and writable.
+    /// The redacted values of an exported configuration are left unchanged.
+    Import {
+        /// Path to the configuration file, as created by `tedge config export`
+        path: Utf8PathBuf,
+
+        /// Unset the current settings missing from the file, which are otherwise kept unchanged
+        #[clap(long)]
+        replace: bool,
+
+        /// Only print the changes, without applying them
+        #[clap(long)]
+        dry_run: bool,
     },
 }
 
@@ -140,8 +176,15 @@ impl BuildCommand for ConfigCmd {
         config_location: TEdgeConfigLocation,
     ) -> Result<Box<dyn Command>, ConfigError> {
         match self {
-            ConfigCmd::Get { key, profile } => Ok(GetConfigCommand {
+            ConfigCmd::Get {
+                key,
+                profile,
+                show_origin,
+                output,
+            } => Ok(GetConfigCommand {
                 key: try_with_profile!(key, profile),
+                show_origin,
+                output,
                 config_location,
             }
             .into_boxed()),
@@ -184,13 +227,31 @@ impl BuildCommand for ConfigCmd {
                 is_all,
                 is_doc,
                 filter,
+                output,
             } => Ok(ListConfigCommand {
                 is_all,
                 is_doc,
+                output,
                 config_location,
                 filter,
             }
             .into_boxed()),
+            ConfigCmd::Export => Ok(ExportConfigCommand { config_location }.into_boxed()),
+            ConfigCmd::Import {
+                path,
+                replace,
+                dry_run,
+            } => Ok(ImportConfigCommand {
+                path,
+                mode: if replace {
+                    ImportMode::Replace
+                } else {
+                    ImportMode::Merge
+                },
+                dry_run,
+                config_location,
+            }
+            .into_boxed()),
         }
     }
 }
@@ -0,0 +1,147 @@
+use crate::command::Command;
+use crate::log::MaybeFancy;
+use std::collections::BTreeMap;
+use tedge_config::TEdgeConfigLocation;
+
+/// The value displayed in place of a sensitive setting
+pub(crate) const REDACTED: &str = "<redacted>";
+
+/// The settings which are not exported nor imported, being managed by `tedge` itself
+const UNMANAGED_KEYS: [&str; 1] = ["config.version"];
+
+pub struct ExportConfigCommand {
+    pub config_location: TEdgeConfigLocation,
+}
+
+#[async_trait::async_trait]
+impl Command for ExportConfigCommand {
+    fn description(&self) -> String {
+        "export the configuration".into()
+    }
+
+    async fn execute(&self) -> Result<(), MaybeFancy<anyhow::Error>> {
+        let dto = self
+            .config_location
+            .load_dto_from_toml_and_drop_ins()
+            .await
+            .map_err(anyhow::Error::new)?;
+        let mut toml = toml::Table::try_from(&dto).map_err(anyhow::Error::new)?;
+        redact(&mut toml);
+        print!(
+            "{}",
+            toml::to_string_pretty(&toml).map_err(anyhow::Error::new)?
+        );
+        Ok(())
+    }
+}
+
+/// Tell if the value of a config key has to be redacted
+pub(crate) fn is_sensitive(key: &str) -> bool {
+    let name = key.rsplit('.').next().unwrap_or(key);
+    matches!(name, "pin" | "password" | "secret" | "token")
+}
+
+fn redact(table: &mut toml::Table) {
+    for (key, value) in table.iter_mut() {
+        match value {
+            toml::Value::Table(inner) => redact(inner),
+            _ if is_sensitive(key) => *value = toml::Value::String(REDACTED.to_string()),
+            _ => (),
+        }
+    }
+}
+
+/// Flatten a TOML document into `tedge config` keys and values
+///
+/// The values are formatted as for `tedge config set`, i.e. with arrays given as comma-separated lists.
+pub(crate) fn flatten(table: &toml::Table) -> BTreeMap<String, String> {
+    let mut settings = BTreeMap::new();
+    flatten_into(&mut settings, None, table);
+    settings
+}
+
+fn flatten_into(
+    settings: &mut BTreeMap<String, String>,
+    prefix: Option<&str>,
+    table: &toml::Table,
+) {
+    for (key, value) in table {
+        let key = match prefix {
+            Some(prefix) => format!("{prefix}.{key}"),
+            None => key.to_string(),
+        };
+        match value {
+            toml::Value::Table(inner) => flatten_into(settings, Some(&key), inner),
+            _ if UNMANAGED_KEYS.contains(&key.as_str()) => (),
+            value => {
+                settings.insert(key, value_to_string(value));
+            }
+        }
+    }
+}
+
+fn value_to_string(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(value) => value.clone(),
+        toml::Value::Array(values) => values
+            .iter()
+            .map(value_to_string)
+            .collect::<Vec<_>>()
+            .join(","),
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sensitive_values_are_redacted() {
+        let mut toml: toml::Table = r#"
+            device.cryptoki.pin = "123456"
+            [c8y]
+            url = "example.cumulocity.com"
+            "#
+        .parse()
+        .unwrap();
+
+        redact(&mut toml);
+
+        assert_eq!(toml["device"]["cryptoki"]["pin"].as_str(), Some(REDACTED));
+        assert_eq!(toml["c8y"]["url"].as_str(), Some("example.cumulocity.com"));
+    }
+
+    #[test]
+    fn profiles_are_flattened_into_profiled_keys() {
+        let toml: toml::Table = r#"
+            config.version = "2"
+            mqtt.bind.port = 1883
+            [c8y]
+            url = "example.cumulocity.com"
+            smartrest.templates = ["template-1", "template-2"]
+            [c8y.profiles.second]
+            url = "second.cumulocity.com"
+            "#
+        .parse()
+        .unwrap();
+
+        let settings = flatten(&toml);
+
+        assert_eq!(
+            settings.into_iter().collect::<Vec<_>>(),
+            vec![
+                (
+                    "c8y.profiles.second.url".into(),
+                    "second.cumulocity.com".into()
+                ),
+                (
+                    "c8y.smartrest.templates".into(),
+                    "template-1,template-2".into()
+                ),
+                ("c8y.url".into(), "example.cumulocity.com".into()),
+                ("mqtt.bind.port".into(), "1883".into()),
+            ]
+        );
+    }
+}
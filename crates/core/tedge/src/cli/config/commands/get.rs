@@ -1,10 +1,14 @@
 use tedge_config::tedge_toml::ReadableKey;
 
+use crate::cli::output::OutputFormat;
 use crate::command::Command;
 use crate::log::MaybeFancy;
 
 pub struct GetConfigCommand {
     pub key: ReadableKey,
+    /// Also print where the value comes from: a file, an environment variable or the defaults
+    pub show_origin: bool,
+    pub output: OutputFormat,
     pub config_location: tedge_config::TEdgeConfigLocation,
 }
 
@@ -21,9 +25,17 @@ impl Command for GetConfigCommand {
             .await
             .map_err(anyhow::Error::new)?;
         match config.read_string(&self.key) {
+            Ok(value) if self.show_origin => {
+                let origins = self.config_location.config_origins();
+                println!("{}\t{}", origins.origin_of(&self.key.to_cow_str()), value);
+            }
             Ok(value) => {
                 println!("{}", value);
             }
+            // With the JSON output, these errors are reported as any other, with a configuration kind
+            Err(err) if self.output == OutputFormat::Json => {
+                return Err(anyhow::Error::new(err).into())
+            }
             Err(tedge_config::tedge_toml::ReadError::ConfigNotSet { .. }) => {
                 eprintln!("The provided config key: '{}' is not set", self.key);
                 std::process::exit(1)
@@ -0,0 +1,481 @@
+use super::export::flatten;
+use super::export::is_sensitive;
+use super::export::REDACTED;
+use crate::command::Command;
+use crate::log::MaybeFancy;
+use anyhow::Context;
+use camino::Utf8PathBuf;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::path::PathBuf;
+use tedge_config::tedge_toml::ConfigOrigin;
+use tedge_config::tedge_toml::WritableKey;
+use tedge_config::TEdgeConfigDto;
+use tedge_config::TEdgeConfigLocation;
+use yansi::Paint as _;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ImportMode {
+    /// Update the imported settings, keeping the others unchanged
+    Merge,
+    /// Update the imported settings, unsetting the others
+    Replace,
+}
+
+pub struct ImportConfigCommand {
+    pub path: Utf8PathBuf,
+    pub mode: ImportMode,
+    pub dry_run: bool,
+    pub config_location: TEdgeConfigLocation,
+}
+
+/// The current value of a setting, along with the file it is read from:
+/// `tedge.toml` or one of its drop-in files
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Setting {
+    value: String,
+    file: PathBuf,
+}
+
+/// A change to be applied to `tedge.toml`
+///
+/// As `tedge.toml` overrides the drop-in files, a setting of a drop-in file is updated
+/// by setting the new value in `tedge.toml`, but cannot be unset.
+#[derive(Debug, Eq, PartialEq)]
+enum Change {
+    Set {
+        key: String,
+        value: String,
+    },
+    Update {
+        key: String,
+        old: Setting,
+        new: String,
+    },
+    Unset {
+        key: String,
+        old: Setting,
+    },
+    /// A setting to be unset, but which is left unchanged being read from a drop-in file
+    Kept {
+        key: String,
+        old: Setting,
+    },
+}
+
+#[async_trait::async_trait]
+impl Command for ImportConfigCommand {
+    fn description(&self) -> String {
+        format!("import the configuration from {}", self.path)
+    }
+
+    async fn execute(&self) -> Result<(), MaybeFancy<anyhow::Error>> {
+        let content = tokio::fs::read_to_string(&self.path)
+            .await
+            .with_context(|| format!("reading {}", self.path))?;
+        let imported: toml::Table = content
+            .parse()
+            .with_context(|| format!("parsing {}", self.path))?;
+        let imported = flatten(&imported);
+        validate(&imported).with_context(|| format!("invalid configuration in {}", self.path))?;
+
+        // The current settings are compared as exported, i.e. including those of the drop-in files
+        let tedge_toml = self.config_location.tedge_config_file_path().as_std_path();
+        let dto = self
+            .config_location
+            .load_dto_from_toml_and_drop_ins()
+            .await
+            .map_err(anyhow::Error::new)?;
+        let origins = self.config_location.persisted_config_origins();
+        let current = flatten(&toml::Table::try_from(&dto).map_err(anyhow::Error::new)?)
+            .into_iter()
+            .map(|(key, value)| {
+                let file = match origins.origin_of(&key) {
+                    ConfigOrigin::File(file) => file,
+                    _ => tedge_toml.to_path_buf(),
+                };
+                (key, Setting { value, file })
+            })
+            .collect();
+
+        let changes = changes(&current, &imported, self.mode, tedge_toml);
+        if changes.is_empty() {
+            println!("No changes to apply.");
+            return Ok(());
+        }
+        for change in &changes {
+            println!("{change}");
+        }
+        if self.dry_run {
+            return Ok(());
+        }
+
+        let updates = changes
+            .iter()
+            .filter_map(|change| match change {
+                Change::Set { key, value }
+                | Change::Update {
+                    key, new: value, ..
+                } => Some((key, Some(value.as_str()))),
+                Change::Unset { key, .. } => Some((key, None)),
+                Change::Kept { .. } => None,
+            })
+            .map(|(key, value)| {
+                let key = key
+                    .parse::<WritableKey>()
+                    .with_context(|| format!("cannot update {key}"))?;
+                Ok((key, value))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if !updates.is_empty() {
+            self.config_location
+                .update_toml(&|dto, _reader| {
+                    for (key, value) in &updates {
+                        match value {
+                            Some(value) => dto.try_update_str(key, value)?,
+                            None => dto.try_unset_key(key)?,
+                        }
+                    }
+                    Ok(())
+                })
+                .await
+                .map_err(anyhow::Error::new)?;
+        }
+        println!("{} change(s) applied.", updates.len());
+        if updates.len() < changes.len() {
+            eprintln!(
+                "Warning: the settings of the drop-in files can only be unset from these files"
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Check that all the imported keys are known and writable, and that their values are valid
+fn validate(imported: &BTreeMap<String, String>) -> anyhow::Result<()> {
+    let mut scratch = TEdgeConfigDto::default();
+    let errors: Vec<String> = imported
+        .iter()
+        .filter(|(_, value)| value.as_str() != REDACTED)
+        .filter_map(|(key, value)| {
+            let result = key
+                .parse::<WritableKey>()
+                .map_err(|err| err.to_string())
+                .and_then(|key| {
+                    scratch
+                        .try_update_str(&key, value)
+                        .map_err(|err| format!("{err:#}"))
+                });
+            result.err().map(|err| format!("{key}: {err}"))
+        })
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        anyhow::bail!("\n  {}", errors.join("\n  "))
+    }
+}
+
+/// The changes turning the current settings into the imported ones
+///
+/// The redacted values of an exported configuration are left unchanged.
+fn changes(
+    current: &BTreeMap<String, Setting>,
+    imported: &BTreeMap<String, String>,
+    mode: ImportMode,
+    tedge_toml: &Path,
+) -> Vec<Change> {
+    let mut changes = vec![];
+    for (key, value) in imported {
+        if value == REDACTED {
+            continue;
+        }
+        match current.get(key) {
+            None => changes.push(Change::Set {
+                key: key.clone(),
+                value: value.clone(),
+            }),
+            Some(old) if old.value != *value => changes.push(Change::Update {
+                key: key.clone(),
+                old: old.clone(),
+                new: value.clone(),
+            }),
+            Some(_) => (),
+        }
+    }
+    if mode == ImportMode::Replace {
+        for (key, old) in current {
+            if !imported.contains_key(key) {
+                let key = key.clone();
+                let old = old.clone();
+                if old.file == tedge_toml {
+                    changes.push(Change::Unset { key, old })
+                } else {
+                    changes.push(Change::Kept { key, old })
+                }
+            }
+        }
+    }
+    changes
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let display = |key: &str, value: &str| -> String {
+            if is_sensitive(key) {
+                REDACTED.to_string()
+            } else {
+                value.to_string()
+            }
+        };
+        match self {
+            Change::Set { key, value } => {
+                let line = format!("+ {key} = {}", display(key, value));
+                write!(f, "{}", line.green())
+            }
+            Change::Update { key, old, new } => {
+                let line = format!(
+                    "~ {key} = {} -> {} (from {})",
+                    display(key, &old.value),
+                    display(key, new),
+                    old.file.display()
+                );
+                write!(f, "{}", line.yellow())
+            }
+            Change::Unset { key, old } => {
+                let line = format!(
+                    "- {key} = {} (from {})",
+                    display(key, &old.value),
+                    old.file.display()
+                );
+                write!(f, "{}", line.red())
+            }
+            Change::Kept { key, old } => {
+                let line = format!(
+                    "! {key} = {} (kept, as set by {})",
+                    display(key, &old.value),
+                    old.file.display()
+                );
+                write!(f, "{}", line.magenta())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tedge_test_utils::fs::TempTedgeDir;
+
+    fn settings(settings: &[(&str, &str)]) -> BTreeMap<String, String> {
+        settings
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    const TEDGE_TOML: &str = "/etc/tedge/tedge.toml";
+
+    /// Current settings, as read from the given file
+    fn current(file: &str, current: &[(&str, &str)]) -> BTreeMap<String, Setting> {
+        current
+            .iter()
+            .map(|(key, value)| (key.to_string(), setting(file, value)))
+            .collect()
+    }
+
+    fn setting(file: &str, value: &str) -> Setting {
+        Setting {
+            value: value.to_string(),
+            file: PathBuf::from(file),
+        }
+    }
+
+    #[test]
+    fn merge_keeps_the_settings_missing_from_the_imported_file() {
+        let current = current(
+            TEDGE_TOML,
+            &[("c8y.url", "old.cumulocity.com"), ("az.url", "hub.azure")],
+        );
+        let imported = settings(&[
+            ("c8y.url", "new.cumulocity.com"),
+            ("mqtt.bind.port", "2883"),
+        ]);
+
+        assert_eq!(
+            changes(&current, &imported, ImportMode::Merge, TEDGE_TOML.as_ref()),
+            vec![
+                Change::Update {
+                    key: "c8y.url".into(),
+                    old: setting(TEDGE_TOML, "old.cumulocity.com"),
+                    new: "new.cumulocity.com".into()
+                },
+                Change::Set {
+                    key: "mqtt.bind.port".into(),
+                    value: "2883".into()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn replace_unsets_the_settings_missing_from_the_imported_file() {
+        let current = current(
+            TEDGE_TOML,
+            &[
+                ("c8y.url", "example.cumulocity.com"),
+                ("az.url", "hub.azure"),
+            ],
+        );
+        let imported = settings(&[("c8y.url", "example.cumulocity.com")]);
+
+        assert_eq!(
+            changes(
+                &current,
+                &imported,
+                ImportMode::Replace,
+                TEDGE_TOML.as_ref()
+            ),
+            vec![Change::Unset {
+                key: "az.url".into(),
+                old: setting(TEDGE_TOML, "hub.azure")
+            }]
+        );
+    }
+
+    #[test]
+    fn replace_keeps_the_settings_of_drop_in_files() {
+        let drop_in = "/etc/tedge/tedge.toml.d/10-az.toml";
+        let current = current(drop_in, &[("az.url", "hub.azure")]);
+        let imported = settings(&[]);
+
+        assert_eq!(
+            changes(
+                &current,
+                &imported,
+                ImportMode::Replace,
+                TEDGE_TOML.as_ref()
+            ),
+            vec![Change::Kept {
+                key: "az.url".into(),
+                old: setting(drop_in, "hub.azure")
+            }]
+        );
+    }
+
+    #[test]
+    fn changes_tell_from_which_file_the_current_settings_are_read() {
+        let drop_in = "/etc/tedge/tedge.toml.d/10-c8y.toml";
+        let change = Change::Update {
+            key: "c8y.url".into(),
+            old: setting(drop_in, "old.cumulocity.com"),
+            new: "new.cumulocity.com".into(),
+        };
+
+        let line = change.to_string();
+
+        assert!(
+            line.contains("old.cumulocity.com -> new.cumulocity.com"),
+            "{line}"
+        );
+        assert!(line.contains(drop_in), "{line}");
+    }
+
+    #[test]
+    fn redacted_values_are_left_unchanged() {
+        let current = current(TEDGE_TOML, &[("device.cryptoki.pin", "123456")]);
+        let imported = settings(&[("device.cryptoki.pin", REDACTED)]);
+
+        assert!(changes(
+            &current,
+            &imported,
+            ImportMode::Replace,
+            TEDGE_TOML.as_ref()
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn unknown_keys_and_invalid_values_are_rejected() {
+        let imported = settings(&[
+            ("c8y.url", "example.cumulocity.com"),
+            ("mqtt.bind.port", "not a port"),
+            ("unknown.key", "value"),
+        ]);
+
+        let err = validate(&imported).unwrap_err().to_string();
+
+        assert!(err.contains("mqtt.bind.port"), "{err}");
+        assert!(err.contains("unknown.key"), "{err}");
+        assert!(!err.contains("c8y.url"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn imported_settings_are_applied_to_tedge_toml() {
+        let ttd = TempTedgeDir::new();
+        ttd.file("tedge.toml").with_raw_content(
+            "[c8y]\nurl = \"old.cumulocity.com\"\n[az]\nurl = \"hub.azure-devices.net\"\n",
+        );
+        ttd.file("import.toml")
+            .with_raw_content("[c8y]\nurl = \"new.cumulocity.com\"\n[mqtt.bind]\nport = 2883\n");
+        let config_location = TEdgeConfigLocation::from_custom_root(ttd.path());
+
+        ImportConfigCommand {
+            path: ttd.utf8_path().join("import.toml"),
+            mode: ImportMode::Replace,
+            dry_run: false,
+            config_location: config_location.clone(),
+        }
+        .execute()
+        .await
+        .unwrap();
+
+        let tedge_toml: toml::Table = std::fs::read_to_string(ttd.path().join("tedge.toml"))
+            .unwrap()
+            .parse()
+            .unwrap();
+        let settings = flatten(&tedge_toml);
+        assert_eq!(settings.get("c8y.url").unwrap(), "new.cumulocity.com");
+        assert_eq!(settings.get("mqtt.bind.port").unwrap(), "2883");
+        assert_eq!(settings.get("az.url"), None);
+    }
+
+    #[tokio::test]
+    async fn the_settings_of_drop_in_files_are_compared_as_exported() {
+        let ttd = TempTedgeDir::new();
+        ttd.file("tedge.toml")
+            .with_raw_content("[az]\nurl = \"hub.azure-devices.net\"\n");
+        ttd.dir("tedge.toml.d")
+            .file("10-c8y.toml")
+            .with_raw_content(
+                "[c8y]\nurl = \"example.cumulocity.com\"\n[mqtt.bind]\nport = 2883\n",
+            );
+        ttd.file("import.toml").with_raw_content(
+            "[c8y]\nurl = \"example.cumulocity.com\"\n[az]\nurl = \"hub.azure-devices.net\"\n",
+        );
+        let config_location = TEdgeConfigLocation::from_custom_root(ttd.path());
+
+        ImportConfigCommand {
+            path: ttd.utf8_path().join("import.toml"),
+            mode: ImportMode::Replace,
+            dry_run: false,
+            config_location: config_location.clone(),
+        }
+        .execute()
+        .await
+        .unwrap();
+
+        // The settings of the drop-in file are neither copied into tedge.toml nor unset
+        let tedge_toml: toml::Table = std::fs::read_to_string(ttd.path().join("tedge.toml"))
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(
+            flatten(&tedge_toml),
+            settings(&[("az.url", "hub.azure-devices.net")])
+        );
+        let drop_in = std::fs::read_to_string(ttd.path().join("tedge.toml.d/10-c8y.toml")).unwrap();
+        assert!(drop_in.contains("port = 2883"), "{drop_in}");
+    }
+}
@@ -1,8 +1,13 @@
+use crate::cli::output::print_json;
+use crate::cli::output::OutputFormat;
 use crate::command::Command;
 use crate::log::MaybeFancy;
 use pad::PadStr;
+use serde::Serialize;
 use std::io::stdout;
 use std::io::IsTerminal;
+use tedge_config::tedge_toml::ConfigOrigin;
+use tedge_config::tedge_toml::ConfigOrigins;
 use tedge_config::tedge_toml::READABLE_KEYS;
 use tedge_config::TEdgeConfig;
 use tedge_config::TEdgeConfigLocation;
@@ -12,6 +17,7 @@ pub struct ListConfigCommand {
     pub is_all: bool,
     pub is_doc: bool,
     pub filter: Option<String>,
+    pub output: OutputFormat,
     pub config_location: TEdgeConfigLocation,
 }
 
@@ -22,21 +28,91 @@ impl Command for ListConfigCommand {
     }
 
     async fn execute(&self) -> Result<(), MaybeFancy<anyhow::Error>> {
-        if self.is_doc {
-            print_config_doc(self.filter.as_deref());
-        } else {
-            let config = self
-                .config_location
-                .load()
-                .await
-                .map_err(anyhow::Error::new)?;
-            print_config_list(&config, self.is_all, self.filter.as_deref())?;
+        let filter = self.filter.as_deref();
+        match (self.is_doc, self.output) {
+            (true, OutputFormat::Text) => print_config_doc(filter),
+            (true, OutputFormat::Json) => print_json(&config_doc(filter))?,
+            (false, format) => {
+                let config = self
+                    .config_location
+                    .load()
+                    .await
+                    .map_err(anyhow::Error::new)?;
+                match format {
+                    OutputFormat::Text => print_config_list(&config, self.is_all, filter)?,
+                    OutputFormat::Json => {
+                        let origins = self.config_location.config_origins();
+                        print_json(&config_entries(&config, &origins, self.is_all, filter))?
+                    }
+                }
+            }
         }
 
         Ok(())
     }
 }
 
+/// A configuration setting, as listed by `tedge config list --output json`
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct ConfigEntry {
+    pub key: String,
+    /// The value, `null` if not set
+    pub value: Option<String>,
+    /// Where the value is defined, `null` if not set
+    pub source: Option<ConfigSource>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigSource {
+    /// Set in `tedge.toml` or in a drop-in file of `tedge.toml.d`
+    File,
+    /// Set by a `TEDGE_` environment variable, which overrides `tedge.toml`
+    Environment,
+    /// Not set, the value being the default
+    Default,
+}
+
+impl From<ConfigOrigin> for ConfigSource {
+    fn from(origin: ConfigOrigin) -> Self {
+        match origin {
+            ConfigOrigin::File(_) => ConfigSource::File,
+            ConfigOrigin::Environment(_) => ConfigSource::Environment,
+            ConfigOrigin::Default => ConfigSource::Default,
+        }
+    }
+}
+
+fn config_entries(
+    config: &TEdgeConfig,
+    origins: &ConfigOrigins,
+    all: bool,
+    filter: Option<&str>,
+) -> Vec<ConfigEntry> {
+    config
+        .readable_keys()
+        .filter_map(|config_key| {
+            let key = config_key.to_cow_str().to_string();
+            if !key_matches_filter(&key, filter) {
+                return None;
+            }
+            match config.read_string(&config_key).ok() {
+                Some(value) => Some(ConfigEntry {
+                    source: Some(origins.origin_of(&key).into()),
+                    key,
+                    value: Some(value),
+                }),
+                None if all => Some(ConfigEntry {
+                    key,
+                    value: None,
+                    source: None,
+                }),
+                None => None,
+            }
+        })
+        .collect()
+}
+
 fn print_config_list(
     config: &TEdgeConfig,
     all: bool,
@@ -136,9 +212,81 @@ fn print_config_doc(filter: Option<&str>) {
     }
 }
 
+/// A configuration key documentation, as listed by `tedge config list --doc --output json`
+#[derive(Serialize, Debug)]
+struct ConfigDoc {
+    key: &'static str,
+    description: Option<&'static str>,
+    note: Option<&'static str>,
+    examples: Vec<String>,
+}
+
+fn config_doc(filter: Option<&str>) -> Vec<ConfigDoc> {
+    READABLE_KEYS
+        .iter()
+        .filter(|(key, _)| key_matches_filter(key, filter))
+        .map(|(key, ty)| ConfigDoc {
+            key,
+            description: ty.comment,
+            note: ty.metas.get("note"),
+            examples: match ty.example {
+                Some(doku::Example::Simple(val)) | Some(doku::Example::Literal(val)) => {
+                    vec![val.to_string()]
+                }
+                Some(doku::Example::Compound(val)) => val.iter().map(|v| v.to_string()).collect(),
+                None => vec![],
+            },
+        })
+        .collect()
+}
+
 fn key_matches_filter(key: &str, filter: Option<&str>) -> bool {
     match filter {
         Some(filter) => key.contains(filter),
         None => true,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drop_in_files_are_reported_as_file_sources() {
+        let origin = ConfigOrigin::File("/etc/tedge/tedge.toml.d/10-package.toml".into());
+
+        assert_eq!(ConfigSource::from(origin), ConfigSource::File);
+        assert_eq!(
+            ConfigSource::from(ConfigOrigin::Environment("TEDGE_C8Y_URL".into())),
+            ConfigSource::Environment
+        );
+    }
+
+    #[test]
+    fn unset_config_entries_are_serialized_with_a_null_value() {
+        let entry = ConfigEntry {
+            key: "c8y.url".to_string(),
+            value: None,
+            source: None,
+        };
+
+        assert_eq!(
+            serde_json::to_value(&entry).unwrap(),
+            serde_json::json!({"key": "c8y.url", "value": null, "source": null})
+        );
+    }
+
+    #[test]
+    fn config_entries_are_serialized_with_their_source() {
+        let entry = ConfigEntry {
+            key: "c8y.url".to_string(),
+            value: Some("example.cumulocity.com".to_string()),
+            source: Some(ConfigSource::File),
+        };
+
+        assert_eq!(
+            serde_json::to_value(&entry).unwrap(),
+            serde_json::json!({"key": "c8y.url", "value": "example.cumulocity.com", "source": "file"})
+        );
+    }
+}
@@ -1,12 +1,16 @@
 mod add;
+mod export;
 mod get;
+mod import;
 mod list;
 mod remove;
 mod set;
 mod unset;
 
 pub use self::add::*;
+pub use self::export::*;
 pub use self::get::*;
+pub use self::import::*;
 pub use self::list::*;
 pub use self::remove::*;
 pub use self::set::*;
@@ -1,5 +1,6 @@
 use crate::command::Command;
 use crate::log::MaybeFancy;
+use tedge_config::tedge_toml::ConfigOrigin;
 use tedge_config::tedge_toml::WritableKey;
 use tedge_config::TEdgeConfigLocation;
 
@@ -27,6 +28,15 @@ impl Command for SetConfigCommand {
             })
             .await
             .map_err(anyhow::Error::new)?;
+
+        let key = self.key.to_cow_str();
+        if let ConfigOrigin::Environment(variable) =
+            self.config_location.config_origins().origin_of(&key)
+        {
+            eprintln!(
+                "Warning: {key} has been updated in tedge.toml, but is overridden by the {variable} environment variable"
+            );
+        }
         Ok(())
     }
 }
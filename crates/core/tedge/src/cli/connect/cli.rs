@@ -1,5 +1,6 @@
 use crate::cli::common::CloudArg;
 use crate::cli::connect::*;
+use crate::cli::output::OutputFormat;
 use crate::command::BuildCommand;
 use crate::command::Command;
 use crate::system_services::service_manager;
@@ -16,6 +17,23 @@ pub struct TEdgeConnectOpt {
     #[clap(long = "offline", global = true)]
     offline_mode: bool,
 
+    /// Check each step of the connection in turn (DNS, TCP, TLS, client certificate, clock skew, proxies, JWT retrieval)
+    #[clap(long, global = true, requires = "is_test_connection")]
+    diagnose: bool,
+
+    /// Format of the diagnostics report
+    #[clap(
+        long,
+        global = true,
+        value_enum,
+        default_value_t,
+        requires = "diagnose"
+    )]
+    format: ReportFormat,
+
+    #[clap(from_global)]
+    output: OutputFormat,
+
     #[clap(subcommand)]
     cloud: CloudArg,
 }
@@ -29,6 +47,9 @@ impl BuildCommand for TEdgeConnectOpt {
         let Self {
             is_test_connection,
             offline_mode,
+            diagnose,
+            format,
+            output,
             cloud,
         } = self;
         Ok(Box::new(ConnectCommand {
@@ -37,8 +58,10 @@ impl BuildCommand for TEdgeConnectOpt {
             cloud: cloud.try_into()?,
             is_test_connection,
             offline_mode,
+            diagnose: diagnose.then_some(format),
             service_manager: service_manager(&config_location.tedge_config_root_path)?,
             is_reconnect: false,
+            output,
         }))
     }
 }
@@ -7,11 +7,13 @@ use crate::bridge::CommonMosquittoConfig;
 use crate::bridge::TEDGE_BRIDGE_CONF_DIR_PATH;
 use crate::cli::common::Cloud;
 use crate::cli::common::MaybeBorrowedCloud;
+use crate::cli::connect::diagnostics;
 use crate::cli::connect::jwt_token::*;
 use crate::cli::connect::*;
 use crate::cli::log::ConfigLogger;
 use crate::cli::log::Fancy;
 use crate::cli::log::Spinner;
+use crate::cli::output::OutputFormat;
 use crate::command::Command;
 use crate::log::MaybeFancy;
 use crate::system_services::*;
@@ -69,8 +71,11 @@ pub struct ConnectCommand {
     pub cloud: Cloud,
     pub is_test_connection: bool,
     pub offline_mode: bool,
+    /// Run the step-by-step diagnostics, reporting in the given format, instead of the connection test
+    pub diagnose: Option<ReportFormat>,
     pub service_manager: Arc<dyn SystemServiceManager>,
     pub is_reconnect: bool,
+    pub output: OutputFormat,
 }
 
 pub enum DeviceStatus {
@@ -102,6 +107,26 @@ impl ConnectCommand {
 
         validate_config(config, &self.cloud)?;
 
+        if self.is_test_connection && (self.diagnose.is_some() || self.output == OutputFormat::Json)
+        {
+            let report = if self.diagnose.is_some() {
+                diagnostics::diagnose(config, &self.cloud, &bridge_config).await
+            } else {
+                let bridge_exists = self.check_if_bridge_exists(&bridge_config).await;
+                diagnostics::test_connection(config, &self.cloud, bridge_exists).await
+            };
+            // The global `--output json` option takes precedence over `--format`
+            let format = match self.output {
+                OutputFormat::Json => ReportFormat::Json,
+                OutputFormat::Text => self.diagnose.unwrap_or_default(),
+            };
+            println!("{}", report.render(format));
+            return match report.success {
+                true => Ok(()),
+                false => Err(ConnectError::ConnectionCheckError.into()),
+            };
+        }
+
         if self.is_test_connection {
             ConfigLogger::log(
                 format!("Testing {} connection with config", self.cloud),
@@ -293,11 +318,7 @@ impl ConnectCommand {
         config: &TEdgeConfig,
     ) -> Result<DeviceStatus, Fancy<ConnectError>> {
         let spinner = Spinner::start("Verifying device is connected to cloud");
-        let res = match &self.cloud {
-            Cloud::Azure(profile) => check_device_status_azure(config, profile.as_deref()).await,
-            Cloud::Aws(profile) => check_device_status_aws(config, profile.as_deref()).await,
-            Cloud::C8y(profile) => check_device_status_c8y(config, profile.as_deref()).await,
-        };
+        let res = check_device_status(config, &self.cloud).await;
         spinner.finish(res)
     }
 
@@ -573,9 +594,20 @@ fn is_bridge_health_up_message(message: &rumqttc::Publish, health_topic: &str) -
         && std::str::from_utf8(&message.payload).is_ok_and(|msg| msg.contains("\"up\""))
 }
 
+pub(crate) async fn check_device_status(
+    config: &TEdgeConfig,
+    cloud: &Cloud,
+) -> Result<DeviceStatus, ConnectError> {
+    match cloud {
+        Cloud::Azure(profile) => check_device_status_azure(config, profile.as_deref()).await,
+        Cloud::Aws(profile) => check_device_status_aws(config, profile.as_deref()).await,
+        Cloud::C8y(profile) => check_device_status_c8y(config, profile.as_deref()).await,
+    }
+}
+
 // Check the connection by using the jwt token retrieval over the mqtt.
 // If successful in getting the jwt token '71,xxxxx', the connection is established.
-async fn check_device_status_c8y(
+pub(crate) async fn check_device_status_c8y(
     tedge_config: &TEdgeConfig,
     c8y_profile: Option<&ProfileName>,
 ) -> Result<DeviceStatus, ConnectError> {
@@ -1,10 +1,12 @@
 pub use self::c8y_direct_connection::*;
 pub use self::cli::*;
 pub use self::command::*;
+pub use self::diagnostics::ReportFormat;
 pub use self::error::*;
 
 mod c8y_direct_connection;
 mod cli;
 mod command;
+mod diagnostics;
 mod error;
 mod jwt_token;
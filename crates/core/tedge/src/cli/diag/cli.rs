@@ -0,0 +1,96 @@
+use crate::cli::diag::collect::DiagCollectCommand;
+use crate::cli::http::http_client;
+use crate::command::BuildCommand;
+use crate::command::Command;
+use crate::ConfigError;
+use camino::Utf8PathBuf;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+use tedge_config::models::SecondsOrHumanTime;
+use tedge_config::TEdgeConfig;
+use tedge_config::TEdgeConfigLocation;
+
+#[derive(clap::Subcommand, Debug)]
+pub enum TEdgeDiagCli {
+    /// Collect diagnostic information into a tarball
+    ///
+    /// The tarball gathers the configuration, the health status of the services and bridges,
+    /// the entities, the pending commands, the recent command logs, the device certificate details
+    /// and the disk usage, plus the output of the diagnostic plugins.
+    ///
+    /// Examples:
+    ///   # Create a tarball in the tmp.path directory
+    ///   tedge diag collect
+    ///
+    ///   # Create /var/tmp/device-diag.tar.gz, running additional plugins
+    ///   tedge diag collect --output-dir /var/tmp --name device-diag --plugin-dir /usr/share/diag-plugins
+    #[clap(verbatim_doc_comment)]
+    Collect {
+        /// Directory where the tarball is created [default: tmp.path]
+        #[clap(long)]
+        output_dir: Option<Utf8PathBuf>,
+
+        /// Name of the tarball, without the .tar.gz extension [default: tedge-diag-<timestamp>]
+        #[clap(long)]
+        name: Option<String>,
+
+        /// Directory of the diagnostic plugins [default: <config-dir>/diag-plugins]
+        #[clap(long)]
+        plugin_dir: Option<Utf8PathBuf>,
+
+        /// Timeout of each diagnostic plugin, and of the collection of the health status messages
+        #[clap(long, default_value = "10s")]
+        timeout: SecondsOrHumanTime,
+
+        /// Keep the directory used to build the tarball
+        #[clap(long)]
+        keep_dir: bool,
+
+        /// Upload the tarball with a PUT request to this URL, e.g. the tedgeUrl of a diag_upload command
+        #[clap(long)]
+        upload_url: Option<String>,
+    },
+}
+
+impl BuildCommand for TEdgeDiagCli {
+    fn build_command(
+        self,
+        config: TEdgeConfig,
+        config_location: TEdgeConfigLocation,
+    ) -> Result<Box<dyn Command>, ConfigError> {
+        match self {
+            TEdgeDiagCli::Collect {
+                output_dir,
+                name,
+                plugin_dir,
+                timeout,
+                keep_dir,
+                upload_url,
+            } => {
+                let config_dir = config_location.tedge_config_root_path;
+                let identity = config.http.client.auth.identity()?;
+                let client = http_client(config.cloud_root_certs(), identity.as_ref())?;
+                let token = config.http.client.auth.bearer_token()?;
+                let name = name.unwrap_or_else(|| {
+                    let now = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap_or_default();
+                    format!("tedge-diag-{}", now.as_secs())
+                });
+                Ok(DiagCollectCommand {
+                    output_dir: output_dir.unwrap_or_else(|| config.tmp.path.clone()),
+                    plugin_dir: plugin_dir.unwrap_or_else(|| config_dir.join("diag-plugins")),
+                    name,
+                    timeout: timeout.duration(),
+                    keep_dir,
+                    upload_url,
+                    client,
+                    token,
+                    config_dir,
+                    config,
+                }
+                .into_boxed())
+            }
+        }
+    }
+}
@@ -0,0 +1,4 @@
+mod cli;
+mod collect;
+
+pub use cli::TEdgeDiagCli;
@@ -202,15 +202,17 @@ impl BuildCommand for TEdgeHttpCli {
     ) -> Result<Box<dyn Command>, ConfigError> {
         let uri = self.uri();
 
-        let (protocol, host, port) = if uri.starts_with("/c8y") {
+        // The bearer token is only sent to the agent, not to the Cumulocity proxy
+        let (protocol, host, port, token) = if uri.starts_with("/c8y") {
             let c8y_config = config.c8y.try_get(self.c8y_profile())?;
             let client = &c8y_config.proxy.client;
             let protocol = https_if_some(&c8y_config.proxy.cert_path);
-            (protocol, client.host.clone(), client.port)
+            (protocol, client.host.clone(), client.port, None)
         } else if uri.starts_with("/tedge") {
             let client = &config.http.client;
             let protocol = https_if_some(&config.http.cert_path);
-            (protocol, client.host.clone(), client.port)
+            let token = client.auth.bearer_token()?;
+            (protocol, client.host.clone(), client.port, token)
         } else {
             return Err(anyhow!("Not a local HTTP uri: {uri}").into());
         };
@@ -224,6 +226,7 @@ impl BuildCommand for TEdgeHttpCli {
             client,
             url,
             action,
+            token,
         }
         .into_boxed())
     }
@@ -290,11 +293,14 @@ impl TEdgeHttpCli {
     }
 }
 
-fn https_if_some<T>(cert_path: &OptionalConfig<T>) -> &'static str {
+pub(crate) fn https_if_some<T>(cert_path: &OptionalConfig<T>) -> &'static str {
     cert_path.or_none().map_or("http", |_| "https")
 }
 
-fn http_client(root_certs: CloudRootCerts, identity: Option<&Identity>) -> Result<Client, Error> {
+pub(crate) fn http_client(
+    root_certs: CloudRootCerts,
+    identity: Option<&Identity>,
+) -> Result<Client, Error> {
     let builder = root_certs.client_builder();
     let builder = if let Some(identity) = identity {
         builder.identity(identity.clone())
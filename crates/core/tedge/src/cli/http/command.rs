@@ -19,6 +19,9 @@ pub struct HttpCommand {
 
     /// Action
     pub action: HttpAction,
+
+    /// Bearer token to authenticate with, if any
+    pub token: Option<String>,
 }
 
 pub enum HttpAction {
@@ -85,7 +88,10 @@ impl HttpCommand {
             HttpAction::Delete => client.delete(url).headers(headers),
         };
 
-        Ok(request)
+        Ok(match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        })
     }
 
     async fn send(request: RequestBuilder) -> Result<(), Error> {
@@ -1,4 +1,6 @@
 mod cli;
 mod command;
 
+pub(crate) use cli::http_client;
+pub(crate) use cli::https_if_some;
 pub use cli::TEdgeHttpCli;
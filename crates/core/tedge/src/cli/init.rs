@@ -1,3 +1,4 @@
+use super::certificate::CreateLocalCaCmd;
 use super::log::MaybeFancy;
 use crate::command::Command;
 use crate::Component;
@@ -119,6 +120,13 @@ impl TEdgeInitCmd {
             .await?;
         }
 
+        if config.mqtt.local_ca.enable {
+            CreateLocalCaCmd::from_tedge_config(config, &self.user, &self.group)?
+                .create_local_certificates()
+                .await
+                .context("creating the local CA and certificates")?;
+        }
+
         Ok(())
     }
 }
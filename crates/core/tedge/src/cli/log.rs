@@ -170,6 +170,16 @@ pub enum MaybeFancy<E> {
     Fancy(Fancy<E>),
 }
 
+impl<E> MaybeFancy<E> {
+    /// The underlying error, be it already logged or not
+    pub fn into_inner(self) -> E {
+        match self {
+            MaybeFancy::Unfancy(err) => err,
+            MaybeFancy::Fancy(Fancy { err, .. }) => err,
+        }
+    }
+}
+
 impl<E> From<E> for MaybeFancy<E> {
     fn from(value: E) -> Self {
         Self::Unfancy(value)
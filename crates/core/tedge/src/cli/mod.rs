@@ -6,6 +6,7 @@ use c8y_firmware_plugin::FirmwarePluginOpt;
 use c8y_remote_access_plugin::C8yRemoteAccessPluginOpt;
 use completions::Shell;
 pub use connect::*;
+use output::OutputFormat;
 use tedge_agent::AgentOpt;
 use tedge_apt_plugin::AptCli;
 use tedge_config::cli::CommonArgs;
@@ -21,11 +22,13 @@ mod common;
 mod completions;
 pub mod config;
 mod connect;
+mod diag;
 mod disconnect;
 mod http;
 mod init;
 pub mod log;
 mod mqtt;
+pub mod output;
 mod reconnect;
 mod refresh_bridges;
 mod upload;
@@ -53,6 +56,13 @@ pub struct TEdgeCli {
     #[clap(flatten)]
     pub common: CommonArgs,
 
+    /// Output format, `json` being supported by `config list`, `cert show`, `connect --test` and `mqtt sub`
+    ///
+    /// With `json`, the errors are also printed as JSON on stderr
+    /// and the exit code tells the kind of error.
+    #[clap(long, global = true, value_enum, default_value_t)]
+    pub output: OutputFormat,
+
     #[clap(subcommand)]
     pub cmd: TEdgeOpt,
 }
@@ -130,6 +140,10 @@ pub enum TEdgeOpt {
     #[clap(subcommand)]
     Http(http::TEdgeHttpCli),
 
+    /// Collect diagnostic information
+    #[clap(subcommand)]
+    Diag(diag::TEdgeDiagCli),
+
     /// Run thin-edge services and plugins
     Run(ComponentOpt),
 
@@ -210,6 +224,7 @@ impl BuildCommand for TEdgeOpt {
             }
             TEdgeOpt::Mqtt(opt) => opt.build_command(config, config_location),
             TEdgeOpt::Http(opt) => opt.build_command(config, config_location),
+            TEdgeOpt::Diag(opt) => opt.build_command(config, config_location),
             TEdgeOpt::Reconnect(opt) => opt.build_command(config, config_location),
             TEdgeOpt::Run(_) => {
                 // This method has to be kept in sync with tedge::redirect_if_multicall()
@@ -1,6 +1,7 @@
 use crate::cli::mqtt::publish::MqttPublishCommand;
 use crate::cli::mqtt::subscribe::MqttSubscribeCommand;
 use crate::cli::mqtt::subscribe::SimpleTopicFilter;
+use crate::cli::output::OutputFormat;
 use crate::command::BuildCommand;
 use crate::command::Command;
 use clap_complete::ArgValueCandidates;
@@ -52,6 +53,9 @@ pub enum TEdgeMqttCli {
         /// Disconnect and exit after receiving the specified number of messages
         #[clap(long, short = 'C')]
         count: Option<u32>,
+
+        #[clap(from_global)]
+        output: OutputFormat,
     },
 }
 
@@ -89,6 +93,7 @@ impl BuildCommand for TEdgeMqttCli {
                     hide_topic,
                     duration,
                     count,
+                    output,
                 } => MqttSubscribeCommand {
                     host: config.mqtt.client.host.clone(),
                     port: config.mqtt.client.port.into(),
@@ -101,6 +106,7 @@ impl BuildCommand for TEdgeMqttCli {
                     client_auth_config: auth_config.client,
                     duration: duration.map(|v| v.duration()),
                     count,
+                    output,
                 }
                 .into_boxed(),
             }
@@ -1,9 +1,12 @@
+use crate::cli::output::OutputFormat;
 use crate::command::Command;
 use crate::log::MaybeFancy;
 use camino::Utf8PathBuf;
+use mqtt_channel::MqttMessage;
 use mqtt_channel::QoS;
 use mqtt_channel::StreamExt;
 use mqtt_channel::TopicFilter;
+use serde::Serialize;
 use std::time::Duration;
 use tedge_config::tedge_toml::MqttAuthClientConfig;
 use tokio::io::AsyncWriteExt;
@@ -25,11 +28,21 @@ pub struct MqttSubscribeCommand {
     pub client_auth_config: Option<MqttAuthClientConfig>,
     pub duration: Option<Duration>,
     pub count: Option<u32>,
+    pub output: OutputFormat,
 }
 
 #[derive(Clone, Debug)]
 pub struct SimpleTopicFilter(String);
 
+/// A message, as printed on a single line by `tedge mqtt sub --output json`
+#[derive(Serialize, Debug)]
+struct JsonMessage<'a> {
+    topic: &'a str,
+    payload: &'a str,
+    qos: u8,
+    retain: bool,
+}
+
 #[async_trait::async_trait]
 impl Command for MqttSubscribeCommand {
     fn description(&self) -> String {
@@ -71,6 +84,7 @@ async fn subscribe(cmd: &MqttSubscribeCommand) -> Result<(), anyhow::Error> {
     let mut signals = tedge_utils::signals::TermSignals::new(cmd.duration);
     let mut n_messages = 0;
     let mut stdout = tokio::io::stdout();
+    let output = cmd.output;
     loop {
         let message = match signals.might_interrupt(mqtt.received.next()).await {
             Ok(Some(message)) => message,
@@ -83,10 +97,10 @@ async fn subscribe(cmd: &MqttSubscribeCommand) -> Result<(), anyhow::Error> {
 
         match message.payload_str() {
             Ok(payload) => {
-                let line = if cmd.hide_topic {
-                    format!("{payload}\n")
-                } else {
-                    format!("[{}] {payload}\n", &message.topic)
+                let line = match output {
+                    OutputFormat::Json => json_line(&message, payload)?,
+                    OutputFormat::Text if cmd.hide_topic => format!("{payload}\n"),
+                    OutputFormat::Text => format!("[{}] {payload}\n", &message.topic),
                 };
                 let _ = stdout.write_all(line.as_bytes()).await;
                 let _ = stdout.flush().await;
@@ -105,6 +119,16 @@ async fn subscribe(cmd: &MqttSubscribeCommand) -> Result<(), anyhow::Error> {
     Ok(())
 }
 
+fn json_line(message: &MqttMessage, payload: &str) -> Result<String, serde_json::Error> {
+    let json = serde_json::to_string(&JsonMessage {
+        topic: &message.topic.name,
+        payload,
+        qos: message.qos as u8,
+        retain: message.retain,
+    })?;
+    Ok(format!("{json}\n"))
+}
+
 // Using TopicFilter for `tedge sub` would lead to complicate code for nothing
 // because a TopicFilter is a set of patterns while `tedge sub` uses a single pattern.
 impl SimpleTopicFilter {
@@ -117,3 +141,26 @@ impl SimpleTopicFilter {
         self.0.as_str()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mqtt_channel::Topic;
+
+    #[test]
+    fn messages_are_printed_as_json_lines() {
+        let message = MqttMessage::new(
+            &Topic::new_unchecked("te/device/main///m/"),
+            "{\"temperature\": 21.3}",
+        )
+        .with_qos(QoS::AtLeastOnce)
+        .with_retain();
+
+        let line = json_line(&message, message.payload_str().unwrap()).unwrap();
+
+        assert_eq!(
+            line,
+            "{\"topic\":\"te/device/main///m/\",\"payload\":\"{\\\"temperature\\\": 21.3}\",\"qos\":1,\"retain\":true}\n"
+        );
+    }
+}
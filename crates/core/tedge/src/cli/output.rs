@@ -0,0 +1,254 @@
+//! Machine-readable output of the tedge commands, as selected by the global `--output` option.
+//!
+//! The output format is parsed by `main` and passed to the commands that support a JSON output,
+//! each subcommand reading the global option with `#[clap(from_global)]`.
+//! When the JSON output is selected, the errors are also reported as JSON,
+//! with an error kind and a matching process exit code, as listed by [ErrorKind].
+//! As text, the process exits with code 1 on any error, as scripts expect.
+use crate::cli::connect::ConnectError;
+use crate::cli::CertError;
+use crate::error::TEdgeError;
+use serde::Serialize;
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, Eq, PartialEq, strum_macros::Display)]
+#[strum(serialize_all = "snake_case")]
+pub enum OutputFormat {
+    /// Human-readable text
+    #[default]
+    Text,
+    /// JSON documents, or JSON lines for streams of messages
+    Json,
+}
+
+/// Print a value as a JSON document on stdout
+pub fn print_json(value: &impl Serialize) -> Result<(), anyhow::Error> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// The kind of error reported when a command fails, each kind having its own exit code
+///
+/// | Kind               | Exit code |
+/// |--------------------|-----------|
+/// | `failure`          | 1         |
+/// | `configuration`    | 3         |
+/// | `certificate`      | 4         |
+/// | `connection_check` | 5         |
+/// | `mqtt`             | 6         |
+///
+/// These exit codes are only used with `--output json`.
+#[derive(Serialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// Any error not covered by a more specific kind
+    Failure,
+    /// The configuration is invalid or incomplete
+    Configuration,
+    /// A certificate or private key is missing or invalid
+    Certificate,
+    /// The connection to the cloud is not established
+    ConnectionCheck,
+    /// The local MQTT broker cannot be reached
+    Mqtt,
+}
+
+impl ErrorKind {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorKind::Failure => 1,
+            ErrorKind::Configuration => 3,
+            ErrorKind::Certificate => 4,
+            ErrorKind::ConnectionCheck => 5,
+            ErrorKind::Mqtt => 6,
+        }
+    }
+
+    /// Classify an error, using the first cause of a known type
+    pub fn of(err: &anyhow::Error) -> ErrorKind {
+        err.chain()
+            .find_map(|cause| {
+                if let Some(err) = cause.downcast_ref::<ConnectError>() {
+                    return Self::of_connect_error(err);
+                }
+                if let Some(err) = cause.downcast_ref::<TEdgeError>() {
+                    return Self::of_tedge_error(err);
+                }
+                if let Some(err) = cause.downcast_ref::<CertError>() {
+                    return match err {
+                        CertError::ConfigError(err) => Self::of_tedge_error(err),
+                        _ => Some(ErrorKind::Certificate),
+                    };
+                }
+                if cause.is::<tedge_config::TEdgeConfigError>()
+                    || cause.is::<tedge_config::ConfigSettingError>()
+                    || cause.is::<tedge_config::tedge_toml::ReadError>()
+                    || cause.is::<tedge_config::tedge_toml::ConfigNotSet>()
+                    || cause.is::<tedge_config::tedge_toml::MultiError>()
+                {
+                    return Some(ErrorKind::Configuration);
+                }
+                if cause.is::<certificate::CertificateError>() {
+                    return Some(ErrorKind::Certificate);
+                }
+                if cause.is::<mqtt_channel::MqttError>() {
+                    return Some(ErrorKind::Mqtt);
+                }
+                None
+            })
+            .unwrap_or(ErrorKind::Failure)
+    }
+
+    fn of_connect_error(err: &ConnectError) -> Option<ErrorKind> {
+        match err {
+            ConnectError::ConnectionCheckError
+            | ConnectError::DeviceNotConnected { .. }
+            | ConnectError::UnknownDeviceStatus
+            | ConnectError::TimeoutElapsedError => Some(ErrorKind::ConnectionCheck),
+            ConnectError::Configuration(err) => Self::of_tedge_error(err),
+            ConnectError::PortSettingError(_)
+            | ConnectError::ConfigLoadError(_)
+            | ConnectError::MultiError(_) => Some(ErrorKind::Configuration),
+            ConnectError::Certificate(_) | ConnectError::CertificateError(_) => {
+                Some(ErrorKind::Certificate)
+            }
+            ConnectError::MqttClient(_) => Some(ErrorKind::Mqtt),
+            _ => None,
+        }
+    }
+
+    fn of_tedge_error(err: &TEdgeError) -> Option<ErrorKind> {
+        match err {
+            TEdgeError::FromTomlParse(_)
+            | TEdgeError::FromInvalidToml(_)
+            | TEdgeError::FromTEdgeConfig(_)
+            | TEdgeError::FromTEdgeConfigSetting(_)
+            | TEdgeError::FromSystemToml(_)
+            | TEdgeError::FromTEdgeConfigRead(_)
+            | TEdgeError::FromConfigNotSet(_)
+            | TEdgeError::FromMultiError(_)
+            | TEdgeError::FromC8yEndPointConfigError(_) => Some(ErrorKind::Configuration),
+            TEdgeError::FromCertificate(_) | TEdgeError::FromCredentialsFileError(_) => {
+                Some(ErrorKind::Certificate)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// An error as reported on stderr when the JSON output is selected
+#[derive(Serialize, Debug)]
+pub struct JsonError {
+    pub kind: ErrorKind,
+    pub exit_code: i32,
+    /// What the command was doing, e.g. "connect to Cumulocity cloud."
+    pub context: String,
+    pub message: String,
+    /// The chain of the underlying causes, the first one excluded
+    pub causes: Vec<String>,
+}
+
+impl JsonError {
+    pub fn new(context: impl Into<String>, err: &anyhow::Error) -> Self {
+        let kind = ErrorKind::of(err);
+        JsonError {
+            kind,
+            exit_code: kind.exit_code(),
+            context: context.into(),
+            message: err.to_string(),
+            causes: err.chain().skip(1).map(|cause| cause.to_string()).collect(),
+        }
+    }
+
+    /// Print this error as a single line of JSON on stderr, returning the exit code to be used
+    pub fn report(self) -> i32 {
+        let exit_code = self.exit_code;
+        eprintln!("{}", serde_json::json!({ "error": self }));
+        exit_code
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connection_check_failures_have_a_dedicated_exit_code() {
+        let err = anyhow::Error::from(ConnectError::DeviceNotConnected {
+            cloud: "Cumulocity".to_string(),
+        });
+
+        let json_error = JsonError::new("test connection to Cumulocity cloud.", &err);
+
+        assert_eq!(json_error.kind, ErrorKind::ConnectionCheck);
+        assert_eq!(json_error.exit_code, 5);
+        assert_eq!(
+            json_error.message,
+            "Device is not connected to Cumulocity cloud"
+        );
+    }
+
+    #[test]
+    fn errors_are_classified_by_their_first_known_cause() {
+        let err = anyhow::Error::from(CertError::CertificateNotFound {
+            path: "/etc/tedge/device-certs/tedge-certificate.pem".into(),
+        })
+        .context("reading the certificate");
+
+        let json_error = JsonError::new("show the device certificate", &err);
+
+        assert_eq!(json_error.kind, ErrorKind::Certificate);
+        assert_eq!(json_error.message, "reading the certificate");
+        assert_eq!(json_error.causes.len(), 1);
+    }
+
+    #[test]
+    fn each_kind_of_error_has_its_own_exit_code() {
+        let cases = [
+            (
+                anyhow::anyhow!("something went wrong"),
+                ErrorKind::Failure,
+                1,
+            ),
+            (
+                anyhow::Error::from(ConnectError::PortSettingError(
+                    tedge_config::ConfigSettingError::ConfigNotSet {
+                        key: "mqtt.bind.port",
+                    },
+                )),
+                ErrorKind::Configuration,
+                3,
+            ),
+            (
+                anyhow::Error::from(CertError::CertificateNotFound {
+                    path: "/etc/tedge/device-certs/tedge-certificate.pem".into(),
+                }),
+                ErrorKind::Certificate,
+                4,
+            ),
+            (
+                anyhow::Error::from(ConnectError::TimeoutElapsedError),
+                ErrorKind::ConnectionCheck,
+                5,
+            ),
+            (
+                anyhow::Error::from(mqtt_channel::MqttError::SubscriptionFailure),
+                ErrorKind::Mqtt,
+                6,
+            ),
+        ];
+
+        for (err, kind, exit_code) in cases {
+            let json_error = JsonError::new("test", &err);
+            assert_eq!(json_error.kind, kind, "{err}");
+            assert_eq!(json_error.exit_code, exit_code, "{err}");
+        }
+    }
+
+    #[test]
+    fn unknown_errors_are_reported_as_failures() {
+        let err = anyhow::anyhow!("something went wrong");
+
+        assert_eq!(ErrorKind::of(&err), ErrorKind::Failure);
+        assert_eq!(ErrorKind::of(&err).exit_code(), 1);
+    }
+}
@@ -1,6 +1,7 @@
 use crate::cli::common::Cloud;
 use crate::cli::connect::ConnectCommand;
 use crate::cli::disconnect::disconnect_bridge::DisconnectBridgeCommand;
+use crate::cli::output::OutputFormat;
 use crate::command::Command;
 use crate::log::MaybeFancy;
 use crate::system_services::SystemServiceManager;
@@ -53,8 +54,10 @@ impl From<&ReconnectBridgeCommand> for ConnectCommand {
             cloud: reconnect_cmd.cloud.clone(),
             is_test_connection: false,
             offline_mode: false,
+            diagnose: None,
             service_manager: reconnect_cmd.service_manager.clone(),
             is_reconnect: true,
+            output: OutputFormat::Text,
         }
     }
 }
@@ -90,6 +90,7 @@ pub trait Command {
     /// use tedge_config::tedge_toml::ReadableKey;
     /// use tedge_config::TEdgeConfigLocation;
     /// use tedge::cli::config::GetConfigCommand;
+    /// use tedge::cli::output::OutputFormat;
     /// use tedge::ConfigError;
     /// use tedge::command::Command;
     ///
@@ -97,7 +98,7 @@ pub trait Command {
     ///
     /// impl SomeStruct {
     ///     fn build_command(self, config_location: TEdgeConfigLocation) -> Result<Box<dyn Command>, ConfigError> {
-    ///         let cmd = GetConfigCommand { config_location, key: ReadableKey::MqttBindPort };
+    ///         let cmd = GetConfigCommand { config_location, key: ReadableKey::MqttBindPort, show_origin: false, output: OutputFormat::Text };
     ///         Ok(cmd.into_boxed())
     ///     }
     /// }
@@ -119,6 +120,7 @@ pub trait Command {
 /// ```
 /// use tedge::command::*;
 /// use tedge::cli::config::*;
+/// use tedge::cli::output::OutputFormat;
 /// use tedge::ConfigError;
 /// use tedge_config::tedge_toml::tedge_config::*;
 /// use tedge_config::TEdgeConfig;
@@ -144,6 +146,8 @@ pub trait Command {
 ///             ConfigCmd::Get { key } => GetConfigCommand {
 ///                 config_location,
 ///                 key,
+///                 show_origin: false,
+///                 output: OutputFormat::Text,
 ///             }.into_boxed(),
 ///         };
 ///         Ok(cmd)
@@ -36,6 +36,9 @@ pub enum TEdgeError {
     #[error(transparent)]
     FromMultiError(#[from] MultiError),
 
+    #[error(transparent)]
+    FromCertificate(#[from] certificate::CertificateError),
+
     #[error(transparent)]
     FromCredentialsFileError(#[from] c8y_api::http_proxy::CredentialsFileError),
 
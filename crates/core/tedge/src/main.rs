@@ -13,6 +13,8 @@ use std::ffi::OsString;
 use std::io::IsTerminal;
 use std::path::PathBuf;
 use std::time::Duration;
+use tedge::cli::output::JsonError;
+use tedge::cli::output::OutputFormat;
 use tedge::command::BuildCommand;
 use tedge::log::MaybeFancy;
 use tedge::Component;
@@ -21,7 +23,6 @@ use tedge::TEdgeCli;
 use tedge::TEdgeOpt;
 use tedge::TEdgeOptMulticall;
 use tedge_apt_plugin::AptCli;
-use tedge_config::cli::CommonArgs;
 use tedge_config::log_init;
 use tracing::log;
 
@@ -38,11 +39,13 @@ async fn main() -> anyhow::Result<()> {
         TEdgeOptMulticall::Component(Component::TedgeMapper(opt)) => {
             let tedge_config = tedge_config::TEdgeConfig::load(&opt.common.config_dir).await?;
             log_memory_usage(tedge_config.run.log_memory_interval.duration());
+            export_memory_usage();
             tedge_mapper::run(opt).await
         }
         TEdgeOptMulticall::Component(Component::TedgeAgent(opt)) => {
             let tedge_config = tedge_config::TEdgeConfig::load(&opt.common.config_dir).await?;
             log_memory_usage(tedge_config.run.log_memory_interval.duration());
+            export_memory_usage();
             tedge_agent::run(opt).await
         }
         TEdgeOptMulticall::Component(Component::C8yFirmwarePlugin(fp_opt)) => {
@@ -66,10 +69,17 @@ async fn main() -> anyhow::Result<()> {
                 .await
                 .context("failed to run tedge apt plugin")?
         }
-        TEdgeOptMulticall::Tedge(TEdgeCli { cmd, common }) => {
+        TEdgeOptMulticall::Tedge(TEdgeCli {
+            cmd,
+            common,
+            output,
+        }) => {
             let tedge_config_location =
                 tedge_config::TEdgeConfigLocation::from_custom_root(&common.config_dir);
-            let tedge_config = tedge_config::TEdgeConfig::load(&common.config_dir).await?;
+            let tedge_config = tedge_config::TEdgeConfig::load(&common.config_dir)
+                .await
+                .map_err(anyhow::Error::from)
+                .or_else(|err| exit_if_json(output, "load the configuration", err))?;
 
             log_init(
                 "tedge",
@@ -79,7 +89,8 @@ async fn main() -> anyhow::Result<()> {
 
             let cmd = cmd
                 .build_command(tedge_config, tedge_config_location)
-                .with_context(|| "missing configuration parameter")?;
+                .with_context(|| "missing configuration parameter")
+                .or_else(|err| exit_if_json(output, "build the command", err))?;
 
             if !std::io::stdout().is_terminal() {
                 yansi::disable();
@@ -87,6 +98,10 @@ async fn main() -> anyhow::Result<()> {
 
             match cmd.execute().await {
                 Ok(()) => Ok(()),
+                Err(err) if output == OutputFormat::Json => {
+                    let err = err.into_inner();
+                    std::process::exit(JsonError::new(cmd.description(), &err).report())
+                }
                 // If the command already prints its own nicely formatted errors
                 // don't also print the error by returning it
                 Err(MaybeFancy::Fancy(_)) => std::process::exit(1),
@@ -98,6 +113,16 @@ async fn main() -> anyhow::Result<()> {
     }
 }
 
+/// With the JSON output, report the error as JSON and exit, otherwise return the error to `main`
+///
+/// See [ErrorKind](tedge::cli::output::ErrorKind) for the exit codes.
+fn exit_if_json<T>(output: OutputFormat, context: &str, err: anyhow::Error) -> anyhow::Result<T> {
+    if output == OutputFormat::Json {
+        std::process::exit(JsonError::new(context, &err).report())
+    }
+    Err(err)
+}
+
 fn log_memory_usage(log_memory_interval: Duration) {
     if log_memory_interval.is_zero() {
         return;
@@ -113,6 +138,13 @@ fn log_memory_usage(log_memory_interval: Duration) {
     });
 }
 
+/// Sample the allocated memory each time the metrics are exported
+fn export_memory_usage() {
+    tedge_metrics::on_collect(|| {
+        tedge_metrics::ALLOCATED_MEMORY.set(&[], ALLOCATOR.allocated() as f64)
+    });
+}
+
 fn executable_name() -> Option<String> {
     Some(
         PathBuf::from(std::env::args_os().next()?)
@@ -148,9 +180,7 @@ where
 
     let cmd2 = cmd.clone();
     match TEdgeOptMulticall::from_arg_matches(&cmd.get_matches_from(args)) {
-        Ok(TEdgeOptMulticall::Tedge(TEdgeCli { cmd, common })) => {
-            redirect_if_multicall(cmd, common)
-        }
+        Ok(TEdgeOptMulticall::Tedge(cli)) => redirect_if_multicall(cli),
         Ok(t) => t,
         Err(e) => {
             eprintln!("{}", RichFormatter::format_error(&e.with_cmd(&cmd2)));
@@ -162,10 +192,10 @@ where
 // Transform `tedge mapper|agent|write` commands into multicall commands
 //
 // This method has to be kept in sync with TEdgeOpt::build_command
-fn redirect_if_multicall(cmd: TEdgeOpt, common: CommonArgs) -> TEdgeOptMulticall {
-    match cmd {
+fn redirect_if_multicall(cli: TEdgeCli) -> TEdgeOptMulticall {
+    match cli.cmd {
         TEdgeOpt::Run(ComponentOpt { component }) => TEdgeOptMulticall::Component(component),
-        cmd => TEdgeOptMulticall::Tedge(TEdgeCli { cmd, common }),
+        _ => TEdgeOptMulticall::Tedge(cli),
     }
 }
 
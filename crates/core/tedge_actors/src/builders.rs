@@ -60,18 +60,24 @@
 //!   The difference is that a [DynSender] can transform the messages sent by the source to adapt them to the sink expectations,
 //!   using an `impl From<SourceMessage> for SinkMessage`. This flexibility allows an actor to receive
 //!   messages from several independent sources (see the [fan_in_message_type](crate::fan_in_message_type) macro).
+use crate::message_boxes::ReclaimSlot;
 use crate::mpsc;
 use crate::CloneSender;
+use crate::CombinedReceiver;
 use crate::DynSender;
+use crate::LinkError;
 use crate::LoggingReceiver;
 use crate::LoggingSender;
 use crate::MappingSender;
 use crate::Message;
+use crate::MessageBoxMetrics;
 use crate::NullSender;
 use crate::RuntimeRequest;
 use crate::SimpleMessageBox;
 use std::convert::Infallible;
 use std::fmt::Debug;
+use std::sync::Arc;
+use std::sync::Mutex;
 
 /// Builder of `T`
 ///
@@ -381,7 +387,8 @@ pub struct SimpleMessageBoxBuilder<I: Debug, O> {
     input_sender: mpsc::Sender<I>,
     signal_sender: mpsc::Sender<RuntimeRequest>,
     output_sender: DynSender<O>,
-    input_receiver: LoggingReceiver<I>,
+    input_receiver: ReclaimSlot<I>,
+    metrics: MessageBoxMetrics,
 }
 
 impl<I: Message, O: Message> SimpleMessageBoxBuilder<I, O> {
@@ -389,18 +396,51 @@ impl<I: Message, O: Message> SimpleMessageBoxBuilder<I, O> {
         let (input_sender, input_receiver) = mpsc::channel(capacity);
         let (signal_sender, signal_receiver) = mpsc::channel(4);
         let output_sender = NullSender.into();
-        let input_receiver =
-            LoggingReceiver::new(name.to_string(), input_receiver, signal_receiver);
+        let input_receiver = CombinedReceiver::new(input_receiver, signal_receiver);
+        let metrics = MessageBoxMetrics::register(name);
 
         SimpleMessageBoxBuilder {
             name: name.to_string(),
             input_sender,
             signal_sender,
             output_sender,
-            input_receiver,
+            input_receiver: Arc::new(Mutex::new(Some(input_receiver))),
+            metrics,
         }
     }
 
+    /// Build a message box that can be taken over by a new instance of the actor
+    ///
+    /// Contrary to [build](Builder::build), this method can be called several times,
+    /// each call returning a message box that uses the same channels:
+    /// the input messages not consumed by the previous instance of the actor are received by the new one.
+    ///
+    /// This is used by a [RestartableBuilder](crate::RestartableBuilder)
+    /// to give the message box of a failed actor to its new instance.
+    /// The previous message box must have been dropped, i.e. the previous actor instance must have terminated.
+    pub fn rebuild(&mut self) -> Result<SimpleMessageBox<I, O>, LinkError> {
+        let receiver = self
+            .input_receiver
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take()
+            .ok_or_else(|| LinkError::MessageBoxInUse {
+                role: self.name.clone(),
+            })?;
+        let receiver = LoggingReceiver::from_parts(
+            self.name.clone(),
+            receiver,
+            self.metrics.clone(),
+            Some(self.input_receiver.clone()),
+        );
+        let sender = LoggingSender::with_metrics(
+            self.name.clone(),
+            self.output_sender.sender_clone(),
+            self.metrics.clone(),
+        );
+        Ok(SimpleMessageBox::new(receiver, sender))
+    }
+
     /// Connect this client message box to the service message box
     pub fn set_connection<Config>(
         &mut self,
@@ -435,7 +475,8 @@ impl<I: Message, O: Message, C> MessageSource<O, C> for SimpleMessageBoxBuilder<
 /// A `SimpleMessageBoxBuilder<Input,Output>` is a [MessageSink] of `Input` messages with no specific config.
 impl<I: Message, O: Message> MessageSink<I> for SimpleMessageBoxBuilder<I, O> {
     fn get_sender(&self) -> DynSender<I> {
-        self.input_sender.sender_clone()
+        self.metrics
+            .counting_sender(self.input_sender.sender_clone())
     }
 }
 
@@ -458,7 +499,15 @@ impl<Req: Message, Res: Message> Builder<SimpleMessageBox<Req, Res>>
     }
 
     fn build(self) -> SimpleMessageBox<Req, Res> {
-        let sender = LoggingSender::new(self.name, self.output_sender);
-        SimpleMessageBox::new(self.input_receiver, sender)
+        let receiver = self
+            .input_receiver
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take()
+            .unwrap_or_else(CombinedReceiver::closed);
+        let receiver =
+            LoggingReceiver::from_parts(self.name.clone(), receiver, self.metrics.clone(), None);
+        let sender = LoggingSender::with_metrics(self.name, self.output_sender, self.metrics);
+        SimpleMessageBox::new(receiver, sender)
     }
 }
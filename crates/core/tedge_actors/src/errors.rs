@@ -58,4 +58,7 @@ pub enum LinkError {
 
     #[error("Extra peer for {role}")]
     ExcessPeer { role: String },
+
+    #[error("The message box of {role} is still used by a previous instance")]
+    MessageBoxInUse { role: String },
 }
@@ -306,10 +306,12 @@ pub mod converter;
 mod errors;
 pub mod message_boxes;
 mod messages;
+pub mod metrics;
 #[doc(hidden)]
 mod run_actor;
 pub mod runtime;
 pub mod servers;
+pub mod supervision;
 
 pub use actors::*;
 pub use builders::*;
@@ -318,8 +320,10 @@ pub use converter::*;
 pub use errors::*;
 pub use message_boxes::*;
 pub use messages::*;
+pub use metrics::*;
 pub use runtime::*;
 pub use servers::*;
+pub use supervision::*;
 
 pub use futures;
 use futures::channel::mpsc;
@@ -85,6 +85,7 @@
 //! TODO
 //!
 use crate::channels::Sender;
+use crate::metrics::MessageBoxMetrics;
 use crate::ChannelError;
 use crate::CloneSender;
 use crate::DynSender;
@@ -95,6 +96,9 @@ use futures::channel::mpsc;
 use futures::StreamExt;
 use log::debug;
 use std::fmt::Debug;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Instant;
 
 #[async_trait]
 pub trait MessageReceiver<Input> {
@@ -115,8 +119,14 @@ pub trait MessageReceiver<Input> {
 pub struct LoggingReceiver<Input: Debug> {
     name: String,
     receiver: CombinedReceiver<Input>,
+    metrics: MessageBoxMetrics,
+    processing_since: Option<Instant>,
+    reclaim: Option<ReclaimSlot<Input>>,
 }
 
+/// Where the channels of a reclaimable [LoggingReceiver] are given back when the receiver is dropped
+pub(crate) type ReclaimSlot<Input> = Arc<Mutex<Option<CombinedReceiver<Input>>>>;
+
 impl<Input: Debug> LoggingReceiver<Input> {
     pub fn new(
         name: String,
@@ -124,7 +134,32 @@ impl<Input: Debug> LoggingReceiver<Input> {
         signal_receiver: mpsc::Receiver<RuntimeRequest>,
     ) -> Self {
         let receiver = CombinedReceiver::new(input_receiver, signal_receiver);
-        Self { name, receiver }
+        let metrics = MessageBoxMetrics::register(&name);
+        Self::from_parts(name, receiver, metrics, None)
+    }
+
+    /// Create a receiver from its parts
+    ///
+    /// When a `reclaim` slot is given, the channels are given back to this slot when the receiver is dropped.
+    /// This is used to restart an actor: the new instance taking over the channels of the failed one.
+    pub(crate) fn from_parts(
+        name: String,
+        receiver: CombinedReceiver<Input>,
+        metrics: MessageBoxMetrics,
+        reclaim: Option<ReclaimSlot<Input>>,
+    ) -> Self {
+        Self {
+            name,
+            receiver,
+            metrics,
+            processing_since: None,
+            reclaim,
+        }
+    }
+
+    /// The metrics of this message box
+    pub fn metrics(&self) -> &MessageBoxMetrics {
+        &self.metrics
     }
 
     /// Splits a `LoggingReceiver` into an input receiver and a signal receiver,
@@ -149,8 +184,12 @@ impl<Input: Debug> LoggingReceiver<Input> {
     ///
     /// This method returns consumes the `LoggingReceiver` and returns owned
     /// receivers, which can then be separately moved.
-    pub fn into_split(self) -> (mpsc::Receiver<Input>, mpsc::Receiver<RuntimeRequest>) {
-        (self.receiver.input_receiver, self.receiver.signal_receiver)
+    ///
+    /// The receivers of a reclaimable `LoggingReceiver` are then no more given back on drop.
+    pub fn into_split(mut self) -> (mpsc::Receiver<Input>, mpsc::Receiver<RuntimeRequest>) {
+        self.reclaim = None;
+        let receiver = std::mem::replace(&mut self.receiver, CombinedReceiver::closed());
+        (receiver.input_receiver, receiver.signal_receiver)
     }
 
     /// Close the input so no new messages can be sent to this receiver
@@ -159,17 +198,37 @@ impl<Input: Debug> LoggingReceiver<Input> {
     }
 }
 
+impl<Input: Debug> Drop for LoggingReceiver<Input> {
+    fn drop(&mut self) {
+        if let Some(reclaim) = self.reclaim.take() {
+            let receiver = std::mem::replace(&mut self.receiver, CombinedReceiver::closed());
+            let mut slot = reclaim
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            *slot = Some(receiver);
+        }
+    }
+}
+
 #[async_trait]
 impl<Input: Send + Debug> MessageReceiver<Input> for LoggingReceiver<Input> {
     async fn try_recv(&mut self) -> Result<Option<Input>, RuntimeRequest> {
+        record_processing_time(&self.metrics, &mut self.processing_since);
         let message = self.receiver.try_recv().await;
         debug!(target: &self.name, "recv {:?}", message);
+        if let Ok(Some(_)) = &message {
+            record_reception(&self.metrics, &mut self.processing_since);
+        }
         message
     }
 
     async fn recv(&mut self) -> Option<Input> {
+        record_processing_time(&self.metrics, &mut self.processing_since);
         let message = self.receiver.recv().await;
         debug!(target: &self.name, "recv {:?}", message);
+        if message.is_some() {
+            record_reception(&self.metrics, &mut self.processing_since);
+        }
         message
     }
 
@@ -180,9 +239,22 @@ impl<Input: Send + Debug> MessageReceiver<Input> for LoggingReceiver<Input> {
     }
 }
 
+/// Record the time spent processing the last received message, if any
+fn record_processing_time(metrics: &MessageBoxMetrics, processing_since: &mut Option<Instant>) {
+    if let Some(since) = processing_since.take() {
+        metrics.message_processed(since.elapsed());
+    }
+}
+
+fn record_reception(metrics: &MessageBoxMetrics, processing_since: &mut Option<Instant>) {
+    metrics.message_received();
+    *processing_since = Some(Instant::now());
+}
+
 pub struct LoggingSender<Output> {
     name: String,
     sender: DynSender<Output>,
+    metrics: MessageBoxMetrics,
 }
 
 impl<Output: 'static> Clone for LoggingSender<Output> {
@@ -190,13 +262,28 @@ impl<Output: 'static> Clone for LoggingSender<Output> {
         LoggingSender {
             name: self.name.clone(),
             sender: self.sender.sender_clone(),
+            metrics: self.metrics.clone(),
         }
     }
 }
 
 impl<Output> LoggingSender<Output> {
     pub fn new(name: String, sender: DynSender<Output>) -> Self {
-        Self { name, sender }
+        let metrics = MessageBoxMetrics::register(&name);
+        Self::with_metrics(name, sender, metrics)
+    }
+
+    /// Create a sender recording the messages sent in the metrics of a message box
+    pub fn with_metrics(
+        name: String,
+        sender: DynSender<Output>,
+        metrics: MessageBoxMetrics,
+    ) -> Self {
+        Self {
+            name,
+            sender,
+            metrics,
+        }
     }
 }
 
@@ -204,7 +291,9 @@ impl<Output> LoggingSender<Output> {
 impl<Output: Message> Sender<Output> for LoggingSender<Output> {
     async fn send(&mut self, message: Output) -> Result<(), ChannelError> {
         log_message_sent(&self.name, &message);
-        self.sender.send(message).await
+        self.sender.send(message).await?;
+        self.metrics.message_sent();
+        Ok(())
     }
 }
 
@@ -220,6 +309,8 @@ pub struct UnboundedLoggingReceiver<Input: Debug> {
     name: String,
     input_receiver: mpsc::UnboundedReceiver<Input>,
     signal_receiver: mpsc::Receiver<RuntimeRequest>,
+    metrics: MessageBoxMetrics,
+    processing_since: Option<Instant>,
 }
 
 impl<Input: Debug> UnboundedLoggingReceiver<Input> {
@@ -228,10 +319,13 @@ impl<Input: Debug> UnboundedLoggingReceiver<Input> {
         input_receiver: mpsc::UnboundedReceiver<Input>,
         signal_receiver: mpsc::Receiver<RuntimeRequest>,
     ) -> Self {
+        let metrics = MessageBoxMetrics::register(&name);
         Self {
             name,
             input_receiver,
             signal_receiver,
+            metrics,
+            processing_since: None,
         }
     }
 
@@ -253,17 +347,25 @@ impl<Input: Debug> UnboundedLoggingReceiver<Input> {
 #[async_trait]
 impl<Input: Send + Debug> MessageReceiver<Input> for UnboundedLoggingReceiver<Input> {
     async fn try_recv(&mut self) -> Result<Option<Input>, RuntimeRequest> {
+        record_processing_time(&self.metrics, &mut self.processing_since);
         let message = self.next_message().await;
         debug!(target: &self.name, "recv {:?}", message);
+        if let Ok(Some(_)) = &message {
+            record_reception(&self.metrics, &mut self.processing_since);
+        }
         message
     }
 
     async fn recv(&mut self) -> Option<Input> {
+        record_processing_time(&self.metrics, &mut self.processing_since);
         let message = match self.next_message().await {
             Ok(Some(message)) => Some(message),
             _ => None,
         };
         debug!(target: &self.name, "recv {:?}", message);
+        if message.is_some() {
+            record_reception(&self.metrics, &mut self.processing_since);
+        }
         message
     }
 
@@ -364,6 +466,13 @@ impl<Input> CombinedReceiver<Input> {
         }
     }
 
+    /// A receiver with no senders
+    pub(crate) fn closed() -> Self {
+        let (_, input_receiver) = mpsc::channel(0);
+        let (_, signal_receiver) = mpsc::channel(0);
+        Self::new(input_receiver, signal_receiver)
+    }
+
     /// Close the input so no new messages can be sent to this receiver
     pub fn close_input(&mut self) {
         self.input_receiver.close();
@@ -0,0 +1,302 @@
+//! Statistics collected on the message boxes of the actors
+//!
+//! Each [LoggingReceiver](crate::LoggingReceiver) and [LoggingSender](crate::LoggingSender)
+//! records the number of messages received and sent as well as the time spent
+//! processing the received messages, i.e. the time elapsed between the reception of a message
+//! and the next call to `recv()`.
+//!
+//! The statistics are kept per message box instance, even when several message boxes are given the same name,
+//! and are discarded as soon as the message box and all the senders connected to it are dropped.
+//!
+//! The senders given to the peers by a [SimpleMessageBoxBuilder](crate::SimpleMessageBoxBuilder)
+//! or a [ServerMessageBoxBuilder](crate::ServerMessageBoxBuilder) also count the messages sent to the box,
+//! so the number of messages waiting in the queue can be computed.
+//!
+//! The statistics of all the message boxes of a process are returned by [message_box_stats],
+//! also available as [RuntimeHandle::message_box_stats](crate::RuntimeHandle::message_box_stats).
+use crate::ChannelError;
+use crate::DynSender;
+use crate::Message;
+use crate::Sender;
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::OnceLock;
+use std::sync::Weak;
+use std::time::Duration;
+
+/// A snapshot of the statistics of a message box
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MessageBoxStats {
+    /// The name of the message box, which is also the target used to log its messages
+    pub name: String,
+
+    /// The id of the message box, unique within the process
+    ///
+    /// Used to tell apart the message boxes given the same name.
+    pub id: u64,
+
+    /// The number of messages received by the actor
+    pub received: u64,
+
+    /// The number of messages sent by the actor
+    pub sent: u64,
+
+    /// The number of messages waiting to be received
+    ///
+    /// `None` when the messages sent to the box are not counted,
+    /// i.e. when the box has not been built by a [SimpleMessageBoxBuilder](crate::SimpleMessageBoxBuilder)
+    /// or a [ServerMessageBoxBuilder](crate::ServerMessageBoxBuilder).
+    pub queued: Option<u64>,
+
+    /// The time spent by the actor processing the received messages
+    pub processing_time: Duration,
+}
+
+/// The counters of a message box, shared by the receiver and the senders
+#[derive(Clone, Debug)]
+pub struct MessageBoxMetrics {
+    counters: Arc<Counters>,
+}
+
+#[derive(Debug)]
+struct Counters {
+    name: String,
+    id: u64,
+    received: AtomicU64,
+    sent: AtomicU64,
+    enqueued: AtomicU64,
+    counts_enqueued: AtomicBool,
+    processing_time_us: AtomicU64,
+}
+
+/// The counters of the live message boxes, indexed by message box id
+///
+/// The registry doesn't own the counters, which are unregistered when dropped.
+fn registry() -> MutexGuard<'static, BTreeMap<u64, Weak<Counters>>> {
+    static REGISTRY: OnceLock<Mutex<BTreeMap<u64, Weak<Counters>>>> = OnceLock::new();
+    REGISTRY
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Return the statistics of all the live message boxes of this process, sorted by name
+pub fn message_box_stats() -> Vec<MessageBoxStats> {
+    // The registry lock is released before the counters are possibly dropped, and so unregistered
+    let live_counters: Vec<Arc<Counters>> = registry().values().filter_map(Weak::upgrade).collect();
+    let mut stats: Vec<MessageBoxStats> = live_counters
+        .iter()
+        .map(|counters| counters.stats())
+        .collect();
+    stats.sort_by(|a, b| a.name.cmp(&b.name).then(a.id.cmp(&b.id)));
+    stats
+}
+
+impl MessageBoxMetrics {
+    /// Register the metrics of a new message box
+    ///
+    /// Each call returns new counters, even for a name already used by another message box.
+    /// These counters are unregistered when the last clone of the returned metrics is dropped.
+    pub fn register(name: &str) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let counters = Arc::new(Counters::new(name.to_string(), id));
+        registry().insert(id, Arc::downgrade(&counters));
+        MessageBoxMetrics { counters }
+    }
+
+    pub fn message_received(&self) {
+        self.counters.received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn message_sent(&self) {
+        self.counters.sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn message_enqueued(&self) {
+        self.counters.enqueued.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn message_processed(&self, processing_time: Duration) {
+        let micros = u64::try_from(processing_time.as_micros()).unwrap_or(u64::MAX);
+        self.counters
+            .processing_time_us
+            .fetch_add(micros, Ordering::Relaxed);
+    }
+
+    /// Wrap the sender of the input channel, so the messages sent to this box are counted
+    pub fn counting_sender<M: Message>(&self, sender: DynSender<M>) -> DynSender<M> {
+        self.counters.counts_enqueued.store(true, Ordering::Relaxed);
+        Box::new(CountingSender {
+            sender,
+            metrics: self.clone(),
+        })
+    }
+
+    /// A snapshot of the statistics of this message box
+    pub fn stats(&self) -> MessageBoxStats {
+        self.counters.stats()
+    }
+}
+
+impl Counters {
+    fn new(name: String, id: u64) -> Self {
+        Counters {
+            name,
+            id,
+            received: AtomicU64::default(),
+            sent: AtomicU64::default(),
+            enqueued: AtomicU64::default(),
+            counts_enqueued: AtomicBool::default(),
+            processing_time_us: AtomicU64::default(),
+        }
+    }
+
+    fn stats(&self) -> MessageBoxStats {
+        let received = self.received.load(Ordering::Relaxed);
+        let queued = self.counts_enqueued.load(Ordering::Relaxed).then(|| {
+            self.enqueued
+                .load(Ordering::Relaxed)
+                .saturating_sub(received)
+        });
+        MessageBoxStats {
+            name: self.name.clone(),
+            id: self.id,
+            received,
+            sent: self.sent.load(Ordering::Relaxed),
+            queued,
+            processing_time: Duration::from_micros(self.processing_time_us.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl Drop for Counters {
+    fn drop(&mut self) {
+        registry().remove(&self.id);
+    }
+}
+
+/// A sender counting the messages sent to a message box
+struct CountingSender<M> {
+    sender: DynSender<M>,
+    metrics: MessageBoxMetrics,
+}
+
+impl<M: 'static> Clone for CountingSender<M> {
+    fn clone(&self) -> Self {
+        CountingSender {
+            sender: self.sender.sender_clone(),
+            metrics: self.metrics.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl<M: Message> Sender<M> for CountingSender<M> {
+    async fn send(&mut self, message: M) -> Result<(), ChannelError> {
+        self.sender.send(message).await?;
+        self.metrics.message_enqueued();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Builder;
+    use crate::MessageReceiver;
+    use crate::MessageSink;
+    use crate::MessageSource;
+    use crate::SimpleMessageBoxBuilder;
+
+    fn stats_of(name: &str) -> MessageBoxStats {
+        message_box_stats()
+            .into_iter()
+            .find(|stats| stats.name == name)
+            .expect("stats for the message box")
+    }
+
+    #[tokio::test]
+    async fn message_boxes_count_messages() {
+        let name = "metrics-test-count";
+        let mut box_builder = SimpleMessageBoxBuilder::<u32, u32>::new(name, 16);
+        let output_builder = SimpleMessageBoxBuilder::<u32, u32>::new("metrics-test-output", 16);
+        box_builder.connect_sink(crate::NoConfig, &output_builder);
+        let mut input = box_builder.get_sender();
+        let mut message_box = box_builder.build();
+
+        for i in 0u32..3 {
+            input.send(i).await.unwrap();
+        }
+        assert_eq!(stats_of(name).queued, Some(3));
+
+        assert_eq!(message_box.recv().await, Some(0));
+        crate::Sender::send(&mut message_box, 42).await.unwrap();
+
+        let stats = stats_of(name);
+        assert_eq!(stats.received, 1);
+        assert_eq!(stats.sent, 1);
+        assert_eq!(stats.queued, Some(2));
+    }
+
+    #[tokio::test]
+    async fn processing_time_is_measured_between_receptions() {
+        let name = "metrics-test-processing";
+        let box_builder = SimpleMessageBoxBuilder::<u32, u32>::new(name, 16);
+        let mut input = box_builder.get_sender();
+        let mut message_box = box_builder.build();
+
+        input.send(1u32).await.unwrap();
+        input.send(2u32).await.unwrap();
+
+        message_box.recv().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        message_box.recv().await.unwrap();
+
+        assert!(stats_of(name).processing_time >= Duration::from_millis(20));
+    }
+
+    #[tokio::test]
+    async fn message_boxes_with_the_same_name_have_their_own_statistics() {
+        let name = "metrics-test-same-name";
+        let first_builder = SimpleMessageBoxBuilder::<u32, u32>::new(name, 16);
+        let second_builder = SimpleMessageBoxBuilder::<u32, u32>::new(name, 16);
+        let mut first_input = first_builder.get_sender();
+        let mut first_box = first_builder.build();
+        let _second_box = second_builder.build();
+
+        first_input.send(1u32).await.unwrap();
+        first_box.recv().await.unwrap();
+
+        let stats: Vec<_> = message_box_stats()
+            .into_iter()
+            .filter(|stats| stats.name == name)
+            .collect();
+        assert_eq!(stats.len(), 2);
+        assert_ne!(stats[0].id, stats[1].id);
+        let received: Vec<_> = stats.iter().map(|stats| stats.received).collect();
+        assert!(received.contains(&1) && received.contains(&0), "{stats:?}");
+    }
+
+    #[tokio::test]
+    async fn statistics_are_discarded_when_the_message_box_is_dropped() {
+        let name = "metrics-test-dropped";
+        let box_builder = SimpleMessageBoxBuilder::<u32, u32>::new(name, 16);
+        let input = box_builder.get_sender();
+        let message_box = box_builder.build();
+        let id = stats_of(name).id;
+
+        // The statistics are kept as long as a sender is connected to the message box
+        drop(message_box);
+        assert_eq!(stats_of(name).id, id);
+
+        drop(input);
+        assert!(message_box_stats().iter().all(|stats| stats.id != id));
+    }
+}
@@ -1,14 +1,19 @@
 //! Supervise the actors of an application
 //!
 use crate::run_actor::RunActor;
+use crate::supervision::RestartPolicy;
+use crate::supervision::RestartableBuilder;
+use crate::supervision::SupervisedActor;
 use crate::Actor;
 use crate::Builder;
 use crate::ChannelError;
 use crate::DynSender;
+use crate::MessageBoxStats;
 use crate::MessageSink;
 use crate::RuntimeError;
 use crate::RuntimeRequestSink;
 use futures::channel::mpsc;
+use futures::future::BoxFuture;
 use futures::prelude::*;
 use futures::stream::FuturesUnordered;
 use log::debug;
@@ -25,6 +30,7 @@ use tokio::task::JoinHandle;
 pub enum RuntimeAction {
     Shutdown,
     Spawn(RunActor),
+    Supervise(SupervisedActor),
 }
 
 /// Requests sent by the runtime to actors
@@ -40,6 +46,7 @@ pub enum RuntimeEvent {
     Started { task: String },
     Stopped { task: String },
     Aborted { task: String, error: String },
+    Restarted { task: String, restarts: usize },
 }
 
 /// The actor runtime
@@ -87,6 +94,19 @@ impl Runtime {
         self.handle.spawn(actor_builder).await
     }
 
+    /// Spawn an actor that is restarted when it fails, following the given policy
+    pub async fn spawn_supervised<T, A>(
+        &mut self,
+        actor_builder: T,
+        policy: RestartPolicy,
+    ) -> Result<(), RuntimeError>
+    where
+        T: RestartableBuilder<A>,
+        A: Actor,
+    {
+        self.handle.spawn_supervised(actor_builder, policy).await
+    }
+
     /// Run the runtime up to completion
     ///
     /// I.e until
@@ -136,6 +156,28 @@ impl RuntimeHandle {
         Ok(self.send(RuntimeAction::Spawn(run_actor)).await?)
     }
 
+    /// Spawn an actor that is restarted when it fails, following the given policy
+    pub async fn spawn_supervised<A, T>(
+        &mut self,
+        actor_builder: T,
+        policy: RestartPolicy,
+    ) -> Result<(), RuntimeError>
+    where
+        A: Actor,
+        T: RestartableBuilder<A>,
+    {
+        let supervised_actor = SupervisedActor::from_builder(actor_builder, policy);
+
+        Ok(self
+            .send(RuntimeAction::Supervise(supervised_actor))
+            .await?)
+    }
+
+    /// Return the statistics of the message boxes of all the actors
+    pub fn message_box_stats(&self) -> Vec<MessageBoxStats> {
+        crate::metrics::message_box_stats()
+    }
+
     /// Send an action to the runtime
     async fn send(&mut self, action: RuntimeAction) -> Result<(), ChannelError> {
         debug!(target: "Runtime", "schedule {:?}", action);
@@ -157,6 +199,9 @@ struct RuntimeActor {
     cleanup_duration: Duration,
     futures: FuturesUnordered<JoinHandle<Result<String, (String, RuntimeError)>>>,
     running_actors: HashMap<String, DynSender<RuntimeRequest>>,
+    supervised_actors: HashMap<String, SupervisedActor>,
+    pending_restarts: FuturesUnordered<BoxFuture<'static, String>>,
+    shutting_down: bool,
 }
 
 impl RuntimeActor {
@@ -171,6 +216,9 @@ impl RuntimeActor {
             cleanup_duration,
             futures: FuturesUnordered::new(),
             running_actors: HashMap::default(),
+            supervised_actors: HashMap::default(),
+            pending_restarts: FuturesUnordered::new(),
+            shutting_down: false,
         }
     }
 
@@ -186,25 +234,35 @@ impl RuntimeActor {
                             match action {
                                 RuntimeAction::Spawn(actor) => {
                                     let running_name = format!("{}-{}", actor.name(), actors_count);
-                                    info!(target: "Runtime", "Running {running_name}");
-                                    self.send_event(RuntimeEvent::Started {
-                                        task: running_name.clone(),
-                                    })
-                                    .await;
-                                    self.running_actors.insert(running_name.clone(), actor.get_signal_sender());
-                                    self.futures.push(tokio::spawn(run_task(actor, running_name)));
+                                    self.start_actor(actor, running_name).await;
                                     actors_count += 1;
                                }
+                               RuntimeAction::Supervise(mut supervised_actor) => {
+                                    match supervised_actor.build() {
+                                        Ok(actor) => {
+                                            let running_name = format!("{}-{}", actor.name(), actors_count);
+                                            self.supervised_actors.insert(running_name.clone(), supervised_actor);
+                                            self.start_actor(actor, running_name).await;
+                                            actors_count += 1;
+                                        }
+                                        Err(error) => {
+                                            error!(target: "Runtime", "Failed to build {supervised_actor:?}: {error}");
+                                            aborting_error = Some(error);
+                                            self.shutdown().await;
+                                            break
+                                        }
+                                    }
+                               }
                                RuntimeAction::Shutdown => {
                                     info!(target: "Runtime", "Shutting down");
-                                    shutdown_actors(&mut self.running_actors).await;
+                                    self.shutdown().await;
                                     break;
                                }
                             }
                         }
                         None => {
                             info!(target: "Runtime", "Runtime actions channel closed, runtime stopping");
-                            shutdown_actors(&mut self.running_actors).await;
+                            self.shutdown().await;
                             break;
                         }
                     }
@@ -213,7 +271,15 @@ impl RuntimeActor {
                     if let Err(error) = self.handle_actor_finishing(finished_actor).await {
                         info!(target: "Runtime", "Shutting down on error: {error}");
                         aborting_error = Some(error);
-                        shutdown_actors(&mut self.running_actors).await;
+                        self.shutdown().await;
+                        break
+                    }
+                }
+                Some(restarting_actor) = self.pending_restarts.next() => {
+                    if let Err(error) = self.restart_actor(restarting_actor).await {
+                        info!(target: "Runtime", "Shutting down on error: {error}");
+                        aborting_error = Some(error);
+                        self.shutdown().await;
                         break
                     }
                 }
@@ -236,6 +302,63 @@ impl RuntimeActor {
         }
     }
 
+    async fn start_actor(&mut self, actor: RunActor, running_name: String) {
+        info!(target: "Runtime", "Running {running_name}");
+        self.send_event(RuntimeEvent::Started {
+            task: running_name.clone(),
+        })
+        .await;
+        self.running_actors
+            .insert(running_name.clone(), actor.get_signal_sender());
+        self.futures
+            .push(tokio::spawn(run_task(actor, running_name)));
+    }
+
+    /// Rebuild and start a supervised actor that failed
+    async fn restart_actor(&mut self, running_name: String) -> Result<(), RuntimeError> {
+        let Some(supervised_actor) = self.supervised_actors.get_mut(&running_name) else {
+            return Ok(());
+        };
+        let restarts = supervised_actor.restart_count();
+        let actor = supervised_actor.build()?;
+        info!(target: "Runtime", "Restarting {running_name} ({restarts} restarts)");
+        self.start_actor(actor, running_name.clone()).await;
+        self.send_event(RuntimeEvent::Restarted {
+            task: running_name,
+            restarts,
+        })
+        .await;
+        Ok(())
+    }
+
+    /// Schedule the restart of a failed actor, if supervised and not restarted too often
+    fn schedule_restart(&mut self, running_name: &str) -> bool {
+        if self.shutting_down {
+            return false;
+        }
+        let Some(supervised_actor) = self.supervised_actors.get_mut(running_name) else {
+            return false;
+        };
+        let Some(delay) = supervised_actor.schedule_restart(tokio::time::Instant::now()) else {
+            error!(target: "Runtime", "{running_name} has been restarted too many times");
+            return false;
+        };
+
+        info!(target: "Runtime", "Restarting {running_name} in {delay:?}");
+        let running_name = running_name.to_string();
+        self.pending_restarts.push(Box::pin(async move {
+            tokio::time::sleep(delay).await;
+            running_name
+        }));
+        true
+    }
+
+    async fn shutdown(&mut self) {
+        self.shutting_down = true;
+        self.pending_restarts = FuturesUnordered::new();
+        shutdown_actors(&mut self.running_actors).await;
+    }
+
     async fn wait_for_actors_to_finish(&mut self) {
         while let Some(finished_actor) = self.futures.next().await {
             let _ = self.handle_actor_finishing(finished_actor).await;
@@ -253,6 +376,7 @@ impl RuntimeActor {
             }
             Ok(Ok(actor)) => {
                 self.running_actors.remove(&actor);
+                self.supervised_actors.remove(&actor);
                 info!(target: "Runtime", "Actor has finished: {actor}");
                 self.send_event(RuntimeEvent::Stopped { task: actor }).await;
                 Ok(())
@@ -265,6 +389,9 @@ impl RuntimeActor {
                     error: format!("{error}"),
                 })
                 .await;
+                if self.schedule_restart(&actor) {
+                    return Ok(());
+                }
                 Err(error)
             }
         }
@@ -309,10 +436,13 @@ mod tests {
     use super::*;
     use crate::fan_in_message_type;
     use crate::message_boxes::MessageReceiver;
+    use crate::LinkError;
     use crate::LoggingReceiver;
     use crate::LoggingSender;
     use crate::Message;
+    use crate::NoConfig;
     use crate::SimpleMessageBox;
+    use crate::SimpleMessageBoxBuilder;
     use async_trait::async_trait;
     use futures::channel::mpsc;
     use std::time::Duration;
@@ -396,6 +526,113 @@ mod tests {
         }
     }
 
+    /// An actor failing on its first runs
+    struct Failing {
+        failures: usize,
+    }
+
+    #[async_trait]
+    impl Actor for Failing {
+        fn name(&self) -> &str {
+            "Failing"
+        }
+
+        async fn run(self) -> Result<(), RuntimeError> {
+            if self.failures > 0 {
+                return Err(RuntimeError::ActorError(
+                    format!("failing {} more times", self.failures - 1).into(),
+                ));
+            }
+            Ok(())
+        }
+    }
+
+    struct FailingBuilder {
+        failures: usize,
+        signal_sender: mpsc::Sender<RuntimeRequest>,
+    }
+
+    impl FailingBuilder {
+        fn new(failures: usize) -> Self {
+            let (signal_sender, _) = mpsc::channel(16);
+            FailingBuilder {
+                failures,
+                signal_sender,
+            }
+        }
+    }
+
+    impl RuntimeRequestSink for FailingBuilder {
+        fn get_signal_sender(&self) -> DynSender<RuntimeRequest> {
+            Box::new(self.signal_sender.clone())
+        }
+    }
+
+    impl RestartableBuilder<Failing> for FailingBuilder {
+        type Error = std::convert::Infallible;
+
+        fn rebuild(&mut self) -> Result<Failing, Self::Error> {
+            let actor = Failing {
+                failures: self.failures,
+            };
+            self.failures = self.failures.saturating_sub(1);
+            Ok(actor)
+        }
+    }
+
+    /// An actor replying to the messages it receives, tagged with its instance number, and crashing on `"crash"`
+    struct Crashing {
+        instance: usize,
+        messages: SimpleMessageBox<String, String>,
+    }
+
+    #[async_trait]
+    impl Actor for Crashing {
+        fn name(&self) -> &str {
+            "Crashing"
+        }
+
+        async fn run(mut self) -> Result<(), RuntimeError> {
+            while let Some(message) = self.messages.recv().await {
+                if message == "crash" {
+                    return Err(RuntimeError::ActorError("crashed".into()));
+                }
+                let reply = format!("{message} #{}", self.instance);
+                crate::Sender::send(&mut self.messages, reply).await?;
+            }
+            Ok(())
+        }
+    }
+
+    struct CrashingBuilder {
+        instances: usize,
+        messages: SimpleMessageBoxBuilder<String, String>,
+    }
+
+    impl RuntimeRequestSink for CrashingBuilder {
+        fn get_signal_sender(&self) -> DynSender<RuntimeRequest> {
+            self.messages.get_signal_sender()
+        }
+    }
+
+    impl RestartableBuilder<Crashing> for CrashingBuilder {
+        type Error = LinkError;
+
+        fn rebuild(&mut self) -> Result<Crashing, Self::Error> {
+            self.instances += 1;
+            Ok(Crashing {
+                instance: self.instances,
+                messages: self.messages.rebuild()?,
+            })
+        }
+    }
+
+    fn quick_restarts(max_restarts: usize) -> RestartPolicy {
+        RestartPolicy::one_for_one()
+            .with_max_restarts(max_restarts, Duration::from_secs(60))
+            .with_backoff(Duration::from_millis(1), Duration::from_millis(10))
+    }
+
     fn create_actor<ActorBuilder, A, Input, Output>(
         actor: ActorBuilder,
     ) -> (mpsc::Sender<Input>, mpsc::Receiver<Output>, RunActor)
@@ -604,4 +841,159 @@ mod tests {
             EchoMessage::String("Echo stopped".into())
         );
     }
+
+    #[tokio::test]
+    async fn supervised_actors_are_restarted_on_failure() {
+        let (mut actions_sender, mut events_receiver, ra) = init();
+        let supervised_actor =
+            SupervisedActor::from_builder(FailingBuilder::new(2), quick_restarts(3));
+
+        actions_sender
+            .send(RuntimeAction::Supervise(supervised_actor))
+            .await
+            .unwrap();
+
+        let wait_for_actor_to_stop = async {
+            let mut restarts = vec![];
+            while let Some(event) = events_receiver.next().await {
+                match event {
+                    RuntimeEvent::Restarted {
+                        task,
+                        restarts: count,
+                    } => {
+                        assert_eq!(task, "Failing-0");
+                        restarts.push(count);
+                    }
+                    RuntimeEvent::Stopped { task } => {
+                        assert_eq!(task, "Failing-0");
+                        return restarts;
+                    }
+                    _ => {}
+                }
+            }
+            restarts
+        };
+
+        tokio::spawn(ra.run());
+
+        let restarts = tokio::time::timeout(Duration::from_secs(1), wait_for_actor_to_stop)
+            .await
+            .expect("Actor to be restarted and to stop in time");
+        assert_eq!(restarts, vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn runtime_stops_when_a_supervised_actor_is_restarted_too_often() {
+        let (mut actions_sender, mut events_receiver, ra) = init();
+        let supervised_actor =
+            SupervisedActor::from_builder(FailingBuilder::new(5), quick_restarts(2));
+        let (_, mut receiver, echo_actor) = create_actor(Echo::new);
+
+        actions_sender
+            .send(RuntimeAction::Supervise(supervised_actor))
+            .await
+            .unwrap();
+        actions_sender
+            .send(RuntimeAction::Spawn(echo_actor))
+            .await
+            .unwrap();
+
+        let result = tokio::time::timeout(Duration::from_secs(1), ra.run())
+            .await
+            .expect("Runtime to stop in time");
+        assert_eq!(result.unwrap_err().to_string(), "failing 2 more times");
+
+        let mut restarts = 0;
+        while let Ok(Some(event)) = events_receiver.try_next() {
+            if matches!(event, RuntimeEvent::Restarted { .. }) {
+                restarts += 1;
+            }
+        }
+        assert_eq!(restarts, 2);
+
+        // The other actors have been shutdown
+        assert_eq!(
+            receiver.next().await.unwrap(),
+            EchoMessage::String("Echo stopped".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn a_restarted_actor_takes_over_the_message_box_while_the_other_actors_keep_running() {
+        let (mut actions_sender, mut events_receiver, ra) = init();
+        let mut crashing_builder = CrashingBuilder {
+            instances: 0,
+            messages: SimpleMessageBoxBuilder::new("Crashing", 16),
+        };
+        let mut client = SimpleMessageBoxBuilder::<String, String>::new("Client", 16)
+            .with_connection(NoConfig, &mut crashing_builder.messages)
+            .build();
+        let (mut echo_sender, mut echo_receiver, echo_actor) = create_actor(Echo::new);
+
+        actions_sender
+            .send(RuntimeAction::Supervise(SupervisedActor::from_builder(
+                crashing_builder,
+                quick_restarts(3),
+            )))
+            .await
+            .unwrap();
+        actions_sender
+            .send(RuntimeAction::Spawn(echo_actor))
+            .await
+            .unwrap();
+        tokio::spawn(ra.run());
+
+        let test = async {
+            crate::Sender::send(&mut client, "hello".to_string())
+                .await
+                .unwrap();
+            assert_eq!(client.recv().await.unwrap(), "hello #1");
+
+            // The message sent after the crash is queued and received by the new instance
+            crate::Sender::send(&mut client, "crash".to_string())
+                .await
+                .unwrap();
+            crate::Sender::send(&mut client, "queued".to_string())
+                .await
+                .unwrap();
+
+            // Meanwhile, the other actors are not impacted
+            echo_sender
+                .send(EchoMessage::String("ping".to_string()))
+                .await
+                .unwrap();
+            assert_eq!(
+                echo_receiver.next().await.unwrap(),
+                EchoMessage::String("ping".to_string())
+            );
+
+            assert_eq!(client.recv().await.unwrap(), "queued #2");
+
+            loop {
+                match events_receiver.next().await.unwrap() {
+                    RuntimeEvent::Restarted { task, restarts } => {
+                        assert_eq!(task, "Crashing-0");
+                        assert_eq!(restarts, 1);
+                        break;
+                    }
+                    RuntimeEvent::Aborted { task, .. } => assert_eq!(task, "Crashing-0"),
+                    RuntimeEvent::Stopped { task } => panic!("{task} unexpectedly stopped"),
+                    _ => {}
+                }
+            }
+
+            echo_sender
+                .send(EchoMessage::String("pong".to_string()))
+                .await
+                .unwrap();
+            assert_eq!(
+                echo_receiver.next().await.unwrap(),
+                EchoMessage::String("pong".to_string())
+            );
+        };
+
+        tokio::time::timeout(Duration::from_secs(1), test)
+            .await
+            .expect("The crashing actor to be restarted in time");
+    }
 }
@@ -52,7 +52,9 @@ impl<Request: Message, Response: Message> ServerMessageBoxBuilder<Request, Respo
 
     /// Return a sender for the requests
     pub fn request_sender(&self) -> DynSender<RequestEnvelope<Request, Response>> {
-        self.request_sender.sender_clone()
+        self.request_receiver
+            .metrics()
+            .counting_sender(self.request_sender.sender_clone())
     }
 
     /// Build a message box ready to be used by the server actor
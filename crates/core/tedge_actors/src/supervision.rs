@@ -0,0 +1,265 @@
+//! Restart the actors that fail
+//!
+//! By default, the [Runtime](crate::Runtime) stops all the actors as soon as one of them fails.
+//! An actor spawned with [spawn_supervised](crate::Runtime::spawn_supervised)
+//! is instead restarted, following a [RestartPolicy]:
+//!
+//! - only the failing actor is restarted, the other actors being left untouched (one-for-one),
+//! - the restarts are delayed by an exponential backoff,
+//! - the runtime is stopped if the actor fails too often, i.e. more than `max_restarts` times within a time window.
+//!
+//! Restarting an actor requires a [RestartableBuilder],
+//! that can build a fresh instance of the actor each time the previous one failed.
+use crate::run_actor::RunActor;
+use crate::Actor;
+use crate::DynSender;
+use crate::RuntimeError;
+use crate::RuntimeRequest;
+use crate::RuntimeRequestSink;
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::marker::PhantomData;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Builder of an actor that can be restarted
+///
+/// Contrary to a [Builder](crate::Builder) which is consumed when building an actor,
+/// a restartable builder is kept by the runtime to build a new instance of the actor
+/// each time the previous one failed.
+///
+/// The new instance has to take over the message boxes of the failed one, using the same channels,
+/// so its peers are not impacted by the restart.
+/// This is done by building the message boxes with [SimpleMessageBoxBuilder::rebuild](crate::SimpleMessageBoxBuilder::rebuild).
+pub trait RestartableBuilder<A: Actor>: RuntimeRequestSink + Send + 'static {
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Build a new instance of the actor
+    ///
+    /// This method is called once when the actor is spawned, then once per restart.
+    fn rebuild(&mut self) -> Result<A, Self::Error>;
+}
+
+/// How an actor is restarted when it fails
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RestartPolicy {
+    /// The maximum number of restarts within the `within` time window
+    ///
+    /// When exceeded, the actor is no more restarted and the runtime is stopped.
+    pub max_restarts: usize,
+
+    /// The time window over which the restarts are counted
+    pub within: Duration,
+
+    /// The delay before the first restart, doubled on each consecutive restart
+    pub initial_backoff: Duration,
+
+    /// The maximum delay before a restart
+    pub max_backoff: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        RestartPolicy::one_for_one()
+    }
+}
+
+impl RestartPolicy {
+    /// Restart the failing actor, and only this actor,
+    /// up to 5 times per minute, waiting from 1 to 30 seconds between restarts.
+    pub fn one_for_one() -> Self {
+        RestartPolicy {
+            max_restarts: 5,
+            within: Duration::from_secs(60),
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+
+    pub fn with_max_restarts(self, max_restarts: usize, within: Duration) -> Self {
+        RestartPolicy {
+            max_restarts,
+            within,
+            ..self
+        }
+    }
+
+    pub fn with_backoff(self, initial_backoff: Duration, max_backoff: Duration) -> Self {
+        RestartPolicy {
+            initial_backoff,
+            max_backoff,
+            ..self
+        }
+    }
+
+    /// The delay before a restart, given the number of restarts already done within the time window
+    pub fn backoff(&self, previous_restarts: usize) -> Duration {
+        let factor = 1u32
+            .checked_shl(previous_restarts as u32)
+            .unwrap_or(u32::MAX);
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// An actor spawned with a restart policy
+pub struct SupervisedActor {
+    // The mutex is only there to make the supervised actor `Sync`, as required for a runtime action
+    factory: Mutex<Box<dyn ActorFactory>>,
+    policy: RestartPolicy,
+    restarts: VecDeque<Instant>,
+}
+
+impl SupervisedActor {
+    pub fn from_builder<A, T>(actor_builder: T, policy: RestartPolicy) -> Self
+    where
+        A: Actor,
+        T: RestartableBuilder<A>,
+    {
+        SupervisedActor {
+            factory: Mutex::new(Box::new(Rebuild {
+                builder: actor_builder,
+                actor: PhantomData,
+            })),
+            policy,
+            restarts: VecDeque::new(),
+        }
+    }
+
+    /// Build a new instance of the supervised actor
+    pub(crate) fn build(&mut self) -> Result<RunActor, RuntimeError> {
+        self.factory().build()
+    }
+
+    fn factory(&mut self) -> &mut Box<dyn ActorFactory> {
+        self.factory
+            .get_mut()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Register a failure of the actor
+    ///
+    /// Return the delay after which the actor has to be restarted,
+    /// or `None` if the actor has been restarted too many times.
+    pub(crate) fn schedule_restart(&mut self, now: Instant) -> Option<Duration> {
+        let window = self.policy.within;
+        while let Some(restart) = self.restarts.front() {
+            if now.saturating_duration_since(*restart) > window {
+                self.restarts.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        let previous_restarts = self.restarts.len();
+        if previous_restarts >= self.policy.max_restarts {
+            return None;
+        }
+        self.restarts.push_back(now);
+        Some(self.policy.backoff(previous_restarts))
+    }
+
+    /// The number of restarts within the current time window
+    pub(crate) fn restart_count(&self) -> usize {
+        self.restarts.len()
+    }
+}
+
+impl Debug for SupervisedActor {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        let factory = self
+            .factory
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        write!(f, "{} (supervised)", factory.name())
+    }
+}
+
+/// Type-erased [RestartableBuilder]
+trait ActorFactory: Send {
+    fn name(&self) -> &str;
+
+    fn build(&mut self) -> Result<RunActor, RuntimeError>;
+}
+
+struct Rebuild<A, T> {
+    builder: T,
+    actor: PhantomData<fn() -> A>,
+}
+
+impl<A, T> ActorFactory for Rebuild<A, T>
+where
+    A: Actor,
+    T: RestartableBuilder<A>,
+{
+    fn name(&self) -> &str {
+        std::any::type_name::<A>()
+    }
+
+    fn build(&mut self) -> Result<RunActor, RuntimeError> {
+        let runtime_request_sender: DynSender<RuntimeRequest> = self.builder.get_signal_sender();
+        let actor = self
+            .builder
+            .rebuild()
+            .map_err(|err| RuntimeError::ActorError(Box::new(err)))?;
+        Ok(RunActor::new(Box::new(actor), runtime_request_sender))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RestartPolicy {
+        RestartPolicy::one_for_one()
+            .with_max_restarts(3, Duration::from_secs(10))
+            .with_backoff(Duration::from_millis(100), Duration::from_millis(300))
+    }
+
+    #[test]
+    fn backoff_is_doubled_up_to_a_maximum() {
+        let policy = policy();
+        assert_eq!(policy.backoff(0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(1), Duration::from_millis(200));
+        assert_eq!(policy.backoff(2), Duration::from_millis(300));
+        assert_eq!(policy.backoff(100), Duration::from_millis(300));
+    }
+
+    #[test]
+    fn restarts_are_limited_within_the_time_window() {
+        let mut supervised = SupervisedActor {
+            factory: Mutex::new(Box::new(NoFactory)),
+            policy: policy(),
+            restarts: VecDeque::new(),
+        };
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            assert!(supervised.schedule_restart(start).is_some());
+        }
+        assert_eq!(supervised.schedule_restart(start), None);
+
+        // Once the time window is over, the actor can be restarted again
+        let later = start + Duration::from_secs(11);
+        assert_eq!(
+            supervised.schedule_restart(later),
+            Some(Duration::from_millis(100))
+        );
+        assert_eq!(supervised.restart_count(), 1);
+    }
+
+    struct NoFactory;
+
+    impl ActorFactory for NoFactory {
+        fn name(&self) -> &str {
+            "NoFactory"
+        }
+
+        fn build(&mut self) -> Result<RunActor, RuntimeError> {
+            Err(RuntimeError::RuntimeCancellation)
+        }
+    }
+}
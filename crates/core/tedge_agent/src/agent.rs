@@ -1,14 +1,23 @@
+use crate::alarm_manager::builder::AlarmManagerBuilder;
+use crate::alarm_manager::config::AlarmManagerConfig;
+use crate::cert_renewal_manager::builder::CertRenewalManagerBuilder;
+use crate::cert_renewal_manager::config::CertRenewalConfig;
 use crate::device_profile_manager::DeviceProfileManagerBuilder;
 use crate::entity_manager;
 use crate::entity_manager::server::EntityStoreRequest;
 use crate::entity_manager::server::EntityStoreServer;
+use crate::http_server::access_control::AccessPolicy;
 use crate::http_server::actor::HttpServerBuilder;
 use crate::http_server::actor::HttpServerConfig;
+use crate::inventory_collector::builder::InventoryCollectorBuilder;
+use crate::inventory_collector::config::InventoryConfig;
 use crate::operation_file_cache::FileCacheActorBuilder;
 use crate::operation_workflows::OperationConfig;
 use crate::operation_workflows::WorkflowActorBuilder;
 use crate::restart_manager::builder::RestartManagerBuilder;
 use crate::restart_manager::config::RestartManagerConfig;
+use crate::shell_command_manager::builder::ShellCommandManagerBuilder;
+use crate::shell_command_manager::config::ShellCommandConfig;
 use crate::software_manager::builder::SoftwareManagerBuilder;
 use crate::software_manager::config::SoftwareManagerConfig;
 use crate::state_repository::state::agent_default_state_dir;
@@ -35,6 +44,7 @@ use tedge_actors::MessageSource;
 use tedge_actors::NoConfig;
 use tedge_actors::NullSender;
 use tedge_actors::RequestEnvelope;
+use tedge_actors::RestartPolicy;
 use tedge_actors::Runtime;
 use tedge_actors::Sequential;
 use tedge_actors::ServerActorBuilder;
@@ -52,10 +62,15 @@ use tedge_config_manager::ConfigManagerConfig;
 use tedge_config_manager::ConfigManagerOptions;
 use tedge_downloader_ext::DownloaderActor;
 use tedge_file_system_ext::FsWatchActorBuilder;
+use tedge_health_ext::CommandBacklogCheck;
+use tedge_health_ext::DiskSpaceCheck;
 use tedge_health_ext::HealthMonitorBuilder;
+use tedge_health_ext::MqttConnectionCheck;
 use tedge_log_manager::LogManagerBuilder;
 use tedge_log_manager::LogManagerConfig;
 use tedge_log_manager::LogManagerOptions;
+use tedge_metrics_ext::MetricsServerBuilder;
+use tedge_metrics_ext::MetricsServerConfig;
 use tedge_mqtt_ext::MqttActorBuilder;
 use tedge_mqtt_ext::MqttConfig;
 use tedge_mqtt_ext::TopicFilter;
@@ -73,7 +88,12 @@ pub const TEDGE_AGENT: &str = "tedge-agent";
 pub(crate) struct AgentConfig {
     pub mqtt_config: MqttConfig,
     pub http_config: HttpServerConfig,
+    pub metrics_config: Option<MetricsServerConfig>,
+    pub inventory_config: Option<InventoryConfig>,
+    pub alarm_manager_config: Option<AlarmManagerConfig>,
     pub restart_config: RestartManagerConfig,
+    pub cert_renewal_config: CertRenewalConfig,
+    pub shell_command_config: ShellCommandConfig,
     pub sw_update_config: SoftwareManagerConfig,
     pub operation_config: OperationConfig,
     pub config_dir: Utf8PathBuf,
@@ -123,7 +143,7 @@ impl AgentConfig {
         let mqtt_session_name = format!("{TEDGE_AGENT}#{mqtt_topic_root}/{mqtt_device_topic_id}");
 
         let mqtt_config = tedge_config
-            .mqtt_config()?
+            .service_mqtt_config(TEDGE_AGENT)?
             .with_session_name(mqtt_session_name);
 
         // Tedge HTTP config
@@ -142,13 +162,57 @@ impl AgentConfig {
             key_path: tedge_config.http.key_path.clone(),
             ca_path: tedge_config.http.ca_path.clone(),
             bind_addr: SocketAddr::from((http_bind_address, http_port)),
+            access_policy: tedge_config
+                .http
+                .access_policy_path
+                .or_none()
+                .map(|path| AccessPolicy::load(path))
+                .transpose()?,
         };
 
+        // Metrics endpoint config
+        let metrics_config = tedge_config
+            .agent
+            .metrics
+            .port
+            .or_none()
+            .map(|port| MetricsServerConfig::from_tedge_config(&tedge_config, *port));
+
+        // Inventory config
+        let inventory_config = tedge_config.agent.inventory.enable.then(|| {
+            InventoryConfig::from_tedge_config(
+                MqttSchema::with_root(mqtt_topic_root.to_string()),
+                &mqtt_device_topic_id,
+                &config_dir,
+                &tedge_config,
+            )
+        });
+
+        // Alarm manager config
+        let alarm_manager_config = tedge_config.agent.alarms.enable.then(|| {
+            AlarmManagerConfig::from_tedge_config(
+                MqttSchema::with_root(mqtt_topic_root.to_string()),
+                &mqtt_device_topic_id,
+                &tedge_config,
+            )
+        });
+
         // Restart config
         let restart_config =
             RestartManagerConfig::from_tedge_config(&mqtt_device_topic_id, tedge_config_location)
                 .await?;
 
+        // Certificate renewal config
+        let cert_renewal_config = CertRenewalConfig::from_tedge_config(
+            MqttSchema::with_root(mqtt_topic_root.to_string()),
+            &mqtt_device_topic_id,
+            &tedge_config,
+        );
+
+        // Command operation config
+        let shell_command_config =
+            ShellCommandConfig::from_tedge_config(&tedge_config, mqtt_config.max_packet_size);
+
         // Software update config
         let sw_update_config =
             SoftwareManagerConfig::from_tedge_config(tedge_config_location).await?;
@@ -179,6 +243,7 @@ impl AgentConfig {
             config_update: tedge_config.agent.enable.config_update,
             config_snapshot: tedge_config.agent.enable.config_snapshot,
             log_upload: tedge_config.agent.enable.log_upload,
+            command: tedge_config.agent.enable.command,
         };
         let fts_url = format!(
             "{}:{}",
@@ -192,7 +257,12 @@ impl AgentConfig {
         Ok(Self {
             mqtt_config,
             http_config,
+            metrics_config,
+            inventory_config,
+            alarm_manager_config,
             restart_config,
+            cert_renewal_config,
+            shell_command_config,
             sw_update_config,
             operation_config,
             config_dir,
@@ -303,12 +373,27 @@ impl Agent {
             device_topic_id: DeviceTopicId::new(self.config.mqtt_device_topic_id.clone()),
         };
         let mqtt_schema = MqttSchema::with_root(self.config.mqtt_topic_root.to_string());
-        let health_actor = HealthMonitorBuilder::from_service_topic_id(
+        let mut health_actor = HealthMonitorBuilder::from_service_topic_id(
             service,
             &mut mqtt_actor_builder,
             &mqtt_schema,
             &self.config.service,
         );
+        health_actor.add_check(
+            "mqtt",
+            MqttConnectionCheck::new(mqtt_actor_builder.connection_status()),
+            &mut mqtt_actor_builder,
+        );
+        health_actor.add_check(
+            "disk",
+            DiskSpaceCheck::new(Utf8PathBuf::from(self.config.data_dir.clone())),
+            &mut mqtt_actor_builder,
+        );
+        health_actor.add_check(
+            "commands",
+            CommandBacklogCheck::new(&mqtt_schema, &self.config.mqtt_device_topic_id),
+            &mut mqtt_actor_builder,
+        );
 
         let mut downloader_actor_builder = DownloaderActor::new(
             self.config.identity.clone(),
@@ -367,6 +452,20 @@ impl Agent {
             None
         };
 
+        let shell_command_builder = if self.config.capabilities.command {
+            let mut shell_command_builder =
+                ShellCommandManagerBuilder::new(self.config.shell_command_config);
+            converter_actor_builder.register_builtin_operation(&mut shell_command_builder);
+            Some(shell_command_builder)
+        } else {
+            None
+        };
+
+        let inventory_builder = self
+            .config
+            .inventory_config
+            .map(|config| InventoryCollectorBuilder::new(config, &mqtt_actor_builder));
+
         // TODO: replace with a call to entity store when we stop assuming default MQTT schema
         let is_main_device =
             self.config.mqtt_device_topic_id == EntityTopicId::default_main_device();
@@ -408,11 +507,28 @@ impl Agent {
                 },
             );
 
-            let file_transfer_server_builder = HttpServerBuilder::try_bind(
+            // The alarm manager is exposed over HTTP, hence only available on the main device
+            let mut alarm_manager_builder = self
+                .config
+                .alarm_manager_config
+                .map(|config| AlarmManagerBuilder::new(config, &mut mqtt_actor_builder));
+
+            let mut file_transfer_server_builder = HttpServerBuilder::try_bind(
                 self.config.http_config,
                 &mut entity_store_actor_builder,
             )
             .await?;
+            if let Some(alarm_manager_builder) = alarm_manager_builder.as_mut() {
+                file_transfer_server_builder =
+                    file_transfer_server_builder.with_alarm_manager(alarm_manager_builder);
+            }
+
+            // The device certificate is only used by the main device to connect the cloud
+            let mut cert_renewal_builder = CertRenewalManagerBuilder::new(
+                self.config.cert_renewal_config,
+                &mut mqtt_actor_builder,
+            );
+            converter_actor_builder.register_builtin_operation(&mut cert_renewal_builder);
 
             let operation_file_cache_builder = FileCacheActorBuilder::new(
                 mqtt_schema,
@@ -425,8 +541,17 @@ impl Agent {
             runtime.spawn(file_transfer_server_builder).await?;
             runtime.spawn(entity_store_actor_builder).await?;
             runtime.spawn(operation_file_cache_builder).await?;
+            runtime.spawn(cert_renewal_builder).await?;
+            if let Some(alarm_manager_builder) = alarm_manager_builder {
+                runtime
+                    .spawn_supervised(alarm_manager_builder, RestartPolicy::one_for_one())
+                    .await?;
+            }
         } else {
             info!("Running as a child device, tedge_to_te_converter and File Transfer Service disabled");
+            if self.config.alarm_manager_config.is_some() {
+                warn!("Alarm management is only supported by the agent of the main device");
+            }
         }
 
         // Spawn all
@@ -435,17 +560,38 @@ impl Agent {
         runtime.spawn(fs_watch_actor_builder).await?;
         runtime.spawn(downloader_actor_builder).await?;
         runtime.spawn(uploader_actor_builder).await?;
+        // The operation managers are restarted on failure, the other actors keeping running
         if let Some(config_actor_builder) = config_actor_builder {
-            runtime.spawn(config_actor_builder).await?;
+            runtime
+                .spawn_supervised(config_actor_builder, RestartPolicy::one_for_one())
+                .await?;
         }
         if let Some(log_actor_builder) = log_actor_builder {
-            runtime.spawn(log_actor_builder).await?;
+            runtime
+                .spawn_supervised(log_actor_builder, RestartPolicy::one_for_one())
+                .await?;
+        }
+        if let Some(shell_command_builder) = shell_command_builder {
+            runtime
+                .spawn_supervised(shell_command_builder, RestartPolicy::one_for_one())
+                .await?;
+        }
+        if let Some(inventory_builder) = inventory_builder {
+            runtime
+                .spawn_supervised(inventory_builder, RestartPolicy::one_for_one())
+                .await?;
         }
         runtime.spawn(restart_actor_builder).await?;
-        runtime.spawn(software_update_builder).await?;
+        runtime
+            .spawn_supervised(software_update_builder, RestartPolicy::one_for_one())
+            .await?;
         runtime.spawn(script_runner).await?;
         runtime.spawn(converter_actor_builder).await?;
         runtime.spawn(health_actor).await?;
+        if let Some(metrics_config) = self.config.metrics_config {
+            let metrics_server_builder = MetricsServerBuilder::try_bind(metrics_config).await?;
+            runtime.spawn(metrics_server_builder).await?;
+        }
 
         runtime.run_to_completion().await?;
 
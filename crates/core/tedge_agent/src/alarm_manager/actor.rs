@@ -0,0 +1,283 @@
+use crate::alarm_manager::config::AlarmManagerConfig;
+use crate::alarm_manager::config::ALARMS_STATE_FILE;
+use crate::alarm_manager::error::AlarmManagerError;
+use crate::alarm_manager::store::AlarmStatus;
+use crate::alarm_manager::store::AlarmStore;
+use crate::alarm_manager::store::ManagedAlarm;
+use crate::state_repository::error::StateError;
+use crate::state_repository::state::AgentStateRepository;
+use async_trait::async_trait;
+use serde_json::Value;
+use tedge_actors::fan_in_message_type;
+use tedge_actors::Actor;
+use tedge_actors::MessageReceiver;
+use tedge_actors::RequestEnvelope;
+use tedge_actors::RuntimeError;
+use tedge_actors::Sender;
+use tedge_actors::SimpleMessageBox;
+use tedge_api::mqtt_topics::Channel;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_api::mqtt_topics::OperationType;
+use tedge_api::workflow::GenericCommandState;
+use tedge_api::workflow::GenericStateUpdate;
+use tedge_mqtt_ext::MqttMessage;
+use tedge_mqtt_ext::QoS;
+use time::OffsetDateTime;
+use tokio::time::MissedTickBehavior;
+use tracing::error;
+use tracing::warn;
+
+/// The operation used by the cloud mappers to feed back the acknowledgements made on the cloud
+pub const ACKNOWLEDGE_ALARM_OPERATION: &str = "acknowledge_alarm";
+
+#[derive(Debug)]
+pub enum AlarmRequest {
+    List,
+    Get {
+        source: EntityTopicId,
+        alarm_type: String,
+    },
+    Update {
+        source: EntityTopicId,
+        alarm_type: String,
+        update: AlarmUpdate,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AlarmUpdate {
+    Acknowledge,
+    Shelve { until: OffsetDateTime },
+    Activate,
+}
+
+#[derive(Debug)]
+pub enum AlarmResponse {
+    List(Vec<AlarmStatus>),
+    Get(Option<AlarmStatus>),
+    Update(Result<AlarmStatus, AlarmManagerError>),
+}
+
+pub type AlarmRequestEnvelope = RequestEnvelope<AlarmRequest, AlarmResponse>;
+
+fan_in_message_type!(AlarmManagerInput[MqttMessage, AlarmRequestEnvelope] : Debug);
+
+/// Manage the lifecycle of the alarms raised by the entities
+///
+/// - Flapping alarms are suppressed, i.e. hidden from the cloud, till stable
+/// - Unacknowledged alarms are escalated to a higher severity after some time
+/// - Alarms can be acknowledged, shelved and restored over HTTP or by the cloud
+///
+/// The status of each alarm is published on `te/<entity>/a/<type>/status`,
+/// for the cloud mappers to drop the alarms that are suppressed or shelved.
+pub struct AlarmManagerActor {
+    config: AlarmManagerConfig,
+    message_box: SimpleMessageBox<AlarmManagerInput, MqttMessage>,
+    repository: AgentStateRepository<Vec<ManagedAlarm>>,
+    store: AlarmStore,
+}
+
+#[async_trait]
+impl Actor for AlarmManagerActor {
+    fn name(&self) -> &str {
+        "AlarmManagerActor"
+    }
+
+    async fn run(mut self) -> Result<(), RuntimeError> {
+        let alarms = self.load_alarms().await;
+        self.store = AlarmStore::new(&self.config, alarms);
+        self.message_box.send(self.capability_message()).await?;
+
+        let mut check = tokio::time::interval(self.config.check_interval);
+        check.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            tokio::select! {
+                input = self.message_box.recv() => match input {
+                    Some(AlarmManagerInput::MqttMessage(message)) => {
+                        self.process_mqtt_message(message).await?
+                    }
+                    Some(AlarmManagerInput::AlarmRequestEnvelope(RequestEnvelope {
+                        request,
+                        mut reply_to,
+                    })) => {
+                        let response = self.process_request(request).await?;
+                        reply_to.send(response).await?;
+                    }
+                    None => break,
+                },
+                _ = check.tick() => {
+                    let messages = self.store.tick(OffsetDateTime::now_utc());
+                    self.publish(messages).await?;
+                }
+            }
+            self.persist_changes().await;
+        }
+
+        Ok(())
+    }
+}
+
+impl AlarmManagerActor {
+    pub fn new(
+        config: AlarmManagerConfig,
+        message_box: SimpleMessageBox<AlarmManagerInput, MqttMessage>,
+    ) -> Self {
+        let repository =
+            AgentStateRepository::with_state_dir(config.state_dir.clone(), ALARMS_STATE_FILE);
+        let store = AlarmStore::new(&config, vec![]);
+        AlarmManagerActor {
+            config,
+            message_box,
+            repository,
+            store,
+        }
+    }
+
+    async fn process_mqtt_message(&mut self, message: MqttMessage) -> Result<(), RuntimeError> {
+        let Ok((source, channel)) = self.config.mqtt_schema.entity_channel_of(&message.topic)
+        else {
+            return Ok(());
+        };
+
+        match channel {
+            Channel::Alarm { alarm_type } => {
+                let Ok(payload) = message.payload_str() else {
+                    warn!(
+                        "Ignoring non UTF-8 alarm published on {}",
+                        message.topic.name
+                    );
+                    return Ok(());
+                };
+                let messages =
+                    self.store
+                        .on_alarm(&source, &alarm_type, payload, OffsetDateTime::now_utc());
+                self.publish(messages).await
+            }
+            Channel::Command {
+                operation: OperationType::Custom(operation),
+                ..
+            } if operation == ACKNOWLEDGE_ALARM_OPERATION => {
+                self.process_acknowledge_command(&source, &message).await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Declare the acknowledge_alarm operation, for the cloud mappers to feed back the acknowledgements
+    fn capability_message(&self) -> MqttMessage {
+        let topic = self.config.mqtt_schema.capability_topic_for(
+            &self.config.device_topic_id,
+            OperationType::Custom(ACKNOWLEDGE_ALARM_OPERATION.to_string()),
+        );
+        MqttMessage::new(&topic, "{}")
+            .with_retain()
+            .with_qos(QoS::AtLeastOnce)
+    }
+
+    /// Acknowledge an alarm on behalf of the cloud
+    ///
+    /// The command payload is expected to be: `{"status": "init", "alarmType": "<type>"}`
+    ///
+    /// The command is moved to `executing` and then to `successful` or `failed`,
+    /// leaving the requester responsible for clearing the command.
+    async fn process_acknowledge_command(
+        &mut self,
+        source: &EntityTopicId,
+        message: &MqttMessage,
+    ) -> Result<(), RuntimeError> {
+        let command = match GenericCommandState::from_command_message(message) {
+            Ok(command) if command.is_init() => command,
+            Ok(_) => return Ok(()),
+            Err(err) => {
+                error!("Invalid {ACKNOWLEDGE_ALARM_OPERATION} command: {err}");
+                return Ok(());
+            }
+        };
+
+        let command = command.move_to(GenericStateUpdate::executing());
+        self.message_box
+            .send(command.clone().into_message())
+            .await?;
+
+        let result = match command.payload.get("alarmType").and_then(Value::as_str) {
+            Some(alarm_type) => self.store.acknowledge(source, alarm_type),
+            None => Err(AlarmManagerError::InvalidCommand(
+                "missing alarmType".to_string(),
+            )),
+        };
+        let command = match result {
+            Ok((_, messages)) => {
+                self.publish(messages).await?;
+                command.move_to(GenericStateUpdate::successful())
+            }
+            Err(err) => command.fail_with(err.to_string()),
+        };
+
+        self.message_box.send(command.into_message()).await?;
+        Ok(())
+    }
+
+    async fn process_request(
+        &mut self,
+        request: AlarmRequest,
+    ) -> Result<AlarmResponse, RuntimeError> {
+        let response = match request {
+            AlarmRequest::List => AlarmResponse::List(self.store.list()),
+            AlarmRequest::Get { source, alarm_type } => {
+                AlarmResponse::Get(self.store.get(&source, &alarm_type))
+            }
+            AlarmRequest::Update {
+                source,
+                alarm_type,
+                update,
+            } => {
+                let result = match update {
+                    AlarmUpdate::Acknowledge => self.store.acknowledge(&source, &alarm_type),
+                    AlarmUpdate::Shelve { until } => self.store.shelve(&source, &alarm_type, until),
+                    AlarmUpdate::Activate => self.store.activate(&source, &alarm_type),
+                };
+                match result {
+                    Ok((status, messages)) => {
+                        self.publish(messages).await?;
+                        AlarmResponse::Update(Ok(status))
+                    }
+                    Err(err) => AlarmResponse::Update(Err(err)),
+                }
+            }
+        };
+        Ok(response)
+    }
+
+    async fn publish(&mut self, messages: Vec<MqttMessage>) -> Result<(), RuntimeError> {
+        for message in messages {
+            self.message_box.send(message).await?;
+        }
+        Ok(())
+    }
+
+    /// Load the alarms persisted by a previous run, if any
+    async fn load_alarms(&self) -> Vec<ManagedAlarm> {
+        match self.repository.load().await {
+            Ok(alarms) => alarms.unwrap_or_default(),
+            Err(StateError::LoadingFromFileFailed { source, .. })
+                if source.kind() == std::io::ErrorKind::NotFound =>
+            {
+                vec![]
+            }
+            Err(err) => {
+                warn!("Ignoring the persisted alarms: {err}");
+                vec![]
+            }
+        }
+    }
+
+    async fn persist_changes(&mut self) {
+        if self.store.take_changes() {
+            let alarms: Vec<ManagedAlarm> = self.store.alarms().into_iter().cloned().collect();
+            if let Err(err) = self.repository.store(&alarms).await {
+                error!("Fail to persist the managed alarms: {err}");
+            }
+        }
+    }
+}
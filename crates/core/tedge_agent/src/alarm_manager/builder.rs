@@ -0,0 +1,92 @@
+use crate::alarm_manager::actor::AlarmManagerActor;
+use crate::alarm_manager::actor::AlarmManagerInput;
+use crate::alarm_manager::actor::AlarmRequestEnvelope;
+use crate::alarm_manager::actor::ACKNOWLEDGE_ALARM_OPERATION;
+use crate::alarm_manager::config::AlarmManagerConfig;
+use tedge_actors::Builder;
+use tedge_actors::CloneSender;
+use tedge_actors::DynSender;
+use tedge_actors::LinkError;
+use tedge_actors::MessageSink;
+use tedge_actors::MessageSource;
+use tedge_actors::NoConfig;
+use tedge_actors::RestartableBuilder;
+use tedge_actors::RuntimeRequest;
+use tedge_actors::RuntimeRequestSink;
+use tedge_actors::SimpleMessageBoxBuilder;
+use tedge_api::mqtt_topics::ChannelFilter;
+use tedge_api::mqtt_topics::EntityFilter;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_api::mqtt_topics::OperationType;
+use tedge_mqtt_ext::MqttMessage;
+use tedge_mqtt_ext::TopicFilter;
+
+pub struct AlarmManagerBuilder {
+    config: AlarmManagerConfig,
+    message_box: SimpleMessageBoxBuilder<AlarmManagerInput, MqttMessage>,
+}
+
+impl AlarmManagerBuilder {
+    pub fn new(
+        config: AlarmManagerConfig,
+        mqtt: &mut (impl MessageSource<MqttMessage, TopicFilter> + MessageSink<MqttMessage>),
+    ) -> Self {
+        let mut message_box = SimpleMessageBoxBuilder::new("AlarmManager", 16);
+        mqtt.connect_sink(
+            Self::subscriptions(&config.mqtt_schema),
+            &message_box.get_sender(),
+        );
+        message_box.connect_sink(NoConfig, mqtt);
+
+        Self {
+            config,
+            message_box,
+        }
+    }
+
+    fn subscriptions(mqtt_schema: &MqttSchema) -> TopicFilter {
+        let mut topics = mqtt_schema.topics(EntityFilter::AnyEntity, ChannelFilter::Alarm);
+        topics.add_all(mqtt_schema.topics(
+            EntityFilter::AnyEntity,
+            ChannelFilter::Command(OperationType::Custom(
+                ACKNOWLEDGE_ALARM_OPERATION.to_string(),
+            )),
+        ));
+        topics
+    }
+}
+
+impl MessageSink<AlarmRequestEnvelope> for AlarmManagerBuilder {
+    fn get_sender(&self) -> DynSender<AlarmRequestEnvelope> {
+        self.message_box.get_sender().sender_clone()
+    }
+}
+
+impl RuntimeRequestSink for AlarmManagerBuilder {
+    fn get_signal_sender(&self) -> DynSender<RuntimeRequest> {
+        self.message_box.get_signal_sender()
+    }
+}
+
+impl Builder<AlarmManagerActor> for AlarmManagerBuilder {
+    type Error = LinkError;
+
+    fn try_build(self) -> Result<AlarmManagerActor, Self::Error> {
+        Ok(self.build())
+    }
+
+    fn build(self) -> AlarmManagerActor {
+        AlarmManagerActor::new(self.config, self.message_box.build())
+    }
+}
+
+impl RestartableBuilder<AlarmManagerActor> for AlarmManagerBuilder {
+    type Error = LinkError;
+
+    fn rebuild(&mut self) -> Result<AlarmManagerActor, Self::Error> {
+        Ok(AlarmManagerActor::new(
+            self.config.clone(),
+            self.message_box.rebuild()?,
+        ))
+    }
+}
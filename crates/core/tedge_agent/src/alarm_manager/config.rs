@@ -0,0 +1,54 @@
+use camino::Utf8PathBuf;
+use std::time::Duration;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_config::TEdgeConfig;
+
+/// The file used to persist the managed alarms, relative to the data dir
+pub const ALARMS_STATE_FILE: &str = "alarms.json";
+
+#[derive(Debug, Clone)]
+pub struct AlarmManagerConfig {
+    pub mqtt_schema: MqttSchema,
+
+    /// The device on which the acknowledge_alarm operation is declared
+    pub device_topic_id: EntityTopicId,
+
+    /// The directory where the managed alarms are persisted
+    pub state_dir: Utf8PathBuf,
+
+    /// The number of raise and clear transitions for an alarm to be suppressed as flapping
+    pub flapping_transitions: usize,
+
+    /// The window over which the transitions are counted
+    pub flapping_window: Duration,
+
+    /// How long an unacknowledged alarm stays at a severity level before being escalated
+    pub escalation_after: Option<Duration>,
+
+    /// How often the time-based rules are applied
+    pub check_interval: Duration,
+}
+
+impl AlarmManagerConfig {
+    pub fn from_tedge_config(
+        mqtt_schema: MqttSchema,
+        device_topic_id: &EntityTopicId,
+        tedge_config: &TEdgeConfig,
+    ) -> Self {
+        let alarms = &tedge_config.agent.alarms;
+        AlarmManagerConfig {
+            mqtt_schema,
+            device_topic_id: device_topic_id.clone(),
+            state_dir: tedge_config.data.path.clone(),
+            flapping_transitions: alarms.flapping.transitions as usize,
+            flapping_window: alarms.flapping.window.duration(),
+            escalation_after: alarms
+                .escalation
+                .after
+                .or_none()
+                .map(|after| after.duration()),
+            check_interval: Duration::from_secs(1),
+        }
+    }
+}
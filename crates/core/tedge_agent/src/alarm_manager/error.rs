@@ -0,0 +1,16 @@
+use tedge_api::mqtt_topics::EntityTopicId;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AlarmManagerError {
+    #[error("No active {alarm_type} alarm for {entity}")]
+    UnknownAlarm {
+        entity: EntityTopicId,
+        alarm_type: String,
+    },
+
+    #[error("Invalid alarm path {0:?}: expecting an entity topic id followed by an alarm type")]
+    InvalidAlarmPath(String),
+
+    #[error("Invalid acknowledge_alarm command: {0}")]
+    InvalidCommand(String),
+}
@@ -0,0 +1,8 @@
+pub mod actor;
+pub mod builder;
+pub mod config;
+pub mod error;
+pub mod store;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,566 @@
+use crate::alarm_manager::config::AlarmManagerConfig;
+use crate::alarm_manager::error::AlarmManagerError;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::str::FromStr;
+use std::time::Duration;
+use tedge_api::mqtt_topics::Channel;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_mqtt_ext::MqttMessage;
+use tedge_mqtt_ext::QoS;
+use time::OffsetDateTime;
+
+/// The severity levels of an alarm, from the lowest to the highest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Minor,
+    Major,
+    Critical,
+}
+
+impl Severity {
+    /// The severity an alarm is given when escalated
+    pub fn escalated(self) -> Severity {
+        match self {
+            Severity::Warning => Severity::Minor,
+            Severity::Minor => Severity::Major,
+            Severity::Major | Severity::Critical => Severity::Critical,
+        }
+    }
+
+    /// The severity of an alarm payload, alarms without a known severity being considered major
+    fn of_payload(payload: &str) -> Severity {
+        serde_json::from_str::<Value>(payload)
+            .ok()
+            .as_ref()
+            .and_then(|json| json.get("severity"))
+            .and_then(Value::as_str)
+            .and_then(|severity| severity.parse().ok())
+            .unwrap_or(Severity::Major)
+    }
+}
+
+impl FromStr for Severity {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "warning" => Ok(Severity::Warning),
+            "minor" => Ok(Severity::Minor),
+            "major" => Ok(Severity::Major),
+            "critical" => Ok(Severity::Critical),
+            _ => Err(format!("Unknown alarm severity: {value}")),
+        }
+    }
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let severity = match self {
+            Severity::Warning => "warning",
+            Severity::Minor => "minor",
+            Severity::Major => "major",
+            Severity::Critical => "critical",
+        };
+        f.write_str(severity)
+    }
+}
+
+/// The lifecycle status of an alarm, as seen by the operators
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlarmState {
+    /// Raised and waiting for an operator
+    Active,
+    /// Raised and acknowledged by an operator
+    Acknowledged,
+    /// Hidden from the cloud till a deadline
+    Shelved,
+    /// Hidden from the cloud as flapping
+    Suppressed,
+}
+
+/// The status of an alarm, as published on `te/<entity>/a/<type>/status` and returned over HTTP
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlarmStatus {
+    pub source: EntityTopicId,
+
+    #[serde(rename = "type")]
+    pub alarm_type: String,
+
+    pub status: AlarmState,
+
+    pub severity: Severity,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub raised_at: OffsetDateTime,
+
+    /// The severity given by the source, when the alarm has been escalated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub escalated_from: Option<Severity>,
+
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "time::serde::rfc3339::option"
+    )]
+    pub shelved_until: Option<OffsetDateTime>,
+}
+
+/// An alarm tracked by the alarm manager
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManagedAlarm {
+    pub source: EntityTopicId,
+
+    #[serde(rename = "type")]
+    pub alarm_type: String,
+
+    /// The severity given by the source
+    pub severity: Severity,
+
+    /// The severity raised by the manager, if any
+    #[serde(default)]
+    pub escalated_severity: Option<Severity>,
+
+    /// The latest payload published by the source, empty when cleared
+    pub payload: String,
+
+    pub raised: bool,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub raised_at: OffsetDateTime,
+
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub escalated_at: Option<OffsetDateTime>,
+
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub shelved_until: Option<OffsetDateTime>,
+
+    #[serde(default)]
+    pub acknowledged: bool,
+
+    #[serde(default)]
+    pub suppressed: bool,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub last_transition_at: OffsetDateTime,
+
+    /// The payload published by the manager on the alarm topic and not received back yet
+    #[serde(default)]
+    pub published: Option<String>,
+
+    /// The raise and clear transitions within the flapping window
+    #[serde(skip)]
+    transitions: Vec<OffsetDateTime>,
+}
+
+impl ManagedAlarm {
+    fn new(
+        source: EntityTopicId,
+        alarm_type: String,
+        payload: String,
+        now: OffsetDateTime,
+    ) -> Self {
+        ManagedAlarm {
+            source,
+            alarm_type,
+            severity: Severity::of_payload(&payload),
+            escalated_severity: None,
+            payload,
+            raised: true,
+            raised_at: now,
+            escalated_at: None,
+            shelved_until: None,
+            acknowledged: false,
+            suppressed: false,
+            last_transition_at: now,
+            published: None,
+            transitions: vec![now],
+        }
+    }
+
+    pub fn state(&self) -> AlarmState {
+        if self.suppressed {
+            AlarmState::Suppressed
+        } else if self.shelved_until.is_some() {
+            AlarmState::Shelved
+        } else if self.acknowledged {
+            AlarmState::Acknowledged
+        } else {
+            AlarmState::Active
+        }
+    }
+
+    pub fn effective_severity(&self) -> Severity {
+        self.escalated_severity.unwrap_or(self.severity)
+    }
+
+    pub fn status(&self) -> AlarmStatus {
+        AlarmStatus {
+            source: self.source.clone(),
+            alarm_type: self.alarm_type.clone(),
+            status: self.state(),
+            severity: self.effective_severity(),
+            raised_at: self.raised_at,
+            escalated_from: self.escalated_severity.map(|_| self.severity),
+            shelved_until: self.shelved_until,
+        }
+    }
+
+    /// The payload to be forwarded to the cloud: the source payload with the escalated severity, if any
+    fn effective_payload(&self) -> String {
+        let Some(severity) = self.escalated_severity else {
+            return self.payload.clone();
+        };
+        match serde_json::from_str::<Value>(&self.payload) {
+            Ok(Value::Object(mut json)) => {
+                json.insert("severity".to_string(), severity.to_string().into());
+                Value::Object(json).to_string()
+            }
+            _ => self.payload.clone(),
+        }
+    }
+
+    /// Record a raise or clear transition, returning true if the alarm is now flapping
+    fn record_transition(&mut self, now: OffsetDateTime, window: Duration, max: usize) -> bool {
+        self.transitions.retain(|at| *at + window > now);
+        self.transitions.push(now);
+        self.last_transition_at = now;
+        self.transitions.len() >= max
+    }
+
+    /// Reset the alarm on a new severity or a new occurrence
+    fn reset_escalation(&mut self) {
+        self.escalated_severity = None;
+        self.escalated_at = None;
+    }
+}
+
+/// The alarms managed by the agent, along the rules to process them
+///
+/// All the methods return the MQTT messages to be published as a consequence of the change.
+pub struct AlarmStore {
+    mqtt_schema: MqttSchema,
+    flapping_transitions: usize,
+    flapping_window: Duration,
+    escalation_after: Option<Duration>,
+    alarms: HashMap<(EntityTopicId, String), ManagedAlarm>,
+    changed: bool,
+}
+
+impl AlarmStore {
+    pub fn new(config: &AlarmManagerConfig, alarms: Vec<ManagedAlarm>) -> Self {
+        let alarms = alarms
+            .into_iter()
+            .map(|alarm| ((alarm.source.clone(), alarm.alarm_type.clone()), alarm))
+            .collect();
+        AlarmStore {
+            mqtt_schema: config.mqtt_schema.clone(),
+            flapping_transitions: config.flapping_transitions,
+            flapping_window: config.flapping_window,
+            escalation_after: config.escalation_after,
+            alarms,
+            changed: false,
+        }
+    }
+
+    /// The managed alarms, sorted by source and type
+    pub fn alarms(&self) -> Vec<&ManagedAlarm> {
+        let mut alarms: Vec<_> = self.alarms.values().collect();
+        alarms.sort_by(|a, b| {
+            (a.source.as_str(), &a.alarm_type).cmp(&(b.source.as_str(), &b.alarm_type))
+        });
+        alarms
+    }
+
+    /// The statuses of the raised alarms
+    pub fn list(&self) -> Vec<AlarmStatus> {
+        self.alarms()
+            .into_iter()
+            .filter(|alarm| alarm.raised)
+            .map(ManagedAlarm::status)
+            .collect()
+    }
+
+    pub fn get(&self, source: &EntityTopicId, alarm_type: &str) -> Option<AlarmStatus> {
+        self.alarms
+            .get(&(source.clone(), alarm_type.to_string()))
+            .filter(|alarm| alarm.raised)
+            .map(ManagedAlarm::status)
+    }
+
+    /// Return true if the alarms have changed since the previous call
+    pub fn take_changes(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+
+    /// Process an alarm raised or cleared by a source
+    pub fn on_alarm(
+        &mut self,
+        source: &EntityTopicId,
+        alarm_type: &str,
+        payload: &str,
+        now: OffsetDateTime,
+    ) -> Vec<MqttMessage> {
+        let raised = !payload.is_empty();
+        let key = (source.clone(), alarm_type.to_string());
+        let Some(alarm) = self.alarms.get_mut(&key) else {
+            if !raised {
+                return vec![];
+            }
+            let alarm = ManagedAlarm::new(
+                source.clone(),
+                alarm_type.to_string(),
+                payload.to_string(),
+                now,
+            );
+            let messages = vec![self.status_message(&alarm)];
+            self.alarms.insert(key, alarm);
+            self.changed = true;
+            return messages;
+        };
+
+        // Ignore the messages published by the manager itself
+        if alarm.published.as_deref() == Some(payload) {
+            alarm.published = None;
+            self.changed = true;
+            return vec![];
+        }
+        if alarm.raised && alarm.effective_payload() == payload {
+            return vec![];
+        }
+
+        if alarm.raised == raised {
+            if !raised {
+                return vec![];
+            }
+            // The alarm has been updated by its source
+            let severity = Severity::of_payload(payload);
+            if severity != alarm.severity {
+                alarm.severity = severity;
+                alarm.reset_escalation();
+            }
+            alarm.payload = payload.to_string();
+        } else {
+            alarm.raised = raised;
+            alarm.payload = payload.to_string();
+            if raised {
+                alarm.severity = Severity::of_payload(payload);
+                alarm.raised_at = now;
+                alarm.acknowledged = false;
+                alarm.reset_escalation();
+            }
+            if alarm.record_transition(now, self.flapping_window, self.flapping_transitions) {
+                alarm.suppressed = true;
+            }
+        }
+
+        self.changed = true;
+        let alarm = alarm.clone();
+        vec![self.status_message(&alarm)]
+    }
+
+    /// Acknowledge a raised alarm
+    pub fn acknowledge(
+        &mut self,
+        source: &EntityTopicId,
+        alarm_type: &str,
+    ) -> Result<(AlarmStatus, Vec<MqttMessage>), AlarmManagerError> {
+        let alarm = self.raised_alarm_mut(source, alarm_type)?;
+        alarm.acknowledged = true;
+        let alarm = alarm.clone();
+        self.changed = true;
+
+        Ok((alarm.status(), vec![self.status_message(&alarm)]))
+    }
+
+    /// Hide a raised alarm from the cloud till the given deadline
+    ///
+    /// The alarm is cleared on the cloud side before being flagged as shelved,
+    /// so the mapper drops only the updates received after the clear.
+    pub fn shelve(
+        &mut self,
+        source: &EntityTopicId,
+        alarm_type: &str,
+        until: OffsetDateTime,
+    ) -> Result<(AlarmStatus, Vec<MqttMessage>), AlarmManagerError> {
+        let alarm = self.raised_alarm_mut(source, alarm_type)?;
+        let mut messages = vec![];
+        if !alarm.suppressed && alarm.shelved_until.is_none() {
+            alarm.published = Some(String::new());
+            messages.push(self.alarm_message(source, alarm_type, ""));
+        }
+        let alarm = self.raised_alarm_mut(source, alarm_type)?;
+        alarm.shelved_until = Some(until);
+        let alarm = alarm.clone();
+        self.changed = true;
+
+        messages.push(self.status_message(&alarm));
+        Ok((alarm.status(), messages))
+    }
+
+    /// Restore a raised alarm as active, unshelving and un-acknowledging it
+    pub fn activate(
+        &mut self,
+        source: &EntityTopicId,
+        alarm_type: &str,
+    ) -> Result<(AlarmStatus, Vec<MqttMessage>), AlarmManagerError> {
+        let alarm = self.raised_alarm_mut(source, alarm_type)?;
+        let was_shelved = alarm.shelved_until.take().is_some();
+        alarm.acknowledged = false;
+        let alarm = alarm.clone();
+        self.changed = true;
+
+        let mut messages = vec![self.status_message(&alarm)];
+        if was_shelved && !alarm.suppressed {
+            messages.push(self.alarm_message(source, alarm_type, &alarm.effective_payload()));
+        }
+        Ok((alarm.status(), messages))
+    }
+
+    /// Apply the time-based rules: shelve expiry, end of flapping, escalation and cleanup
+    pub fn tick(&mut self, now: OffsetDateTime) -> Vec<MqttMessage> {
+        let mut messages = vec![];
+        let mut stale = vec![];
+
+        for (key, alarm) in self.alarms.iter_mut() {
+            let mut updated = false;
+            let mut restored = false;
+
+            if alarm.shelved_until.is_some_and(|until| until <= now) {
+                alarm.shelved_until = None;
+                updated = true;
+                restored = !alarm.suppressed;
+            }
+
+            if alarm.suppressed && alarm.last_transition_at + self.flapping_window <= now {
+                alarm.suppressed = false;
+                alarm.transitions.clear();
+                updated = true;
+                restored = alarm.shelved_until.is_none();
+            }
+
+            if let Some(after) = self.escalation_after {
+                let since = alarm.escalated_at.unwrap_or(alarm.raised_at);
+                if alarm.raised
+                    && alarm.state() == AlarmState::Active
+                    && alarm.effective_severity() != Severity::Critical
+                    && since + after <= now
+                {
+                    alarm.escalated_severity = Some(alarm.effective_severity().escalated());
+                    alarm.escalated_at = Some(now);
+                    messages.push(Self::status_for(&self.mqtt_schema, alarm));
+                    messages.push(Self::alarm_for(
+                        &self.mqtt_schema,
+                        key,
+                        &alarm.effective_payload(),
+                    ));
+                    self.changed = true;
+                    continue;
+                }
+            }
+
+            if updated {
+                // The status has to be updated before the alarm is forwarded again to the cloud
+                messages.push(Self::status_for(&self.mqtt_schema, alarm));
+                if restored {
+                    messages.push(Self::alarm_for(
+                        &self.mqtt_schema,
+                        key,
+                        &alarm.effective_payload(),
+                    ));
+                }
+                self.changed = true;
+            }
+
+            if !alarm.raised
+                && !alarm.suppressed
+                && alarm.shelved_until.is_none()
+                && alarm.last_transition_at + self.flapping_window <= now
+            {
+                stale.push(key.clone());
+            }
+        }
+
+        for key in stale {
+            self.alarms.remove(&key);
+            self.changed = true;
+        }
+
+        messages
+    }
+
+    fn raised_alarm_mut(
+        &mut self,
+        source: &EntityTopicId,
+        alarm_type: &str,
+    ) -> Result<&mut ManagedAlarm, AlarmManagerError> {
+        self.alarms
+            .get_mut(&(source.clone(), alarm_type.to_string()))
+            .filter(|alarm| alarm.raised)
+            .ok_or_else(|| AlarmManagerError::UnknownAlarm {
+                entity: source.clone(),
+                alarm_type: alarm_type.to_string(),
+            })
+    }
+
+    fn status_message(&self, alarm: &ManagedAlarm) -> MqttMessage {
+        Self::status_for(&self.mqtt_schema, alarm)
+    }
+
+    fn alarm_message(
+        &self,
+        source: &EntityTopicId,
+        alarm_type: &str,
+        payload: &str,
+    ) -> MqttMessage {
+        Self::alarm_for(
+            &self.mqtt_schema,
+            &(source.clone(), alarm_type.to_string()),
+            payload,
+        )
+    }
+
+    /// The retained status message of an alarm, empty once the alarm is cleared and no more hidden
+    fn status_for(mqtt_schema: &MqttSchema, alarm: &ManagedAlarm) -> MqttMessage {
+        let topic = mqtt_schema.topic_for(
+            &alarm.source,
+            &Channel::AlarmStatus {
+                alarm_type: alarm.alarm_type.clone(),
+            },
+        );
+        let payload = if alarm.raised || alarm.suppressed || alarm.shelved_until.is_some() {
+            serde_json::to_string(&alarm.status()).unwrap()
+        } else {
+            String::new()
+        };
+        MqttMessage::new(&topic, payload)
+            .with_retain()
+            .with_qos(QoS::AtLeastOnce)
+    }
+
+    fn alarm_for(
+        mqtt_schema: &MqttSchema,
+        (source, alarm_type): &(EntityTopicId, String),
+        payload: &str,
+    ) -> MqttMessage {
+        let topic = mqtt_schema.topic_for(
+            source,
+            &Channel::Alarm {
+                alarm_type: alarm_type.clone(),
+            },
+        );
+        MqttMessage::new(&topic, payload)
+            .with_retain()
+            .with_qos(QoS::AtLeastOnce)
+    }
+}
@@ -0,0 +1,266 @@
+use crate::alarm_manager::builder::AlarmManagerBuilder;
+use crate::alarm_manager::config::AlarmManagerConfig;
+use crate::alarm_manager::config::ALARMS_STATE_FILE;
+use crate::alarm_manager::error::AlarmManagerError;
+use crate::alarm_manager::store::AlarmState;
+use crate::alarm_manager::store::AlarmStore;
+use crate::alarm_manager::store::Severity;
+use serde_json::json;
+use serde_json::Value;
+use std::time::Duration;
+use tedge_actors::test_helpers::MessageReceiverExt;
+use tedge_actors::test_helpers::TimedMessageBox;
+use tedge_actors::Actor;
+use tedge_actors::Builder;
+use tedge_actors::DynError;
+use tedge_actors::MessageReceiver;
+use tedge_actors::Sender;
+use tedge_actors::SimpleMessageBox;
+use tedge_actors::SimpleMessageBoxBuilder;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_mqtt_ext::MqttMessage;
+use tedge_mqtt_ext::Topic;
+use tedge_test_utils::fs::TempTedgeDir;
+use time::OffsetDateTime;
+
+const TEST_TIMEOUT_MS: Duration = Duration::from_millis(3000);
+
+const ALARM_TOPIC: &str = "te/device/main///a/temperature_high";
+const STATUS_TOPIC: &str = "te/device/main///a/temperature_high/status";
+const RAISED: &str = r#"{"text":"Temperature is high","severity":"minor"}"#;
+
+#[test]
+fn new_alarms_are_tracked_and_duplicates_ignored() {
+    let mut store = alarm_store(5, None);
+
+    let messages = store.on_alarm(&main(), "temperature_high", RAISED, at(0));
+    assert_eq!(topics(&messages), vec![STATUS_TOPIC]);
+    assert_eq!(status_of(&messages[0])["status"], "active");
+    assert_eq!(status_of(&messages[0])["severity"], "minor");
+
+    let messages = store.on_alarm(&main(), "temperature_high", RAISED, at(1));
+    assert!(messages.is_empty());
+
+    let messages = store.on_alarm(&main(), "temperature_high", "", at(2));
+    assert_eq!(topics(&messages), vec![STATUS_TOPIC]);
+    assert_eq!(messages[0].payload_str().unwrap(), "");
+    assert!(store.list().is_empty());
+}
+
+#[test]
+fn flapping_alarms_are_suppressed_till_stable() {
+    let mut store = alarm_store(4, None);
+
+    store.on_alarm(&main(), "temperature_high", RAISED, at(0));
+    store.on_alarm(&main(), "temperature_high", "", at(1));
+    store.on_alarm(&main(), "temperature_high", RAISED, at(2));
+    let messages = store.on_alarm(&main(), "temperature_high", "", at(3));
+    assert_eq!(status_of(&messages[0])["status"], "suppressed");
+
+    let messages = store.on_alarm(&main(), "temperature_high", RAISED, at(4));
+    assert_eq!(status_of(&messages[0])["status"], "suppressed");
+
+    // Still flapping
+    assert!(store.tick(at(30)).is_empty());
+
+    // Stable over the window: the status is restored before the alarm is forwarded again
+    let messages = store.tick(at(64));
+    assert_eq!(topics(&messages), vec![STATUS_TOPIC, ALARM_TOPIC]);
+    assert_eq!(status_of(&messages[0])["status"], "active");
+    assert_eq!(messages[1].payload_str().unwrap(), RAISED);
+}
+
+#[test]
+fn unacknowledged_alarms_are_escalated() {
+    let mut store = alarm_store(5, Some(Duration::from_secs(600)));
+
+    store.on_alarm(&main(), "temperature_high", RAISED, at(0));
+    assert!(store.tick(at(599)).is_empty());
+
+    let messages = store.tick(at(600));
+    assert_eq!(topics(&messages), vec![STATUS_TOPIC, ALARM_TOPIC]);
+    assert_eq!(status_of(&messages[0])["severity"], "major");
+    assert_eq!(status_of(&messages[0])["escalatedFrom"], "minor");
+    let alarm: Value = serde_json::from_str(messages[1].payload_str().unwrap()).unwrap();
+    assert_eq!(alarm["severity"], "major");
+    assert_eq!(alarm["text"], "Temperature is high");
+
+    // The escalated alarm received back from the broker is not a new occurrence
+    let messages = store.on_alarm(
+        &main(),
+        "temperature_high",
+        messages[1].payload_str().unwrap(),
+        at(601),
+    );
+    assert!(messages.is_empty());
+
+    // Acknowledged alarms are no more escalated
+    store.acknowledge(&main(), "temperature_high").unwrap();
+    assert!(store.tick(at(1300)).is_empty());
+    let status = store.get(&main(), "temperature_high").unwrap();
+    assert_eq!(status.status, AlarmState::Acknowledged);
+    assert_eq!(status.severity, Severity::Major);
+}
+
+#[test]
+fn shelved_alarms_are_cleared_then_restored() {
+    let mut store = alarm_store(5, None);
+
+    store.on_alarm(&main(), "temperature_high", RAISED, at(0));
+    let (status, messages) = store.shelve(&main(), "temperature_high", at(3600)).unwrap();
+    assert_eq!(status.status, AlarmState::Shelved);
+    assert_eq!(topics(&messages), vec![ALARM_TOPIC, STATUS_TOPIC]);
+    assert_eq!(messages[0].payload_str().unwrap(), "");
+    assert_eq!(status_of(&messages[1])["status"], "shelved");
+
+    // The clear message received back from the broker doesn't clear the alarm
+    assert!(store
+        .on_alarm(&main(), "temperature_high", "", at(1))
+        .is_empty());
+    assert!(store.get(&main(), "temperature_high").is_some());
+
+    let messages = store.tick(at(3600));
+    assert_eq!(topics(&messages), vec![STATUS_TOPIC, ALARM_TOPIC]);
+    assert_eq!(status_of(&messages[0])["status"], "active");
+    assert_eq!(messages[1].payload_str().unwrap(), RAISED);
+}
+
+#[test]
+fn only_raised_alarms_can_be_acknowledged() {
+    let mut store = alarm_store(5, None);
+
+    let err = store.acknowledge(&main(), "temperature_high").unwrap_err();
+    assert!(matches!(err, AlarmManagerError::UnknownAlarm { .. }));
+}
+
+#[tokio::test]
+async fn alarms_are_acknowledged_by_the_cloud() -> Result<(), DynError> {
+    let temp_dir = TempTedgeDir::new();
+    let mut mqtt_box = spawn_alarm_manager(&temp_dir).await?;
+
+    mqtt_box
+        .send(MqttMessage::new(&Topic::new_unchecked(ALARM_TOPIC), RAISED).with_retain())
+        .await?;
+    let status = mqtt_box.recv().await.expect("alarm status");
+    assert_eq!(status.topic.name, STATUS_TOPIC);
+
+    let command_topic = Topic::new_unchecked("te/device/main///cmd/acknowledge_alarm/c8y-1234");
+    mqtt_box
+        .send(MqttMessage::new(
+            &command_topic,
+            json!({"status": "init", "alarmType": "temperature_high"}).to_string(),
+        ))
+        .await?;
+
+    let command = mqtt_box.recv().await.expect("command status");
+    assert_eq!(command.topic, command_topic);
+    assert_eq!(status_of(&command)["status"], "executing");
+
+    let status = mqtt_box.recv().await.expect("alarm status");
+    assert_eq!(status.topic.name, STATUS_TOPIC);
+    assert_eq!(status_of(&status)["status"], "acknowledged");
+
+    let command = mqtt_box.recv().await.expect("command status");
+    assert_eq!(command.topic, command_topic);
+    let command: Value = serde_json::from_str(command.payload_str()?)?;
+    assert_eq!(command["status"], "successful");
+
+    // The alarms are persisted under the data directory
+    mqtt_box
+        .send(MqttMessage::new(&Topic::new_unchecked(ALARM_TOPIC), "").with_retain())
+        .await?;
+    let status = mqtt_box.recv().await.expect("alarm status");
+    assert_eq!(status.payload_str()?, "");
+
+    let persisted = std::fs::read_to_string(temp_dir.path().join(ALARMS_STATE_FILE))?;
+    let persisted: Value = serde_json::from_str(&persisted)?;
+    assert_eq!(persisted[0]["acknowledged"], true);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn acknowledging_an_unknown_alarm_fails() -> Result<(), DynError> {
+    let temp_dir = TempTedgeDir::new();
+    let mut mqtt_box = spawn_alarm_manager(&temp_dir).await?;
+
+    let command_topic = Topic::new_unchecked("te/device/main///cmd/acknowledge_alarm/c8y-1234");
+    mqtt_box
+        .send(MqttMessage::new(
+            &command_topic,
+            json!({"status": "init", "alarmType": "temperature_high"}).to_string(),
+        ))
+        .await?;
+
+    let command = mqtt_box.recv().await.expect("command status");
+    assert_eq!(status_of(&command)["status"], "executing");
+    let command = mqtt_box.recv().await.expect("command status");
+    let command: Value = serde_json::from_str(command.payload_str()?)?;
+    assert_eq!(command["status"], "failed");
+
+    Ok(())
+}
+
+fn main() -> EntityTopicId {
+    EntityTopicId::default_main_device()
+}
+
+fn at(secs: u64) -> OffsetDateTime {
+    OffsetDateTime::UNIX_EPOCH + Duration::from_secs(secs)
+}
+
+fn topics(messages: &[MqttMessage]) -> Vec<&str> {
+    messages
+        .iter()
+        .map(|message| message.topic.name.as_str())
+        .collect()
+}
+
+fn status_of(message: &MqttMessage) -> Value {
+    serde_json::from_str(message.payload_str().unwrap()).unwrap()
+}
+
+fn config(
+    state_dir: &TempTedgeDir,
+    transitions: usize,
+    escalation: Option<Duration>,
+) -> AlarmManagerConfig {
+    AlarmManagerConfig {
+        mqtt_schema: MqttSchema::default(),
+        device_topic_id: main(),
+        state_dir: state_dir.utf8_path_buf(),
+        flapping_transitions: transitions,
+        flapping_window: Duration::from_secs(60),
+        escalation_after: escalation,
+        check_interval: Duration::from_secs(3600),
+    }
+}
+
+fn alarm_store(transitions: usize, escalation: Option<Duration>) -> AlarmStore {
+    let temp_dir = TempTedgeDir::new();
+    AlarmStore::new(&config(&temp_dir, transitions, escalation), vec![])
+}
+
+async fn spawn_alarm_manager(
+    temp_dir: &TempTedgeDir,
+) -> Result<TimedMessageBox<SimpleMessageBox<MqttMessage, MqttMessage>>, DynError> {
+    let mut mqtt_builder: SimpleMessageBoxBuilder<MqttMessage, MqttMessage> =
+        SimpleMessageBoxBuilder::new("MQTT", 10);
+
+    let actor_builder = AlarmManagerBuilder::new(config(temp_dir, 5, None), &mut mqtt_builder);
+    let mut mqtt_box = mqtt_builder.build().with_timeout(TEST_TIMEOUT_MS);
+
+    let actor = actor_builder.build();
+    tokio::spawn(async move { actor.run().await });
+
+    // The acknowledge_alarm operation is declared on start
+    let capability = mqtt_box.recv().await.expect("capability message");
+    assert_eq!(
+        capability.topic.name,
+        "te/device/main///cmd/acknowledge_alarm"
+    );
+    assert!(capability.retain);
+
+    Ok(mqtt_box)
+}
@@ -0,0 +1,418 @@
+use crate::cert_renewal_manager::config::CertRenewalConfig;
+use crate::cert_renewal_manager::config::Enrolment;
+use crate::cert_renewal_manager::error::CertRenewalError;
+use async_trait::async_trait;
+use camino::Utf8Path;
+use certificate::est;
+use certificate::KeyCertPair;
+use certificate::KeyKind;
+use certificate::PemCertificate;
+use reqwest::header::CONTENT_TYPE;
+use std::collections::HashSet;
+use std::time::Duration;
+use tedge_actors::fan_in_message_type;
+use tedge_actors::Actor;
+use tedge_actors::LoggingSender;
+use tedge_actors::MessageReceiver;
+use tedge_actors::RuntimeError;
+use tedge_actors::Sender;
+use tedge_actors::SimpleMessageBox;
+use tedge_api::commands::CertRenewCmd;
+use tedge_api::commands::CommandStatus;
+use tedge_api::workflow::GenericCommandState;
+use tedge_mqtt_ext::MqttMessage;
+use tedge_utils::fs::atomically_write_file_async;
+use time::OffsetDateTime;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::time::MissedTickBehavior;
+use tracing::debug;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+/// The prefix of the ids of the `cert_renew` commands triggered on certificate expiry
+const AUTO_RENEWAL_PREFIX: &str = "cert-renew-";
+
+fan_in_message_type!(CertRenewalInput[CertRenewCmd, MqttMessage] : Debug);
+
+pub struct CertRenewalManagerActor {
+    config: CertRenewalConfig,
+    message_box: SimpleMessageBox<CertRenewalInput, CertRenewCmd>,
+    mqtt_publisher: LoggingSender<MqttMessage>,
+    /// The id of the renewal triggered on certificate expiry, till it reaches a terminal state
+    pending_renewal: Option<String>,
+
+    /// The renewals processed by this actor,
+    /// i.e. for which the executing state is an echo of the state sent by this actor
+    executing: HashSet<String>,
+}
+
+#[async_trait]
+impl Actor for CertRenewalManagerActor {
+    fn name(&self) -> &str {
+        "CertRenewalManagerActor"
+    }
+
+    async fn run(mut self) -> Result<(), RuntimeError> {
+        let mut expiry_check = tokio::time::interval(self.config.check_interval);
+        expiry_check.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            tokio::select! {
+                input = self.message_box.recv() => match input {
+                    Some(CertRenewalInput::CertRenewCmd(command)) => {
+                        self.process_command(command).await?
+                    }
+                    Some(CertRenewalInput::MqttMessage(message)) => {
+                        self.process_command_update(&message)
+                    }
+                    None => break,
+                },
+
+                // No new renewal is triggered while the previous one is still in progress
+                _ = expiry_check.tick(), if self.config.enable && self.pending_renewal.is_none() => {
+                    self.check_certificate_expiry().await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl CertRenewalManagerActor {
+    pub fn new(
+        config: CertRenewalConfig,
+        message_box: SimpleMessageBox<CertRenewalInput, CertRenewCmd>,
+        mqtt_publisher: LoggingSender<MqttMessage>,
+    ) -> Self {
+        Self {
+            config,
+            message_box,
+            mqtt_publisher,
+            pending_renewal: None,
+            executing: HashSet::new(),
+        }
+    }
+
+    /// Track the state of the renewals triggered on certificate expiry, as published over MQTT
+    ///
+    /// A renewal left in progress by a previous run of the agent is also tracked,
+    /// so a new one is only triggered once this one is successful, failed or cleared.
+    fn process_command_update(&mut self, message: &MqttMessage) {
+        let Ok(command) = GenericCommandState::from_command_message(message) else {
+            return;
+        };
+        let Some(cmd_id) = command.cmd_id() else {
+            return;
+        };
+
+        let terminated = command.is_cleared() || command.is_finished();
+        match &self.pending_renewal {
+            Some(pending) if *pending == cmd_id && terminated => {
+                debug!("The certificate renewal {cmd_id} is over");
+                self.pending_renewal = None;
+            }
+            None if cmd_id.starts_with(AUTO_RENEWAL_PREFIX) && !terminated => {
+                debug!("The certificate renewal {cmd_id} is in progress");
+                self.pending_renewal = Some(cmd_id);
+            }
+            _ => {}
+        }
+    }
+
+    async fn process_command(&mut self, command: CertRenewCmd) -> Result<(), RuntimeError> {
+        match command.status() {
+            CommandStatus::Scheduled => {
+                self.executing.insert(command.cmd_id.clone());
+                let executing = command.clone().with_status(CommandStatus::Executing);
+                self.message_box.send(executing).await?;
+
+                let rotate_key = command.payload.rotate_key.unwrap_or(self.config.rotate_key);
+                let response = match self.renew_certificate(rotate_key).await {
+                    Ok(not_after) => {
+                        info!(
+                            "The device certificate has been renewed, new expiry date: {not_after}"
+                        );
+                        let mut command = command.with_status(CommandStatus::Successful);
+                        command.payload.not_after = Some(not_after);
+                        command
+                    }
+                    Err(err) => {
+                        let error = format!("Fail to renew the device certificate: {err}");
+                        error!(error);
+                        command.with_error(error)
+                    }
+                };
+                self.message_box.send(response).await?;
+            }
+            CommandStatus::Executing => {
+                if !self.executing.remove(&command.cmd_id) {
+                    // The renewal cannot be resumed, as the CSR has been lost
+                    let error = "The agent has been restarted during the certificate renewal";
+                    error!(error);
+                    self.message_box
+                        .send(command.with_error(error.to_string()))
+                        .await?;
+                }
+            }
+            _ => {
+                // Only handle commands in the scheduled state
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Trigger a `cert_renew` command if the device certificate is about to expire
+    ///
+    /// The command is published over MQTT, so it goes through the `cert_renew` workflow,
+    /// as if it were requested by any other component.
+    async fn check_certificate_expiry(&mut self) -> Result<(), RuntimeError> {
+        let not_after = match self
+            .read_device_certificate()
+            .and_then(|cert| cert.expiry().map_err(CertRenewalError::from))
+        {
+            Ok(not_after) => not_after,
+            Err(err) => {
+                warn!("Cannot check the expiry date of the device certificate: {err}");
+                return Ok(());
+            }
+        };
+
+        let now = OffsetDateTime::now_utc();
+        if !renewal_due(not_after, now, self.config.window) {
+            return Ok(());
+        }
+
+        info!("The device certificate expires on {not_after}, triggering its renewal");
+        let cmd_id = format!("{AUTO_RENEWAL_PREFIX}{}", now.unix_timestamp());
+        let command = CertRenewCmd::new(&self.config.device_topic_id, cmd_id.clone())
+            .with_status(CommandStatus::Init);
+        self.mqtt_publisher
+            .send(command.command_message(&self.config.mqtt_schema))
+            .await?;
+        self.pending_renewal = Some(cmd_id);
+
+        Ok(())
+    }
+
+    /// Get a new device certificate signed, returning its expiry date
+    async fn renew_certificate(
+        &mut self,
+        rotate_key: bool,
+    ) -> Result<OffsetDateTime, CertRenewalError> {
+        let enrolment = self.config.enrolment()?;
+        let current_certificate = self.read_device_certificate()?;
+        let common_name = current_certificate.subject_common_name()?;
+
+        let key_kind = if rotate_key {
+            KeyKind::New
+        } else {
+            KeyKind::Reuse {
+                keypair_pem: self.read_private_key().await?,
+            }
+        };
+        let csr = KeyCertPair::new_certificate_sign_request(
+            &self.config.csr_template,
+            &common_name,
+            &key_kind,
+        )?;
+
+        info!("Requesting a new certificate for {common_name} using {enrolment:?}");
+        let certificate_pem = match enrolment {
+            Enrolment::Est { url } => self.enrol_with_est(&url, &csr).await?,
+            Enrolment::Script { script } => self.enrol_with_script(&script, &csr).await?,
+        };
+
+        let renewed_certificate = PemCertificate::from_pem_string(&certificate_pem)?;
+        let renewed_common_name = renewed_certificate.subject_common_name()?;
+        if renewed_common_name != common_name {
+            return Err(CertRenewalError::UnexpectedCommonName {
+                expected: common_name,
+                actual: renewed_common_name,
+            });
+        }
+        let not_after = renewed_certificate.expiry()?;
+
+        self.install_certificate(&certificate_pem, rotate_key.then_some(&csr))
+            .await?;
+
+        if let Some(cloud) = self.config.reconnect.clone() {
+            self.reconnect(&cloud).await?;
+        }
+
+        Ok(not_after)
+    }
+
+    fn read_device_certificate(&self) -> Result<PemCertificate, CertRenewalError> {
+        // Checked before any use of the device certificate settings
+        self.config.check_device_settings()?;
+        PemCertificate::from_pem_file(&self.config.cert_path).map_err(|err| {
+            CertRenewalError::InvalidDeviceCertificate {
+                path: self.config.cert_path.clone(),
+                reason: err.to_string(),
+            }
+        })
+    }
+
+    async fn read_private_key(&self) -> Result<String, CertRenewalError> {
+        tokio::fs::read_to_string(&self.config.key_path)
+            .await
+            .map_err(|source| CertRenewalError::InvalidPrivateKey {
+                path: self.config.key_path.clone(),
+                source,
+            })
+    }
+
+    /// Enrol using the EST `simplereenroll` endpoint, authenticated with the current certificate
+    async fn enrol_with_est(
+        &self,
+        url: &str,
+        csr: &KeyCertPair,
+    ) -> Result<String, CertRenewalError> {
+        let mut identity_pem = self.read_private_key().await?.into_bytes();
+        identity_pem.extend(tokio::fs::read(&self.config.cert_path).await?);
+        let identity = reqwest::Identity::from_pem(&identity_pem)?;
+
+        let client = self
+            .config
+            .root_certs
+            .client_builder()
+            .identity(identity)
+            .build()?;
+        let response = client
+            .post(format!("{url}/simplereenroll"))
+            .header(CONTENT_TYPE, "application/pkcs10")
+            .header("Content-Transfer-Encoding", "base64")
+            .body(est::encode_csr(&csr.certificate_signing_request_der()?))
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(CertRenewalError::EnrolmentFailed(format!(
+                "{status} {body}"
+            )));
+        }
+
+        let body = response.bytes().await?;
+        Ok(est::decode_certificate(&body)?)
+    }
+
+    /// Enrol using a script given the path to the CSR and printing the new certificate on stdout
+    async fn enrol_with_script(
+        &self,
+        script: &Utf8Path,
+        csr: &KeyCertPair,
+    ) -> Result<String, CertRenewalError> {
+        let csr_path = self.config.tmp_dir.join("tedge-cert-renew.csr");
+        atomically_write_file_async(
+            &csr_path,
+            csr.certificate_signing_request_string()?.as_bytes(),
+        )
+        .await?;
+
+        let output = Command::new(script).arg(&csr_path).output().await;
+        let _ = tokio::fs::remove_file(&csr_path).await;
+
+        let script_failed = |reason: String| CertRenewalError::ScriptFailed {
+            script: script.to_owned(),
+            reason,
+        };
+        let output = output.map_err(|err| script_failed(err.to_string()))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(script_failed(format!(
+                "{}: {}",
+                output.status,
+                stderr.trim()
+            )));
+        }
+
+        Ok(est::decode_certificate(&output.stdout)?)
+    }
+
+    /// Replace the device certificate, along with the private key if a new one has been generated
+    async fn install_certificate(
+        &self,
+        certificate_pem: &str,
+        new_key: Option<&KeyCertPair>,
+    ) -> Result<(), CertRenewalError> {
+        let Some(new_key) = new_key else {
+            atomically_write_file_async(&self.config.cert_path, certificate_pem.as_bytes()).await?;
+            return Ok(());
+        };
+
+        let previous_key = self.read_private_key().await?;
+        write_private_key(
+            &self.config.key_path,
+            new_key.private_key_pem_string()?.as_bytes(),
+        )
+        .await?;
+
+        if let Err(err) =
+            atomically_write_file_async(&self.config.cert_path, certificate_pem.as_bytes()).await
+        {
+            // Keep the private key matching the certificate
+            if let Err(err) =
+                write_private_key(&self.config.key_path, previous_key.as_bytes()).await
+            {
+                error!("Fail to restore the previous private key: {err}");
+            }
+            return Err(err.into());
+        }
+
+        Ok(())
+    }
+
+    async fn reconnect(&self, cloud: &str) -> Result<(), CertRenewalError> {
+        info!("Reconnecting {cloud} with the renewed certificate");
+        let mut command = tokio::process::Command::from(self.config.sudo.command("tedge"));
+        let output = command
+            .args(["reconnect", cloud])
+            .output()
+            .await
+            .map_err(|err| CertRenewalError::ReconnectFailed {
+                cloud: cloud.to_string(),
+                reason: err.to_string(),
+            })?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(CertRenewalError::ReconnectFailed {
+                cloud: cloud.to_string(),
+                reason: stderr.trim().to_string(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Return true if a certificate expiring on `not_after` has to be renewed
+///
+/// A window too large to be added to the current date covers any expiry date.
+pub fn renewal_due(not_after: OffsetDateTime, now: OffsetDateTime, window: Duration) -> bool {
+    time::Duration::try_from(window)
+        .ok()
+        .and_then(|window| now.checked_add(window))
+        .map_or(true, |renewal_date| renewal_date >= not_after)
+}
+
+/// Write a private key, making sure it is never readable by others, even transiently
+async fn write_private_key(path: &Utf8Path, content: &[u8]) -> Result<(), std::io::Error> {
+    let tmp_path = path.with_extension("new");
+    let mut file = tokio::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&tmp_path)
+        .await?;
+    file.write_all(content).await?;
+    file.sync_all().await?;
+    tokio::fs::rename(&tmp_path, path).await
+}
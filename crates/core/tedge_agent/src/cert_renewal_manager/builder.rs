@@ -0,0 +1,105 @@
+use crate::cert_renewal_manager::actor::CertRenewalInput;
+use crate::cert_renewal_manager::actor::CertRenewalManagerActor;
+use crate::cert_renewal_manager::config::CertRenewalConfig;
+use tedge_actors::Builder;
+use tedge_actors::CloneSender;
+use tedge_actors::DynSender;
+use tedge_actors::LinkError;
+use tedge_actors::LoggingSender;
+use tedge_actors::MappingSender;
+use tedge_actors::MessageSink;
+use tedge_actors::MessageSource;
+use tedge_actors::NoConfig;
+use tedge_actors::RuntimeRequest;
+use tedge_actors::RuntimeRequestSink;
+use tedge_actors::SimpleMessageBoxBuilder;
+use tedge_api::commands::CertRenewCmd;
+use tedge_api::mqtt_topics::ChannelFilter;
+use tedge_api::mqtt_topics::EntityFilter;
+use tedge_api::mqtt_topics::OperationType;
+use tedge_api::workflow::GenericCommandData;
+use tedge_api::workflow::GenericCommandState;
+use tedge_api::workflow::OperationName;
+use tedge_mqtt_ext::MqttMessage;
+use tedge_mqtt_ext::TopicFilter;
+
+pub struct CertRenewalManagerBuilder {
+    config: CertRenewalConfig,
+    message_box: SimpleMessageBoxBuilder<CertRenewalInput, CertRenewCmd>,
+    mqtt_publisher: DynSender<MqttMessage>,
+}
+
+impl CertRenewalManagerBuilder {
+    pub fn new(
+        config: CertRenewalConfig,
+        mqtt: &mut (impl MessageSource<MqttMessage, TopicFilter> + MessageSink<MqttMessage>),
+    ) -> Self {
+        let message_box = SimpleMessageBoxBuilder::new("CertRenewalManager", 10);
+        // Watch the device cert_renew commands, to not trigger a renewal while one is in progress
+        let commands = config.mqtt_schema.topics(
+            EntityFilter::Entity(&config.device_topic_id),
+            ChannelFilter::Command(OperationType::CertRenew),
+        );
+        mqtt.connect_sink(commands, &message_box.get_sender());
+
+        Self {
+            config,
+            message_box,
+            mqtt_publisher: mqtt.get_sender(),
+        }
+    }
+}
+
+impl MessageSink<CertRenewCmd> for CertRenewalManagerBuilder {
+    fn get_sender(&self) -> DynSender<CertRenewCmd> {
+        self.message_box.get_sender().sender_clone()
+    }
+}
+
+impl MessageSource<GenericCommandData, NoConfig> for CertRenewalManagerBuilder {
+    fn connect_sink(&mut self, config: NoConfig, peer: &impl MessageSink<GenericCommandData>) {
+        self.message_box.connect_sink(config, &peer.get_sender())
+    }
+}
+
+impl MessageSource<CertRenewCmd, NoConfig> for CertRenewalManagerBuilder {
+    fn connect_sink(&mut self, config: NoConfig, peer: &impl MessageSink<CertRenewCmd>) {
+        self.message_box.connect_sink(config, peer)
+    }
+}
+
+impl IntoIterator for &CertRenewalManagerBuilder {
+    type Item = (OperationName, DynSender<GenericCommandState>);
+    type IntoIter = std::vec::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let sender = MappingSender::new(
+            self.message_box.get_sender().sender_clone(),
+            |msg: GenericCommandState| {
+                CertRenewCmd::try_from(msg)
+                    .map(CertRenewalInput::CertRenewCmd)
+                    .ok()
+            },
+        );
+        vec![(OperationType::CertRenew.to_string(), sender.into())].into_iter()
+    }
+}
+
+impl RuntimeRequestSink for CertRenewalManagerBuilder {
+    fn get_signal_sender(&self) -> DynSender<RuntimeRequest> {
+        self.message_box.get_signal_sender()
+    }
+}
+
+impl Builder<CertRenewalManagerActor> for CertRenewalManagerBuilder {
+    type Error = LinkError;
+
+    fn try_build(self) -> Result<CertRenewalManagerActor, Self::Error> {
+        Ok(self.build())
+    }
+
+    fn build(self) -> CertRenewalManagerActor {
+        let mqtt_publisher = LoggingSender::new("MqttPublisher".into(), self.mqtt_publisher);
+        CertRenewalManagerActor::new(self.config, self.message_box.build(), mqtt_publisher)
+    }
+}
@@ -0,0 +1,176 @@
+use crate::cert_renewal_manager::error::CertRenewalError;
+use camino::Utf8PathBuf;
+use certificate::CloudRootCerts;
+use certificate::NewCertificateConfig;
+use std::time::Duration;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_config::models::CertRenewalMethod;
+use tedge_config::tedge_toml::Cloud;
+use tedge_config::SudoCommandBuilder;
+use tedge_config::TEdgeConfig;
+
+#[derive(Debug, Clone)]
+pub struct CertRenewalConfig {
+    pub mqtt_schema: MqttSchema,
+    pub device_topic_id: EntityTopicId,
+    pub tmp_dir: Utf8PathBuf,
+    pub cert_path: Utf8PathBuf,
+    pub key_path: Utf8PathBuf,
+
+    /// When not enabled, the certificate is only renewed on an explicit `cert_renew` command
+    pub enable: bool,
+    pub window: Duration,
+    pub check_interval: Duration,
+    pub rotate_key: bool,
+    pub csr_template: NewCertificateConfig,
+    pub method: CertRenewalMethod,
+    pub est_url: Option<String>,
+    pub script: Option<Utf8PathBuf>,
+    /// The base URL of the local Cumulocity proxy, set only when Cumulocity is configured
+    pub c8y_proxy_url: Option<String>,
+    pub reconnect: Option<String>,
+
+    pub root_certs: CloudRootCerts,
+    pub sudo: SudoCommandBuilder,
+
+    /// The reason why the device certificate settings are invalid, if so
+    ///
+    /// This is only reported when the certificate is checked or renewed,
+    /// so the agent can start even if these settings are not used.
+    pub invalid_device_settings: Option<String>,
+}
+
+/// How a new certificate is obtained, once the configuration has been resolved
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Enrolment {
+    /// POST the CSR to the `simplereenroll` endpoint of an EST server,
+    /// possibly through the local Cumulocity proxy
+    Est { url: String },
+
+    /// Run a script with the path to the CSR, reading the new certificate from its stdout
+    Script { script: Utf8PathBuf },
+}
+
+impl CertRenewalConfig {
+    pub fn from_tedge_config(
+        mqtt_schema: MqttSchema,
+        device_topic_id: &EntityTopicId,
+        tedge_config: &TEdgeConfig,
+    ) -> CertRenewalConfig {
+        let cert_renewal = &tedge_config.agent.cert_renewal;
+        // The requests to Cumulocity are sent through the local proxy of the mapper,
+        // which adds the JWT or basic-auth header required by Cumulocity
+        let c8y_proxy_url = tedge_config
+            .c8y
+            .try_get::<str>(None)
+            .ok()
+            .filter(|c8y| c8y.http.or_none().is_some())
+            .map(|c8y| {
+                let protocol = match c8y.proxy.cert_path.or_none() {
+                    Some(_) => "https",
+                    None => "http",
+                };
+                let proxy = &c8y.proxy.client;
+                format!("{protocol}://{}:{}/c8y", proxy.host, proxy.port)
+            });
+
+        let mut invalid_device_settings = None;
+        let cert_path = or_invalid(
+            tedge_config
+                .device_cert_path(None::<Cloud>)
+                .map(ToOwned::to_owned),
+            &mut invalid_device_settings,
+        );
+        let key_path = or_invalid(
+            tedge_config
+                .device_key_path(None::<Cloud>)
+                .map(ToOwned::to_owned),
+            &mut invalid_device_settings,
+        );
+        let csr_template = or_invalid(
+            tedge_config.device.cert.new_certificate_config(),
+            &mut invalid_device_settings,
+        );
+
+        CertRenewalConfig {
+            mqtt_schema,
+            device_topic_id: device_topic_id.clone(),
+            tmp_dir: tedge_config.tmp.path.clone(),
+            cert_path,
+            key_path,
+            enable: cert_renewal.enable,
+            window: cert_renewal.window.duration(),
+            check_interval: cert_renewal.check_interval.duration(),
+            rotate_key: cert_renewal.rotate_key,
+            csr_template,
+            method: cert_renewal.method,
+            est_url: cert_renewal.url.or_none().cloned(),
+            script: cert_renewal.script.or_none().cloned(),
+            c8y_proxy_url,
+            reconnect: cert_renewal.reconnect.or_none().cloned(),
+            root_certs: tedge_config.cloud_root_certs(),
+            sudo: SudoCommandBuilder::new(tedge_config),
+            invalid_device_settings,
+        }
+    }
+
+    /// Check that the device certificate settings are valid
+    pub fn check_device_settings(&self) -> Result<(), CertRenewalError> {
+        match &self.invalid_device_settings {
+            Some(reason) => Err(CertRenewalError::InvalidConfiguration {
+                key: "device",
+                reason: reason.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Resolve the enrolment method, checking the settings it requires are set
+    pub fn enrolment(&self) -> Result<Enrolment, CertRenewalError> {
+        let missing = |key| CertRenewalError::MissingConfiguration {
+            key,
+            method: self.method.to_string(),
+        };
+        match self.method {
+            CertRenewalMethod::Est => {
+                let url = self
+                    .est_url
+                    .as_ref()
+                    .ok_or_else(|| missing("agent.cert_renewal.url"))?;
+                Ok(Enrolment::Est {
+                    url: url.trim_end_matches('/').to_string(),
+                })
+            }
+            CertRenewalMethod::C8y => {
+                let proxy_url = self
+                    .c8y_proxy_url
+                    .as_ref()
+                    .ok_or_else(|| missing("c8y.http"))?;
+                Ok(Enrolment::Est {
+                    url: format!("{proxy_url}/.well-known/est"),
+                })
+            }
+            CertRenewalMethod::Script => {
+                let script = self
+                    .script
+                    .as_ref()
+                    .ok_or_else(|| missing("agent.cert_renewal.script"))?;
+                Ok(Enrolment::Script {
+                    script: script.clone(),
+                })
+            }
+        }
+    }
+}
+
+/// Return the resolved value of a setting, or a default value recording why it is invalid
+fn or_invalid<T: Default, E: std::fmt::Display>(
+    result: Result<T, E>,
+    invalid_settings: &mut Option<String>,
+) -> T {
+    result.unwrap_or_else(|err| {
+        invalid_settings.get_or_insert(err.to_string());
+        T::default()
+    })
+}
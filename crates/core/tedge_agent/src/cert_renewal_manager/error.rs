@@ -0,0 +1,43 @@
+use camino::Utf8PathBuf;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CertRenewalError {
+    #[error("Fail to read the device certificate {path}: {reason}")]
+    InvalidDeviceCertificate { path: Utf8PathBuf, reason: String },
+
+    #[error("Fail to read the device private key {path}: {source}")]
+    InvalidPrivateKey {
+        path: Utf8PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("No {key} is configured while required by the {method} certificate renewal method")]
+    MissingConfiguration { key: &'static str, method: String },
+
+    #[error("Invalid {key} settings: {reason}")]
+    InvalidConfiguration { key: &'static str, reason: String },
+
+    #[error(transparent)]
+    FromCertificate(#[from] certificate::CertificateError),
+
+    #[error("The enrolment server returned an error: {0}")]
+    EnrolmentFailed(String),
+
+    #[error(transparent)]
+    FromReqwest(#[from] reqwest::Error),
+
+    #[error("The renewal script {script} failed: {reason}")]
+    ScriptFailed { script: Utf8PathBuf, reason: String },
+
+    #[error("The renewed certificate is issued for {actual} and not for {expected}")]
+    UnexpectedCommonName { expected: String, actual: String },
+
+    #[error("Fail to reconnect {cloud}: {reason}")]
+    ReconnectFailed { cloud: String, reason: String },
+
+    #[error(transparent)]
+    FromIo(#[from] std::io::Error),
+
+    #[error(transparent)]
+    FromAtomFileError(#[from] tedge_utils::fs::AtomFileError),
+}
@@ -0,0 +1,359 @@
+use crate::cert_renewal_manager::actor::renewal_due;
+use crate::cert_renewal_manager::builder::CertRenewalManagerBuilder;
+use crate::cert_renewal_manager::config::CertRenewalConfig;
+use crate::cert_renewal_manager::config::Enrolment;
+use crate::cert_renewal_manager::error::CertRenewalError;
+use certificate::CloudRootCerts;
+use certificate::KeyCertPair;
+use certificate::KeyKind;
+use certificate::NewCertificateConfig;
+use certificate::PemCertificate;
+use std::os::unix::fs::PermissionsExt;
+use std::time::Duration;
+use tedge_actors::test_helpers::MessageReceiverExt;
+use tedge_actors::test_helpers::TimedMessageBox;
+use tedge_actors::Actor;
+use tedge_actors::Builder;
+use tedge_actors::DynError;
+use tedge_actors::MessageReceiver;
+use tedge_actors::MessageSource;
+use tedge_actors::NoConfig;
+use tedge_actors::Sender;
+use tedge_actors::SimpleMessageBox;
+use tedge_actors::SimpleMessageBoxBuilder;
+use tedge_api::commands::CertRenewCmd;
+use tedge_api::commands::CommandStatus;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_config::models::CertRenewalMethod;
+use tedge_config::SudoCommandBuilder;
+use tedge_mqtt_ext::MqttMessage;
+use tedge_test_utils::fs::TempTedgeDir;
+use time::OffsetDateTime;
+
+const TEST_TIMEOUT_MS: Duration = Duration::from_millis(3000);
+
+#[test]
+fn renewal_is_due_within_the_window() {
+    let now = OffsetDateTime::now_utc();
+    let window = Duration::from_secs(30 * 24 * 3600);
+
+    assert!(!renewal_due(now + time::Duration::days(60), now, window));
+    assert!(renewal_due(now + time::Duration::days(20), now, window));
+    assert!(renewal_due(now - time::Duration::days(1), now, window));
+}
+
+#[test]
+fn a_huge_renewal_window_makes_the_renewal_due() {
+    let now = OffsetDateTime::now_utc();
+
+    assert!(renewal_due(
+        now + time::Duration::days(60),
+        now,
+        Duration::MAX
+    ));
+}
+
+#[tokio::test]
+async fn invalid_csr_settings_are_reported_as_a_failed_renewal() -> Result<(), DynError> {
+    let temp_dir = TempTedgeDir::new();
+    let mut config = test_config(&temp_dir, 10);
+    config.invalid_device_settings = Some("invalid subject alternative name".to_string());
+
+    let (mut converter_box, _mqtt_box) = spawn_cert_renewal_manager(config).await?;
+    converter_box
+        .send(cert_renew_cmd(CommandStatus::Scheduled))
+        .await?;
+
+    converter_box.skip(1).await;
+    let response = converter_box.recv().await.unwrap();
+    assert!(
+        matches!(response.status(), CommandStatus::Failed { reason } if reason.contains("invalid subject alternative name"))
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn renew_certificate_using_a_script() -> Result<(), DynError> {
+    let temp_dir = TempTedgeDir::new();
+    let config = test_config(&temp_dir, 10);
+    let renewed_certificate = new_certificate("test-device", 365);
+    enrolment_script(&temp_dir, &format!("cat <<EOF\n{renewed_certificate}EOF"));
+
+    let (mut converter_box, _mqtt_box) = spawn_cert_renewal_manager(config.clone()).await?;
+    converter_box
+        .send(cert_renew_cmd(CommandStatus::Scheduled))
+        .await?;
+
+    let status = converter_box.recv().await.unwrap().status();
+    assert_eq!(status, CommandStatus::Executing);
+
+    let response = converter_box.recv().await.unwrap();
+    assert_eq!(response.status(), CommandStatus::Successful);
+
+    let installed_certificate = std::fs::read_to_string(&config.cert_path)?;
+    assert_eq!(installed_certificate.trim(), renewed_certificate.trim());
+    let expected_expiry = PemCertificate::from_pem_string(&renewed_certificate)?.expiry()?;
+    assert_eq!(response.payload.not_after, Some(expected_expiry));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn the_echo_of_the_executing_state_is_not_reported_as_failed() -> Result<(), DynError> {
+    let temp_dir = TempTedgeDir::new();
+    let config = test_config(&temp_dir, 10);
+    let renewed_certificate = new_certificate("test-device", 365);
+    enrolment_script(&temp_dir, &format!("cat <<EOF\n{renewed_certificate}EOF"));
+
+    let (mut converter_box, _mqtt_box) = spawn_cert_renewal_manager(config).await?;
+    converter_box
+        .send(cert_renew_cmd(CommandStatus::Scheduled))
+        .await?;
+
+    // The workflow actor forwards back the executing state sent by the renewal manager
+    let executing = converter_box.recv().await.unwrap();
+    assert_eq!(executing.status(), CommandStatus::Executing);
+    converter_box.send(executing).await?;
+
+    let response = converter_box.recv().await.unwrap();
+    assert_eq!(response.status(), CommandStatus::Successful);
+    assert!(converter_box.recv().await.is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn an_executing_renewal_left_by_a_previous_run_is_failed() -> Result<(), DynError> {
+    let temp_dir = TempTedgeDir::new();
+    let config = test_config(&temp_dir, 10);
+
+    let (mut converter_box, _mqtt_box) = spawn_cert_renewal_manager(config).await?;
+    converter_box
+        .send(cert_renew_cmd(CommandStatus::Executing))
+        .await?;
+
+    let response = converter_box.recv().await.unwrap();
+    assert!(matches!(response.status(), CommandStatus::Failed { .. }));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn reject_a_certificate_issued_for_another_device() -> Result<(), DynError> {
+    let temp_dir = TempTedgeDir::new();
+    let config = test_config(&temp_dir, 10);
+    let initial_certificate = std::fs::read_to_string(&config.cert_path)?;
+    let other_certificate = new_certificate("other-device", 365);
+    enrolment_script(&temp_dir, &format!("cat <<EOF\n{other_certificate}EOF"));
+
+    let (mut converter_box, _mqtt_box) = spawn_cert_renewal_manager(config.clone()).await?;
+    converter_box
+        .send(cert_renew_cmd(CommandStatus::Scheduled))
+        .await?;
+
+    converter_box.skip(1).await;
+    let response = converter_box.recv().await.unwrap();
+    assert!(
+        matches!(response.status(), CommandStatus::Failed { reason } if reason.contains("other-device"))
+    );
+
+    // The current certificate is left untouched
+    assert_eq!(
+        std::fs::read_to_string(&config.cert_path)?,
+        initial_certificate
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn report_script_failures() -> Result<(), DynError> {
+    let temp_dir = TempTedgeDir::new();
+    let config = test_config(&temp_dir, 10);
+    enrolment_script(&temp_dir, "echo 'CA is unreachable' >&2; exit 1");
+
+    let (mut converter_box, _mqtt_box) = spawn_cert_renewal_manager(config).await?;
+    converter_box
+        .send(cert_renew_cmd(CommandStatus::Scheduled))
+        .await?;
+
+    converter_box.skip(1).await;
+    let response = converter_box.recv().await.unwrap();
+    assert!(
+        matches!(response.status(), CommandStatus::Failed { reason } if reason.contains("CA is unreachable"))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn cumulocity_enrolment_goes_through_the_local_proxy() {
+    let temp_dir = TempTedgeDir::new();
+    let mut config = test_config(&temp_dir, 365);
+    config.method = CertRenewalMethod::C8y;
+
+    assert!(matches!(
+        config.enrolment(),
+        Err(CertRenewalError::MissingConfiguration {
+            key: "c8y.http",
+            ..
+        })
+    ));
+
+    config.c8y_proxy_url = Some("http://127.0.0.1:8001/c8y".to_string());
+    assert_eq!(
+        config.enrolment().unwrap(),
+        Enrolment::Est {
+            url: "http://127.0.0.1:8001/c8y/.well-known/est".to_string()
+        }
+    );
+}
+
+#[tokio::test]
+async fn trigger_a_renewal_when_the_certificate_is_about_to_expire() -> Result<(), DynError> {
+    let temp_dir = TempTedgeDir::new();
+    let mut config = test_config(&temp_dir, 10);
+    config.enable = true;
+
+    let (_converter_box, mut mqtt_box) = spawn_cert_renewal_manager(config).await?;
+
+    let message = mqtt_box.recv().await.expect("a cert_renew command");
+    assert!(message
+        .topic
+        .name
+        .starts_with("te/device/main///cmd/cert_renew/cert-renew-"));
+    assert_eq!(message.payload_str()?, r#"{"status":"init"}"#);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn no_new_renewal_is_triggered_while_one_is_in_progress() -> Result<(), DynError> {
+    let temp_dir = TempTedgeDir::new();
+    let mut config = test_config(&temp_dir, 10);
+    config.enable = true;
+    config.check_interval = Duration::from_millis(100);
+
+    let (_converter_box, mut mqtt_box) = spawn_cert_renewal_manager(config).await?;
+
+    let command = mqtt_box.recv().await.expect("a cert_renew command");
+    assert_eq!(command.payload_str()?, r#"{"status":"init"}"#);
+
+    // Several checks are done within the renewal window, but the renewal is still in progress
+    assert!(mqtt_box.recv().await.is_none());
+
+    // Once the renewal is over, a new one can be triggered
+    mqtt_box
+        .send(MqttMessage::new(
+            &command.topic,
+            r#"{"status":"failed","reason":"CA is unreachable"}"#,
+        ))
+        .await?;
+    let command = mqtt_box.recv().await.expect("a new cert_renew command");
+    assert_eq!(command.payload_str()?, r#"{"status":"init"}"#);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn no_renewal_when_the_certificate_is_not_about_to_expire() -> Result<(), DynError> {
+    let temp_dir = TempTedgeDir::new();
+    let mut config = test_config(&temp_dir, 365);
+    config.enable = true;
+
+    let (_converter_box, mut mqtt_box) = spawn_cert_renewal_manager(config).await?;
+
+    assert!(mqtt_box.recv().await.is_none());
+
+    Ok(())
+}
+
+fn new_certificate(common_name: &str, validity_period_days: u32) -> String {
+    let config = NewCertificateConfig {
+        validity_period_days,
+        ..NewCertificateConfig::default()
+    };
+    KeyCertPair::new_selfsigned_certificate(&config, common_name, &KeyKind::New)
+        .unwrap()
+        .certificate_pem_string()
+        .unwrap()
+}
+
+/// Prepare a config with a device certificate expiring in `validity_period_days`
+fn test_config(temp_dir: &TempTedgeDir, validity_period_days: u32) -> CertRenewalConfig {
+    let config = NewCertificateConfig {
+        validity_period_days,
+        ..NewCertificateConfig::default()
+    };
+    let device_cert =
+        KeyCertPair::new_selfsigned_certificate(&config, "test-device", &KeyKind::New).unwrap();
+    temp_dir
+        .file("tedge-certificate.pem")
+        .with_raw_content(&device_cert.certificate_pem_string().unwrap());
+    temp_dir
+        .file("tedge-private-key.pem")
+        .with_raw_content(&device_cert.private_key_pem_string().unwrap());
+
+    CertRenewalConfig {
+        mqtt_schema: MqttSchema::default(),
+        device_topic_id: EntityTopicId::default_main_device(),
+        tmp_dir: temp_dir.utf8_path_buf(),
+        cert_path: temp_dir.utf8_path().join("tedge-certificate.pem"),
+        key_path: temp_dir.utf8_path().join("tedge-private-key.pem"),
+        enable: false,
+        window: Duration::from_secs(30 * 24 * 3600),
+        check_interval: Duration::from_secs(3600),
+        rotate_key: false,
+        csr_template: NewCertificateConfig::default(),
+        method: CertRenewalMethod::Script,
+        est_url: None,
+        script: Some(temp_dir.utf8_path().join("renew.sh")),
+        c8y_proxy_url: None,
+        reconnect: None,
+        root_certs: CloudRootCerts::from([]),
+        sudo: SudoCommandBuilder::enabled(false),
+        invalid_device_settings: None,
+    }
+}
+
+fn enrolment_script(temp_dir: &TempTedgeDir, body: &str) {
+    let script = temp_dir
+        .file("renew.sh")
+        .with_raw_content(&format!("#!/bin/sh\n{body}\n"));
+    std::fs::set_permissions(script.path(), std::fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+fn cert_renew_cmd(status: CommandStatus) -> CertRenewCmd {
+    CertRenewCmd::new(&EntityTopicId::default_main_device(), "1234".to_string()).with_status(status)
+}
+
+async fn spawn_cert_renewal_manager(
+    config: CertRenewalConfig,
+) -> Result<
+    (
+        TimedMessageBox<SimpleMessageBox<CertRenewCmd, CertRenewCmd>>,
+        TimedMessageBox<SimpleMessageBox<MqttMessage, MqttMessage>>,
+    ),
+    DynError,
+> {
+    let mut converter_builder: SimpleMessageBoxBuilder<CertRenewCmd, CertRenewCmd> =
+        SimpleMessageBoxBuilder::new("Converter", 5);
+    let mut mqtt_builder: SimpleMessageBoxBuilder<MqttMessage, MqttMessage> =
+        SimpleMessageBoxBuilder::new("MQTT", 5);
+
+    let mut actor_builder = CertRenewalManagerBuilder::new(config, &mut mqtt_builder);
+    converter_builder.connect_sink(NoConfig, &actor_builder);
+    actor_builder.connect_sink(NoConfig, &converter_builder);
+
+    let converter_box = converter_builder.build().with_timeout(TEST_TIMEOUT_MS);
+    let mqtt_box = mqtt_builder
+        .build()
+        .with_timeout(Duration::from_millis(500));
+
+    let actor = actor_builder.build();
+    tokio::spawn(async move { actor.run().await });
+
+    Ok((converter_box, mqtt_box))
+}
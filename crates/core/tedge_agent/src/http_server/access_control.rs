@@ -0,0 +1,666 @@
+//! Access control for the agent HTTP API.
+//!
+//! When `http.access_policy_path` is set, every request to the file transfer service and the
+//! entity store must be made by a client declared in the policy file. Clients are identified
+//! either by a bearer token (`Authorization: Bearer <token>`) or by the common name of the
+//! client certificate they used to establish the TLS connection.
+//!
+//! A client is either an `admin`, with unrestricted access, or scoped to a single entity:
+//!
+//! - it can only read, write and delete files under its own `file-transfer` subtree
+//! - it can only register the entity of its topic id, the services of this entity
+//!   and the child devices declared with this entity as parent
+//! - it can only read, update or deregister this entity and its direct children
+//!
+//! ```toml
+//! # Whether requests without any credentials are granted full access (default: false)
+//! allow_anonymous = false
+//!
+//! [[clients]]
+//! name = "c8y-mapper"
+//! common_name = "my-device"
+//! admin = true
+//!
+//! [[clients]]
+//! name = "child01"
+//! token = "a-long-random-secret"
+//! topic_id = "device/child01//"
+//! # Defaults to the client name
+//! file_transfer_dir = "child01"
+//! ```
+use axum::extract::FromRequestParts;
+use axum::extract::Request;
+use axum::extract::State;
+use axum::http::header::AUTHORIZATION;
+use axum::http::request::Parts;
+use axum::http::HeaderMap;
+use axum::middleware::Next;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use axum::Json;
+use axum_tls::TlsData;
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use hyper::StatusCode;
+use serde::Deserialize;
+use serde_json::json;
+use std::convert::Infallible;
+use std::sync::Arc;
+use tedge_api::entity::EntityType;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tracing::warn;
+
+/// The access policy of the agent HTTP API, as loaded from `http.access_policy_path`
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct AccessPolicy {
+    /// Grant full access to the requests made without any credentials
+    #[serde(default)]
+    pub allow_anonymous: bool,
+
+    #[serde(default)]
+    pub clients: Vec<ClientPolicy>,
+}
+
+/// The credentials and permissions of a client of the agent HTTP API
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ClientPolicy {
+    /// A name used to identify the client in the logs
+    pub name: String,
+
+    /// A bearer token the client authenticates with
+    pub token: Option<String>,
+
+    /// The common name of the certificate the client authenticates with
+    pub common_name: Option<String>,
+
+    /// Grant unrestricted access to this client
+    #[serde(default)]
+    pub admin: bool,
+
+    /// The entity this client is restricted to, if not an admin
+    pub topic_id: Option<EntityTopicId>,
+
+    /// The directory, relative to the file transfer root, this client is restricted to.
+    ///
+    /// Defaults to the client name.
+    pub file_transfer_dir: Option<Utf8PathBuf>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum AccessPolicyError {
+    #[error("Failed to read the access policy file {path}: {source}")]
+    Read {
+        path: Utf8PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("Failed to parse the access policy file {path}: {source}")]
+    Parse {
+        path: Utf8PathBuf,
+        source: toml::de::Error,
+    },
+
+    #[error("Client {0:?} has neither a `token` nor a `common_name` to authenticate with")]
+    MissingCredentials(String),
+
+    #[error("Client {0:?} is not an admin and must be restricted to a `topic_id`")]
+    MissingTopicId(String),
+
+    #[error("Client {0:?} has an invalid `file_transfer_dir`: it must be a relative path")]
+    InvalidFileTransferDir(String),
+
+    #[error("Client {0:?} has an invalid name to be used as its `file_transfer_dir`: it must be a non-empty name without `/` nor `..`")]
+    InvalidName(String),
+}
+
+impl AccessPolicy {
+    pub fn load(path: &Utf8Path) -> Result<Self, AccessPolicyError> {
+        let content = std::fs::read_to_string(path).map_err(|source| AccessPolicyError::Read {
+            path: path.to_owned(),
+            source,
+        })?;
+        let policy: AccessPolicy =
+            toml::from_str(&content).map_err(|source| AccessPolicyError::Parse {
+                path: path.to_owned(),
+                source,
+            })?;
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    fn validate(&self) -> Result<(), AccessPolicyError> {
+        for client in &self.clients {
+            if client.token.is_none() && client.common_name.is_none() {
+                return Err(AccessPolicyError::MissingCredentials(client.name.clone()));
+            }
+            if !client.admin && client.topic_id.is_none() {
+                return Err(AccessPolicyError::MissingTopicId(client.name.clone()));
+            }
+            match &client.file_transfer_dir {
+                Some(dir) => {
+                    if !dir.is_relative() || dir.as_str().split('/').any(|c| c == "..") {
+                        return Err(AccessPolicyError::InvalidFileTransferDir(
+                            client.name.clone(),
+                        ));
+                    }
+                }
+                // The name of a scoped client is then used as its file transfer directory
+                None if !client.admin => {
+                    let name = &client.name;
+                    if name.is_empty() || name.contains('/') || name.contains("..") {
+                        return Err(AccessPolicyError::InvalidName(name.clone()));
+                    }
+                }
+                None => (),
+            }
+        }
+        Ok(())
+    }
+
+    /// Resolve the access granted to a request, given its headers and TLS data
+    fn access_for(
+        &self,
+        headers: &HeaderMap,
+        tls_data: Option<&TlsData>,
+    ) -> Result<Access, AccessDenied> {
+        let token = match headers.get(AUTHORIZATION) {
+            None => None,
+            Some(value) => Some(
+                value
+                    .to_str()
+                    .ok()
+                    .and_then(|value| value.strip_prefix("Bearer "))
+                    .ok_or(AccessDenied::MalformedCredentials)?,
+            ),
+        };
+        let common_name = tls_data.and_then(|data| data.common_name.as_deref());
+
+        if let Some(token) = token {
+            return self
+                .clients
+                .iter()
+                .find(|client| {
+                    client
+                        .token
+                        .as_deref()
+                        .is_some_and(|expected| constant_time_eq(expected, token))
+                })
+                .map(Access::for_client)
+                .ok_or(AccessDenied::UnknownToken);
+        }
+
+        if let Some(common_name) = common_name {
+            return self
+                .clients
+                .iter()
+                .find(|client| client.common_name.as_deref() == Some(common_name))
+                .map(Access::for_client)
+                .ok_or_else(|| AccessDenied::UnknownCommonName(common_name.to_owned()));
+        }
+
+        if self.allow_anonymous {
+            Ok(Access::Unrestricted)
+        } else {
+            Err(AccessDenied::Anonymous)
+        }
+    }
+}
+
+/// The access granted to a request
+#[derive(Debug, Clone)]
+pub enum Access {
+    Unrestricted,
+    Scoped(Arc<ClientPolicy>),
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum AccessDenied {
+    #[error("Authentication required")]
+    Anonymous,
+
+    #[error("Malformed authorization header, expected a bearer token")]
+    MalformedCredentials,
+
+    #[error("Unknown bearer token")]
+    UnknownToken,
+
+    #[error("Unknown client certificate common name: {0}")]
+    UnknownCommonName(String),
+
+    #[error("Client {client:?} is not allowed to access file {path:?}")]
+    File { client: String, path: Utf8PathBuf },
+
+    #[error("File {0:?} is outside of the file transfer directory")]
+    OutsideFileTransferDir(Utf8PathBuf),
+
+    #[error("Client {client:?} is not allowed to access entity {topic_id}")]
+    Entity {
+        client: String,
+        topic_id: EntityTopicId,
+    },
+}
+
+impl AccessDenied {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            AccessDenied::Anonymous
+            | AccessDenied::MalformedCredentials
+            | AccessDenied::UnknownToken
+            | AccessDenied::UnknownCommonName(_) => StatusCode::UNAUTHORIZED,
+            AccessDenied::File { .. }
+            | AccessDenied::OutsideFileTransferDir(_)
+            | AccessDenied::Entity { .. } => StatusCode::FORBIDDEN,
+        }
+    }
+}
+
+/// The response to a denied request
+///
+/// The denial is attached to the response, for the [authorize] middleware to log it.
+impl IntoResponse for AccessDenied {
+    fn into_response(self) -> Response {
+        let mut response = (
+            self.status_code(),
+            Json(json!({ "error": self.to_string() })),
+        )
+            .into_response();
+        response.extensions_mut().insert(self);
+        response
+    }
+}
+
+impl Access {
+    fn for_client(client: &ClientPolicy) -> Self {
+        if client.admin {
+            Access::Unrestricted
+        } else {
+            Access::Scoped(Arc::new(client.clone()))
+        }
+    }
+
+    /// Check that a request can access the given path, relative to the file transfer root.
+    ///
+    /// The path is expected to have already been cleaned from any `..` component.
+    pub fn check_file(&self, relative_path: &Utf8Path) -> Result<(), AccessDenied> {
+        let Access::Scoped(client) = self else {
+            return Ok(());
+        };
+
+        let allowed_dir = client
+            .file_transfer_dir
+            .clone()
+            .unwrap_or_else(|| Utf8PathBuf::from(&client.name));
+        if relative_path.starts_with(&allowed_dir) && relative_path != allowed_dir.as_path() {
+            Ok(())
+        } else {
+            Err(AccessDenied::File {
+                client: client.name.clone(),
+                path: relative_path.to_owned(),
+            })
+        }
+    }
+
+    /// Check that a request can manage the given entity, knowing its parent if any.
+    ///
+    /// A scoped client can only manage the entity it is restricted to and the direct children of this entity.
+    pub fn check_entity(
+        &self,
+        topic_id: &EntityTopicId,
+        parent: Option<&EntityTopicId>,
+    ) -> Result<(), AccessDenied> {
+        let Access::Scoped(client) = self else {
+            return Ok(());
+        };
+
+        let own_topic_id = client.topic_id.as_ref();
+        if own_topic_id == Some(topic_id) || (parent.is_some() && own_topic_id == parent) {
+            Ok(())
+        } else {
+            Err(AccessDenied::Entity {
+                client: client.name.clone(),
+                topic_id: topic_id.clone(),
+            })
+        }
+    }
+
+    /// Check that a request can register the given entity, with the parent declared by the client if any.
+    ///
+    /// A scoped client can register the entities it can manage: the entity it is restricted to,
+    /// a service of this entity, the parent being then resolved from the topic id of the service,
+    /// or a child device declared with this entity as parent.
+    /// The caller has to check that an already registered entity is managed by the client too.
+    pub fn check_registration(
+        &self,
+        topic_id: &EntityTopicId,
+        entity_type: &EntityType,
+        parent: Option<&EntityTopicId>,
+    ) -> Result<(), AccessDenied> {
+        let Access::Scoped(client) = self else {
+            return Ok(());
+        };
+
+        let own_topic_id = client.topic_id.as_ref();
+        let is_own_entity = own_topic_id == Some(topic_id);
+        let is_own_service = topic_id.default_service_parent_identifier().as_ref() == own_topic_id
+            && parent.map_or(true, |parent| Some(parent) == own_topic_id);
+        let is_own_child_device =
+            *entity_type == EntityType::ChildDevice && parent.is_some() && parent == own_topic_id;
+        if own_topic_id.is_some() && (is_own_entity || is_own_service || is_own_child_device) {
+            Ok(())
+        } else {
+            Err(AccessDenied::Entity {
+                client: client.name.clone(),
+                topic_id: topic_id.clone(),
+            })
+        }
+    }
+}
+
+/// Compare two secrets in a time that doesn't depend on the position of the first difference
+fn constant_time_eq(expected: &str, actual: &str) -> bool {
+    let (expected, actual) = (expected.as_bytes(), actual.as_bytes());
+    expected.len() == actual.len()
+        && expected
+            .iter()
+            .zip(actual)
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Requests that went through no [authorize] layer have unrestricted access
+impl<S: Send + Sync> FromRequestParts<S> for Access {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .extensions
+            .get::<Access>()
+            .cloned()
+            .unwrap_or(Access::Unrestricted))
+    }
+}
+
+/// A middleware authenticating the requests against an [AccessPolicy]
+pub(crate) async fn authorize(
+    State(policy): State<Arc<AccessPolicy>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().clone();
+    let path = request.uri().path().to_owned();
+    let tls_data = request.extensions().get::<TlsData>();
+    let response = match policy.access_for(request.headers(), tls_data) {
+        Ok(access) => {
+            request.extensions_mut().insert(access);
+            next.run(request).await
+        }
+        Err(denied) => denied.into_response(),
+    };
+
+    // Denials are logged once, be they raised by this middleware or by a handler
+    if let Some(denied) = response.extensions().get::<AccessDenied>() {
+        warn!("Access denied to {method} {path}: {denied}");
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    const POLICY: &str = r#"
+        [[clients]]
+        name = "mapper"
+        common_name = "my-device"
+        admin = true
+
+        [[clients]]
+        name = "child01"
+        token = "secret"
+        topic_id = "device/child01//"
+    "#;
+
+    fn policy() -> AccessPolicy {
+        let policy: AccessPolicy = toml::from_str(POLICY).unwrap();
+        policy.validate().unwrap();
+        policy
+    }
+
+    fn bearer(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {token}")).unwrap(),
+        );
+        headers
+    }
+
+    fn tls_data(common_name: &str) -> TlsData {
+        TlsData {
+            common_name: Some(Arc::from(common_name)),
+            is_secure: true,
+        }
+    }
+
+    fn topic_id(topic_id: &str) -> EntityTopicId {
+        topic_id.parse().unwrap()
+    }
+
+    #[test]
+    fn anonymous_requests_are_denied_by_default() {
+        let access = policy().access_for(&HeaderMap::new(), None);
+
+        assert_eq!(access.unwrap_err(), AccessDenied::Anonymous);
+    }
+
+    #[test]
+    fn anonymous_requests_can_be_allowed() {
+        let policy = AccessPolicy {
+            allow_anonymous: true,
+            ..policy()
+        };
+
+        let access = policy.access_for(&HeaderMap::new(), None).unwrap();
+
+        assert!(matches!(access, Access::Unrestricted));
+    }
+
+    #[test]
+    fn unknown_tokens_are_rejected() {
+        let access = policy().access_for(&bearer("not-a-secret"), None);
+
+        assert_eq!(access.unwrap_err(), AccessDenied::UnknownToken);
+    }
+
+    #[test]
+    fn unknown_certificates_are_rejected() {
+        let access = policy().access_for(&HeaderMap::new(), Some(&tls_data("intruder")));
+
+        assert_eq!(
+            access.unwrap_err(),
+            AccessDenied::UnknownCommonName("intruder".to_owned())
+        );
+    }
+
+    #[test]
+    fn admin_certificates_have_unrestricted_access() {
+        let access = policy()
+            .access_for(&HeaderMap::new(), Some(&tls_data("my-device")))
+            .unwrap();
+
+        assert!(access.check_file(Utf8Path::new("any/file")).is_ok());
+        assert!(access
+            .check_entity(&topic_id("device/other//"), None)
+            .is_ok());
+    }
+
+    #[test]
+    fn scoped_clients_can_only_access_their_own_file_transfer_subtree() {
+        let access = policy().access_for(&bearer("secret"), None).unwrap();
+
+        assert!(access
+            .check_file(Utf8Path::new("child01/log_upload/file"))
+            .is_ok());
+        assert!(access.check_file(Utf8Path::new("child01")).is_err());
+        assert!(access
+            .check_file(Utf8Path::new("child02/log_upload/file"))
+            .is_err());
+        assert!(access
+            .check_file(Utf8Path::new("child01-evil/file"))
+            .is_err());
+    }
+
+    #[test]
+    fn scoped_clients_can_only_manage_their_own_entities() {
+        let access = policy().access_for(&bearer("secret"), None).unwrap();
+        let own = topic_id("device/child01//");
+
+        assert!(access.check_entity(&own, None).is_ok());
+        assert!(access
+            .check_entity(&topic_id("device/child01/service/app"), Some(&own))
+            .is_ok());
+        assert!(access
+            .check_entity(&topic_id("device/child02//"), None)
+            .is_err());
+        assert!(access
+            .check_entity(
+                &topic_id("device/child03//"),
+                Some(&topic_id("device/child02//"))
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn scoped_clients_can_only_register_their_own_entity_and_services() {
+        let access = policy().access_for(&bearer("secret"), None).unwrap();
+        let own = topic_id("device/child01//");
+        let device = &EntityType::ChildDevice;
+        let service = &EntityType::Service;
+
+        assert!(access.check_registration(&own, device, None).is_ok());
+        assert!(access
+            .check_registration(&topic_id("device/child01/service/app"), service, None)
+            .is_ok());
+        assert!(access
+            .check_registration(&topic_id("device/child01/service/app"), service, Some(&own))
+            .is_ok());
+
+        // The parent declared by the client doesn't widen its scope
+        assert!(access
+            .check_registration(&topic_id("device/child02/service/app"), service, Some(&own))
+            .is_err());
+        assert!(access
+            .check_registration(
+                &topic_id("device/child01/service/app"),
+                service,
+                Some(&topic_id("device/main//"))
+            )
+            .is_err());
+        assert!(access
+            .check_registration(
+                &topic_id("device/child03//"),
+                device,
+                Some(&topic_id("device/child02//"))
+            )
+            .is_err());
+        assert!(access
+            .check_registration(&topic_id("device/child02//"), device, None)
+            .is_err());
+    }
+
+    #[test]
+    fn scoped_clients_can_register_the_child_devices_they_manage() {
+        let access = policy().access_for(&bearer("secret"), None).unwrap();
+        let own = topic_id("device/child01//");
+        let child = topic_id("factory/line1/robot/arm");
+
+        assert!(access
+            .check_registration(&child, &EntityType::ChildDevice, Some(&own))
+            .is_ok());
+        assert!(access.check_entity(&child, Some(&own)).is_ok());
+    }
+
+    #[test]
+    fn tokens_are_compared_byte_for_byte() {
+        assert!(constant_time_eq("secret", "secret"));
+        assert!(!constant_time_eq("secret", "secreT"));
+        assert!(!constant_time_eq("secret", "secret-but-longer"));
+        assert!(!constant_time_eq("secret", ""));
+    }
+
+    #[test]
+    fn clients_must_be_scoped_unless_admin() {
+        let policy: AccessPolicy = toml::from_str(
+            r#"
+            [[clients]]
+            name = "child01"
+            token = "secret"
+            "#,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            policy.validate(),
+            Err(AccessPolicyError::MissingTopicId(_))
+        ));
+    }
+
+    #[test]
+    fn clients_must_have_credentials() {
+        let policy: AccessPolicy = toml::from_str(
+            r#"
+            [[clients]]
+            name = "child01"
+            topic_id = "device/child01//"
+            "#,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            policy.validate(),
+            Err(AccessPolicyError::MissingCredentials(_))
+        ));
+    }
+
+    #[test]
+    fn file_transfer_dir_cannot_escape_the_file_transfer_root() {
+        let policy: AccessPolicy = toml::from_str(
+            r#"
+            [[clients]]
+            name = "child01"
+            token = "secret"
+            topic_id = "device/child01//"
+            file_transfer_dir = "../etc"
+            "#,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            policy.validate(),
+            Err(AccessPolicyError::InvalidFileTransferDir(_))
+        ));
+    }
+
+    #[test]
+    fn the_default_file_transfer_dir_cannot_escape_the_file_transfer_root() {
+        for name in ["../etc", "child01/../..", "..", ""] {
+            let policy: AccessPolicy = toml::from_str(&format!(
+                r#"
+                [[clients]]
+                name = "{name}"
+                token = "secret"
+                topic_id = "device/child01//"
+                "#
+            ))
+            .unwrap();
+
+            assert!(
+                matches!(policy.validate(), Err(AccessPolicyError::InvalidName(_))),
+                "{name:?} must be rejected"
+            );
+        }
+    }
+}
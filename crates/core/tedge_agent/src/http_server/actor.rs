@@ -1,5 +1,8 @@
+use crate::alarm_manager::actor::AlarmRequest;
+use crate::alarm_manager::actor::AlarmResponse;
 use crate::entity_manager::server::EntityStoreRequest;
 use crate::entity_manager::server::EntityStoreResponse;
+use crate::http_server::access_control::AccessPolicy;
 use crate::http_server::error::HttpServerError;
 use crate::http_server::server::http_server;
 use crate::http_server::server::AgentState;
@@ -29,9 +32,11 @@ use tracing::log::info;
 pub struct HttpServerActor {
     file_transfer_dir: Utf8PathBuf,
     rustls_config: Option<ServerConfig>,
+    access_policy: Option<AccessPolicy>,
     signal_receiver: mpsc::Receiver<RuntimeRequest>,
     listener: TcpListener,
     entity_store_handle: ClientMessageBox<EntityStoreRequest, EntityStoreResponse>,
+    alarm_manager_handle: Option<ClientMessageBox<AlarmRequest, AlarmResponse>>,
 }
 
 #[derive(Debug, Clone)]
@@ -43,6 +48,8 @@ pub(crate) struct HttpServerConfig<CertKeyPath = Utf8PathBuf, CaPath = Utf8PathB
     pub key_path: OptionalConfig<CertKeyPath>,
     pub ca_path: OptionalConfig<CaPath>,
     pub bind_addr: SocketAddr,
+    /// The access policy applied to the requests, if any
+    pub access_policy: Option<AccessPolicy>,
 }
 
 /// HTTP file transfer server is stand-alone.
@@ -53,9 +60,15 @@ impl Actor for HttpServerActor {
     }
 
     async fn run(mut self) -> Result<(), RuntimeError> {
-        let agent_state = AgentState::new(self.file_transfer_dir, self.entity_store_handle);
+        let agent_state = AgentState::new(self.file_transfer_dir, self.entity_store_handle)
+            .with_alarm_manager(self.alarm_manager_handle);
 
-        let server = http_server(self.listener, self.rustls_config, agent_state)?;
+        let server = http_server(
+            self.listener,
+            self.rustls_config,
+            self.access_policy,
+            agent_state,
+        )?;
 
         tokio::select! {
             result = server => {
@@ -73,10 +86,12 @@ impl Actor for HttpServerActor {
 pub struct HttpServerBuilder {
     file_transfer_dir: Utf8PathBuf,
     rustls_config: Option<ServerConfig>,
+    access_policy: Option<AccessPolicy>,
     signal_sender: mpsc::Sender<RuntimeRequest>,
     signal_receiver: mpsc::Receiver<RuntimeRequest>,
     listener: TcpListener,
     entity_store_handle: ClientMessageBox<EntityStoreRequest, EntityStoreResponse>,
+    alarm_manager_handle: Option<ClientMessageBox<AlarmRequest, AlarmResponse>>,
 }
 
 impl HttpServerBuilder {
@@ -98,12 +113,23 @@ impl HttpServerBuilder {
                 "File transfer service",
             )?,
             file_transfer_dir: config.file_transfer_dir,
+            access_policy: config.access_policy,
             signal_sender,
             signal_receiver,
             listener,
             entity_store_handle,
+            alarm_manager_handle: None,
         })
     }
+
+    /// Expose the alarms managed by the agent under `/tedge/alarms`
+    pub(crate) fn with_alarm_manager(
+        mut self,
+        alarm_manager: &mut impl Service<AlarmRequest, AlarmResponse>,
+    ) -> Self {
+        self.alarm_manager_handle = Some(ClientMessageBox::new(alarm_manager));
+        self
+    }
 }
 
 impl RuntimeRequestSink for HttpServerBuilder {
@@ -119,9 +145,11 @@ impl Builder<HttpServerActor> for HttpServerBuilder {
         Ok(HttpServerActor {
             file_transfer_dir: self.file_transfer_dir,
             rustls_config: self.rustls_config,
+            access_policy: self.access_policy,
             signal_receiver: self.signal_receiver,
             listener: self.listener,
             entity_store_handle: self.entity_store_handle,
+            alarm_manager_handle: self.alarm_manager_handle,
         })
     }
 }
@@ -382,6 +410,7 @@ mod tests {
             key_path: OptionalConfig::empty("http.key_path"),
             ca_path: OptionalConfig::empty("http.ca_path"),
             bind_addr: ([127, 0, 0, 1], bind_port).into(),
+            access_policy: None,
         }
     }
 
@@ -411,6 +440,7 @@ mod tests {
                 .map(|c| OptionalConfig::present(InjectedValue(c), "http.ca_path"))
                 .unwrap_or_else(|| OptionalConfig::empty("http.ca_path")),
             bind_addr: ([127, 0, 0, 1], 0).into(),
+            access_policy: None,
         })
     }
 }
@@ -0,0 +1,439 @@
+//! This module defines the axum routes and handlers for the alarm management REST APIs.
+//! The following endpoints are supported when the alarm manager is enabled:
+//!
+//! - `GET /v1/alarms`: Lists the active alarms.
+//! - `GET /v1/alarms/*path`: Retrieves the status of an alarm, given an entity topic id followed by an alarm type.
+//! - `PATCH /v1/alarms/*path`: Acknowledges, shelves or restores an alarm.
+use super::access_control::Access;
+use super::access_control::AccessDenied;
+use super::entity_store::parent_for_access;
+use super::server::AgentState;
+use crate::alarm_manager::actor::AlarmRequest;
+use crate::alarm_manager::actor::AlarmResponse;
+use crate::alarm_manager::actor::AlarmUpdate;
+use crate::alarm_manager::error::AlarmManagerError;
+use crate::alarm_manager::store::AlarmStatus;
+use axum::extract::Path;
+use axum::extract::State;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use axum::routing::get;
+use axum::Json;
+use axum::Router;
+use hyper::StatusCode;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::str::FromStr;
+use tedge_actors::ClientMessageBox;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_api::mqtt_topics::TopicIdError;
+use tedge_config::models::SecondsOrHumanTime;
+use time::OffsetDateTime;
+
+/// The changes an operator can apply to an alarm
+#[derive(Debug, Deserialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+enum AlarmStatusUpdate {
+    Acknowledged,
+    Shelved { duration: SecondsOrHumanTime },
+    Active,
+}
+
+#[derive(thiserror::Error, Debug)]
+enum Error {
+    #[error(transparent)]
+    InvalidEntityTopicId(#[from] TopicIdError),
+
+    #[allow(clippy::enum_variant_names)]
+    #[error(transparent)]
+    AlarmManagerError(#[from] AlarmManagerError),
+
+    #[error("Alarm {alarm_type} not found for {entity}")]
+    AlarmNotFound {
+        entity: EntityTopicId,
+        alarm_type: String,
+    },
+
+    #[allow(clippy::enum_variant_names)]
+    #[error("Failed to reach the alarm manager")]
+    ChannelError(#[from] tedge_actors::ChannelError),
+
+    #[error("Received unexpected response from the alarm manager")]
+    InvalidAlarmManagerResponse,
+
+    #[error("Invalid shelve duration: {0}")]
+    InvalidShelveDuration(String),
+
+    #[error(transparent)]
+    EntityStore(#[from] super::entity_store::Error),
+
+    #[error(transparent)]
+    AccessDenied(#[from] AccessDenied),
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let err = match self {
+            Error::AccessDenied(denied) => return denied.into_response(),
+            Error::EntityStore(err) => return err.into_response(),
+            err => err,
+        };
+        let status_code = match &err {
+            Error::InvalidEntityTopicId(_) => StatusCode::BAD_REQUEST,
+            Error::AlarmManagerError(err) => match err {
+                AlarmManagerError::UnknownAlarm { .. } => StatusCode::NOT_FOUND,
+                AlarmManagerError::InvalidAlarmPath(_) => StatusCode::BAD_REQUEST,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            },
+            Error::AlarmNotFound { .. } => StatusCode::NOT_FOUND,
+            Error::ChannelError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::InvalidAlarmManagerResponse => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::InvalidShelveDuration(_) => StatusCode::BAD_REQUEST,
+            Error::EntityStore(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::AccessDenied(err) => err.status_code(),
+        };
+        let error_message = err.to_string();
+
+        (status_code, Json(json!({ "error": error_message }))).into_response()
+    }
+}
+
+pub(crate) fn alarms_router(state: AgentState) -> Router {
+    Router::new()
+        .route("/v1/alarms", get(list_alarms))
+        .route("/v1/alarms/{*path}", get(get_alarm).patch(update_alarm))
+        .with_state(state)
+}
+
+async fn list_alarms(
+    State(state): State<AgentState>,
+    access: Access,
+) -> Result<Json<Vec<AlarmStatus>>, Error> {
+    let response = alarm_manager(&state)?
+        .await_response(AlarmRequest::List)
+        .await?;
+    let AlarmResponse::List(alarms) = response else {
+        return Err(Error::InvalidAlarmManagerResponse);
+    };
+
+    // A scoped client only sees the alarms of the entities it can manage
+    let mut granted = HashMap::new();
+    let mut visible_alarms = Vec::new();
+    for alarm in alarms {
+        let is_granted = match granted.get(&alarm.source) {
+            Some(is_granted) => *is_granted,
+            None => {
+                let parent = parent_for_access(&state, &access, &alarm.source).await?;
+                let is_granted = access.check_entity(&alarm.source, parent.as_ref()).is_ok();
+                granted.insert(alarm.source.clone(), is_granted);
+                is_granted
+            }
+        };
+        if is_granted {
+            visible_alarms.push(alarm);
+        }
+    }
+    Ok(Json(visible_alarms))
+}
+
+async fn get_alarm(
+    State(state): State<AgentState>,
+    access: Access,
+    Path(path): Path<String>,
+) -> Result<Json<AlarmStatus>, Error> {
+    let (entity, alarm_type) = parse_alarm_path(&path)?;
+    check_alarm_access(&state, &access, &entity).await?;
+
+    let response = alarm_manager(&state)?
+        .await_response(AlarmRequest::Get {
+            source: entity.clone(),
+            alarm_type: alarm_type.clone(),
+        })
+        .await?;
+    let AlarmResponse::Get(alarm) = response else {
+        return Err(Error::InvalidAlarmManagerResponse);
+    };
+
+    alarm
+        .map(Json)
+        .ok_or(Error::AlarmNotFound { entity, alarm_type })
+}
+
+async fn update_alarm(
+    State(state): State<AgentState>,
+    access: Access,
+    Path(path): Path<String>,
+    Json(update): Json<AlarmStatusUpdate>,
+) -> Result<Json<AlarmStatus>, Error> {
+    let (entity, alarm_type) = parse_alarm_path(&path)?;
+    check_alarm_access(&state, &access, &entity).await?;
+
+    let update = match update {
+        AlarmStatusUpdate::Acknowledged => AlarmUpdate::Acknowledge,
+        AlarmStatusUpdate::Shelved { duration } => AlarmUpdate::Shelve {
+            until: shelved_until(&duration)?,
+        },
+        AlarmStatusUpdate::Active => AlarmUpdate::Activate,
+    };
+    let response = alarm_manager(&state)?
+        .await_response(AlarmRequest::Update {
+            source: entity,
+            alarm_type,
+            update,
+        })
+        .await?;
+    let AlarmResponse::Update(result) = response else {
+        return Err(Error::InvalidAlarmManagerResponse);
+    };
+
+    Ok(Json(result?))
+}
+
+/// Check that a request can manage the alarms of an entity, as it can manage this entity
+async fn check_alarm_access(
+    state: &AgentState,
+    access: &Access,
+    entity: &EntityTopicId,
+) -> Result<(), Error> {
+    let parent = parent_for_access(state, access, entity).await?;
+    Ok(access.check_entity(entity, parent.as_ref())?)
+}
+
+/// The date till which an alarm is shelved, rejecting durations that cannot be represented
+fn shelved_until(duration: &SecondsOrHumanTime) -> Result<OffsetDateTime, Error> {
+    time::Duration::try_from(duration.duration())
+        .ok()
+        .and_then(|duration| OffsetDateTime::now_utc().checked_add(duration))
+        .ok_or_else(|| Error::InvalidShelveDuration(duration.to_string()))
+}
+
+fn alarm_manager(
+    state: &AgentState,
+) -> Result<ClientMessageBox<AlarmRequest, AlarmResponse>, Error> {
+    state
+        .alarm_manager_handle
+        .clone()
+        .ok_or(Error::InvalidAlarmManagerResponse)
+}
+
+/// Split an alarm path into the entity topic id and the alarm type
+fn parse_alarm_path(path: &str) -> Result<(EntityTopicId, String), Error> {
+    let (entity, alarm_type) = path
+        .rsplit_once('/')
+        .filter(|(_, alarm_type)| !alarm_type.is_empty())
+        .ok_or_else(|| AlarmManagerError::InvalidAlarmPath(path.to_string()))?;
+    Ok((EntityTopicId::from_str(entity)?, alarm_type.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AgentState;
+    use crate::alarm_manager::actor::AlarmRequest;
+    use crate::alarm_manager::actor::AlarmResponse;
+    use crate::alarm_manager::actor::AlarmUpdate;
+    use crate::alarm_manager::error::AlarmManagerError;
+    use crate::alarm_manager::store::AlarmState;
+    use crate::alarm_manager::store::AlarmStatus;
+    use crate::alarm_manager::store::Severity;
+    use crate::entity_manager::server::EntityStoreRequest;
+    use crate::entity_manager::server::EntityStoreResponse;
+    use crate::http_server::alarms::alarms_router;
+    use axum::body::Body;
+    use axum::Router;
+    use http_body_util::BodyExt as _;
+    use hyper::Method;
+    use hyper::Request;
+    use hyper::StatusCode;
+    use serde_json::json;
+    use serde_json::Value;
+    use tedge_actors::Builder;
+    use tedge_actors::ClientMessageBox;
+    use tedge_actors::MessageReceiver;
+    use tedge_actors::ServerMessageBox;
+    use tedge_actors::ServerMessageBoxBuilder;
+    use tedge_api::mqtt_topics::EntityTopicId;
+    use tedge_test_utils::fs::TempTedgeDir;
+    use time::OffsetDateTime;
+    use tower::Service;
+
+    #[tokio::test]
+    async fn alarm_get() {
+        let TestHandle {
+            mut app,
+            mut alarm_manager_box,
+        } = setup();
+
+        tokio::spawn(async move {
+            if let Some(mut req) = alarm_manager_box.recv().await {
+                if let AlarmRequest::Get { source, alarm_type } = req.request {
+                    let alarm = (source == EntityTopicId::default_main_device())
+                        .then(|| alarm_status(&alarm_type, AlarmState::Active));
+                    req.reply_to.send(AlarmResponse::Get(alarm)).await.unwrap();
+                }
+            }
+        });
+
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri("/v1/alarms/device/main///temperature_high")
+            .body(Body::empty())
+            .expect("request builder");
+
+        let response = app.call(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let alarm: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(alarm["source"], "device/main//");
+        assert_eq!(alarm["type"], "temperature_high");
+        assert_eq!(alarm["status"], "active");
+    }
+
+    #[tokio::test]
+    async fn alarm_get_invalid_path() {
+        let TestHandle { mut app, .. } = setup();
+
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri("/v1/alarms/device/main//")
+            .body(Body::empty())
+            .expect("request builder");
+
+        let response = app.call(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn alarm_shelve() {
+        let TestHandle {
+            mut app,
+            mut alarm_manager_box,
+        } = setup();
+
+        tokio::spawn(async move {
+            if let Some(mut req) = alarm_manager_box.recv().await {
+                if let AlarmRequest::Update {
+                    alarm_type,
+                    update: AlarmUpdate::Shelve { until },
+                    ..
+                } = req.request
+                {
+                    let mut alarm = alarm_status(&alarm_type, AlarmState::Shelved);
+                    alarm.shelved_until = Some(until);
+                    req.reply_to
+                        .send(AlarmResponse::Update(Ok(alarm)))
+                        .await
+                        .unwrap();
+                }
+            }
+        });
+
+        let req = Request::builder()
+            .method(Method::PATCH)
+            .uri("/v1/alarms/device/main///temperature_high")
+            .header("Content-Type", "application/json")
+            .body(Body::from(
+                json!({"status": "shelved", "duration": "1h"}).to_string(),
+            ))
+            .expect("request builder");
+
+        let response = app.call(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let alarm: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(alarm["status"], "shelved");
+        assert!(alarm["shelvedUntil"].is_string());
+    }
+
+    #[tokio::test]
+    async fn alarm_shelve_for_too_long() {
+        let TestHandle { mut app, .. } = setup();
+
+        let req = Request::builder()
+            .method(Method::PATCH)
+            .uri("/v1/alarms/device/main///temperature_high")
+            .header("Content-Type", "application/json")
+            .body(Body::from(
+                json!({"status": "shelved", "duration": u64::MAX.to_string()}).to_string(),
+            ))
+            .expect("request builder");
+
+        let response = app.call(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn alarm_acknowledge_unknown_alarm() {
+        let TestHandle {
+            mut app,
+            mut alarm_manager_box,
+        } = setup();
+
+        tokio::spawn(async move {
+            if let Some(mut req) = alarm_manager_box.recv().await {
+                if let AlarmRequest::Update {
+                    source, alarm_type, ..
+                } = req.request
+                {
+                    let err = AlarmManagerError::UnknownAlarm {
+                        entity: source,
+                        alarm_type,
+                    };
+                    req.reply_to
+                        .send(AlarmResponse::Update(Err(err)))
+                        .await
+                        .unwrap();
+                }
+            }
+        });
+
+        let req = Request::builder()
+            .method(Method::PATCH)
+            .uri("/v1/alarms/device/main///temperature_high")
+            .header("Content-Type", "application/json")
+            .body(Body::from(json!({"status": "acknowledged"}).to_string()))
+            .expect("request builder");
+
+        let response = app.call(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    fn alarm_status(alarm_type: &str, status: AlarmState) -> AlarmStatus {
+        AlarmStatus {
+            source: EntityTopicId::default_main_device(),
+            alarm_type: alarm_type.to_string(),
+            status,
+            severity: Severity::Major,
+            raised_at: OffsetDateTime::UNIX_EPOCH,
+            escalated_from: None,
+            shelved_until: None,
+        }
+    }
+
+    struct TestHandle {
+        app: Router,
+        alarm_manager_box: ServerMessageBox<AlarmRequest, AlarmResponse>,
+    }
+
+    fn setup() -> TestHandle {
+        let ttd: TempTedgeDir = TempTedgeDir::new();
+        let file_transfer_dir = ttd.utf8_path_buf();
+
+        let mut entity_store_box: ServerMessageBoxBuilder<EntityStoreRequest, EntityStoreResponse> =
+            ServerMessageBoxBuilder::new("EntityStoreBox", 16);
+        let entity_store_handle = ClientMessageBox::new(&mut entity_store_box);
+        let mut alarm_manager_box = ServerMessageBoxBuilder::new("AlarmManagerBox", 16);
+        let alarm_manager_handle = ClientMessageBox::new(&mut alarm_manager_box);
+
+        let agent_state = AgentState::new(file_transfer_dir, entity_store_handle)
+            .with_alarm_manager(Some(alarm_manager_handle));
+        let app: Router = alarms_router(agent_state);
+
+        TestHandle {
+            app,
+            alarm_manager_box: alarm_manager_box.build(),
+        }
+    }
+}
@@ -8,6 +8,8 @@
 //! References:
 //!
 //! - https://github.com/thin-edge/thin-edge.io/blob/main/design/decisions/0005-entity-registration-api.md
+use super::access_control::Access;
+use super::access_control::AccessDenied;
 use super::server::AgentState;
 use crate::entity_manager::server::EntityStoreRequest;
 use crate::entity_manager::server::EntityStoreResponse;
@@ -92,7 +94,7 @@ impl TryFrom<ListParams> for ListFilters {
 }
 
 #[derive(thiserror::Error, Debug)]
-enum Error {
+pub(super) enum Error {
     #[error(transparent)]
     InvalidEntityTopicId(#[from] TopicIdError),
 
@@ -115,10 +117,16 @@ enum Error {
 
     #[error(transparent)]
     InvalidTwinData(#[from] InvalidTwinData),
+
+    #[error(transparent)]
+    AccessDenied(#[from] AccessDenied),
 }
 
 impl IntoResponse for Error {
     fn into_response(self) -> Response {
+        if let Error::AccessDenied(denied) = self {
+            return denied.into_response();
+        }
         let status_code = match &self {
             Error::InvalidEntityTopicId(_) => StatusCode::BAD_REQUEST,
             Error::EntityStoreError(err) => match err {
@@ -131,6 +139,7 @@ impl IntoResponse for Error {
             Error::InvalidEntityStoreResponse => StatusCode::INTERNAL_SERVER_ERROR,
             Error::InvalidInput(_) => StatusCode::BAD_REQUEST,
             Error::InvalidTwinData(_) => StatusCode::BAD_REQUEST,
+            Error::AccessDenied(err) => err.status_code(),
         };
         let error_message = self.to_string();
 
@@ -152,8 +161,17 @@ pub(crate) fn entity_store_router(state: AgentState) -> Router {
 
 async fn register_entity(
     State(state): State<AgentState>,
+    access: Access,
     Json(entity): Json<EntityRegistrationMessage>,
 ) -> impl IntoResponse {
+    access.check_registration(&entity.topic_id, &entity.r#type, entity.parent.as_ref())?;
+    if let Access::Scoped(_) = access {
+        // An entity already registered can only be updated by a client managing it
+        if let Some(registered) = registered_entity(&state, &entity.topic_id).await? {
+            access.check_entity(&entity.topic_id, registered.parent.as_ref())?;
+        }
+    }
+
     let response = state
         .entity_store_handle
         .clone()
@@ -172,10 +190,12 @@ async fn register_entity(
 
 async fn patch_entity(
     State(state): State<AgentState>,
+    access: Access,
     Path(path): Path<String>,
     Json(twin_fragments): Json<Map<String, Value>>,
 ) -> impl IntoResponse {
     let topic_id = EntityTopicId::from_str(&path)?;
+    check_entity_access(&state, &access, &topic_id).await?;
     let twin_data = EntityTwinData::try_new(topic_id, twin_fragments)?;
 
     let response = state
@@ -188,16 +208,18 @@ async fn patch_entity(
     };
     res?;
 
-    let entity = get_entity(State(state), Path(path)).await?;
+    let entity = get_entity(State(state), access, Path(path)).await?;
 
     Ok(entity)
 }
 
 async fn get_entity(
     State(state): State<AgentState>,
+    access: Access,
     Path(path): Path<String>,
 ) -> Result<Json<EntityMetadata>, Error> {
     let topic_id = EntityTopicId::from_str(&path)?;
+    check_entity_access(&state, &access, &topic_id).await?;
 
     let response = state
         .entity_store_handle
@@ -218,9 +240,11 @@ async fn get_entity(
 
 async fn deregister_entity(
     State(state): State<AgentState>,
+    access: Access,
     Path(path): Path<String>,
 ) -> Result<Response, Error> {
     let topic_id = EntityTopicId::from_str(&path)?;
+    check_entity_access(&state, &access, &topic_id).await?;
 
     let response = state
         .entity_store_handle
@@ -241,9 +265,17 @@ async fn deregister_entity(
 
 async fn list_entities(
     State(state): State<AgentState>,
+    access: Access,
     Query(params): Query<ListParams>,
 ) -> Result<Json<Vec<EntityMetadata>>, Error> {
-    let filters = params.try_into()?;
+    let filters: ListFilters = params.try_into()?;
+    // A scoped client can only list the entities under its own topic id
+    let root = filters
+        .root
+        .clone()
+        .or_else(|| filters.parent.clone())
+        .unwrap_or_else(EntityTopicId::default_main_device);
+    access.check_entity(&root, None)?;
     let response = state
         .entity_store_handle
         .clone()
@@ -257,6 +289,50 @@ async fn list_entities(
     Ok(Json(entities))
 }
 
+/// Check that a request can manage an entity, looking up its parent when required
+async fn check_entity_access(
+    state: &AgentState,
+    access: &Access,
+    topic_id: &EntityTopicId,
+) -> Result<(), Error> {
+    let parent = parent_for_access(state, access, topic_id).await?;
+    Ok(access.check_entity(topic_id, parent.as_ref())?)
+}
+
+/// The registered parent of an entity, when required to check the access of a scoped client
+///
+/// This is also used by the alarm routes, so a client can manage the alarms of the entities it can manage.
+pub(super) async fn parent_for_access(
+    state: &AgentState,
+    access: &Access,
+    topic_id: &EntityTopicId,
+) -> Result<Option<EntityTopicId>, Error> {
+    match access {
+        Access::Scoped(client) if client.topic_id.as_ref() != Some(topic_id) => {
+            Ok(registered_entity(state, topic_id)
+                .await?
+                .and_then(|entity| entity.parent))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Look up an entity in the entity store
+async fn registered_entity(
+    state: &AgentState,
+    topic_id: &EntityTopicId,
+) -> Result<Option<EntityMetadata>, Error> {
+    let response = state
+        .entity_store_handle
+        .clone()
+        .await_response(EntityStoreRequest::Get(topic_id.clone()))
+        .await?;
+    let EntityStoreResponse::Get(entity_metadata) = response else {
+        return Err(Error::InvalidEntityStoreResponse);
+    };
+    Ok(entity_metadata)
+}
+
 #[cfg(test)]
 mod tests {
     use super::AgentState;
@@ -932,6 +1008,7 @@ mod tests {
         let agent_state = AgentState {
             file_transfer_dir,
             entity_store_handle,
+            alarm_manager_handle: None,
         };
         // TODO: Add a timeout to this router. Attempts to add a tower_http::timer::TimeoutLayer as a layer failed.
         let app: Router = entity_store_router(agent_state);
@@ -3,6 +3,7 @@ use axum::response::IntoResponse;
 use hyper::StatusCode;
 use tedge_actors::RuntimeError;
 
+use super::access_control::AccessDenied;
 use super::request_files::RequestPath;
 
 #[derive(Debug, thiserror::Error)]
@@ -54,6 +55,15 @@ pub enum HttpRequestError {
 
     #[error("Path rejection: {0}")]
     PathRejection(#[from] PathRejection),
+
+    #[error(transparent)]
+    AccessDenied(#[from] AccessDenied),
+}
+
+impl From<std::convert::Infallible> for HttpRequestError {
+    fn from(infallible: std::convert::Infallible) -> Self {
+        match infallible {}
+    }
 }
 
 impl From<HttpServerError> for RuntimeError {
@@ -86,6 +96,7 @@ impl IntoResponse for HttpRequestError {
             E::CannotUploadDirectory { .. } => {
                 (StatusCode::CONFLICT, error_message).into_response()
             }
+            E::AccessDenied(err) => err.into_response(),
         }
     }
 }
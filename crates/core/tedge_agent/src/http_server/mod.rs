@@ -1,4 +1,6 @@
+pub mod access_control;
 pub mod actor;
+mod alarms;
 mod entity_store;
 pub mod error;
 mod file_transfer;
@@ -8,6 +8,8 @@ use axum::http::request::Parts;
 use camino::Utf8Path;
 use camino::Utf8PathBuf;
 
+use super::access_control::Access;
+use super::access_control::AccessDenied;
 use super::error::HttpRequestError;
 
 #[derive(Clone)]
@@ -61,7 +63,15 @@ impl FromRequestParts<FileTransferDir> for FileTransferPath {
     ) -> Result<Self, Self::Rejection> {
         let Path(request_path) =
             Path::<Utf8PathBuf>::from_request_parts(parts, &file_transfer_dir).await?;
-        local_path_for_file(RequestPath(request_path), &file_transfer_dir.0)
+        let path = local_path_for_file(RequestPath(request_path), &file_transfer_dir.0)?;
+
+        let access = Access::from_request_parts(parts, file_transfer_dir).await?;
+        let relative_path = path
+            .full
+            .strip_prefix(&*file_transfer_dir.0)
+            .map_err(|_| AccessDenied::OutsideFileTransferDir(path.request.to_path_buf()))?;
+        access.check_file(relative_path)?;
+        Ok(path)
     }
 }
 
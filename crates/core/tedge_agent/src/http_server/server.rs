@@ -1,13 +1,20 @@
+use super::access_control::authorize;
+use super::access_control::AccessPolicy;
+use super::alarms::alarms_router;
 use super::entity_store::entity_store_router;
 use super::file_transfer::file_transfer_router;
+use crate::alarm_manager::actor::AlarmRequest;
+use crate::alarm_manager::actor::AlarmResponse;
 use crate::entity_manager::server::EntityStoreRequest;
 use crate::entity_manager::server::EntityStoreResponse;
 use crate::http_server::error::HttpServerError;
+use axum::middleware::from_fn_with_state;
 use axum::Router;
 use camino::Utf8PathBuf;
 use futures::future::FutureExt;
 use rustls::ServerConfig;
 use std::future::Future;
+use std::sync::Arc;
 use tedge_actors::ClientMessageBox;
 use tokio::io;
 use tokio::net::TcpListener;
@@ -16,6 +23,7 @@ use tokio::net::TcpListener;
 pub(crate) struct AgentState {
     pub(crate) file_transfer_dir: Utf8PathBuf,
     pub(crate) entity_store_handle: ClientMessageBox<EntityStoreRequest, EntityStoreResponse>,
+    pub(crate) alarm_manager_handle: Option<ClientMessageBox<AlarmRequest, AlarmResponse>>,
 }
 
 impl AgentState {
@@ -26,16 +34,26 @@ impl AgentState {
         AgentState {
             file_transfer_dir,
             entity_store_handle,
+            alarm_manager_handle: None,
         }
     }
+
+    pub fn with_alarm_manager(
+        mut self,
+        alarm_manager_handle: Option<ClientMessageBox<AlarmRequest, AlarmResponse>>,
+    ) -> Self {
+        self.alarm_manager_handle = alarm_manager_handle;
+        self
+    }
 }
 
 pub(crate) fn http_server(
     listener: TcpListener,
     rustls_config: Option<ServerConfig>,
+    access_policy: Option<AccessPolicy>,
     agent_state: AgentState,
 ) -> Result<impl Future<Output = io::Result<()>>, HttpServerError> {
-    let router = router(agent_state);
+    let router = router(agent_state, access_policy);
 
     let listener = listener.into_std()?;
 
@@ -50,11 +68,286 @@ pub(crate) fn http_server(
     Ok(server)
 }
 
-fn router(state: AgentState) -> Router {
+fn router(state: AgentState, access_policy: Option<AccessPolicy>) -> Router {
     let file_transfer_router = file_transfer_router(state.file_transfer_dir.clone());
+    let alarms_router = state
+        .alarm_manager_handle
+        .is_some()
+        .then(|| alarms_router(state.clone()));
     let entity_store_router = entity_store_router(state);
 
-    Router::new()
+    let mut router = Router::new()
         .nest("/tedge/entity-store", entity_store_router)
-        .merge(file_transfer_router)
+        .merge(file_transfer_router);
+    if let Some(alarms_router) = alarms_router {
+        router = router.nest("/tedge/alarms", alarms_router);
+    }
+
+    match access_policy {
+        Some(policy) => router.layer(from_fn_with_state(Arc::new(policy), authorize)),
+        None => router,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::header::AUTHORIZATION;
+    use hyper::Method;
+    use hyper::Request;
+    use hyper::StatusCode;
+    use serde_json::json;
+    use tedge_actors::Builder;
+    use tedge_actors::MessageReceiver;
+    use tedge_actors::ServerMessageBox;
+    use tedge_actors::ServerMessageBoxBuilder;
+    use tedge_api::entity::EntityMetadata;
+    use tedge_api::mqtt_topics::EntityTopicId;
+    use tedge_test_utils::fs::TempTedgeDir;
+    use tower::Service;
+
+    const POLICY: &str = r#"
+        [[clients]]
+        name = "mapper"
+        token = "admin-secret"
+        admin = true
+
+        [[clients]]
+        name = "child01"
+        token = "child-secret"
+        topic_id = "device/child01//"
+    "#;
+
+    #[tokio::test]
+    async fn anonymous_requests_are_rejected() {
+        let (_ttd, mut app, _) = app_with_policy();
+
+        let response = app
+            .call(request(
+                Method::GET,
+                "/tedge/file-transfer/child01/file",
+                None,
+            ))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn scoped_clients_are_restricted_to_their_file_transfer_subtree() {
+        let (_ttd, mut app, _) = app_with_policy();
+
+        let own_file = request(
+            Method::PUT,
+            "/tedge/file-transfer/child01/file",
+            Some("child-secret"),
+        );
+        let response = app.call(own_file).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let other_file = request(
+            Method::PUT,
+            "/tedge/file-transfer/child02/file",
+            Some("child-secret"),
+        );
+        let response = app.call(other_file).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let any_file = request(
+            Method::PUT,
+            "/tedge/file-transfer/child02/file",
+            Some("admin-secret"),
+        );
+        let response = app.call(any_file).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    #[tokio::test]
+    async fn scoped_clients_cannot_register_entities_out_of_their_scope() {
+        let (_ttd, mut app, _) = app_with_policy();
+
+        // The parent declared by the client is checked against its scope
+        for parent in ["device/child03//", "device/main//"] {
+            let registration = json!({
+                "@topic-id": "device/child02//",
+                "@type": "child-device",
+                "@parent": parent,
+            });
+            let response = app
+                .call(post_entity(registration, "child-secret"))
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        }
+    }
+
+    #[tokio::test]
+    async fn scoped_clients_can_register_their_own_services() {
+        let (_ttd, mut app, entity_store_box) = app_with_policy();
+        serve_entity_store(
+            entity_store_box,
+            EntityTopicId::default_child_service("child01", "app").unwrap(),
+        );
+
+        let registration = json!({
+            "@topic-id": "device/child01/service/app",
+            "@type": "service",
+        });
+        let response = app
+            .call(post_entity(registration, "child-secret"))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    #[tokio::test]
+    async fn scoped_clients_can_register_child_devices_of_their_own_device() {
+        let (_ttd, mut app, entity_store_box) = app_with_policy();
+        serve_entity_store(entity_store_box, "device/child02//".parse().unwrap());
+
+        let registration = json!({
+            "@topic-id": "device/child02//",
+            "@type": "child-device",
+            "@parent": "device/child01//",
+        });
+        let response = app
+            .call(post_entity(registration, "child-secret"))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    #[tokio::test]
+    async fn scoped_clients_can_manage_the_alarms_of_their_child_devices() {
+        let (_ttd, mut app, mut entity_store_box, mut alarm_manager_box) = app_with_alarms();
+
+        tokio::spawn(async move {
+            while let Some(mut req) = entity_store_box.recv().await {
+                if let EntityStoreRequest::Get(topic_id) = req.request {
+                    let mut entity = EntityMetadata::child_device(
+                        topic_id.default_device_name().unwrap().to_string(),
+                    )
+                    .unwrap();
+                    if topic_id.as_str() == "device/child02//" {
+                        entity.parent = Some("device/child01//".parse().unwrap());
+                    }
+                    req.reply_to
+                        .send(EntityStoreResponse::Get(Some(entity)))
+                        .await
+                        .unwrap();
+                }
+            }
+        });
+        tokio::spawn(async move {
+            while let Some(mut req) = alarm_manager_box.recv().await {
+                if let AlarmRequest::Get { .. } = req.request {
+                    req.reply_to.send(AlarmResponse::Get(None)).await.unwrap();
+                }
+            }
+        });
+
+        // The alarm is not found, but the client is granted access
+        let child_alarm = request(
+            Method::GET,
+            "/tedge/alarms/v1/alarms/device/child02///temperature_high",
+            Some("child-secret"),
+        );
+        let response = app.call(child_alarm).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let other_alarm = request(
+            Method::GET,
+            "/tedge/alarms/v1/alarms/device/child03///temperature_high",
+            Some("child-secret"),
+        );
+        let response = app.call(other_alarm).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    /// Serve the entity store requests for the registration of a new entity
+    fn serve_entity_store(
+        mut entity_store_box: ServerMessageBox<EntityStoreRequest, EntityStoreResponse>,
+        expected_topic_id: EntityTopicId,
+    ) {
+        tokio::spawn(async move {
+            while let Some(mut req) = entity_store_box.recv().await {
+                let response = match req.request {
+                    EntityStoreRequest::Get(topic_id) => {
+                        assert_eq!(topic_id, expected_topic_id);
+                        EntityStoreResponse::Get(None)
+                    }
+                    EntityStoreRequest::Create(entity) => {
+                        assert_eq!(entity.topic_id, expected_topic_id);
+                        EntityStoreResponse::Create(Ok(vec![]))
+                    }
+                    _ => continue,
+                };
+                req.reply_to.send(response).await.unwrap();
+            }
+        });
+    }
+
+    fn request(method: Method, uri: &str, token: Option<&str>) -> Request<Body> {
+        let mut request = Request::builder().method(method).uri(uri);
+        if let Some(token) = token {
+            request = request.header(AUTHORIZATION, format!("Bearer {token}"));
+        }
+        request
+            .body(Body::from("content"))
+            .expect("request builder")
+    }
+
+    fn post_entity(registration: serde_json::Value, token: &str) -> Request<Body> {
+        Request::builder()
+            .method(Method::POST)
+            .uri("/tedge/entity-store/v1/entities")
+            .header("Content-Type", "application/json")
+            .header(AUTHORIZATION, format!("Bearer {token}"))
+            .body(Body::from(registration.to_string()))
+            .expect("request builder")
+    }
+
+    fn app_with_alarms() -> (
+        TempTedgeDir,
+        Router,
+        ServerMessageBox<EntityStoreRequest, EntityStoreResponse>,
+        ServerMessageBox<AlarmRequest, AlarmResponse>,
+    ) {
+        let ttd = TempTedgeDir::new();
+        let mut entity_store_box = ServerMessageBoxBuilder::new("EntityStoreBox", 16);
+        let entity_store_handle = ClientMessageBox::new(&mut entity_store_box);
+        let mut alarm_manager_box = ServerMessageBoxBuilder::new("AlarmManagerBox", 16);
+        let alarm_manager_handle = ClientMessageBox::new(&mut alarm_manager_box);
+        let agent_state = AgentState::new(ttd.utf8_path_buf(), entity_store_handle)
+            .with_alarm_manager(Some(alarm_manager_handle));
+        let policy = toml::from_str(POLICY).unwrap();
+
+        let app = router(agent_state, Some(policy));
+        (
+            ttd,
+            app,
+            entity_store_box.build(),
+            alarm_manager_box.build(),
+        )
+    }
+
+    fn app_with_policy() -> (
+        TempTedgeDir,
+        Router,
+        ServerMessageBox<EntityStoreRequest, EntityStoreResponse>,
+    ) {
+        let ttd = TempTedgeDir::new();
+        let mut entity_store_box = ServerMessageBoxBuilder::new("EntityStoreBox", 16);
+        let entity_store_handle = ClientMessageBox::new(&mut entity_store_box);
+        let agent_state = AgentState::new(ttd.utf8_path_buf(), entity_store_handle);
+        let policy = toml::from_str(POLICY).unwrap();
+
+        let app = router(agent_state, Some(policy));
+        (ttd, app, entity_store_box.build())
+    }
 }
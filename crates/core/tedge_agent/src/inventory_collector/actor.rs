@@ -0,0 +1,110 @@
+use crate::inventory_collector::collectors::builtin_fragments;
+use crate::inventory_collector::collectors::script_fragments;
+use crate::inventory_collector::collectors::Fragments;
+use crate::inventory_collector::config::InventoryConfig;
+use async_trait::async_trait;
+use tedge_actors::Actor;
+use tedge_actors::MessageReceiver;
+use tedge_actors::NoMessage;
+use tedge_actors::RuntimeError;
+use tedge_actors::Sender;
+use tedge_actors::SimpleMessageBox;
+use tedge_api::entity_store::EntityTwinMessage;
+use tedge_api::mqtt_topics::Channel;
+use tedge_mqtt_ext::MqttMessage;
+use tokio::time::MissedTickBehavior;
+use tracing::error;
+
+/// Publish the inventory of the device as twin fragments
+///
+/// The inventory is collected on start then periodically,
+/// only the fragments that have changed since the previous collection being published.
+pub struct InventoryCollectorActor {
+    config: InventoryConfig,
+    message_box: SimpleMessageBox<NoMessage, MqttMessage>,
+
+    /// The fragments published so far
+    published: Fragments,
+}
+
+#[async_trait]
+impl Actor for InventoryCollectorActor {
+    fn name(&self) -> &str {
+        "InventoryCollectorActor"
+    }
+
+    async fn run(mut self) -> Result<(), RuntimeError> {
+        let mut collection = tokio::time::interval(self.config.interval);
+        collection.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            tokio::select! {
+                _ = self.message_box.recv() => break,
+                _ = collection.tick() => self.publish_inventory().await?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl InventoryCollectorActor {
+    pub fn new(
+        config: InventoryConfig,
+        message_box: SimpleMessageBox<NoMessage, MqttMessage>,
+    ) -> Self {
+        Self {
+            config,
+            message_box,
+            published: Fragments::new(),
+        }
+    }
+
+    async fn publish_inventory(&mut self) -> Result<(), RuntimeError> {
+        let mut fragments = match tokio::task::spawn_blocking(builtin_fragments).await {
+            Ok(fragments) => fragments,
+            Err(err) => {
+                error!("Fail to collect the device inventory: {err}");
+                Fragments::new()
+            }
+        };
+        fragments
+            .extend(script_fragments(&self.config.scripts_dir, self.config.script_timeout).await);
+
+        for message in self.changes(fragments) {
+            self.message_box.send(message).await?;
+        }
+        Ok(())
+    }
+
+    /// The messages updating the fragments that have changed, and clearing those that are gone
+    fn changes(&mut self, fragments: Fragments) -> Vec<MqttMessage> {
+        let mut messages = vec![];
+
+        for fragment_key in self.published.keys() {
+            if !fragments.contains_key(fragment_key) {
+                let topic = self.config.mqtt_schema.topic_for(
+                    &self.config.device_topic_id,
+                    &Channel::EntityTwinData {
+                        fragment_key: fragment_key.clone(),
+                    },
+                );
+                messages.push(MqttMessage::new(&topic, "").with_retain());
+            }
+        }
+
+        for (fragment_key, fragment_value) in &fragments {
+            if self.published.get(fragment_key) != Some(fragment_value) {
+                let twin_message = EntityTwinMessage::new(
+                    self.config.device_topic_id.clone(),
+                    fragment_key.clone(),
+                    fragment_value.clone(),
+                );
+                messages.push(twin_message.to_mqtt_message(&self.config.mqtt_schema));
+            }
+        }
+
+        self.published = fragments;
+        messages
+    }
+}
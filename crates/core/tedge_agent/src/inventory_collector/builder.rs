@@ -0,0 +1,60 @@
+use crate::inventory_collector::actor::InventoryCollectorActor;
+use crate::inventory_collector::config::InventoryConfig;
+use tedge_actors::Builder;
+use tedge_actors::DynSender;
+use tedge_actors::LinkError;
+use tedge_actors::MessageSink;
+use tedge_actors::MessageSource;
+use tedge_actors::NoConfig;
+use tedge_actors::NoMessage;
+use tedge_actors::RestartableBuilder;
+use tedge_actors::RuntimeRequest;
+use tedge_actors::RuntimeRequestSink;
+use tedge_actors::SimpleMessageBoxBuilder;
+use tedge_mqtt_ext::MqttMessage;
+
+pub struct InventoryCollectorBuilder {
+    config: InventoryConfig,
+    message_box: SimpleMessageBoxBuilder<NoMessage, MqttMessage>,
+}
+
+impl InventoryCollectorBuilder {
+    pub fn new(config: InventoryConfig, mqtt: &impl MessageSink<MqttMessage>) -> Self {
+        let mut message_box = SimpleMessageBoxBuilder::new("InventoryCollector", 10);
+        message_box.connect_sink(NoConfig, mqtt);
+
+        Self {
+            config,
+            message_box,
+        }
+    }
+}
+
+impl RuntimeRequestSink for InventoryCollectorBuilder {
+    fn get_signal_sender(&self) -> DynSender<RuntimeRequest> {
+        self.message_box.get_signal_sender()
+    }
+}
+
+impl Builder<InventoryCollectorActor> for InventoryCollectorBuilder {
+    type Error = LinkError;
+
+    fn try_build(self) -> Result<InventoryCollectorActor, Self::Error> {
+        Ok(self.build())
+    }
+
+    fn build(self) -> InventoryCollectorActor {
+        InventoryCollectorActor::new(self.config, self.message_box.build())
+    }
+}
+
+impl RestartableBuilder<InventoryCollectorActor> for InventoryCollectorBuilder {
+    type Error = LinkError;
+
+    fn rebuild(&mut self) -> Result<InventoryCollectorActor, Self::Error> {
+        Ok(InventoryCollectorActor::new(
+            self.config.clone(),
+            self.message_box.rebuild()?,
+        ))
+    }
+}
@@ -0,0 +1,353 @@
+//! Collect the inventory of the device, as a map of twin fragments.
+//!
+//! The built-in collectors read the details of the hardware, OS, network and storage
+//! from `/proc` and `/sys`, ignoring the details that are not available on the device.
+//! These built-in fragments can be extended or overridden by scripts,
+//! each script printing a JSON object of fragments on its standard output.
+use camino::Utf8Path;
+use serde_json::json;
+use serde_json::Map;
+use serde_json::Value as JsonValue;
+use std::collections::BTreeMap;
+use std::net::SocketAddrV4;
+use std::net::SocketAddrV6;
+use std::os::unix::fs::PermissionsExt;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+use tracing::warn;
+
+pub type Fragments = Map<String, JsonValue>;
+
+/// Collect all the built-in fragments
+pub fn builtin_fragments() -> Fragments {
+    let mut fragments = Fragments::new();
+    fragments.insert("hardware".to_string(), hardware());
+    fragments.insert("os".to_string(), os());
+    fragments.insert("network".to_string(), network());
+    fragments.insert("storage".to_string(), storage());
+    fragments.insert("agent".to_string(), agent());
+    fragments
+}
+
+/// Model, serial number and revision of the device, along with its CPU and memory
+fn hardware() -> JsonValue {
+    let cpuinfo = read("/proc/cpuinfo").unwrap_or_default();
+    let meminfo = read("/proc/meminfo").unwrap_or_default();
+
+    let mut hardware = Fragments::new();
+    insert(
+        &mut hardware,
+        "model",
+        read("/proc/device-tree/model").or_else(|| read("/sys/class/dmi/id/product_name")),
+    );
+    insert(
+        &mut hardware,
+        "serialNumber",
+        read("/proc/device-tree/serial-number")
+            .or_else(|| read("/sys/class/dmi/id/product_serial"))
+            .or_else(|| cpuinfo_field(&cpuinfo, "Serial")),
+    );
+    insert(
+        &mut hardware,
+        "revision",
+        cpuinfo_field(&cpuinfo, "Revision").or_else(|| read("/sys/class/dmi/id/product_version")),
+    );
+    insert(
+        &mut hardware,
+        "cpuModel",
+        cpuinfo_field(&cpuinfo, "model name").or_else(|| cpuinfo_field(&cpuinfo, "Model")),
+    );
+    hardware.insert("cpuCores".to_string(), json!(cpu_cores(&cpuinfo)));
+    if let Some(memory) = total_memory(&meminfo) {
+        hardware.insert("memory".to_string(), json!(memory));
+    }
+    JsonValue::Object(hardware)
+}
+
+/// Name and version of the operating system
+fn os() -> JsonValue {
+    let os_release = parse_os_release(&read("/etc/os-release").unwrap_or_default());
+
+    let mut os = Fragments::new();
+    insert(&mut os, "name", os_release.get("NAME").cloned());
+    insert(&mut os, "version", os_release.get("VERSION_ID").cloned());
+    insert(
+        &mut os,
+        "description",
+        os_release.get("PRETTY_NAME").cloned(),
+    );
+    insert(&mut os, "kernel", read("/proc/sys/kernel/osrelease"));
+    insert(&mut os, "hostname", read("/proc/sys/kernel/hostname"));
+    os.insert("architecture".to_string(), json!(std::env::consts::ARCH));
+    JsonValue::Object(os)
+}
+
+/// The network interfaces, but the loopback, with their MAC and IP addresses
+fn network() -> JsonValue {
+    let mut addresses: BTreeMap<String, (Vec<String>, Vec<String>)> = BTreeMap::new();
+    match nix::ifaddrs::getifaddrs() {
+        Ok(interfaces) => {
+            for interface in interfaces {
+                let Some(address) = interface.address else {
+                    continue;
+                };
+                let entry = addresses.entry(interface.interface_name).or_default();
+                if let Some(ipv4) = address.as_sockaddr_in() {
+                    entry.0.push(SocketAddrV4::from(*ipv4).ip().to_string());
+                } else if let Some(ipv6) = address.as_sockaddr_in6() {
+                    entry.1.push(SocketAddrV6::from(*ipv6).ip().to_string());
+                }
+            }
+        }
+        Err(err) => warn!("Cannot list the network interfaces: {err}"),
+    }
+
+    let interfaces: Vec<JsonValue> = addresses
+        .into_iter()
+        .filter(|(name, _)| name != "lo")
+        .map(|(name, (ipv4, ipv6))| {
+            let mut interface = Fragments::new();
+            insert(
+                &mut interface,
+                "mac",
+                read(format!("/sys/class/net/{name}/address")),
+            );
+            insert(
+                &mut interface,
+                "state",
+                read(format!("/sys/class/net/{name}/operstate")),
+            );
+            interface.insert("name".to_string(), json!(name));
+            interface.insert("ipv4".to_string(), json!(ipv4));
+            interface.insert("ipv6".to_string(), json!(ipv6));
+            JsonValue::Object(interface)
+        })
+        .collect();
+
+    json!({ "interfaces": interfaces })
+}
+
+/// The size of the file systems mounted from a block device
+#[allow(clippy::unnecessary_cast)]
+fn storage() -> JsonValue {
+    let mounts = parse_mounts(&read("/proc/mounts").unwrap_or_default());
+
+    let filesystems: Vec<JsonValue> = mounts
+        .into_iter()
+        .filter_map(|mount| {
+            let stats = nix::sys::statvfs::statvfs(mount.mount_point.as_str()).ok()?;
+            let block_size = stats.fragment_size() as u64;
+            Some(json!({
+                "device": mount.device,
+                "mountPoint": mount.mount_point,
+                "type": mount.fs_type,
+                "size": stats.blocks() as u64 * block_size,
+            }))
+        })
+        .collect();
+
+    json!({ "filesystems": filesystems })
+}
+
+/// The version of the agent
+fn agent() -> JsonValue {
+    json!({
+        "name": "tedge-agent",
+        "version": env!("CARGO_PKG_VERSION"),
+    })
+}
+
+/// Run the inventory scripts of a directory, in lexical order, collecting the fragments they print
+///
+/// The fragments of a script override those of the built-in collectors and of the previous scripts.
+pub async fn script_fragments(scripts_dir: &Utf8Path, timeout: Duration) -> Fragments {
+    let mut fragments = Fragments::new();
+    let Ok(entries) = std::fs::read_dir(scripts_dir) else {
+        return fragments;
+    };
+    let mut scripts: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.metadata().is_ok_and(|metadata| {
+                metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+            })
+        })
+        .collect();
+    scripts.sort();
+
+    for script in scripts {
+        let output = Command::new(&script)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .output();
+        let output = match tokio::time::timeout(timeout, output).await {
+            Ok(Ok(output)) if output.status.success() => output,
+            Ok(Ok(output)) => {
+                warn!(
+                    "Inventory script {} exited with {}",
+                    script.display(),
+                    output.status
+                );
+                continue;
+            }
+            Ok(Err(err)) => {
+                warn!("Cannot run inventory script {}: {err}", script.display());
+                continue;
+            }
+            Err(_) => {
+                warn!(
+                    "Inventory script {} timed out after {timeout:?}",
+                    script.display()
+                );
+                continue;
+            }
+        };
+        match serde_json::from_slice::<Fragments>(&output.stdout) {
+            Ok(script_fragments) => fragments.extend(script_fragments),
+            Err(err) => warn!(
+                "Inventory script {} didn't print a JSON object: {err}",
+                script.display()
+            ),
+        }
+    }
+
+    fragments
+}
+
+fn read(path: impl AsRef<std::path::Path>) -> Option<String> {
+    let content = std::fs::read_to_string(path).ok()?;
+    let content = content.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    (!content.is_empty()).then(|| content.to_string())
+}
+
+fn insert(fragment: &mut Fragments, key: &str, value: Option<String>) {
+    if let Some(value) = value {
+        fragment.insert(key.to_string(), JsonValue::String(value));
+    }
+}
+
+/// Return the value of the first `key : value` line of `/proc/cpuinfo`
+fn cpuinfo_field(cpuinfo: &str, key: &str) -> Option<String> {
+    cpuinfo.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        (name.trim() == key && !value.trim().is_empty()).then(|| value.trim().to_string())
+    })
+}
+
+fn cpu_cores(cpuinfo: &str) -> usize {
+    let processors = cpuinfo
+        .lines()
+        .filter(|line| line.split(':').next().map(str::trim) == Some("processor"))
+        .count();
+    if processors > 0 {
+        processors
+    } else {
+        std::thread::available_parallelism().map_or(1, |cores| cores.get())
+    }
+}
+
+/// Return the total memory in bytes, as given in kB by `/proc/meminfo`
+fn total_memory(meminfo: &str) -> Option<u64> {
+    let line = meminfo.lines().find(|line| line.starts_with("MemTotal:"))?;
+    let kb = line.split_whitespace().nth(1)?.parse::<u64>().ok()?;
+    Some(kb * 1024)
+}
+
+fn parse_os_release(content: &str) -> BTreeMap<String, String> {
+    content
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| {
+            let value = value.trim().trim_matches('"').trim_matches('\'');
+            (key.trim().to_string(), value.to_string())
+        })
+        .collect()
+}
+
+#[derive(Debug, Eq, PartialEq)]
+struct Mount {
+    device: String,
+    mount_point: String,
+    fs_type: String,
+}
+
+/// List the file systems of `/proc/mounts` that are mounted from a block device
+fn parse_mounts(content: &str) -> Vec<Mount> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let device = fields.next()?;
+            let mount_point = fields.next()?;
+            let fs_type = fields.next()?;
+            device.starts_with("/dev/").then(|| Mount {
+                device: device.to_string(),
+                // Spaces in mount points are escaped as \040
+                mount_point: mount_point.replace("\\040", " "),
+                fs_type: fs_type.to_string(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CPUINFO: &str = "processor\t: 0\nBogoMIPS\t: 108.00\n\nprocessor\t: 1\nBogoMIPS\t: 108.00\n\nRevision\t: c03114\nSerial\t\t: 100000001234abcd\nModel\t\t: Raspberry Pi 4 Model B Rev 1.4\n";
+
+    #[test]
+    fn parse_cpuinfo() {
+        assert_eq!(cpu_cores(CPUINFO), 2);
+        assert_eq!(
+            cpuinfo_field(CPUINFO, "Model").as_deref(),
+            Some("Raspberry Pi 4 Model B Rev 1.4")
+        );
+        assert_eq!(
+            cpuinfo_field(CPUINFO, "Revision").as_deref(),
+            Some("c03114")
+        );
+        assert_eq!(cpuinfo_field(CPUINFO, "model name"), None);
+    }
+
+    #[test]
+    fn parse_meminfo() {
+        let meminfo = "MemTotal:        3882420 kB\nMemFree:          150820 kB\n";
+        assert_eq!(total_memory(meminfo), Some(3882420 * 1024));
+    }
+
+    #[test]
+    fn parse_os_release_file() {
+        let os_release = parse_os_release(
+            "PRETTY_NAME=\"Debian GNU/Linux 12 (bookworm)\"\nNAME=\"Debian GNU/Linux\"\nVERSION_ID=\"12\"\nID=debian\n",
+        );
+        assert_eq!(os_release.get("NAME").unwrap(), "Debian GNU/Linux");
+        assert_eq!(os_release.get("VERSION_ID").unwrap(), "12");
+        assert_eq!(os_release.get("ID").unwrap(), "debian");
+    }
+
+    #[test]
+    fn only_block_devices_are_listed() {
+        let mounts = parse_mounts(
+            "/dev/root / ext4 rw,noatime 0 0\nproc /proc proc rw 0 0\n/dev/sda1 /mnt/my\\040disk vfat rw 0 0\n",
+        );
+        assert_eq!(
+            mounts,
+            vec![
+                Mount {
+                    device: "/dev/root".to_string(),
+                    mount_point: "/".to_string(),
+                    fs_type: "ext4".to_string(),
+                },
+                Mount {
+                    device: "/dev/sda1".to_string(),
+                    mount_point: "/mnt/my disk".to_string(),
+                    fs_type: "vfat".to_string(),
+                },
+            ]
+        );
+    }
+}
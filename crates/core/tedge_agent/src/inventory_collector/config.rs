@@ -0,0 +1,41 @@
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use std::time::Duration;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_config::TEdgeConfig;
+
+/// The directory of the user-provided inventory scripts, relative to the config dir
+const INVENTORY_SCRIPTS_DIR: &str = "inventory.d";
+
+#[derive(Debug, Clone)]
+pub struct InventoryConfig {
+    pub mqtt_schema: MqttSchema,
+    pub device_topic_id: EntityTopicId,
+
+    /// How often the inventory is collected
+    pub interval: Duration,
+
+    /// The directory of the scripts extending the built-in collectors
+    pub scripts_dir: Utf8PathBuf,
+
+    /// How long a script is given to print its fragments
+    pub script_timeout: Duration,
+}
+
+impl InventoryConfig {
+    pub fn from_tedge_config(
+        mqtt_schema: MqttSchema,
+        device_topic_id: &EntityTopicId,
+        config_dir: &Utf8Path,
+        tedge_config: &TEdgeConfig,
+    ) -> InventoryConfig {
+        InventoryConfig {
+            mqtt_schema,
+            device_topic_id: device_topic_id.clone(),
+            interval: tedge_config.agent.inventory.interval.duration(),
+            scripts_dir: config_dir.join(INVENTORY_SCRIPTS_DIR),
+            script_timeout: tedge_config.agent.inventory.script_timeout.duration(),
+        }
+    }
+}
@@ -0,0 +1,7 @@
+pub mod actor;
+pub mod builder;
+pub mod collectors;
+pub mod config;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,153 @@
+use crate::inventory_collector::builder::InventoryCollectorBuilder;
+use crate::inventory_collector::config::InventoryConfig;
+use std::collections::BTreeMap;
+use std::os::unix::fs::PermissionsExt;
+use std::time::Duration;
+use tedge_actors::test_helpers::MessageReceiverExt;
+use tedge_actors::test_helpers::TimedMessageBox;
+use tedge_actors::Actor;
+use tedge_actors::Builder;
+use tedge_actors::DynError;
+use tedge_actors::MessageReceiver;
+use tedge_actors::NoMessage;
+use tedge_actors::RuntimeRequestSink;
+use tedge_actors::SimpleMessageBox;
+use tedge_actors::SimpleMessageBoxBuilder;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_mqtt_ext::MqttMessage;
+use tedge_test_utils::fs::TempTedgeDir;
+
+const TEST_TIMEOUT_MS: Duration = Duration::from_millis(3000);
+
+#[tokio::test]
+async fn publish_the_builtin_and_script_fragments_on_start() -> Result<(), DynError> {
+    let temp_dir = TempTedgeDir::new();
+    inventory_script(&temp_dir, "10-site", r#"{"site": {"name": "plant-1"}}"#);
+    let mut mqtt_box = spawn_inventory_collector(&temp_dir, Duration::from_secs(3600)).await?;
+
+    let fragments = recv_fragments(&mut mqtt_box, 6).await;
+    for fragment in ["hardware", "os", "network", "storage", "agent", "site"] {
+        assert!(fragments.contains_key(fragment), "missing {fragment}");
+    }
+    assert_eq!(fragments.get("site").unwrap(), r#"{"name":"plant-1"}"#);
+    assert!(fragments
+        .get("agent")
+        .unwrap()
+        .contains(env!("CARGO_PKG_VERSION")));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn only_publish_the_fragments_that_have_changed() -> Result<(), DynError> {
+    let temp_dir = TempTedgeDir::new();
+    let site_file = temp_dir.path().join("site.json");
+    std::fs::write(&site_file, r#"{"site": "plant-1"}"#)?;
+    inventory_script(
+        &temp_dir,
+        "10-site",
+        &format!("cat {}", site_file.display()),
+    );
+    let mut mqtt_box = spawn_inventory_collector(&temp_dir, Duration::from_millis(200)).await?;
+
+    let fragments = recv_fragments(&mut mqtt_box, 6).await;
+    assert_eq!(fragments.get("site").unwrap(), r#""plant-1""#);
+
+    std::fs::write(&site_file, r#"{"site": "plant-2"}"#)?;
+    let fragments = recv_fragments(&mut mqtt_box, 1).await;
+    assert_eq!(
+        fragments,
+        BTreeMap::from([("site".to_string(), r#""plant-2""#.to_string())])
+    );
+
+    std::fs::write(&site_file, "{}")?;
+    let fragments = recv_fragments(&mut mqtt_box, 1).await;
+    assert_eq!(
+        fragments,
+        BTreeMap::from([("site".to_string(), "".to_string())])
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn scripts_override_the_builtin_fragments() -> Result<(), DynError> {
+    let temp_dir = TempTedgeDir::new();
+    inventory_script(
+        &temp_dir,
+        "10-hardware",
+        r#"{"hardware": {"model": "custom-board"}}"#,
+    );
+    let mut mqtt_box = spawn_inventory_collector(&temp_dir, Duration::from_secs(3600)).await?;
+
+    let fragments = recv_fragments(&mut mqtt_box, 5).await;
+    assert_eq!(
+        fragments.get("hardware").unwrap(),
+        r#"{"model":"custom-board"}"#
+    );
+
+    Ok(())
+}
+
+/// Create an inventory script printing the output of a shell command, or a JSON literal
+fn inventory_script(temp_dir: &TempTedgeDir, name: &str, output: &str) {
+    let scripts_dir = temp_dir.path().join("inventory.d");
+    std::fs::create_dir_all(&scripts_dir).unwrap();
+    let script = scripts_dir.join(name);
+    let body = if output.starts_with('{') {
+        format!("echo '{output}'")
+    } else {
+        output.to_string()
+    };
+    std::fs::write(&script, format!("#!/bin/sh\n{body}\n")).unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+/// Receive twin messages, returning the payloads indexed by fragment key
+async fn recv_fragments(
+    mqtt_box: &mut TimedMessageBox<SimpleMessageBox<MqttMessage, NoMessage>>,
+    count: usize,
+) -> BTreeMap<String, String> {
+    let mut fragments = BTreeMap::new();
+    for _ in 0..count {
+        let message = mqtt_box.recv().await.expect("twin message");
+        assert!(message.retain);
+        let fragment_key = message
+            .topic
+            .name
+            .strip_prefix("te/device/main///twin/")
+            .expect("twin topic")
+            .to_string();
+        fragments.insert(fragment_key, message.payload_str().unwrap().to_string());
+    }
+    fragments
+}
+
+async fn spawn_inventory_collector(
+    temp_dir: &TempTedgeDir,
+    interval: Duration,
+) -> Result<TimedMessageBox<SimpleMessageBox<MqttMessage, NoMessage>>, DynError> {
+    let config = InventoryConfig {
+        mqtt_schema: MqttSchema::default(),
+        device_topic_id: EntityTopicId::default_main_device(),
+        interval,
+        scripts_dir: temp_dir.utf8_path().join("inventory.d"),
+        script_timeout: Duration::from_secs(1),
+    };
+    let mqtt_builder: SimpleMessageBoxBuilder<MqttMessage, NoMessage> =
+        SimpleMessageBoxBuilder::new("MQTT", 10);
+
+    let actor_builder = InventoryCollectorBuilder::new(config, &mqtt_builder);
+    let mqtt_box = mqtt_builder.build().with_timeout(TEST_TIMEOUT_MS);
+
+    // The actor stops as soon as its message box is closed, i.e. when no signal can be sent
+    let signal_sender = actor_builder.get_signal_sender();
+    let actor = actor_builder.build();
+    tokio::spawn(async move {
+        let _signal_sender = signal_sender;
+        actor.run().await
+    });
+
+    Ok(mqtt_box)
+}
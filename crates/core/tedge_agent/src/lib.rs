@@ -8,7 +8,11 @@
 //! It also has following capabilities:
 //!
 //! - File transfer HTTP server
+//! - Alarm lifecycle management
+//! - Device inventory collection
 //! - Restart management
+//! - Device certificate renewal
+//! - Remote command execution
 //! - Software management
 
 use std::sync::Arc;
@@ -19,12 +23,16 @@ use tedge_config::log_init;
 use tracing::log::warn;
 
 mod agent;
+mod alarm_manager;
+mod cert_renewal_manager;
 mod device_profile_manager;
 mod entity_manager;
 mod http_server;
+mod inventory_collector;
 mod operation_file_cache;
 mod operation_workflows;
 mod restart_manager;
+mod shell_command_manager;
 mod software_manager;
 mod state_repository;
 mod tedge_to_te_converter;
@@ -83,6 +91,7 @@ pub struct Capabilities {
     config_update: bool,
     config_snapshot: bool,
     log_upload: bool,
+    command: bool,
 }
 
 #[cfg(test)]
@@ -92,6 +101,7 @@ impl Default for Capabilities {
             config_update: true,
             config_snapshot: true,
             log_upload: true,
+            command: false,
         }
     }
 }
@@ -5,6 +5,7 @@ use async_trait::async_trait;
 use camino::Utf8PathBuf;
 use log::error;
 use log::info;
+use std::collections::HashMap;
 use std::process::Output;
 use std::time::Duration;
 use tedge_actors::fan_in_message_type;
@@ -37,6 +38,7 @@ use tedge_mqtt_ext::MqttMessage;
 use tedge_mqtt_ext::QoS;
 use tedge_script_ext::Execute;
 use tokio::time::sleep;
+use tokio::time::Instant;
 
 /// A generic command state that is published by the [TedgeOperationConverterActor]
 /// to itself for further processing .i.e. after a state update
@@ -56,6 +58,8 @@ pub struct WorkflowActor {
     pub(crate) command_sender: DynSender<InternalCommandState>,
     pub(crate) mqtt_publisher: LoggingSender<MqttMessage>,
     pub(crate) script_runner: ClientMessageBox<Execute, std::io::Result<Output>>,
+    /// The current step of each command in progress, with the instant this step has been entered
+    pub(crate) step_timers: HashMap<String, (String, Instant)>,
 }
 
 #[async_trait]
@@ -161,6 +165,9 @@ impl WorkflowActor {
                 if new_state.is_init() {
                     self.process_command_update(new_state.with_log_path(&log_file.path))
                         .await?;
+                } else if new_state.is_cleared() {
+                    // The command has been removed from the board, whatever its current step
+                    self.step_timers.remove(&new_state.topic.name);
                 }
             }
             Err(WorkflowExecutionError::UnknownOperation { operation }) => {
@@ -189,6 +196,7 @@ impl WorkflowActor {
             log::error!("Unknown command channel: {}", state.topic.name);
             return Ok(());
         };
+        self.record_step_metrics(&operation, &state);
         let mut log_file = self.open_command_log(&state, &operation, &cmd_id);
 
         let action = match self.workflow_repository.get_action(&state) {
@@ -458,6 +466,32 @@ impl WorkflowActor {
         self.process_command_update(adapted_state).await
     }
 
+    /// Update the command metrics on each step transition
+    ///
+    /// - the number of commands reaching each status is counted per operation,
+    /// - the time spent by a command in the previous step is recorded.
+    fn record_step_metrics(&mut self, operation: &OperationType, state: &GenericCommandState) {
+        let operation = operation.to_string();
+        let now = Instant::now();
+        let topic = &state.topic.name;
+        let previous = match self.step_timers.get(topic) {
+            Some((step, _)) if step == &state.status => return,
+            _ => self.step_timers.remove(topic),
+        };
+        if let Some((step, entered_at)) = previous {
+            tedge_metrics::WORKFLOW_STEP_DURATION.observe(
+                &[("operation", &operation), ("step", &step)],
+                now.duration_since(entered_at),
+            );
+        }
+        tedge_metrics::COMMANDS.inc(&[("operation", &operation), ("status", &state.status)]);
+
+        if !state.is_finished() && !state.is_cleared() {
+            self.step_timers
+                .insert(topic.clone(), (state.status.clone(), now));
+        }
+    }
+
     fn open_command_log(
         &mut self,
         state: &GenericCommandState,
@@ -496,7 +530,9 @@ impl WorkflowActor {
             error!("Fail to persist workflow operation state: {err}");
         }
         self.persist_command_board().await?;
-        if !new_state.is_cleared() {
+        if new_state.is_cleared() {
+            self.step_timers.remove(&new_state.topic.name);
+        } else {
             log_file.log_next_step(&new_state.status).await;
             self.command_sender
                 .send(InternalCommandState(new_state.clone()))
@@ -6,6 +6,7 @@ use crate::operation_workflows::message_box::CommandDispatcher;
 use crate::operation_workflows::persist::WorkflowRepository;
 use crate::state_repository::state::agent_state_dir;
 use crate::state_repository::state::AgentStateRepository;
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::process::Output;
 use tedge_actors::futures::channel::mpsc;
@@ -139,6 +140,7 @@ impl Builder<WorkflowActor> for WorkflowActorBuilder {
             mqtt_publisher: self.mqtt_publisher,
             command_sender: self.command_sender,
             script_runner: self.script_runner,
+            step_timers: HashMap::new(),
         }
     }
 }
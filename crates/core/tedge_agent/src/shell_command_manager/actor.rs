@@ -0,0 +1,368 @@
+use crate::shell_command_manager::config::ShellCommandConfig;
+use crate::shell_command_manager::error::ShellCommandError;
+use async_trait::async_trait;
+use std::collections::HashSet;
+use std::process::ExitStatus;
+use std::process::Stdio;
+use tedge_actors::Actor;
+use tedge_actors::ChannelError;
+use tedge_actors::CloneSender;
+use tedge_actors::MessageReceiver;
+use tedge_actors::RuntimeError;
+use tedge_actors::Sender;
+use tedge_actors::SimpleMessageBox;
+use tedge_api::commands::CommandStatus;
+use tedge_api::commands::Jsonify;
+use tedge_api::commands::ShellCommandCmd;
+
+use tedge_api::mqtt_topics::OperationType;
+use tedge_api::CommandLog;
+use tedge_script_ext::kill_group;
+use tedge_script_ext::kill_group_on_timeout;
+use tedge_script_ext::Execute;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::BufReader;
+use tokio::process::Command;
+use tokio::task::JoinSet;
+use tracing::error;
+use tracing::info;
+
+/// The marker appended to an output truncated to fit in the command payload
+const TRUNCATION_MARKER: &str = "[output truncated]\n";
+
+/// Room left in the command payload for the status, the failure reason and the MQTT topic
+const PAYLOAD_MARGIN: usize = 512;
+
+pub struct ShellCommandManagerActor {
+    config: ShellCommandConfig,
+    message_box: SimpleMessageBox<ShellCommandCmd, ShellCommandCmd>,
+
+    /// The commands being executed by this actor,
+    /// i.e. for which the executing state is an echo of the state sent by this actor
+    executing: HashSet<String>,
+}
+
+#[async_trait]
+impl Actor for ShellCommandManagerActor {
+    fn name(&self) -> &str {
+        "ShellCommandManagerActor"
+    }
+
+    async fn run(mut self) -> Result<(), RuntimeError> {
+        // Each command is run by its own task, so a long-running command doesn't block the others.
+        // The tasks are aborted, killing their processes, when the actor stops
+        let mut running = JoinSet::new();
+        loop {
+            tokio::select! {
+                request = self.message_box.recv() => match request {
+                    Some(request) => self.process_command(&mut running, request).await?,
+                    None => break,
+                },
+                Some(result) = running.join_next() => match result {
+                    Ok(Ok(())) => (),
+                    Ok(Err(err)) => return Err(err.into()),
+                    Err(err) => error!("A command task failed: {err}"),
+                },
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The output of a command, as collected while the command is running
+struct CommandOutput {
+    output: String,
+    /// The size of the output once escaped as a JSON string
+    size: usize,
+    max_size: usize,
+    truncated: bool,
+    log: Option<CommandLog>,
+}
+
+impl ShellCommandManagerActor {
+    pub fn new(
+        config: ShellCommandConfig,
+        message_box: SimpleMessageBox<ShellCommandCmd, ShellCommandCmd>,
+    ) -> Self {
+        Self {
+            config,
+            message_box,
+            executing: HashSet::new(),
+        }
+    }
+
+    async fn process_command(
+        &mut self,
+        running: &mut JoinSet<Result<(), ChannelError>>,
+        command: ShellCommandCmd,
+    ) -> Result<(), RuntimeError> {
+        match command.status() {
+            CommandStatus::Scheduled => {
+                self.executing.insert(command.cmd_id.clone());
+                let executing = command.clone().with_status(CommandStatus::Executing);
+                self.message_box.send(executing).await?;
+
+                let config = self.config.clone();
+                let mut responses = self.message_box.sender_clone();
+                running.spawn(async move {
+                    let response = execute_command(&config, command).await;
+                    responses.send(response).await
+                });
+            }
+            CommandStatus::Executing => {
+                if !self.executing.remove(&command.cmd_id) {
+                    // The process has been lost, and cannot be resumed
+                    let error = "The agent has been restarted while executing the command";
+                    error!(error);
+                    self.message_box
+                        .send(command.with_error(error.to_string()))
+                        .await?;
+                }
+            }
+            _ => {
+                // Only handle commands in the scheduled state
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Run a command till completion, returning the command in its final state
+async fn execute_command(config: &ShellCommandConfig, command: ShellCommandCmd) -> ShellCommandCmd {
+    match run_command(config, &command).await {
+        Ok((output, status)) => {
+            let mut response = if status.success() {
+                command.with_status(CommandStatus::Successful)
+            } else {
+                let error = format!("The command exited with {status}");
+                error!(error);
+                command.with_error(error)
+            };
+            response.payload.output = Some(output);
+            response.payload.exit_code = status.code();
+            response
+        }
+        Err(err) => {
+            let error = format!("Fail to execute the command: {err}");
+            error!(error);
+            command.with_error(error)
+        }
+    }
+}
+
+/// Run the command, streaming its output lines to the command log as they are produced
+async fn run_command(
+    config: &ShellCommandConfig,
+    command: &ShellCommandCmd,
+) -> Result<(String, ExitStatus), ShellCommandError> {
+    let command_line = &command.payload.command;
+    if let Some(operator) = shell_syntax(command_line) {
+        return Err(ShellCommandError::ShellSyntax { operator });
+    }
+    let execute = Execute::try_new(command_line)
+        .map_err(|_| ShellCommandError::InvalidCommandLine(command_line.clone()))?;
+    if !config.is_allowed(&execute.command) {
+        return Err(ShellCommandError::NotAllowed {
+            program: execute.command,
+        });
+    }
+
+    let mut process = match &config.user {
+        Some(user) => {
+            let mut process = Command::new("sudo");
+            process.args(["-n", "-u", user, "--"]).arg(&execute.command);
+            process
+        }
+        None => Command::new(&execute.command),
+    };
+    // The command is run in its own process group,
+    // so the processes it spawns (notably the command run by sudo) can be killed along
+    let mut child = process
+        .args(&execute.args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0)
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|source| ShellCommandError::ExecutionFailed {
+            program: execute.command.clone(),
+            source,
+        })?;
+    let mut group = ProcessGroupGuard { pgid: child.id() };
+
+    let mut output = CommandOutput {
+        output: String::new(),
+        size: 0,
+        max_size: max_output_size(config, command),
+        truncated: false,
+        log: command.payload.log_path.as_ref().map(|path| {
+            CommandLog::from_log_path(
+                path,
+                OperationType::Command.to_string(),
+                command.cmd_id.clone(),
+            )
+        }),
+    };
+    output.log_info(&format!("Running: {command_line}")).await;
+
+    let pgid = group.pgid;
+    let mut stdout = child.stdout.take().map(|out| BufReader::new(out).lines());
+    let mut stderr = child.stderr.take().map(|err| BufReader::new(err).lines());
+    let collect = async {
+        loop {
+            let line = tokio::select! {
+                line = next_line(&mut stdout), if stdout.is_some() => line,
+                line = next_line(&mut stderr), if stderr.is_some() => line,
+                else => break,
+            };
+            if let Some(line) = line {
+                output.push_line(line).await;
+            }
+        }
+        child.wait().await
+    };
+
+    let graceful_timeout = config.graceful_timeout;
+    let forceful_timeout = config.forceful_timeout;
+    let status = match pgid {
+        Some(pgid) => tokio::select! {
+            status = collect => status,
+            not_killed = kill_group_on_timeout(pgid, graceful_timeout, forceful_timeout) => Err(not_killed),
+        },
+        None => collect.await,
+    };
+    if status.is_ok() {
+        group.disarm();
+    }
+    let status = status.map_err(|source| ShellCommandError::ExecutionFailed {
+        program: execute.command.clone(),
+        source,
+    })?;
+
+    output.log_info(&format!("Exited with {status}")).await;
+    Ok((output.into_string(), status))
+}
+
+/// The maximum size of the output, once escaped in the JSON payload of the command
+///
+/// The output is bounded by the size requested by the requester, if any,
+/// and by the agent limit minus the size of the other fields of the payload.
+fn max_output_size(config: &ShellCommandConfig, command: &ShellCommandCmd) -> usize {
+    let mut payload = command.payload.clone();
+    payload.output = Some(String::new());
+    payload.exit_code = Some(i32::MIN);
+    let other_fields = payload.to_bytes().len() + PAYLOAD_MARGIN;
+    let max_size = config.max_output_size.saturating_sub(other_fields);
+    match command.payload.max_output_size {
+        Some(requested) => requested.min(max_size),
+        None => max_size,
+    }
+}
+
+/// Return the first shell operator used by a command line, if any
+///
+/// As a command is run without a shell, pipes, redirections, command lists and expansions
+/// would be passed verbatim to the program, hence are rejected.
+/// Quoted operators are accepted, as plain arguments.
+fn shell_syntax(command_line: &str) -> Option<char> {
+    let mut single_quoted = false;
+    let mut double_quoted = false;
+    let mut escaped = false;
+    for c in command_line.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if !single_quoted => escaped = true,
+            '\'' if !double_quoted => single_quoted = !single_quoted,
+            '"' if !single_quoted => double_quoted = !double_quoted,
+            '$' | '`' if !single_quoted => return Some(c),
+            '|' | '&' | ';' | '<' | '>' | '(' | ')' if !single_quoted && !double_quoted => {
+                return Some(c)
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Read the next line of an output stream, marking the stream as closed on end of file
+async fn next_line<R>(lines: &mut Option<tokio::io::Lines<R>>) -> Option<String>
+where
+    R: tokio::io::AsyncBufRead + Unpin,
+{
+    let reader = lines.as_mut()?;
+    match reader.next_line().await {
+        Ok(Some(line)) => Some(line),
+        Ok(None) | Err(_) => {
+            *lines = None;
+            None
+        }
+    }
+}
+
+impl CommandOutput {
+    async fn push_line(&mut self, line: String) {
+        if let Some(log) = self.log.as_mut() {
+            if let Err(err) = log.write(format!("{line}\n")).await {
+                error!("Fail to log to {}: {err}", log.path)
+            }
+        }
+
+        // Only whole lines are returned, leaving room for the truncation marker.
+        // The sizes are those of the escaped JSON strings, quotes and control characters taking more room.
+        let capacity = self.max_size.saturating_sub(json_size(TRUNCATION_MARKER));
+        let line_size = json_size(&line) + json_size("\n");
+        if !self.truncated && self.size + line_size <= capacity {
+            self.output.push_str(&line);
+            self.output.push('\n');
+            self.size += line_size;
+        } else {
+            self.truncated = true;
+        }
+    }
+
+    async fn log_info(&mut self, message: &str) {
+        match self.log.as_mut() {
+            Some(log) => log.log_info(message).await,
+            None => info!("{message}"),
+        }
+    }
+
+    fn into_string(self) -> String {
+        if self.truncated {
+            format!("{}{TRUNCATION_MARKER}", self.output)
+        } else {
+            self.output
+        }
+    }
+}
+
+/// The size of a string once escaped in a JSON payload, without the enclosing quotes
+fn json_size(text: &str) -> usize {
+    serde_json::to_string(text)
+        .map(|json| json.len() - 2)
+        .unwrap_or(text.len() * 6)
+}
+
+/// Kill the process group of a command which is not awaited till completion
+///
+/// The direct child is killed on drop by tokio, but not the processes it spawned.
+struct ProcessGroupGuard {
+    pgid: Option<u32>,
+}
+
+impl ProcessGroupGuard {
+    fn disarm(&mut self) {
+        self.pgid = None;
+    }
+}
+
+impl Drop for ProcessGroupGuard {
+    fn drop(&mut self) {
+        if let Some(pgid) = self.pgid {
+            kill_group(pgid);
+        }
+    }
+}
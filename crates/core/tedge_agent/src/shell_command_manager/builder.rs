@@ -0,0 +1,94 @@
+use crate::shell_command_manager::actor::ShellCommandManagerActor;
+use crate::shell_command_manager::config::ShellCommandConfig;
+use tedge_actors::Builder;
+use tedge_actors::DynSender;
+use tedge_actors::LinkError;
+use tedge_actors::MappingSender;
+use tedge_actors::MessageSink;
+use tedge_actors::MessageSource;
+use tedge_actors::NoConfig;
+use tedge_actors::RestartableBuilder;
+use tedge_actors::RuntimeRequest;
+use tedge_actors::RuntimeRequestSink;
+use tedge_actors::SimpleMessageBoxBuilder;
+use tedge_api::commands::ShellCommandCmd;
+use tedge_api::mqtt_topics::OperationType;
+use tedge_api::workflow::GenericCommandData;
+use tedge_api::workflow::GenericCommandState;
+use tedge_api::workflow::OperationName;
+
+pub struct ShellCommandManagerBuilder {
+    config: ShellCommandConfig,
+    message_box: SimpleMessageBoxBuilder<ShellCommandCmd, ShellCommandCmd>,
+}
+
+impl ShellCommandManagerBuilder {
+    pub fn new(config: ShellCommandConfig) -> Self {
+        let message_box = SimpleMessageBoxBuilder::new("ShellCommandManager", 10);
+
+        Self {
+            config,
+            message_box,
+        }
+    }
+}
+
+impl MessageSink<ShellCommandCmd> for ShellCommandManagerBuilder {
+    fn get_sender(&self) -> DynSender<ShellCommandCmd> {
+        self.message_box.get_sender()
+    }
+}
+
+impl MessageSource<GenericCommandData, NoConfig> for ShellCommandManagerBuilder {
+    fn connect_sink(&mut self, config: NoConfig, peer: &impl MessageSink<GenericCommandData>) {
+        self.message_box.connect_sink(config, &peer.get_sender())
+    }
+}
+
+impl MessageSource<ShellCommandCmd, NoConfig> for ShellCommandManagerBuilder {
+    fn connect_sink(&mut self, config: NoConfig, peer: &impl MessageSink<ShellCommandCmd>) {
+        self.message_box.connect_sink(config, peer)
+    }
+}
+
+impl IntoIterator for &ShellCommandManagerBuilder {
+    type Item = (OperationName, DynSender<GenericCommandState>);
+    type IntoIter = std::vec::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let sender =
+            MappingSender::new(self.message_box.get_sender(), |msg: GenericCommandState| {
+                msg.try_into().ok()
+            });
+        vec![(OperationType::Command.to_string(), sender.into())].into_iter()
+    }
+}
+
+impl RuntimeRequestSink for ShellCommandManagerBuilder {
+    fn get_signal_sender(&self) -> DynSender<RuntimeRequest> {
+        self.message_box.get_signal_sender()
+    }
+}
+
+impl Builder<ShellCommandManagerActor> for ShellCommandManagerBuilder {
+    type Error = LinkError;
+
+    fn try_build(self) -> Result<ShellCommandManagerActor, Self::Error> {
+        Ok(self.build())
+    }
+
+    fn build(self) -> ShellCommandManagerActor {
+        ShellCommandManagerActor::new(self.config, self.message_box.build())
+    }
+}
+
+impl RestartableBuilder<ShellCommandManagerActor> for ShellCommandManagerBuilder {
+    type Error = LinkError;
+
+    fn rebuild(&mut self) -> Result<ShellCommandManagerActor, Self::Error> {
+        Ok(ShellCommandManagerActor::new(
+            self.config.clone(),
+            self.message_box.rebuild()?,
+        ))
+    }
+}
@@ -0,0 +1,45 @@
+use std::time::Duration;
+use tedge_config::TEdgeConfig;
+
+#[derive(Debug, Clone)]
+pub struct ShellCommandConfig {
+    /// The programs allowed to be run, `*` allowing any program
+    pub allowed: Vec<String>,
+
+    /// The user running the commands, if not the agent user
+    pub user: Option<String>,
+
+    pub graceful_timeout: Duration,
+    pub forceful_timeout: Duration,
+
+    /// The maximum size of the output returned in the command payload
+    ///
+    /// The output is still fully logged in the command log file.
+    pub max_output_size: usize,
+}
+
+impl ShellCommandConfig {
+    pub fn from_tedge_config(
+        tedge_config: &TEdgeConfig,
+        max_output_size: usize,
+    ) -> ShellCommandConfig {
+        let command = &tedge_config.agent.command;
+        ShellCommandConfig {
+            allowed: command.allowed.0.clone(),
+            user: command.user.or_none().cloned(),
+            graceful_timeout: command.timeout.duration(),
+            forceful_timeout: command.kill_timeout.duration(),
+            max_output_size,
+        }
+    }
+
+    /// Tell if a program is allowed to be run
+    ///
+    /// The program has to be listed as given in the command line:
+    /// allowing `ls` doesn't allow `/bin/ls` nor `./ls`.
+    pub fn is_allowed(&self, program: &str) -> bool {
+        self.allowed
+            .iter()
+            .any(|allowed| allowed == "*" || allowed == program)
+    }
+}
@@ -0,0 +1,21 @@
+#[derive(Debug, thiserror::Error)]
+pub enum ShellCommandError {
+    #[error("Invalid command line: {0:?}")]
+    InvalidCommandLine(String),
+
+    #[error(
+        "Shell syntax is not supported, found {operator:?}: wrap the command line into a script"
+    )]
+    ShellSyntax { operator: char },
+
+    #[error(
+        "The program {program:?} is not allowed to be run, as not listed in agent.command.allowed"
+    )]
+    NotAllowed { program: String },
+
+    #[error("Fail to run {program:?}: {source}")]
+    ExecutionFailed {
+        program: String,
+        source: std::io::Error,
+    },
+}
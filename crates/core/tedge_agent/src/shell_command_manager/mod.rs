@@ -0,0 +1,7 @@
+pub mod actor;
+pub mod builder;
+pub mod config;
+pub mod error;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,259 @@
+use crate::shell_command_manager::builder::ShellCommandManagerBuilder;
+use crate::shell_command_manager::config::ShellCommandConfig;
+use std::time::Duration;
+use tedge_actors::test_helpers::MessageReceiverExt;
+use tedge_actors::test_helpers::TimedMessageBox;
+use tedge_actors::Actor;
+use tedge_actors::Builder;
+use tedge_actors::DynError;
+use tedge_actors::MessageReceiver;
+use tedge_actors::MessageSource;
+use tedge_actors::NoConfig;
+use tedge_actors::Sender;
+use tedge_actors::SimpleMessageBox;
+use tedge_actors::SimpleMessageBoxBuilder;
+use tedge_api::commands::CommandStatus;
+use tedge_api::commands::Jsonify;
+use tedge_api::commands::ShellCommandCmd;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_test_utils::fs::TempTedgeDir;
+
+const TEST_TIMEOUT_MS: Duration = Duration::from_millis(3000);
+
+#[tokio::test]
+async fn run_an_allowed_command() -> Result<(), DynError> {
+    let temp_dir = TempTedgeDir::new();
+    let log_path = temp_dir.utf8_path().join("workflow-command-1234.log");
+    let mut converter_box = spawn_shell_command_manager(test_config(&["echo"])).await?;
+
+    let mut command = shell_command_cmd("echo 'hello world'");
+    command.payload.log_path = Some(log_path.clone());
+    converter_box.send(command).await?;
+
+    let status = converter_box.recv().await.unwrap().status();
+    assert_eq!(status, CommandStatus::Executing);
+
+    let response = converter_box.recv().await.unwrap();
+    assert_eq!(response.status(), CommandStatus::Successful);
+    assert_eq!(response.payload.output.as_deref(), Some("hello world\n"));
+    assert_eq!(response.payload.exit_code, Some(0));
+
+    let log = std::fs::read_to_string(&log_path)?;
+    assert!(log.contains("hello world"), "{log}");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn report_the_exit_code_of_a_failing_command() -> Result<(), DynError> {
+    let mut converter_box = spawn_shell_command_manager(test_config(&["sh"])).await?;
+    converter_box
+        .send(shell_command_cmd("sh -c 'echo oops >&2; exit 3'"))
+        .await?;
+
+    let _executing = converter_box.recv().await.unwrap();
+    let response = converter_box.recv().await.unwrap();
+    assert!(matches!(response.status(), CommandStatus::Failed { .. }));
+    assert_eq!(response.payload.output.as_deref(), Some("oops\n"));
+    assert_eq!(response.payload.exit_code, Some(3));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn truncate_the_output_to_the_requested_size() -> Result<(), DynError> {
+    let temp_dir = TempTedgeDir::new();
+    let log_path = temp_dir.utf8_path().join("workflow-command-1234.log");
+    let mut converter_box = spawn_shell_command_manager(test_config(&["seq"])).await?;
+
+    let mut command = shell_command_cmd("seq 1000");
+    command.payload.max_output_size = Some(100);
+    command.payload.log_path = Some(log_path.clone());
+    converter_box.send(command).await?;
+
+    let _executing = converter_box.recv().await.unwrap();
+    let response = converter_box.recv().await.unwrap();
+    assert_eq!(response.status(), CommandStatus::Successful);
+    let output = response.payload.output.unwrap();
+    assert!(output.len() <= 100, "{output}");
+    assert!(output.starts_with("1\n2\n3\n"), "{output}");
+    assert!(output.ends_with("[output truncated]\n"), "{output}");
+
+    // The full output is kept in the log file
+    let log = std::fs::read_to_string(&log_path)?;
+    assert!(log.contains("\n1000\n"), "{log}");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn bound_the_output_by_its_escaped_size() -> Result<(), DynError> {
+    let mut config = test_config(&["sh"]);
+    config.max_output_size = 1024;
+    let mut converter_box = spawn_shell_command_manager(config).await?;
+
+    // Each line of 5 bytes takes 18 bytes once escaped in the JSON payload
+    converter_box
+        .send(shell_command_cmd(
+            r#"sh -c 'for i in $(seq 200); do printf "\001\002\"\\\\\n"; done'"#,
+        ))
+        .await?;
+
+    let _executing = converter_box.recv().await.unwrap();
+    let response = converter_box.recv().await.unwrap();
+    assert_eq!(response.status(), CommandStatus::Successful);
+    let payload = response.payload.to_bytes();
+    assert!(
+        payload.len() <= 1024,
+        "{}",
+        String::from_utf8_lossy(&payload)
+    );
+    let output = response.payload.output.unwrap();
+    assert!(output.starts_with("\u{1}\u{2}\"\\\n"), "{output:?}");
+    assert!(output.ends_with("[output truncated]\n"), "{output:?}");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn reject_a_program_that_is_not_allowed() -> Result<(), DynError> {
+    let mut converter_box = spawn_shell_command_manager(test_config(&["echo"])).await?;
+    converter_box
+        .send(shell_command_cmd("rm -rf /tmp/foo"))
+        .await?;
+
+    let _executing = converter_box.recv().await.unwrap();
+    let response = converter_box.recv().await.unwrap();
+    let CommandStatus::Failed { reason } = response.status() else {
+        panic!("Unexpected status: {:?}", response.status());
+    };
+    assert!(reason.contains("not allowed"), "{reason}");
+    assert_eq!(response.payload.exit_code, None);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn reject_shell_syntax() -> Result<(), DynError> {
+    let mut converter_box = spawn_shell_command_manager(test_config(&["*"])).await?;
+    converter_box
+        .send(shell_command_cmd("df -h | grep root"))
+        .await?;
+
+    let _executing = converter_box.recv().await.unwrap();
+    let response = converter_box.recv().await.unwrap();
+    let CommandStatus::Failed { reason } = response.status() else {
+        panic!("Unexpected status: {:?}", response.status());
+    };
+    assert!(reason.contains("Shell syntax is not supported"), "{reason}");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn accept_quoted_shell_operators_as_arguments() -> Result<(), DynError> {
+    let mut converter_box = spawn_shell_command_manager(test_config(&["echo"])).await?;
+    converter_box
+        .send(shell_command_cmd(r#"echo 'a | b' "c > d" e\;f"#))
+        .await?;
+
+    let _executing = converter_box.recv().await.unwrap();
+    let response = converter_box.recv().await.unwrap();
+    assert_eq!(response.status(), CommandStatus::Successful);
+    assert_eq!(
+        response.payload.output.as_deref(),
+        Some("a | b c > d e;f\n")
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn run_commands_concurrently() -> Result<(), DynError> {
+    let mut converter_box = spawn_shell_command_manager(test_config(&["*"])).await?;
+    converter_box.send(shell_command_cmd("sleep 10")).await?;
+    let mut quick_command = shell_command_cmd("echo hello");
+    quick_command.cmd_id = "5678".to_string();
+    converter_box.send(quick_command).await?;
+
+    let executing = converter_box.recv().await.unwrap();
+    assert_eq!(executing.cmd_id, "1234");
+    assert_eq!(executing.status(), CommandStatus::Executing);
+    let executing = converter_box.recv().await.unwrap();
+    assert_eq!(executing.cmd_id, "5678");
+    assert_eq!(executing.status(), CommandStatus::Executing);
+
+    // The quick command completes while the long-running one is still running
+    let response = converter_box.recv().await.unwrap();
+    assert_eq!(response.cmd_id, "5678");
+    assert_eq!(response.status(), CommandStatus::Successful);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn kill_a_command_on_timeout() -> Result<(), DynError> {
+    let mut config = test_config(&["*"]);
+    config.graceful_timeout = Duration::from_millis(100);
+    config.forceful_timeout = Duration::from_millis(100);
+    let mut converter_box = spawn_shell_command_manager(config).await?;
+    converter_box.send(shell_command_cmd("sleep 10")).await?;
+
+    let _executing = converter_box.recv().await.unwrap();
+    let response = converter_box.recv().await.unwrap();
+    let CommandStatus::Failed { reason } = response.status() else {
+        panic!("Unexpected status: {:?}", response.status());
+    };
+    assert!(reason.contains("signal"), "{reason}");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn fail_a_command_interrupted_by_a_restart() -> Result<(), DynError> {
+    let mut converter_box = spawn_shell_command_manager(test_config(&["*"])).await?;
+    converter_box
+        .send(shell_command_cmd("echo hello").with_status(CommandStatus::Executing))
+        .await?;
+
+    let response = converter_box.recv().await.unwrap();
+    assert!(matches!(response.status(), CommandStatus::Failed { .. }));
+
+    Ok(())
+}
+
+fn test_config(allowed: &[&str]) -> ShellCommandConfig {
+    ShellCommandConfig {
+        allowed: allowed.iter().map(|program| program.to_string()).collect(),
+        user: None,
+        graceful_timeout: Duration::from_secs(5),
+        forceful_timeout: Duration::from_secs(1),
+        max_output_size: 1024 * 1024,
+    }
+}
+
+fn shell_command_cmd(command_line: &str) -> ShellCommandCmd {
+    let mut command =
+        ShellCommandCmd::new(&EntityTopicId::default_main_device(), "1234".to_string())
+            .with_status(CommandStatus::Scheduled);
+    command.payload.command = command_line.to_string();
+    command
+}
+
+async fn spawn_shell_command_manager(
+    config: ShellCommandConfig,
+) -> Result<TimedMessageBox<SimpleMessageBox<ShellCommandCmd, ShellCommandCmd>>, DynError> {
+    let mut converter_builder: SimpleMessageBoxBuilder<ShellCommandCmd, ShellCommandCmd> =
+        SimpleMessageBoxBuilder::new("Converter", 5);
+
+    let mut actor_builder = ShellCommandManagerBuilder::new(config);
+    converter_builder.connect_sink(NoConfig, &actor_builder);
+    actor_builder.connect_sink(NoConfig, &converter_builder);
+
+    let converter_box = converter_builder.build().with_timeout(TEST_TIMEOUT_MS);
+
+    let actor = actor_builder.build();
+    tokio::spawn(async move { actor.run().await });
+
+    Ok(converter_box)
+}
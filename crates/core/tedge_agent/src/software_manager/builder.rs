@@ -8,6 +8,7 @@ use tedge_actors::MappingSender;
 use tedge_actors::MessageSink;
 use tedge_actors::MessageSource;
 use tedge_actors::NoConfig;
+use tedge_actors::RestartableBuilder;
 use tedge_actors::RuntimeRequest;
 use tedge_actors::RuntimeRequestSink;
 use tedge_actors::SimpleMessageBoxBuilder;
@@ -104,3 +105,14 @@ impl Builder<SoftwareManagerActor> for SoftwareManagerBuilder {
         SoftwareManagerActor::new(self.config, self.message_box.build())
     }
 }
+
+impl RestartableBuilder<SoftwareManagerActor> for SoftwareManagerBuilder {
+    type Error = LinkError;
+
+    fn rebuild(&mut self) -> Result<SoftwareManagerActor, Self::Error> {
+        Ok(SoftwareManagerActor::new(
+            self.config.clone(),
+            self.message_box.rebuild()?,
+        ))
+    }
+}
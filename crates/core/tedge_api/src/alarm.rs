@@ -1,7 +1,14 @@
+use crate::mqtt_topics::Channel;
+use crate::mqtt_topics::ChannelFilter;
+use crate::mqtt_topics::EntityFilter;
 use crate::mqtt_topics::EntityTopicId;
+use crate::mqtt_topics::MqttSchema;
+use mqtt_channel::MqttMessage;
+use mqtt_channel::TopicFilter;
 use serde::Deserialize;
 use serde_json::Value;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::convert::TryFrom;
 use tedge_utils::timestamp::deserialize_optional_string_or_unix_timestamp;
 use time::OffsetDateTime;
@@ -100,6 +107,57 @@ impl TryFrom<&str> for ThinEdgeAlarmData {
     }
 }
 
+/// The alarms hidden from the cloud by the agent, i.e. the alarms that are shelved or suppressed as flapping
+///
+/// The cloud mappers track these alarms from the statuses published by the agent on `te/<entity>/a/<type>/status`.
+/// The agent clears a shelved alarm before hiding it, and restores the state of an alarm before forwarding it again.
+#[derive(Debug, Default)]
+pub struct HiddenAlarms {
+    topics: HashSet<String>,
+}
+
+impl HiddenAlarms {
+    /// The alarm status topics a mapper has to subscribe to, in order to track the hidden alarms
+    pub fn status_topics(mqtt_schema: &MqttSchema) -> TopicFilter {
+        mqtt_schema.topics(EntityFilter::AnyEntity, ChannelFilter::AlarmStatus)
+    }
+
+    /// Tell if a message has to be kept off the cloud, tracking the alarm statuses along the way
+    ///
+    /// The alarm statuses are internal to the device, and so are the alarms that are shelved or suppressed.
+    /// A cloud mapper forwards neither.
+    pub fn hides(&mut self, channel: &Channel, message: &MqttMessage) -> bool {
+        match channel {
+            Channel::AlarmStatus { .. } => {
+                self.update(message);
+                true
+            }
+            Channel::Alarm { .. } => self.contains(&message.topic.name),
+            _ => false,
+        }
+    }
+
+    /// Update the hidden alarms from an alarm status message
+    pub fn update(&mut self, status_message: &MqttMessage) {
+        let Some(alarm_topic) = status_message.topic.name.strip_suffix("/status") else {
+            return;
+        };
+        let status = serde_json::from_slice::<Value>(status_message.payload_bytes())
+            .ok()
+            .and_then(|json| json.get("status")?.as_str().map(str::to_string));
+        if matches!(status.as_deref(), Some("shelved" | "suppressed")) {
+            self.topics.insert(alarm_topic.to_string());
+        } else {
+            self.topics.remove(alarm_topic);
+        }
+    }
+
+    /// Tell if the alarm published on this topic has to be kept off the cloud
+    pub fn contains(&self, alarm_topic: &str) -> bool {
+        self.topics.contains(alarm_topic)
+    }
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum ThinEdgeAlarmDeserializerError {
     #[error("Parsing of alarm message for the type: {alarm_type} failed due to error: {error}. Snipped payload: {payload}")]
@@ -109,7 +167,7 @@ pub enum ThinEdgeAlarmDeserializerError {
         payload: String,
     },
 
-    #[error("Failed to parse as an UTF-8 string the payload received on topic: {topic}, due to error: {error}.")]
+    #[error("Failed to decode the payload received on topic: {topic}, due to error: {error}.")]
     FailedToParsePayloadToString { topic: String, error: String },
 }
 
@@ -245,4 +303,47 @@ mod tests {
 
         assert_eq!(alarm, expected_alarm);
     }
+
+    #[test]
+    fn shelved_and_suppressed_alarms_are_hidden() {
+        let alarm_topic = "te/device/main///a/temperature_high";
+        let status_topic = mqtt_channel::Topic::new_unchecked(&format!("{alarm_topic}/status"));
+        let mut hidden_alarms = HiddenAlarms::default();
+
+        for status in ["shelved", "suppressed"] {
+            hidden_alarms.update(&MqttMessage::new(
+                &status_topic,
+                json!({"status": status}).to_string(),
+            ));
+            assert!(hidden_alarms.contains(alarm_topic));
+        }
+
+        hidden_alarms.update(&MqttMessage::new(&status_topic, r#"{"status":"active"}"#));
+        assert!(!hidden_alarms.contains(alarm_topic));
+
+        hidden_alarms.update(&MqttMessage::new(&status_topic, r#"{"status":"shelved"}"#));
+        hidden_alarms.update(&MqttMessage::new(&status_topic, ""));
+        assert!(!hidden_alarms.contains(alarm_topic));
+    }
+
+    #[test]
+    fn alarm_statuses_and_hidden_alarms_are_kept_off_the_cloud() {
+        let mqtt_schema = MqttSchema::default();
+        let alarm = MqttMessage::new(
+            &mqtt_channel::Topic::new_unchecked("te/device/main///a/temperature_high"),
+            r#"{"text":"too hot"}"#,
+        );
+        let status = MqttMessage::new(
+            &mqtt_channel::Topic::new_unchecked("te/device/main///a/temperature_high/status"),
+            r#"{"status":"shelved"}"#,
+        );
+        let (_, alarm_channel) = mqtt_schema.entity_channel_of(&alarm.topic.name).unwrap();
+        let (_, status_channel) = mqtt_schema.entity_channel_of(&status.topic.name).unwrap();
+        let mut hidden_alarms = HiddenAlarms::default();
+
+        assert!(HiddenAlarms::status_topics(&mqtt_schema).accept(&status));
+        assert!(!hidden_alarms.hides(&alarm_channel, &alarm));
+        assert!(hidden_alarms.hides(&status_channel, &status));
+        assert!(hidden_alarms.hides(&alarm_channel, &alarm));
+    }
 }
@@ -953,6 +953,94 @@ impl CommandPayload for FirmwareUpdateCmdPayload {
     }
 }
 
+/// Command to renew the device certificate
+pub type CertRenewCmd = Command<CertRenewCmdPayload>;
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CertRenewCmdPayload {
+    #[serde(flatten)]
+    pub status: CommandStatus,
+
+    /// Force the generation of a new private key, whatever the configured default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotate_key: Option<bool>,
+
+    /// The expiry date of the renewed certificate, once the command is successful
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "time::serde::rfc3339::option"
+    )]
+    pub not_after: Option<OffsetDateTime>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_path: Option<Utf8PathBuf>,
+}
+
+impl Jsonify for CertRenewCmdPayload {}
+
+impl CommandPayload for CertRenewCmdPayload {
+    fn operation_type() -> OperationType {
+        OperationType::CertRenew
+    }
+
+    fn status(&self) -> CommandStatus {
+        self.status.clone()
+    }
+
+    fn set_status(&mut self, status: CommandStatus) {
+        self.status = status
+    }
+}
+
+/// Command to run a command line on a device, e.g. as requested by a remote shell
+pub type ShellCommandCmd = Command<ShellCommandCmdPayload>;
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ShellCommandCmdPayload {
+    #[serde(flatten)]
+    pub status: CommandStatus,
+
+    /// The command line, split into a program and its arguments as by a shell,
+    /// but run without a shell, i.e. with no pipes, redirections nor variable expansions
+    pub command: String,
+
+    /// The maximum size of the output returned in this payload, as requested by the requester
+    ///
+    /// The full output is kept in the command log file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_output_size: Option<usize>,
+
+    /// The standard output and error of the command, once completed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+
+    /// The exit code of the command, if it exited normally
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_path: Option<Utf8PathBuf>,
+}
+
+impl Jsonify for ShellCommandCmdPayload {}
+
+impl CommandPayload for ShellCommandCmdPayload {
+    fn operation_type() -> OperationType {
+        OperationType::Command
+    }
+
+    fn status(&self) -> CommandStatus {
+        self.status.clone()
+    }
+
+    fn set_status(&mut self, status: CommandStatus) {
+        self.status = status
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1048,4 +1136,25 @@ mod tests {
         // However, if serialized again the custom status is lost
         assert_eq!(request.to_json(), r#"{"status":"unknown"}"#);
     }
+
+    #[test]
+    fn serde_shell_command() {
+        let request = ShellCommandCmdPayload::from_json(
+            r#"{"status":"init","command":"df -h","maxOutputSize":16000}"#,
+        )
+        .expect("Fail to parse the json request");
+        assert_eq!(request.command, "df -h");
+        assert_eq!(request.max_output_size, Some(16000));
+
+        let response = ShellCommandCmdPayload {
+            status: CommandStatus::Successful,
+            output: Some("Filesystem Size Used Avail Use% Mounted on\n".to_string()),
+            exit_code: Some(0),
+            ..request
+        };
+        assert_eq!(
+            response.to_json(),
+            r#"{"status":"successful","command":"df -h","maxOutputSize":16000,"output":"Filesystem Size Used Avail Use% Mounted on\n","exitCode":0}"#
+        );
+    }
 }
@@ -44,7 +44,7 @@ pub mod error {
             payload: String,
         },
 
-        #[error("Failed to parse as an UTF-8 string the payload received on topic: {topic}, due to error: {error}.")]
+        #[error("Failed to decode the payload received on topic: {topic}, due to error: {error}.")]
         FailedToParsePayloadToString { topic: String, error: String },
     }
 }
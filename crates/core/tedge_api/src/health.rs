@@ -10,6 +10,7 @@ use mqtt_channel::Topic;
 use serde::Deserialize;
 use serde::Serialize;
 use serde_json::json;
+use std::collections::BTreeMap;
 use std::fmt::Display;
 use std::process;
 use std::sync::Arc;
@@ -72,6 +73,14 @@ impl ServiceHealthTopic {
     }
 
     pub fn up_message(&self) -> MqttMessage {
+        self.status_message(&HealthStatus {
+            status: Status::Up,
+            checks: BTreeMap::new(),
+        })
+    }
+
+    /// The health message of a service which is running, with the outcome of its self-checks
+    pub fn status_message(&self, health_status: &HealthStatus) -> MqttMessage {
         let now = WallClock.now();
         let time_format = self.time_format;
         let timestamp = time_format.to_json(now).unwrap_or_else(|err| {
@@ -81,16 +90,18 @@ impl ServiceHealthTopic {
             now.to_string().into()
         });
 
-        let health_status = json!({
-            "status": "up",
+        let mut health_status_json = json!({
+            "status": health_status.status,
             "pid": process::id(),
             "time": timestamp
-        })
-        .to_string();
+        });
+        if !health_status.checks.is_empty() {
+            health_status_json["checks"] = json!(health_status.checks);
+        }
 
         let response_topic_health = Topic::new_unchecked(self.as_str());
 
-        MqttMessage::new(&response_topic_health, health_status)
+        MqttMessage::new(&response_topic_health, health_status_json.to_string())
             .with_qos(mqtt_channel::QoS::AtLeastOnce)
             .with_retain()
     }
@@ -106,6 +117,10 @@ impl ServiceHealthTopic {
 pub struct HealthStatus {
     /// Current status of the service, synced by the mapper to the cloud
     pub status: Status,
+
+    /// Outcome of the self-checks of the service, indexed by check name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub checks: BTreeMap<String, HealthCheck>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
@@ -113,10 +128,52 @@ pub struct HealthStatus {
 pub enum Status {
     Up,
     Down,
+    /// The service is running, but some of its self-checks are failing
+    Degraded,
     #[serde(untagged)]
     Other(String),
 }
 
+/// Outcome of a self-check of a service, e.g. the connection to the MQTT broker
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct HealthCheck {
+    pub status: Status,
+
+    /// Human-readable detail, explaining why a check is failing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl HealthCheck {
+    pub fn up() -> Self {
+        HealthCheck {
+            status: Status::Up,
+            detail: None,
+        }
+    }
+
+    pub fn degraded(detail: impl Into<String>) -> Self {
+        HealthCheck {
+            status: Status::Degraded,
+            detail: Some(detail.into()),
+        }
+    }
+
+    pub fn down(detail: impl Into<String>) -> Self {
+        HealthCheck {
+            status: Status::Down,
+            detail: Some(detail.into()),
+        }
+    }
+
+    pub fn with_detail(self, detail: impl Into<String>) -> Self {
+        HealthCheck {
+            detail: Some(detail.into()),
+            ..self
+        }
+    }
+}
+
 impl Default for Status {
     fn default() -> Self {
         Status::Other("unknown".to_string())
@@ -128,6 +185,7 @@ impl Display for Status {
         let status = match self {
             Status::Up => "up",
             Status::Down => "down",
+            Status::Degraded => "degraded",
             Status::Other(val) if val.is_empty() => "unknown",
             Status::Other(val) => val,
         };
@@ -150,7 +208,10 @@ impl HealthStatus {
                     Ok("0") => Status::Down,
                     _ => Status::default(),
                 };
-                HealthStatus { status }
+                HealthStatus {
+                    status,
+                    checks: BTreeMap::new(),
+                }
             } else {
                 serde_json::from_slice(message.payload()).unwrap_or_default()
             };
@@ -160,8 +221,36 @@ impl HealthStatus {
         }
     }
 
+    /// The health status of a running service, derived from the outcome of its self-checks
+    ///
+    /// A service is `up` when all its checks are `up`, and `degraded` otherwise.
+    /// A service is never reported `down` by itself: this status is published on its behalf
+    /// by the MQTT broker when the service is stopped.
+    pub fn from_checks(checks: BTreeMap<String, HealthCheck>) -> Self {
+        let status = if checks.values().all(|check| check.status == Status::Up) {
+            Status::Up
+        } else {
+            Status::Degraded
+        };
+        HealthStatus { status, checks }
+    }
+
     pub fn is_valid(&self) -> bool {
-        self.status == Status::Up || self.status == Status::Down
+        matches!(self.status, Status::Up | Status::Down | Status::Degraded)
+    }
+
+    /// A one-line summary of the failing checks, e.g. `disk: 2% free space left in /var/tedge`
+    pub fn failing_checks(&self) -> Option<String> {
+        let failing: Vec<String> = self
+            .checks
+            .iter()
+            .filter(|(_, check)| check.status != Status::Up)
+            .map(|(name, check)| match &check.detail {
+                Some(detail) => format!("{name}: {detail}"),
+                None => format!("{name}: {}", check.status),
+            })
+            .collect();
+        (!failing.is_empty()).then(|| failing.join(", "))
     }
 }
 
@@ -221,6 +310,12 @@ mod tests {
         Status::default();
         "service-health-status-empty-message"
     )]
+    #[test_case(
+        "te/device/main/service/tedge-mapper-c8y/status/health",
+        r#"{"status":"degraded","checks":{"disk":{"status":"degraded","detail":"1% free"}}}"#,
+        Status::Degraded;
+        "service-health-status-degraded"
+    )]
     #[test_case(
         "te/device/main/service/mosquitto-xyz-bridge/status/health",
         "1",
@@ -275,6 +370,50 @@ mod tests {
         assert!(regex.is_match(timestamp));
     }
 
+    #[test]
+    fn status_is_derived_from_checks() {
+        let checks = BTreeMap::from([
+            ("mqtt".to_string(), HealthCheck::up()),
+            (
+                "disk".to_string(),
+                HealthCheck::up().with_detail("50% free"),
+            ),
+        ]);
+        assert_eq!(HealthStatus::from_checks(checks.clone()).status, Status::Up);
+
+        let mut checks = checks;
+        checks.insert(
+            "bridge".to_string(),
+            HealthCheck::down("tedge-mapper-bridge-c8y is down"),
+        );
+        let health_status = HealthStatus::from_checks(checks);
+        assert_eq!(health_status.status, Status::Degraded);
+        assert_eq!(
+            health_status.failing_checks().as_deref(),
+            Some("bridge: tedge-mapper-bridge-c8y is down")
+        );
+    }
+
+    #[test]
+    fn checks_are_published_with_the_status() {
+        let health_topic = ServiceHealthTopic {
+            topic: "te/device/main/service/test_daemon/status/health".into(),
+            time_format: TimeFormat::Unix,
+        };
+        let health_status = HealthStatus::from_checks(BTreeMap::from([(
+            "disk".to_string(),
+            HealthCheck::degraded("1% free space left in /var/tedge"),
+        )]));
+        let msg = health_topic.status_message(&health_status);
+
+        let payload: Value = serde_json::from_slice(msg.payload_bytes()).unwrap();
+        assert_eq!(payload["status"], "degraded");
+        assert_eq!(
+            payload["checks"],
+            serde_json::json!({"disk": {"status": "degraded", "detail": "1% free space left in /var/tedge"}})
+        );
+    }
+
     #[test]
     fn is_unix_timestamp() {
         let health_topic = ServiceHealthTopic {
@@ -9,6 +9,7 @@ pub mod health;
 pub mod measurement;
 pub mod mqtt_topics;
 pub mod path;
+pub mod payload;
 pub mod script;
 mod software;
 pub mod store;
@@ -3,6 +3,8 @@
 //! [^1]: It only allocates in presence of escaped strings as keys.
 //!
 use crate::measurement::MeasurementVisitor;
+use crate::payload::cbor_content;
+use crate::payload::PayloadEncoding;
 use serde::de::DeserializeSeed;
 use serde::de::MapAccess;
 use serde::de::{self};
@@ -27,6 +29,34 @@ pub fn parse_str<T: MeasurementVisitor>(
     Ok(())
 }
 
+/// Parses `input` as ThinEdge CBOR yielding the parsed measurements to the `visitor`.
+///
+/// ThinEdge CBOR is ThinEdge JSON encoded as CBOR,
+/// optionally prefixed by the CBOR self-described tag.
+pub fn parse_cbor<T: MeasurementVisitor>(
+    input: &[u8],
+    visitor: &mut T,
+) -> Result<(), ThinEdgeCborParserError> {
+    let value: serde_json::Value = ciborium::from_reader(cbor_content(input))?;
+
+    let parser = ThinEdgeJsonParser { visitor };
+
+    value.deserialize_map(parser)?;
+    Ok(())
+}
+
+/// Parses `input` as ThinEdge JSON or ThinEdge CBOR, depending on the content marker of the payload.
+pub fn parse_payload<T: MeasurementVisitor>(
+    input: &[u8],
+    visitor: &mut T,
+) -> Result<(), ThinEdgePayloadParserError> {
+    match PayloadEncoding::of(input) {
+        PayloadEncoding::Json => parse_str(std::str::from_utf8(input)?, visitor)?,
+        PayloadEncoding::Cbor => parse_cbor(input, visitor)?,
+    }
+    Ok(())
+}
+
 /// The error returned by `parse_str`.
 #[derive(Debug, thiserror::Error)]
 #[error("Invalid JSON: {error}: `{input_excerpt}`")]
@@ -37,6 +67,29 @@ pub struct ThinEdgeJsonParserError {
     input_excerpt: String,
 }
 
+/// The error returned by `parse_cbor`.
+#[derive(Debug, thiserror::Error)]
+pub enum ThinEdgeCborParserError {
+    #[error("Invalid CBOR: {0}")]
+    InvalidCbor(#[from] ciborium::de::Error<std::io::Error>),
+
+    #[error("Invalid measurement: {0}")]
+    InvalidMeasurement(#[from] serde_json::Error),
+}
+
+/// The error returned by `parse_payload`.
+#[derive(Debug, thiserror::Error)]
+pub enum ThinEdgePayloadParserError {
+    #[error(transparent)]
+    Json(#[from] ThinEdgeJsonParserError),
+
+    #[error(transparent)]
+    Cbor(#[from] ThinEdgeCborParserError),
+
+    #[error("Invalid UTF-8: {0}")]
+    NotUtf8(#[from] std::str::Utf8Error),
+}
+
 /// Parses top-level ThinEdge JSON:
 ///
 /// ```grammar
@@ -287,7 +340,11 @@ mod tests {
     use time::macros::datetime;
     use time::OffsetDateTime;
 
+    use super::parse_cbor;
+    use super::parse_payload;
     use super::parse_str;
+    use crate::payload::encode_cbor;
+    use serde_json::json;
 
     #[test]
     fn it_deserializes_thin_edge_json() -> anyhow::Result<()> {
@@ -412,4 +469,75 @@ mod tests {
 
         parse_str(input, &mut builder).unwrap();
     }
+
+    #[test]
+    fn it_deserializes_thin_edge_cbor() -> anyhow::Result<()> {
+        use crate::measurement::builder::ThinEdgeJsonBuilder;
+        let input = encode_cbor(&json!({
+            "coordinate": {
+                "x": 1,
+                "y": 2.0,
+                "z": -42.0
+            },
+            "pressure": 123.4,
+            "temperature": 24,
+            "time": 1619794994,
+        }))
+        .unwrap();
+
+        let mut builder = ThinEdgeJsonBuilder::default();
+
+        parse_cbor(&input, &mut builder)?;
+
+        let output = builder.done()?;
+
+        assert_eq!(output.timestamp, Some(datetime!(2021-04-30 15:03:14 UTC)));
+
+        assert_eq!(
+            output.values,
+            vec![
+                (
+                    "coordinate",
+                    vec![("x", 1.0).into(), ("y", 2.0).into(), ("z", -42.0).into(),]
+                )
+                    .into(),
+                ("pressure", 123.4).into(),
+                ("temperature", 24.0).into(),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn it_selects_the_parser_from_the_payload_content_marker() {
+        use crate::measurement::builder::ThinEdgeJsonBuilder;
+
+        let mut builder = ThinEdgeJsonBuilder::default();
+        parse_payload(br#"{"temperature": 24}"#, &mut builder).unwrap();
+        assert_eq!(
+            builder.done().unwrap().values,
+            vec![("temperature", 24.0).into()]
+        );
+
+        let mut builder = ThinEdgeJsonBuilder::default();
+        parse_payload(
+            &encode_cbor(&json!({"temperature": 24})).unwrap(),
+            &mut builder,
+        )
+        .unwrap();
+        assert_eq!(
+            builder.done().unwrap().values,
+            vec![("temperature", 24.0).into()]
+        );
+    }
+
+    #[test]
+    fn it_rejects_invalid_thin_edge_cbor() {
+        use crate::measurement::builder::ThinEdgeJsonBuilder;
+
+        let input = encode_cbor(&json!({"temperature": [21.5, 22.0]})).unwrap();
+        let mut builder = ThinEdgeJsonBuilder::default();
+
+        assert!(parse_payload(&input, &mut builder).is_err());
+    }
 }
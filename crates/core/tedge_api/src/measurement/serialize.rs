@@ -138,7 +138,9 @@ impl MeasurementVisitor for ThinEdgeJsonSerializer {
         Ok(())
     }
 
-    fn visit_text_property(&mut self, _name: &str, _value: &str) -> Result<(), Self::Error> {
+    fn visit_text_property(&mut self, name: &str, value: &str) -> Result<(), Self::Error> {
+        self.json.write_key(name)?;
+        self.json.write_str(value)?;
         Ok(())
     }
 }
@@ -209,6 +211,17 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn serialize_text_properties() -> anyhow::Result<()> {
+        let mut serializer = ThinEdgeJsonSerializer::new();
+        serializer.visit_text_property("type", "environment")?;
+        serializer.visit_measurement("temperature", 25.5)?;
+        let expected_output = r#"{"type":"environment","temperature":25.5}"#;
+        let output = serializer.into_string()?;
+        assert_eq!(expected_output, output);
+        Ok(())
+    }
+
     #[test]
     fn serialize_empty_message() -> anyhow::Result<()> {
         let mut serializer = ThinEdgeJsonSerializer::new();
@@ -175,6 +175,7 @@ impl MqttSchema {
             ChannelFilter::EventMetadata => "/e/+/meta".to_string(),
             ChannelFilter::Alarm => "/a/+".to_string(),
             ChannelFilter::AlarmMetadata => "/a/+/meta".to_string(),
+            ChannelFilter::AlarmStatus => "/a/+/status".to_string(),
             ChannelFilter::AnyCommand => "/cmd/+/+".to_string(),
             ChannelFilter::Command(operation) => format!("/cmd/{operation}/+"),
             ChannelFilter::AnyCommandMetadata => "/cmd/+".to_string(),
@@ -598,6 +599,10 @@ pub enum Channel {
     AlarmMetadata {
         alarm_type: String,
     },
+    /// The lifecycle status of an alarm, as managed by the agent
+    AlarmStatus {
+        alarm_type: String,
+    },
     CommandMetadata {
         operation: OperationType,
     },
@@ -633,6 +638,9 @@ impl FromStr for Channel {
             ["a", alarm_type, "meta"] => Ok(Channel::AlarmMetadata {
                 alarm_type: alarm_type.to_string(),
             }),
+            ["a", alarm_type, "status"] => Ok(Channel::AlarmStatus {
+                alarm_type: alarm_type.to_string(),
+            }),
 
             ["cmd", operation] => Ok(Channel::CommandMetadata {
                 operation: operation.parse().unwrap(), // Infallible
@@ -664,6 +672,7 @@ impl Display for Channel {
 
             Channel::Alarm { alarm_type } => write!(f, "a/{alarm_type}"),
             Channel::AlarmMetadata { alarm_type } => write!(f, "a/{alarm_type}/meta"),
+            Channel::AlarmStatus { alarm_type } => write!(f, "a/{alarm_type}/status"),
 
             Channel::Command { operation, cmd_id } => write!(f, "cmd/{operation}/{cmd_id}"),
             Channel::CommandMetadata { operation } => write!(f, "cmd/{operation}"),
@@ -697,6 +706,8 @@ pub enum OperationType {
     FirmwareUpdate,
     Health,
     DeviceProfile,
+    CertRenew,
+    Command,
     Custom(String),
 }
 
@@ -739,6 +750,8 @@ impl<'a> From<&'a str> for OperationType {
             "config_update" => OperationType::ConfigUpdate,
             "firmware_update" => OperationType::FirmwareUpdate,
             "device_profile" => OperationType::DeviceProfile,
+            "cert_renew" => OperationType::CertRenew,
+            "command" => OperationType::Command,
             operation => OperationType::Custom(operation.to_string()),
         }
     }
@@ -756,6 +769,8 @@ impl Display for OperationType {
             OperationType::FirmwareUpdate => write!(f, "firmware_update"),
             OperationType::Health => write!(f, "health"),
             OperationType::DeviceProfile => write!(f, "device_profile"),
+            OperationType::CertRenew => write!(f, "cert_renew"),
+            OperationType::Command => write!(f, "command"),
             OperationType::Custom(operation) => write!(f, "{operation}"),
         }
     }
@@ -793,6 +808,7 @@ pub enum ChannelFilter {
     MeasurementMetadata,
     EventMetadata,
     AlarmMetadata,
+    AlarmStatus,
     AnyCommandMetadata,
     CommandMetadata(OperationType),
     Health,
@@ -817,6 +833,7 @@ impl From<&Channel> for ChannelFilter {
             } => ChannelFilter::MeasurementMetadata,
             Channel::EventMetadata { event_type: _ } => ChannelFilter::EventMetadata,
             Channel::AlarmMetadata { alarm_type: _ } => ChannelFilter::AlarmMetadata,
+            Channel::AlarmStatus { alarm_type: _ } => ChannelFilter::AlarmStatus,
             Channel::CommandMetadata { operation } => {
                 ChannelFilter::CommandMetadata(operation.clone())
             }
@@ -1052,6 +1069,15 @@ mod tests {
             ),
             mqtt_channel::Topic::new_unchecked("te/device/main///a/type/meta")
         );
+        assert_eq!(
+            mqtt_schema.topic_for(
+                &device,
+                &Channel::AlarmStatus {
+                    alarm_type: "type".to_string()
+                }
+            ),
+            mqtt_channel::Topic::new_unchecked("te/device/main///a/type/status")
+        );
         assert_eq!(
             mqtt_schema.topic_for(
                 &device,
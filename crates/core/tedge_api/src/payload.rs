@@ -0,0 +1,129 @@
+//! Encodings of the telemetry payloads published on `te/` topics.
+//!
+//! Measurements, events and alarms are published as JSON text by default.
+//! Constrained devices can publish them as [CBOR](https://www.rfc-editor.org/rfc/rfc8949) instead,
+//! using the same data model, i.e. a CBOR map with the same keys and values as the JSON object.
+//!
+//! A CBOR payload is told apart from a JSON payload by its content marker:
+//! it must start with the CBOR self-described tag (`0xd9d9f7`),
+//! a sequence of bytes that cannot start a UTF-8 text and hence a JSON payload.
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::borrow::Cow;
+
+/// The CBOR self-described tag (tag 55799), which marks a payload as CBOR
+pub const CBOR_SELF_DESCRIBED_TAG: [u8; 3] = [0xd9, 0xd9, 0xf7];
+
+/// The encoding of a telemetry payload
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum PayloadEncoding {
+    Json,
+    Cbor,
+}
+
+impl PayloadEncoding {
+    /// Detect the encoding of a payload from its content marker
+    pub fn of(payload: &[u8]) -> Self {
+        if payload.starts_with(&CBOR_SELF_DESCRIBED_TAG) {
+            PayloadEncoding::Cbor
+        } else {
+            PayloadEncoding::Json
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum PayloadError {
+    #[error("Invalid UTF-8 payload: {0}")]
+    NotUtf8(#[from] std::str::Utf8Error),
+
+    #[error("Invalid CBOR payload: {0}")]
+    InvalidCbor(#[from] ciborium::de::Error<std::io::Error>),
+
+    #[error("Invalid JSON payload: {0}")]
+    InvalidJson(#[from] serde_json::Error),
+
+    #[error("Cannot encode a CBOR payload: {0}")]
+    CborEncoding(#[from] ciborium::ser::Error<std::io::Error>),
+}
+
+/// Return the CBOR content of a payload marked as CBOR
+pub(crate) fn cbor_content(payload: &[u8]) -> &[u8] {
+    payload
+        .strip_prefix(&CBOR_SELF_DESCRIBED_TAG)
+        .unwrap_or(payload)
+}
+
+/// Deserialize a JSON or CBOR payload
+pub fn decode<T: DeserializeOwned>(payload: &[u8]) -> Result<T, PayloadError> {
+    match PayloadEncoding::of(payload) {
+        PayloadEncoding::Json => Ok(serde_json::from_slice(payload)?),
+        PayloadEncoding::Cbor => Ok(ciborium::from_reader(cbor_content(payload))?),
+    }
+}
+
+/// Return a payload as JSON text, translating CBOR payloads into JSON
+///
+/// JSON payloads are only checked to be UTF-8.
+pub fn to_json(payload: &[u8]) -> Result<Cow<'_, str>, PayloadError> {
+    match PayloadEncoding::of(payload) {
+        PayloadEncoding::Json => Ok(Cow::Borrowed(std::str::from_utf8(payload)?)),
+        PayloadEncoding::Cbor => {
+            let value: serde_json::Value = decode(payload)?;
+            Ok(Cow::Owned(serde_json::to_string(&value)?))
+        }
+    }
+}
+
+/// Encode a value as a CBOR payload, marked with the CBOR self-described tag
+pub fn encode_cbor<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, PayloadError> {
+    let mut payload = CBOR_SELF_DESCRIBED_TAG.to_vec();
+    ciborium::into_writer(value, &mut payload)?;
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn payload_encoding_is_detected_from_content_marker() {
+        assert_eq!(
+            PayloadEncoding::of(br#"{"temperature":21.5}"#),
+            PayloadEncoding::Json
+        );
+        assert_eq!(PayloadEncoding::of(b""), PayloadEncoding::Json);
+        assert_eq!(
+            PayloadEncoding::of(&encode_cbor(&json!({"temperature": 21.5})).unwrap()),
+            PayloadEncoding::Cbor
+        );
+    }
+
+    #[test]
+    fn cbor_payloads_are_translated_into_json() {
+        let payload = encode_cbor(&json!({"text": "door open", "time": 1714557600})).unwrap();
+
+        let json: serde_json::Value = serde_json::from_str(&to_json(&payload).unwrap()).unwrap();
+
+        assert_eq!(json, json!({"text": "door open", "time": 1714557600}));
+    }
+
+    #[test]
+    fn json_payloads_are_left_unchanged() {
+        let payload = r#"{"text":"door open"}"#;
+
+        assert_eq!(to_json(payload.as_bytes()).unwrap(), payload);
+    }
+
+    #[test]
+    fn invalid_cbor_payloads_are_rejected() {
+        let mut payload = CBOR_SELF_DESCRIBED_TAG.to_vec();
+        payload.extend_from_slice(&[0xbf, 0x61]);
+
+        assert!(matches!(
+            to_json(&payload),
+            Err(PayloadError::InvalidCbor(_))
+        ));
+    }
+}
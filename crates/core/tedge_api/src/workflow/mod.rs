@@ -314,6 +314,8 @@ impl OperationWorkflow {
             OperationType::Custom(_)
             | OperationType::Restart
             | OperationType::DeviceProfile
+            | OperationType::CertRenew
+            | OperationType::Command
             | OperationType::FirmwareUpdate => {
                 let meta_topic = schema.capability_topic_for(target, self.operation.clone());
                 let payload = "{}".to_string();
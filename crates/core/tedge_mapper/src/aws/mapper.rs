@@ -1,4 +1,6 @@
 use crate::core::component::TEdgeComponent;
+use crate::core::mapper::measurement_pipeline;
+use crate::core::mapper::spawn_metrics_server;
 use crate::core::mapper::start_basic_actors;
 use async_trait::async_trait;
 use aws_mapper_ext::converter::AwsConverter;
@@ -9,6 +11,7 @@ use tedge_actors::ConvertingActor;
 use tedge_actors::MessageSink;
 use tedge_actors::MessageSource;
 use tedge_actors::NoConfig;
+use tedge_api::alarm::HiddenAlarms;
 use tedge_api::mqtt_topics::EntityTopicId;
 use tedge_api::mqtt_topics::MqttSchema;
 use tedge_api::service_health_topic;
@@ -16,6 +19,7 @@ use tedge_config::models::TopicPrefix;
 use tedge_config::tedge_toml::ProfileName;
 use tedge_config::tedge_toml::TEdgeConfigReaderAws;
 use tedge_config::TEdgeConfig;
+use tedge_file_system_ext::FsWatchActorBuilder;
 use tedge_mqtt_bridge::use_key_and_cert;
 use tedge_mqtt_bridge::BridgeConfig;
 use tedge_mqtt_bridge::MqttBridgeActorBuilder;
@@ -30,13 +34,13 @@ impl TEdgeComponent for AwsMapper {
     async fn start(
         &self,
         tedge_config: TEdgeConfig,
-        _config_dir: &tedge_config::Path,
+        config_dir: &tedge_config::Path,
     ) -> Result<(), anyhow::Error> {
         let aws_config = tedge_config.aws.try_get(self.profile.as_deref())?;
         let prefix = &aws_config.bridge.topic_prefix;
         let aws_mapper_name = format!("tedge-mapper-{prefix}");
         let (mut runtime, mut mqtt_actor) =
-            start_basic_actors(&aws_mapper_name, &tedge_config).await?;
+            start_basic_actors(&aws_mapper_name, &tedge_config, Some(prefix)).await?;
 
         let mqtt_schema = MqttSchema::with_root(tedge_config.mqtt.topic_root.clone());
         if tedge_config.mqtt.bridge.built_in {
@@ -71,30 +75,52 @@ impl TEdgeComponent for AwsMapper {
         let aws_converter = AwsConverter::new(
             aws_config.mapper.timestamp,
             clock,
-            mqtt_schema,
+            mqtt_schema.clone(),
             aws_config.mapper.timestamp_format,
             prefix.clone(),
             aws_config.mapper.mqtt.max_payload_size.0,
         );
+        let mut fs_watch_actor = FsWatchActorBuilder::new();
+        let mut pipeline_actor = measurement_pipeline(
+            prefix.as_str(),
+            &tedge_config,
+            config_dir,
+            &mqtt_actor,
+            &mut fs_watch_actor,
+        )
+        .await?;
         let mut aws_converting_actor = ConvertingActor::builder("AwsConverter", aws_converter);
 
-        aws_converting_actor.connect_source(get_topic_filter(aws_config), &mut mqtt_actor);
+        aws_converting_actor.connect_source(
+            get_topic_filter(aws_config, &mqtt_schema),
+            &mut pipeline_actor,
+        );
         aws_converting_actor.connect_sink(NoConfig, &mqtt_actor);
+        pipeline_actor.connect_mqtt(&mut mqtt_actor);
 
         runtime.spawn(aws_converting_actor).await?;
+        runtime.spawn(pipeline_actor).await?;
+        runtime.spawn(fs_watch_actor).await?;
         runtime.spawn(mqtt_actor).await?;
+        spawn_metrics_server(
+            &mut runtime,
+            &tedge_config,
+            aws_config.mapper.metrics.port.or_none(),
+        )
+        .await?;
         runtime.run_to_completion().await?;
         Ok(())
     }
 }
 
-fn get_topic_filter(aws_config: &TEdgeConfigReaderAws) -> TopicFilter {
+fn get_topic_filter(aws_config: &TEdgeConfigReaderAws, mqtt_schema: &MqttSchema) -> TopicFilter {
     let mut topics = TopicFilter::empty();
     for topic in aws_config.topics.0.clone() {
         if topics.add(&topic).is_err() {
             warn!("The configured topic '{topic}' is invalid and ignored.");
         }
     }
+    topics.add_all(HiddenAlarms::status_topics(mqtt_schema));
     topics
 }
 
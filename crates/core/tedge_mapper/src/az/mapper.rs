@@ -1,4 +1,6 @@
 use crate::core::component::TEdgeComponent;
+use crate::core::mapper::measurement_pipeline;
+use crate::core::mapper::spawn_metrics_server;
 use crate::core::mapper::start_basic_actors;
 use async_trait::async_trait;
 use az_mapper_ext::converter::AzureConverter;
@@ -10,6 +12,7 @@ use tedge_actors::ConvertingActor;
 use tedge_actors::MessageSink;
 use tedge_actors::MessageSource;
 use tedge_actors::NoConfig;
+use tedge_api::alarm::HiddenAlarms;
 use tedge_api::mqtt_topics::EntityTopicId;
 use tedge_api::mqtt_topics::MqttSchema;
 use tedge_api::service_health_topic;
@@ -17,6 +20,7 @@ use tedge_config::models::TopicPrefix;
 use tedge_config::tedge_toml::ProfileName;
 use tedge_config::tedge_toml::TEdgeConfigReaderAz;
 use tedge_config::TEdgeConfig;
+use tedge_file_system_ext::FsWatchActorBuilder;
 use tedge_mqtt_bridge::use_key_and_cert;
 use tedge_mqtt_bridge::BridgeConfig;
 use tedge_mqtt_bridge::MqttBridgeActorBuilder;
@@ -31,13 +35,13 @@ impl TEdgeComponent for AzureMapper {
     async fn start(
         &self,
         tedge_config: TEdgeConfig,
-        _config_dir: &tedge_config::Path,
+        config_dir: &tedge_config::Path,
     ) -> Result<(), anyhow::Error> {
         let az_config = tedge_config.az.try_get(self.profile.as_deref())?;
         let prefix = &az_config.bridge.topic_prefix;
         let az_mapper_name = format!("tedge-mapper-{prefix}");
         let (mut runtime, mut mqtt_actor) =
-            start_basic_actors(&az_mapper_name, &tedge_config).await?;
+            start_basic_actors(&az_mapper_name, &tedge_config, Some(prefix)).await?;
         let mqtt_schema = MqttSchema::with_root(tedge_config.mqtt.topic_root.clone());
 
         if tedge_config.mqtt.bridge.built_in {
@@ -80,29 +84,51 @@ impl TEdgeComponent for AzureMapper {
         let az_converter = AzureConverter::new(
             az_config.mapper.timestamp,
             Box::new(WallClock),
-            mqtt_schema,
+            mqtt_schema.clone(),
             az_config.mapper.timestamp_format,
             prefix,
             az_config.mapper.mqtt.max_payload_size.0,
         );
+        let mut fs_watch_actor = FsWatchActorBuilder::new();
+        let mut pipeline_actor = measurement_pipeline(
+            prefix.as_str(),
+            &tedge_config,
+            config_dir,
+            &mqtt_actor,
+            &mut fs_watch_actor,
+        )
+        .await?;
         let mut az_converting_actor = ConvertingActor::builder("AzConverter", az_converter);
-        az_converting_actor.connect_source(get_topic_filter(az_config), &mut mqtt_actor);
+        az_converting_actor.connect_source(
+            get_topic_filter(az_config, &mqtt_schema),
+            &mut pipeline_actor,
+        );
         az_converting_actor.connect_sink(NoConfig, &mqtt_actor);
+        pipeline_actor.connect_mqtt(&mut mqtt_actor);
 
         runtime.spawn(az_converting_actor).await?;
+        runtime.spawn(pipeline_actor).await?;
+        runtime.spawn(fs_watch_actor).await?;
         runtime.spawn(mqtt_actor).await?;
+        spawn_metrics_server(
+            &mut runtime,
+            &tedge_config,
+            az_config.mapper.metrics.port.or_none(),
+        )
+        .await?;
         runtime.run_to_completion().await?;
         Ok(())
     }
 }
 
-fn get_topic_filter(az_config: &TEdgeConfigReaderAz) -> TopicFilter {
+fn get_topic_filter(az_config: &TEdgeConfigReaderAz, mqtt_schema: &MqttSchema) -> TopicFilter {
     let mut topics = TopicFilter::empty();
     for topic in az_config.topics.0.clone() {
         if topics.add(&topic).is_err() {
             warn!("The configured topic '{topic}' is invalid and ignored.");
         }
     }
+    topics.add_all(HiddenAlarms::status_topics(mqtt_schema));
     topics
 }
 
@@ -1,4 +1,6 @@
 use crate::core::component::TEdgeComponent;
+use crate::core::mapper::measurement_pipeline;
+use crate::core::mapper::spawn_metrics_server;
 use crate::core::mapper::start_basic_actors;
 use anyhow::Context;
 use async_trait::async_trait;
@@ -45,7 +47,7 @@ impl TEdgeComponent for CumulocityMapper {
         let prefix = &c8y_config.bridge.topic_prefix;
         let c8y_mapper_name = format!("tedge-mapper-{prefix}");
         let (mut runtime, mut mqtt_actor) =
-            start_basic_actors(&c8y_mapper_name, &tedge_config).await?;
+            start_basic_actors(&c8y_mapper_name, &tedge_config, Some(prefix)).await?;
 
         let c8y_mapper_config =
             C8yMapperConfig::from_tedge_config(cfg_dir, &tedge_config, c8y_profile)?;
@@ -261,10 +263,19 @@ impl TEdgeComponent for CumulocityMapper {
             c8y_profile,
         )?);
 
+        let mut pipeline_actor = measurement_pipeline(
+            prefix.as_str(),
+            &tedge_config,
+            cfg_dir,
+            &mqtt_actor,
+            &mut fs_watch_actor,
+        )
+        .await?;
+
         C8yMapperBuilder::init(&c8y_mapper_config).await?;
         let mut c8y_mapper_actor = C8yMapperBuilder::try_new(
             c8y_mapper_config,
-            &mut mqtt_actor,
+            &mut pipeline_actor,
             &mut http_actor,
             &mut timer_actor,
             &mut uploader_actor,
@@ -272,6 +283,7 @@ impl TEdgeComponent for CumulocityMapper {
             &mut fs_watch_actor,
             &mut service_monitor_actor,
         )?;
+        pipeline_actor.connect_mqtt(&mut mqtt_actor);
 
         let c8y_prefix = &c8y_config.bridge.topic_prefix;
         // Adaptor translating commands sent on te/device/main///cmd/+/+ into requests on tedge/commands/req/+/+
@@ -294,6 +306,7 @@ impl TEdgeComponent for CumulocityMapper {
         runtime.spawn(fs_watch_actor).await?;
         runtime.spawn(timer_actor).await?;
         runtime.spawn(c8y_mapper_actor).await?;
+        runtime.spawn(pipeline_actor).await?;
         runtime.spawn(service_monitor_actor).await?;
         runtime.spawn(uploader_actor).await?;
         runtime.spawn(downloader_actor).await?;
@@ -301,6 +314,12 @@ impl TEdgeComponent for CumulocityMapper {
         if let Some(availability_actor) = availability_actor {
             runtime.spawn(availability_actor).await?;
         }
+        spawn_metrics_server(
+            &mut runtime,
+            &tedge_config,
+            c8y_config.mapper.metrics.port.or_none(),
+        )
+        .await?;
         runtime.run_to_completion().await?;
 
         Ok(())
@@ -351,7 +370,7 @@ pub fn service_monitor_client_config(
     )?;
 
     let mqtt_config = tedge_config
-        .mqtt_config()?
+        .service_mqtt_config(c8y_mapper_name)?
         .with_session_name(format!("last_will_{prefix}_mapper"))
         .with_last_will_message(last_will_message);
     Ok(mqtt_config)
@@ -3,16 +3,17 @@ use crate::core::mapper::start_basic_actors;
 use async_trait::async_trait;
 use batcher::BatchingActorBuilder;
 use collectd_ext::actor::CollectdActorBuilder;
+use collectd_ext::config::CollectdConfig;
+use collectd_ext::types::TypesDb;
 use mqtt_channel::QoS;
-use mqtt_channel::Topic;
 use mqtt_channel::TopicFilter;
 use tedge_actors::MessageSink;
 use tedge_actors::NoConfig;
+use tedge_api::mqtt_topics::MqttSchema;
 use tedge_config::TEdgeConfig;
 
 const COLLECTD_MAPPER_NAME: &str = "tedge-mapper-collectd";
 const COLLECTD_INPUT_TOPICS: &str = "collectd/#";
-const COLLECTD_OUTPUT_TOPIC: &str = "te/device/main///m/";
 
 pub struct CollectdMapper;
 
@@ -20,10 +21,6 @@ impl CollectdMapper {
     fn input_topics() -> TopicFilter {
         TopicFilter::new_unchecked(COLLECTD_INPUT_TOPICS).with_qos(QoS::AtMostOnce)
     }
-
-    fn output_topic() -> Topic {
-        Topic::new_unchecked(COLLECTD_OUTPUT_TOPIC)
-    }
 }
 
 #[async_trait]
@@ -31,21 +28,23 @@ impl TEdgeComponent for CollectdMapper {
     async fn start(
         &self,
         tedge_config: TEdgeConfig,
-        _config_dir: &tedge_config::Path,
+        config_dir: &tedge_config::Path,
     ) -> Result<(), anyhow::Error> {
         let (mut runtime, mut mqtt_actor) =
-            start_basic_actors(COLLECTD_MAPPER_NAME, &tedge_config).await?;
+            start_basic_actors(COLLECTD_MAPPER_NAME, &tedge_config, None).await?;
 
+        let config = CollectdConfig::read(config_dir)?;
+        let types_db = TypesDb::read(&config.types_db);
+        let mqtt_schema = MqttSchema::with_root(tedge_config.mqtt.topic_root.clone());
         let input_topic = CollectdMapper::input_topics();
-        let output_topic = CollectdMapper::output_topic();
 
         let mut batching_actor = BatchingActorBuilder::default();
-        let mut collectd_actor = CollectdActorBuilder::new(input_topic);
+        let mut collectd_actor = CollectdActorBuilder::new(input_topic, types_db);
 
         collectd_actor.add_input(&mut mqtt_actor);
         batching_actor.connect_source(NoConfig, &mut collectd_actor);
         mqtt_actor.connect_mapped_source(NoConfig, &mut batching_actor, move |batch| {
-            collectd_ext::converter::batch_into_mqtt_messages(&output_topic, batch)
+            collectd_ext::converter::batch_into_mqtt_messages(&mqtt_schema, &config, batch)
         });
 
         runtime.spawn(collectd_actor).await?;
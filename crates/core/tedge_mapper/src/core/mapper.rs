@@ -1,19 +1,32 @@
+use anyhow::Context;
 #[cfg(test)]
 use std::result::Result::Ok;
 use tedge_actors::Runtime;
+use tedge_api::health::service_health_topic;
 use tedge_api::mqtt_topics::DeviceTopicId;
 use tedge_api::mqtt_topics::EntityTopicId;
 use tedge_api::mqtt_topics::MqttSchema;
 use tedge_api::mqtt_topics::Service;
 use tedge_api::mqtt_topics::ServiceTopicId;
+use tedge_config::models::TopicPrefix;
 use tedge_config::TEdgeConfig;
+use tedge_file_system_ext::FsWatchActorBuilder;
+use tedge_health_ext::DiskSpaceCheck;
 use tedge_health_ext::HealthMonitorBuilder;
+use tedge_health_ext::MqttConnectionCheck;
+use tedge_health_ext::ServiceHealthCheck;
+use tedge_metrics_ext::MetricsServerBuilder;
+use tedge_metrics_ext::MetricsServerConfig;
 use tedge_mqtt_ext::MqttActorBuilder;
+use tedge_pipeline_ext::MeasurementPipelineBuilder;
+use tedge_pipeline_ext::MeasurementPipelineConfig;
+use tedge_pipeline_ext::PipelineStages;
 use tedge_signal_ext::SignalActor;
 
 pub async fn start_basic_actors(
     mapper_name: &str,
     config: &TEdgeConfig,
+    bridge_prefix: Option<&TopicPrefix>,
 ) -> Result<(Runtime, MqttActorBuilder), anyhow::Error> {
     let mut runtime = Runtime::new();
 
@@ -27,12 +40,33 @@ pub async fn start_basic_actors(
         device_topic_id: DeviceTopicId::new(EntityTopicId::default_main_device()),
     };
     let mqtt_schema = MqttSchema::with_root(config.mqtt.topic_root.clone());
-    let health_actor = HealthMonitorBuilder::from_service_topic_id(
+    let mut health_actor = HealthMonitorBuilder::from_service_topic_id(
         service,
         &mut mqtt_actor,
         &mqtt_schema,
         &config.service,
     );
+    health_actor.add_check(
+        "mqtt",
+        MqttConnectionCheck::new(mqtt_actor.connection_status()),
+        &mut mqtt_actor,
+    );
+    health_actor.add_check(
+        "disk",
+        DiskSpaceCheck::new(config.data.path.clone()),
+        &mut mqtt_actor,
+    );
+    if let Some(prefix) = bridge_prefix {
+        let bridge_service = bridge_service_name(prefix, config);
+        let device_topic_id = config.mqtt.device_topic_id.parse::<EntityTopicId>()?;
+        let bridge_health_topic =
+            service_health_topic(&mqtt_schema, &device_topic_id, &bridge_service);
+        health_actor.add_check(
+            "bridge",
+            ServiceHealthCheck::new(bridge_service, bridge_health_topic, &mqtt_schema),
+            &mut mqtt_actor,
+        );
+    }
 
     // Shutdown on SIGINT
     let signal_actor = SignalActor::builder(&runtime.get_handle());
@@ -42,11 +76,64 @@ pub async fn start_basic_actors(
     Ok((runtime, mqtt_actor))
 }
 
+/// The name of the service bridging the local MQTT broker to the cloud
+pub fn bridge_service_name(prefix: &TopicPrefix, config: &TEdgeConfig) -> String {
+    if config.mqtt.bridge.built_in {
+        format!("tedge-mapper-bridge-{prefix}")
+    } else {
+        format!("mosquitto-{prefix}-bridge")
+    }
+}
+
+/// Serve the mapper metrics on the given port, if any
+pub async fn spawn_metrics_server(
+    runtime: &mut Runtime,
+    config: &TEdgeConfig,
+    port: Option<&u16>,
+) -> Result<(), anyhow::Error> {
+    if let Some(port) = port {
+        let metrics_config = MetricsServerConfig::from_tedge_config(config, *port);
+        let metrics_server = MetricsServerBuilder::try_bind(metrics_config).await?;
+        runtime.spawn(metrics_server).await?;
+    }
+    Ok(())
+}
+
+/// Create the measurement pipeline to be interposed between the MQTT actor and the converter of a mapper
+///
+/// The pipeline is defined in `{config_dir}/mappers/{mapper}/pipeline.toml`.
+/// As all the messages received by the converter go through the pipeline,
+/// the mapper is not started if the pipeline directory cannot be watched or the definition is invalid.
+pub async fn measurement_pipeline(
+    mapper: &str,
+    config: &TEdgeConfig,
+    config_dir: &tedge_config::Path,
+    mqtt_actor: &MqttActorBuilder,
+    fs_watch_actor: &mut FsWatchActorBuilder,
+) -> Result<MeasurementPipelineBuilder, anyhow::Error> {
+    let mqtt_schema = MqttSchema::with_root(config.mqtt.topic_root.clone());
+    let pipeline_config = MeasurementPipelineConfig::new(mqtt_schema, config_dir, mapper);
+    MeasurementPipelineBuilder::init(&pipeline_config)
+        .await
+        .with_context(|| {
+            format!(
+                "Fail to create the measurement pipeline directory {}",
+                pipeline_config.config_dir
+            )
+        })?;
+    PipelineStages::read(&pipeline_config.config_file)?;
+    Ok(MeasurementPipelineBuilder::new(
+        pipeline_config,
+        mqtt_actor,
+        fs_watch_actor,
+    ))
+}
+
 async fn get_mqtt_actor(
     session_name: &str,
     tedge_config: &TEdgeConfig,
 ) -> Result<MqttActorBuilder, anyhow::Error> {
-    let mqtt_config = tedge_config.mqtt_config()?;
+    let mqtt_config = tedge_config.service_mqtt_config(session_name)?;
 
     Ok(MqttActorBuilder::new(
         mqtt_config.with_session_name(session_name),
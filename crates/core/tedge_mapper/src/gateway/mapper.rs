@@ -0,0 +1,34 @@
+use crate::core::component::TEdgeComponent;
+use crate::core::mapper::start_basic_actors;
+use async_trait::async_trait;
+use tedge_config::TEdgeConfig;
+use tedge_gateway_ext::actor::GatewayActorBuilder;
+use tedge_gateway_ext::config::GatewayConfig;
+use tracing::warn;
+
+const GATEWAY_MAPPER_NAME: &str = "tedge-mapper-gateway";
+
+pub struct GatewayMapper;
+
+#[async_trait]
+impl TEdgeComponent for GatewayMapper {
+    async fn start(
+        &self,
+        tedge_config: TEdgeConfig,
+        _config_dir: &tedge_config::Path,
+    ) -> Result<(), anyhow::Error> {
+        let (mut runtime, mut mqtt_actor) =
+            start_basic_actors(GATEWAY_MAPPER_NAME, &tedge_config, None).await?;
+
+        let config = GatewayConfig::from_tedge_config(&tedge_config);
+        if config.mqttsn_bind.is_none() && config.coap_bind.is_none() {
+            warn!("Neither gateway.mqttsn.bind.port nor gateway.coap.bind.port is set: no client can connect");
+        }
+        let gateway_actor = GatewayActorBuilder::try_new(config, &mut mqtt_actor).await?;
+
+        runtime.spawn(gateway_actor).await?;
+        runtime.spawn(mqtt_actor).await?;
+        runtime.run_to_completion().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,61 @@
+use crate::core::component::TEdgeComponent;
+use crate::core::mapper::start_basic_actors;
+use async_trait::async_trait;
+use batcher::BatchingActorBuilder;
+use influx_ext::actor::LineProtocolActorBuilder;
+use influx_ext::config::InfluxConfig;
+use influx_ext::http::HttpServerBuilder;
+use influx_ext::udp::UdpServerBuilder;
+use std::net::SocketAddr;
+use tedge_actors::MessageSink;
+use tedge_actors::NoConfig;
+use tedge_config::TEdgeConfig;
+
+const INFLUX_MAPPER_NAME: &str = "tedge-mapper-influx";
+
+pub struct InfluxMapper;
+
+#[async_trait]
+impl TEdgeComponent for InfluxMapper {
+    async fn start(
+        &self,
+        tedge_config: TEdgeConfig,
+        _config_dir: &tedge_config::Path,
+    ) -> Result<(), anyhow::Error> {
+        let (mut runtime, mut mqtt_actor) =
+            start_basic_actors(INFLUX_MAPPER_NAME, &tedge_config, None).await?;
+
+        let config = InfluxConfig::from_tedge_config(&tedge_config);
+        let mqtt_schema = config.mqtt_schema.clone();
+        let precision = config.precision;
+
+        let mut batching_actor = BatchingActorBuilder::default();
+        let mut line_protocol_actor = LineProtocolActorBuilder::new(config);
+
+        line_protocol_actor.add_input(&mut mqtt_actor);
+        batching_actor.connect_source(NoConfig, &mut line_protocol_actor);
+        mqtt_actor.connect_mapped_source(NoConfig, &mut batching_actor, move |batch| {
+            influx_ext::converter::batch_into_mqtt_messages(&mqtt_schema, batch)
+        });
+
+        if let Some(port) = tedge_config.influx.udp.bind.port.or_none() {
+            let bind_addr = SocketAddr::from((tedge_config.influx.udp.bind.address, *port));
+            let udp_server =
+                UdpServerBuilder::try_bind(bind_addr, precision, &line_protocol_actor).await?;
+            runtime.spawn(udp_server).await?;
+        }
+
+        if let Some(port) = tedge_config.influx.http.bind.port.or_none() {
+            let bind_addr = SocketAddr::from((tedge_config.influx.http.bind.address, *port));
+            let http_server =
+                HttpServerBuilder::try_bind(bind_addr, precision, &line_protocol_actor).await?;
+            runtime.spawn(http_server).await?;
+        }
+
+        runtime.spawn(line_protocol_actor).await?;
+        runtime.spawn(batching_actor).await?;
+        runtime.spawn(mqtt_actor).await?;
+        runtime.run_to_completion().await?;
+        Ok(())
+    }
+}
@@ -5,6 +5,10 @@ use crate::az::mapper::AzureMapper;
 use crate::c8y::mapper::CumulocityMapper;
 use crate::collectd::mapper::CollectdMapper;
 use crate::core::component::TEdgeComponent;
+use crate::gateway::mapper::GatewayMapper;
+use crate::influx::mapper::InfluxMapper;
+use crate::modbus::mapper::ModbusMapper;
+use crate::opcua::mapper::OpcUaMapper;
 use anyhow::Context;
 use clap::Parser;
 use flockfile::check_another_instance_is_not_running;
@@ -18,6 +22,10 @@ mod az;
 mod c8y;
 mod collectd;
 mod core;
+mod gateway;
+mod influx;
+mod modbus;
+mod opcua;
 
 /// Set the cloud profile either from the CLI argument or env variable,
 /// then set the environment variable so child processes automatically
@@ -47,6 +55,10 @@ fn lookup_component(component_name: MapperName) -> Box<dyn TEdgeComponent> {
             profile: read_and_set_var!(profile, "TEDGE_CLOUD_PROFILE"),
         }),
         MapperName::Collectd => Box::new(CollectdMapper),
+        MapperName::Gateway => Box::new(GatewayMapper),
+        MapperName::Influx => Box::new(InfluxMapper),
+        MapperName::Modbus => Box::new(ModbusMapper),
+        MapperName::Opcua => Box::new(OpcUaMapper),
         MapperName::C8y { profile } => Box::new(CumulocityMapper {
             profile: read_and_set_var!(profile, "TEDGE_CLOUD_PROFILE"),
         }),
@@ -96,6 +108,10 @@ pub enum MapperName {
         profile: Option<ProfileName>,
     },
     Collectd,
+    Gateway,
+    Influx,
+    Modbus,
+    Opcua,
 }
 
 impl fmt::Display for MapperName {
@@ -114,6 +130,10 @@ impl fmt::Display for MapperName {
                 profile: Some(profile),
             } => write!(f, "tedge-mapper-c8y@{profile}"),
             MapperName::Collectd => write!(f, "tedge-mapper-collectd"),
+            MapperName::Gateway => write!(f, "tedge-mapper-gateway"),
+            MapperName::Influx => write!(f, "tedge-mapper-influx"),
+            MapperName::Modbus => write!(f, "tedge-mapper-modbus"),
+            MapperName::Opcua => write!(f, "tedge-mapper-opcua"),
         }
     }
 }
@@ -0,0 +1,32 @@
+use crate::core::component::TEdgeComponent;
+use crate::core::mapper::start_basic_actors;
+use async_trait::async_trait;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_config::TEdgeConfig;
+use tedge_modbus_ext::actor::ModbusActorBuilder;
+use tedge_modbus_ext::config::ModbusConfig;
+
+const MODBUS_MAPPER_NAME: &str = "tedge-mapper-modbus";
+
+pub struct ModbusMapper;
+
+#[async_trait]
+impl TEdgeComponent for ModbusMapper {
+    async fn start(
+        &self,
+        tedge_config: TEdgeConfig,
+        config_dir: &tedge_config::Path,
+    ) -> Result<(), anyhow::Error> {
+        let (mut runtime, mut mqtt_actor) =
+            start_basic_actors(MODBUS_MAPPER_NAME, &tedge_config, None).await?;
+
+        let config = ModbusConfig::read(config_dir)?;
+        let mqtt_schema = MqttSchema::with_root(tedge_config.mqtt.topic_root.clone());
+        let modbus_actor = ModbusActorBuilder::new(config, mqtt_schema, &mut mqtt_actor);
+
+        runtime.spawn(modbus_actor).await?;
+        runtime.spawn(mqtt_actor).await?;
+        runtime.run_to_completion().await?;
+        Ok(())
+    }
+}
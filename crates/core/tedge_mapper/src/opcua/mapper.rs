@@ -0,0 +1,37 @@
+use crate::core::component::TEdgeComponent;
+use crate::core::mapper::start_basic_actors;
+use async_trait::async_trait;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_config::TEdgeConfig;
+use tedge_file_system_ext::FsWatchActorBuilder;
+use tedge_opcua_ext::actor::OpcUaActorBuilder;
+use tedge_opcua_ext::config::OpcUaConfig;
+
+const OPCUA_MAPPER_NAME: &str = "tedge-mapper-opcua";
+
+pub struct OpcUaMapper;
+
+#[async_trait]
+impl TEdgeComponent for OpcUaMapper {
+    async fn start(
+        &self,
+        tedge_config: TEdgeConfig,
+        config_dir: &tedge_config::Path,
+    ) -> Result<(), anyhow::Error> {
+        let (mut runtime, mut mqtt_actor) =
+            start_basic_actors(OPCUA_MAPPER_NAME, &tedge_config, None).await?;
+
+        let mqtt_schema = MqttSchema::with_root(tedge_config.mqtt.topic_root.clone());
+        let config = OpcUaConfig::new(mqtt_schema, config_dir);
+        OpcUaActorBuilder::init(&config).await?;
+
+        let mut fs_watch_actor = FsWatchActorBuilder::new();
+        let opcua_actor = OpcUaActorBuilder::new(config, &mut mqtt_actor, &mut fs_watch_actor);
+
+        runtime.spawn(opcua_actor).await?;
+        runtime.spawn(fs_watch_actor).await?;
+        runtime.spawn(mqtt_actor).await?;
+        runtime.run_to_completion().await?;
+        Ok(())
+    }
+}
@@ -1,6 +1,8 @@
 use mqtt_channel::MqttError;
+use std::process::ExitStatus;
 use tedge_config::CertificateError;
 use tedge_config::ConfigSettingError;
+use tedge_config::SystemTomlError;
 use tedge_config::TEdgeConfigError;
 use time::error::Parse;
 
@@ -12,6 +14,17 @@ pub enum WatchdogError {
     #[error("Fail to run `{cmd}`: {from}")]
     CommandExecError { cmd: String, from: std::io::Error },
 
+    #[error("`{cmd}` failed with {status}")]
+    CommandFailed { cmd: String, status: ExitStatus },
+
+    #[error(
+        "The command `{cmd}` of system.toml is missing a '{{}}' placeholder for the service name"
+    )]
+    MissingServicePlaceholder { cmd: String },
+
+    #[error(transparent)]
+    FromSystemTomlError(#[from] SystemTomlError),
+
     #[error(transparent)]
     FromTedgeConfigError(#[from] TEdgeConfigError),
 
@@ -30,6 +43,7 @@ pub enum WatchdogError {
     #[error(transparent)]
     ParseSystemdFile(#[from] std::io::Error),
 
+    #[cfg(target_os = "linux")]
     #[error("Did not find the WatchdogSec in {file}")]
     NoWatchdogSec { file: String },
 
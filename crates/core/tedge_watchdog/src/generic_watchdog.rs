@@ -0,0 +1,574 @@
+//! Supervision of the thin-edge services on devices not using systemd
+//!
+//! Health check requests are periodically sent to the monitored services.
+//! A service that doesn't respond within `watchdog.timeout` is restarted,
+//! using the `restart` command of the `init` section of `system.toml`.
+//!
+//! The restarts are bounded by a budget of `watchdog.max_restarts` per `watchdog.restart_window`.
+//! Once the budget is exhausted, the service is no more restarted and an alarm is raised,
+//! this alarm being cleared as soon as the service responds again.
+//!
+//! Only the services seen up since the watchdog started are supervised,
+//! so the services which are not installed or which have been stopped are left untouched.
+//! A service reported down is no more supervised until seen up again,
+//! and retained health statuses published before the watchdog started are ignored.
+use anyhow::Context;
+use futures::SinkExt;
+use futures::StreamExt;
+use mqtt_channel::MqttMessage;
+use mqtt_channel::QoS;
+use mqtt_channel::Topic;
+use mqtt_channel::TopicFilter;
+use serde_json::json;
+use std::collections::VecDeque;
+use std::process::Stdio;
+use std::time::Duration;
+use std::time::Instant;
+use tedge_api::health::ServiceHealthTopic;
+use tedge_api::health::Status;
+use tedge_api::mqtt_topics::Channel;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_api::mqtt_topics::OperationType;
+use tedge_config::Path;
+use tedge_config::SystemConfig;
+use tedge_config::TEdgeConfig;
+use time::OffsetDateTime;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+use crate::error::WatchdogError;
+use crate::health_status::HealthStatusExt;
+
+const SERVICE_NAME: &str = "tedge-watchdog";
+
+/// The alarm raised when a service is still unresponsive after exhausting its restart budget
+const UNRESPONSIVE_ALARM: &str = "watchdog_unresponsive";
+
+/// The event published each time a service is restarted
+const RESTART_EVENT: &str = "watchdog_restart";
+
+pub async fn start_watchdog(
+    tedge_config: &TEdgeConfig,
+    tedge_config_dir: &Path,
+) -> Result<(), anyhow::Error> {
+    let init_config = SystemConfig::try_new(tedge_config_dir)?.init;
+    let watchdog_config = &tedge_config.watchdog;
+    let check_interval = watchdog_config.check_interval.duration();
+    let timeout = watchdog_config.timeout.duration();
+    let budget = RestartBudget::new(
+        watchdog_config.max_restarts as usize,
+        watchdog_config.restart_window.duration(),
+    );
+
+    let mqtt_topic_root = &tedge_config.mqtt.topic_root;
+    let mqtt_schema = MqttSchema::with_root(mqtt_topic_root.clone());
+    let mqtt_device_topic_id: EntityTopicId = tedge_config
+        .mqtt
+        .device_topic_id
+        .parse()
+        .context("Can't parse as device topic id")?;
+
+    let started_at = OffsetDateTime::now_utc();
+    let mut services = Vec::new();
+    for name in watchdog_config.services.0.iter() {
+        let service_topic_id = mqtt_device_topic_id
+            .default_service_for_device(name)
+            .context("Services not in default scheme unsupported")?;
+        let restart_command = service_command(&init_config.restart, name)?;
+        services.push(MonitoredService::new(
+            name,
+            &service_topic_id,
+            &mqtt_schema,
+            restart_command,
+            budget.clone(),
+            started_at,
+        ));
+    }
+    if services.is_empty() {
+        warn!("tedge generic watchdog not started because no services to monitor");
+        return Ok(());
+    }
+
+    let service_topic_id = mqtt_device_topic_id
+        .default_service_for_device(SERVICE_NAME)
+        .unwrap();
+    let service_health_topic = ServiceHealthTopic::from_new_topic(
+        &service_topic_id.into(),
+        &mqtt_schema,
+        tedge_config.service.timestamp_format,
+    );
+    let _service_health_topic = service_health_topic.clone();
+
+    let subscriptions: TopicFilter = services
+        .iter()
+        .map(|service| service.health_topic.filter())
+        .collect();
+    let mqtt_session_name = format!("{SERVICE_NAME}#{mqtt_topic_root}/{mqtt_device_topic_id}");
+    let mqtt_config = tedge_config
+        .service_mqtt_config(SERVICE_NAME)?
+        .with_session_name(mqtt_session_name)
+        .with_subscriptions(subscriptions)
+        .with_initial_message(move || _service_health_topic.up_message())
+        .with_last_will_message(service_health_topic.down_message());
+
+    let client = mqtt_channel::Connection::new(&mqtt_config).await?;
+    let mut received = client.received;
+    let mut publisher = client.published;
+
+    info!(
+        "Starting generic watchdog for {} using {}",
+        watchdog_config.services.0.join(", "),
+        init_config.name
+    );
+    publisher
+        .send(service_health_topic.up_message())
+        .await
+        .context("Could not send initial health status message")?;
+
+    loop {
+        let request_time = OffsetDateTime::now_utc();
+        let start = tokio::time::Instant::now();
+        for service in services.iter_mut() {
+            service.responded = false;
+            let request = MqttMessage::new(&service.request_topic, "");
+            publisher.send(request).await?;
+        }
+
+        let deadline = start + timeout;
+        while services.iter().any(|service| !service.responded) {
+            match tokio::time::timeout_at(deadline, received.next()).await {
+                Ok(Some(message)) => {
+                    if let Some(service) = services
+                        .iter_mut()
+                        .find(|service| service.health_topic == message.topic)
+                    {
+                        service.update(&message, request_time);
+                    }
+                }
+                Ok(None) => return Err(WatchdogError::ChannelClosed.into()),
+                Err(_) => break,
+            }
+        }
+
+        for service in services.iter_mut() {
+            let messages = match service.check(Instant::now()) {
+                Verdict::Alive => vec![],
+                Verdict::Recovered => {
+                    info!("{} is responding again", service.name);
+                    vec![service.clear_alarm()]
+                }
+                Verdict::Restart => {
+                    warn!(
+                        "No health check response received from {} in time, restarting it",
+                        service.name
+                    );
+                    match service.restart().await {
+                        Ok(()) => vec![service.restart_event(timeout)],
+                        Err(err) => {
+                            error!("Failed to restart {}: {err}", service.name);
+                            vec![]
+                        }
+                    }
+                }
+                Verdict::GiveUp => {
+                    error!(
+                        "{} is still unresponsive after {} restarts, giving up",
+                        service.name, service.restarts.max_restarts
+                    );
+                    vec![service.raise_alarm()]
+                }
+                Verdict::Unresponsive => vec![],
+            };
+            for message in messages {
+                publisher.send(message).await?;
+            }
+        }
+
+        tokio::time::sleep_until(start + check_interval).await;
+    }
+}
+
+/// A service supervised by the generic watchdog
+struct MonitoredService {
+    name: String,
+    request_topic: Topic,
+    health_topic: Topic,
+    alarm_topic: Topic,
+    event_topic: Topic,
+    restart_command: Vec<String>,
+    restarts: RestartBudget,
+
+    /// When the watchdog started, older health statuses being ignored
+    started_at: OffsetDateTime,
+
+    /// Set when the service has been seen up since the watchdog started and not reported down since
+    seen_up: bool,
+
+    /// Set when the service responded to the latest health check request
+    responded: bool,
+
+    /// Set when the unresponsive alarm might be raised for this service
+    ///
+    /// This is initially set, so an alarm left over by a previous run of the watchdog
+    /// is cleared as soon as the service responds.
+    alarm_raised: bool,
+}
+
+/// What to do with a service once the health check responses have been collected
+#[derive(Debug, Eq, PartialEq)]
+enum Verdict {
+    Alive,
+    Recovered,
+    Restart,
+    GiveUp,
+    Unresponsive,
+}
+
+impl MonitoredService {
+    fn new(
+        name: &str,
+        service: &EntityTopicId,
+        mqtt_schema: &MqttSchema,
+        restart_command: Vec<String>,
+        restarts: RestartBudget,
+        started_at: OffsetDateTime,
+    ) -> Self {
+        MonitoredService {
+            name: name.to_string(),
+            request_topic: mqtt_schema.topic_for(
+                service,
+                &Channel::Command {
+                    operation: OperationType::Health,
+                    cmd_id: "check".to_string(),
+                },
+            ),
+            health_topic: mqtt_schema.topic_for(service, &Channel::Health),
+            alarm_topic: mqtt_schema.topic_for(
+                service,
+                &Channel::Alarm {
+                    alarm_type: UNRESPONSIVE_ALARM.to_string(),
+                },
+            ),
+            event_topic: mqtt_schema.topic_for(
+                service,
+                &Channel::Event {
+                    event_type: RESTART_EVENT.to_string(),
+                },
+            ),
+            restart_command,
+            restarts,
+            started_at,
+            seen_up: false,
+            responded: false,
+            alarm_raised: true,
+        }
+    }
+
+    /// Update the state of the service from a health status message
+    fn update(&mut self, message: &MqttMessage, request_time: OffsetDateTime) {
+        let Ok(health_status) = message
+            .payload_str()
+            .map_err(|_| ())
+            .and_then(|payload| serde_json::from_str::<HealthStatusExt>(payload).map_err(|_| ()))
+        else {
+            return;
+        };
+        if health_status.status == Some(Status::Down) {
+            // The service has been stopped on purpose or has crashed, leaving its last will:
+            // it is no more supervised until seen up again
+            self.seen_up = false;
+            return;
+        }
+
+        // Compared with a 1s precision, as unix timestamps might be given without sub-second precision
+        let Some(time) = health_status.timestamp() else {
+            return;
+        };
+        if time.unix_timestamp() < self.started_at.unix_timestamp() {
+            // A retained status doesn't tell if the service is still installed and running
+            return;
+        }
+
+        self.seen_up = true;
+        if time.unix_timestamp() >= request_time.unix_timestamp() {
+            self.responded = true;
+        }
+    }
+
+    fn check(&mut self, now: Instant) -> Verdict {
+        if self.responded {
+            if self.alarm_raised {
+                self.alarm_raised = false;
+                return Verdict::Recovered;
+            }
+            return Verdict::Alive;
+        }
+        if !self.seen_up {
+            return Verdict::Unresponsive;
+        }
+        if self.restarts.try_restart(now) {
+            return Verdict::Restart;
+        }
+        if self.alarm_raised {
+            return Verdict::Unresponsive;
+        }
+        self.alarm_raised = true;
+        Verdict::GiveUp
+    }
+
+    async fn restart(&self) -> Result<(), WatchdogError> {
+        let cmd = self.restart_command.join(" ");
+        let status = tokio::process::Command::new(&self.restart_command[0])
+            .args(&self.restart_command[1..])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .await
+            .map_err(|err| WatchdogError::CommandExecError {
+                cmd: cmd.clone(),
+                from: err,
+            })?;
+        if !status.success() {
+            return Err(WatchdogError::CommandFailed { cmd, status });
+        }
+        Ok(())
+    }
+
+    fn restart_event(&self, timeout: Duration) -> MqttMessage {
+        let text = format!(
+            "{} restarted by {SERVICE_NAME}: no health check response within {}s",
+            self.name,
+            timeout.as_secs()
+        );
+        MqttMessage::new(&self.event_topic, json!({ "text": text }).to_string())
+    }
+
+    fn raise_alarm(&self) -> MqttMessage {
+        let text = format!(
+            "{} is unresponsive and has been restarted {} times within {}s",
+            self.name,
+            self.restarts.max_restarts,
+            self.restarts.window.as_secs()
+        );
+        let payload = json!({ "text": text, "severity": "critical" }).to_string();
+        MqttMessage::new(&self.alarm_topic, payload)
+            .with_retain()
+            .with_qos(QoS::AtLeastOnce)
+    }
+
+    fn clear_alarm(&self) -> MqttMessage {
+        MqttMessage::new(&self.alarm_topic, "")
+            .with_retain()
+            .with_qos(QoS::AtLeastOnce)
+    }
+}
+
+/// Bound the number of restarts of a service within a time window
+#[derive(Clone, Debug)]
+struct RestartBudget {
+    max_restarts: usize,
+    window: Duration,
+    restarts: VecDeque<Instant>,
+}
+
+impl RestartBudget {
+    fn new(max_restarts: usize, window: Duration) -> Self {
+        RestartBudget {
+            max_restarts,
+            window,
+            restarts: VecDeque::new(),
+        }
+    }
+
+    /// Record a restart at the given time, unless the budget is exhausted
+    fn try_restart(&mut self, now: Instant) -> bool {
+        while let Some(restart) = self.restarts.front() {
+            if now.duration_since(*restart) < self.window {
+                break;
+            }
+            self.restarts.pop_front();
+        }
+        if self.restarts.len() >= self.max_restarts {
+            return false;
+        }
+        self.restarts.push_back(now);
+        true
+    }
+}
+
+/// Build the command for a service, replacing the `{}` placeholder by the service name
+fn service_command(command: &[String], service_name: &str) -> Result<Vec<String>, WatchdogError> {
+    if !command.iter().any(|arg| arg == "{}") {
+        return Err(WatchdogError::MissingServicePlaceholder {
+            cmd: command.join(" "),
+        });
+    }
+    Ok(command
+        .iter()
+        .map(|arg| {
+            if arg == "{}" {
+                service_name.to_string()
+            } else {
+                arg.to_string()
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_service(max_restarts: usize) -> MonitoredService {
+        let mqtt_schema = MqttSchema::new();
+        let service = EntityTopicId::default_main_service("tedge-agent").unwrap();
+        MonitoredService::new(
+            "tedge-agent",
+            &service,
+            &mqtt_schema,
+            vec!["rc-service".into(), "tedge-agent".into(), "restart".into()],
+            RestartBudget::new(max_restarts, Duration::from_secs(3600)),
+            OffsetDateTime::now_utc() - Duration::from_secs(600),
+        )
+    }
+
+    fn health_message(
+        service: &MonitoredService,
+        status: &str,
+        time: OffsetDateTime,
+    ) -> MqttMessage {
+        let payload = json!({
+            "status": status,
+            "pid": 1234,
+            "time": time.unix_timestamp(),
+        });
+        MqttMessage::new(&service.health_topic, payload.to_string())
+    }
+
+    #[test]
+    fn restart_command_is_given_the_service_name() {
+        let command = vec![
+            "rc-service".to_string(),
+            "{}".to_string(),
+            "restart".to_string(),
+        ];
+        assert_eq!(
+            service_command(&command, "tedge-agent").unwrap(),
+            vec!["rc-service", "tedge-agent", "restart"]
+        );
+
+        let command = vec!["reboot".to_string()];
+        assert!(matches!(
+            service_command(&command, "tedge-agent"),
+            Err(WatchdogError::MissingServicePlaceholder { .. })
+        ));
+    }
+
+    #[test]
+    fn restarts_are_bounded_within_the_window() {
+        let mut budget = RestartBudget::new(2, Duration::from_secs(60));
+        let start = Instant::now();
+        assert!(budget.try_restart(start));
+        assert!(budget.try_restart(start + Duration::from_secs(10)));
+        assert!(!budget.try_restart(start + Duration::from_secs(20)));
+
+        // Once the first restart is out of the window, a new restart is allowed
+        assert!(budget.try_restart(start + Duration::from_secs(61)));
+        assert!(!budget.try_restart(start + Duration::from_secs(65)));
+    }
+
+    #[test]
+    fn services_never_seen_up_are_not_restarted() {
+        let mut service = test_service(1);
+        let request_time = OffsetDateTime::now_utc();
+
+        assert_eq!(service.check(Instant::now()), Verdict::Unresponsive);
+
+        let message = health_message(&service, "down", request_time);
+        service.update(&message, request_time);
+        assert_eq!(service.check(Instant::now()), Verdict::Unresponsive);
+    }
+
+    #[test]
+    fn retained_statuses_published_before_the_watchdog_started_are_ignored() {
+        let mut service = test_service(1);
+        let request_time = OffsetDateTime::now_utc();
+
+        let retained = health_message(&service, "up", service.started_at - Duration::from_secs(60));
+        service.update(&retained, request_time);
+        assert_eq!(service.check(Instant::now()), Verdict::Unresponsive);
+    }
+
+    #[test]
+    fn services_reported_down_are_no_more_restarted() {
+        let mut service = test_service(1);
+        let now = Instant::now();
+        let request_time = OffsetDateTime::now_utc();
+
+        let up = health_message(&service, "up", request_time);
+        service.update(&up, request_time);
+        assert_eq!(service.check(now), Verdict::Recovered);
+
+        // The service is stopped, leaving a down status
+        service.responded = false;
+        let down = health_message(&service, "down", request_time);
+        service.update(&down, request_time);
+        assert_eq!(service.check(now), Verdict::Unresponsive);
+        assert_eq!(service.check(now), Verdict::Unresponsive);
+
+        // Until started again
+        service.update(&up, request_time);
+        assert_eq!(service.check(now), Verdict::Alive);
+    }
+
+    #[test]
+    fn unresponsive_services_are_restarted_then_alarmed() {
+        let mut service = test_service(1);
+        let now = Instant::now();
+        let request_time = OffsetDateTime::now_utc();
+
+        // A fresh response clears any alarm left over by a previous run
+        let message = health_message(&service, "up", request_time);
+        service.update(&message, request_time);
+        assert_eq!(service.check(now), Verdict::Recovered);
+
+        // A stale response proves the service has been up, but not that it is still responsive
+        service.responded = false;
+        let stale_message = health_message(&service, "up", request_time - Duration::from_secs(60));
+        service.update(&stale_message, request_time);
+        assert_eq!(service.check(now), Verdict::Restart);
+
+        // The restart budget is exhausted
+        assert_eq!(service.check(now), Verdict::GiveUp);
+        assert_eq!(service.check(now), Verdict::Unresponsive);
+
+        // The alarm is cleared once the service responds again
+        service.update(&message, request_time);
+        assert_eq!(service.check(now), Verdict::Recovered);
+        assert_eq!(service.check(now), Verdict::Alive);
+    }
+
+    #[test]
+    fn unresponsive_alarm_is_raised_on_the_service() {
+        let service = test_service(3);
+        let alarm = service.raise_alarm();
+        assert_eq!(
+            alarm.topic.name,
+            "te/device/main/service/tedge-agent/a/watchdog_unresponsive"
+        );
+        assert!(alarm.retain);
+        let payload: serde_json::Value =
+            serde_json::from_str(alarm.payload_str().unwrap()).unwrap();
+        assert_eq!(
+            payload,
+            json!({
+                "text": "tedge-agent is unresponsive and has been restarted 3 times within 3600s",
+                "severity": "critical",
+            })
+        );
+        assert_eq!(service.clear_alarm().payload_str().unwrap(), "");
+    }
+}
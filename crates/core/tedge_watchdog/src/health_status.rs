@@ -0,0 +1,72 @@
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use std::collections::BTreeMap;
+use tedge_api::health::HealthCheck;
+use tedge_api::health::HealthStatus;
+use tedge_api::health::Status;
+use tedge_utils::timestamp::IsoOrUnix;
+use time::OffsetDateTime;
+
+/// A subset of fields of health status payload required by the watchdog.
+///
+/// https://thin-edge.github.io/thin-edge.io/operate/troubleshooting/monitoring-service-health/
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HealthStatusExt {
+    /// Used for tracking service restarts
+    pub pid: Option<u32>,
+    pub time: Option<JsonValue>,
+
+    /// A service is `degraded` when some of its self-checks are failing
+    #[serde(default)]
+    pub status: Option<Status>,
+    #[serde(default)]
+    pub checks: BTreeMap<String, HealthCheck>,
+}
+
+impl HealthStatusExt {
+    /// The status of the service, with the details of the failing checks if any
+    pub fn summary(&self) -> String {
+        let health_status = HealthStatus {
+            status: self.status.clone().unwrap_or_default(),
+            checks: self.checks.clone(),
+        };
+        match health_status.failing_checks() {
+            Some(failing) => format!("{} ({failing})", health_status.status),
+            None => health_status.status.to_string(),
+        }
+    }
+
+    /// The time at which the health status has been published, if given and valid
+    pub fn timestamp(&self) -> Option<OffsetDateTime> {
+        let time = IsoOrUnix::try_from(self.time.as_ref()?).ok()?;
+        Some(time.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn summary_of_a_degraded_service() {
+        let health_status: HealthStatusExt = serde_json::from_value(json!({
+            "pid": 1234,
+            "status": "degraded",
+            "checks": {
+                "bridge": {"status": "down", "detail": "tedge-mapper-bridge-c8y is down"},
+                "mqtt": {"status": "up"}
+            }
+        }))
+        .unwrap();
+        assert_eq!(
+            health_status.summary(),
+            "degraded (bridge: tedge-mapper-bridge-c8y is down)"
+        );
+
+        let health_status: HealthStatusExt =
+            serde_json::from_value(json!({"pid": 1234, "status": "up"})).unwrap();
+        assert_eq!(health_status.summary(), "up");
+    }
+}
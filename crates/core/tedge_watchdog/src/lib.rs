@@ -1,15 +1,18 @@
 use tedge_config::cli::CommonArgs;
 use tedge_config::log_init;
+use tedge_config::models::WatchdogMode;
+
+mod error;
+mod generic_watchdog;
+mod health_status;
 
 // on linux, we use systemd
 #[cfg(target_os = "linux")]
 mod systemd_watchdog;
 #[cfg(target_os = "linux")]
 use systemd_watchdog as watchdog;
-#[cfg(target_os = "linux")]
-mod error;
 
-// on non-linux, we do nothing for now
+// on non-linux, only the generic watchdog is available
 #[cfg(not(target_os = "linux"))]
 mod dummy_watchdog;
 #[cfg(not(target_os = "linux"))]
@@ -36,5 +39,13 @@ pub async fn run(watchdog_opt: WatchdogOpt) -> Result<(), anyhow::Error> {
         &tedge_config_location.tedge_config_root_path,
     )?;
 
-    watchdog::start_watchdog(watchdog_opt.common.config_dir.as_std_path()).await
+    let tedge_config = tedge_config::TEdgeConfig::try_new(tedge_config_location).await?;
+    match tedge_config.watchdog.mode {
+        WatchdogMode::Systemd => {
+            watchdog::start_watchdog(watchdog_opt.common.config_dir.as_std_path()).await
+        }
+        WatchdogMode::Generic => {
+            generic_watchdog::start_watchdog(&tedge_config, &watchdog_opt.common.config_dir).await
+        }
+    }
 }
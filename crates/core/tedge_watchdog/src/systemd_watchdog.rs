@@ -7,9 +7,6 @@ use futures::StreamExt;
 use mqtt_channel::MqttMessage;
 use mqtt_channel::PubChannel;
 use mqtt_channel::Topic;
-use serde::Deserialize;
-use serde::Serialize;
-use serde_json::Value as JsonValue;
 use std::path::Path;
 use std::process;
 use std::process::Command;
@@ -18,6 +15,7 @@ use std::process::Stdio;
 use std::time::Duration;
 use std::time::Instant;
 use tedge_api::health::ServiceHealthTopic;
+use tedge_api::health::Status;
 use tedge_api::mqtt_topics::Channel;
 use tedge_api::mqtt_topics::EntityTopicId;
 use tedge_api::mqtt_topics::MqttSchema;
@@ -31,6 +29,7 @@ use tracing::info;
 use tracing::warn;
 
 use crate::error::WatchdogError;
+use crate::health_status::HealthStatusExt;
 
 const SERVICE_NAME: &str = "tedge-watchdog";
 
@@ -41,16 +40,6 @@ const SERVICE_NAME: &str = "tedge-watchdog";
 /// a timing misalignment.
 const NOTIFY_SEND_FREQ_RATIO: u64 = 4;
 
-/// A subset of fields of health status payload required by the watchdog.
-///
-/// https://thin-edge.github.io/thin-edge.io/operate/troubleshooting/monitoring-service-health/
-#[derive(Debug, Serialize, Deserialize)]
-struct HealthStatusExt {
-    /// Used for tracking service restarts
-    pub pid: Option<u32>,
-    pub time: Option<JsonValue>,
-}
-
 pub async fn start_watchdog(tedge_config_dir: &Path) -> Result<(), anyhow::Error> {
     // Send ready notification to systemd.
     notify_systemd(process::id(), "--ready")?;
@@ -114,6 +103,10 @@ async fn start_watchdog_for_tedge_services(tedge_config_dir: &Path) {
         "tedge-mapper-az",
         "tedge-mapper-aws",
         "tedge-mapper-collectd",
+        "tedge-mapper-influx",
+        "tedge-mapper-modbus",
+        "tedge-mapper-opcua",
+        "tedge-mapper-gateway",
         "tedge-agent",
         "c8y-firmware-plugin",
     ]
@@ -194,7 +187,7 @@ async fn monitor_tedge_service(
     let _service_health_topic = service_health_topic.clone();
 
     let mqtt_config = tedge_config
-        .mqtt_config()?
+        .service_mqtt_config(SERVICE_NAME)?
         .with_session_name(mqtt_session_name)
         .with_subscriptions(res_topic.into())
         .with_initial_message(move || _service_health_topic.up_message())
@@ -214,6 +207,7 @@ async fn monitor_tedge_service(
         .await
         .context("Could not send initial health status message")?;
 
+    let mut last_summary = None;
     loop {
         let message = MqttMessage::new(&req_topic, "");
         let _ = publisher
@@ -232,8 +226,24 @@ async fn monitor_tedge_service(
             Ok(health_status) => {
                 let health_status = health_status?;
                 if let Some(pid) = health_status.pid {
-                    debug!("Sending notification for {} with pid: {}", name, pid);
-                    notify_systemd(pid, "WATCHDOG=1")?;
+                    // A degraded service is still alive: restarting it would not fix its failing checks
+                    if health_status.status == Some(Status::Down) {
+                        warn!("{name} reports to be down");
+                    } else {
+                        debug!("Sending notification for {} with pid: {}", name, pid);
+                        notify_systemd(pid, "WATCHDOG=1")?;
+                    }
+
+                    let summary = health_status.summary();
+                    if last_summary.as_ref() != Some(&summary) {
+                        if health_status.status == Some(Status::Degraded) {
+                            warn!("{name} is {summary}");
+                        } else {
+                            info!("{name} is {summary}");
+                        }
+                        notify_systemd(pid, &format!("STATUS={summary}"))?;
+                        last_summary = Some(summary);
+                    }
                 } else {
                     error!(
                         "Ignoring invalid health status message from {name} without a `pid` field in it"
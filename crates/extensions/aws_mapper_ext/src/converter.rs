@@ -4,9 +4,11 @@ use serde_json::Map;
 use serde_json::Value;
 use std::convert::Infallible;
 use tedge_actors::Converter;
+use tedge_api::alarm::HiddenAlarms;
 use tedge_api::mqtt_topics::Channel;
 use tedge_api::mqtt_topics::EntityTopicId;
 use tedge_api::mqtt_topics::MqttSchema;
+use tedge_api::payload;
 use tedge_config::models::TopicPrefix;
 use tedge_mqtt_ext::MqttMessage;
 use tedge_mqtt_ext::Topic;
@@ -24,6 +26,7 @@ pub struct AwsConverter {
     pub mqtt_schema: MqttSchema,
     pub time_format: TimeFormat,
     pub topic_prefix: TopicPrefix,
+    hidden_alarms: HiddenAlarms,
 }
 
 impl AwsConverter {
@@ -43,6 +46,7 @@ impl AwsConverter {
             mqtt_schema: mqtt_schema.clone(),
             time_format,
             topic_prefix,
+            hidden_alarms: HiddenAlarms::default(),
         }
     }
 
@@ -77,6 +81,9 @@ impl AwsConverter {
         if source.as_str() == MOSQUITTO_BRIDGE_TOPIC_ID {
             return Ok(vec![]);
         }
+        if self.hidden_alarms.hides(&channel, input) {
+            return Ok(vec![]);
+        }
 
         match channel {
             Channel::Measurement {
@@ -144,7 +151,8 @@ impl AwsConverter {
     }
 
     fn with_timestamp(&self, input: &MqttMessage) -> Result<String, ConversionError> {
-        let mut payload: Map<String, Value> = serde_json::from_slice(input.payload.as_bytes())?;
+        let mut payload: Map<String, Value> =
+            serde_json::from_str(&payload::to_json(input.payload_bytes())?)?;
 
         let time = match payload.remove("time") {
             Some(time) => Some(self.time_format.reformat_json(time)?),
@@ -168,6 +176,7 @@ impl AwsConverter {
 
     fn new_error_message(&self, error: ConversionError) -> MqttMessage {
         error!("Mapping error: {}", error);
+        tedge_metrics::CONVERSION_ERRORS.inc(&[("mapper", "aws")]);
         MqttMessage::new(&self.mqtt_schema.error_topic(), error.to_string())
     }
 }
@@ -313,6 +322,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn cbor_input_is_converted_to_json() {
+        let mut converter = create_test_converter(false);
+
+        let input = payload::encode_cbor(&json!({
+            "time" : 1702029646,
+            "temperature": 23.0
+        }))
+        .unwrap();
+
+        let expected_output = json!({
+            "time" : "2023-12-08T10:00:46Z",
+            "temperature": 23.0
+        });
+
+        let output = converter
+            .convert(&MqttMessage::new(
+                &Topic::new_unchecked("te/device/main///m/"),
+                input,
+            ))
+            .unwrap();
+
+        assert_json_eq!(
+            serde_json::from_str::<serde_json::Value>(&extract_first_message_payload(output))
+                .unwrap(),
+            expected_output
+        );
+    }
+
     #[test]
     fn unix_timestamp_is_converted_to_rfc3339() {
         let mut converter = create_test_converter(false);
@@ -803,6 +841,24 @@ mod tests {
         assert_eq!(res[0], expected_msg);
     }
 
+    #[test]
+    fn alarms_hidden_by_the_agent_are_not_forwarded() {
+        let mut converter = create_test_converter(false);
+        let alarm = MqttMessage::new(
+            &Topic::new_unchecked("te/device/main///a/temperature_high"),
+            r#"{"severity":"critical"}"#,
+        );
+        let status_topic = Topic::new_unchecked("te/device/main///a/temperature_high/status");
+
+        let shelved = MqttMessage::new(&status_topic, r#"{"status":"shelved"}"#);
+        assert!(converter.try_convert(&shelved).unwrap().is_empty());
+        assert!(converter.try_convert(&alarm).unwrap().is_empty());
+
+        let active = MqttMessage::new(&status_topic, r#"{"status":"active"}"#);
+        assert!(converter.try_convert(&active).unwrap().is_empty());
+        assert_eq!(converter.try_convert(&alarm).unwrap().len(), 1);
+    }
+
     fn create_test_converter(add_timestamp: bool) -> AwsConverter {
         AwsConverter::new(
             add_timestamp,
@@ -6,9 +6,11 @@ use serde_json::Map;
 use serde_json::Value;
 use std::convert::Infallible;
 use tedge_actors::Converter;
+use tedge_api::alarm::HiddenAlarms;
 use tedge_api::mqtt_topics::Channel;
 use tedge_api::mqtt_topics::EntityTopicId;
 use tedge_api::mqtt_topics::MqttSchema;
+use tedge_api::payload;
 use tedge_config::models::timestamp::TimeFormat;
 use tedge_config::models::TopicPrefix;
 use tedge_mqtt_ext::MqttMessage;
@@ -29,6 +31,7 @@ pub struct AzureConverter {
     pub(crate) size_threshold: SizeThreshold,
     pub(crate) mapper_config: MapperConfig,
     pub mqtt_schema: MqttSchema,
+    hidden_alarms: HiddenAlarms,
 }
 
 impl AzureConverter {
@@ -52,6 +55,7 @@ impl AzureConverter {
             size_threshold,
             mapper_config,
             mqtt_schema: MqttSchema::default(),
+            hidden_alarms: HiddenAlarms::default(),
         }
     }
 
@@ -90,6 +94,9 @@ impl AzureConverter {
         if entity.as_str() == MOSQUITTO_BRIDGE_TOPIC_ID {
             return Ok(vec![]);
         }
+        if self.hidden_alarms.hides(&channel, input) {
+            return Ok(vec![]);
+        }
 
         match &channel {
             Channel::Measurement { .. }
@@ -106,7 +113,8 @@ impl AzureConverter {
 
     fn with_timestamp(&mut self, input: &MqttMessage) -> Result<String, ConversionError> {
         let time_format = self.mapper_config.time_format;
-        let mut payload: Map<String, Value> = serde_json::from_slice(input.payload.as_bytes())?;
+        let mut payload: Map<String, Value> =
+            serde_json::from_str(&payload::to_json(input.payload_bytes())?)?;
 
         let time = match payload.remove("time") {
             Some(time) => Some(time_format.reformat_json(time)?),
@@ -130,6 +138,7 @@ impl AzureConverter {
 
     fn new_error_message(&self, error: ConversionError) -> MqttMessage {
         error!("Mapping error: {}", error);
+        tedge_metrics::CONVERSION_ERRORS.inc(&[("mapper", "az")]);
         MqttMessage::new(&self.mapper_config.errors_topic, error.to_string())
     }
 }
@@ -341,6 +350,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn cbor_input_is_converted_to_json() {
+        let mut converter = create_test_converter(false);
+
+        let input = payload::encode_cbor(&json!({
+            "time" : 1702029646,
+            "temperature": 23.0
+        }))
+        .unwrap();
+
+        let expected_output = json!({
+            "time" : "2023-12-08T10:00:46Z",
+            "temperature": 23.0
+        });
+
+        let output = converter
+            .convert(&MqttMessage::new(
+                &Topic::new_unchecked("te/device/main///m/"),
+                input,
+            ))
+            .unwrap();
+
+        assert_json_eq!(
+            serde_json::from_str::<serde_json::Value>(&extract_first_message_payload(output))
+                .unwrap(),
+            expected_output
+        );
+    }
+
     #[test]
     fn converting_input_without_timestamp_produces_output_with_timestamp_given_add_timestamp_is_true(
     ) {
@@ -496,6 +534,24 @@ mod tests {
         assert_eq!(res[0], expected_msg);
     }
 
+    #[test]
+    fn alarms_hidden_by_the_agent_are_not_forwarded() {
+        let mut converter = create_test_converter(false);
+        let alarm = MqttMessage::new(
+            &Topic::new_unchecked("te/device/main///a/temperature_high"),
+            r#"{"severity":"critical"}"#,
+        );
+        let status_topic = Topic::new_unchecked("te/device/main///a/temperature_high/status");
+
+        let shelved = MqttMessage::new(&status_topic, r#"{"status":"shelved"}"#);
+        assert!(converter.try_convert(&shelved).unwrap().is_empty());
+        assert!(converter.try_convert(&alarm).unwrap().is_empty());
+
+        let active = MqttMessage::new(&status_topic, r#"{"status":"active"}"#);
+        assert!(converter.try_convert(&active).unwrap().is_empty());
+        assert_eq!(converter.try_convert(&alarm).unwrap().len(), 1);
+    }
+
     fn create_test_converter(add_timestamp: bool) -> AzureConverter {
         AzureConverter::new(
             add_timestamp,
@@ -15,6 +15,9 @@ pub enum ConversionError {
     #[error(transparent)]
     FromSerdeJson(#[from] serde_json::Error),
 
+    #[error(transparent)]
+    FromPayload(#[from] tedge_api::payload::PayloadError),
+
     #[error(transparent)]
     FromTimeFormatError(#[from] time::error::Format),
 }
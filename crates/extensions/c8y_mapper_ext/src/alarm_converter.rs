@@ -8,6 +8,7 @@ use tedge_api::alarm::ThinEdgeAlarmDeserializerError;
 use tedge_api::entity::EntityExternalId;
 use tedge_api::entity::EntityType;
 use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_api::payload;
 use tedge_config::models::TopicPrefix;
 use tedge_mqtt_ext::MqttMessage;
 use tedge_mqtt_ext::Topic;
@@ -55,14 +56,15 @@ impl AlarmConverter {
             Self::Synced => {
                 // Regular conversion phase
                 let mqtt_topic = input_message.topic.name.clone();
-                let mqtt_payload = input_message.payload_str().map_err(|e| {
-                    ThinEdgeAlarmDeserializerError::FailedToParsePayloadToString {
-                        topic: mqtt_topic.clone(),
-                        error: e.to_string(),
-                    }
-                })?;
+                let mqtt_payload =
+                    payload::to_json(input_message.payload_bytes()).map_err(|e| {
+                        ThinEdgeAlarmDeserializerError::FailedToParsePayloadToString {
+                            topic: mqtt_topic.clone(),
+                            error: e.to_string(),
+                        }
+                    })?;
 
-                let tedge_alarm = ThinEdgeAlarm::try_from(alarm_type, source, mqtt_payload)?;
+                let tedge_alarm = ThinEdgeAlarm::try_from(alarm_type, source, &mqtt_payload)?;
                 let c8y_alarm = C8yAlarm::from(&tedge_alarm, external_id, entity_type);
 
                 // If the message doesn't contain any fields other than `text`, `severity` and `time`, convert to SmartREST
@@ -267,8 +267,9 @@ impl AvailabilityActor {
             .map(|ids| (&ids.health_topic_id, ids.external_id.as_ref()))
         {
             if let Some(health_status) = self.health_status_map.get(service_topic_id) {
-                // Send an empty JSON over MQTT message if the target service status is "up"
-                if health_status.status == Status::Up {
+                // Send an empty JSON over MQTT message if the target service is running,
+                // i.e. if its status is "up" or "degraded"
+                if matches!(health_status.status, Status::Up | Status::Degraded) {
                     let json_over_mqtt = C8yJsonInventoryUpdate {
                         external_id: external_id.into(),
                         payload: json!({}),
@@ -276,7 +277,7 @@ impl AvailabilityActor {
                     };
                     self.message_box.send(json_over_mqtt.into()).await?;
                 } else {
-                    debug!("Heartbeat message is not sent because the status of the service '{service_topic_id}' is not 'up'");
+                    debug!("Heartbeat message is not sent because the status of the service '{service_topic_id}' is neither 'up' nor 'degraded'");
                 }
             }
 
@@ -62,6 +62,7 @@ pub struct C8yMapperConfig {
     pub bridge_service_name: String,
     pub bridge_health_topic: Topic,
     pub smartrest_use_operation_id: bool,
+    pub enable_device_details: bool,
 
     pub data_dir: DataDir,
     pub config_dir: Arc<Utf8Path>,
@@ -102,6 +103,7 @@ impl C8yMapperConfig {
         software_management_with_types: bool,
         auto_log_upload: AutoLogUpload,
         smartrest_use_operation_id: bool,
+        enable_device_details: bool,
         max_mqtt_payload_size: u32,
     ) -> Self {
         let ops_dir = config_dir
@@ -143,6 +145,7 @@ impl C8yMapperConfig {
             bridge_service_name,
             bridge_health_topic,
             smartrest_use_operation_id,
+            enable_device_details,
 
             config_dir,
             logs_path,
@@ -190,6 +193,7 @@ impl C8yMapperConfig {
             config_update: c8y_config.enable.config_update,
             firmware_update: c8y_config.enable.firmware_update,
             device_profile: c8y_config.enable.device_profile,
+            command: c8y_config.enable.command,
         };
         let bridge_config = BridgeConfig {
             c8y_prefix: c8y_config.bridge.topic_prefix.clone(),
@@ -205,6 +209,7 @@ impl C8yMapperConfig {
 
         let auto_log_upload = c8y_config.operations.auto_log_upload;
         let smartrest_use_operation_id = c8y_config.smartrest.use_operation_id;
+        let enable_device_details = c8y_config.enable.device_details;
         let max_mqtt_payload_size = c8y_config.mapper.mqtt.max_payload_size.0;
 
         // Add command topics
@@ -254,6 +259,7 @@ impl C8yMapperConfig {
             software_management_with_types,
             auto_log_upload,
             smartrest_use_operation_id,
+            enable_device_details,
             max_mqtt_payload_size,
         ))
     }
@@ -301,6 +307,7 @@ impl C8yMapperConfig {
             "te/+/+/+/+/m/+",
             "te/+/+/+/+/e/+",
             "te/+/+/+/+/a/+",
+            "te/+/+/+/+/a/+/status",
             "te/+/+/+/+/status/health",
         ]
         .try_into()
@@ -53,6 +53,7 @@ use plugin_sm::operation_logs::OperationLogsError;
 use serde_json::json;
 use serde_json::Value;
 use service_monitor::convert_health_status_message;
+use service_monitor::HealthChecksState;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::fs;
@@ -63,6 +64,7 @@ use std::sync::Arc;
 use tedge_actors::ClientMessageBox;
 use tedge_actors::LoggingSender;
 use tedge_actors::Sender;
+use tedge_api::alarm::HiddenAlarms;
 use tedge_api::commands::RestartCommand;
 use tedge_api::commands::SoftwareCommandMetadata;
 use tedge_api::commands::SoftwareListCommand;
@@ -76,6 +78,7 @@ use tedge_api::mqtt_topics::EntityTopicId;
 use tedge_api::mqtt_topics::IdGenerator;
 use tedge_api::mqtt_topics::MqttSchema;
 use tedge_api::mqtt_topics::OperationType;
+use tedge_api::payload;
 use tedge_api::pending_entity_store::RegisteredEntityData;
 use tedge_api::script::ShellScript;
 use tedge_api::workflow::GenericCommandState;
@@ -141,6 +144,7 @@ impl CumulocityConverter {
 
     pub fn new_error_message(&self, error: impl std::error::Error) -> MqttMessage {
         error!("Mapping error: {}", error);
+        tedge_metrics::CONVERSION_ERRORS.inc(&[("mapper", "c8y")]);
         MqttMessage::new(&self.get_mapper_config().errors_topic, error.to_string())
     }
 
@@ -177,6 +181,9 @@ pub struct CumulocityConverter {
     pub(crate) device_topic_id: EntityTopicId,
     pub(crate) device_type: String,
     alarm_converter: AlarmConverter,
+    hidden_alarms: HiddenAlarms,
+    // States of the self-checks last sent for each service, so they are only sent again on change
+    health_checks: HashMap<EntityTopicId, HealthChecksState>,
     operation_logs: OperationLogs,
     mqtt_publisher: LoggingSender<MqttMessage>,
     pub http_proxy: C8YHttpProxy,
@@ -269,6 +276,8 @@ impl CumulocityConverter {
             device_topic_id,
             device_type,
             alarm_converter,
+            hidden_alarms: HiddenAlarms::default(),
+            health_checks: HashMap::new(),
             supported_operations: operation_manager,
             operation_logs,
             http_proxy,
@@ -500,7 +509,7 @@ impl CumulocityConverter {
         if let Some(entity) = self.entity_cache.get(source) {
             // Need to check if the input Thin Edge JSON is valid before adding a child ID to list
             let c8y_json_payload =
-                json::from_thin_edge_json(input.payload_str()?, entity, measurement_type)?;
+                json::from_thin_edge_payload(input.payload_bytes(), entity, measurement_type)?;
 
             if c8y_json_payload.len() < self.size_threshold.0 {
                 mqtt_messages.push(MqttMessage::new(
@@ -509,7 +518,10 @@ impl CumulocityConverter {
                 ));
             } else {
                 return Err(ConversionError::TranslatedSizeExceededThreshold {
-                    payload: input.payload_str()?.chars().take(50).collect(),
+                    payload: String::from_utf8_lossy(input.payload_bytes())
+                        .chars()
+                        .take(50)
+                        .collect(),
                     topic: input.topic.name.clone(),
                     actual_size: c8y_json_payload.len(),
                     threshold: self.size_threshold.0,
@@ -534,7 +546,7 @@ impl CumulocityConverter {
 
         if let Some(entity) = self.entity_cache.get(source) {
             let mqtt_topic = input.topic.name.clone();
-            let mqtt_payload = input.payload_str().map_err(|e| {
+            let mqtt_payload = payload::to_json(input.payload_bytes()).map_err(|e| {
                 ThinEdgeJsonDeserializerError::FailedToParsePayloadToString {
                     topic: mqtt_topic.clone(),
                     error: e.to_string(),
@@ -545,7 +557,7 @@ impl CumulocityConverter {
                 event_type,
                 &entity.metadata.r#type,
                 &entity.external_id,
-                mqtt_payload,
+                &mqtt_payload,
             )
             .map_err(
                 |e| ThinEdgeJsonDeserializerError::FailedToParseJsonPayload {
@@ -599,6 +611,10 @@ impl CumulocityConverter {
         alarm_type: &str,
     ) -> Result<Vec<MqttMessage>, ConversionError> {
         self.size_threshold.validate(input)?;
+        if self.hidden_alarms.contains(&input.topic.name) {
+            debug!(topic = ?input.topic.name, "Ignoring alarm shelved or suppressed by the agent");
+            return Ok(vec![]);
+        }
         let entity = self.entity_cache.try_get(source)?;
 
         let mqtt_messages = self.alarm_converter.try_convert_alarm(
@@ -620,6 +636,7 @@ impl CumulocityConverter {
     ) -> Result<Vec<MqttMessage>, ConversionError> {
         let entity = self.entity_cache.try_get(entity_tid)?;
         let parent_xid = self.entity_cache.parent_external_id(entity_tid)?;
+        let sent_checks = self.health_checks.entry(entity_tid.clone()).or_default();
 
         Ok(convert_health_status_message(
             &self.config.mqtt_schema,
@@ -628,6 +645,7 @@ impl CumulocityConverter {
             self.entity_cache.main_device_external_id(),
             message,
             &self.config.bridge_config.c8y_prefix,
+            sent_checks,
         ))
     }
 
@@ -678,7 +696,17 @@ impl CumulocityConverter {
     ) -> Result<Vec<MqttMessage>, CumulocityMapperError> {
         let cmd_id = self.command_id.new_id_with_str(&operation_id);
 
-        let msgs = match C8yDeviceControlOperation::from_json_object(extras)? {
+        let operation = match C8yDeviceControlOperation::from_json_object(extras)? {
+            // An operation template defined for c8y_Command takes precedence over the built-in command operation
+            C8yDeviceControlOperation::Command(_)
+                if self.has_custom_operation_handler(&device_xid, "c8y_Command", message) =>
+            {
+                C8yDeviceControlOperation::Custom
+            }
+            operation => operation,
+        };
+
+        let msgs = match operation {
             C8yDeviceControlOperation::Restart(_) => {
                 self.forward_restart_request(device_xid, cmd_id)?
             }
@@ -737,6 +765,17 @@ impl CumulocityConverter {
                     vec![]
                 }
             }
+            C8yDeviceControlOperation::Command(request) => {
+                if self.config.capabilities.command {
+                    self.convert_command_request(device_xid, cmd_id, request)?
+                } else {
+                    warn!("Received a c8y_Command operation, however, command feature is disabled");
+                    vec![]
+                }
+            }
+            C8yDeviceControlOperation::AcknowledgeAlarm(request) => {
+                self.convert_acknowledge_alarm_request(device_xid, cmd_id, request)?
+            }
             C8yDeviceControlOperation::Custom => {
                 return self
                     .process_json_custom_operation(
@@ -821,6 +860,20 @@ impl CumulocityConverter {
         Ok(vec![])
     }
 
+    fn has_custom_operation_handler(
+        &self,
+        device_xid: &str,
+        on_fragment: &str,
+        message: &MqttMessage,
+    ) -> bool {
+        self.get_operation_handlers(
+            device_xid,
+            &message.topic.name,
+            &self.config.bridge_config.c8y_prefix,
+        )
+        .is_ok_and(|handlers| handlers.iter().any(|(fragment, _)| fragment == on_fragment))
+    }
+
     fn get_operation_handlers(
         &self,
         device_xid: &str,
@@ -1318,6 +1371,11 @@ impl CumulocityConverter {
                 self.process_alarm_messages(&source, message, alarm_type)
             }
 
+            Channel::AlarmStatus { .. } => {
+                self.hidden_alarms.update(message);
+                Ok(vec![])
+            }
+
             Channel::Command { cmd_id, .. } if message.payload_bytes().is_empty() => {
                 // The command has been fully processed
                 self.active_commands.remove(cmd_id);
@@ -1354,6 +1412,20 @@ impl CumulocityConverter {
                     OperationType::DeviceProfile => {
                         self.register_device_profile_operation(&source).await
                     }
+                    OperationType::Command
+                        if self
+                            .supported_operations
+                            .get_operation_name_by_workflow_operation("command")
+                            .is_some() =>
+                    {
+                        self.register_custom_operation(&source, "command").await
+                    }
+                    OperationType::Command => self.register_command_operation(&source).await,
+                    OperationType::Custom(command_name)
+                        if command_name == operations::ACKNOWLEDGE_ALARM_OPERATION =>
+                    {
+                        self.register_acknowledge_alarm_operation(&source).await
+                    }
                     OperationType::Custom(command_name) => {
                         self.register_custom_operation(&source, command_name).await
                     }
@@ -1673,6 +1745,7 @@ pub(crate) mod tests {
     use tedge_api::mqtt_topics::EntityTopicId;
     use tedge_api::mqtt_topics::MqttSchema;
     use tedge_api::mqtt_topics::OperationType;
+    use tedge_api::payload;
     use tedge_api::pending_entity_store::RegisteredEntityData;
     use tedge_api::script::ShellScript;
     use tedge_api::SoftwareUpdateCommand;
@@ -1743,6 +1816,26 @@ pub(crate) mod tests {
         assert!(converter.convert(&internal_alarm_message).await.is_empty());
     }
 
+    #[tokio::test]
+    async fn alarms_hidden_by_the_agent_are_not_forwarded() {
+        let tmp_dir = TempTedgeDir::new();
+        let (mut converter, _http_proxy) = create_c8y_converter(&tmp_dir).await;
+        let _ = converter.sync_messages();
+
+        let alarm_topic = "te/device/main///a/temperature_alarm";
+        let alarm_payload = r#"{ "severity": "critical", "text": "Temperature very high" }"#;
+        let alarm_message = MqttMessage::new(&Topic::new_unchecked(alarm_topic), alarm_payload);
+        let status_topic = Topic::new_unchecked("te/device/main///a/temperature_alarm/status");
+
+        let shelved = MqttMessage::new(&status_topic, r#"{"status":"shelved"}"#);
+        assert!(converter.convert(&shelved).await.is_empty());
+        assert!(converter.convert(&alarm_message).await.is_empty());
+
+        let active = MqttMessage::new(&status_topic, r#"{"status":"active"}"#);
+        assert!(converter.convert(&active).await.is_empty());
+        assert!(!converter.convert(&alarm_message).await.is_empty());
+    }
+
     #[tokio::test]
     async fn test_sync_child_alarms() {
         let tmp_dir = TempTedgeDir::new();
@@ -2156,6 +2249,35 @@ pub(crate) mod tests {
         assert_eq!(out_first_messages, vec![expected_c8y_json_message.clone()]);
     }
 
+    #[tokio::test]
+    async fn convert_cbor_measurement() {
+        let tmp_dir = TempTedgeDir::new();
+        let (mut converter, _http_proxy) = create_c8y_converter(&tmp_dir).await;
+
+        let in_topic = "te/device/main///m/test_type";
+        let in_payload = payload::encode_cbor(&json!({
+            "temp": 1,
+            "time": "2021-11-16T17:45:40.571760714+01:00"
+        }))
+        .unwrap();
+        let in_message = MqttMessage::new(&Topic::new_unchecked(in_topic), in_payload);
+
+        register_source_entities(in_topic, &mut converter).await;
+
+        let expected_c8y_json_message = MqttMessage::new(
+            &Topic::new_unchecked("c8y/measurement/measurements/create"),
+            r#"{"temp":{"temp":{"value":1.0}},"time":"2021-11-16T17:45:40.571760714+01:00","type":"test_type"}"#,
+        );
+
+        let out_messages: Vec<_> = converter
+            .convert(&in_message)
+            .await
+            .into_iter()
+            .filter(|m| m.topic.name.starts_with("c8y"))
+            .collect();
+        assert_eq!(out_messages, vec![expected_c8y_json_message]);
+    }
+
     #[tokio::test]
     async fn convert_measurement_with_main_id_with_measurement_type_in_payload() {
         let tmp_dir = TempTedgeDir::new();
@@ -2272,6 +2394,29 @@ pub(crate) mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn convert_cbor_event_to_c8y_smartrest() {
+        let tmp_dir = TempTedgeDir::new();
+        let (mut converter, _http_proxy) = create_c8y_converter(&tmp_dir).await;
+        let event_topic = "te/device/main///e/click_event";
+        let event_payload = payload::encode_cbor(&json!({
+            "text": "Someone clicked",
+            "time": "2020-02-02T01:02:03+05:30"
+        }))
+        .unwrap();
+        let event_message = MqttMessage::new(&Topic::new_unchecked(event_topic), event_payload);
+
+        let converted_events = converter.convert(&event_message).await;
+        assert_eq!(converted_events.len(), 1);
+        let converted_event = converted_events.first().unwrap();
+        assert_eq!(converted_event.topic.name, "c8y/s/us");
+
+        assert_eq!(
+            converted_event.payload_str().unwrap(),
+            r#"400,click_event,"Someone clicked",2020-02-02T01:02:03+05:30"#
+        );
+    }
+
     #[tokio::test]
     async fn convert_event_use_event_type_from_payload_to_c8y_smartrest() {
         let tmp_dir = TempTedgeDir::new();
@@ -3084,7 +3229,7 @@ pub(crate) mod tests {
         create_c8y_converter_from_config(config)
     }
 
-    fn c8y_converter_config(tmp_dir: &TempTedgeDir) -> C8yMapperConfig {
+    pub(crate) fn c8y_converter_config(tmp_dir: &TempTedgeDir) -> C8yMapperConfig {
         tmp_dir.dir("operations").dir("c8y");
         tmp_dir.dir("tedge").dir("agent");
         tmp_dir.dir(".tedge-mapper-c8y");
@@ -3136,11 +3281,12 @@ pub(crate) mod tests {
             true,
             AutoLogUpload::Never,
             false,
+            false,
             16184,
         )
     }
 
-    fn create_c8y_converter_from_config(
+    pub(crate) fn create_c8y_converter_from_config(
         config: C8yMapperConfig,
     ) -> (CumulocityConverter, FakeServerBox<HttpRequest, HttpResult>) {
         let mqtt_builder: SimpleMessageBoxBuilder<MqttMessage, MqttMessage> =
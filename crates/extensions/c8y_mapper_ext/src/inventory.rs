@@ -106,6 +106,18 @@ impl CumulocityConverter {
             fragment_key = "c8y_Firmware";
         }
 
+        // Map the device details published by tedge-agent to their c8y equivalents, if enabled,
+        // as these c8y fragments might be already managed by other means
+        if self.config.enable_device_details {
+            if let Some((c8y_fragment_key, c8y_fragment_value)) =
+                c8y_device_details(fragment_key, fragment_value)
+            {
+                let mapped_json = json!({ c8y_fragment_key: c8y_fragment_value });
+                let mapped_message = self.inventory_update_message(source, mapped_json)?;
+                return Ok(vec![mapped_message]);
+            }
+        }
+
         // All services in C8Y must have a fixed `type` fragment called `c8y_Service`.
         // The service specific type fragment is called `serviceType` and hence
         // we need to map the entity `type` into `serviceType` for services.
@@ -185,9 +197,61 @@ impl CumulocityConverter {
     }
 }
 
+/// Map the `hardware` and `network` fragments published by tedge-agent to `c8y_Hardware` and `c8y_Network`
+///
+/// Only the properties defined by the c8y device management schema are mapped:
+/// the `model`, `serialNumber` and `revision` of the hardware,
+/// and the `name`, `mac` and `ip` of the first interface with an IPv4 address as the `c8y_LAN`.
+/// A cleared fragment is mapped to a cleared c8y fragment.
+fn c8y_device_details(
+    fragment_key: &str,
+    fragment_value: &JsonValue,
+) -> Option<(&'static str, JsonValue)> {
+    match fragment_key {
+        "hardware" if fragment_value.is_null() => Some(("c8y_Hardware", JsonValue::Null)),
+        "hardware" => {
+            let mut hardware = serde_json::Map::new();
+            for property in ["model", "serialNumber", "revision"] {
+                if let Some(value) = fragment_value.get(property).filter(|v| v.is_string()) {
+                    hardware.insert(property.to_string(), value.clone());
+                }
+            }
+            Some(("c8y_Hardware", JsonValue::Object(hardware)))
+        }
+        "network" if fragment_value.is_null() => Some(("c8y_Network", JsonValue::Null)),
+        "network" => {
+            let interfaces = fragment_value
+                .get("interfaces")
+                .and_then(JsonValue::as_array)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            let lan = interfaces.iter().find_map(|interface| {
+                let ip = interface.get("ipv4")?.as_array()?.first()?.as_str()?;
+                let mut lan = serde_json::Map::new();
+                lan.insert("name".to_string(), interface.get("name")?.clone());
+                if let Some(mac) = interface.get("mac") {
+                    lan.insert("mac".to_string(), mac.clone());
+                }
+                lan.insert("ip".to_string(), json!(ip));
+                let up = interface.get("state").and_then(JsonValue::as_str) != Some("down");
+                lan.insert("enabled".to_string(), json!(u8::from(up)));
+                Some(JsonValue::Object(lan))
+            });
+            let network = match lan {
+                Some(lan) => json!({ "c8y_LAN": lan }),
+                None => json!({}),
+            };
+            Some(("c8y_Network", network))
+        }
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::converter::tests::c8y_converter_config;
     use crate::converter::tests::create_c8y_converter;
+    use crate::converter::tests::create_c8y_converter_from_config;
     use crate::converter::tests::register_source_entities;
     use serde_json::json;
     use tedge_mqtt_ext::test_helpers::assert_messages_matching;
@@ -532,6 +596,71 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn convert_inventory_fragments_to_c8y_device_details() {
+        let tmp_dir = TempTedgeDir::new();
+        let mut config = c8y_converter_config(&tmp_dir);
+        config.enable_device_details = true;
+        let (mut converter, _http_proxy) = create_c8y_converter_from_config(config);
+
+        let hardware_message = MqttMessage::new(
+            &Topic::new_unchecked("te/device/main///twin/hardware"),
+            r#"{"model":"Raspberry Pi 4 Model B Rev 1.4","serialNumber":"100000001234abcd","revision":"c03114","cpuCores":4}"#,
+        );
+        let inventory_messages = converter.convert(&hardware_message).await;
+        assert_messages_matching(
+            &inventory_messages,
+            [(
+                "c8y/inventory/managedObjects/update/test-device",
+                json!({"c8y_Hardware":{"model":"Raspberry Pi 4 Model B Rev 1.4","serialNumber":"100000001234abcd","revision":"c03114"}}).into(),
+            )],
+        );
+
+        let network_message = MqttMessage::new(
+            &Topic::new_unchecked("te/device/main///twin/network"),
+            r#"{"interfaces":[{"name":"docker0","ipv4":[],"state":"down"},{"name":"eth0","mac":"dc:a6:32:00:00:01","state":"up","ipv4":["192.168.1.10"],"ipv6":[]}]}"#,
+        );
+        let inventory_messages = converter.convert(&network_message).await;
+        assert_messages_matching(
+            &inventory_messages,
+            [(
+                "c8y/inventory/managedObjects/update/test-device",
+                json!({"c8y_Network":{"c8y_LAN":{"name":"eth0","mac":"dc:a6:32:00:00:01","ip":"192.168.1.10","enabled":1}}})
+                    .into(),
+            )],
+        );
+
+        let cleared_message =
+            MqttMessage::new(&Topic::new_unchecked("te/device/main///twin/hardware"), "");
+        let inventory_messages = converter.convert(&cleared_message).await;
+        assert_messages_matching(
+            &inventory_messages,
+            [(
+                "c8y/inventory/managedObjects/update/test-device",
+                json!({ "c8y_Hardware": null }).into(),
+            )],
+        );
+    }
+
+    #[tokio::test]
+    async fn inventory_fragments_are_not_mapped_to_c8y_device_details_by_default() {
+        let tmp_dir = TempTedgeDir::new();
+        let (mut converter, _http_proxy) = create_c8y_converter(&tmp_dir).await;
+
+        let hardware_message = MqttMessage::new(
+            &Topic::new_unchecked("te/device/main///twin/hardware"),
+            r#"{"model":"Raspberry Pi 4 Model B Rev 1.4"}"#,
+        );
+        let inventory_messages = converter.convert(&hardware_message).await;
+        assert_messages_matching(
+            &inventory_messages,
+            [(
+                "c8y/inventory/managedObjects/update/test-device",
+                json!({"hardware":{"model":"Raspberry Pi 4 Model B Rev 1.4"}}).into(),
+            )],
+        );
+    }
+
     #[tokio::test]
     async fn convert_service_type() {
         let tmp_dir = TempTedgeDir::new();
@@ -31,6 +31,9 @@ pub enum CumulocityJsonError {
 
     #[error(transparent)]
     ThinEdgeJsonParserError(#[from] ThinEdgeJsonParserError),
+
+    #[error(transparent)]
+    ThinEdgePayloadParserError(#[from] ThinEdgePayloadParserError),
 }
 
 /// Converts from thin-edge measurement JSON to C8Y measurement JSON
@@ -44,6 +47,18 @@ pub fn from_thin_edge_json(
     Ok(c8y_vec)
 }
 
+/// Converts from thin-edge measurement JSON or CBOR payload to C8Y measurement JSON
+pub fn from_thin_edge_payload(
+    input: &[u8],
+    entity: &CloudEntityMetadata,
+    m_type: &str,
+) -> Result<String, CumulocityJsonError> {
+    let timestamp = WallClock.now();
+    let mut serializer = serializer::C8yJsonSerializer::new(timestamp, entity, m_type);
+    parse_payload(input, &mut serializer)?;
+    Ok(serializer.into_string()?)
+}
+
 fn from_thin_edge_json_with_timestamp(
     input: &str,
     timestamp: OffsetDateTime,
@@ -221,6 +236,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn convert_thin_edge_cbor_to_c8y_json() {
+        let input = tedge_api::payload::encode_cbor(&json!({
+            "time": "2013-06-22T17:03:14.000+02:00",
+            "temperature": 23.0,
+        }))
+        .unwrap();
+
+        let entity = CloudEntityMetadata::new("foo".into(), EntityMetadata::main_device());
+        let output = from_thin_edge_payload(&input, &entity, "").unwrap();
+
+        assert_json_eq!(
+            serde_json::from_str::<serde_json::Value>(&output).unwrap(),
+            json!({
+                "time": "2013-06-22T17:03:14+02:00",
+                "temperature": {
+                    "temperature": {
+                        "value": 23.0
+                    }
+                },
+                "type": "ThinEdgeMeasurement"
+            })
+        );
+    }
+
     #[test]
     fn thin_edge_json_round_tiny_number() {
         let input = r#"{
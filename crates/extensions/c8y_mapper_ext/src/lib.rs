@@ -25,6 +25,7 @@ pub struct Capabilities {
     pub config_update: bool,
     pub firmware_update: bool,
     pub device_profile: bool,
+    pub command: bool,
 }
 
 #[cfg(test)]
@@ -36,6 +37,7 @@ impl Default for Capabilities {
             config_update: true,
             firmware_update: true,
             device_profile: true,
+            command: true,
         }
     }
 }
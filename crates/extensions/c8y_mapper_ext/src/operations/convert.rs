@@ -1,5 +1,7 @@
 //! Converting Cumulocity Smartrest operation messages into local thin-edge operation messages.
 use crate::supported_operations::operation::Operation;
+use c8y_api::json_c8y_deserializer::C8yAcknowledgeAlarm;
+use c8y_api::json_c8y_deserializer::C8yCommand;
 use c8y_api::json_c8y_deserializer::C8yDeviceProfile;
 use c8y_api::json_c8y_deserializer::C8yDownloadConfigFile;
 use c8y_api::json_c8y_deserializer::C8yFirmware;
@@ -17,6 +19,7 @@ use tedge_api::commands::ConfigUpdateCmdPayload;
 use tedge_api::commands::FirmwareUpdateCmdPayload;
 use tedge_api::commands::LogMetadata;
 use tedge_api::commands::LogUploadCmdPayload;
+use tedge_api::commands::ShellCommandCmdPayload;
 use tedge_api::device_profile::ConfigPayload;
 use tedge_api::device_profile::DeviceProfileCmdPayload;
 use tedge_api::entity::EntityExternalId;
@@ -34,6 +37,12 @@ use crate::converter::CumulocityConverter;
 use crate::error::ConversionError;
 use crate::error::CumulocityMapperError;
 
+/// The operation used by the agent to acknowledge alarms on behalf of the cloud
+pub const ACKNOWLEDGE_ALARM_OPERATION: &str = "acknowledge_alarm";
+
+/// The Cumulocity operation carrying the alarm acknowledgements made on the cloud
+const C8Y_ACKNOWLEDGE_ALARM: &str = "c8y_AcknowledgeAlarm";
+
 impl CumulocityConverter {
     /// Converts a config_snapshot metadata message to
     /// - supported operation "c8y_UploadConfigFile"
@@ -419,6 +428,108 @@ impl CumulocityConverter {
         }
     }
 
+    /// Convert c8y_Command JSON over MQTT operation to ThinEdge command command.
+    pub fn convert_command_request(
+        &self,
+        device_xid: String,
+        cmd_id: String,
+        command_request: C8yCommand,
+    ) -> Result<Vec<MqttMessage>, CumulocityMapperError> {
+        let entity_xid: EntityExternalId = device_xid.into();
+
+        let target = self.entity_cache.try_get_by_external_id(&entity_xid)?;
+
+        let channel = Channel::Command {
+            operation: OperationType::Command,
+            cmd_id,
+        };
+        let topic = self
+            .mqtt_schema
+            .topic_for(&target.metadata.topic_id, &channel);
+
+        let request = ShellCommandCmdPayload {
+            status: CommandStatus::Init,
+            command: command_request.text,
+            max_output_size: Some(self.config.max_mqtt_payload_size as usize),
+            output: None,
+            exit_code: None,
+            log_path: None,
+        };
+
+        // Command messages must be retained
+        Ok(vec![
+            MqttMessage::new(&topic, request.to_json()).with_retain()
+        ])
+    }
+
+    pub async fn register_command_operation(
+        &mut self,
+        topic_id: &EntityTopicId,
+    ) -> Result<Vec<MqttMessage>, ConversionError> {
+        if !self.config.capabilities.command {
+            warn!("Received command metadata, however, command feature is disabled");
+            return Ok(vec![]);
+        }
+
+        match self.register_operation(topic_id, "c8y_Command").await {
+            Err(err) => {
+                error!("Failed to register `c8y_Command` operation for {topic_id} due to: {err}");
+                Ok(vec![])
+            }
+            Ok(messages) => Ok(messages),
+        }
+    }
+
+    /// Convert a c8y_AcknowledgeAlarm operation into an acknowledge_alarm command
+    ///
+    /// The command is tagged with the Cumulocity operation,
+    /// for its status to be reported as for a custom operation.
+    pub fn convert_acknowledge_alarm_request(
+        &self,
+        device_xid: String,
+        cmd_id: String,
+        request: C8yAcknowledgeAlarm,
+    ) -> Result<Vec<MqttMessage>, CumulocityMapperError> {
+        let entity_xid: EntityExternalId = device_xid.into();
+
+        let target = self.entity_cache.try_get_by_external_id(&entity_xid)?;
+
+        let channel = Channel::Command {
+            operation: OperationType::Custom(ACKNOWLEDGE_ALARM_OPERATION.to_string()),
+            cmd_id,
+        };
+        let topic = self
+            .mqtt_schema
+            .topic_for(&target.metadata.topic_id, &channel);
+
+        let mapper_id = self.command_id.prefix();
+        let payload = json!({
+            "alarmType": request.alarm_type,
+            mapper_id: {
+                "on_fragment": C8Y_ACKNOWLEDGE_ALARM,
+            }
+        });
+        let request = GenericCommandState::new(topic, CommandStatus::Init.to_string(), payload);
+
+        Ok(vec![request.into_message()])
+    }
+
+    pub async fn register_acknowledge_alarm_operation(
+        &mut self,
+        topic_id: &EntityTopicId,
+    ) -> Result<Vec<MqttMessage>, ConversionError> {
+        match self
+            .register_operation(topic_id, C8Y_ACKNOWLEDGE_ALARM)
+            .await
+        {
+            Err(err) => {
+                error!("Failed to register `{C8Y_ACKNOWLEDGE_ALARM}` operation for {topic_id} due to: {err}");
+                Ok(vec![])
+            }
+            Ok(messages) => Ok(messages),
+        }
+    }
+
     pub fn convert_custom_operation_request(
         &self,
         device_xid: String,
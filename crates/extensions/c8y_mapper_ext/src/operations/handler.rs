@@ -58,6 +58,7 @@ impl OperationHandler {
             context: Arc::new(OperationContext {
                 capabilities: c8y_mapper_config.capabilities,
                 auto_log_upload: c8y_mapper_config.auto_log_upload,
+                max_mqtt_payload_size: c8y_mapper_config.max_mqtt_payload_size,
                 tedge_http_host: c8y_mapper_config.tedge_http_host.clone(),
                 tmp_dir: c8y_mapper_config.tmp_dir.clone(),
                 mqtt_schema: c8y_mapper_config.mqtt_schema.clone(),
@@ -252,6 +253,13 @@ impl OperationHandler {
             ]);
         }
 
+        if capabilities.command {
+            topics.extend([
+                (AnyEntity, Command(OperationType::Command)),
+                (AnyEntity, CommandMetadata(OperationType::Command)),
+            ]);
+        }
+
         topics
     }
 }
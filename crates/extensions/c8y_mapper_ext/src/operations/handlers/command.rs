@@ -0,0 +1,310 @@
+use super::error::OperationError;
+use super::EntityTarget;
+use super::OperationContext;
+use super::OperationOutcome;
+use anyhow::Context;
+use c8y_api::smartrest::smartrest_serializer::CumulocitySupportedOperations;
+use c8y_api::smartrest::smartrest_serializer::TextOrCsv;
+use camino::Utf8Path;
+use tedge_api::commands::CommandStatus;
+use tedge_api::commands::ShellCommandCmd;
+use tedge_config::models::AutoLogUpload;
+use tedge_mqtt_ext::MqttMessage;
+use tracing::warn;
+
+/// The room left in a SmartREST message for the operation name and the binary URL
+const SMARTREST_OVERHEAD: usize = 500;
+
+impl OperationContext {
+    /// Address a received command command. If its status is
+    /// - "executing", it converts the message to SmartREST "Executing".
+    /// - "successful", it converts the message to SmartREST "Successful" with the command output as result,
+    ///   uploading the full command log as an event binary when the output is too large for a SmartREST message.
+    /// - "failed", it converts the message to SmartREST "Failed", with the head of the command output.
+    pub async fn handle_command_state_change(
+        &self,
+        target: &EntityTarget,
+        cmd_id: &str,
+        message: &MqttMessage,
+    ) -> Result<OperationOutcome, OperationError> {
+        if !self.capabilities.command {
+            warn!("Received a command command, however, command feature is disabled");
+            return Ok(OperationOutcome::Ignored);
+        }
+
+        let command = match ShellCommandCmd::try_from_bytes(
+            target.topic_id.clone(),
+            cmd_id.into(),
+            message.payload_bytes(),
+        )
+        .context("Could not parse command as a command command")?
+        {
+            Some(command) => command,
+            None => {
+                // The command has been fully processed
+                return Ok(OperationOutcome::Ignored);
+            }
+        };
+
+        match command.status() {
+            CommandStatus::Executing => Ok(OperationOutcome::Executing {
+                extra_messages: vec![],
+            }),
+            CommandStatus::Successful => {
+                let output = command.payload.output.clone().unwrap_or_default();
+                let result = if output.len() > self.max_inline_output_size() {
+                    self.upload_command_output(target, cmd_id, &command, &output)
+                        .await?
+                } else {
+                    output
+                };
+
+                let smartrest_operation_status = self
+                    .try_get_smartrest_successful_status_payload_with_args(
+                        CumulocitySupportedOperations::C8yCommand,
+                        cmd_id,
+                        TextOrCsv::Text(result),
+                    );
+                let c8y_notification =
+                    MqttMessage::new(&target.smartrest_publish_topic, smartrest_operation_status);
+
+                Ok(OperationOutcome::Finished {
+                    messages: vec![c8y_notification],
+                })
+            }
+            CommandStatus::Failed { reason } => {
+                // The failure reason is truncated when sent to c8y,
+                // the full output being available in the operation log
+                let reason = match command.payload.output.as_deref() {
+                    Some(output) if !output.is_empty() => format!("{reason}\n{output}"),
+                    _ => reason,
+                };
+                Err(anyhow::anyhow!(reason).into())
+            }
+            _ => Ok(OperationOutcome::Ignored),
+        }
+    }
+
+    /// The output size above which the full output is uploaded to c8y as an event binary
+    fn max_inline_output_size(&self) -> usize {
+        (self.max_mqtt_payload_size as usize).saturating_sub(SMARTREST_OVERHEAD)
+    }
+
+    /// Upload the full output of a command, returning a truncated output that refers to the upload
+    ///
+    /// The uploaded file is the command log, where the agent logs the whole output,
+    /// unless this log is uploaded anyway along the operation log.
+    async fn upload_command_output(
+        &self,
+        target: &EntityTarget,
+        cmd_id: &str,
+        command: &ShellCommandCmd,
+        output: &str,
+    ) -> Result<String, OperationError> {
+        let log_path = command
+            .payload
+            .log_path
+            .as_ref()
+            .filter(|log_path| log_path.exists());
+        let note = match log_path {
+            Some(_) if self.auto_log_upload == AutoLogUpload::Always => {
+                "Output truncated, see the operation log".to_string()
+            }
+            Some(log_path) => self.upload_command_file(target, cmd_id, log_path).await,
+            None => {
+                let output_path = self.tmp_dir.join(format!("{cmd_id}-output.log"));
+                tokio::fs::write(&output_path, output)
+                    .await
+                    .context("Could not write the command output to a temporary file")?;
+                let note = self.upload_command_file(target, cmd_id, &output_path).await;
+                let _ = tokio::fs::remove_file(&output_path).await;
+                note
+            }
+        };
+
+        let mut end = self.max_inline_output_size().min(output.len());
+        while !output.is_char_boundary(end) {
+            end -= 1;
+        }
+        Ok(format!("{note}\n{}", &output[..end]))
+    }
+
+    /// Upload a file holding the full output of a command, returning a note referring to the upload
+    async fn upload_command_file(
+        &self,
+        target: &EntityTarget,
+        cmd_id: &str,
+        path: &Utf8Path,
+    ) -> String {
+        let upload = self
+            .upload_file(
+                &target.external_id,
+                path,
+                None,
+                Some(mime::TEXT_PLAIN),
+                cmd_id,
+                "c8y_Command_output".to_string(),
+                Some("Command output".to_string()),
+            )
+            .await;
+
+        match upload {
+            Ok((binary_url, Ok(_))) => format!("Output truncated, see {binary_url}"),
+            Ok((_, Err(err))) => {
+                warn!("Could not upload the output of the command {cmd_id}: {err}");
+                "Output truncated".to_string()
+            }
+            Err(err) => {
+                warn!("Could not upload the output of the command {cmd_id}: {err}");
+                "Output truncated".to_string()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::*;
+    use c8y_api::json_c8y_deserializer::C8yDeviceControlTopic;
+    use serde_json::json;
+    use std::time::Duration;
+    use tedge_actors::test_helpers::MessageReceiverExt;
+    use tedge_actors::Sender;
+    use tedge_mqtt_ext::test_helpers::assert_received_contains_str;
+    use tedge_mqtt_ext::test_helpers::assert_received_includes_json;
+    use tedge_mqtt_ext::MqttMessage;
+    use tedge_mqtt_ext::Topic;
+    use tedge_test_utils::fs::TempTedgeDir;
+
+    const TEST_TIMEOUT_MS: Duration = Duration::from_millis(3000);
+
+    #[tokio::test]
+    async fn create_command_operation_file_for_main_device() {
+        let ttd = TempTedgeDir::new();
+        let test_handle = spawn_c8y_mapper_actor(&ttd, true).await;
+        let TestHandle { mqtt, .. } = test_handle;
+        let mut mqtt = mqtt.with_timeout(TEST_TIMEOUT_MS);
+
+        skip_init_messages(&mut mqtt).await;
+
+        mqtt.send(MqttMessage::new(
+            &Topic::new_unchecked("te/device/main///cmd/command"),
+            "{}",
+        ))
+        .await
+        .expect("Send failed");
+
+        assert_received_contains_str(&mut mqtt, [("c8y/s/us", "114,c8y_Command")]).await;
+        assert!(ttd.path().join("operations/c8y/c8y_Command").exists());
+    }
+
+    #[tokio::test]
+    async fn mapper_converts_c8y_command_to_command_cmd() {
+        let ttd = TempTedgeDir::new();
+        let test_handle = spawn_c8y_mapper_actor(&ttd, true).await;
+        let TestHandle { mqtt, .. } = test_handle;
+        let mut mqtt = mqtt.with_timeout(TEST_TIMEOUT_MS);
+
+        skip_init_messages(&mut mqtt).await;
+
+        mqtt.send(MqttMessage::new(
+            &C8yDeviceControlTopic::topic(&"c8y".try_into().unwrap()),
+            json!({
+                "id": "123456",
+                "c8y_Command": {
+                    "text": "df -h"
+                },
+                "externalSource": {
+                    "externalId": "test-device",
+                    "type": "c8y_Serial"
+                }
+            })
+            .to_string(),
+        ))
+        .await
+        .expect("Send failed");
+
+        assert_received_includes_json(
+            &mut mqtt,
+            [(
+                "te/device/main///cmd/command/c8y-mapper-123456",
+                json!({
+                    "status": "init",
+                    "command": "df -h"
+                }),
+            )],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn mapper_reports_the_command_output_as_operation_result() {
+        let ttd = TempTedgeDir::new();
+        let test_handle = spawn_c8y_mapper_actor(&ttd, true).await;
+        let TestHandle { mqtt, .. } = test_handle;
+        let mut mqtt = mqtt.with_timeout(TEST_TIMEOUT_MS);
+
+        skip_init_messages(&mut mqtt).await;
+
+        mqtt.send(MqttMessage::new(
+            &Topic::new_unchecked("te/device/main///cmd/command/c8y-mapper-1234"),
+            json!({
+                "status": "executing",
+                "command": "echo hello"
+            })
+            .to_string(),
+        ))
+        .await
+        .expect("Send failed");
+
+        assert_received_contains_str(&mut mqtt, [("c8y/s/us", "501,c8y_Command")]).await;
+
+        mqtt.send(MqttMessage::new(
+            &Topic::new_unchecked("te/device/main///cmd/command/c8y-mapper-1234"),
+            json!({
+                "status": "successful",
+                "command": "echo hello",
+                "output": "hello\n",
+                "exitCode": 0
+            })
+            .to_string(),
+        ))
+        .await
+        .expect("Send failed");
+
+        assert_received_contains_str(&mut mqtt, [("c8y/s/us", "503,c8y_Command,\"hello")]).await;
+    }
+
+    #[tokio::test]
+    async fn mapper_reports_a_failed_command() {
+        let ttd = TempTedgeDir::new();
+        let test_handle = spawn_c8y_mapper_actor(&ttd, true).await;
+        let TestHandle { mqtt, .. } = test_handle;
+        let mut mqtt = mqtt.with_timeout(TEST_TIMEOUT_MS);
+
+        skip_init_messages(&mut mqtt).await;
+
+        mqtt.send(MqttMessage::new(
+            &Topic::new_unchecked("te/device/main///cmd/command/c8y-mapper-1234"),
+            json!({
+                "status": "failed",
+                "reason": "The command exited with exit status: 2",
+                "command": "ls /missing",
+                "output": "ls: cannot access '/missing': No such file or directory\n",
+                "exitCode": 2
+            })
+            .to_string(),
+        ))
+        .await
+        .expect("Send failed");
+
+        assert_received_contains_str(
+            &mut mqtt,
+            [(
+                "c8y/s/us",
+                "502,c8y_Command,\"The command exited with exit status: 2\nls: cannot access '/missing'",
+            )],
+        )
+        .await;
+    }
+}
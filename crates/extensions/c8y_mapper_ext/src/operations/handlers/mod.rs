@@ -1,5 +1,6 @@
 //! Handling of different types of thin-edge.io operations.
 
+mod command;
 mod config_snapshot;
 mod config_update;
 mod custom_operation;
@@ -55,6 +56,7 @@ use tracing::error;
 pub(super) struct OperationContext {
     pub(super) capabilities: Capabilities,
     pub(super) auto_log_upload: AutoLogUpload,
+    pub(super) max_mqtt_payload_size: u32,
     pub(super) tedge_http_host: Arc<str>,
     pub(super) tmp_dir: Arc<Utf8Path>,
     pub(super) mqtt_schema: MqttSchema,
@@ -88,7 +90,7 @@ impl OperationContext {
         let mut c8y_operation = to_c8y_operation(&operation);
 
         let operation_result = match operation {
-            OperationType::Health => {
+            OperationType::Health | OperationType::CertRenew => {
                 debug!(
                     topic = message.topic.name,
                     ?operation,
@@ -148,7 +150,12 @@ impl OperationContext {
                 self.handle_device_profile_state_change(&entity, &cmd_id, &message)
                     .await
             }
-            OperationType::Custom(_) => {
+            // A command created from an operation template carries the mapper fragment
+            OperationType::Command if command.payload.get(self.command_id.prefix()).is_none() => {
+                self.handle_command_state_change(&entity, &cmd_id, &message)
+                    .await
+            }
+            OperationType::Command | OperationType::Custom(_) => {
                 let (outcome, maybe_c8y_operation) = self
                     .handle_custom_operation_state_change(&entity, &cmd_id, &message)
                     .await;
@@ -333,6 +340,7 @@ fn to_c8y_operation(operation_type: &OperationType) -> Option<CumulocitySupporte
         OperationType::FirmwareUpdate => Some(CumulocitySupportedOperations::C8yFirmware),
         OperationType::SoftwareUpdate => Some(CumulocitySupportedOperations::C8ySoftwareUpdate),
         OperationType::DeviceProfile => Some(CumulocitySupportedOperations::C8yDeviceProfile),
+        OperationType::Command => Some(CumulocitySupportedOperations::C8yCommand),
         // Cannot convert custom operation name systematically
         OperationType::Custom(_) => None,
         // software list is not an c8y, only a fragment, but is a local operation that is spawned as
@@ -340,6 +348,8 @@ fn to_c8y_operation(operation_type: &OperationType) -> Option<CumulocitySupporte
         OperationType::SoftwareList => None,
         // local-only operation, not always invoked by c8y, handled in other codepath
         OperationType::Health => None,
+        // local-only operation, triggered by the agent itself
+        OperationType::CertRenew => None,
     }
 }
 /// An MQTT message that contains an operation payload.
@@ -17,6 +17,8 @@
 //! https://thin-edge.github.io/thin-edge.io/operate/c8y/supported-operations/
 
 mod convert;
+pub use convert::ACKNOWLEDGE_ALARM_OPERATION;
+
 mod error;
 
 mod handler;
@@ -1,6 +1,8 @@
 use crate::converter::create_get_pending_operations_message;
 use crate::entity_cache::CloudEntityMetadata;
 use c8y_api::smartrest;
+use serde_json::json;
+use std::collections::BTreeMap;
 use tedge_api::entity::EntityExternalId;
 use tedge_api::entity::EntityType;
 use tedge_api::mqtt_topics::MqttSchema;
@@ -23,6 +25,14 @@ pub fn is_c8y_bridge_established(
     }
 }
 
+/// The states of the self-checks of a service, indexed by check name
+pub(crate) type HealthChecksState = BTreeMap<String, Status>;
+
+/// Convert a health status message into a service status update
+///
+/// The self-checks of the service are attached to the service managed object,
+/// but only when the set of checks or their states differ from the `sent_checks`,
+/// which are then updated.
 pub(crate) fn convert_health_status_message(
     mqtt_schema: &MqttSchema,
     entity: &CloudEntityMetadata,
@@ -30,13 +40,14 @@ pub(crate) fn convert_health_status_message(
     main_device_xid: &EntityExternalId,
     message: &MqttMessage,
     prefix: &TopicPrefix,
+    sent_checks: &mut HealthChecksState,
 ) -> Vec<MqttMessage> {
     // TODO: introduce type to remove entity type guards
     if entity.metadata.r#type != EntityType::Service {
         return vec![];
     }
 
-    let HealthStatus { status } =
+    let HealthStatus { status, checks } =
         HealthStatus::try_from_health_status_message(message, mqtt_schema).unwrap();
 
     let external_id = entity.external_id.as_ref();
@@ -63,6 +74,23 @@ pub(crate) fn convert_health_status_message(
 
     let mut value = vec![status_message];
 
+    let checks_state: HealthChecksState = checks
+        .iter()
+        .map(|(name, check)| (name.clone(), check.status.clone()))
+        .collect();
+    if checks_state != *sent_checks {
+        // The details of the self-checks are attached to the service managed object
+        *sent_checks = checks_state;
+        let inventory_update_topic = Topic::new_unchecked(&format!(
+            "{prefix}/inventory/managedObjects/update/{external_id}"
+        ));
+        let checks_fragment = json!({ "c8y_HealthChecks": checks });
+        value.push(MqttMessage::new(
+            &inventory_update_topic,
+            checks_fragment.to_string(),
+        ));
+    }
+
     if display_name == format!("mosquitto-{prefix}-bridge") && status == Status::Up {
         // Receiving this message indicates mosquitto has reconnected (following a
         // disconnection) to the cloud. We need to re-request operations in case any
@@ -216,10 +244,87 @@ mod tests {
             &main_device_id.into(),
             &health_message,
             &"c8y".try_into().unwrap(),
+            &mut HealthChecksState::new(),
         );
         assert_eq!(msg[0], expected_message);
     }
 
+    #[test]
+    fn health_checks_are_attached_to_the_service_managed_object() {
+        let topic = Topic::new_unchecked("te/device/main/service/tedge-agent/status/health");
+        let health_message = MqttMessage::new(
+            &topic,
+            r#"{"status":"degraded","checks":{"disk":{"status":"degraded","detail":"1% free space left in /var/tedge"},"mqtt":{"status":"up"}}}"#,
+        );
+        let mqtt_schema = MqttSchema::new();
+        let (entity_topic_id, _) = mqtt_schema.entity_channel_of(&topic).unwrap();
+        let external_id: EntityExternalId = "test_device:device:main:service:tedge-agent".into();
+        let entity = CloudEntityMetadata::new(
+            external_id.clone(),
+            EntityMetadata {
+                topic_id: entity_topic_id,
+                external_id: Some(external_id),
+                r#type: EntityType::Service,
+                parent: None,
+                twin_data: Map::new(),
+            },
+        );
+
+        let mut sent_checks = HealthChecksState::new();
+        let mut convert = |message: &MqttMessage| {
+            convert_health_status_message(
+                &mqtt_schema,
+                &entity,
+                None,
+                &"test_device".into(),
+                message,
+                &"c8y".try_into().unwrap(),
+                &mut sent_checks,
+            )
+        };
+        let messages = convert(&health_message);
+
+        assert_eq!(
+            messages[0].payload_str().unwrap(),
+            "102,test_device:device:main:service:tedge-agent,service,tedge-agent,degraded"
+        );
+        assert_eq!(
+            messages[1].topic.name,
+            "c8y/inventory/managedObjects/update/test_device:device:main:service:tedge-agent"
+        );
+        let fragment: serde_json::Value =
+            serde_json::from_slice(messages[1].payload_bytes()).unwrap();
+        assert_eq!(
+            fragment,
+            json!({"c8y_HealthChecks": {
+                "disk": {"status": "degraded", "detail": "1% free space left in /var/tedge"},
+                "mqtt": {"status": "up"},
+            }})
+        );
+
+        // The checks are not sent again as long as their states are unchanged
+        let same_states = MqttMessage::new(
+            &topic,
+            r#"{"status":"degraded","checks":{"disk":{"status":"degraded","detail":"0.9% free space left in /var/tedge"},"mqtt":{"status":"up"}}}"#,
+        );
+        let messages = convert(&same_states);
+        assert_eq!(messages.len(), 1, "{messages:?}");
+
+        // But are sent as soon as a state changes
+        let new_states = MqttMessage::new(
+            &topic,
+            r#"{"status":"up","checks":{"disk":{"status":"up"},"mqtt":{"status":"up"}}}"#,
+        );
+        let messages = convert(&new_states);
+        assert_eq!(messages.len(), 2, "{messages:?}");
+        let fragment: serde_json::Value =
+            serde_json::from_slice(messages[1].payload_bytes()).unwrap();
+        assert_eq!(
+            fragment,
+            json!({"c8y_HealthChecks": {"disk": {"status": "up"}, "mqtt": {"status": "up"}}})
+        );
+    }
+
     const C8Y_BRIDGE_HEALTH_TOPIC: &str =
         "te/device/main/service/mosquitto-c8y-bridge/status/health";
 
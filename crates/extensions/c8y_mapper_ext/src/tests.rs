@@ -2521,6 +2521,97 @@ async fn mapper_converts_custom_operation_for_child_device() {
     .await;
 }
 
+#[tokio::test]
+async fn mapper_converts_cloud_alarm_acknowledgements_to_acknowledge_alarm_commands() {
+    let ttd = TempTedgeDir::new();
+    let config = C8yMapperConfig {
+        smartrest_use_operation_id: true,
+        ..test_mapper_config(&ttd)
+    };
+    let test_handle = spawn_c8y_mapper_actor_with_config(&ttd, config, true).await;
+    let TestHandle { mqtt, http, .. } = test_handle;
+    spawn_dummy_c8y_http_proxy(http);
+
+    let mut mqtt = mqtt.with_timeout(TEST_TIMEOUT_MS);
+
+    skip_init_messages(&mut mqtt).await;
+
+    // The agent declares the acknowledge_alarm operation when alarm management is enabled
+    mqtt.send(
+        MqttMessage::new(
+            &Topic::new_unchecked("te/device/main///cmd/acknowledge_alarm"),
+            "{}",
+        )
+        .with_retain(),
+    )
+    .await
+    .expect("Send failed");
+    assert_received_contains_str(&mut mqtt, [("c8y/s/us", "114,c8y_AcknowledgeAlarm")]).await;
+
+    // An alarm acknowledged on the cloud
+    let input_message = MqttMessage::new(
+        &Topic::new_unchecked("c8y/devicecontrol/notifications"),
+        json!({
+            "status": "PENDING",
+            "id": "1234",
+            "c8y_AcknowledgeAlarm": {
+                "type": "temperature_high"
+            },
+            "externalSource": {
+                "externalId": "test-device",
+                "type": "c8y_Serial"
+            }
+        })
+        .to_string(),
+    );
+    mqtt.send(input_message).await.expect("Send failed");
+
+    assert_received_includes_json(
+        &mut mqtt,
+        [(
+            "te/device/main///cmd/acknowledge_alarm/c8y-mapper-1234",
+            json!({
+                "status": "init",
+                "alarmType": "temperature_high",
+                "c8y-mapper": {
+                    "on_fragment": "c8y_AcknowledgeAlarm"
+                }
+            }),
+        )],
+    )
+    .await;
+
+    // The agent acknowledges the alarm
+    for status in ["executing", "successful"] {
+        mqtt.send(
+            MqttMessage::new(
+                &Topic::new_unchecked("te/device/main///cmd/acknowledge_alarm/c8y-mapper-1234"),
+                json!({
+                    "status": status,
+                    "alarmType": "temperature_high",
+                    "c8y-mapper": {
+                        "on_fragment": "c8y_AcknowledgeAlarm"
+                    }
+                })
+                .to_string(),
+            )
+            .with_retain(),
+        )
+        .await
+        .expect("Send failed");
+    }
+
+    assert_received_contains_str(&mut mqtt, [("c8y/s/us", "504,1234")]).await;
+    assert_received_contains_str(&mut mqtt, [("c8y/s/us", "506,1234")]).await;
+
+    // The processed command is cleared
+    assert_received_contains_str(
+        &mut mqtt,
+        [("te/device/main///cmd/acknowledge_alarm/c8y-mapper-1234", "")],
+    )
+    .await;
+}
+
 #[tokio::test]
 async fn c8y_mapper_nested_child_alarm_mapping_to_smartrest() {
     let ttd = TempTedgeDir::new();
@@ -3418,6 +3509,7 @@ pub(crate) fn test_mapper_config(tmp_dir: &TempTedgeDir) -> C8yMapperConfig {
         true,
         AutoLogUpload::Never,
         false,
+        false,
         C8Y_MQTT_PAYLOAD_LIMIT,
     )
 }
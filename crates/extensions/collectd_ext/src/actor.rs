@@ -1,4 +1,6 @@
 use crate::collectd::CollectdMessage;
+use crate::rates::RateConverter;
+use crate::types::TypesDb;
 use async_trait::async_trait;
 use log::error;
 use std::convert::Infallible;
@@ -19,7 +21,12 @@ use tedge_mqtt_ext::MqttMessage;
 use tedge_mqtt_ext::TopicFilter;
 
 /// An actor that collects measurements from collectd over MQTT
+///
+/// The collectd types are used to name the values of multi-value metrics,
+/// and to convert the values of counters into rates.
 pub struct CollectdActor {
+    types_db: TypesDb,
+    rates: RateConverter,
     messages: SimpleMessageBox<MqttMessage, CollectdMessage>,
 }
 
@@ -31,7 +38,7 @@ impl Actor for CollectdActor {
 
     async fn run(mut self) -> Result<(), RuntimeError> {
         while let Some(message) = self.messages.recv().await {
-            match CollectdMessage::parse_from(&message) {
+            match CollectdMessage::parse_with(&message, &self.types_db, &mut self.rates) {
                 Ok(collectd_message) => {
                     for msg in collectd_message {
                         self.messages.send(msg).await?
@@ -48,13 +55,15 @@ impl Actor for CollectdActor {
 
 pub struct CollectdActorBuilder {
     topics: TopicFilter,
+    types_db: TypesDb,
     message_box: SimpleMessageBoxBuilder<MqttMessage, CollectdMessage>,
 }
 
 impl CollectdActorBuilder {
-    pub fn new(topics: TopicFilter) -> Self {
+    pub fn new(topics: TopicFilter, types_db: TypesDb) -> Self {
         CollectdActorBuilder {
             topics,
+            types_db,
             message_box: SimpleMessageBoxBuilder::new("Collectd", 16),
         }
     }
@@ -85,6 +94,8 @@ impl Builder<CollectdActor> for CollectdActorBuilder {
 
     fn build(self) -> CollectdActor {
         CollectdActor {
+            types_db: self.types_db,
+            rates: RateConverter::default(),
             messages: self.message_box.build(),
         }
     }
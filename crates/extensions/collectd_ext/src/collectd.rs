@@ -1,4 +1,8 @@
+use crate::rates::RateConverter;
+use crate::types::DataSourceKind;
+use crate::types::TypesDb;
 use batcher::Batchable;
+use log::warn;
 use tedge_api::measurement::MeasurementVisitor;
 use tedge_mqtt_ext::MqttMessage;
 use time::Duration;
@@ -6,6 +10,7 @@ use time::OffsetDateTime;
 
 #[derive(Debug)]
 pub struct CollectdMessage {
+    pub hostname: String,
     pub metric_group_key: String,
     pub metric_key: String,
     pub timestamp: OffsetDateTime,
@@ -47,6 +52,7 @@ impl CollectdMessage {
         timestamp: OffsetDateTime,
     ) -> Self {
         Self {
+            hostname: "localhost".to_string(),
             metric_group_key: metric_group_key.to_string(),
             metric_key: metric_key.to_string(),
             timestamp,
@@ -54,7 +60,29 @@ impl CollectdMessage {
         }
     }
 
+    /// Parse a collectd message, with no knowledge of the collectd types
+    ///
+    /// All the values are handled as gauges and the values of a multi-value metric
+    /// are named after their position: `<metric_key>_val1`, `<metric_key>_val2` ...
     pub fn parse_from(mqtt_message: &MqttMessage) -> Result<Vec<Self>, CollectdError> {
+        CollectdMessage::parse_with(
+            mqtt_message,
+            &TypesDb::default(),
+            &mut RateConverter::default(),
+        )
+    }
+
+    /// Parse a collectd message, using the collectd types to name and convert its values
+    ///
+    /// The values of a multi-value metric are named after their data source:
+    /// e.g. `if_octets_rx` and `if_octets_tx` for an `if_octets` metric.
+    /// The values of counters are converted into rates per second,
+    /// no message being returned for the first value of a counter.
+    pub fn parse_with(
+        mqtt_message: &MqttMessage,
+        types_db: &TypesDb,
+        rates: &mut RateConverter,
+    ) -> Result<Vec<Self>, CollectdError> {
         let topic = mqtt_message.topic.name.as_str();
         let collectd_topic = match CollectdTopic::from_str(topic) {
             Ok(collectd_topic) => collectd_topic,
@@ -71,19 +99,48 @@ impl CollectdMessage {
             .map_err(|err| CollectdError::InvalidMeasurementPayload(topic.into(), err))?;
 
         let num_measurements = collectd_payload.metric_values.len();
+        let data_sources = match types_db.data_sources(collectd_topic.metric_key) {
+            Some(data_sources) if data_sources.len() == num_measurements => Some(data_sources),
+            Some(data_sources) => {
+                warn!(
+                    "Expected {} values on {topic} but received {num_measurements}. Values handled as gauges",
+                    data_sources.len()
+                );
+                None
+            }
+            None => None,
+        };
+
+        let timestamp = collectd_payload.timestamp();
         let mut collectd_messages: Vec<CollectdMessage> = Vec::with_capacity(num_measurements);
 
         for (i, value) in collectd_payload.metric_values.iter().enumerate() {
+            let data_source = data_sources.map(|data_sources| &data_sources[i]);
             let mut metric_key = collectd_topic.metric_key.to_string();
-            // If there are multiple values, then create unique keys metric_key_val1, metric_key_val2 etc.
+            // If there are multiple values, then create unique keys named after the data sources,
+            // or metric_key_val1, metric_key_val2 etc. when the data sources are unknown
             if num_measurements > 1 {
-                metric_key = format!("{}_val{}", metric_key, i + 1);
+                metric_key = match data_source {
+                    Some(data_source) => format!("{}_{}", metric_key, data_source.name),
+                    None => format!("{}_val{}", metric_key, i + 1),
+                };
             }
+
+            let kind = data_source.map_or(DataSourceKind::Gauge, |data_source| data_source.kind);
+            let series = format!(
+                "{}/{}/{}",
+                collectd_topic.hostname, collectd_topic.metric_group_key, metric_key
+            );
+            let Some(metric_value) = rates.convert(&series, kind, *value, timestamp) else {
+                continue;
+            };
+
             collectd_messages.push(CollectdMessage {
+                hostname: collectd_topic.hostname.to_string(),
                 metric_group_key: collectd_topic.metric_group_key.to_string(),
                 metric_key,
-                timestamp: collectd_payload.timestamp(),
-                metric_value: *value,
+                timestamp,
+                metric_value,
             });
         }
         Ok(collectd_messages)
@@ -92,6 +149,7 @@ impl CollectdMessage {
 
 #[derive(Debug, Eq, PartialEq, Hash)]
 pub struct CollectdTopic<'a> {
+    hostname: &'a str,
     metric_group_key: &'a str,
     metric_key: &'a str,
 }
@@ -103,12 +161,13 @@ impl<'a> CollectdTopic<'a> {
     fn from_str(topic_name: &'a str) -> Result<Self, InvalidCollectdTopicName> {
         let mut iter = topic_name.split('/');
         let _collectd_prefix = iter.next().ok_or(InvalidCollectdTopicName)?;
-        let _hostname = iter.next().ok_or(InvalidCollectdTopicName)?;
+        let hostname = iter.next().ok_or(InvalidCollectdTopicName)?;
         let metric_group_key = iter.next().ok_or(InvalidCollectdTopicName)?;
         let metric_key = iter.next().ok_or(InvalidCollectdTopicName)?;
 
         match iter.next() {
             None => Ok(CollectdTopic {
+                hostname,
                 metric_group_key,
                 metric_key,
             }),
@@ -179,7 +238,10 @@ impl Batchable for CollectdMessage {
     type Key = String;
 
     fn key(&self) -> Self::Key {
-        format!("{}/{}", &self.metric_group_key, &self.metric_key)
+        format!(
+            "{}/{}/{}",
+            &self.hostname, &self.metric_group_key, &self.metric_key
+        )
     }
 
     fn event_time(&self) -> OffsetDateTime {
@@ -209,6 +271,7 @@ mod tests {
             metric_key,
             timestamp,
             metric_value,
+            ..
         } = collectd_message.index(0);
         assert_eq!(metric_group_key, "temperature");
 
@@ -229,6 +292,7 @@ mod tests {
             metric_key,
             timestamp,
             metric_value: _,
+            ..
         } = collectd_message.index(0);
         assert_eq!(metric_group_key, "temperature");
 
@@ -240,6 +304,7 @@ mod tests {
             metric_key,
             timestamp,
             metric_value,
+            ..
         } = collectd_message.index(1);
 
         assert_eq!(metric_group_key, "temperature");
@@ -260,6 +325,7 @@ mod tests {
             metric_key,
             timestamp,
             metric_value,
+            ..
         } = collectd_message.index(0);
 
         assert_eq!(metric_group_key, "temperature");
@@ -268,6 +334,104 @@ mod tests {
         assert_eq!(*metric_value, 32.5);
     }
 
+    #[test]
+    fn collectd_message_parsing_keeps_the_hostname() {
+        let topic = Topic::new_unchecked("collectd/sensor01/temperature/value");
+        let mqtt_message = MqttMessage::new(&topic, "123456789:32.5");
+
+        let collectd_message = CollectdMessage::parse_from(&mqtt_message).unwrap();
+
+        assert_eq!(collectd_message[0].hostname, "sensor01");
+    }
+
+    #[test]
+    fn multi_valued_measurements_are_named_after_their_data_sources() {
+        let types_db = TypesDb::parse(
+            "load shortterm:GAUGE:0:5000, midterm:GAUGE:0:5000, longterm:GAUGE:0:5000",
+        );
+        let topic = Topic::new_unchecked("collectd/localhost/load/load");
+        let mqtt_message = MqttMessage::new(&topic, "123456789:0.5:0.25:0.125");
+
+        let collectd_message =
+            CollectdMessage::parse_with(&mqtt_message, &types_db, &mut RateConverter::default())
+                .unwrap();
+
+        let values: Vec<_> = collectd_message
+            .iter()
+            .map(|message| (message.metric_key.as_str(), message.metric_value))
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                ("load_shortterm", 0.5),
+                ("load_midterm", 0.25),
+                ("load_longterm", 0.125)
+            ]
+        );
+    }
+
+    #[test]
+    fn counters_are_sent_as_rates() {
+        let types_db = TypesDb::parse("if_octets rx:DERIVE:0:U, tx:DERIVE:0:U");
+        let mut rates = RateConverter::default();
+        let topic = Topic::new_unchecked("collectd/localhost/interface-eth0/if_octets");
+
+        let first_message = MqttMessage::new(&topic, "123456789:1000:500");
+        let collectd_message =
+            CollectdMessage::parse_with(&first_message, &types_db, &mut rates).unwrap();
+        assert!(collectd_message.is_empty());
+
+        let second_message = MqttMessage::new(&topic, "123456799:6000:1500");
+        let collectd_message =
+            CollectdMessage::parse_with(&second_message, &types_db, &mut rates).unwrap();
+
+        let values: Vec<_> = collectd_message
+            .iter()
+            .map(|message| {
+                (
+                    message.metric_group_key.as_str(),
+                    message.metric_key.as_str(),
+                    message.metric_value,
+                )
+            })
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                ("interface-eth0", "if_octets_rx", 500.0),
+                ("interface-eth0", "if_octets_tx", 100.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn rates_are_computed_per_host() {
+        let types_db = TypesDb::parse("if_octets rx:DERIVE:0:U, tx:DERIVE:0:U");
+        let mut rates = RateConverter::default();
+
+        for host in ["host1", "host2"] {
+            let topic = Topic::new_unchecked(&format!("collectd/{host}/interface-eth0/if_octets"));
+            let mqtt_message = MqttMessage::new(&topic, "123456789:1000:500");
+            let collectd_message =
+                CollectdMessage::parse_with(&mqtt_message, &types_db, &mut rates).unwrap();
+            assert!(collectd_message.is_empty());
+        }
+    }
+
+    #[test]
+    fn unexpected_number_of_values_are_handled_as_gauges() {
+        let types_db = TypesDb::parse("if_octets rx:DERIVE:0:U, tx:DERIVE:0:U");
+        let topic = Topic::new_unchecked("collectd/localhost/interface-eth0/if_octets");
+        let mqtt_message = MqttMessage::new(&topic, "123456789:1000");
+
+        let collectd_message =
+            CollectdMessage::parse_with(&mqtt_message, &types_db, &mut RateConverter::default())
+                .unwrap();
+
+        assert_eq!(collectd_message[0].metric_key, "if_octets");
+        assert_eq!(collectd_message[0].metric_value, 1000.0);
+    }
+
     #[test]
     fn invalid_collectd_message_topic() {
         let topic = Topic::new("collectd/less/level").unwrap();
@@ -0,0 +1,174 @@
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use serde::Deserialize;
+use std::collections::HashMap;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_api::mqtt_topics::TopicIdError;
+
+pub const COLLECTD_CONFIG_FILE_NAME: &str = "collectd.toml";
+
+const DEFAULT_TYPES_DB: &str = "/usr/share/collectd/types.db";
+
+#[derive(thiserror::Error, Debug)]
+pub enum CollectdConfigError {
+    #[error("Failed to read {path}: {source}")]
+    ReadConfig {
+        path: Utf8PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("Invalid collectd mapper configuration {path}: {source}")]
+    InvalidConfig {
+        path: Utf8PathBuf,
+        source: toml::de::Error,
+    },
+
+    #[error("Invalid entity topic id {topic_id:?} for the collectd host {host:?}: {source}")]
+    InvalidEntityTopicId {
+        host: String,
+        topic_id: String,
+        source: TopicIdError,
+    },
+}
+
+/// Configuration of the collectd mapper, as read from `{config_dir}/mappers/collectd.toml`
+///
+/// ```toml
+/// types_db = "/usr/share/collectd/types.db"
+///
+/// [hosts]
+/// sensor01 = "device/sensor01//"
+/// ```
+#[derive(Clone, Debug)]
+pub struct CollectdConfig {
+    /// The collectd `types.db` file defining the data sources of the collectd metrics
+    pub types_db: Utf8PathBuf,
+
+    /// The entities the metrics published by each collectd host are attributed to
+    pub hosts: HashMap<String, EntityTopicId>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CollectdConfigFile {
+    #[serde(default = "default_types_db")]
+    types_db: Utf8PathBuf,
+
+    #[serde(default)]
+    hosts: HashMap<String, String>,
+}
+
+fn default_types_db() -> Utf8PathBuf {
+    DEFAULT_TYPES_DB.into()
+}
+
+impl Default for CollectdConfig {
+    fn default() -> Self {
+        CollectdConfig {
+            types_db: default_types_db(),
+            hosts: HashMap::new(),
+        }
+    }
+}
+
+impl CollectdConfig {
+    /// Read the configuration of the collectd mapper, a missing file defining the default configuration
+    pub fn read(config_dir: &Utf8Path) -> Result<Self, CollectdConfigError> {
+        let path = config_dir.join("mappers").join(COLLECTD_CONFIG_FILE_NAME);
+        match std::fs::read_to_string(&path) {
+            Ok(content) => {
+                let config: CollectdConfigFile = toml::from_str(&content)
+                    .map_err(|source| CollectdConfigError::InvalidConfig { path, source })?;
+                config.try_into()
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(CollectdConfig::default()),
+            Err(source) => Err(CollectdConfigError::ReadConfig { path, source }),
+        }
+    }
+
+    /// The entity the metrics of a collectd host are attributed to
+    ///
+    /// The metrics of the hosts that are not explicitly mapped are attributed to the main device.
+    pub fn entity_of(&self, host: &str) -> EntityTopicId {
+        self.hosts
+            .get(host)
+            .cloned()
+            .unwrap_or_else(EntityTopicId::default_main_device)
+    }
+}
+
+impl TryFrom<CollectdConfigFile> for CollectdConfig {
+    type Error = CollectdConfigError;
+
+    fn try_from(config: CollectdConfigFile) -> Result<Self, Self::Error> {
+        let hosts = config
+            .hosts
+            .into_iter()
+            .map(|(host, topic_id)| match topic_id.parse() {
+                Ok(entity) => Ok((host, entity)),
+                Err(source) => Err(CollectdConfigError::InvalidEntityTopicId {
+                    host,
+                    topic_id,
+                    source,
+                }),
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(CollectdConfig {
+            types_db: config.types_db,
+            hosts,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+    use tedge_test_utils::fs::TempTedgeDir;
+
+    #[test]
+    fn collectd_hosts_are_mapped_to_entities() {
+        let ttd = TempTedgeDir::new();
+        ttd.dir("mappers").file("collectd.toml").with_raw_content(
+            r#"
+            types_db = "/etc/collectd/types.db"
+
+            [hosts]
+            sensor01 = "device/sensor01//"
+            gateway = "device/gateway"
+            "#,
+        );
+
+        let config = CollectdConfig::read(ttd.utf8_path()).unwrap();
+
+        assert_eq!(config.types_db, "/etc/collectd/types.db");
+        assert_eq!(config.entity_of("sensor01"), "device/sensor01//");
+        assert_eq!(config.entity_of("gateway"), "device/gateway//");
+        assert_eq!(config.entity_of("raspberrypi"), "device/main//");
+    }
+
+    #[test]
+    fn a_missing_file_defines_the_default_config() {
+        let ttd = TempTedgeDir::new();
+
+        let config = CollectdConfig::read(ttd.utf8_path()).unwrap();
+
+        assert_eq!(config.types_db, DEFAULT_TYPES_DB);
+        assert!(config.hosts.is_empty());
+    }
+
+    #[test]
+    fn invalid_entity_topic_ids_are_rejected() {
+        let ttd = TempTedgeDir::new();
+        ttd.dir("mappers")
+            .file("collectd.toml")
+            .with_raw_content(r#"hosts = { sensor01 = "device/sensor01/service/too/long" }"#);
+
+        let result = CollectdConfig::read(ttd.utf8_path());
+
+        assert_matches!(
+            result,
+            Err(CollectdConfigError::InvalidEntityTopicId { .. })
+        );
+    }
+}
@@ -1,26 +1,85 @@
 use crate::batcher::MessageBatch;
 use crate::collectd::CollectdMessage;
+use crate::config::CollectdConfig;
 use batcher::BatchDriverOutput;
 use log::error;
+use std::collections::BTreeMap;
+use tedge_api::mqtt_topics::Channel;
+use tedge_api::mqtt_topics::MqttSchema;
 use tedge_mqtt_ext::MqttMessage;
-use tedge_mqtt_ext::Topic;
 
+/// Translate a batch of collectd measurements into thin-edge measurements
+///
+/// The measurements of each collectd host are published on the measurement topic
+/// of the entity this host is mapped to: e.g. `te/device/<child>///m/`.
 pub fn batch_into_mqtt_messages(
-    output_topic: &Topic,
+    mqtt_schema: &MqttSchema,
+    config: &CollectdConfig,
     in_message: BatchDriverOutput<CollectdMessage>,
 ) -> Vec<MqttMessage> {
     match in_message {
         BatchDriverOutput::Batch(measurements) => {
-            match MessageBatch::thin_edge_json(output_topic, measurements) {
-                Ok(message) => {
-                    vec![message]
-                }
-                Err(err) => {
-                    error!("Error while encoding a thin-edge json message: {}", err);
-                    vec![]
+            let mut measurements_per_entity = BTreeMap::new();
+            for measurement in measurements {
+                let entity = config.entity_of(&measurement.hostname);
+                measurements_per_entity
+                    .entry(entity.to_string())
+                    .or_insert_with(|| (entity, Vec::new()))
+                    .1
+                    .push(measurement);
+            }
+
+            let channel = Channel::Measurement {
+                measurement_type: "".to_string(),
+            };
+            let mut messages = Vec::new();
+            for (entity, measurements) in measurements_per_entity.into_values() {
+                let output_topic = mqtt_schema.topic_for(&entity, &channel);
+                match MessageBatch::thin_edge_json(&output_topic, measurements) {
+                    Ok(message) => messages.push(message),
+                    Err(err) => {
+                        error!("Error while encoding a thin-edge json message: {}", err);
+                    }
                 }
             }
+            messages
         }
         BatchDriverOutput::Flush => vec![],
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tedge_api::mqtt_topics::EntityTopicId;
+    use time::macros::datetime;
+
+    #[test]
+    fn measurements_are_published_on_the_topics_of_the_hosts() {
+        let mut config = CollectdConfig::default();
+        config.hosts.insert(
+            "sensor01".to_string(),
+            EntityTopicId::default_child_device("sensor01").unwrap(),
+        );
+        let timestamp = datetime!(2024-05-01 10:00:00 UTC);
+        let mut from_sensor = CollectdMessage::new("temperature", "value", 21.5, timestamp);
+        from_sensor.hostname = "sensor01".to_string();
+        let from_gateway = CollectdMessage::new("cpu", "percent-active", 12.0, timestamp);
+
+        let messages = batch_into_mqtt_messages(
+            &MqttSchema::default(),
+            &config,
+            BatchDriverOutput::Batch(vec![from_sensor, from_gateway]),
+        );
+
+        let topics: Vec<_> = messages
+            .iter()
+            .map(|message| message.topic.name.as_str())
+            .collect();
+        assert_eq!(
+            topics,
+            vec!["te/device/main///m/", "te/device/sensor01///m/"]
+        );
+        assert!(messages[1].payload_str().unwrap().contains("temperature"));
+    }
+}
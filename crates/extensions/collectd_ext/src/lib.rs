@@ -1,5 +1,8 @@
 pub mod actor;
 pub mod batcher;
 pub mod collectd;
+pub mod config;
 pub mod converter;
 pub mod error;
+pub mod rates;
+pub mod types;
@@ -0,0 +1,168 @@
+use crate::types::DataSourceKind;
+use std::collections::HashMap;
+use time::OffsetDateTime;
+
+/// Convert the values of collectd counters into rates per second
+///
+/// The rate of a series is computed from its previous value,
+/// hence no rate is returned for the first value of a series.
+#[derive(Debug, Default)]
+pub struct RateConverter {
+    previous_values: HashMap<String, (f64, OffsetDateTime)>,
+}
+
+impl RateConverter {
+    /// Return the value to be sent for a series, if any
+    ///
+    /// - Gauges are returned unchanged.
+    /// - Counters and derives are returned as the rate of change since their previous value.
+    /// - Absolute values, which are reset when read, are returned divided by the time elapsed.
+    ///
+    /// Values that are not more recent than the previous value of the series are ignored.
+    pub fn convert(
+        &mut self,
+        series: &str,
+        kind: DataSourceKind,
+        value: f64,
+        timestamp: OffsetDateTime,
+    ) -> Option<f64> {
+        if kind == DataSourceKind::Gauge {
+            return Some(value);
+        }
+
+        let (previous_value, previous_timestamp) = self
+            .previous_values
+            .insert(series.to_string(), (value, timestamp))?;
+
+        let elapsed = (timestamp - previous_timestamp).as_seconds_f64();
+        if elapsed <= 0.0 {
+            self.previous_values
+                .insert(series.to_string(), (previous_value, previous_timestamp));
+            return None;
+        }
+
+        let delta = match kind {
+            DataSourceKind::Gauge => unreachable!(),
+            DataSourceKind::Counter => counter_delta(previous_value, value),
+            DataSourceKind::Derive => value - previous_value,
+            DataSourceKind::Absolute => value,
+        };
+        Some(delta / elapsed)
+    }
+}
+
+/// The increase of a counter, assuming the counter wrapped around if it decreased
+///
+/// As collectd, the width of the counter is guessed from the previous value: 32 or 64 bits.
+fn counter_delta(previous_value: f64, value: f64) -> f64 {
+    if value >= previous_value {
+        value - previous_value
+    } else if previous_value <= u32::MAX as f64 {
+        u32::MAX as f64 - previous_value + value + 1.0
+    } else {
+        u64::MAX as f64 - previous_value + value + 1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+    use time::Duration;
+
+    const T0: OffsetDateTime = datetime!(2024-05-01 10:00:00 UTC);
+
+    #[test]
+    fn gauges_are_unchanged() {
+        let mut rates = RateConverter::default();
+
+        assert_eq!(
+            rates.convert("cpu/percent", DataSourceKind::Gauge, 42.0, T0),
+            Some(42.0)
+        );
+    }
+
+    #[test]
+    fn counters_are_converted_into_rates() {
+        let mut rates = RateConverter::default();
+        let series = "interface-eth0/if_octets_rx";
+
+        assert_eq!(
+            rates.convert(series, DataSourceKind::Derive, 1000.0, T0),
+            None
+        );
+        assert_eq!(
+            rates.convert(
+                series,
+                DataSourceKind::Derive,
+                3000.0,
+                T0 + Duration::seconds(10)
+            ),
+            Some(200.0)
+        );
+        assert_eq!(
+            rates.convert(
+                series,
+                DataSourceKind::Derive,
+                3500.0,
+                T0 + Duration::seconds(20)
+            ),
+            Some(50.0)
+        );
+    }
+
+    #[test]
+    fn counters_wrap_around() {
+        let mut rates = RateConverter::default();
+        let series = "interface-eth0/if_packets_rx";
+
+        rates.convert(series, DataSourceKind::Counter, u32::MAX as f64 - 9.0, T0);
+        assert_eq!(
+            rates.convert(
+                series,
+                DataSourceKind::Counter,
+                10.0,
+                T0 + Duration::seconds(10)
+            ),
+            Some(2.0)
+        );
+    }
+
+    #[test]
+    fn absolute_values_are_divided_by_the_elapsed_time() {
+        let mut rates = RateConverter::default();
+        let series = "requests/count";
+
+        rates.convert(series, DataSourceKind::Absolute, 100.0, T0);
+        assert_eq!(
+            rates.convert(
+                series,
+                DataSourceKind::Absolute,
+                50.0,
+                T0 + Duration::seconds(5)
+            ),
+            Some(10.0)
+        );
+    }
+
+    #[test]
+    fn outdated_values_are_ignored() {
+        let mut rates = RateConverter::default();
+        let series = "interface-eth0/if_octets_tx";
+
+        rates.convert(series, DataSourceKind::Derive, 1000.0, T0);
+        assert_eq!(
+            rates.convert(series, DataSourceKind::Derive, 900.0, T0),
+            None
+        );
+        assert_eq!(
+            rates.convert(
+                series,
+                DataSourceKind::Derive,
+                2000.0,
+                T0 + Duration::seconds(10)
+            ),
+            Some(100.0)
+        );
+    }
+}
@@ -0,0 +1,171 @@
+use camino::Utf8Path;
+use log::warn;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// How collectd computes the values of a data source
+///
+/// See the `types.db(5)` manual page of collectd.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DataSourceKind {
+    /// A value that is sent as is
+    Gauge,
+
+    /// An ever increasing value, that might wrap around, and that is sent as a rate
+    Counter,
+
+    /// A value that might increase or decrease, and that is sent as a rate
+    Derive,
+
+    /// A value that is reset when read, and that is sent as a rate
+    Absolute,
+}
+
+impl FromStr for DataSourceKind {
+    type Err = String;
+
+    fn from_str(kind: &str) -> Result<Self, Self::Err> {
+        match kind.to_ascii_uppercase().as_str() {
+            "GAUGE" => Ok(DataSourceKind::Gauge),
+            "COUNTER" => Ok(DataSourceKind::Counter),
+            "DERIVE" => Ok(DataSourceKind::Derive),
+            "ABSOLUTE" => Ok(DataSourceKind::Absolute),
+            _ => Err(format!("unknown data source type: {kind}")),
+        }
+    }
+}
+
+/// One of the values of a collectd type, e.g. `rx` for the `if_octets` type
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DataSource {
+    pub name: String,
+    pub kind: DataSourceKind,
+}
+
+/// The data sources of the collectd types, as defined by a collectd `types.db` file
+///
+/// ```text
+/// if_octets    rx:DERIVE:0:U, tx:DERIVE:0:U
+/// temperature  value:GAUGE:U:U
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct TypesDb {
+    types: HashMap<String, Vec<DataSource>>,
+}
+
+impl TypesDb {
+    /// Read the data sources from a `types.db` file
+    ///
+    /// A missing or unreadable file is not an error but leads to an empty database,
+    /// all the values being then handled as gauges.
+    pub fn read(path: &Utf8Path) -> Self {
+        match std::fs::read_to_string(path) {
+            Ok(content) => TypesDb::parse(&content),
+            Err(err) => {
+                warn!("Cannot read the collectd types from {path}: {err}. All the values are handled as gauges");
+                TypesDb::default()
+            }
+        }
+    }
+
+    /// Parse the content of a `types.db` file, skipping the invalid lines
+    pub fn parse(content: &str) -> Self {
+        let mut types = HashMap::new();
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match TypesDb::parse_line(line) {
+                Ok((type_name, data_sources)) => {
+                    types.insert(type_name.to_string(), data_sources);
+                }
+                Err(err) => warn!("Ignoring line {} of collectd types: {err}", number + 1),
+            }
+        }
+        TypesDb { types }
+    }
+
+    fn parse_line(line: &str) -> Result<(&str, Vec<DataSource>), String> {
+        let (type_name, specs) = line
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| format!("no data source defined: {line}"))?;
+
+        let mut data_sources = Vec::new();
+        for spec in specs
+            .split(',')
+            .map(str::trim)
+            .filter(|spec| !spec.is_empty())
+        {
+            let mut fields = spec.split(':');
+            let (Some(name), Some(kind)) = (fields.next(), fields.next()) else {
+                return Err(format!("invalid data source: {spec}"));
+            };
+            data_sources.push(DataSource {
+                name: name.to_string(),
+                kind: kind.parse()?,
+            });
+        }
+        Ok((type_name, data_sources))
+    }
+
+    /// The data sources of a collectd metric, given its `<type>[-<type_instance>]` key
+    pub fn data_sources(&self, metric_key: &str) -> Option<&[DataSource]> {
+        let type_name = metric_key.split('-').next().unwrap_or(metric_key);
+        self.types.get(type_name).map(Vec::as_slice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TYPES_DB: &str = r#"
+# Extract of /usr/share/collectd/types.db
+if_octets               rx:DERIVE:0:U, tx:DERIVE:0:U
+load                    shortterm:GAUGE:0:5000, midterm:GAUGE:0:5000, longterm:GAUGE:0:5000
+percent                 value:GAUGE:0:100.1
+invalid                 value:RATE:0:U
+"#;
+
+    #[test]
+    fn parse_types_db() {
+        let types = TypesDb::parse(TYPES_DB);
+
+        assert_eq!(
+            types.data_sources("if_octets"),
+            Some(
+                [
+                    DataSource {
+                        name: "rx".to_string(),
+                        kind: DataSourceKind::Derive
+                    },
+                    DataSource {
+                        name: "tx".to_string(),
+                        kind: DataSourceKind::Derive
+                    }
+                ]
+                .as_slice()
+            )
+        );
+        assert_eq!(types.data_sources("load").map(<[_]>::len), Some(3));
+    }
+
+    #[test]
+    fn type_instances_share_the_data_sources_of_their_type() {
+        let types = TypesDb::parse(TYPES_DB);
+
+        assert_eq!(
+            types.data_sources("percent-active"),
+            types.data_sources("percent")
+        );
+    }
+
+    #[test]
+    fn invalid_types_are_ignored() {
+        let types = TypesDb::parse(TYPES_DB);
+
+        assert_eq!(types.data_sources("invalid"), None);
+        assert_eq!(types.data_sources("unknown"), None);
+    }
+}
@@ -0,0 +1,213 @@
+use crate::config::InfluxConfig;
+use crate::line_protocol::parse_lines;
+use crate::line_protocol::Point;
+use crate::measurement::InfluxMeasurement;
+use async_trait::async_trait;
+use std::convert::Infallible;
+use tedge_actors::fan_in_message_type;
+use tedge_actors::Actor;
+use tedge_actors::Builder;
+use tedge_actors::ChannelError;
+use tedge_actors::CloneSender;
+use tedge_actors::DynSender;
+use tedge_actors::MessageReceiver;
+use tedge_actors::MessageSink;
+use tedge_actors::MessageSource;
+use tedge_actors::NoConfig;
+use tedge_actors::RuntimeError;
+use tedge_actors::RuntimeRequest;
+use tedge_actors::RuntimeRequestSink;
+use tedge_actors::Sender;
+use tedge_actors::SimpleMessageBox;
+use tedge_actors::SimpleMessageBoxBuilder;
+use tedge_mqtt_ext::MqttMessage;
+use tedge_mqtt_ext::TopicFilter;
+use time::OffsetDateTime;
+use tracing::error;
+use tracing::warn;
+
+/// Line protocol points received over UDP or HTTP, and already parsed
+#[derive(Debug)]
+pub struct LineProtocolPoints(pub Vec<Point>);
+
+fan_in_message_type!(LineProtocolInput[MqttMessage, LineProtocolPoints] : Debug);
+
+/// An actor that translates line protocol points into measurements of thin-edge entities
+pub struct LineProtocolActor {
+    config: InfluxConfig,
+    messages: SimpleMessageBox<LineProtocolInput, InfluxMeasurement>,
+}
+
+#[async_trait]
+impl Actor for LineProtocolActor {
+    fn name(&self) -> &str {
+        "LineProtocol"
+    }
+
+    async fn run(mut self) -> Result<(), RuntimeError> {
+        while let Some(input) = self.messages.recv().await {
+            match input {
+                LineProtocolInput::MqttMessage(message) => {
+                    self.process_mqtt_message(message).await?
+                }
+                LineProtocolInput::LineProtocolPoints(LineProtocolPoints(points)) => {
+                    self.process_points(points).await?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl LineProtocolActor {
+    async fn process_mqtt_message(&mut self, message: MqttMessage) -> Result<(), ChannelError> {
+        let Ok(payload) = message.payload_str() else {
+            error!("Non UTF-8 line protocol received on {}", message.topic.name);
+            return Ok(());
+        };
+
+        let (points, errors) =
+            parse_lines(payload, self.config.precision, OffsetDateTime::now_utc());
+        for err in errors {
+            error!(
+                "Error while decoding line protocol received on {}: {err}",
+                message.topic.name
+            );
+        }
+        self.process_points(points).await
+    }
+
+    async fn process_points(&mut self, points: Vec<Point>) -> Result<(), ChannelError> {
+        for point in points {
+            let entity = match self.config.entity_of(&point) {
+                Ok(entity) => entity,
+                Err(err) => {
+                    warn!(
+                        "Ignoring a {} point, which tags don't designate an entity: {err}",
+                        point.measurement
+                    );
+                    continue;
+                }
+            };
+            for measurement in InfluxMeasurement::from_point(point, &entity) {
+                self.messages.send(measurement).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct LineProtocolActorBuilder {
+    config: InfluxConfig,
+    message_box: SimpleMessageBoxBuilder<LineProtocolInput, InfluxMeasurement>,
+}
+
+impl LineProtocolActorBuilder {
+    pub fn new(config: InfluxConfig) -> Self {
+        LineProtocolActorBuilder {
+            config,
+            message_box: SimpleMessageBoxBuilder::new("LineProtocol", 16),
+        }
+    }
+
+    pub fn add_input(&mut self, source: &mut impl MessageSource<MqttMessage, TopicFilter>) {
+        source.connect_sink(self.config.topics.clone(), &self.message_box.get_sender())
+    }
+}
+
+impl MessageSink<LineProtocolPoints> for LineProtocolActorBuilder {
+    fn get_sender(&self) -> DynSender<LineProtocolPoints> {
+        self.message_box.get_sender().sender_clone()
+    }
+}
+
+impl RuntimeRequestSink for LineProtocolActorBuilder {
+    fn get_signal_sender(&self) -> DynSender<RuntimeRequest> {
+        self.message_box.get_signal_sender()
+    }
+}
+
+impl MessageSource<InfluxMeasurement, NoConfig> for LineProtocolActorBuilder {
+    fn connect_sink(&mut self, config: NoConfig, peer: &impl MessageSink<InfluxMeasurement>) {
+        self.message_box.connect_sink(config, peer)
+    }
+}
+
+impl Builder<LineProtocolActor> for LineProtocolActorBuilder {
+    type Error = Infallible;
+
+    fn try_build(self) -> Result<LineProtocolActor, Self::Error> {
+        Ok(self.build())
+    }
+
+    fn build(self) -> LineProtocolActor {
+        LineProtocolActor {
+            config: self.config,
+            messages: self.message_box.build(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::measurement::MeasurementValue;
+    use std::time::Duration;
+    use tedge_actors::test_helpers::WithTimeout;
+    use tedge_actors::NoMessage;
+    use tedge_api::mqtt_topics::MqttSchema;
+    use tedge_config::models::TimestampPrecision;
+    use tedge_mqtt_ext::Topic;
+
+    const TEST_TIMEOUT: Duration = Duration::from_secs(3);
+
+    #[tokio::test]
+    async fn points_received_over_mqtt_are_split_into_measurements() {
+        let config = InfluxConfig {
+            mqtt_schema: MqttSchema::default(),
+            topics: TopicFilter::new_unchecked("influx/#"),
+            precision: TimestampPrecision::Seconds,
+            device_tag: "device".to_string(),
+            service_tag: "service".to_string(),
+        };
+        let mut mqtt: SimpleMessageBoxBuilder<MqttMessage, MqttMessage> =
+            SimpleMessageBoxBuilder::new("MQTT", 16);
+        let mut builder = LineProtocolActorBuilder::new(config);
+        builder.add_input(&mut mqtt);
+        let output: SimpleMessageBoxBuilder<InfluxMeasurement, NoMessage> =
+            SimpleMessageBoxBuilder::new("Output", 16);
+        output.connect_source(NoConfig, &mut builder);
+        let actor = builder.build();
+        tokio::spawn(async move { actor.run().await });
+        let mut mqtt = mqtt.build();
+        let mut output = output.build();
+
+        mqtt.send(MqttMessage::new(
+            &Topic::new_unchecked("influx/telegraf"),
+            "env,device=sensor01 temperature=21.5,status=\"ok\" 1714557600\nnot valid",
+        ))
+        .await
+        .unwrap();
+
+        let temperature = output
+            .recv()
+            .with_timeout(TEST_TIMEOUT)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(temperature.entity, "device/sensor01//");
+        assert_eq!(temperature.measurement_type, "env");
+        assert_eq!(temperature.name, "temperature");
+        assert_eq!(temperature.value, MeasurementValue::Number(21.5));
+        assert_eq!(temperature.timestamp.unix_timestamp(), 1714557600);
+
+        let status = output
+            .recv()
+            .with_timeout(TEST_TIMEOUT)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(status.name, "status");
+        assert_eq!(status.value, MeasurementValue::Text("ok".to_string()));
+    }
+}
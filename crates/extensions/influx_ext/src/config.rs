@@ -0,0 +1,103 @@
+use crate::line_protocol::Point;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_api::mqtt_topics::TopicIdError;
+use tedge_config::models::TimestampPrecision;
+use tedge_config::TEdgeConfig;
+use tedge_mqtt_ext::TopicFilter;
+use tracing::warn;
+
+/// Configuration of the InfluxDB line protocol mapper
+#[derive(Clone, Debug)]
+pub struct InfluxConfig {
+    pub mqtt_schema: MqttSchema,
+
+    /// The MQTT topics on which line protocol points are received
+    pub topics: TopicFilter,
+
+    /// The precision of the timestamps of the points received over MQTT and UDP
+    pub precision: TimestampPrecision,
+
+    /// The tag naming the child device a point is attributed to
+    pub device_tag: String,
+
+    /// The tag naming the service a point is attributed to
+    pub service_tag: String,
+}
+
+impl InfluxConfig {
+    pub fn from_tedge_config(tedge_config: &TEdgeConfig) -> Self {
+        let mut topics = TopicFilter::empty();
+        for topic in tedge_config.influx.topics.0.iter() {
+            if topics.add(topic).is_err() {
+                warn!("The configured topic '{topic}' is invalid and ignored.");
+            }
+        }
+
+        InfluxConfig {
+            mqtt_schema: MqttSchema::with_root(tedge_config.mqtt.topic_root.clone()),
+            topics,
+            precision: tedge_config.influx.precision,
+            device_tag: tedge_config.influx.device_tag.clone(),
+            service_tag: tedge_config.influx.service_tag.clone(),
+        }
+    }
+
+    /// The entity a point is attributed to, as given by its device and service tags
+    ///
+    /// The points with no device tag are attributed to the main device or to one of its services.
+    pub fn entity_of(&self, point: &Point) -> Result<EntityTopicId, TopicIdError> {
+        match (point.tag(&self.device_tag), point.tag(&self.service_tag)) {
+            (None, None) => Ok(EntityTopicId::default_main_device()),
+            (Some(device), None) => EntityTopicId::default_child_device(device),
+            (None, Some(service)) => EntityTopicId::default_main_service(service),
+            (Some(device), Some(service)) => EntityTopicId::default_child_service(device, service),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::line_protocol::parse_line;
+    use time::OffsetDateTime;
+
+    fn entity_of(line: &str) -> Result<EntityTopicId, TopicIdError> {
+        let config = InfluxConfig {
+            mqtt_schema: MqttSchema::default(),
+            topics: TopicFilter::new_unchecked("influx/#"),
+            precision: TimestampPrecision::Nanoseconds,
+            device_tag: "device".to_string(),
+            service_tag: "service".to_string(),
+        };
+        let point = parse_line(
+            line,
+            TimestampPrecision::Nanoseconds,
+            OffsetDateTime::now_utc(),
+        )
+        .unwrap();
+        config.entity_of(&point)
+    }
+
+    #[test]
+    fn points_are_attributed_to_entities_by_tags() {
+        assert_eq!(entity_of("cpu usage=1").unwrap(), "device/main//");
+        assert_eq!(
+            entity_of("cpu,device=sensor01 usage=1").unwrap(),
+            "device/sensor01//"
+        );
+        assert_eq!(
+            entity_of("cpu,service=nginx usage=1").unwrap(),
+            "device/main/service/nginx"
+        );
+        assert_eq!(
+            entity_of("cpu,host=gw,service=nginx,device=sensor01 usage=1").unwrap(),
+            "device/sensor01/service/nginx"
+        );
+    }
+
+    #[test]
+    fn invalid_device_names_are_rejected() {
+        assert!(entity_of("cpu,device=a/b usage=1").is_err());
+    }
+}
@@ -0,0 +1,148 @@
+use crate::measurement::InfluxMeasurement;
+use crate::measurement::MeasurementValue;
+use batcher::BatchDriverOutput;
+use std::collections::BTreeMap;
+use tedge_api::measurement::MeasurementVisitor;
+use tedge_api::measurement::ThinEdgeJsonSerializationError;
+use tedge_api::measurement::ThinEdgeJsonSerializer;
+use tedge_api::mqtt_topics::Channel;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_mqtt_ext::MqttMessage;
+use tracing::error;
+
+/// Translate a batch of line protocol measurements into thin-edge measurements
+///
+/// A message is published for each entity and measurement type of the batch,
+/// on the `te/<entity>/m/<measurement type>` topic.
+pub fn batch_into_mqtt_messages(
+    mqtt_schema: &MqttSchema,
+    in_message: BatchDriverOutput<InfluxMeasurement>,
+) -> Vec<MqttMessage> {
+    let BatchDriverOutput::Batch(measurements) = in_message else {
+        return vec![];
+    };
+
+    let mut series = BTreeMap::new();
+    for measurement in measurements {
+        series
+            .entry((
+                measurement.entity.to_string(),
+                measurement.measurement_type.clone(),
+            ))
+            .or_insert_with(Vec::new)
+            .push(measurement);
+    }
+
+    let mut messages = Vec::new();
+    for measurements in series.into_values() {
+        let first = &measurements[0];
+        let channel = Channel::Measurement {
+            measurement_type: first.measurement_type.clone(),
+        };
+        let topic = mqtt_schema.topic_for(&first.entity, &channel);
+        match thin_edge_json(&measurements) {
+            Ok(payload) => messages.push(MqttMessage::new(&topic, payload)),
+            Err(err) => error!("Error while encoding a thin-edge json message: {}", err),
+        }
+    }
+    messages
+}
+
+/// Serialize measurements of the same entity and type, using the timestamp of the first one
+fn thin_edge_json(
+    measurements: &[InfluxMeasurement],
+) -> Result<String, ThinEdgeJsonSerializationError> {
+    let mut serializer = ThinEdgeJsonSerializer::new();
+    if let Some(first) = measurements.first() {
+        serializer.visit_timestamp(first.timestamp)?;
+    }
+    for measurement in measurements {
+        match &measurement.value {
+            MeasurementValue::Number(value) => {
+                serializer.visit_measurement(&measurement.name, *value)?
+            }
+            MeasurementValue::Text(value) => {
+                serializer.visit_text_property(&measurement.name, value)?
+            }
+        }
+    }
+    serializer.into_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tedge_api::mqtt_topics::EntityTopicId;
+    use time::macros::datetime;
+
+    fn measurement(
+        entity: &str,
+        measurement_type: &str,
+        name: &str,
+        value: MeasurementValue,
+    ) -> InfluxMeasurement {
+        InfluxMeasurement {
+            entity: entity.parse::<EntityTopicId>().unwrap(),
+            measurement_type: measurement_type.to_string(),
+            name: name.to_string(),
+            value,
+            timestamp: datetime!(2024-05-01 10:00:00 UTC),
+        }
+    }
+
+    #[test]
+    fn measurements_are_grouped_per_entity_and_type() {
+        let batch = vec![
+            measurement(
+                "device/main//",
+                "cpu",
+                "usage",
+                MeasurementValue::Number(12.5),
+            ),
+            measurement(
+                "device/sensor01//",
+                "env",
+                "temperature",
+                MeasurementValue::Number(21.0),
+            ),
+            measurement(
+                "device/main//",
+                "cpu",
+                "idle",
+                MeasurementValue::Number(87.5),
+            ),
+            measurement(
+                "device/sensor01//",
+                "env",
+                "status",
+                MeasurementValue::Text("ok".to_string()),
+            ),
+        ];
+
+        let messages =
+            batch_into_mqtt_messages(&MqttSchema::default(), BatchDriverOutput::Batch(batch));
+
+        let messages: Vec<_> = messages
+            .iter()
+            .map(|message| {
+                let payload: serde_json::Value =
+                    serde_json::from_str(message.payload_str().unwrap()).unwrap();
+                (message.topic.name.as_str(), payload)
+            })
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                (
+                    "te/device/main///m/cpu",
+                    json!({"time": "2024-05-01T10:00:00Z", "usage": 12.5, "idle": 87.5})
+                ),
+                (
+                    "te/device/sensor01///m/env",
+                    json!({"time": "2024-05-01T10:00:00Z", "temperature": 21.0, "status": "ok"})
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,249 @@
+//! An InfluxDB compatible HTTP endpoint, so tools such as Telegraf can write points to the mapper
+//!
+//! Both the InfluxDB 1.x `/write` and 2.x `/api/v2/write` endpoints are served,
+//! the other parameters of these endpoints (database, bucket, organization, credentials) being ignored.
+use crate::actor::LineProtocolPoints;
+use crate::line_protocol::parse_lines;
+use anyhow::Context;
+use async_trait::async_trait;
+use axum::extract::Query;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use axum::routing::post;
+use axum::Json;
+use axum::Router;
+use futures::channel::mpsc;
+use futures::FutureExt;
+use futures::StreamExt;
+use serde::Deserialize;
+use serde_json::json;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use tedge_actors::Actor;
+use tedge_actors::Builder;
+use tedge_actors::CloneSender;
+use tedge_actors::DynSender;
+use tedge_actors::MessageSink;
+use tedge_actors::RuntimeError;
+use tedge_actors::RuntimeRequest;
+use tedge_actors::RuntimeRequestSink;
+use tedge_actors::Sender;
+use tedge_config::models::TimestampPrecision;
+use time::OffsetDateTime;
+use tokio::net::TcpListener;
+use tracing::info;
+
+pub struct HttpServerActor {
+    listener: TcpListener,
+    state: WriteState,
+    signal_receiver: mpsc::Receiver<RuntimeRequest>,
+}
+
+struct WriteState {
+    precision: TimestampPrecision,
+    points: DynSender<LineProtocolPoints>,
+}
+
+impl Clone for WriteState {
+    fn clone(&self) -> Self {
+        WriteState {
+            precision: self.precision,
+            points: self.points.sender_clone(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct WriteParams {
+    precision: Option<String>,
+}
+
+#[async_trait]
+impl Actor for HttpServerActor {
+    fn name(&self) -> &str {
+        "LineProtocolHttpServer"
+    }
+
+    async fn run(mut self) -> Result<(), RuntimeError> {
+        let listener = self.listener.into_std().map_err(Box::new)?;
+        let router = Router::new()
+            .route("/write", post(write))
+            .route("/api/v2/write", post(write))
+            .with_state(self.state);
+        let server = axum_server::from_tcp(listener)
+            .serve(router.into_make_service())
+            .boxed();
+
+        tokio::select! {
+            result = server => {
+                info!("Done");
+                Ok(result.map_err(Box::new)?)
+            }
+            Some(RuntimeRequest::Shutdown) = self.signal_receiver.next() => {
+                info!("Shutdown");
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Forward the valid points of the request body, rejecting the request if any line is invalid
+///
+/// As InfluxDB, the valid points are kept even if the request is rejected (a partial write).
+async fn write(
+    State(mut state): State<WriteState>,
+    Query(params): Query<WriteParams>,
+    body: String,
+) -> Response {
+    let precision = match params.precision.as_deref().map(parse_precision) {
+        None => state.precision,
+        Some(Some(precision)) => precision,
+        Some(None) => {
+            return bad_request(format!(
+                "invalid precision: {}",
+                params.precision.unwrap_or_default()
+            ))
+        }
+    };
+
+    let (points, errors) = parse_lines(&body, precision, OffsetDateTime::now_utc());
+    if !points.is_empty() && state.points.send(LineProtocolPoints(points)).await.is_err() {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    }
+
+    match errors.first() {
+        None => StatusCode::NO_CONTENT.into_response(),
+        Some(err) => bad_request(format!("partial write: {err}")),
+    }
+}
+
+fn bad_request(error: String) -> Response {
+    (StatusCode::BAD_REQUEST, Json(json!({ "error": error }))).into_response()
+}
+
+/// Parse the precision parameter, accepting the values of both InfluxDB 1.x and 2.x
+fn parse_precision(precision: &str) -> Option<TimestampPrecision> {
+    match precision {
+        "n" | "ns" => Some(TimestampPrecision::Nanoseconds),
+        "u" | "us" => Some(TimestampPrecision::Microseconds),
+        "ms" => Some(TimestampPrecision::Milliseconds),
+        "s" => Some(TimestampPrecision::Seconds),
+        _ => None,
+    }
+}
+
+pub struct HttpServerBuilder {
+    listener: TcpListener,
+    state: WriteState,
+    signal_sender: mpsc::Sender<RuntimeRequest>,
+    signal_receiver: mpsc::Receiver<RuntimeRequest>,
+}
+
+impl HttpServerBuilder {
+    pub async fn try_bind(
+        bind_addr: SocketAddr,
+        precision: TimestampPrecision,
+        points: &impl MessageSink<LineProtocolPoints>,
+    ) -> Result<Self, anyhow::Error> {
+        let listener = TcpListener::bind(bind_addr)
+            .await
+            .with_context(|| format!("Binding line protocol HTTP server to {bind_addr}"))?;
+        let (signal_sender, signal_receiver) = mpsc::channel(10);
+
+        Ok(HttpServerBuilder {
+            listener,
+            state: WriteState {
+                precision,
+                points: points.get_sender(),
+            },
+            signal_sender,
+            signal_receiver,
+        })
+    }
+
+    /// The address the server is actually bound to
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+}
+
+impl RuntimeRequestSink for HttpServerBuilder {
+    fn get_signal_sender(&self) -> DynSender<RuntimeRequest> {
+        Box::new(self.signal_sender.clone())
+    }
+}
+
+impl Builder<HttpServerActor> for HttpServerBuilder {
+    type Error = Infallible;
+
+    fn try_build(self) -> Result<HttpServerActor, Self::Error> {
+        Ok(HttpServerActor {
+            listener: self.listener,
+            state: self.state,
+            signal_receiver: self.signal_receiver,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+    use std::time::Duration;
+    use tedge_actors::test_helpers::WithTimeout;
+    use tedge_actors::MessageReceiver;
+    use tedge_actors::NoMessage;
+    use tedge_actors::SimpleMessageBoxBuilder;
+
+    #[tokio::test]
+    async fn points_are_written_over_http() {
+        let receiver: SimpleMessageBoxBuilder<LineProtocolPoints, NoMessage> =
+            SimpleMessageBoxBuilder::new("Points", 16);
+        let builder = HttpServerBuilder::try_bind(
+            SocketAddr::from((Ipv4Addr::LOCALHOST, 0)),
+            TimestampPrecision::Nanoseconds,
+            &receiver,
+        )
+        .await
+        .unwrap();
+        let addr = builder.local_addr().unwrap();
+        tokio::spawn(builder.build().run());
+        let mut receiver = receiver.build();
+        #[allow(clippy::disallowed_methods)]
+        let client = reqwest::Client::new();
+
+        let response = client
+            .post(format!("http://{addr}/write?db=telegraf&precision=s"))
+            .body("cpu usage=12.5 1714557600")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NO_CONTENT);
+
+        let LineProtocolPoints(points) = receiver
+            .recv()
+            .with_timeout(Duration::from_secs(3))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(points[0].timestamp.unix_timestamp(), 1714557600);
+
+        let response = client
+            .post(format!("http://{addr}/api/v2/write?bucket=tedge"))
+            .body("cpu usage=12.5\ncpu usage")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+
+        let LineProtocolPoints(points) = receiver
+            .recv()
+            .with_timeout(Duration::from_secs(3))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(points.len(), 1);
+    }
+}
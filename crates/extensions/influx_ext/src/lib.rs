@@ -0,0 +1,12 @@
+//! Translate InfluxDB line protocol points into thin-edge measurements
+//!
+//! The points are received over MQTT and, optionally, over UDP and HTTP.
+//! Each point is split into measurements, one per field, that are batched
+//! and published on the `te/<entity>/m/<measurement>` topic of the entity designated by the point tags.
+pub mod actor;
+pub mod config;
+pub mod converter;
+pub mod http;
+pub mod line_protocol;
+pub mod measurement;
+pub mod udp;
@@ -0,0 +1,346 @@
+//! Parser of the [InfluxDB line protocol](https://docs.influxdata.com/influxdb/v1/write_protocols/line_protocol_reference/)
+//!
+//! ```text
+//! <measurement>[,<tag_key>=<tag_value>...] <field_key>=<field_value>[,<field_key>=<field_value>...] [<timestamp>]
+//! ```
+use tedge_config::models::TimestampPrecision;
+use time::OffsetDateTime;
+
+/// A point, i.e. a line of line protocol
+#[derive(Clone, Debug, PartialEq)]
+pub struct Point {
+    pub measurement: String,
+    pub tags: Vec<(String, String)>,
+    pub fields: Vec<(String, FieldValue)>,
+    pub timestamp: OffsetDateTime,
+}
+
+impl Point {
+    pub fn tag(&self, key: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == key)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum FieldValue {
+    Float(f64),
+    Integer(i64),
+    UInteger(u64),
+    String(String),
+    Boolean(bool),
+}
+
+#[derive(thiserror::Error, Debug, Eq, PartialEq)]
+pub enum LineProtocolError {
+    #[error("Missing measurement name in line: {0}")]
+    MissingMeasurement(String),
+
+    #[error("Missing fields in line: {0}")]
+    MissingFields(String),
+
+    #[error("Invalid tag {tag:?} in line: {line}")]
+    InvalidTag { tag: String, line: String },
+
+    #[error("Invalid field {field:?} in line: {line}")]
+    InvalidField { field: String, line: String },
+
+    #[error("Invalid timestamp {timestamp:?} in line: {line}")]
+    InvalidTimestamp { timestamp: String, line: String },
+}
+
+/// Parse a batch of lines, returning the valid points along with the errors of the invalid lines
+///
+/// The timestamps are given with the precision, the points with no timestamp being timestamped with `now`.
+/// Empty lines and comments, starting with `#`, are ignored.
+pub fn parse_lines(
+    input: &str,
+    precision: TimestampPrecision,
+    now: OffsetDateTime,
+) -> (Vec<Point>, Vec<LineProtocolError>) {
+    let mut points = Vec::new();
+    let mut errors = Vec::new();
+    for line in input.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match parse_line(line, precision, now) {
+            Ok(point) => points.push(point),
+            Err(err) => errors.push(err),
+        }
+    }
+    (points, errors)
+}
+
+/// Parse a single line of line protocol
+pub fn parse_line(
+    line: &str,
+    precision: TimestampPrecision,
+    now: OffsetDateTime,
+) -> Result<Point, LineProtocolError> {
+    // The quotes are only meaningful in the field set, where they delimit string values
+    let series = split_unescaped(line, ' ', false)
+        .into_iter()
+        .next()
+        .unwrap_or_default();
+    let rest = line.get(series.len() + 1..).unwrap_or_default();
+    let sections = split_unescaped(rest, ' ', true);
+    let mut sections = sections.into_iter().filter(|section| !section.is_empty());
+    let field_set = sections
+        .next()
+        .ok_or_else(|| LineProtocolError::MissingFields(line.to_string()))?;
+    let timestamp = match sections.next() {
+        None => now,
+        Some(timestamp) => parse_timestamp(timestamp, precision).ok_or_else(|| {
+            LineProtocolError::InvalidTimestamp {
+                timestamp: timestamp.to_string(),
+                line: line.to_string(),
+            }
+        })?,
+    };
+    if let Some(unexpected) = sections.next() {
+        return Err(LineProtocolError::InvalidTimestamp {
+            timestamp: unexpected.to_string(),
+            line: line.to_string(),
+        });
+    }
+
+    let mut series = split_unescaped(series, ',', false).into_iter();
+    let measurement = unescape(series.next().unwrap_or_default());
+    if measurement.is_empty() {
+        return Err(LineProtocolError::MissingMeasurement(line.to_string()));
+    }
+
+    let mut tags = Vec::new();
+    for tag in series {
+        match split_key_value(tag) {
+            Some((key, value)) if !key.is_empty() && !value.is_empty() => {
+                tags.push((unescape(key), unescape(value)))
+            }
+            _ => {
+                return Err(LineProtocolError::InvalidTag {
+                    tag: tag.to_string(),
+                    line: line.to_string(),
+                })
+            }
+        }
+    }
+
+    let mut fields = Vec::new();
+    for field in split_unescaped(field_set, ',', true) {
+        let invalid_field = || LineProtocolError::InvalidField {
+            field: field.to_string(),
+            line: line.to_string(),
+        };
+        let (key, value) = split_key_value(field).ok_or_else(invalid_field)?;
+        if key.is_empty() {
+            return Err(invalid_field());
+        }
+        let value = parse_field_value(value).ok_or_else(invalid_field)?;
+        fields.push((unescape(key), value));
+    }
+
+    Ok(Point {
+        measurement,
+        tags,
+        fields,
+        timestamp,
+    })
+}
+
+fn parse_field_value(value: &str) -> Option<FieldValue> {
+    if let Some(quoted) = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+    {
+        return Some(FieldValue::String(unescape(quoted)));
+    }
+    if let Some(integer) = value.strip_suffix('i') {
+        return integer.parse().ok().map(FieldValue::Integer);
+    }
+    if let Some(unsigned) = value.strip_suffix('u') {
+        return unsigned.parse().ok().map(FieldValue::UInteger);
+    }
+    match value {
+        "t" | "T" | "true" | "True" | "TRUE" => Some(FieldValue::Boolean(true)),
+        "f" | "F" | "false" | "False" | "FALSE" => Some(FieldValue::Boolean(false)),
+        _ => value
+            .parse()
+            .ok()
+            .filter(|value: &f64| value.is_finite())
+            .map(FieldValue::Float),
+    }
+}
+
+fn parse_timestamp(timestamp: &str, precision: TimestampPrecision) -> Option<OffsetDateTime> {
+    let timestamp: i128 = timestamp.parse().ok()?;
+    let nanos_per_unit = match precision {
+        TimestampPrecision::Nanoseconds => 1,
+        TimestampPrecision::Microseconds => 1_000,
+        TimestampPrecision::Milliseconds => 1_000_000,
+        TimestampPrecision::Seconds => 1_000_000_000,
+    };
+    OffsetDateTime::from_unix_timestamp_nanos(timestamp.checked_mul(nanos_per_unit)?).ok()
+}
+
+/// Split `input` on the separators that are neither escaped nor, if `quotes` is set, within double quotes
+fn split_unescaped(input: &str, separator: char, quotes: bool) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    let mut quoted = false;
+    for (i, c) in input.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == '"' && quotes {
+            quoted = !quoted;
+        } else if c == separator && !quoted {
+            parts.push(&input[start..i]);
+            start = i + c.len_utf8();
+        }
+    }
+    parts.push(&input[start..]);
+    parts
+}
+
+fn split_key_value(input: &str) -> Option<(&str, &str)> {
+    let mut parts = split_unescaped(input, '=', false).into_iter();
+    let key = parts.next()?;
+    let value = input.get(key.len() + 1..)?;
+    Some((key, value))
+}
+
+fn unescape(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some(escaped @ (',' | '=' | ' ' | '"' | '\\')) => output.push(escaped),
+                Some(other) => {
+                    output.push('\\');
+                    output.push(other);
+                }
+                None => output.push('\\'),
+            }
+        } else {
+            output.push(c);
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    const NOW: OffsetDateTime = datetime!(2024-05-01 10:00:00 UTC);
+
+    fn parse(line: &str) -> Result<Point, LineProtocolError> {
+        parse_line(line, TimestampPrecision::Nanoseconds, NOW)
+    }
+
+    #[test]
+    fn parse_point() {
+        let point =
+            parse("weather,location=us-midwest,season=summer temperature=82,humidity=71i 1465839830100400200")
+                .unwrap();
+
+        assert_eq!(point.measurement, "weather");
+        assert_eq!(point.tag("location"), Some("us-midwest"));
+        assert_eq!(point.tag("season"), Some("summer"));
+        assert_eq!(
+            point.fields,
+            vec![
+                ("temperature".to_string(), FieldValue::Float(82.0)),
+                ("humidity".to_string(), FieldValue::Integer(71))
+            ]
+        );
+        assert_eq!(point.timestamp, datetime!(2016-06-13 17:43:50.1004002 UTC));
+    }
+
+    #[test]
+    fn points_without_timestamp_are_timestamped_on_reception() {
+        let point = parse("cpu usage=12.5").unwrap();
+
+        assert_eq!(point.tags, vec![]);
+        assert_eq!(point.timestamp, NOW);
+    }
+
+    #[test]
+    fn timestamps_are_given_with_the_precision() {
+        let point = parse_line(
+            "cpu usage=12.5 1465839830",
+            TimestampPrecision::Seconds,
+            NOW,
+        )
+        .unwrap();
+        assert_eq!(point.timestamp, datetime!(2016-06-13 17:43:50 UTC));
+
+        let point = parse_line(
+            "cpu usage=12.5 1465839830100",
+            TimestampPrecision::Milliseconds,
+            NOW,
+        )
+        .unwrap();
+        assert_eq!(point.timestamp, datetime!(2016-06-13 17:43:50.1 UTC));
+    }
+
+    #[test]
+    fn parse_field_types() {
+        let point = parse(r#"sensor a=1.5,b=-2i,c=3u,d=t,e=FALSE,f="on, \"really\"""#).unwrap();
+
+        assert_eq!(
+            point.fields,
+            vec![
+                ("a".to_string(), FieldValue::Float(1.5)),
+                ("b".to_string(), FieldValue::Integer(-2)),
+                ("c".to_string(), FieldValue::UInteger(3)),
+                ("d".to_string(), FieldValue::Boolean(true)),
+                ("e".to_string(), FieldValue::Boolean(false)),
+                (
+                    "f".to_string(),
+                    FieldValue::String(r#"on, "really""#.to_string())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_escaped_characters() {
+        let point = parse(r#"disk\ io,path=/var/lib\,tedge,label=a\=b read\ bytes=1024i"#).unwrap();
+
+        assert_eq!(point.measurement, "disk io");
+        assert_eq!(point.tag("path"), Some("/var/lib,tedge"));
+        assert_eq!(point.tag("label"), Some("a=b"));
+        assert_eq!(
+            point.fields,
+            vec![("read bytes".to_string(), FieldValue::Integer(1024))]
+        );
+    }
+
+    #[test]
+    fn invalid_lines_are_reported() {
+        let (points, errors) = parse_lines(
+            "# a comment\n\ncpu usage=12.5\ncpu\ncpu usage=abc\ncpu,host usage=1\ncpu usage=1 yesterday\n",
+            TimestampPrecision::Nanoseconds,
+            NOW,
+        );
+
+        assert_eq!(points.len(), 1);
+        assert!(matches!(
+            errors.as_slice(),
+            [
+                LineProtocolError::MissingFields(_),
+                LineProtocolError::InvalidField { .. },
+                LineProtocolError::InvalidTag { .. },
+                LineProtocolError::InvalidTimestamp { .. },
+            ]
+        ));
+    }
+}
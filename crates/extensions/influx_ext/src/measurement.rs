@@ -0,0 +1,70 @@
+use crate::line_protocol::FieldValue;
+use crate::line_protocol::Point;
+use batcher::Batchable;
+use tedge_api::mqtt_topics::EntityTopicId;
+use time::OffsetDateTime;
+
+/// A single field of a line protocol point, attributed to an entity
+#[derive(Debug)]
+pub struct InfluxMeasurement {
+    pub entity: EntityTopicId,
+    pub measurement_type: String,
+    pub name: String,
+    pub value: MeasurementValue,
+    pub timestamp: OffsetDateTime,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MeasurementValue {
+    Number(f64),
+    Text(String),
+}
+
+impl From<FieldValue> for MeasurementValue {
+    fn from(value: FieldValue) -> Self {
+        match value {
+            FieldValue::Float(value) => MeasurementValue::Number(value),
+            FieldValue::Integer(value) => MeasurementValue::Number(value as f64),
+            FieldValue::UInteger(value) => MeasurementValue::Number(value as f64),
+            FieldValue::Boolean(value) => MeasurementValue::Number(if value { 1.0 } else { 0.0 }),
+            FieldValue::String(value) => MeasurementValue::Text(value),
+        }
+    }
+}
+
+impl InfluxMeasurement {
+    /// Split a point into measurements of the given entity, one per field
+    ///
+    /// The type of the measurements is the name of the point measurement.
+    /// Booleans are translated into 0 and 1, and strings into text properties.
+    pub fn from_point(point: Point, entity: &EntityTopicId) -> Vec<Self> {
+        let Point {
+            measurement,
+            fields,
+            timestamp,
+            ..
+        } = point;
+        fields
+            .into_iter()
+            .map(|(name, value)| InfluxMeasurement {
+                entity: entity.clone(),
+                measurement_type: measurement.clone(),
+                name,
+                value: value.into(),
+                timestamp,
+            })
+            .collect()
+    }
+}
+
+impl Batchable for InfluxMeasurement {
+    type Key = String;
+
+    fn key(&self) -> Self::Key {
+        format!("{}/{}/{}", self.entity, self.measurement_type, self.name)
+    }
+
+    fn event_time(&self) -> OffsetDateTime {
+        self.timestamp
+    }
+}
@@ -0,0 +1,159 @@
+use crate::actor::LineProtocolPoints;
+use crate::line_protocol::parse_lines;
+use async_trait::async_trait;
+use futures::channel::mpsc;
+use futures::StreamExt;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use tedge_actors::Actor;
+use tedge_actors::Builder;
+use tedge_actors::DynSender;
+use tedge_actors::MessageSink;
+use tedge_actors::RuntimeError;
+use tedge_actors::RuntimeRequest;
+use tedge_actors::RuntimeRequestSink;
+use tedge_actors::Sender;
+use tedge_config::models::TimestampPrecision;
+use time::OffsetDateTime;
+use tokio::net::UdpSocket;
+use tracing::error;
+use tracing::info;
+
+/// The maximum size of a datagram, as for the UDP service of InfluxDB
+const MAX_DATAGRAM_SIZE: usize = 64 * 1024;
+
+/// Receive line protocol points over UDP, one or several lines per datagram
+pub struct UdpServerActor {
+    socket: UdpSocket,
+    precision: TimestampPrecision,
+    points: DynSender<LineProtocolPoints>,
+    signal_receiver: mpsc::Receiver<RuntimeRequest>,
+}
+
+#[async_trait]
+impl Actor for UdpServerActor {
+    fn name(&self) -> &str {
+        "LineProtocolUdpServer"
+    }
+
+    async fn run(mut self) -> Result<(), RuntimeError> {
+        let mut buffer = vec![0u8; MAX_DATAGRAM_SIZE];
+        loop {
+            tokio::select! {
+                received = self.socket.recv_from(&mut buffer) => {
+                    let (size, peer) = received.map_err(Box::new)?;
+                    let Ok(datagram) = std::str::from_utf8(&buffer[..size]) else {
+                        error!("Non UTF-8 line protocol received from {peer}");
+                        continue;
+                    };
+                    let (points, errors) =
+                        parse_lines(datagram, self.precision, OffsetDateTime::now_utc());
+                    for err in errors {
+                        error!("Error while decoding line protocol received from {peer}: {err}");
+                    }
+                    if !points.is_empty() {
+                        self.points.send(LineProtocolPoints(points)).await?;
+                    }
+                }
+                Some(RuntimeRequest::Shutdown) = self.signal_receiver.next() => {
+                    info!("Shutdown");
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+pub struct UdpServerBuilder {
+    socket: UdpSocket,
+    precision: TimestampPrecision,
+    points: DynSender<LineProtocolPoints>,
+    signal_sender: mpsc::Sender<RuntimeRequest>,
+    signal_receiver: mpsc::Receiver<RuntimeRequest>,
+}
+
+impl UdpServerBuilder {
+    pub async fn try_bind(
+        bind_addr: SocketAddr,
+        precision: TimestampPrecision,
+        points: &impl MessageSink<LineProtocolPoints>,
+    ) -> Result<Self, std::io::Error> {
+        let socket = UdpSocket::bind(bind_addr).await?;
+        let (signal_sender, signal_receiver) = mpsc::channel(10);
+        Ok(UdpServerBuilder {
+            socket,
+            precision,
+            points: points.get_sender(),
+            signal_sender,
+            signal_receiver,
+        })
+    }
+
+    /// The address the socket is actually bound to
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+}
+
+impl RuntimeRequestSink for UdpServerBuilder {
+    fn get_signal_sender(&self) -> DynSender<RuntimeRequest> {
+        Box::new(self.signal_sender.clone())
+    }
+}
+
+impl Builder<UdpServerActor> for UdpServerBuilder {
+    type Error = Infallible;
+
+    fn try_build(self) -> Result<UdpServerActor, Self::Error> {
+        Ok(UdpServerActor {
+            socket: self.socket,
+            precision: self.precision,
+            points: self.points,
+            signal_receiver: self.signal_receiver,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+    use std::time::Duration;
+    use tedge_actors::test_helpers::WithTimeout;
+    use tedge_actors::MessageReceiver;
+    use tedge_actors::NoMessage;
+    use tedge_actors::SimpleMessageBoxBuilder;
+
+    #[tokio::test]
+    async fn points_are_received_over_udp() {
+        let receiver: SimpleMessageBoxBuilder<LineProtocolPoints, NoMessage> =
+            SimpleMessageBoxBuilder::new("Points", 16);
+        let builder = UdpServerBuilder::try_bind(
+            SocketAddr::from((Ipv4Addr::LOCALHOST, 0)),
+            TimestampPrecision::Seconds,
+            &receiver,
+        )
+        .await
+        .unwrap();
+        let addr = builder.local_addr().unwrap();
+        tokio::spawn(builder.build().run());
+        let mut receiver = receiver.build();
+
+        let client = UdpSocket::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+            .await
+            .unwrap();
+        client
+            .send_to(b"cpu usage=12.5 1714557600\ncpu idle=87.5 1714557600", addr)
+            .await
+            .unwrap();
+
+        let LineProtocolPoints(points) = receiver
+            .recv()
+            .with_timeout(Duration::from_secs(3))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[0].timestamp.unix_timestamp(), 1714557600);
+    }
+}
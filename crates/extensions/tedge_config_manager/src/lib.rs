@@ -18,6 +18,7 @@ use tedge_actors::MappingSender;
 use tedge_actors::MessageSink;
 use tedge_actors::MessageSource;
 use tedge_actors::NoConfig;
+use tedge_actors::RestartableBuilder;
 use tedge_actors::RuntimeRequest;
 use tedge_actors::RuntimeRequestSink;
 use tedge_actors::Service;
@@ -194,6 +195,26 @@ impl Builder<ConfigManagerActor> for ConfigManagerBuilder {
     }
 }
 
+/// When restarted after a failure, the config manager reloads its plugin config
+/// and takes over the message box of the failed instance.
+impl RestartableBuilder<ConfigManagerActor> for ConfigManagerBuilder {
+    type Error = LinkError;
+
+    fn rebuild(&mut self) -> Result<ConfigManagerActor, Self::Error> {
+        let (output_sender, input_receiver) = self.box_builder.rebuild()?.into_split();
+        let plugin_config = PluginConfig::new(self.config.plugin_config_path.as_path());
+
+        Ok(ConfigManagerActor::new(
+            self.config.clone(),
+            plugin_config,
+            input_receiver,
+            output_sender,
+            self.downloader.clone(),
+            self.uploader.clone(),
+        ))
+    }
+}
+
 impl MessageSource<GenericCommandData, NoConfig> for ConfigManagerBuilder {
     fn connect_sink(&mut self, config: NoConfig, peer: &impl MessageSink<GenericCommandData>) {
         self.box_builder.connect_mapped_sink(
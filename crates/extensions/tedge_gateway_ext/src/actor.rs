@@ -0,0 +1,973 @@
+use crate::coap;
+use crate::coap::Code;
+use crate::coap::MessageType;
+use crate::coap::RecentExchanges;
+use crate::config::GatewayConfig;
+use crate::error::GatewayInitError;
+use crate::gateway::ClientCommand;
+use crate::gateway::Gateway;
+use crate::mqttsn::Packet;
+use crate::mqttsn::PublishFlags;
+use crate::mqttsn::QoS;
+use crate::mqttsn::ReturnCode;
+use crate::mqttsn::SubscribeTopic;
+use crate::mqttsn::TopicRef;
+use crate::session::SessionState;
+use crate::session::Sessions;
+use async_trait::async_trait;
+use serde_json::json;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::time::Instant;
+use tedge_actors::Actor;
+use tedge_actors::Builder;
+use tedge_actors::ChannelError;
+use tedge_actors::DynSender;
+use tedge_actors::MessageReceiver;
+use tedge_actors::MessageSink;
+use tedge_actors::MessageSource;
+use tedge_actors::NoConfig;
+use tedge_actors::RuntimeError;
+use tedge_actors::RuntimeRequest;
+use tedge_actors::RuntimeRequestSink;
+use tedge_actors::Sender;
+use tedge_actors::SimpleMessageBox;
+use tedge_actors::SimpleMessageBoxBuilder;
+use tedge_api::mqtt_topics::ChannelFilter;
+use tedge_api::mqtt_topics::EntityFilter;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_mqtt_ext::MqttMessage;
+use tedge_mqtt_ext::TopicFilter;
+use tokio::net::UdpSocket;
+use tracing::info;
+use tracing::warn;
+
+/// The maximum size of a datagram
+const MAX_DATAGRAM_SIZE: usize = 64 * 1024;
+
+/// Translate the MQTT-SN packets and CoAP requests of constrained clients into `te/` messages,
+/// and deliver to these clients the commands targeting their child devices
+pub struct GatewayActor {
+    gateway: Gateway,
+    sessions: Sessions,
+    mqttsn: Option<UdpSocket>,
+    coap: Option<UdpSocket>,
+    coap_exchanges: RecentExchanges,
+    messages: SimpleMessageBox<MqttMessage, MqttMessage>,
+}
+
+#[async_trait]
+impl Actor for GatewayActor {
+    fn name(&self) -> &str {
+        "Gateway"
+    }
+
+    async fn run(mut self) -> Result<(), RuntimeError> {
+        let mut mqttsn_buffer = vec![0u8; MAX_DATAGRAM_SIZE];
+        let mut coap_buffer = vec![0u8; MAX_DATAGRAM_SIZE];
+
+        loop {
+            // Commands are processed first, for clients waking up to get the latest ones
+            tokio::select! {
+                biased;
+
+                message = self.messages.recv() => match message {
+                    Some(message) => self.process_command(message).await?,
+                    None => break,
+                },
+                Some((size, peer)) = recv_from(self.mqttsn.as_ref(), &mut mqttsn_buffer) => {
+                    self.process_mqttsn_packet(peer, &mqttsn_buffer[..size]).await?
+                },
+                Some((size, peer)) = recv_from(self.coap.as_ref(), &mut coap_buffer) => {
+                    self.process_coap_request(peer, &coap_buffer[..size]).await?
+                },
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl GatewayActor {
+    /// Deliver the commands targeting an active MQTT-SN client,
+    /// those for sleeping and CoAP clients being kept till the client wakes up
+    async fn process_command(&mut self, message: MqttMessage) -> Result<(), ChannelError> {
+        let Some(entity) = self.gateway.update_command(&message) else {
+            return Ok(());
+        };
+        let Some(session) = self.sessions.by_entity(&entity) else {
+            return Ok(());
+        };
+        if session.state == SessionState::Active {
+            let client_id = session.client_id.clone();
+            self.deliver_commands(&client_id).await;
+        }
+        Ok(())
+    }
+
+    async fn process_mqttsn_packet(
+        &mut self,
+        peer: SocketAddr,
+        bytes: &[u8],
+    ) -> Result<(), ChannelError> {
+        let packet = match Packet::decode(bytes) {
+            Ok(packet) => packet,
+            Err(err) => {
+                warn!("Invalid MQTT-SN packet received from {peer}: {err}");
+                return Ok(());
+            }
+        };
+
+        match packet {
+            Packet::Connect {
+                clean_session,
+                client_id,
+                ..
+            } => {
+                let return_code = match self.gateway.register_client(&client_id) {
+                    Ok((entity, registrations)) => {
+                        for registration in registrations {
+                            self.messages.send(registration).await?;
+                        }
+                        info!("MQTT-SN client {client_id} connected from {peer}");
+                        self.sessions
+                            .connect(&client_id, entity, peer, clean_session);
+                        ReturnCode::Accepted
+                    }
+                    Err(err) => {
+                        warn!("Rejecting the MQTT-SN client {client_id:?} connecting from {peer}: {err}");
+                        ReturnCode::NotSupported
+                    }
+                };
+                self.send_mqttsn(peer, Packet::ConnAck { return_code })
+                    .await;
+                if return_code == ReturnCode::Accepted {
+                    self.deliver_commands(&client_id).await;
+                }
+            }
+
+            Packet::Register {
+                msg_id, topic_name, ..
+            } => {
+                let Some(session) = self.sessions.by_peer(peer) else {
+                    warn!("Ignoring an MQTT-SN REGISTER from {peer}, which is not connected");
+                    return Ok(());
+                };
+                let topic_id = session.register(&topic_name);
+                let regack = Packet::RegAck {
+                    topic_id,
+                    msg_id,
+                    return_code: ReturnCode::Accepted,
+                };
+                self.send_mqttsn(peer, regack).await;
+            }
+
+            Packet::Publish {
+                flags,
+                topic,
+                msg_id,
+                data,
+            } => {
+                let Some(session) = self.sessions.by_peer(peer) else {
+                    warn!("Ignoring an MQTT-SN PUBLISH from {peer}, which is not connected");
+                    return Ok(());
+                };
+                let topic_name = match &topic {
+                    TopicRef::Id(topic_id) => session.topic(*topic_id).map(str::to_string),
+                    TopicRef::Short(name) => Some(name.clone()),
+                    TopicRef::Predefined(_) => None,
+                };
+                let entity = session.entity.clone();
+
+                let return_code = match topic_name {
+                    None => ReturnCode::InvalidTopicId,
+                    Some(_) if flags.qos == QoS::ExactlyOnce => ReturnCode::NotSupported,
+                    Some(topic_name) => {
+                        match self
+                            .gateway
+                            .client_message(&entity, &topic_name, data, flags.retain)
+                        {
+                            Ok(message) => {
+                                self.messages.send(message).await?;
+                                ReturnCode::Accepted
+                            }
+                            Err(err) => {
+                                warn!("Ignoring an MQTT-SN PUBLISH from {peer}: {err}");
+                                ReturnCode::InvalidTopicId
+                            }
+                        }
+                    }
+                };
+                if flags.qos == QoS::AtLeastOnce || return_code != ReturnCode::Accepted {
+                    let puback = Packet::PubAck {
+                        topic_id: topic.topic_id(),
+                        msg_id,
+                        return_code,
+                    };
+                    self.send_mqttsn(peer, puback).await;
+                }
+            }
+
+            Packet::Subscribe { msg_id, topic } => {
+                let Some(session) = self.sessions.by_peer(peer) else {
+                    warn!("Ignoring an MQTT-SN SUBSCRIBE from {peer}, which is not connected");
+                    return Ok(());
+                };
+                let (topic_id, return_code) = match topic {
+                    SubscribeTopic::Name(filter) | SubscribeTopic::Short(filter)
+                        if session.subscribe(&filter) =>
+                    {
+                        let is_wildcard = filter.contains(['#', '+']);
+                        let topic_id = if is_wildcard {
+                            0
+                        } else {
+                            session.register(&filter)
+                        };
+                        (topic_id, ReturnCode::Accepted)
+                    }
+                    SubscribeTopic::Name(_) | SubscribeTopic::Short(_) => {
+                        (0, ReturnCode::InvalidTopicId)
+                    }
+                    SubscribeTopic::Predefined(topic_id) => (topic_id, ReturnCode::NotSupported),
+                };
+                let client_id = session.client_id.clone();
+                let suback = Packet::SubAck {
+                    topic_id,
+                    msg_id,
+                    return_code,
+                };
+                self.send_mqttsn(peer, suback).await;
+                self.deliver_commands(&client_id).await;
+            }
+
+            Packet::Unsubscribe { msg_id, topic } => {
+                if let Some(session) = self.sessions.by_peer(peer) {
+                    if let SubscribeTopic::Name(filter) | SubscribeTopic::Short(filter) = topic {
+                        session.unsubscribe(&filter);
+                    }
+                }
+                self.send_mqttsn(peer, Packet::UnsubAck { msg_id }).await;
+            }
+
+            Packet::PingReq { client_id } => {
+                // A sleeping client wakes up to receive the commands sent while asleep.
+                // The client id being not authenticated, a client is only woken up from its own address:
+                // a client which address has changed has to connect again.
+                if let Some(client_id) = client_id {
+                    match self.sessions.by_client_id(&client_id) {
+                        Some(session) if session.peer == peer => {
+                            session.wake_up();
+                            self.deliver_commands(&client_id).await;
+                        }
+                        Some(_) => {
+                            warn!("Ignoring an MQTT-SN PINGREQ for {client_id} from {peer}, which is not the address of this client");
+                        }
+                        None => {}
+                    }
+                }
+                self.send_mqttsn(peer, Packet::PingResp).await;
+            }
+
+            Packet::Disconnect { duration } => {
+                if let Some(session) = self.sessions.by_peer(peer) {
+                    let client_id = session.client_id.clone();
+                    match duration {
+                        Some(duration) => {
+                            info!("MQTT-SN client {client_id} sleeping for {duration}s");
+                            session.state = SessionState::Asleep;
+                        }
+                        None => {
+                            info!("MQTT-SN client {client_id} disconnected");
+                            self.sessions.disconnect(&client_id);
+                        }
+                    }
+                }
+                self.send_mqttsn(peer, Packet::Disconnect { duration: None })
+                    .await;
+            }
+
+            // The acknowledgements of the packets sent by the gateway with QoS 0
+            Packet::ConnAck { .. }
+            | Packet::RegAck { .. }
+            | Packet::PubAck { .. }
+            | Packet::SubAck { .. }
+            | Packet::UnsubAck { .. }
+            | Packet::PingResp => {}
+        }
+        Ok(())
+    }
+
+    /// Publish to an MQTT-SN client its pending commands matching its subscriptions
+    ///
+    /// The commands already delivered since the client connected or woke up are not published again.
+    /// The topic of a command is registered with the client before the command is published.
+    async fn deliver_commands(&mut self, client_id: &str) {
+        let Some(session) = self.sessions.by_client_id(client_id) else {
+            return;
+        };
+        let pending = self
+            .gateway
+            .pending_commands(&session.entity, |topic| session.is_subscribed(topic));
+        let undelivered = session.undelivered(pending.iter().map(|command| command.topic.as_str()));
+        let commands: Vec<ClientCommand> = pending
+            .iter()
+            .filter(|command| undelivered.contains(&command.topic.as_str()))
+            .cloned()
+            .collect();
+
+        let mut packets = Vec::new();
+        for ClientCommand { topic, payload } in commands {
+            let topic_id = match session.topic_id(&topic) {
+                Some(topic_id) => topic_id,
+                None => {
+                    let topic_id = session.register(&topic);
+                    packets.push(Packet::Register {
+                        topic_id,
+                        msg_id: session.next_msg_id(),
+                        topic_name: topic,
+                    });
+                    topic_id
+                }
+            };
+            packets.push(Packet::Publish {
+                flags: PublishFlags::default(),
+                topic: TopicRef::Id(topic_id),
+                msg_id: 0,
+                data: payload,
+            });
+        }
+
+        let peer = session.peer;
+        for packet in packets {
+            self.send_mqttsn(peer, packet).await;
+        }
+    }
+
+    async fn send_mqttsn(&self, peer: SocketAddr, packet: Packet) {
+        if let Some(socket) = &self.mqttsn {
+            if let Err(err) = socket.send_to(&packet.encode(), peer).await {
+                warn!("Failed to send an MQTT-SN packet to {peer}: {err}");
+            }
+        }
+    }
+
+    async fn process_coap_request(
+        &mut self,
+        peer: SocketAddr,
+        bytes: &[u8],
+    ) -> Result<(), ChannelError> {
+        let request = match coap::Message::decode(bytes) {
+            Ok(request) => request,
+            Err(err) => {
+                warn!("Invalid CoAP message received from {peer}: {err}");
+                return Ok(());
+            }
+        };
+        if !matches!(
+            request.message_type,
+            MessageType::Confirmable | MessageType::NonConfirmable
+        ) {
+            return Ok(());
+        }
+
+        // A retransmitted confirmable request is acknowledged again, but not processed again
+        let is_confirmable = request.message_type == MessageType::Confirmable;
+        let now = Instant::now();
+        if is_confirmable {
+            if let Some(response) = self.coap_exchanges.response(peer, request.message_id, now) {
+                let response = response.to_vec();
+                self.send_coap(peer, &response).await;
+                return Ok(());
+            }
+        }
+
+        let (code, payload) = self.coap_response(&request).await?;
+        let response = request.response(code, payload).encode();
+        self.send_coap(peer, &response).await;
+        if is_confirmable {
+            self.coap_exchanges
+                .insert(peer, request.message_id, now, response);
+        }
+        Ok(())
+    }
+
+    async fn send_coap(&self, peer: SocketAddr, response: &[u8]) {
+        if let Some(socket) = &self.coap {
+            if let Err(err) = socket.send_to(response, peer).await {
+                warn!("Failed to send a CoAP response to {peer}: {err}");
+            }
+        }
+    }
+
+    /// Handle a CoAP request on `/<client id>/<topic relative to the client child device>`
+    ///
+    /// - `POST` publishes a message, `PUT` publishes a retained message
+    /// - `GET` on `/<client id>/cmd` returns the pending commands of the client,
+    ///   which are returned again till updated by the client
+    async fn coap_response(
+        &mut self,
+        request: &coap::Message,
+    ) -> Result<(Code, Vec<u8>), ChannelError> {
+        let [client_id, topic @ ..] = request.path.as_slice() else {
+            return Ok((Code::NOT_FOUND, vec![]));
+        };
+        let (entity, registrations) = match self.gateway.register_client(client_id) {
+            Ok(registered) => registered,
+            Err(err) => return Ok((Code::BAD_REQUEST, err.to_string().into_bytes())),
+        };
+        for registration in registrations {
+            self.messages.send(registration).await?;
+        }
+
+        let topic = topic.join("/");
+        let response = match request.code {
+            Code::GET if topic == "cmd" => {
+                let commands: Vec<_> = self
+                    .gateway
+                    .pending_commands(&entity, |_| true)
+                    .into_iter()
+                    .map(|command| {
+                        let payload = serde_json::from_slice(&command.payload)
+                            .unwrap_or_else(|_| json!(String::from_utf8_lossy(&command.payload)));
+                        json!({ "topic": command.topic, "payload": payload })
+                    })
+                    .collect();
+                (Code::CONTENT, json!(commands).to_string().into_bytes())
+            }
+            Code::GET => (Code::NOT_FOUND, vec![]),
+            Code::POST | Code::PUT => {
+                let retain = request.code == Code::PUT;
+                match self
+                    .gateway
+                    .client_message(&entity, &topic, request.payload.clone(), retain)
+                {
+                    Ok(message) => {
+                        self.messages.send(message).await?;
+                        (Code::CHANGED, vec![])
+                    }
+                    Err(err) => (Code::BAD_REQUEST, err.to_string().into_bytes()),
+                }
+            }
+            _ => (Code::METHOD_NOT_ALLOWED, vec![]),
+        };
+        Ok(response)
+    }
+}
+
+async fn recv_from(socket: Option<&UdpSocket>, buffer: &mut [u8]) -> Option<(usize, SocketAddr)> {
+    let Some(socket) = socket else {
+        return std::future::pending().await;
+    };
+    match socket.recv_from(buffer).await {
+        Ok(received) => Some(received),
+        Err(err) => {
+            warn!("Failed to receive a datagram: {err}");
+            None
+        }
+    }
+}
+
+pub struct GatewayActorBuilder {
+    gateway: Gateway,
+    mqttsn: Option<UdpSocket>,
+    coap: Option<UdpSocket>,
+    box_builder: SimpleMessageBoxBuilder<MqttMessage, MqttMessage>,
+}
+
+impl GatewayActorBuilder {
+    /// Bind the MQTT-SN and CoAP sockets, and load the clients already registered
+    pub async fn try_new(
+        config: GatewayConfig,
+        mqtt: &mut (impl MessageSource<MqttMessage, TopicFilter> + MessageSink<MqttMessage>),
+    ) -> Result<Self, GatewayInitError> {
+        let mqttsn = bind("MQTT-SN", config.mqttsn_bind).await?;
+        let coap = bind("CoAP", config.coap_bind).await?;
+        let gateway = Gateway::new(&config)?;
+
+        let mut box_builder = SimpleMessageBoxBuilder::new("Gateway", 16);
+        box_builder.connect_sink(NoConfig, mqtt);
+        mqtt.connect_sink(Self::subscriptions(&config.mqtt_schema), &box_builder);
+
+        Ok(GatewayActorBuilder {
+            gateway,
+            mqttsn,
+            coap,
+            box_builder,
+        })
+    }
+
+    /// The address the MQTT-SN socket is actually bound to
+    pub fn mqttsn_addr(&self) -> Option<SocketAddr> {
+        self.mqttsn.as_ref()?.local_addr().ok()
+    }
+
+    /// The address the CoAP socket is actually bound to
+    pub fn coap_addr(&self) -> Option<SocketAddr> {
+        self.coap.as_ref()?.local_addr().ok()
+    }
+
+    fn subscriptions(mqtt_schema: &MqttSchema) -> TopicFilter {
+        mqtt_schema.topics(EntityFilter::AnyEntity, ChannelFilter::AnyCommand)
+    }
+}
+
+async fn bind(
+    protocol: &'static str,
+    address: Option<SocketAddr>,
+) -> Result<Option<UdpSocket>, GatewayInitError> {
+    let Some(address) = address else {
+        return Ok(None);
+    };
+    let socket = UdpSocket::bind(address)
+        .await
+        .map_err(|source| GatewayInitError::Bind {
+            protocol,
+            address,
+            source,
+        })?;
+    info!("Accepting {protocol} clients on {address}");
+    Ok(Some(socket))
+}
+
+impl RuntimeRequestSink for GatewayActorBuilder {
+    fn get_signal_sender(&self) -> DynSender<RuntimeRequest> {
+        self.box_builder.get_signal_sender()
+    }
+}
+
+impl Builder<GatewayActor> for GatewayActorBuilder {
+    type Error = Infallible;
+
+    fn try_build(self) -> Result<GatewayActor, Self::Error> {
+        Ok(self.build())
+    }
+
+    fn build(self) -> GatewayActor {
+        GatewayActor {
+            gateway: self.gateway,
+            sessions: Sessions::default(),
+            mqttsn: self.mqttsn,
+            coap: self.coap,
+            coap_exchanges: RecentExchanges::default(),
+            messages: self.box_builder.build(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+    use std::time::Duration;
+    use tedge_actors::test_helpers::MessageReceiverExt;
+    use tedge_actors::test_helpers::TimedMessageBox;
+    use tedge_mqtt_ext::Topic;
+    use tedge_test_utils::fs::TempTedgeDir;
+
+    const TEST_TIMEOUT: Duration = Duration::from_secs(3);
+
+    #[tokio::test]
+    async fn mqttsn_clients_publish_on_topics_relative_to_their_child_device() {
+        let ttd = TempTedgeDir::new();
+        let (mut mqtt, client, _) = spawn_gateway(&ttd).await;
+
+        client
+            .exchange(Packet::Connect {
+                clean_session: true,
+                duration: 60,
+                client_id: "sensor01".to_string(),
+            })
+            .await;
+        let registration = mqtt.recv().await.unwrap();
+        assert_eq!(registration.topic.name, "te/device/sensor01//");
+
+        let regack = client
+            .exchange(Packet::Register {
+                topic_id: 0,
+                msg_id: 1,
+                topic_name: "m/environment".to_string(),
+            })
+            .await;
+        let Packet::RegAck { topic_id, .. } = regack else {
+            panic!("Unexpected response: {regack:?}")
+        };
+
+        let puback = client
+            .exchange(Packet::Publish {
+                flags: PublishFlags {
+                    qos: QoS::AtLeastOnce,
+                    ..PublishFlags::default()
+                },
+                topic: TopicRef::Id(topic_id),
+                msg_id: 2,
+                data: br#"{"temperature":21.5}"#.to_vec(),
+            })
+            .await;
+        assert_eq!(
+            puback,
+            Packet::PubAck {
+                topic_id,
+                msg_id: 2,
+                return_code: ReturnCode::Accepted
+            }
+        );
+
+        let measurement = mqtt.recv().await.unwrap();
+        assert_eq!(measurement.topic.name, "te/device/sensor01///m/environment");
+        assert_eq!(
+            measurement.payload_str().unwrap(),
+            r#"{"temperature":21.5}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn commands_are_delivered_to_sleeping_mqttsn_clients_on_wake_up() {
+        let ttd = TempTedgeDir::new();
+        let (mut mqtt, client, _) = spawn_gateway(&ttd).await;
+
+        client
+            .exchange(Packet::Connect {
+                clean_session: true,
+                duration: 60,
+                client_id: "sensor01".to_string(),
+            })
+            .await;
+        mqtt.skip(1).await;
+        client
+            .exchange(Packet::Subscribe {
+                msg_id: 1,
+                topic: SubscribeTopic::Name("cmd/+/+".to_string()),
+            })
+            .await;
+        client
+            .exchange(Packet::Disconnect {
+                duration: Some(600),
+            })
+            .await;
+
+        mqtt.send(
+            MqttMessage::new(
+                &Topic::new_unchecked("te/device/sensor01///cmd/restart/1234"),
+                r#"{"status":"init"}"#,
+            )
+            .with_retain(),
+        )
+        .await
+        .unwrap();
+
+        client
+            .send(Packet::PingReq {
+                client_id: Some("sensor01".to_string()),
+            })
+            .await;
+        let Packet::Register {
+            topic_id,
+            topic_name,
+            ..
+        } = client.recv().await
+        else {
+            panic!("Expected the command topic to be registered")
+        };
+        assert_eq!(topic_name, "cmd/restart/1234");
+        assert_eq!(
+            client.recv().await,
+            Packet::Publish {
+                flags: PublishFlags::default(),
+                topic: TopicRef::Id(topic_id),
+                msg_id: 0,
+                data: br#"{"status":"init"}"#.to_vec(),
+            }
+        );
+        assert_eq!(client.recv().await, Packet::PingResp);
+    }
+
+    #[tokio::test]
+    async fn coap_clients_post_messages_and_fetch_their_commands() {
+        let ttd = TempTedgeDir::new();
+        let (mut mqtt, _, coap_addr) = spawn_gateway(&ttd).await;
+        let client = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+
+        let response = coap_exchange(
+            &client,
+            coap_addr,
+            1,
+            Code::POST,
+            &["sensor02", "e", "door"],
+            br#"{"text":"open"}"#,
+        )
+        .await;
+        assert_eq!(response.code, Code::CHANGED);
+        assert_eq!(
+            mqtt.recv().await.unwrap().topic.name,
+            "te/device/sensor02//"
+        );
+        let event = mqtt.recv().await.unwrap();
+        assert_eq!(event.topic.name, "te/device/sensor02///e/door");
+
+        mqtt.send(
+            MqttMessage::new(
+                &Topic::new_unchecked("te/device/sensor02///cmd/restart/1234"),
+                r#"{"status":"init"}"#,
+            )
+            .with_retain(),
+        )
+        .await
+        .unwrap();
+
+        let response =
+            coap_exchange(&client, coap_addr, 2, Code::GET, &["sensor02", "cmd"], b"").await;
+        assert_eq!(response.code, Code::CONTENT);
+        let commands: serde_json::Value = serde_json::from_slice(&response.payload).unwrap();
+        assert_eq!(
+            commands,
+            json!([{"topic": "cmd/restart/1234", "payload": {"status": "init"}}])
+        );
+
+        let response = coap_exchange(
+            &client,
+            coap_addr,
+            3,
+            Code::POST,
+            &["sensor02", "unknown"],
+            b"{}",
+        )
+        .await;
+        assert_eq!(response.code, Code::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn coap_clients_fetch_their_commands_till_updated() {
+        let ttd = TempTedgeDir::new();
+        let (mut mqtt, _, coap_addr) = spawn_gateway(&ttd).await;
+        let client = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+
+        coap_exchange(&client, coap_addr, 1, Code::GET, &["sensor02", "cmd"], b"").await;
+        mqtt.skip(1).await;
+        mqtt.send(
+            MqttMessage::new(
+                &Topic::new_unchecked("te/device/sensor02///cmd/restart/1234"),
+                r#"{"status":"init"}"#,
+            )
+            .with_retain(),
+        )
+        .await
+        .unwrap();
+
+        // A response lost by the client is not lost for the client
+        for message_id in [2, 3] {
+            let response = coap_exchange(
+                &client,
+                coap_addr,
+                message_id,
+                Code::GET,
+                &["sensor02", "cmd"],
+                b"",
+            )
+            .await;
+            let commands: serde_json::Value = serde_json::from_slice(&response.payload).unwrap();
+            assert_eq!(
+                commands,
+                json!([{"topic": "cmd/restart/1234", "payload": {"status": "init"}}])
+            );
+        }
+
+        mqtt.send(
+            MqttMessage::new(
+                &Topic::new_unchecked("te/device/sensor02///cmd/restart/1234"),
+                r#"{"status":"executing"}"#,
+            )
+            .with_retain(),
+        )
+        .await
+        .unwrap();
+
+        let response =
+            coap_exchange(&client, coap_addr, 4, Code::GET, &["sensor02", "cmd"], b"").await;
+        let commands: serde_json::Value = serde_json::from_slice(&response.payload).unwrap();
+        assert_eq!(commands, json!([]));
+    }
+
+    #[tokio::test]
+    async fn retransmitted_coap_requests_are_acknowledged_but_processed_once() {
+        let ttd = TempTedgeDir::new();
+        let (mut mqtt, _, coap_addr) = spawn_gateway(&ttd).await;
+        let client = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+
+        for _ in 0..2 {
+            let response = coap_exchange(
+                &client,
+                coap_addr,
+                7,
+                Code::POST,
+                &["sensor02", "e", "door"],
+                br#"{"text":"open"}"#,
+            )
+            .await;
+            assert_eq!(response.code, Code::CHANGED);
+        }
+        assert_eq!(
+            mqtt.recv().await.unwrap().topic.name,
+            "te/device/sensor02//"
+        );
+        assert_eq!(
+            mqtt.recv().await.unwrap().topic.name,
+            "te/device/sensor02///e/door"
+        );
+
+        let response = coap_exchange(
+            &client,
+            coap_addr,
+            8,
+            Code::POST,
+            &["sensor02", "e", "door"],
+            br#"{"text":"closed"}"#,
+        )
+        .await;
+        assert_eq!(response.code, Code::CHANGED);
+        let event = mqtt.recv().await.unwrap();
+        assert_eq!(event.payload_str().unwrap(), r#"{"text":"closed"}"#);
+    }
+
+    #[tokio::test]
+    async fn mqttsn_clients_are_not_woken_up_from_another_address() {
+        let ttd = TempTedgeDir::new();
+        let (mut mqtt, client, _) = spawn_gateway(&ttd).await;
+        let mqttsn_addr = client.0.peer_addr().unwrap();
+
+        client
+            .exchange(Packet::Connect {
+                clean_session: true,
+                duration: 60,
+                client_id: "sensor01".to_string(),
+            })
+            .await;
+        mqtt.skip(1).await;
+        client
+            .exchange(Packet::Subscribe {
+                msg_id: 1,
+                topic: SubscribeTopic::Name("cmd/+/+".to_string()),
+            })
+            .await;
+        client
+            .exchange(Packet::Disconnect {
+                duration: Some(600),
+            })
+            .await;
+
+        mqtt.send(
+            MqttMessage::new(
+                &Topic::new_unchecked("te/device/sensor01///cmd/restart/1234"),
+                r#"{"status":"init"}"#,
+            )
+            .with_retain(),
+        )
+        .await
+        .unwrap();
+
+        let intruder = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        intruder.connect(mqttsn_addr).await.unwrap();
+        let intruder = MqttSnClient(intruder);
+        let response = intruder
+            .exchange(Packet::PingReq {
+                client_id: Some("sensor01".to_string()),
+            })
+            .await;
+        assert_eq!(response, Packet::PingResp);
+
+        // The commands are still delivered to the genuine client
+        client
+            .send(Packet::PingReq {
+                client_id: Some("sensor01".to_string()),
+            })
+            .await;
+        let Packet::Register { topic_name, .. } = client.recv().await else {
+            panic!("Expected the command topic to be registered")
+        };
+        assert_eq!(topic_name, "cmd/restart/1234");
+    }
+
+    async fn spawn_gateway(
+        ttd: &TempTedgeDir,
+    ) -> (
+        TimedMessageBox<SimpleMessageBox<MqttMessage, MqttMessage>>,
+        MqttSnClient,
+        SocketAddr,
+    ) {
+        let localhost = SocketAddr::from((Ipv4Addr::LOCALHOST, 0));
+        let config = GatewayConfig {
+            mqtt_schema: MqttSchema::default(),
+            mqttsn_bind: Some(localhost),
+            coap_bind: Some(localhost),
+            max_pending_commands: 16,
+            state_dir: ttd.utf8_path().to_owned(),
+        };
+        let mut mqtt: SimpleMessageBoxBuilder<MqttMessage, MqttMessage> =
+            SimpleMessageBoxBuilder::new("MQTT", 16);
+        let builder = GatewayActorBuilder::try_new(config, &mut mqtt)
+            .await
+            .unwrap();
+        let mqttsn_addr = builder.mqttsn_addr().unwrap();
+        let coap_addr = builder.coap_addr().unwrap();
+        let actor = builder.build();
+        tokio::spawn(async move { actor.run().await });
+
+        let socket = UdpSocket::bind(localhost).await.unwrap();
+        socket.connect(mqttsn_addr).await.unwrap();
+        (
+            mqtt.build().with_timeout(TEST_TIMEOUT),
+            MqttSnClient(socket),
+            coap_addr,
+        )
+    }
+
+    struct MqttSnClient(UdpSocket);
+
+    impl MqttSnClient {
+        async fn send(&self, packet: Packet) {
+            self.0.send(&packet.encode()).await.unwrap();
+        }
+
+        async fn recv(&self) -> Packet {
+            let mut buffer = vec![0u8; MAX_DATAGRAM_SIZE];
+            let size = tokio::time::timeout(TEST_TIMEOUT, self.0.recv(&mut buffer))
+                .await
+                .expect("a response from the gateway")
+                .unwrap();
+            Packet::decode(&buffer[..size]).unwrap()
+        }
+
+        async fn exchange(&self, packet: Packet) -> Packet {
+            self.send(packet).await;
+            self.recv().await
+        }
+    }
+
+    async fn coap_exchange(
+        client: &UdpSocket,
+        gateway: SocketAddr,
+        message_id: u16,
+        code: Code,
+        path: &[&str],
+        payload: &[u8],
+    ) -> coap::Message {
+        let request = coap::Message {
+            message_type: MessageType::Confirmable,
+            code,
+            message_id,
+            token: vec![7],
+            path: path.iter().map(|segment| segment.to_string()).collect(),
+            payload: payload.to_vec(),
+        };
+        client.send_to(&request.encode(), gateway).await.unwrap();
+
+        let mut buffer = vec![0u8; MAX_DATAGRAM_SIZE];
+        let (size, _) = tokio::time::timeout(TEST_TIMEOUT, client.recv_from(&mut buffer))
+            .await
+            .expect("a response from the gateway")
+            .unwrap();
+        let response = coap::Message::decode(&buffer[..size]).unwrap();
+        assert_eq!(response.message_type, MessageType::Acknowledgement);
+        assert_eq!(response.token, vec![7]);
+        response
+    }
+}
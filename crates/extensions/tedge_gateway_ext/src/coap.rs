@@ -0,0 +1,340 @@
+//! The subset of CoAP (RFC 7252) used by the clients of the gateway
+//!
+//! Only the `Uri-Path` option of the requests is interpreted, the other options being ignored.
+use crate::error::DecodeError;
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::time::Duration;
+use std::time::Instant;
+
+const VERSION: u8 = 1;
+const PAYLOAD_MARKER: u8 = 0xff;
+const OPTION_URI_PATH: u16 = 11;
+const OPTION_CONTENT_FORMAT: u16 = 12;
+const CONTENT_FORMAT_JSON: u8 = 50;
+
+/// How long a message id is remembered to detect duplicates, the `EXCHANGE_LIFETIME` of RFC 7252
+const EXCHANGE_LIFETIME: Duration = Duration::from_secs(247);
+
+/// The maximum number of exchanges remembered to detect duplicates
+const MAX_EXCHANGES: usize = 1024;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MessageType {
+    Confirmable = 0,
+    NonConfirmable = 1,
+    Acknowledgement = 2,
+    Reset = 3,
+}
+
+/// A request or response code, as `class.detail`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Code(pub u8);
+
+impl Code {
+    pub const EMPTY: Code = Code(0x00);
+    pub const GET: Code = Code(0x01);
+    pub const POST: Code = Code(0x02);
+    pub const PUT: Code = Code(0x03);
+    pub const CHANGED: Code = Code(0x44);
+    pub const CONTENT: Code = Code(0x45);
+    pub const BAD_REQUEST: Code = Code(0x80);
+    pub const NOT_FOUND: Code = Code(0x84);
+    pub const METHOD_NOT_ALLOWED: Code = Code(0x85);
+    pub const INTERNAL_SERVER_ERROR: Code = Code(0xa0);
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Message {
+    pub message_type: MessageType,
+    pub code: Code,
+    pub message_id: u16,
+    pub token: Vec<u8>,
+    /// The segments of the `Uri-Path` option
+    pub path: Vec<String>,
+    pub payload: Vec<u8>,
+}
+
+impl Message {
+    /// The response to this request, piggybacked on the acknowledgement of confirmable requests
+    pub fn response(&self, code: Code, payload: Vec<u8>) -> Message {
+        let message_type = match self.message_type {
+            MessageType::Confirmable => MessageType::Acknowledgement,
+            _ => MessageType::NonConfirmable,
+        };
+        Message {
+            message_type,
+            code,
+            message_id: self.message_id,
+            token: self.token.clone(),
+            path: vec![],
+            payload,
+        }
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let [first, code, id_high, id_low, rest @ ..] = bytes else {
+            return Err(DecodeError::Truncated);
+        };
+        if first >> 6 != VERSION {
+            return Err(DecodeError::UnsupportedVersion(first >> 6));
+        }
+        let message_type = match (first >> 4) & 0x03 {
+            0 => MessageType::Confirmable,
+            1 => MessageType::NonConfirmable,
+            2 => MessageType::Acknowledgement,
+            _ => MessageType::Reset,
+        };
+        let token_length = (first & 0x0f) as usize;
+        if token_length > 8 || rest.len() < token_length {
+            return Err(DecodeError::Truncated);
+        }
+        let (token, mut rest) = rest.split_at(token_length);
+
+        let mut path = Vec::new();
+        let mut option_number = 0u16;
+        let payload = loop {
+            let Some((&byte, tail)) = rest.split_first() else {
+                break vec![];
+            };
+            if byte == PAYLOAD_MARKER {
+                break tail.to_vec();
+            }
+            rest = tail;
+            let delta = option_value(byte >> 4, &mut rest)?;
+            let length = option_value(byte & 0x0f, &mut rest)? as usize;
+            if rest.len() < length {
+                return Err(DecodeError::Truncated);
+            }
+            option_number = option_number
+                .checked_add(delta)
+                .ok_or(DecodeError::InvalidOption)?;
+            let (value, tail) = rest.split_at(length);
+            rest = tail;
+            if option_number == OPTION_URI_PATH {
+                let segment =
+                    String::from_utf8(value.to_vec()).map_err(|_| DecodeError::NonUtf8String)?;
+                path.push(segment);
+            }
+        };
+
+        Ok(Message {
+            message_type,
+            code: Code(*code),
+            message_id: u16::from_be_bytes([*id_high, *id_low]),
+            token: token.to_vec(),
+            path,
+            payload,
+        })
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = vec![
+            (VERSION << 6) | ((self.message_type as u8) << 4) | self.token.len() as u8,
+            self.code.0,
+        ];
+        bytes.extend_from_slice(&self.message_id.to_be_bytes());
+        bytes.extend_from_slice(&self.token);
+
+        let mut previous_option = 0;
+        for segment in &self.path {
+            push_option(
+                &mut bytes,
+                OPTION_URI_PATH - previous_option,
+                segment.as_bytes(),
+            );
+            previous_option = OPTION_URI_PATH;
+        }
+        if !self.payload.is_empty() {
+            push_option(
+                &mut bytes,
+                OPTION_CONTENT_FORMAT - previous_option,
+                &[CONTENT_FORMAT_JSON],
+            );
+            bytes.push(PAYLOAD_MARKER);
+            bytes.extend_from_slice(&self.payload);
+        }
+        bytes
+    }
+}
+
+/// The responses recently sent to confirmable requests
+///
+/// A client retransmits a confirmable request when the acknowledgement is lost.
+/// Such a duplicate, with the same message id, is answered with the same response,
+/// without being processed again.
+#[derive(Default)]
+pub struct RecentExchanges {
+    exchanges: VecDeque<Exchange>,
+}
+
+struct Exchange {
+    peer: SocketAddr,
+    message_id: u16,
+    received_at: Instant,
+    response: Vec<u8>,
+}
+
+impl RecentExchanges {
+    /// The response sent to a previous request of a peer with the same message id, if any
+    pub fn response(&mut self, peer: SocketAddr, message_id: u16, now: Instant) -> Option<&[u8]> {
+        while self
+            .exchanges
+            .front()
+            .is_some_and(|exchange| now.duration_since(exchange.received_at) > EXCHANGE_LIFETIME)
+        {
+            self.exchanges.pop_front();
+        }
+        self.exchanges
+            .iter()
+            .find(|exchange| exchange.peer == peer && exchange.message_id == message_id)
+            .map(|exchange| exchange.response.as_slice())
+    }
+
+    /// Remember the response sent to a request, the oldest exchanges being forgotten first
+    pub fn insert(&mut self, peer: SocketAddr, message_id: u16, now: Instant, response: Vec<u8>) {
+        if self.exchanges.len() >= MAX_EXCHANGES {
+            self.exchanges.pop_front();
+        }
+        self.exchanges.push_back(Exchange {
+            peer,
+            message_id,
+            received_at: now,
+            response,
+        });
+    }
+}
+
+/// Read an option delta or length, possibly extended over the following bytes
+fn option_value(nibble: u8, rest: &mut &[u8]) -> Result<u16, DecodeError> {
+    let (value, extension) = match nibble {
+        0..=12 => return Ok(nibble as u16),
+        13 => (rest.first().map(|byte| *byte as u16 + 13), 1),
+        14 => (
+            rest.get(..2)
+                .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]).saturating_add(269)),
+            2,
+        ),
+        _ => return Err(DecodeError::InvalidOption),
+    };
+    let value = value.ok_or(DecodeError::Truncated)?;
+    *rest = &rest[extension..];
+    Ok(value)
+}
+
+fn push_option(bytes: &mut Vec<u8>, delta: u16, value: &[u8]) {
+    let (delta_nibble, delta_extension) = option_nibble(delta);
+    let (length_nibble, length_extension) = option_nibble(value.len() as u16);
+    bytes.push((delta_nibble << 4) | length_nibble);
+    bytes.extend_from_slice(&delta_extension);
+    bytes.extend_from_slice(&length_extension);
+    bytes.extend_from_slice(value);
+}
+
+fn option_nibble(value: u16) -> (u8, Vec<u8>) {
+    match value {
+        0..=12 => (value as u8, vec![]),
+        13..=268 => (13, vec![(value - 13) as u8]),
+        _ => (14, (value - 269).to_be_bytes().to_vec()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_a_confirmable_post() {
+        let mut bytes = vec![0x42, 0x02, 0x12, 0x34, 0xaa, 0xbb];
+        // Uri-Path: sensor01
+        bytes.extend_from_slice(&[0xb8]);
+        bytes.extend_from_slice(b"sensor01");
+        // Uri-Path: m
+        bytes.extend_from_slice(&[0x01, b'm']);
+        // Uri-Path: environment
+        bytes.extend_from_slice(&[0x0b]);
+        bytes.extend_from_slice(b"environment");
+        // Content-Format: application/json
+        bytes.extend_from_slice(&[0x11, 50]);
+        bytes.push(PAYLOAD_MARKER);
+        bytes.extend_from_slice(br#"{"temperature":21.5}"#);
+
+        assert_eq!(
+            Message::decode(&bytes).unwrap(),
+            Message {
+                message_type: MessageType::Confirmable,
+                code: Code::POST,
+                message_id: 0x1234,
+                token: vec![0xaa, 0xbb],
+                path: vec![
+                    "sensor01".to_string(),
+                    "m".to_string(),
+                    "environment".to_string()
+                ],
+                payload: br#"{"temperature":21.5}"#.to_vec(),
+            }
+        );
+    }
+
+    #[test]
+    fn responses_to_confirmable_requests_are_piggybacked() {
+        let request = Message {
+            message_type: MessageType::Confirmable,
+            code: Code::GET,
+            message_id: 7,
+            token: vec![1],
+            path: vec!["sensor01".to_string(), "cmd".to_string()],
+            payload: vec![],
+        };
+
+        let response = request.response(Code::CONTENT, b"[]".to_vec());
+
+        assert_eq!(response.message_type, MessageType::Acknowledgement);
+        assert_eq!(response.message_id, 7);
+        assert_eq!(response.token, vec![1]);
+        assert_eq!(Message::decode(&response.encode()).unwrap(), response);
+    }
+
+    #[test]
+    fn long_options_are_decoded_as_encoded() {
+        let request = Message {
+            message_type: MessageType::NonConfirmable,
+            code: Code::PUT,
+            message_id: 1,
+            token: vec![],
+            path: vec!["a".repeat(20), "b".repeat(300)],
+            payload: b"{}".to_vec(),
+        };
+
+        assert_eq!(Message::decode(&request.encode()).unwrap(), request);
+    }
+
+    #[test]
+    fn duplicated_requests_are_detected_till_the_exchange_lifetime() {
+        let mut exchanges = RecentExchanges::default();
+        let peer = SocketAddr::from(([127, 0, 0, 1], 5683));
+        let other_peer = SocketAddr::from(([127, 0, 0, 1], 5684));
+        let start = Instant::now();
+
+        assert_eq!(exchanges.response(peer, 1, start), None);
+        exchanges.insert(peer, 1, start, b"response".to_vec());
+
+        assert_eq!(
+            exchanges.response(peer, 1, start + Duration::from_secs(10)),
+            Some(b"response".as_slice())
+        );
+        assert_eq!(exchanges.response(peer, 2, start), None);
+        assert_eq!(exchanges.response(other_peer, 1, start), None);
+        assert_eq!(
+            exchanges.response(peer, 1, start + EXCHANGE_LIFETIME + Duration::from_secs(1)),
+            None
+        );
+    }
+
+    #[test]
+    fn invalid_messages_are_rejected() {
+        assert!(Message::decode(&[0x40, 0x01]).is_err());
+        assert!(Message::decode(&[0x80, 0x01, 0x00, 0x01]).is_err());
+        assert!(Message::decode(&[0x48, 0x01, 0x00, 0x01, 0x00]).is_err());
+    }
+}
@@ -0,0 +1,48 @@
+use camino::Utf8PathBuf;
+use std::net::SocketAddr;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_config::TEdgeConfig;
+
+/// Configuration of the MQTT-SN and CoAP gateway
+#[derive(Clone, Debug)]
+pub struct GatewayConfig {
+    pub mqtt_schema: MqttSchema,
+
+    /// The address of the MQTT-SN socket, if any
+    pub mqttsn_bind: Option<SocketAddr>,
+
+    /// The address of the CoAP socket, if any
+    pub coap_bind: Option<SocketAddr>,
+
+    /// The maximum number of commands kept for a sleeping client
+    pub max_pending_commands: usize,
+
+    /// The directory where the clients registered as child devices are persisted
+    pub state_dir: Utf8PathBuf,
+}
+
+impl GatewayConfig {
+    pub fn from_tedge_config(tedge_config: &TEdgeConfig) -> Self {
+        let gateway = &tedge_config.gateway;
+        let mqttsn_bind = gateway
+            .mqttsn
+            .bind
+            .port
+            .or_none()
+            .map(|port| SocketAddr::from((gateway.mqttsn.bind.address, *port)));
+        let coap_bind = gateway
+            .coap
+            .bind
+            .port
+            .or_none()
+            .map(|port| SocketAddr::from((gateway.coap.bind.address, *port)));
+
+        GatewayConfig {
+            mqtt_schema: MqttSchema::with_root(tedge_config.mqtt.topic_root.clone()),
+            mqttsn_bind,
+            coap_bind,
+            max_pending_commands: gateway.max_pending_commands as usize,
+            state_dir: tedge_config.data.path.join("gateway"),
+        }
+    }
+}
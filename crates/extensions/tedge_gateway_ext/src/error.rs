@@ -0,0 +1,63 @@
+use std::net::SocketAddr;
+use tedge_api::entity_store::InitError;
+use tedge_api::mqtt_topics::ChannelError;
+use tedge_api::mqtt_topics::TopicIdError;
+
+/// Error decoding an MQTT-SN packet or a CoAP message
+#[derive(thiserror::Error, Debug, Eq, PartialEq)]
+pub enum DecodeError {
+    #[error("Truncated message")]
+    Truncated,
+
+    #[error("Unsupported MQTT-SN packet type: {0:#04x}")]
+    UnsupportedPacket(u8),
+
+    #[error("Invalid MQTT-SN topic id type")]
+    InvalidTopicIdType,
+
+    #[error("Invalid MQTT-SN return code: {0:#04x}")]
+    InvalidReturnCode(u8),
+
+    #[error("Unsupported CoAP version: {0}")]
+    UnsupportedVersion(u8),
+
+    #[error("Invalid CoAP option")]
+    InvalidOption,
+
+    #[error("Non UTF-8 string")]
+    NonUtf8String,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum GatewayError {
+    #[error("Empty client id")]
+    EmptyClientId,
+
+    #[error("Invalid client id {client_id:?}: {source}")]
+    InvalidClientId {
+        client_id: String,
+        source: TopicIdError,
+    },
+
+    #[error("Invalid topic {topic:?}: {source}")]
+    InvalidTopic { topic: String, source: ChannelError },
+
+    #[error("The topic {0:?} is reserved to the gateway")]
+    ReservedTopic(String),
+
+    #[error(transparent)]
+    EntityStore(#[from] tedge_api::entity_store::Error),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum GatewayInitError {
+    #[error("Failed to bind the {protocol} socket to {address}: {source}")]
+    Bind {
+        protocol: &'static str,
+        address: SocketAddr,
+        source: std::io::Error,
+    },
+
+    #[error("Failed to load the clients registered by the gateway: {0}")]
+    EntityStore(#[from] InitError),
+}
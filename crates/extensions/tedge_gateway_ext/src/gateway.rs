@@ -0,0 +1,287 @@
+use crate::config::GatewayConfig;
+use crate::error::GatewayError;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use tedge_api::entity_store::EntityRegistrationMessage;
+use tedge_api::entity_store::InitError;
+use tedge_api::mqtt_topics::Channel;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_api::workflow::GenericCommandState;
+use tedge_api::EntityStore;
+use tedge_mqtt_ext::MqttMessage;
+use tedge_mqtt_ext::QoS;
+use tracing::warn;
+
+/// The state shared by the MQTT-SN and CoAP clients of the gateway
+///
+/// The clients are registered as child devices named after their client id,
+/// and publish on topics relative to the topic of this child device, e.g. `m/environment`.
+/// The commands targeting a client are kept until the client moves them out of the `init` state,
+/// so sleeping clients receive the commands sent while they were asleep,
+/// and commands lost on the way to a client are delivered again.
+pub struct Gateway {
+    mqtt_schema: MqttSchema,
+    entity_store: EntityStore,
+    pending_commands: HashMap<EntityTopicId, VecDeque<ClientCommand>>,
+    max_pending_commands: usize,
+}
+
+/// A command to be delivered to a client
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ClientCommand {
+    /// The topic of the command, relative to the client, e.g. `cmd/restart/123`
+    pub topic: String,
+    pub payload: Vec<u8>,
+}
+
+impl Gateway {
+    pub fn new(config: &GatewayConfig) -> Result<Self, InitError> {
+        let main_device = EntityRegistrationMessage::main_device(None);
+        let entity_store = EntityStore::with_main_device(
+            config.mqtt_schema.clone(),
+            main_device,
+            0,
+            &config.state_dir,
+            false,
+        )?;
+        Ok(Gateway {
+            mqtt_schema: config.mqtt_schema.clone(),
+            entity_store,
+            pending_commands: HashMap::new(),
+            max_pending_commands: config.max_pending_commands,
+        })
+    }
+
+    /// Register a client as a child device, unless already registered
+    ///
+    /// Return the child device of the client and the registration messages to be published.
+    pub fn register_client(
+        &mut self,
+        client_id: &str,
+    ) -> Result<(EntityTopicId, Vec<MqttMessage>), GatewayError> {
+        if client_id.is_empty() {
+            return Err(GatewayError::EmptyClientId);
+        }
+        let entity = EntityTopicId::default_child_device(client_id).map_err(|source| {
+            GatewayError::InvalidClientId {
+                client_id: client_id.to_string(),
+                source,
+            }
+        })?;
+        let registrations = self
+            .entity_store
+            .auto_register_entity(&entity)?
+            .into_iter()
+            .map(|registration| registration.to_mqtt_message(&self.mqtt_schema))
+            .collect();
+        Ok((entity, registrations))
+    }
+
+    /// Translate a message published by a client on a topic relative to its child device
+    pub fn client_message(
+        &self,
+        entity: &EntityTopicId,
+        topic: &str,
+        payload: Vec<u8>,
+        retain: bool,
+    ) -> Result<MqttMessage, GatewayError> {
+        let channel: Channel = topic.parse().map_err(|source| GatewayError::InvalidTopic {
+            topic: topic.to_string(),
+            source,
+        })?;
+        if channel.is_entity_metadata() {
+            return Err(GatewayError::ReservedTopic(topic.to_string()));
+        }
+        let topic = self.mqtt_schema.topic_for(entity, &channel);
+        let message = MqttMessage::new(&topic, payload).with_qos(QoS::AtLeastOnce);
+        let is_command = matches!(channel, Channel::Command { .. });
+        Ok(if retain || is_command {
+            message.with_retain()
+        } else {
+            message
+        })
+    }
+
+    /// Keep track of the commands targeting a client, until the client updates them
+    ///
+    /// A command is kept while in the `init` state, and forgotten once updated by the client or cleared.
+    /// Return the child device of the client, if the command targets a client.
+    pub fn update_command(&mut self, message: &MqttMessage) -> Option<EntityTopicId> {
+        let (entity, channel) = self.mqtt_schema.entity_channel_of(&message.topic).ok()?;
+        if !matches!(channel, Channel::Command { .. }) || !self.is_client(&entity) {
+            return None;
+        }
+        let topic = channel.to_string();
+        let is_init = GenericCommandState::from_command_message(message)
+            .map(|command| command.is_init())
+            .unwrap_or(false);
+
+        let pending = self.pending_commands.entry(entity.clone()).or_default();
+        pending.retain(|command| command.topic != topic);
+        if is_init {
+            if pending.len() >= self.max_pending_commands {
+                if let Some(dropped) = pending.pop_front() {
+                    warn!(
+                        "Too many pending commands for {entity}: dropping {}",
+                        dropped.topic
+                    );
+                }
+            }
+            pending.push_back(ClientCommand {
+                topic,
+                payload: message.payload_bytes().to_vec(),
+            });
+        }
+        Some(entity)
+    }
+
+    /// The pending commands of a client which topic is accepted by the given filter
+    ///
+    /// The commands are still pending till updated by the client.
+    pub fn pending_commands(
+        &self,
+        entity: &EntityTopicId,
+        accept: impl Fn(&str) -> bool,
+    ) -> Vec<ClientCommand> {
+        let Some(pending) = self.pending_commands.get(entity) else {
+            return vec![];
+        };
+        pending
+            .iter()
+            .filter(|command| accept(&command.topic))
+            .cloned()
+            .collect()
+    }
+
+    fn is_client(&self, entity: &EntityTopicId) -> bool {
+        entity != self.entity_store.main_device() && self.entity_store.get(entity).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tedge_mqtt_ext::Topic;
+    use tedge_test_utils::fs::TempTedgeDir;
+
+    fn gateway(ttd: &TempTedgeDir) -> Gateway {
+        Gateway::new(&GatewayConfig {
+            mqtt_schema: MqttSchema::default(),
+            mqttsn_bind: None,
+            coap_bind: None,
+            max_pending_commands: 2,
+            state_dir: ttd.utf8_path().to_owned(),
+        })
+        .unwrap()
+    }
+
+    fn command(topic: &str, payload: &str) -> MqttMessage {
+        MqttMessage::new(&Topic::new_unchecked(topic), payload).with_retain()
+    }
+
+    #[test]
+    fn clients_are_registered_once() {
+        let ttd = TempTedgeDir::new();
+        let mut gateway = gateway(&ttd);
+
+        let (entity, registrations) = gateway.register_client("sensor01").unwrap();
+        assert_eq!(entity.as_str(), "device/sensor01//");
+        assert_eq!(registrations.len(), 1);
+        assert_eq!(registrations[0].topic.name, "te/device/sensor01//");
+
+        let (_, registrations) = gateway.register_client("sensor01").unwrap();
+        assert!(registrations.is_empty());
+
+        assert!(gateway.register_client("sensor/01").is_err());
+    }
+
+    #[test]
+    fn client_registrations_are_persisted() {
+        let ttd = TempTedgeDir::new();
+        gateway(&ttd).register_client("sensor01").unwrap();
+
+        let (_, registrations) = gateway(&ttd).register_client("sensor01").unwrap();
+
+        assert!(registrations.is_empty());
+    }
+
+    #[test]
+    fn client_topics_are_relative_to_the_client_device() {
+        let ttd = TempTedgeDir::new();
+        let mut gateway = gateway(&ttd);
+        let (entity, _) = gateway.register_client("sensor01").unwrap();
+
+        let message = gateway
+            .client_message(&entity, "m/environment", b"{}".to_vec(), false)
+            .unwrap();
+        assert_eq!(message.topic.name, "te/device/sensor01///m/environment");
+        assert!(!message.retain);
+
+        let message = gateway
+            .client_message(&entity, "cmd/restart/123", b"{}".to_vec(), false)
+            .unwrap();
+        assert_eq!(message.topic.name, "te/device/sensor01///cmd/restart/123");
+        assert!(message.retain);
+
+        assert!(gateway
+            .client_message(&entity, "", b"{}".to_vec(), true)
+            .is_err());
+        assert!(gateway
+            .client_message(&entity, "unknown/topic", b"{}".to_vec(), false)
+            .is_err());
+    }
+
+    #[test]
+    fn init_commands_are_kept_until_updated() {
+        let ttd = TempTedgeDir::new();
+        let mut gateway = gateway(&ttd);
+        let (entity, _) = gateway.register_client("sensor01").unwrap();
+
+        let restart = command("te/device/sensor01///cmd/restart/1", r#"{"status":"init"}"#);
+        assert_eq!(gateway.update_command(&restart), Some(entity.clone()));
+        let not_a_client = command("te/device/other///cmd/restart/1", r#"{"status":"init"}"#);
+        assert_eq!(gateway.update_command(&not_a_client), None);
+
+        assert!(gateway
+            .pending_commands(&entity, |topic| topic.starts_with("cmd/config_update/"))
+            .is_empty());
+        let expected = vec![ClientCommand {
+            topic: "cmd/restart/1".to_string(),
+            payload: br#"{"status":"init"}"#.to_vec(),
+        }];
+        assert_eq!(gateway.pending_commands(&entity, |_| true), expected);
+
+        // Fetching the commands doesn't acknowledge them
+        assert_eq!(gateway.pending_commands(&entity, |_| true), expected);
+
+        let executing = command(
+            "te/device/sensor01///cmd/restart/1",
+            r#"{"status":"executing"}"#,
+        );
+        gateway.update_command(&executing);
+        assert!(gateway.pending_commands(&entity, |_| true).is_empty());
+    }
+
+    #[test]
+    fn updated_commands_are_no_longer_pending() {
+        let ttd = TempTedgeDir::new();
+        let mut gateway = gateway(&ttd);
+        let (entity, _) = gateway.register_client("sensor01").unwrap();
+
+        for id in 1..=3 {
+            gateway.update_command(&command(
+                &format!("te/device/sensor01///cmd/restart/{id}"),
+                r#"{"status":"init"}"#,
+            ));
+        }
+        gateway.update_command(&command(
+            "te/device/sensor01///cmd/restart/3",
+            r#"{"status":"executing"}"#,
+        ));
+
+        let pending = gateway.pending_commands(&entity, |_| true);
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].topic, "cmd/restart/2");
+    }
+}
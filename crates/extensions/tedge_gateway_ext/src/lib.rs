@@ -0,0 +1,14 @@
+//! A gateway for constrained child devices speaking MQTT-SN or CoAP over UDP
+//!
+//! Each client is registered as a child device named after its client id,
+//! and publishes measurements, events, alarms and twin data on topics relative to this child device,
+//! e.g. `m/environment` for `te/device/<client id>///m/environment`.
+//! The commands targeting a client are kept by the gateway till delivered,
+//! so sleeping MQTT-SN clients and polling CoAP clients receive the commands sent while away.
+pub mod actor;
+pub mod coap;
+pub mod config;
+pub mod error;
+pub mod gateway;
+pub mod mqttsn;
+pub mod session;
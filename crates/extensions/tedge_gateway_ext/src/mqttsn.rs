@@ -0,0 +1,552 @@
+//! The subset of the MQTT-SN v1.2 packets used by the clients of the gateway
+//!
+//! The gateway doesn't advertise itself, nor supports QoS 2, wills and predefined topic ids.
+use crate::error::DecodeError;
+
+const CONNECT: u8 = 0x04;
+const CONNACK: u8 = 0x05;
+const REGISTER: u8 = 0x0a;
+const REGACK: u8 = 0x0b;
+const PUBLISH: u8 = 0x0c;
+const PUBACK: u8 = 0x0d;
+const SUBSCRIBE: u8 = 0x12;
+const SUBACK: u8 = 0x13;
+const UNSUBSCRIBE: u8 = 0x14;
+const UNSUBACK: u8 = 0x15;
+const PINGREQ: u8 = 0x16;
+const PINGRESP: u8 = 0x17;
+const DISCONNECT: u8 = 0x18;
+
+const FLAG_DUP: u8 = 0x80;
+const FLAG_QOS: u8 = 0x60;
+const FLAG_RETAIN: u8 = 0x10;
+const FLAG_CLEAN_SESSION: u8 = 0x04;
+const FLAG_TOPIC_ID_TYPE: u8 = 0x03;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Packet {
+    Connect {
+        clean_session: bool,
+        duration: u16,
+        client_id: String,
+    },
+    ConnAck {
+        return_code: ReturnCode,
+    },
+    Register {
+        topic_id: u16,
+        msg_id: u16,
+        topic_name: String,
+    },
+    RegAck {
+        topic_id: u16,
+        msg_id: u16,
+        return_code: ReturnCode,
+    },
+    Publish {
+        flags: PublishFlags,
+        topic: TopicRef,
+        msg_id: u16,
+        data: Vec<u8>,
+    },
+    PubAck {
+        topic_id: u16,
+        msg_id: u16,
+        return_code: ReturnCode,
+    },
+    Subscribe {
+        msg_id: u16,
+        topic: SubscribeTopic,
+    },
+    SubAck {
+        topic_id: u16,
+        msg_id: u16,
+        return_code: ReturnCode,
+    },
+    Unsubscribe {
+        msg_id: u16,
+        topic: SubscribeTopic,
+    },
+    UnsubAck {
+        msg_id: u16,
+    },
+    /// A ping request, with the id of the client when a sleeping client wakes up
+    PingReq {
+        client_id: Option<String>,
+    },
+    PingResp,
+    /// A disconnection, with the sleep duration when a client goes to sleep
+    Disconnect {
+        duration: Option<u16>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ReturnCode {
+    Accepted = 0x00,
+    Congestion = 0x01,
+    InvalidTopicId = 0x02,
+    NotSupported = 0x03,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PublishFlags {
+    pub dup: bool,
+    pub qos: QoS,
+    pub retain: bool,
+}
+
+/// The quality of service of a publish packet, QoS -1 being publishing without connection
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum QoS {
+    #[default]
+    AtMostOnce,
+    AtLeastOnce,
+    ExactlyOnce,
+    NoConnection,
+}
+
+/// The topic of a publish packet
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TopicRef {
+    /// A topic id previously registered
+    Id(u16),
+    /// A predefined topic id, which are not supported
+    Predefined(u16),
+    /// A two characters topic name
+    Short(String),
+}
+
+/// The topic of a subscribe packet
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SubscribeTopic {
+    /// A topic name, possibly with wildcards
+    Name(String),
+    Predefined(u16),
+    Short(String),
+}
+
+impl Packet {
+    pub fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let (length, header) = match bytes {
+            [0x01, high, low, ..] => (u16::from_be_bytes([*high, *low]) as usize, 3),
+            [length, ..] => (*length as usize, 1),
+            [] => return Err(DecodeError::Truncated),
+        };
+        if length < header + 1 || bytes.len() < length {
+            return Err(DecodeError::Truncated);
+        }
+        let msg_type = bytes[header];
+        let mut body = Reader(&bytes[header + 1..length]);
+
+        let packet = match msg_type {
+            CONNECT => {
+                let flags = body.u8()?;
+                let _protocol_id = body.u8()?;
+                Packet::Connect {
+                    clean_session: flags & FLAG_CLEAN_SESSION != 0,
+                    duration: body.u16()?,
+                    client_id: body.string()?,
+                }
+            }
+            CONNACK => Packet::ConnAck {
+                return_code: ReturnCode::try_from(body.u8()?)?,
+            },
+            REGISTER => Packet::Register {
+                topic_id: body.u16()?,
+                msg_id: body.u16()?,
+                topic_name: body.string()?,
+            },
+            REGACK => Packet::RegAck {
+                topic_id: body.u16()?,
+                msg_id: body.u16()?,
+                return_code: ReturnCode::try_from(body.u8()?)?,
+            },
+            PUBLISH => {
+                let flags = body.u8()?;
+                let topic = match flags & FLAG_TOPIC_ID_TYPE {
+                    0 => TopicRef::Id(body.u16()?),
+                    1 => TopicRef::Predefined(body.u16()?),
+                    2 => TopicRef::Short(body.short_name()?),
+                    _ => return Err(DecodeError::InvalidTopicIdType),
+                };
+                Packet::Publish {
+                    flags: PublishFlags {
+                        dup: flags & FLAG_DUP != 0,
+                        qos: QoS::from_flags(flags),
+                        retain: flags & FLAG_RETAIN != 0,
+                    },
+                    topic,
+                    msg_id: body.u16()?,
+                    data: body.rest().to_vec(),
+                }
+            }
+            PUBACK => Packet::PubAck {
+                topic_id: body.u16()?,
+                msg_id: body.u16()?,
+                return_code: ReturnCode::try_from(body.u8()?)?,
+            },
+            SUBSCRIBE | UNSUBSCRIBE => {
+                let flags = body.u8()?;
+                let msg_id = body.u16()?;
+                let topic = match flags & FLAG_TOPIC_ID_TYPE {
+                    0 => SubscribeTopic::Name(body.string()?),
+                    1 => SubscribeTopic::Predefined(body.u16()?),
+                    2 => SubscribeTopic::Short(body.short_name()?),
+                    _ => return Err(DecodeError::InvalidTopicIdType),
+                };
+                if msg_type == SUBSCRIBE {
+                    Packet::Subscribe { msg_id, topic }
+                } else {
+                    Packet::Unsubscribe { msg_id, topic }
+                }
+            }
+            SUBACK => {
+                let _flags = body.u8()?;
+                Packet::SubAck {
+                    topic_id: body.u16()?,
+                    msg_id: body.u16()?,
+                    return_code: ReturnCode::try_from(body.u8()?)?,
+                }
+            }
+            UNSUBACK => Packet::UnsubAck {
+                msg_id: body.u16()?,
+            },
+            PINGREQ => Packet::PingReq {
+                client_id: Some(body.string()?).filter(|client_id| !client_id.is_empty()),
+            },
+            PINGRESP => Packet::PingResp,
+            DISCONNECT => Packet::Disconnect {
+                duration: if body.0.is_empty() {
+                    None
+                } else {
+                    Some(body.u16()?)
+                },
+            },
+            _ => return Err(DecodeError::UnsupportedPacket(msg_type)),
+        };
+        Ok(packet)
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut body = Vec::new();
+        let msg_type = match self {
+            Packet::Connect {
+                clean_session,
+                duration,
+                client_id,
+            } => {
+                body.push(if *clean_session {
+                    FLAG_CLEAN_SESSION
+                } else {
+                    0
+                });
+                body.push(0x01);
+                body.extend_from_slice(&duration.to_be_bytes());
+                body.extend_from_slice(client_id.as_bytes());
+                CONNECT
+            }
+            Packet::ConnAck { return_code } => {
+                body.push(*return_code as u8);
+                CONNACK
+            }
+            Packet::Register {
+                topic_id,
+                msg_id,
+                topic_name,
+            } => {
+                body.extend_from_slice(&topic_id.to_be_bytes());
+                body.extend_from_slice(&msg_id.to_be_bytes());
+                body.extend_from_slice(topic_name.as_bytes());
+                REGISTER
+            }
+            Packet::RegAck {
+                topic_id,
+                msg_id,
+                return_code,
+            } => {
+                body.extend_from_slice(&topic_id.to_be_bytes());
+                body.extend_from_slice(&msg_id.to_be_bytes());
+                body.push(*return_code as u8);
+                REGACK
+            }
+            Packet::Publish {
+                flags,
+                topic,
+                msg_id,
+                data,
+            } => {
+                let (topic_id_type, topic_id) = match topic {
+                    TopicRef::Id(topic_id) => (0, topic_id.to_be_bytes()),
+                    TopicRef::Predefined(topic_id) => (1, topic_id.to_be_bytes()),
+                    TopicRef::Short(name) => (2, short_name_bytes(name)),
+                };
+                body.push(flags.to_flags() | topic_id_type);
+                body.extend_from_slice(&topic_id);
+                body.extend_from_slice(&msg_id.to_be_bytes());
+                body.extend_from_slice(data);
+                PUBLISH
+            }
+            Packet::PubAck {
+                topic_id,
+                msg_id,
+                return_code,
+            } => {
+                body.extend_from_slice(&topic_id.to_be_bytes());
+                body.extend_from_slice(&msg_id.to_be_bytes());
+                body.push(*return_code as u8);
+                PUBACK
+            }
+            Packet::Subscribe { msg_id, topic } | Packet::Unsubscribe { msg_id, topic } => {
+                let (topic_id_type, topic) = match topic {
+                    SubscribeTopic::Name(name) => (0, name.as_bytes().to_vec()),
+                    SubscribeTopic::Predefined(topic_id) => (1, topic_id.to_be_bytes().to_vec()),
+                    SubscribeTopic::Short(name) => (2, short_name_bytes(name).to_vec()),
+                };
+                body.push(topic_id_type);
+                body.extend_from_slice(&msg_id.to_be_bytes());
+                body.extend_from_slice(&topic);
+                if matches!(self, Packet::Subscribe { .. }) {
+                    SUBSCRIBE
+                } else {
+                    UNSUBSCRIBE
+                }
+            }
+            Packet::SubAck {
+                topic_id,
+                msg_id,
+                return_code,
+            } => {
+                // The commands are forwarded to the clients with QoS 0
+                body.push(0);
+                body.extend_from_slice(&topic_id.to_be_bytes());
+                body.extend_from_slice(&msg_id.to_be_bytes());
+                body.push(*return_code as u8);
+                SUBACK
+            }
+            Packet::UnsubAck { msg_id } => {
+                body.extend_from_slice(&msg_id.to_be_bytes());
+                UNSUBACK
+            }
+            Packet::PingReq { client_id } => {
+                if let Some(client_id) = client_id {
+                    body.extend_from_slice(client_id.as_bytes());
+                }
+                PINGREQ
+            }
+            Packet::PingResp => PINGRESP,
+            Packet::Disconnect { duration } => {
+                if let Some(duration) = duration {
+                    body.extend_from_slice(&duration.to_be_bytes());
+                }
+                DISCONNECT
+            }
+        };
+
+        let length = body.len() + 2;
+        let mut packet = if length < 256 {
+            vec![length as u8]
+        } else {
+            let mut header = vec![0x01];
+            header.extend_from_slice(&((length + 2) as u16).to_be_bytes());
+            header
+        };
+        packet.push(msg_type);
+        packet.extend_from_slice(&body);
+        packet
+    }
+}
+
+impl TryFrom<u8> for ReturnCode {
+    type Error = DecodeError;
+
+    fn try_from(code: u8) -> Result<Self, Self::Error> {
+        match code {
+            0x00 => Ok(ReturnCode::Accepted),
+            0x01 => Ok(ReturnCode::Congestion),
+            0x02 => Ok(ReturnCode::InvalidTopicId),
+            0x03 => Ok(ReturnCode::NotSupported),
+            _ => Err(DecodeError::InvalidReturnCode(code)),
+        }
+    }
+}
+
+impl TopicRef {
+    /// The topic id, as given in the acknowledgements
+    pub fn topic_id(&self) -> u16 {
+        match self {
+            TopicRef::Id(topic_id) | TopicRef::Predefined(topic_id) => *topic_id,
+            TopicRef::Short(name) => u16::from_be_bytes(short_name_bytes(name)),
+        }
+    }
+}
+
+impl QoS {
+    fn from_flags(flags: u8) -> Self {
+        match (flags & FLAG_QOS) >> 5 {
+            0 => QoS::AtMostOnce,
+            1 => QoS::AtLeastOnce,
+            2 => QoS::ExactlyOnce,
+            _ => QoS::NoConnection,
+        }
+    }
+}
+
+impl PublishFlags {
+    fn to_flags(self) -> u8 {
+        let qos = match self.qos {
+            QoS::AtMostOnce => 0,
+            QoS::AtLeastOnce => 1,
+            QoS::ExactlyOnce => 2,
+            QoS::NoConnection => 3,
+        };
+        let mut flags = qos << 5;
+        if self.dup {
+            flags |= FLAG_DUP;
+        }
+        if self.retain {
+            flags |= FLAG_RETAIN;
+        }
+        flags
+    }
+}
+
+fn short_name_bytes(name: &str) -> [u8; 2] {
+    let bytes = name.as_bytes();
+    [
+        bytes.first().copied().unwrap_or(0),
+        bytes.get(1).copied().unwrap_or(0),
+    ]
+}
+
+/// Read the fields of a packet body
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn u8(&mut self) -> Result<u8, DecodeError> {
+        let (byte, rest) = self.0.split_first().ok_or(DecodeError::Truncated)?;
+        self.0 = rest;
+        Ok(*byte)
+    }
+
+    fn u16(&mut self) -> Result<u16, DecodeError> {
+        Ok(u16::from_be_bytes([self.u8()?, self.u8()?]))
+    }
+
+    fn short_name(&mut self) -> Result<String, DecodeError> {
+        let bytes = [self.u8()?, self.u8()?];
+        String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError::NonUtf8String)
+    }
+
+    /// Read a string spanning up to the end of the packet
+    fn string(&mut self) -> Result<String, DecodeError> {
+        let string = std::str::from_utf8(self.rest()).map_err(|_| DecodeError::NonUtf8String)?;
+        Ok(string.to_string())
+    }
+
+    fn rest(&mut self) -> &[u8] {
+        std::mem::take(&mut self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_connect() {
+        let bytes = [
+            0x0b, 0x04, 0x04, 0x01, 0x00, 0x3c, b's', b'e', b'n', b's', b'1',
+        ];
+
+        assert_eq!(
+            Packet::decode(&bytes).unwrap(),
+            Packet::Connect {
+                clean_session: true,
+                duration: 60,
+                client_id: "sens1".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn decode_publish_on_a_registered_topic() {
+        let bytes = [0x0a, 0x0c, 0x30, 0x00, 0x01, 0x00, 0x07, b'{', b'}', b'!'];
+
+        assert_eq!(
+            Packet::decode(&bytes).unwrap(),
+            Packet::Publish {
+                flags: PublishFlags {
+                    dup: false,
+                    qos: QoS::AtLeastOnce,
+                    retain: true,
+                },
+                topic: TopicRef::Id(1),
+                msg_id: 7,
+                data: b"{}!".to_vec(),
+            }
+        );
+    }
+
+    #[test]
+    fn decode_sleep_and_wake_up() {
+        assert_eq!(
+            Packet::decode(&[0x04, 0x18, 0x01, 0x2c]).unwrap(),
+            Packet::Disconnect {
+                duration: Some(300)
+            }
+        );
+        assert_eq!(
+            Packet::decode(&[0x02, 0x18]).unwrap(),
+            Packet::Disconnect { duration: None }
+        );
+        assert_eq!(
+            Packet::decode(&[0x04, 0x16, b'a', b'b']).unwrap(),
+            Packet::PingReq {
+                client_id: Some("ab".to_string())
+            }
+        );
+    }
+
+    #[test]
+    fn packets_are_decoded_as_encoded() {
+        let packets = [
+            Packet::Register {
+                topic_id: 3,
+                msg_id: 12,
+                topic_name: "cmd/restart/42".to_string(),
+            },
+            Packet::Publish {
+                flags: PublishFlags::default(),
+                topic: TopicRef::Short("m1".to_string()),
+                msg_id: 0,
+                data: vec![b'x'; 300],
+            },
+            Packet::Subscribe {
+                msg_id: 1,
+                topic: SubscribeTopic::Name("cmd/#".to_string()),
+            },
+            Packet::SubAck {
+                topic_id: 0,
+                msg_id: 1,
+                return_code: ReturnCode::Accepted,
+            },
+            Packet::PubAck {
+                topic_id: 1,
+                msg_id: 2,
+                return_code: ReturnCode::InvalidTopicId,
+            },
+        ];
+
+        for packet in packets {
+            assert_eq!(Packet::decode(&packet.encode()).unwrap(), packet);
+        }
+    }
+
+    #[test]
+    fn truncated_packets_are_rejected() {
+        assert!(Packet::decode(&[]).is_err());
+        assert!(Packet::decode(&[0x05, 0x0b, 0x00]).is_err());
+        assert!(Packet::decode(&[0x01, 0x01, 0x00, 0x0c]).is_err());
+    }
+}
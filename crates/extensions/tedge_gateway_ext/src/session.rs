@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_mqtt_ext::Topic;
+use tedge_mqtt_ext::TopicFilter;
+
+/// The MQTT-SN clients connected to the gateway
+#[derive(Default)]
+pub struct Sessions {
+    sessions: HashMap<String, Session>,
+}
+
+/// The session of an MQTT-SN client, which outlives the disconnection of sleeping clients
+pub struct Session {
+    pub client_id: String,
+
+    /// The child device of the client
+    pub entity: EntityTopicId,
+
+    /// The address the last packet of the client has been received from
+    pub peer: SocketAddr,
+
+    pub state: SessionState,
+
+    /// The topics registered by the client or by the gateway, relative to the client child device
+    topics: HashMap<u16, String>,
+    last_topic_id: u16,
+    last_msg_id: u16,
+
+    /// The topic filters the client subscribed to, relative to the client child device
+    subscriptions: Vec<String>,
+
+    /// The topics of the commands delivered since the client connected or woke up
+    delivered: HashSet<String>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SessionState {
+    Active,
+    Asleep,
+}
+
+impl Sessions {
+    /// Open a session for a client, resuming its previous session unless a clean session is requested
+    pub fn connect(
+        &mut self,
+        client_id: &str,
+        entity: EntityTopicId,
+        peer: SocketAddr,
+        clean_session: bool,
+    ) -> &mut Session {
+        if clean_session {
+            self.sessions.remove(client_id);
+        }
+        let session = self
+            .sessions
+            .entry(client_id.to_string())
+            .or_insert_with(|| Session {
+                client_id: client_id.to_string(),
+                entity,
+                peer,
+                state: SessionState::Active,
+                topics: HashMap::new(),
+                last_topic_id: 0,
+                last_msg_id: 0,
+                subscriptions: Vec::new(),
+                delivered: HashSet::new(),
+            });
+        session.peer = peer;
+        session.state = SessionState::Active;
+        session.delivered.clear();
+        session
+    }
+
+    pub fn disconnect(&mut self, client_id: &str) {
+        self.sessions.remove(client_id);
+    }
+
+    pub fn by_client_id(&mut self, client_id: &str) -> Option<&mut Session> {
+        self.sessions.get_mut(client_id)
+    }
+
+    pub fn by_peer(&mut self, peer: SocketAddr) -> Option<&mut Session> {
+        self.sessions
+            .values_mut()
+            .find(|session| session.peer == peer)
+    }
+
+    pub fn by_entity(&mut self, entity: &EntityTopicId) -> Option<&mut Session> {
+        self.sessions
+            .values_mut()
+            .find(|session| session.entity == *entity)
+    }
+}
+
+impl Session {
+    /// Register a topic, returning its id
+    pub fn register(&mut self, topic: &str) -> u16 {
+        if let Some(topic_id) = self.topic_id(topic) {
+            return topic_id;
+        }
+        self.last_topic_id = self.last_topic_id.wrapping_add(1).max(1);
+        self.topics.insert(self.last_topic_id, topic.to_string());
+        self.last_topic_id
+    }
+
+    pub fn topic_id(&self, topic: &str) -> Option<u16> {
+        self.topics
+            .iter()
+            .find(|(_, name)| name.as_str() == topic)
+            .map(|(topic_id, _)| *topic_id)
+    }
+
+    pub fn topic(&self, topic_id: u16) -> Option<&str> {
+        self.topics.get(&topic_id).map(|topic| topic.as_str())
+    }
+
+    pub fn next_msg_id(&mut self) -> u16 {
+        self.last_msg_id = self.last_msg_id.wrapping_add(1).max(1);
+        self.last_msg_id
+    }
+
+    /// Wake up a sleeping client, the commands delivered so far being delivered again
+    ///
+    /// The commands are published with QoS 0 and might have been lost:
+    /// these are delivered again on wake up, till the client updates them.
+    pub fn wake_up(&mut self) {
+        self.state = SessionState::Active;
+        self.delivered.clear();
+    }
+
+    /// Select the commands not delivered yet, among the pending commands of the client
+    pub fn undelivered<'a>(&mut self, pending: impl IntoIterator<Item = &'a str>) -> Vec<&'a str> {
+        let pending: Vec<&str> = pending.into_iter().collect();
+        self.delivered
+            .retain(|topic| pending.contains(&topic.as_str()));
+        pending
+            .into_iter()
+            .filter(|topic| self.delivered.insert(topic.to_string()))
+            .collect()
+    }
+
+    /// Subscribe to a topic filter, returning false if the filter is invalid
+    pub fn subscribe(&mut self, filter: &str) -> bool {
+        if TopicFilter::new(filter).is_err() {
+            return false;
+        }
+        if !self.subscriptions.iter().any(|pattern| pattern == filter) {
+            self.subscriptions.push(filter.to_string());
+        }
+        true
+    }
+
+    pub fn unsubscribe(&mut self, filter: &str) {
+        self.subscriptions.retain(|pattern| pattern != filter);
+    }
+
+    /// Check if the client subscribed to a topic
+    pub fn is_subscribed(&self, topic: &str) -> bool {
+        let topic = Topic::new_unchecked(topic);
+        self.subscriptions
+            .iter()
+            .any(|pattern| TopicFilter::new_unchecked(pattern).accept_topic(&topic))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    fn peer(port: u16) -> SocketAddr {
+        SocketAddr::from((Ipv4Addr::LOCALHOST, port))
+    }
+
+    #[test]
+    fn sessions_are_resumed_unless_clean() {
+        let mut sessions = Sessions::default();
+        let entity = EntityTopicId::default_child_device("sensor01").unwrap();
+
+        let session = sessions.connect("sensor01", entity.clone(), peer(1000), true);
+        session.subscribe("cmd/#");
+        session.state = SessionState::Asleep;
+
+        let session = sessions.connect("sensor01", entity.clone(), peer(2000), false);
+        assert_eq!(session.state, SessionState::Active);
+        assert!(session.is_subscribed("cmd/restart/1"));
+        assert!(sessions.by_peer(peer(2000)).is_some());
+        assert!(sessions.by_peer(peer(1000)).is_none());
+
+        let session = sessions.connect("sensor01", entity, peer(2000), true);
+        assert!(!session.is_subscribed("cmd/restart/1"));
+    }
+
+    #[test]
+    fn commands_are_delivered_again_only_on_wake_up() {
+        let mut sessions = Sessions::default();
+        let entity = EntityTopicId::default_child_device("sensor01").unwrap();
+        let session = sessions.connect("sensor01", entity, peer(1000), true);
+
+        assert_eq!(
+            session.undelivered(["cmd/restart/1"]),
+            vec!["cmd/restart/1"]
+        );
+        assert!(session.undelivered(["cmd/restart/1"]).is_empty());
+        assert_eq!(
+            session.undelivered(["cmd/restart/1", "cmd/restart/2"]),
+            vec!["cmd/restart/2"]
+        );
+
+        session.state = SessionState::Asleep;
+        session.wake_up();
+        assert_eq!(
+            session.undelivered(["cmd/restart/2"]),
+            vec!["cmd/restart/2"]
+        );
+    }
+
+    #[test]
+    fn topics_are_registered_once() {
+        let mut sessions = Sessions::default();
+        let entity = EntityTopicId::default_child_device("sensor01").unwrap();
+        let session = sessions.connect("sensor01", entity, peer(1000), true);
+
+        let measurements = session.register("m/environment");
+        let events = session.register("e/door");
+
+        assert_ne!(measurements, events);
+        assert_eq!(session.register("m/environment"), measurements);
+        assert_eq!(session.topic(events), Some("e/door"));
+        assert_eq!(session.topic(0), None);
+    }
+
+    #[test]
+    fn invalid_subscriptions_are_rejected() {
+        let mut sessions = Sessions::default();
+        let entity = EntityTopicId::default_child_device("sensor01").unwrap();
+        let session = sessions.connect("sensor01", entity, peer(1000), true);
+
+        assert!(!session.subscribe("cmd/#/restart"));
+        assert!(session.subscribe("cmd/+/+"));
+        assert!(session.is_subscribed("cmd/restart/1"));
+        assert!(!session.is_subscribed("cmd/restart"));
+
+        session.unsubscribe("cmd/+/+");
+        assert!(!session.is_subscribed("cmd/restart/1"));
+    }
+}
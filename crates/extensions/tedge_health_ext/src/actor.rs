@@ -1,34 +1,75 @@
+use crate::checks::HealthProbe;
 use async_trait::async_trait;
+use serde_json::json;
+use serde_json::Map;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
 use tedge_actors::Actor;
+use tedge_actors::MessageBoxStats;
 use tedge_actors::MessageReceiver;
 use tedge_actors::RuntimeError;
 use tedge_actors::Sender;
 use tedge_actors::SimpleMessageBox;
+use tedge_api::health::HealthStatus;
 use tedge_api::health::ServiceHealthTopic;
+use tedge_api::health::Status;
 use tedge_mqtt_ext::MqttMessage;
+use tedge_mqtt_ext::Topic;
+use tedge_mqtt_ext::TopicFilter;
+use tokio::time::Interval;
+
+/// How often the health checks are evaluated, the health status being published only on changes
+const CHECK_INTERVAL: Duration = Duration::from_secs(10);
 
 pub struct HealthMonitorActor {
     // TODO(marcel): move this
     service_registration_message: Option<MqttMessage>,
     health_topic: ServiceHealthTopic,
+    checks: Vec<NamedCheck>,
+    health_status: SharedHealthStatus,
+    metrics_publisher: Option<MetricsPublisher>,
     messages: SimpleMessageBox<MqttMessage, MqttMessage>,
 }
 
+/// A health check registered under a name
+pub(crate) struct NamedCheck {
+    pub name: String,
+    pub subscriptions: TopicFilter,
+    pub probe: Box<dyn HealthProbe>,
+}
+
+/// The latest health status of the service,
+/// shared with the MQTT connection to be published on each reconnection
+pub(crate) type SharedHealthStatus = Arc<Mutex<HealthStatus>>;
+
 impl HealthMonitorActor {
-    pub fn new(
+    pub(crate) fn new(
         service_registration_message: Option<MqttMessage>,
         health_topic: ServiceHealthTopic,
+        checks: Vec<NamedCheck>,
+        health_status: SharedHealthStatus,
+        metrics_publisher: Option<MetricsPublisher>,
         messages: SimpleMessageBox<MqttMessage, MqttMessage>,
     ) -> Self {
         Self {
             service_registration_message,
             health_topic,
+            checks,
+            health_status,
+            metrics_publisher,
             messages,
         }
     }
 
     pub fn up_health_status(&self) -> MqttMessage {
-        self.health_topic.up_message()
+        let health_status = self
+            .health_status
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        self.health_topic.status_message(&health_status)
     }
 
     pub fn down_health_status(&self) -> MqttMessage {
@@ -47,11 +88,157 @@ impl Actor for HealthMonitorActor {
             self.messages.send(registration_message.clone()).await?;
         }
 
+        self.evaluate_checks();
         self.messages.send(self.up_health_status()).await?;
 
-        while let Some(_message) = self.messages.recv().await {
-            self.messages.send(self.up_health_status()).await?;
+        let mut metrics_timer = self
+            .metrics_publisher
+            .as_ref()
+            .map(|publisher| tokio::time::interval(publisher.interval));
+        let mut check_timer =
+            (!self.checks.is_empty()).then(|| tokio::time::interval(CHECK_INTERVAL));
+        loop {
+            tokio::select! {
+                message = self.messages.recv() => {
+                    let Some(message) = message else {
+                        break;
+                    };
+                    if self.update_checks(&message) {
+                        // Only status changes are published
+                        if self.evaluate_checks() {
+                            self.messages.send(self.up_health_status()).await?;
+                        }
+                    } else {
+                        // This is a health check request
+                        self.evaluate_checks();
+                        self.messages.send(self.up_health_status()).await?;
+                    }
+                }
+                _ = tick(&mut check_timer) => {
+                    if self.evaluate_checks() {
+                        self.messages.send(self.up_health_status()).await?;
+                    }
+                }
+                _ = tick(&mut metrics_timer) => {
+                    if let Some(publisher) = &self.metrics_publisher {
+                        let stats = tedge_actors::message_box_stats();
+                        self.messages.send(publisher.metrics_message(&stats)).await?;
+                    }
+                }
+            }
         }
         Ok(())
     }
 }
+
+impl HealthMonitorActor {
+    /// Forward a message to the checks watching its topic
+    ///
+    /// Return `false` if no checks are watching this topic.
+    fn update_checks(&mut self, message: &MqttMessage) -> bool {
+        let mut watched = false;
+        for check in self.checks.iter_mut() {
+            if check.subscriptions.accept(message) {
+                check.probe.update(message);
+                watched = true;
+            }
+        }
+        watched
+    }
+
+    /// Evaluate all the checks, updating the health status of the service
+    ///
+    /// Return `true` if the status of the service or of any of its checks has changed.
+    fn evaluate_checks(&mut self) -> bool {
+        let checks: BTreeMap<_, _> = self
+            .checks
+            .iter_mut()
+            .map(|check| (check.name.clone(), check.probe.probe()))
+            .collect();
+        let new_status = HealthStatus::from_checks(checks);
+
+        let mut health_status = self
+            .health_status
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let changed = statuses(&health_status) != statuses(&new_status);
+        *health_status = new_status;
+        changed
+    }
+}
+
+/// The status of a service and of its checks, ignoring the details
+fn statuses(health_status: &HealthStatus) -> (&Status, Vec<(&String, &Status)>) {
+    let checks = health_status
+        .checks
+        .iter()
+        .map(|(name, check)| (name, &check.status))
+        .collect();
+    (&health_status.status, checks)
+}
+
+/// Wait for the next tick of the timer, if any, or forever
+async fn tick(timer: &mut Option<Interval>) {
+    match timer {
+        Some(timer) => {
+            timer.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Publish the statistics of the message boxes of the service actors as a measurement
+pub struct MetricsPublisher {
+    topic: Topic,
+    interval: Duration,
+}
+
+impl MetricsPublisher {
+    pub fn new(topic: Topic, interval: Duration) -> Self {
+        MetricsPublisher { topic, interval }
+    }
+
+    pub fn metrics_message(&self, stats: &[MessageBoxStats]) -> MqttMessage {
+        let mut name_counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for stats in stats {
+            *name_counts.entry(stats.name.as_str()).or_default() += 1;
+        }
+
+        let mut measurement = Map::new();
+        for stats in stats {
+            let mut values = Map::new();
+            values.insert("received".to_string(), json!(stats.received));
+            values.insert("sent".to_string(), json!(stats.sent));
+            if let Some(queued) = stats.queued {
+                values.insert("queued".to_string(), json!(queued));
+            }
+            values.insert(
+                "processing_time".to_string(),
+                json!(stats.processing_time.as_secs_f64()),
+            );
+            // The message boxes sharing the same name are told apart by their id
+            let name = if name_counts[stats.name.as_str()] > 1 {
+                format!("{}-{}", stats.name, stats.id)
+            } else {
+                stats.name.clone()
+            };
+            measurement.insert(fragment_name(&name), Value::Object(values));
+        }
+
+        MqttMessage::new(&self.topic, Value::Object(measurement).to_string())
+    }
+}
+
+/// The name of a message box, as a measurement fragment name
+///
+/// Message box names are free text, but are used as measurement fragment names,
+/// hence the characters others than alphanumeric, `-` and `_` are replaced.
+fn fragment_name(message_box: &str) -> String {
+    message_box
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect()
+}
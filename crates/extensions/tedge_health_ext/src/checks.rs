@@ -0,0 +1,256 @@
+//! Self-checks included in the health status of a service
+use camino::Utf8PathBuf;
+use std::collections::HashSet;
+use tedge_api::health::HealthCheck;
+use tedge_api::health::HealthStatus;
+use tedge_api::health::Status;
+use tedge_api::mqtt_topics::ChannelFilter;
+use tedge_api::mqtt_topics::EntityFilter;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_api::workflow::GenericCommandState;
+use tedge_mqtt_ext::ConnectionStatus;
+use tedge_mqtt_ext::MqttMessage;
+use tedge_mqtt_ext::Topic;
+use tedge_mqtt_ext::TopicFilter;
+
+/// A check evaluated each time the health status of a service is published
+///
+/// A probe can watch MQTT messages, e.g. the health status of another service,
+/// in which case the health status is re-evaluated each time such a message is received.
+pub trait HealthProbe: Send + Sync + 'static {
+    /// Evaluate the check
+    fn probe(&mut self) -> HealthCheck;
+
+    /// The MQTT topics watched by this probe
+    fn subscriptions(&self) -> TopicFilter {
+        TopicFilter::empty()
+    }
+
+    /// Update the probe state from a message received on one of its subscriptions
+    fn update(&mut self, _message: &MqttMessage) {}
+}
+
+/// Check that the service is connected to the MQTT broker
+pub struct MqttConnectionCheck {
+    connection_status: ConnectionStatus,
+}
+
+impl MqttConnectionCheck {
+    pub fn new(connection_status: ConnectionStatus) -> Self {
+        MqttConnectionCheck { connection_status }
+    }
+}
+
+impl HealthProbe for MqttConnectionCheck {
+    fn probe(&mut self) -> HealthCheck {
+        if self.connection_status.is_connected() {
+            HealthCheck::up()
+        } else {
+            HealthCheck::down("disconnected from the MQTT broker")
+        }
+    }
+}
+
+/// Check the free disk space on the file system of a directory
+pub struct DiskSpaceCheck {
+    path: Utf8PathBuf,
+    min_free_percent: f64,
+}
+
+impl DiskSpaceCheck {
+    /// The service is degraded when less than 5% of the disk space is free
+    pub const DEFAULT_MIN_FREE_PERCENT: f64 = 5.0;
+
+    pub fn new(path: impl Into<Utf8PathBuf>) -> Self {
+        DiskSpaceCheck {
+            path: path.into(),
+            min_free_percent: Self::DEFAULT_MIN_FREE_PERCENT,
+        }
+    }
+
+    pub fn with_min_free_percent(self, min_free_percent: f64) -> Self {
+        DiskSpaceCheck {
+            min_free_percent,
+            ..self
+        }
+    }
+
+    fn check(&self, available_bytes: u64, total_bytes: u64) -> HealthCheck {
+        let path = &self.path;
+        if total_bytes == 0 {
+            return HealthCheck::up();
+        }
+        let free_percent = available_bytes as f64 * 100.0 / total_bytes as f64;
+        let detail = format!(
+            "{} MB ({free_percent:.0}%) free space left in {path}",
+            available_bytes / 1_000_000
+        );
+        if free_percent < self.min_free_percent {
+            HealthCheck::degraded(detail)
+        } else {
+            HealthCheck::up().with_detail(detail)
+        }
+    }
+}
+
+impl HealthProbe for DiskSpaceCheck {
+    #[allow(clippy::unnecessary_cast)]
+    fn probe(&mut self) -> HealthCheck {
+        match nix::sys::statvfs::statvfs(self.path.as_std_path()) {
+            Ok(stats) => {
+                let block_size = stats.fragment_size() as u64;
+                self.check(
+                    stats.blocks_available() as u64 * block_size,
+                    stats.blocks() as u64 * block_size,
+                )
+            }
+            Err(err) => HealthCheck::degraded(format!(
+                "cannot get the free disk space in {}: {err}",
+                self.path
+            )),
+        }
+    }
+}
+
+/// Mirror the health status of another service, e.g. the bridge connecting the cloud
+pub struct ServiceHealthCheck {
+    service: String,
+    health_topic: Topic,
+    mqtt_schema: MqttSchema,
+    status: Status,
+}
+
+impl ServiceHealthCheck {
+    pub fn new(service: impl Into<String>, health_topic: Topic, mqtt_schema: &MqttSchema) -> Self {
+        ServiceHealthCheck {
+            service: service.into(),
+            health_topic,
+            mqtt_schema: mqtt_schema.clone(),
+            status: Status::default(),
+        }
+    }
+}
+
+impl HealthProbe for ServiceHealthCheck {
+    fn probe(&mut self) -> HealthCheck {
+        let service = &self.service;
+        match &self.status {
+            Status::Up => HealthCheck::up(),
+            Status::Down => HealthCheck::down(format!("{service} is down")),
+            status => HealthCheck {
+                status: status.clone(),
+                detail: Some(format!("{service} is {status}")),
+            },
+        }
+    }
+
+    fn subscriptions(&self) -> TopicFilter {
+        self.health_topic.clone().into()
+    }
+
+    fn update(&mut self, message: &MqttMessage) {
+        if let Ok(health_status) =
+            HealthStatus::try_from_health_status_message(message, &self.mqtt_schema)
+        {
+            self.status = health_status.status;
+        }
+    }
+}
+
+/// Count the commands in progress for a device
+pub struct CommandBacklogCheck {
+    commands: TopicFilter,
+    pending: HashSet<String>,
+    max_pending: usize,
+}
+
+impl CommandBacklogCheck {
+    /// The service is degraded when more than 20 commands are in progress
+    pub const DEFAULT_MAX_PENDING: usize = 20;
+
+    pub fn new(mqtt_schema: &MqttSchema, device: &EntityTopicId) -> Self {
+        CommandBacklogCheck {
+            commands: mqtt_schema.topics(EntityFilter::Entity(device), ChannelFilter::AnyCommand),
+            pending: HashSet::new(),
+            max_pending: Self::DEFAULT_MAX_PENDING,
+        }
+    }
+
+    pub fn with_max_pending(self, max_pending: usize) -> Self {
+        CommandBacklogCheck {
+            max_pending,
+            ..self
+        }
+    }
+}
+
+impl HealthProbe for CommandBacklogCheck {
+    fn probe(&mut self) -> HealthCheck {
+        let pending = self.pending.len();
+        let detail = format!("{pending} pending commands");
+        if pending > self.max_pending {
+            HealthCheck::degraded(detail)
+        } else {
+            HealthCheck::up().with_detail(detail)
+        }
+    }
+
+    fn subscriptions(&self) -> TopicFilter {
+        self.commands.clone()
+    }
+
+    fn update(&mut self, message: &MqttMessage) {
+        let topic = &message.topic.name;
+        match GenericCommandState::from_command_message(message) {
+            Ok(state) if !state.is_finished() && !state.is_cleared() => {
+                self.pending.insert(topic.clone());
+            }
+            _ => {
+                self.pending.remove(topic);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disk_space_check() {
+        let check = DiskSpaceCheck::new("/var/tedge");
+        assert_eq!(
+            check.check(50_000_000, 100_000_000),
+            HealthCheck::up().with_detail("50 MB (50%) free space left in /var/tedge")
+        );
+        assert_eq!(
+            check.check(1_000_000, 100_000_000),
+            HealthCheck::degraded("1 MB (1%) free space left in /var/tedge")
+        );
+    }
+
+    #[test]
+    fn command_backlog_check() {
+        let mqtt_schema = MqttSchema::new();
+        let device = EntityTopicId::default_main_device();
+        let mut check = CommandBacklogCheck::new(&mqtt_schema, &device).with_max_pending(1);
+        let command = |id: &str, payload: &str| {
+            let topic = Topic::new_unchecked(&format!("te/device/main///cmd/restart/{id}"));
+            MqttMessage::new(&topic, payload.to_string())
+        };
+
+        check.update(&command("1", r#"{"status":"executing"}"#));
+        assert_eq!(check.probe().status, Status::Up);
+
+        check.update(&command("2", r#"{"status":"init"}"#));
+        assert_eq!(check.probe(), HealthCheck::degraded("2 pending commands"));
+
+        check.update(&command("1", r#"{"status":"successful"}"#));
+        check.update(&command("2", ""));
+        assert_eq!(
+            check.probe(),
+            HealthCheck::up().with_detail("0 pending commands")
+        );
+    }
+}
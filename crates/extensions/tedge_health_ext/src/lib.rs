@@ -1,9 +1,18 @@
 mod actor;
+mod checks;
 
 #[cfg(test)]
 mod tests;
 
+pub use checks::*;
+
 use actor::HealthMonitorActor;
+use actor::MetricsPublisher;
+use actor::NamedCheck;
+use actor::SharedHealthStatus;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::sync::Mutex;
 use tedge_actors::Builder;
 use tedge_actors::DynSender;
 use tedge_actors::LinkError;
@@ -15,6 +24,7 @@ use tedge_actors::RuntimeRequestSink;
 use tedge_actors::SimpleMessageBoxBuilder;
 use tedge_api::entity::EntityType;
 use tedge_api::entity_store::EntityRegistrationMessage;
+use tedge_api::health::HealthStatus;
 use tedge_api::health::ServiceHealthTopic;
 use tedge_api::mqtt_topics::Channel;
 use tedge_api::mqtt_topics::MqttSchema;
@@ -28,6 +38,9 @@ use tedge_mqtt_ext::TopicFilter;
 pub struct HealthMonitorBuilder {
     registration_message: Option<MqttMessage>,
     health_topic: ServiceHealthTopic,
+    checks: Vec<NamedCheck>,
+    health_status: SharedHealthStatus,
+    metrics_publisher: Option<MetricsPublisher>,
     box_builder: SimpleMessageBoxBuilder<MqttMessage, MqttMessage>,
 }
 
@@ -94,9 +107,23 @@ impl HealthMonitorBuilder {
         let health_topic =
             ServiceHealthTopic::from_new_topic(service_topic_id, mqtt_schema, time_format);
 
+        let metrics_interval = service_config.metrics_interval.duration();
+        let metrics_publisher = (!metrics_interval.is_zero()).then(|| {
+            let metrics_topic = mqtt_schema.topic_for(
+                service_topic_id.entity(),
+                &Channel::Measurement {
+                    measurement_type: "tedge_actors".to_string(),
+                },
+            );
+            MetricsPublisher::new(metrics_topic, metrics_interval)
+        });
+
         let builder = HealthMonitorBuilder {
             health_topic,
             registration_message: Some(registration_message),
+            checks: Vec::new(),
+            health_status: Arc::new(Mutex::new(HealthStatus::from_checks(BTreeMap::new()))),
+            metrics_publisher,
             box_builder,
         };
 
@@ -110,11 +137,37 @@ impl HealthMonitorBuilder {
         builder
     }
 
+    /// Add a self-check to the health status of the service
+    ///
+    /// The MQTT actor is used to subscribe to the topics watched by the check, if any.
+    pub fn add_check(
+        &mut self,
+        name: impl Into<String>,
+        probe: impl HealthProbe,
+        mqtt: &mut impl MessageSource<MqttMessage, TopicFilter>,
+    ) {
+        let subscriptions = probe.subscriptions();
+        if !subscriptions.patterns().is_empty() {
+            mqtt.connect_sink(subscriptions.clone(), &self.box_builder);
+        }
+        self.checks.push(NamedCheck {
+            name: name.into(),
+            subscriptions,
+            probe: Box::new(probe),
+        });
+    }
+
     fn set_init_and_last_will(&self, config: MqttConfig) -> MqttConfig {
         let name = self.health_topic.to_owned();
         let _name = name.clone();
+        let health_status = self.health_status.clone();
         config
-            .with_initial_message(move || _name.up_message())
+            .with_initial_message(move || {
+                let health_status = health_status
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+                _name.status_message(&health_status)
+            })
             .with_last_will_message(name.down_message())
     }
 }
@@ -131,8 +184,14 @@ impl Builder<HealthMonitorActor> for HealthMonitorBuilder {
     fn try_build(self) -> Result<HealthMonitorActor, Self::Error> {
         let message_box = self.box_builder.build();
 
-        let actor =
-            HealthMonitorActor::new(self.registration_message, self.health_topic, message_box);
+        let actor = HealthMonitorActor::new(
+            self.registration_message,
+            self.health_topic,
+            self.checks,
+            self.health_status,
+            self.metrics_publisher,
+            message_box,
+        );
 
         Ok(actor)
     }
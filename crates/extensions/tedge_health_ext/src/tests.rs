@@ -1,4 +1,5 @@
 use crate::HealthMonitorBuilder;
+use crate::ServiceHealthCheck;
 use crate::TopicFilter;
 use std::time::Duration;
 use tedge_actors::test_helpers::MessageReceiverExt;
@@ -8,6 +9,7 @@ use tedge_actors::DynSender;
 use tedge_actors::MessageReceiver;
 use tedge_actors::MessageSink;
 use tedge_actors::MessageSource;
+use tedge_actors::Sender;
 use tedge_actors::SimpleMessageBox;
 use tedge_actors::SimpleMessageBoxBuilder;
 use tedge_api::mqtt_topics::EntityTopicId;
@@ -76,14 +78,157 @@ async fn health_check_set_init_and_last_will_message() -> Result<(), anyhow::Err
     Ok(())
 }
 
+#[tokio::test]
+async fn publish_actor_metrics_periodically() -> Result<(), anyhow::Error> {
+    let mut mqtt_config = MqttConfig::default();
+    let mut mqtt_message_box = spawn_a_health_check_actor_with_config(
+        "metrics-service",
+        &mut mqtt_config,
+        "service.metrics_interval = \"1s\"",
+    )
+    .await;
+
+    // skip registration and health messages
+    mqtt_message_box.skip(2).await;
+
+    let message = timeout(TEST_TIMEOUT, mqtt_message_box.recv())
+        .await?
+        .expect("a metrics message");
+    assert_eq!(
+        message.topic.name,
+        "te/device/main/service/metrics-service/m/tedge_actors"
+    );
+
+    // The message box of the health monitor itself is named after the service topic id
+    let metrics: serde_json::Value = serde_json::from_str(message.payload_str()?)?;
+    let health_box = &metrics["device_main_service_metrics-service"];
+    assert!(health_box["received"].is_u64(), "{metrics}");
+    assert!(health_box["sent"].as_u64().unwrap() >= 2, "{metrics}");
+    assert!(health_box["queued"].is_u64(), "{metrics}");
+
+    Ok(())
+}
+
+#[test]
+fn message_boxes_sharing_a_name_are_published_apart() {
+    let stats = |name: &str, id: u64| tedge_actors::MessageBoxStats {
+        name: name.to_string(),
+        id,
+        received: id,
+        ..Default::default()
+    };
+    let publisher = crate::actor::MetricsPublisher::new(
+        Topic::new_unchecked("te/device/main/service/test/m/tedge_actors"),
+        Duration::from_secs(60),
+    );
+
+    let message = publisher.metrics_message(&[
+        stats("MqttPublisher", 3),
+        stats("MqttPublisher", 7),
+        stats("Mqtt", 5),
+    ]);
+
+    let metrics: serde_json::Value = serde_json::from_str(message.payload_str().unwrap()).unwrap();
+    assert_eq!(metrics["MqttPublisher-3"]["received"], 3, "{metrics}");
+    assert_eq!(metrics["MqttPublisher-7"]["received"], 7, "{metrics}");
+    assert_eq!(metrics["Mqtt"]["received"], 5, "{metrics}");
+}
+
+#[tokio::test]
+async fn health_status_is_degraded_when_a_check_fails() -> Result<(), anyhow::Error> {
+    let mut mqtt_config = MqttConfig::default();
+    let mut health_mqtt_builder = MqttActorBuilder::new(&mut mqtt_config);
+    let mqtt_schema = MqttSchema::new();
+    let config = TEdgeConfig::load_toml_str("service.ty = \"service\"");
+    let service = Service {
+        service_topic_id: EntityTopicId::default_main_service("checked-service")
+            .unwrap()
+            .into(),
+        device_topic_id: EntityTopicId::default_main_device().into(),
+    };
+    let mut health_actor = HealthMonitorBuilder::from_service_topic_id(
+        service,
+        &mut health_mqtt_builder,
+        &mqtt_schema,
+        &config.service,
+    );
+    let bridge_health_topic =
+        Topic::new_unchecked("te/device/main/service/tedge-mapper-bridge-c8y/status/health");
+    health_actor.add_check(
+        "bridge",
+        ServiceHealthCheck::new(
+            "tedge-mapper-bridge-c8y",
+            bridge_health_topic.clone(),
+            &mqtt_schema,
+        ),
+        &mut health_mqtt_builder,
+    );
+    let actor = health_actor.build();
+    tokio::spawn(async move { actor.run().await });
+    let mut mqtt_message_box = health_mqtt_builder.build();
+
+    // skip registration message
+    mqtt_message_box.skip(1).await;
+
+    // The bridge status is not known yet
+    let message = timeout(TEST_TIMEOUT, mqtt_message_box.recv())
+        .await?
+        .expect("health message");
+    let health: serde_json::Value = serde_json::from_str(message.payload_str()?)?;
+    assert_eq!(health["status"], "degraded");
+
+    // The service is up as soon as the bridge is up
+    mqtt_message_box
+        .send(MqttMessage::new(&bridge_health_topic, r#"{"status":"up"}"#))
+        .await?;
+    let message = timeout(TEST_TIMEOUT, mqtt_message_box.recv())
+        .await?
+        .expect("health message");
+    let health: serde_json::Value = serde_json::from_str(message.payload_str()?)?;
+    assert_eq!(health["status"], "up");
+    assert_eq!(health["checks"]["bridge"]["status"], "up");
+
+    // And degraded when the bridge is down
+    mqtt_message_box
+        .send(MqttMessage::new(
+            &bridge_health_topic,
+            r#"{"status":"down"}"#,
+        ))
+        .await?;
+    let message = timeout(TEST_TIMEOUT, mqtt_message_box.recv())
+        .await?
+        .expect("health message");
+    let health: serde_json::Value = serde_json::from_str(message.payload_str()?)?;
+    assert_eq!(health["status"], "degraded");
+    assert_eq!(
+        health["checks"]["bridge"],
+        serde_json::json!({"status": "down", "detail": "tedge-mapper-bridge-c8y is down"})
+    );
+
+    Ok(())
+}
+
 async fn spawn_a_health_check_actor(
     service_to_be_monitored: &str,
     mqtt_config: &mut MqttConfig,
+) -> SimpleMessageBox<MqttMessage, MqttMessage> {
+    spawn_a_health_check_actor_with_config(
+        service_to_be_monitored,
+        mqtt_config,
+        "service.ty = \"service\"",
+    )
+    .await
+}
+
+async fn spawn_a_health_check_actor_with_config(
+    service_to_be_monitored: &str,
+    mqtt_config: &mut MqttConfig,
+    toml: &str,
 ) -> SimpleMessageBox<MqttMessage, MqttMessage> {
     let mut health_mqtt_builder = MqttActorBuilder::new(mqtt_config);
 
     let mqtt_schema = MqttSchema::new();
-    let config = TEdgeConfig::load_toml_str("service.ty = \"service\"");
+    let config = TEdgeConfig::load_toml_str(toml);
     let service = Service {
         service_topic_id: EntityTopicId::default_main_service(service_to_be_monitored)
             .unwrap()
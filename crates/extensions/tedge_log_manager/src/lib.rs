@@ -19,6 +19,7 @@ use tedge_actors::MappingSender;
 use tedge_actors::MessageSink;
 use tedge_actors::MessageSource;
 use tedge_actors::NoConfig;
+use tedge_actors::RestartableBuilder;
 use tedge_actors::RuntimeRequest;
 use tedge_actors::RuntimeRequestSink;
 use tedge_actors::Service;
@@ -190,6 +191,24 @@ impl Builder<LogManagerActor> for LogManagerBuilder {
     }
 }
 
+/// When restarted after a failure, the log manager reloads its plugin config
+/// and takes over the message box of the failed instance.
+impl RestartableBuilder<LogManagerActor> for LogManagerBuilder {
+    type Error = LinkError;
+
+    fn rebuild(&mut self) -> Result<LogManagerActor, Self::Error> {
+        let message_box = self.box_builder.rebuild()?;
+        let plugin_config = LogPluginConfig::new(&self.config.plugin_config_path);
+
+        Ok(LogManagerActor::new(
+            self.config.clone(),
+            plugin_config,
+            message_box,
+            self.upload_sender.sender_clone(),
+        ))
+    }
+}
+
 impl MessageSource<GenericCommandData, NoConfig> for LogManagerBuilder {
     fn connect_sink(&mut self, config: NoConfig, peer: &impl MessageSink<GenericCommandData>) {
         self.box_builder
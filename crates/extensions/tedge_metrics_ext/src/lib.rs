@@ -0,0 +1,189 @@
+//! Serve the metrics of a thin-edge service on a Prometheus `/metrics` endpoint
+//!
+//! The metrics are those collected by the [tedge_metrics] crate, exported in the OpenMetrics text format.
+//! As for the File Transfer Service, the endpoint is served over HTTPS if a certificate is configured,
+//! and the clients are required to authenticate with a certificate if a CA directory is configured.
+use anyhow::Context;
+use async_trait::async_trait;
+use axum::http::header::CONTENT_TYPE;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+use axum_tls::config::load_ssl_config;
+use axum_tls::config::PemReader;
+use axum_tls::config::TrustStoreLoader;
+use camino::Utf8PathBuf;
+use futures::channel::mpsc;
+use futures::FutureExt;
+use futures::StreamExt;
+use rustls::ServerConfig;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use tedge_actors::Actor;
+use tedge_actors::Builder;
+use tedge_actors::DynSender;
+use tedge_actors::RuntimeError;
+use tedge_actors::RuntimeRequest;
+use tedge_actors::RuntimeRequestSink;
+use tedge_config::OptionalConfig;
+use tedge_config::TEdgeConfig;
+use tokio::net::TcpListener;
+use tracing::info;
+
+#[derive(Debug, Clone)]
+pub struct MetricsServerConfig<CertKeyPath = Utf8PathBuf, CaPath = Utf8PathBuf> {
+    pub bind_addr: SocketAddr,
+    pub cert_path: OptionalConfig<CertKeyPath>,
+    pub key_path: OptionalConfig<CertKeyPath>,
+    pub ca_path: OptionalConfig<CaPath>,
+}
+
+impl MetricsServerConfig {
+    /// The configuration of a metrics endpoint served on the given port
+    pub fn from_tedge_config(tedge_config: &TEdgeConfig, port: u16) -> Self {
+        MetricsServerConfig {
+            bind_addr: SocketAddr::from((tedge_config.metrics.bind.address, port)),
+            cert_path: tedge_config.metrics.cert_path.clone(),
+            key_path: tedge_config.metrics.key_path.clone(),
+            ca_path: tedge_config.metrics.ca_path.clone(),
+        }
+    }
+}
+
+pub struct MetricsServerActor {
+    listener: TcpListener,
+    rustls_config: Option<ServerConfig>,
+    signal_receiver: mpsc::Receiver<RuntimeRequest>,
+}
+
+#[async_trait]
+impl Actor for MetricsServerActor {
+    fn name(&self) -> &str {
+        "MetricsServer"
+    }
+
+    async fn run(mut self) -> Result<(), RuntimeError> {
+        let listener = self.listener.into_std().map_err(Box::new)?;
+        let router = Router::new().route("/metrics", get(metrics));
+        let server = if let Some(rustls_config) = self.rustls_config {
+            axum_tls::start_tls_server(listener, rustls_config, router).boxed()
+        } else {
+            axum_server::from_tcp(listener)
+                .serve(router.into_make_service())
+                .boxed()
+        };
+
+        tokio::select! {
+            result = server => {
+                info!("Done");
+                Ok(result.map_err(Box::new)?)
+            }
+            Some(RuntimeRequest::Shutdown) = self.signal_receiver.next() => {
+                info!("Shutdown");
+                Ok(())
+            }
+        }
+    }
+}
+
+async fn metrics() -> impl IntoResponse {
+    (
+        [(CONTENT_TYPE, tedge_metrics::CONTENT_TYPE)],
+        tedge_metrics::encode(),
+    )
+}
+
+pub struct MetricsServerBuilder {
+    listener: TcpListener,
+    rustls_config: Option<ServerConfig>,
+    signal_sender: mpsc::Sender<RuntimeRequest>,
+    signal_receiver: mpsc::Receiver<RuntimeRequest>,
+}
+
+impl MetricsServerBuilder {
+    pub async fn try_bind(
+        config: MetricsServerConfig<impl PemReader, impl TrustStoreLoader>,
+    ) -> Result<Self, anyhow::Error> {
+        let listener = TcpListener::bind(config.bind_addr)
+            .await
+            .with_context(|| format!("Binding metrics server to {}", config.bind_addr))?;
+        let (signal_sender, signal_receiver) = mpsc::channel(10);
+
+        // The metrics are only worth recording when served
+        tedge_metrics::enable();
+
+        Ok(MetricsServerBuilder {
+            rustls_config: load_ssl_config(
+                config.cert_path,
+                config.key_path,
+                config.ca_path,
+                "Metrics endpoint",
+            )?,
+            listener,
+            signal_sender,
+            signal_receiver,
+        })
+    }
+
+    /// The address the server is actually bound to
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+}
+
+impl RuntimeRequestSink for MetricsServerBuilder {
+    fn get_signal_sender(&self) -> DynSender<RuntimeRequest> {
+        Box::new(self.signal_sender.clone())
+    }
+}
+
+impl Builder<MetricsServerActor> for MetricsServerBuilder {
+    type Error = Infallible;
+
+    fn try_build(self) -> Result<MetricsServerActor, Self::Error> {
+        Ok(MetricsServerActor {
+            listener: self.listener,
+            rustls_config: self.rustls_config,
+            signal_receiver: self.signal_receiver,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    static TEST_COUNTER: tedge_metrics::Family =
+        tedge_metrics::Family::counter("test_scrapes", "Test counter");
+
+    #[tokio::test]
+    async fn metrics_are_served_in_the_openmetrics_format() {
+        let config: MetricsServerConfig = MetricsServerConfig {
+            bind_addr: SocketAddr::from((Ipv4Addr::LOCALHOST, 0)),
+            cert_path: OptionalConfig::Empty("metrics.cert_path".into()),
+            key_path: OptionalConfig::Empty("metrics.key_path".into()),
+            ca_path: OptionalConfig::Empty("metrics.ca_path".into()),
+        };
+        let builder = MetricsServerBuilder::try_bind(config).await.unwrap();
+        let addr = builder.local_addr().unwrap();
+        let actor = builder.build();
+        tokio::spawn(actor.run());
+
+        TEST_COUNTER.inc(&[("service", "test")]);
+
+        let response = reqwest::get(format!("http://{addr}/metrics"))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers()[CONTENT_TYPE.as_str()],
+            tedge_metrics::CONTENT_TYPE
+        );
+        let body = response.text().await.unwrap();
+        assert!(
+            body.contains(r#"test_scrapes_total{service="test"} 1"#),
+            "{body}"
+        );
+        assert!(body.ends_with("# EOF\n"));
+    }
+}
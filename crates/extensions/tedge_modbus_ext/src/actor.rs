@@ -0,0 +1,664 @@
+use crate::client::ModbusClient;
+use crate::command::ModbusWriteCmd;
+use crate::command::ModbusWriteCmdPayload;
+use crate::config::BusConfig;
+use crate::config::DeviceConfig;
+use crate::config::ModbusConfig;
+use crate::error::ModbusError;
+use async_trait::async_trait;
+use serde_json::json;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use tedge_actors::Actor;
+use tedge_actors::Builder;
+use tedge_actors::ChannelError;
+use tedge_actors::CloneSender;
+use tedge_actors::DynSender;
+use tedge_actors::MessageReceiver;
+use tedge_actors::MessageSink;
+use tedge_actors::MessageSource;
+use tedge_actors::NoConfig;
+use tedge_actors::RuntimeError;
+use tedge_actors::RuntimeRequest;
+use tedge_actors::RuntimeRequestSink;
+use tedge_actors::Sender;
+use tedge_actors::SimpleMessageBox;
+use tedge_actors::SimpleMessageBoxBuilder;
+use tedge_api::commands::CommandPayload;
+use tedge_api::commands::CommandStatus;
+use tedge_api::entity::EntityType;
+use tedge_api::entity_store::EntityRegistrationMessage;
+use tedge_api::measurement::MeasurementVisitor;
+use tedge_api::measurement::ThinEdgeJsonSerializationError;
+use tedge_api::measurement::ThinEdgeJsonSerializer;
+use tedge_api::mqtt_topics::Channel;
+use tedge_api::mqtt_topics::ChannelFilter;
+use tedge_api::mqtt_topics::EntityFilter;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_mqtt_ext::MqttMessage;
+use tedge_mqtt_ext::TopicFilter;
+use time::OffsetDateTime;
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+use tokio::time::Instant;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+/// The number of `modbus_write` commands queued for a bus
+const COMMAND_QUEUE_SIZE: usize = 16;
+
+/// Poll the registers of Modbus devices, publishing their values as measurements of child devices,
+/// and write these registers on `modbus_write` commands
+///
+/// Each bus, i.e. each set of devices sharing a connection, is served by its own task,
+/// so a device that doesn't respond only delays the devices on the same bus.
+pub struct ModbusActor {
+    mqtt_schema: MqttSchema,
+    buses: Vec<BusConfig>,
+    messages: SimpleMessageBox<MqttMessage, MqttMessage>,
+}
+
+/// Serve the devices of a bus, polling their registers and executing the commands forwarded by the actor
+struct BusWorker {
+    mqtt_schema: MqttSchema,
+    client: ModbusClient,
+    devices: Vec<PolledDevice>,
+    commands: mpsc::Receiver<ModbusWriteCmd>,
+    messages: DynSender<MqttMessage>,
+}
+
+struct PolledDevice {
+    config: DeviceConfig,
+    next_poll: Instant,
+}
+
+#[async_trait]
+impl Actor for ModbusActor {
+    fn name(&self) -> &str {
+        "Modbus"
+    }
+
+    async fn run(mut self) -> Result<(), RuntimeError> {
+        self.register_devices().await?;
+
+        // The workers are aborted when the actor stops
+        let mut workers = JoinSet::new();
+        let mut device_queues = HashMap::new();
+        for bus in std::mem::take(&mut self.buses) {
+            let (queue, commands) = mpsc::channel(COMMAND_QUEUE_SIZE);
+            for device in &bus.devices {
+                device_queues.insert(device.name.entity().clone(), queue.clone());
+            }
+            let worker = BusWorker::new(
+                self.mqtt_schema.clone(),
+                bus,
+                commands,
+                self.messages.sender_clone(),
+            );
+            workers.spawn(worker.run());
+        }
+
+        loop {
+            tokio::select! {
+                message = self.messages.recv() => match message {
+                    Some(message) => self.process_command(&device_queues, message).await?,
+                    None => break,
+                },
+                Some(result) = workers.join_next() => match result {
+                    Ok(Ok(())) => (),
+                    Ok(Err(err)) => return Err(err.into()),
+                    Err(err) => error!("A Modbus bus worker failed: {err}"),
+                },
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl ModbusActor {
+    /// Register each Modbus device as a child device,
+    /// declaring the `modbus_write` capability for those with writable registers
+    async fn register_devices(&mut self) -> Result<(), ChannelError> {
+        for device in self.buses.iter().flat_map(|bus| &bus.devices) {
+            let entity = device.name.entity();
+            let registration =
+                EntityRegistrationMessage::new_custom(entity.clone(), EntityType::ChildDevice)
+                    .with_other_fragment("name".to_string(), json!(device.name.as_str()))
+                    .with_other_fragment("type".to_string(), json!("modbus"))
+                    .to_mqtt_message(&self.mqtt_schema);
+            self.messages.send(registration).await?;
+
+            if device.has_writable_registers() {
+                let capability = ModbusWriteCmd::capability_message(&self.mqtt_schema, entity);
+                self.messages.send(capability).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Forward a `modbus_write` command to the worker of the bus of the target device
+    async fn process_command(
+        &mut self,
+        device_queues: &HashMap<EntityTopicId, mpsc::Sender<ModbusWriteCmd>>,
+        message: MqttMessage,
+    ) -> Result<(), ChannelError> {
+        let mut command = match ModbusWriteCmd::parse(&self.mqtt_schema, message) {
+            Ok(Some(command)) => command,
+            Ok(None) => return Ok(()),
+            Err(err) => {
+                error!("Invalid modbus_write command: {err}");
+                return Ok(());
+            }
+        };
+        if command.status() != CommandStatus::Init {
+            return Ok(());
+        }
+        let Some(queue) = device_queues.get(&command.target) else {
+            warn!(
+                "Ignoring a modbus_write command for {}, which is not a Modbus device",
+                command.target
+            );
+            return Ok(());
+        };
+
+        command.executing();
+        self.messages
+            .send(command.command_message(&self.mqtt_schema))
+            .await?;
+        if queue.send(command).await.is_err() {
+            error!("The Modbus bus worker has stopped");
+        }
+        Ok(())
+    }
+}
+
+impl BusWorker {
+    fn new(
+        mqtt_schema: MqttSchema,
+        bus: BusConfig,
+        commands: mpsc::Receiver<ModbusWriteCmd>,
+        messages: DynSender<MqttMessage>,
+    ) -> Self {
+        let now = Instant::now();
+        let devices = bus
+            .devices
+            .into_iter()
+            .map(|config| PolledDevice {
+                config,
+                next_poll: now,
+            })
+            .collect();
+
+        BusWorker {
+            mqtt_schema,
+            client: ModbusClient::new(&bus.connection),
+            devices,
+            commands,
+            messages,
+        }
+    }
+
+    async fn run(mut self) -> Result<(), ChannelError> {
+        loop {
+            let next_poll = self.devices.iter().map(|device| device.next_poll).min();
+            tokio::select! {
+                // Commands are given priority over polling
+                biased;
+
+                command = self.commands.recv() => match command {
+                    Some(command) => self.execute_command(command).await?,
+                    None => break,
+                },
+                _ = sleep_until(next_poll) => self.poll_devices().await?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Poll the devices which poll interval is over
+    async fn poll_devices(&mut self) -> Result<(), ChannelError> {
+        let now = Instant::now();
+        for index in 0..self.devices.len() {
+            if self.devices[index].next_poll > now {
+                continue;
+            }
+            let values = self.poll(index).await?;
+            let device = &mut self.devices[index];
+            device.next_poll = Instant::now() + device.config.poll_interval;
+            if values.is_empty() {
+                continue;
+            }
+
+            match thin_edge_json(&values) {
+                Ok(payload) => {
+                    let channel = Channel::Measurement {
+                        measurement_type: device.config.measurement_type.clone(),
+                    };
+                    let topic = self
+                        .mqtt_schema
+                        .topic_for(device.config.name.entity(), &channel);
+                    self.messages
+                        .send(MqttMessage::new(&topic, payload))
+                        .await?;
+                }
+                Err(err) => error!(
+                    "Error while encoding the measurements of the Modbus device {}: {err}",
+                    device.config.name
+                ),
+            }
+        }
+        Ok(())
+    }
+
+    /// Read all the registers of a device
+    ///
+    /// The registers that cannot be read are skipped,
+    /// and the remaining ones too if the device is no longer reachable.
+    /// The pending commands are executed between two reads.
+    async fn poll(&mut self, index: usize) -> Result<Vec<(String, f64)>, ChannelError> {
+        let mut values = Vec::new();
+        for register in 0..self.devices[index].config.registers.len() {
+            while let Ok(command) = self.commands.try_recv() {
+                self.execute_command(command).await?;
+            }
+
+            let device = &self.devices[index].config;
+            let register = &device.registers[register];
+            match self.client.read(device, register).await {
+                Ok(value) => values.push((register.name.clone(), value)),
+                Err(err) => {
+                    warn!(
+                        "Failed to read the register {} of the Modbus device {}: {err}",
+                        register.name, device.name
+                    );
+                    if err.is_connection_lost() {
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(values)
+    }
+
+    async fn execute_command(&mut self, mut command: ModbusWriteCmd) -> Result<(), ChannelError> {
+        let Some(device) = self
+            .devices
+            .iter()
+            .find(|device| device.config.name.entity() == &command.target)
+        else {
+            return Ok(());
+        };
+        let device = &device.config;
+
+        let register = command.payload.register.clone();
+        let value = command.payload.value;
+        match write(&mut self.client, device, &register, value).await {
+            Ok(()) => {
+                info!(
+                    "Written {value} into the register {register} of the Modbus device {}",
+                    device.name
+                );
+                command.successful()
+            }
+            Err(err) => command.failed(format!(
+                "Failed to write the register {register} of the Modbus device {}: {err}",
+                device.name
+            )),
+        }
+        self.messages
+            .send(command.command_message(&self.mqtt_schema))
+            .await
+    }
+}
+
+async fn write(
+    client: &mut ModbusClient,
+    device: &DeviceConfig,
+    register: &str,
+    value: f64,
+) -> Result<(), ModbusError> {
+    let register = device
+        .register(register)
+        .ok_or_else(|| ModbusError::UnknownRegister(register.to_string()))?;
+    client.write(device, register, value).await
+}
+
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+fn thin_edge_json(values: &[(String, f64)]) -> Result<String, ThinEdgeJsonSerializationError> {
+    let mut serializer = ThinEdgeJsonSerializer::new();
+    serializer.visit_timestamp(OffsetDateTime::now_utc())?;
+    for (name, value) in values {
+        serializer.visit_measurement(name, *value)?;
+    }
+    serializer.into_string()
+}
+
+pub struct ModbusActorBuilder {
+    config: ModbusConfig,
+    mqtt_schema: MqttSchema,
+    box_builder: SimpleMessageBoxBuilder<MqttMessage, MqttMessage>,
+}
+
+impl ModbusActorBuilder {
+    pub fn new(
+        config: ModbusConfig,
+        mqtt_schema: MqttSchema,
+        mqtt: &mut (impl MessageSource<MqttMessage, TopicFilter> + MessageSink<MqttMessage>),
+    ) -> Self {
+        let mut box_builder = SimpleMessageBoxBuilder::new("Modbus", 16);
+        box_builder.connect_sink(NoConfig, mqtt);
+        mqtt.connect_sink(Self::subscriptions(&mqtt_schema), &box_builder);
+
+        ModbusActorBuilder {
+            config,
+            mqtt_schema,
+            box_builder,
+        }
+    }
+
+    fn subscriptions(mqtt_schema: &MqttSchema) -> TopicFilter {
+        mqtt_schema.topics(
+            EntityFilter::AnyEntity,
+            ChannelFilter::Command(ModbusWriteCmdPayload::operation_type()),
+        )
+    }
+}
+
+impl RuntimeRequestSink for ModbusActorBuilder {
+    fn get_signal_sender(&self) -> DynSender<RuntimeRequest> {
+        self.box_builder.get_signal_sender()
+    }
+}
+
+impl Builder<ModbusActor> for ModbusActorBuilder {
+    type Error = Infallible;
+
+    fn try_build(self) -> Result<ModbusActor, Self::Error> {
+        Ok(self.build())
+    }
+
+    fn build(self) -> ModbusActor {
+        ModbusActor {
+            mqtt_schema: self.mqtt_schema,
+            buses: self.config.buses(),
+            messages: self.box_builder.build(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use tedge_actors::test_helpers::MessageReceiverExt;
+    use tedge_actors::test_helpers::TimedMessageBox;
+    use tedge_mqtt_ext::Topic;
+    use tokio::io::AsyncReadExt;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::TcpListener;
+    use tokio::net::TcpStream;
+
+    const TEST_TIMEOUT: Duration = Duration::from_secs(3);
+
+    #[tokio::test]
+    async fn registers_are_published_as_measurements_of_a_child_device() {
+        let simulator = ModbusSimulator::start(&[(0, 215), (1, 0x0001), (2, 0x0002)]).await;
+        let mut mqtt = spawn_modbus_actor(&format!(
+            r#"
+            [[device]]
+            name = "plc01"
+            protocol = "tcp"
+            address = "{}"
+            poll_interval = "1h"
+
+            [[device.register]]
+            name = "temperature"
+            address = 0
+            scale = 0.1
+
+            [[device.register]]
+            name = "counter"
+            address = 1
+            data_type = "u32"
+            "#,
+            simulator.address
+        ))
+        .await;
+
+        let registration = mqtt.recv().await.unwrap();
+        assert_eq!(registration.topic.name, "te/device/plc01//");
+        let registration: serde_json::Value =
+            serde_json::from_str(registration.payload_str().unwrap()).unwrap();
+        assert_eq!(registration["@type"], "child-device");
+        assert_eq!(registration["name"], "plc01");
+
+        let capability = mqtt.recv().await.unwrap();
+        assert_eq!(capability.topic.name, "te/device/plc01///cmd/modbus_write");
+
+        let measurement = mqtt.recv().await.unwrap();
+        assert_eq!(measurement.topic.name, "te/device/plc01///m/modbus");
+        let measurement: serde_json::Value =
+            serde_json::from_str(measurement.payload_str().unwrap()).unwrap();
+        assert_eq!(measurement["temperature"], 21.5);
+        assert_eq!(measurement["counter"], 65538.0);
+    }
+
+    #[tokio::test]
+    async fn registers_are_written_on_modbus_write_commands() {
+        let simulator = ModbusSimulator::start(&[]).await;
+        let mut mqtt = spawn_modbus_actor(&format!(
+            r#"
+            [[device]]
+            name = "plc01"
+            protocol = "tcp"
+            address = "{}"
+            poll_interval = "1h"
+
+            [[device.register]]
+            name = "setpoint"
+            address = 10
+            data_type = "i32"
+            scale = 0.5
+            "#,
+            simulator.address
+        ))
+        .await;
+        mqtt.skip(3).await;
+
+        mqtt.send(MqttMessage::new(
+            &Topic::new_unchecked("te/device/plc01///cmd/modbus_write/1234"),
+            r#"{"status":"init","register":"setpoint","value":-21.5}"#,
+        ))
+        .await
+        .unwrap();
+
+        let executing = mqtt.recv().await.unwrap();
+        assert_eq!(
+            executing.topic.name,
+            "te/device/plc01///cmd/modbus_write/1234"
+        );
+        assert!(executing.payload_str().unwrap().contains("executing"));
+        let successful = mqtt.recv().await.unwrap();
+        assert!(successful.payload_str().unwrap().contains("successful"));
+        assert_eq!(simulator.register(10), Some(0xffff));
+        assert_eq!(simulator.register(11), Some(0xffd5));
+
+        mqtt.send(MqttMessage::new(
+            &Topic::new_unchecked("te/device/plc01///cmd/modbus_write/5678"),
+            r#"{"status":"init","register":"unknown","value":1}"#,
+        ))
+        .await
+        .unwrap();
+
+        mqtt.skip(1).await;
+        let failed = mqtt.recv().await.unwrap();
+        let failed: serde_json::Value =
+            serde_json::from_str(failed.payload_str().unwrap()).unwrap();
+        assert_eq!(failed["status"], "failed");
+        assert!(failed["reason"].as_str().unwrap().contains("unknown"));
+    }
+
+    #[tokio::test]
+    async fn a_device_not_responding_does_not_delay_the_commands_for_other_devices() {
+        let simulator = ModbusSimulator::start(&[]).await;
+        let silent_device = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let silent_address = silent_device.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let mut connections = Vec::new();
+            while let Ok((stream, _)) = silent_device.accept().await {
+                connections.push(stream);
+            }
+        });
+        let mut mqtt = spawn_modbus_actor(&format!(
+            r#"
+            [[device]]
+            name = "silent"
+            protocol = "tcp"
+            address = "{silent_address}"
+            poll_interval = "10ms"
+            timeout = "10s"
+
+            [[device.register]]
+            name = "temperature"
+            address = 0
+
+            [[device]]
+            name = "plc01"
+            protocol = "tcp"
+            address = "{}"
+            poll_interval = "1h"
+
+            [[device.register]]
+            name = "setpoint"
+            address = 10
+            "#,
+            simulator.address
+        ))
+        .await;
+
+        let command_topic = "te/device/plc01///cmd/modbus_write/1234";
+        mqtt.send(MqttMessage::new(
+            &Topic::new_unchecked(command_topic),
+            r#"{"status":"init","register":"setpoint","value":42}"#,
+        ))
+        .await
+        .unwrap();
+
+        // Skipping the registration messages and the measurements
+        let mut statuses = Vec::new();
+        while statuses.len() < 2 {
+            let message = mqtt.recv().await.expect("the command to be executed");
+            if message.topic.name == command_topic {
+                statuses.push(message.payload_str().unwrap().to_string());
+            }
+        }
+        assert!(statuses[0].contains("executing"));
+        assert!(statuses[1].contains("successful"));
+        assert_eq!(simulator.register(10), Some(42));
+    }
+
+    async fn spawn_modbus_actor(
+        config: &str,
+    ) -> TimedMessageBox<SimpleMessageBox<MqttMessage, MqttMessage>> {
+        let config: ModbusConfig = toml::from_str(config).unwrap();
+        let mut mqtt: SimpleMessageBoxBuilder<MqttMessage, MqttMessage> =
+            SimpleMessageBoxBuilder::new("MQTT", 16);
+        let actor = ModbusActorBuilder::new(config, MqttSchema::default(), &mut mqtt).build();
+        tokio::spawn(async move { actor.run().await });
+        mqtt.build().with_timeout(TEST_TIMEOUT)
+    }
+
+    /// A Modbus TCP server exposing holding registers
+    struct ModbusSimulator {
+        address: String,
+        registers: Arc<Mutex<HashMap<u16, u16>>>,
+    }
+
+    impl ModbusSimulator {
+        async fn start(registers: &[(u16, u16)]) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let address = listener.local_addr().unwrap().to_string();
+            let registers = Arc::new(Mutex::new(registers.iter().copied().collect()));
+            let served_registers = registers.clone();
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    tokio::spawn(serve(stream, served_registers.clone()));
+                }
+            });
+            ModbusSimulator { address, registers }
+        }
+
+        fn register(&self, address: u16) -> Option<u16> {
+            self.registers.lock().unwrap().get(&address).copied()
+        }
+    }
+
+    async fn serve(mut stream: TcpStream, registers: Arc<Mutex<HashMap<u16, u16>>>) {
+        let mut header = [0u8; 7];
+        while stream.read_exact(&mut header).await.is_ok() {
+            let length = u16::from_be_bytes([header[4], header[5]]) as usize;
+            let mut pdu = vec![0u8; length - 1];
+            if stream.read_exact(&mut pdu).await.is_err() {
+                return;
+            }
+
+            let response = respond(&pdu, &mut registers.lock().unwrap());
+            let mut frame = header[..4].to_vec();
+            frame.extend_from_slice(&(response.len() as u16 + 1).to_be_bytes());
+            frame.push(header[6]);
+            frame.extend_from_slice(&response);
+            if stream.write_all(&frame).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    fn respond(pdu: &[u8], registers: &mut HashMap<u16, u16>) -> Vec<u8> {
+        let function = pdu[0];
+        let address = u16::from_be_bytes([pdu[1], pdu[2]]);
+        match function {
+            // Read holding registers, read input registers
+            0x03 | 0x04 => {
+                let count = u16::from_be_bytes([pdu[3], pdu[4]]);
+                let mut response = vec![function, (count * 2) as u8];
+                for i in 0..count {
+                    let value = registers.get(&(address + i)).copied().unwrap_or(0);
+                    response.extend_from_slice(&value.to_be_bytes());
+                }
+                response
+            }
+            // Write single register
+            0x06 => {
+                registers.insert(address, u16::from_be_bytes([pdu[3], pdu[4]]));
+                pdu.to_vec()
+            }
+            // Write multiple registers
+            0x10 => {
+                let count = u16::from_be_bytes([pdu[3], pdu[4]]);
+                for i in 0..count {
+                    let offset = 6 + 2 * i as usize;
+                    registers.insert(
+                        address + i,
+                        u16::from_be_bytes([pdu[offset], pdu[offset + 1]]),
+                    );
+                }
+                pdu[..5].to_vec()
+            }
+            // Illegal function
+            _ => vec![function | 0x80, 0x01],
+        }
+    }
+}
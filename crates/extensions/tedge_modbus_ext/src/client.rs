@@ -0,0 +1,198 @@
+use crate::config::Connection;
+use crate::config::DeviceConfig;
+use crate::config::Parity;
+use crate::config::RegisterConfig;
+use crate::config::RegisterTable;
+use crate::error::ModbusError;
+use std::io;
+use tokio_modbus::client::rtu;
+use tokio_modbus::client::tcp;
+use tokio_modbus::client::Context;
+use tokio_modbus::client::Reader;
+use tokio_modbus::client::Writer;
+use tokio_modbus::prelude::SlaveContext;
+use tokio_modbus::Slave;
+use tokio_serial::SerialStream;
+
+/// A client reading and writing the registers of the Modbus devices sharing a connection
+///
+/// The connection is established on first use, and re-established after a transport error or a timeout.
+/// Each request is addressed to the unit id of the target device,
+/// so the devices on the same serial line share a single serial port.
+pub struct ModbusClient {
+    connection: Connection,
+    context: Option<Context>,
+}
+
+impl ModbusClient {
+    pub fn new(connection: &Connection) -> Self {
+        ModbusClient {
+            connection: connection.clone(),
+            context: None,
+        }
+    }
+
+    /// Read the current value of a register of a device
+    pub async fn read(
+        &mut self,
+        device: &DeviceConfig,
+        register: &RegisterConfig,
+    ) -> Result<f64, ModbusError> {
+        let timeout = device.timeout;
+        let result = tokio::time::timeout(timeout, self.try_read(device, register))
+            .await
+            .unwrap_or(Err(ModbusError::Timeout(timeout)));
+        self.check_connection(result)
+    }
+
+    /// Write a new value into a register of a device
+    pub async fn write(
+        &mut self,
+        device: &DeviceConfig,
+        register: &RegisterConfig,
+        value: f64,
+    ) -> Result<(), ModbusError> {
+        if !register.table.is_writable() {
+            return Err(ModbusError::ReadOnlyRegister(register.name.clone()));
+        }
+        let words = register.encode(value)?;
+
+        let timeout = device.timeout;
+        let result = tokio::time::timeout(timeout, self.try_write(device, register, &words))
+            .await
+            .unwrap_or(Err(ModbusError::Timeout(timeout)));
+        self.check_connection(result)
+    }
+
+    async fn try_read(
+        &mut self,
+        device: &DeviceConfig,
+        register: &RegisterConfig,
+    ) -> Result<f64, ModbusError> {
+        let address = register.address;
+        let count = register.word_count();
+        let context = self.context(device).await?;
+        let words = match register.table {
+            RegisterTable::Coil => bits_as_words(context.read_coils(address, 1).await??),
+            RegisterTable::DiscreteInput => {
+                bits_as_words(context.read_discrete_inputs(address, 1).await??)
+            }
+            RegisterTable::Holding => context.read_holding_registers(address, count).await??,
+            RegisterTable::Input => context.read_input_registers(address, count).await??,
+        };
+        Ok(register.decode(&words))
+    }
+
+    async fn try_write(
+        &mut self,
+        device: &DeviceConfig,
+        register: &RegisterConfig,
+        words: &[u16],
+    ) -> Result<(), ModbusError> {
+        let address = register.address;
+        let context = self.context(device).await?;
+        match (register.table, words) {
+            (RegisterTable::Coil, [word]) => {
+                context.write_single_coil(address, *word != 0).await??
+            }
+            (RegisterTable::Holding, [word]) => {
+                context.write_single_register(address, *word).await??
+            }
+            (RegisterTable::Holding, words) => {
+                context.write_multiple_registers(address, words).await??
+            }
+            _ => return Err(ModbusError::ReadOnlyRegister(register.name.clone())),
+        }
+        Ok(())
+    }
+
+    /// Return the client context addressing the given device, connecting if not connected yet
+    async fn context(&mut self, device: &DeviceConfig) -> Result<&mut Context, ModbusError> {
+        let slave = Slave(device.unit_id);
+        let mut context = match self.context.take() {
+            Some(context) => context,
+            None => connect(&self.connection, slave).await.map_err(|source| {
+                ModbusError::Connection {
+                    device: device.name.to_string(),
+                    source,
+                }
+            })?,
+        };
+        context.set_slave(slave);
+        Ok(self.context.insert(context))
+    }
+
+    /// Drop the connection when the device is no longer reachable
+    fn check_connection<T>(&mut self, result: Result<T, ModbusError>) -> Result<T, ModbusError> {
+        if let Err(err) = &result {
+            if err.is_connection_lost() {
+                self.context = None;
+            }
+        }
+        result
+    }
+}
+
+async fn connect(connection: &Connection, slave: Slave) -> io::Result<Context> {
+    match connection {
+        Connection::Tcp { address } => {
+            let socket_addr = tokio::net::lookup_host(address)
+                .await?
+                .next()
+                .ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::NotFound, format!("Cannot resolve {address}"))
+                })?;
+            tcp::connect_slave(socket_addr, slave).await
+        }
+        Connection::Rtu {
+            port,
+            baud_rate,
+            parity,
+            data_bits,
+            stop_bits,
+        } => {
+            let builder = tokio_serial::new(port.as_str(), *baud_rate)
+                .parity(serial_parity(*parity))
+                .data_bits(serial_data_bits(*data_bits)?)
+                .stop_bits(serial_stop_bits(*stop_bits)?);
+            let stream = SerialStream::open(&builder)?;
+            Ok(rtu::attach_slave(stream, slave))
+        }
+    }
+}
+
+fn bits_as_words(bits: Vec<bool>) -> Vec<u16> {
+    bits.into_iter().map(u16::from).collect()
+}
+
+fn serial_parity(parity: Parity) -> tokio_serial::Parity {
+    match parity {
+        Parity::None => tokio_serial::Parity::None,
+        Parity::Even => tokio_serial::Parity::Even,
+        Parity::Odd => tokio_serial::Parity::Odd,
+    }
+}
+
+fn serial_data_bits(data_bits: u8) -> io::Result<tokio_serial::DataBits> {
+    match data_bits {
+        5 => Ok(tokio_serial::DataBits::Five),
+        6 => Ok(tokio_serial::DataBits::Six),
+        7 => Ok(tokio_serial::DataBits::Seven),
+        8 => Ok(tokio_serial::DataBits::Eight),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid number of data bits: {data_bits}"),
+        )),
+    }
+}
+
+fn serial_stop_bits(stop_bits: u8) -> io::Result<tokio_serial::StopBits> {
+    match stop_bits {
+        1 => Ok(tokio_serial::StopBits::One),
+        2 => Ok(tokio_serial::StopBits::Two),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid number of stop bits: {stop_bits}"),
+        )),
+    }
+}
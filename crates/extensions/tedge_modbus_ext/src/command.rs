@@ -0,0 +1,45 @@
+use serde::Deserialize;
+use serde::Serialize;
+use tedge_api::commands::Command;
+use tedge_api::commands::CommandPayload;
+use tedge_api::commands::CommandStatus;
+use tedge_api::mqtt_topics::OperationType;
+use tedge_api::Jsonify;
+
+/// The name of the command used to write a register of a Modbus device
+pub const MODBUS_WRITE_OPERATION: &str = "modbus_write";
+
+/// Command to write a value into a register of a Modbus device
+///
+/// ```json
+/// {"status": "init", "register": "setpoint", "value": 21.5}
+/// ```
+pub type ModbusWriteCmd = Command<ModbusWriteCmdPayload>;
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct ModbusWriteCmdPayload {
+    #[serde(flatten)]
+    pub status: CommandStatus,
+
+    /// The name of the register, as defined in the Modbus configuration
+    pub register: String,
+
+    /// The value to write, before scaling
+    pub value: f64,
+}
+
+impl Jsonify for ModbusWriteCmdPayload {}
+
+impl CommandPayload for ModbusWriteCmdPayload {
+    fn operation_type() -> OperationType {
+        OperationType::Custom(MODBUS_WRITE_OPERATION.to_string())
+    }
+
+    fn status(&self) -> CommandStatus {
+        self.status.clone()
+    }
+
+    fn set_status(&mut self, status: CommandStatus) {
+        self.status = status
+    }
+}
@@ -0,0 +1,639 @@
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use serde::Deserialize;
+use serde::Deserializer;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+use std::time::Duration;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_api::mqtt_topics::TopicIdError;
+
+pub const MODBUS_CONFIG_FILE_NAME: &str = "modbus.toml";
+
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(3);
+const DEFAULT_MEASUREMENT_TYPE: &str = "modbus";
+const DEFAULT_BAUD_RATE: u32 = 9600;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ModbusConfigError {
+    #[error("Failed to read {path}: {source}")]
+    ReadConfig {
+        path: Utf8PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("Invalid Modbus configuration {path}: {source}")]
+    InvalidConfig {
+        path: Utf8PathBuf,
+        source: toml::de::Error,
+    },
+
+    #[error("The Modbus device {device:?} is defined twice")]
+    DuplicatedDevice { device: String },
+
+    #[error("The Modbus device {device:?} is connected to {endpoint} with settings conflicting with the other devices on {endpoint}")]
+    ConflictingConnection { device: String, endpoint: String },
+
+    #[error("The Modbus device {device:?} has the same unit id {unit_id} as another device on {endpoint}")]
+    DuplicatedUnitId {
+        device: String,
+        unit_id: u8,
+        endpoint: String,
+    },
+
+    #[error("The register {register:?} of the Modbus device {device:?} is defined twice")]
+    DuplicatedRegister { device: String, register: String },
+
+    #[error("Invalid register {register:?} of the Modbus device {device:?}: {reason}")]
+    InvalidRegister {
+        device: String,
+        register: String,
+        reason: String,
+    },
+}
+
+/// Configuration of the Modbus devices, as read from `{config_dir}/mappers/modbus.toml`
+///
+/// ```toml
+/// [[device]]
+/// name = "plc01"
+/// protocol = "tcp"
+/// address = "192.168.1.10:502"
+/// unit_id = 1
+/// poll_interval = "1s"
+///
+/// [[device.register]]
+/// name = "temperature"
+/// table = "holding"
+/// address = 0
+/// data_type = "i16"
+/// scale = 0.1
+/// ```
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ModbusConfig {
+    #[serde(default, rename = "device")]
+    pub devices: Vec<DeviceConfig>,
+}
+
+/// A Modbus device, registered as a child device named after the device
+#[derive(Clone, Debug, Deserialize)]
+pub struct DeviceConfig {
+    pub name: DeviceName,
+
+    #[serde(flatten)]
+    pub connection: Connection,
+
+    /// The Modbus unit identifier of the device, a.k.a. the slave id
+    #[serde(default = "default_unit_id")]
+    pub unit_id: u8,
+
+    #[serde(
+        default = "default_poll_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub poll_interval: Duration,
+
+    /// The maximum time to wait for the device to connect or respond
+    #[serde(default = "default_timeout", deserialize_with = "deserialize_duration")]
+    pub timeout: Duration,
+
+    /// The type of the measurements published for this device
+    #[serde(default = "default_measurement_type")]
+    pub measurement_type: String,
+
+    #[serde(default, rename = "register")]
+    pub registers: Vec<RegisterConfig>,
+}
+
+/// The name of a Modbus device, which is also the id of the child device it is registered as
+#[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "String")]
+pub struct DeviceName {
+    name: String,
+    entity: EntityTopicId,
+}
+
+impl DeviceName {
+    pub fn as_str(&self) -> &str {
+        &self.name
+    }
+
+    /// The child device this Modbus device is registered as
+    pub fn entity(&self) -> &EntityTopicId {
+        &self.entity
+    }
+}
+
+impl TryFrom<String> for DeviceName {
+    type Error = TopicIdError;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        let entity = EntityTopicId::default_child_device(&name)?;
+        Ok(DeviceName { name, entity })
+    }
+}
+
+impl fmt::Display for DeviceName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.name.fmt(f)
+    }
+}
+
+/// How to connect a Modbus device
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(tag = "protocol", rename_all = "lowercase")]
+pub enum Connection {
+    /// Modbus TCP, the address being given as `host:port`
+    Tcp { address: String },
+
+    /// Modbus RTU, over a serial port
+    Rtu {
+        port: Utf8PathBuf,
+
+        #[serde(default = "default_baud_rate")]
+        baud_rate: u32,
+
+        #[serde(default)]
+        parity: Parity,
+
+        #[serde(default = "default_data_bits")]
+        data_bits: u8,
+
+        #[serde(default = "default_stop_bits")]
+        stop_bits: u8,
+    },
+}
+
+impl Connection {
+    /// The TCP address or the serial port of the connection
+    pub fn endpoint(&self) -> &str {
+        match self {
+            Connection::Tcp { address } => address,
+            Connection::Rtu { port, .. } => port.as_str(),
+        }
+    }
+}
+
+/// The Modbus devices sharing a connection, notably the devices on the same serial line
+///
+/// The devices of a bus are accessed one after the other over a single connection,
+/// each request being addressed to the unit id of a device.
+#[derive(Clone, Debug)]
+pub struct BusConfig {
+    pub connection: Connection,
+    pub devices: Vec<DeviceConfig>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Parity {
+    #[default]
+    None,
+    Even,
+    Odd,
+}
+
+/// A value read from a Modbus device and published as a measurement
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct RegisterConfig {
+    /// The name of the measurement
+    pub name: String,
+
+    #[serde(default)]
+    pub table: RegisterTable,
+
+    /// The address of the (first) register
+    pub address: u16,
+
+    /// The type of the value, `bool` for coils and discrete inputs, `u16` for registers by default
+    pub data_type: Option<DataType>,
+
+    /// The order of the registers of values spanning several registers
+    #[serde(default)]
+    pub word_order: WordOrder,
+
+    /// The factor applied to the raw value
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+
+    /// The offset added to the scaled value
+    #[serde(default)]
+    pub offset: f64,
+}
+
+/// The four Modbus tables
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RegisterTable {
+    Coil,
+    DiscreteInput,
+    #[default]
+    Holding,
+    Input,
+}
+
+impl RegisterTable {
+    /// Coils and discrete inputs hold single bits, holding and input registers 16-bit words
+    pub fn is_bit_table(self) -> bool {
+        matches!(self, RegisterTable::Coil | RegisterTable::DiscreteInput)
+    }
+
+    /// Only coils and holding registers can be written
+    pub fn is_writable(self) -> bool {
+        matches!(self, RegisterTable::Coil | RegisterTable::Holding)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DataType {
+    Bool,
+    U16,
+    I16,
+    U32,
+    I32,
+    F32,
+    U64,
+    I64,
+    F64,
+}
+
+/// The order of the 16-bit words of a value spanning several registers
+///
+/// The bytes of each word are always big-endian, as mandated by the Modbus specification.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum WordOrder {
+    /// Most significant word first
+    #[default]
+    Big,
+    /// Least significant word first
+    Little,
+}
+
+impl ModbusConfig {
+    /// Read the configuration of the Modbus devices, a missing file defining no devices
+    pub fn read(config_dir: &Utf8Path) -> Result<Self, ModbusConfigError> {
+        let path = config_dir.join("mappers").join(MODBUS_CONFIG_FILE_NAME);
+        match std::fs::read_to_string(&path) {
+            Ok(content) => {
+                let config: ModbusConfig = toml::from_str(&content)
+                    .map_err(|source| ModbusConfigError::InvalidConfig { path, source })?;
+                config.validate()?;
+                Ok(config)
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(ModbusConfig::default()),
+            Err(source) => Err(ModbusConfigError::ReadConfig { path, source }),
+        }
+    }
+
+    /// Group the devices sharing a connection, in the order of their definition
+    pub fn buses(&self) -> Vec<BusConfig> {
+        let mut buses: Vec<BusConfig> = Vec::new();
+        for device in &self.devices {
+            let endpoint = device.connection.endpoint();
+            match buses
+                .iter_mut()
+                .find(|bus| bus.connection.endpoint() == endpoint)
+            {
+                Some(bus) => bus.devices.push(device.clone()),
+                None => buses.push(BusConfig {
+                    connection: device.connection.clone(),
+                    devices: vec![device.clone()],
+                }),
+            }
+        }
+        buses
+    }
+
+    fn validate(&self) -> Result<(), ModbusConfigError> {
+        let mut devices = HashSet::new();
+        let mut endpoints: HashMap<&str, (&Connection, HashSet<u8>)> = HashMap::new();
+        for device in &self.devices {
+            if !devices.insert(device.name.as_str()) {
+                return Err(ModbusConfigError::DuplicatedDevice {
+                    device: device.name.to_string(),
+                });
+            }
+
+            // The devices sharing a connection must agree on its settings and have distinct unit ids
+            let endpoint = device.connection.endpoint();
+            let (connection, unit_ids) = endpoints
+                .entry(endpoint)
+                .or_insert_with(|| (&device.connection, HashSet::new()));
+            if *connection != &device.connection {
+                return Err(ModbusConfigError::ConflictingConnection {
+                    device: device.name.to_string(),
+                    endpoint: endpoint.to_string(),
+                });
+            }
+            if !unit_ids.insert(device.unit_id) {
+                return Err(ModbusConfigError::DuplicatedUnitId {
+                    device: device.name.to_string(),
+                    unit_id: device.unit_id,
+                    endpoint: endpoint.to_string(),
+                });
+            }
+
+            device.validate()?;
+        }
+        Ok(())
+    }
+}
+
+impl DeviceConfig {
+    /// Return the register with the given name
+    pub fn register(&self, name: &str) -> Option<&RegisterConfig> {
+        self.registers.iter().find(|register| register.name == name)
+    }
+
+    /// Tell if any register of this device can be written
+    pub fn has_writable_registers(&self) -> bool {
+        self.registers
+            .iter()
+            .any(|register| register.table.is_writable())
+    }
+
+    fn validate(&self) -> Result<(), ModbusConfigError> {
+        let mut registers = HashSet::new();
+        for register in &self.registers {
+            if !registers.insert(register.name.as_str()) {
+                return Err(ModbusConfigError::DuplicatedRegister {
+                    device: self.name.to_string(),
+                    register: register.name.clone(),
+                });
+            }
+            let data_type = register.data_type();
+            if register.table.is_bit_table() != (data_type == DataType::Bool) {
+                return Err(ModbusConfigError::InvalidRegister {
+                    device: self.name.to_string(),
+                    register: register.name.clone(),
+                    reason: format!(
+                        "a {data_type:?} value cannot be stored in the {:?} table",
+                        register.table
+                    ),
+                });
+            }
+            if register.scale == 0.0 {
+                return Err(ModbusConfigError::InvalidRegister {
+                    device: self.name.to_string(),
+                    register: register.name.clone(),
+                    reason: "the scale cannot be zero".to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl RegisterConfig {
+    /// The data type of the value, defaulting to the natural type of the register table
+    pub fn data_type(&self) -> DataType {
+        match self.data_type {
+            Some(data_type) => data_type,
+            None if self.table.is_bit_table() => DataType::Bool,
+            None => DataType::U16,
+        }
+    }
+}
+
+fn default_unit_id() -> u8 {
+    1
+}
+
+fn default_poll_interval() -> Duration {
+    DEFAULT_POLL_INTERVAL
+}
+
+fn default_timeout() -> Duration {
+    DEFAULT_TIMEOUT
+}
+
+fn default_measurement_type() -> String {
+    DEFAULT_MEASUREMENT_TYPE.to_string()
+}
+
+fn default_baud_rate() -> u32 {
+    DEFAULT_BAUD_RATE
+}
+
+fn default_data_bits() -> u8 {
+    8
+}
+
+fn default_stop_bits() -> u8 {
+    1
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+fn deserialize_duration<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let duration = String::deserialize(deserializer)?;
+    humantime::parse_duration(&duration).map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tedge_test_utils::fs::TempTedgeDir;
+
+    #[test]
+    fn read_tcp_and_rtu_devices() {
+        let config: ModbusConfig = toml::from_str(
+            r#"
+            [[device]]
+            name = "plc01"
+            protocol = "tcp"
+            address = "192.168.1.10:502"
+            poll_interval = "1s"
+
+            [[device.register]]
+            name = "temperature"
+            address = 0
+            data_type = "i16"
+            scale = 0.1
+
+            [[device.register]]
+            name = "running"
+            table = "coil"
+            address = 3
+
+            [[device]]
+            name = "meter"
+            protocol = "rtu"
+            port = "/dev/ttyUSB0"
+            baud_rate = 19200
+            parity = "even"
+            unit_id = 2
+
+            [[device.register]]
+            name = "energy"
+            table = "input"
+            address = 100
+            data_type = "f32"
+            word_order = "little"
+            "#,
+        )
+        .unwrap();
+        config.validate().unwrap();
+
+        let plc = &config.devices[0];
+        assert_eq!(plc.name.entity().as_str(), "device/plc01//");
+        assert_eq!(
+            plc.connection,
+            Connection::Tcp {
+                address: "192.168.1.10:502".to_string()
+            }
+        );
+        assert_eq!(plc.unit_id, 1);
+        assert_eq!(plc.poll_interval, Duration::from_secs(1));
+        assert_eq!(plc.measurement_type, "modbus");
+        assert_eq!(plc.registers[0].table, RegisterTable::Holding);
+        assert_eq!(plc.registers[0].data_type(), DataType::I16);
+        assert_eq!(plc.registers[1].data_type(), DataType::Bool);
+        assert!(plc.has_writable_registers());
+
+        let meter = &config.devices[1];
+        assert_eq!(
+            meter.connection,
+            Connection::Rtu {
+                port: "/dev/ttyUSB0".into(),
+                baud_rate: 19200,
+                parity: Parity::Even,
+                data_bits: 8,
+                stop_bits: 1,
+            }
+        );
+        assert_eq!(meter.unit_id, 2);
+        assert_eq!(meter.poll_interval, DEFAULT_POLL_INTERVAL);
+        assert_eq!(meter.registers[0].word_order, WordOrder::Little);
+        assert!(!meter.has_writable_registers());
+    }
+
+    #[test]
+    fn rtu_devices_on_the_same_port_share_a_bus() {
+        let config: ModbusConfig = toml::from_str(
+            r#"
+            [[device]]
+            name = "meter01"
+            protocol = "rtu"
+            port = "/dev/ttyUSB0"
+            baud_rate = 19200
+            unit_id = 1
+
+            [[device]]
+            name = "plc01"
+            protocol = "tcp"
+            address = "192.168.1.10:502"
+
+            [[device]]
+            name = "meter02"
+            protocol = "rtu"
+            port = "/dev/ttyUSB0"
+            baud_rate = 19200
+            unit_id = 2
+            "#,
+        )
+        .unwrap();
+        config.validate().unwrap();
+
+        let buses = config.buses();
+        assert_eq!(buses.len(), 2);
+        assert_eq!(buses[0].connection.endpoint(), "/dev/ttyUSB0");
+        assert_eq!(
+            buses[0]
+                .devices
+                .iter()
+                .map(|device| (device.name.as_str(), device.unit_id))
+                .collect::<Vec<_>>(),
+            vec![("meter01", 1), ("meter02", 2)]
+        );
+        assert_eq!(buses[1].connection.endpoint(), "192.168.1.10:502");
+        assert_eq!(buses[1].devices.len(), 1);
+    }
+
+    #[test]
+    fn reject_devices_sharing_a_port_with_conflicting_settings() {
+        let config: ModbusConfig = toml::from_str(
+            r#"
+            [[device]]
+            name = "meter01"
+            protocol = "rtu"
+            port = "/dev/ttyUSB0"
+            baud_rate = 19200
+            unit_id = 1
+
+            [[device]]
+            name = "meter02"
+            protocol = "rtu"
+            port = "/dev/ttyUSB0"
+            baud_rate = 9600
+            unit_id = 2
+            "#,
+        )
+        .unwrap();
+        let error = config.validate().unwrap_err();
+        assert!(matches!(
+            error,
+            ModbusConfigError::ConflictingConnection { .. }
+        ));
+
+        let config: ModbusConfig = toml::from_str(
+            r#"
+            [[device]]
+            name = "meter01"
+            protocol = "rtu"
+            port = "/dev/ttyUSB0"
+
+            [[device]]
+            name = "meter02"
+            protocol = "rtu"
+            port = "/dev/ttyUSB0"
+            "#,
+        )
+        .unwrap();
+        let error = config.validate().unwrap_err();
+        assert!(matches!(error, ModbusConfigError::DuplicatedUnitId { .. }));
+    }
+
+    #[test]
+    fn reject_registers_not_matching_their_table() {
+        let ttd = TempTedgeDir::new();
+        ttd.dir("mappers")
+            .file(MODBUS_CONFIG_FILE_NAME)
+            .with_raw_content(
+                r#"
+            [[device]]
+            name = "plc01"
+            protocol = "tcp"
+            address = "127.0.0.1:502"
+
+            [[device.register]]
+            name = "temperature"
+            table = "coil"
+            address = 0
+            data_type = "f32"
+            "#,
+            );
+
+        let error = ModbusConfig::read(ttd.utf8_path()).unwrap_err();
+
+        assert!(matches!(error, ModbusConfigError::InvalidRegister { .. }));
+    }
+
+    #[test]
+    fn a_missing_config_file_defines_no_devices() {
+        let ttd = TempTedgeDir::new();
+
+        let config = ModbusConfig::read(ttd.utf8_path()).unwrap();
+
+        assert!(config.devices.is_empty());
+    }
+}
@@ -0,0 +1,38 @@
+use std::time::Duration;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ModbusError {
+    #[error("Failed to connect the Modbus device {device}: {source}")]
+    Connection {
+        device: String,
+        source: std::io::Error,
+    },
+
+    #[error(transparent)]
+    Modbus(#[from] tokio_modbus::Error),
+
+    #[error("The Modbus device returned an exception: {0}")]
+    Exception(#[from] tokio_modbus::ExceptionCode),
+
+    #[error("The Modbus device didn't respond within {0:?}")]
+    Timeout(Duration),
+
+    #[error("Unknown register: {0}")]
+    UnknownRegister(String),
+
+    #[error("The register {0} cannot be written")]
+    ReadOnlyRegister(String),
+
+    #[error("The value {value} is out of the range of the register {register}")]
+    OutOfRange { register: String, value: f64 },
+}
+
+impl ModbusError {
+    /// Tell if the connection to the device has to be re-established after this error
+    pub fn is_connection_lost(&self) -> bool {
+        matches!(
+            self,
+            ModbusError::Connection { .. } | ModbusError::Modbus(_) | ModbusError::Timeout(_)
+        )
+    }
+}
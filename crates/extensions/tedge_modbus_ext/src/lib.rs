@@ -0,0 +1,12 @@
+//! Read and write the registers of Modbus TCP and RTU devices
+//!
+//! Each Modbus device defined in `{config_dir}/mappers/modbus.toml` is registered as a child device.
+//! Its registers are polled periodically and published as measurements of this child device,
+//! on the `te/device/<name>///m/<measurement type>` topic.
+//! The writable registers of a device can be updated using `modbus_write` commands.
+pub mod actor;
+pub mod client;
+pub mod command;
+pub mod config;
+pub mod error;
+pub mod register;
@@ -0,0 +1,197 @@
+//! Translation of Modbus registers into measurement values, and back
+use crate::config::DataType;
+use crate::config::RegisterConfig;
+use crate::config::WordOrder;
+use crate::error::ModbusError;
+
+impl DataType {
+    /// The number of 16-bit registers holding a value of this type
+    pub fn word_count(self) -> u16 {
+        match self {
+            DataType::Bool | DataType::U16 | DataType::I16 => 1,
+            DataType::U32 | DataType::I32 | DataType::F32 => 2,
+            DataType::U64 | DataType::I64 | DataType::F64 => 4,
+        }
+    }
+
+    /// Decode the value held by consecutive registers, ordered most significant word first
+    fn decode(self, words: &[u16]) -> f64 {
+        let bits = words
+            .iter()
+            .fold(0u64, |bits, word| (bits << 16) | u64::from(*word));
+        match self {
+            DataType::Bool => (bits != 0) as u8 as f64,
+            DataType::U16 => bits as u16 as f64,
+            DataType::I16 => bits as u16 as i16 as f64,
+            DataType::U32 => bits as u32 as f64,
+            DataType::I32 => bits as u32 as i32 as f64,
+            DataType::F32 => f32::from_bits(bits as u32) as f64,
+            DataType::U64 => bits as f64,
+            DataType::I64 => bits as i64 as f64,
+            DataType::F64 => f64::from_bits(bits),
+        }
+    }
+
+    /// Encode a value into consecutive registers, ordered most significant word first
+    ///
+    /// Return `None` if the value cannot be represented by this type.
+    fn encode(self, value: f64) -> Option<Vec<u16>> {
+        let bits = match self {
+            DataType::F32 => (value as f32).to_bits() as u64,
+            DataType::F64 => value.to_bits(),
+            DataType::Bool | DataType::U16 | DataType::U32 | DataType::U64 => {
+                let value = value.round();
+                let max = match self {
+                    DataType::Bool => 1.0,
+                    DataType::U16 => u16::MAX as f64,
+                    DataType::U32 => u32::MAX as f64,
+                    _ => u64::MAX as f64,
+                };
+                if !(0.0..=max).contains(&value) {
+                    return None;
+                }
+                value as u64
+            }
+            DataType::I16 | DataType::I32 | DataType::I64 => {
+                let value = value.round();
+                let (min, max, mask) = match self {
+                    DataType::I16 => (i16::MIN as f64, i16::MAX as f64, 0xffff),
+                    DataType::I32 => (i32::MIN as f64, i32::MAX as f64, 0xffff_ffff),
+                    _ => (i64::MIN as f64, i64::MAX as f64, u64::MAX),
+                };
+                if !(min..=max).contains(&value) {
+                    return None;
+                }
+                (value as i64 as u64) & mask
+            }
+        };
+
+        let count = self.word_count();
+        Some(
+            (0..count)
+                .rev()
+                .map(|i| (bits >> (16 * i)) as u16)
+                .collect(),
+        )
+    }
+}
+
+impl RegisterConfig {
+    /// The number of registers to read for this value
+    pub fn word_count(&self) -> u16 {
+        self.data_type().word_count()
+    }
+
+    /// Translate the registers read from the device into a measurement value
+    pub fn decode(&self, words: &[u16]) -> f64 {
+        let words = self.ordered(words.to_vec());
+        let raw = self.data_type().decode(&words);
+        raw * self.scale + self.offset
+    }
+
+    /// Translate a measurement value into the registers to be written to the device
+    pub fn encode(&self, value: f64) -> Result<Vec<u16>, ModbusError> {
+        let raw = (value - self.offset) / self.scale;
+        let words = self
+            .data_type()
+            .encode(raw)
+            .ok_or_else(|| ModbusError::OutOfRange {
+                register: self.name.clone(),
+                value,
+            })?;
+        Ok(self.ordered(words))
+    }
+
+    /// Reorder words from and to the most significant word first order
+    fn ordered(&self, mut words: Vec<u16>) -> Vec<u16> {
+        if self.word_order == WordOrder::Little {
+            words.reverse();
+        }
+        words
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RegisterTable;
+
+    fn register(data_type: DataType, word_order: WordOrder) -> RegisterConfig {
+        RegisterConfig {
+            name: "value".to_string(),
+            table: RegisterTable::Holding,
+            address: 0,
+            data_type: Some(data_type),
+            word_order,
+            scale: 1.0,
+            offset: 0.0,
+        }
+    }
+
+    #[test]
+    fn decode_values_spanning_several_registers() {
+        let u32_big = register(DataType::U32, WordOrder::Big);
+        assert_eq!(u32_big.decode(&[0x0001, 0x0002]), 65538.0);
+
+        let u32_little = register(DataType::U32, WordOrder::Little);
+        assert_eq!(u32_little.decode(&[0x0002, 0x0001]), 65538.0);
+
+        let i16 = register(DataType::I16, WordOrder::Big);
+        assert_eq!(i16.decode(&[0xfffe]), -2.0);
+
+        let f32 = register(DataType::F32, WordOrder::Big);
+        assert_eq!(f32.decode(&[0x41ac, 0x0000]), 21.5);
+
+        let i64 = register(DataType::I64, WordOrder::Big);
+        assert_eq!(i64.decode(&[0xffff, 0xffff, 0xffff, 0xfff6]), -10.0);
+    }
+
+    #[test]
+    fn values_are_scaled_and_offset() {
+        let register = RegisterConfig {
+            scale: 0.1,
+            offset: -40.0,
+            ..register(DataType::U16, WordOrder::Big)
+        };
+
+        assert_eq!(register.decode(&[615]), 21.5);
+        assert_eq!(register.encode(21.5).unwrap(), vec![615]);
+    }
+
+    #[test]
+    fn encoding_is_the_inverse_of_decoding() {
+        for (data_type, value) in [
+            (DataType::U16, 65535.0),
+            (DataType::I16, -32768.0),
+            (DataType::U32, 4_000_000_000.0),
+            (DataType::I32, -2_000_000_000.0),
+            (DataType::F32, -21.5),
+            (DataType::I64, -10.0),
+            (DataType::F64, 1234.5678),
+        ] {
+            for word_order in [WordOrder::Big, WordOrder::Little] {
+                let register = register(data_type, word_order);
+                let words = register.encode(value).unwrap();
+                assert_eq!(words.len(), data_type.word_count() as usize);
+                assert_eq!(
+                    register.decode(&words),
+                    value,
+                    "{data_type:?} {word_order:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn out_of_range_values_are_rejected() {
+        assert!(register(DataType::U16, WordOrder::Big)
+            .encode(65536.0)
+            .is_err());
+        assert!(register(DataType::U16, WordOrder::Big)
+            .encode(-1.0)
+            .is_err());
+        assert!(register(DataType::I16, WordOrder::Big)
+            .encode(40000.0)
+            .is_err());
+    }
+}
@@ -18,6 +18,7 @@ use tracing::log::info;
 /// When [Self::monitor] runs, this will watch the status of the bridge halves, and notify the
 /// relevant MQTT topic about the overall health.
 pub struct BridgeHealthMonitor {
+    service_name: String,
     topic: String,
     rx_status: mpsc::Receiver<(&'static str, Status)>,
     companion_bridge_half: BridgeMessageSender,
@@ -25,6 +26,7 @@ pub struct BridgeHealthMonitor {
 
 impl BridgeHealthMonitor {
     pub(crate) fn new<Client: MqttClient + 'static>(
+        service_name: &str,
         topic: String,
         bridge_half: &BridgeAsyncClient<Client>,
     ) -> (mpsc::Sender<(&'static str, Status)>, Self) {
@@ -32,6 +34,7 @@ impl BridgeHealthMonitor {
         (
             tx,
             BridgeHealthMonitor {
+                service_name: service_name.to_string(),
                 topic,
                 rx_status,
                 companion_bridge_half: bridge_half.clone_sender(),
@@ -45,6 +48,9 @@ impl BridgeHealthMonitor {
         loop {
             let (name, status) = self.rx_status.recv().await.unwrap();
             *statuses.entry(name).or_insert(Some(status)) = Some(status);
+            let connected = if status == Status::Up { 1.0 } else { 0.0 };
+            tedge_metrics::BRIDGE_CONNECTED
+                .set(&[("bridge", &self.service_name), ("half", name)], connected);
 
             let status = statuses.values().fold(Some(Status::Up), overall_status);
             if last_status != status {
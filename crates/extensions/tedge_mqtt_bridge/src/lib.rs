@@ -79,7 +79,7 @@ impl MqttBridgeActorBuilder {
             tedge_config.mqtt.client.port.into(),
         );
         // TODO cope with certs but not ca_dir, or handle that case with an explicit error message?
-        let auth_config = tedge_config.mqtt_client_auth_config();
+        let auth_config = tedge_config.service_mqtt_client_auth_config(service_name);
         let local_tls_config = auth_config.to_rustls_client_config().unwrap();
         if let Some(tls_config) = local_tls_config {
             local_config.set_transport(Transport::tls_with_config(tls_config.into()));
@@ -127,7 +127,7 @@ impl MqttBridgeActorBuilder {
         let [(convert_local, bidir_local), (convert_cloud, bidir_cloud)] =
             rules.converters_and_bidirectional_topic_filters();
         let (tx_status, monitor) =
-            BridgeHealthMonitor::new(health_topic.name.clone(), &local_target);
+            BridgeHealthMonitor::new(service_name, health_topic.name.clone(), &local_target);
         tokio::spawn(monitor.monitor());
         tokio::spawn(half_bridge(
             local_event_loop,
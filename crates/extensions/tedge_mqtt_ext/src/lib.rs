@@ -7,6 +7,9 @@ use async_trait::async_trait;
 use mqtt_channel::SinkExt;
 use mqtt_channel::StreamExt;
 use std::convert::Infallible;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
 use tedge_actors::futures::channel::mpsc;
 use tedge_actors::Actor;
 use tedge_actors::Builder;
@@ -35,6 +38,7 @@ pub struct MqttActorBuilder {
     publish_sender: mpsc::Sender<MqttMessage>,
     pub subscriber_addresses: Vec<(TopicFilter, DynSender<MqttMessage>)>,
     signal_sender: mpsc::Sender<RuntimeRequest>,
+    connection_status: ConnectionStatus,
 }
 
 impl MqttActorBuilder {
@@ -49,9 +53,15 @@ impl MqttActorBuilder {
             publish_sender,
             subscriber_addresses: Vec::new(),
             signal_sender,
+            connection_status: ConnectionStatus::default(),
         }
     }
 
+    /// The status of the connection to the MQTT broker, as seen by the actor being built
+    pub fn connection_status(&self) -> ConnectionStatus {
+        self.connection_status.clone()
+    }
+
     pub(crate) fn build_actor(self) -> MqttActor {
         let mut combined_topic_filter = TopicFilter::empty();
         for (topic_filter, _) in self.subscriber_addresses.iter() {
@@ -66,7 +76,12 @@ impl MqttActorBuilder {
             tracing::warn!(target: "MQTT sub", "ignoring overlapping subscription to {pattern}");
         }
 
-        let mqtt_config = self.mqtt_config.with_subscriptions(combined_topic_filter);
+        let connection_status = self.connection_status.clone();
+        let mqtt_config = self
+            .mqtt_config
+            .with_subscriptions(combined_topic_filter)
+            .with_connection_callback(move |connected| connection_status.set_connected(connected));
+
         MqttActor::new(mqtt_config, self.input_receiver, self.subscriber_addresses)
     }
 }
@@ -123,6 +138,7 @@ impl FromPeers {
     ) -> Result<(), RuntimeError> {
         while let Ok(Some(message)) = self.try_recv().await {
             tracing::debug!(target: "MQTT pub", "{message}");
+            count_message(&tedge_metrics::MQTT_MESSAGES_PUBLISHED, &message);
             SinkExt::send(outgoing_mqtt, message)
                 .await
                 .map_err(Box::new)?;
@@ -148,6 +164,7 @@ impl ToPeers {
     ) -> Result<(), RuntimeError> {
         while let Some(message) = incoming_mqtt.next().await {
             tracing::debug!(target: "MQTT recv", "{message}");
+            count_message(&tedge_metrics::MQTT_MESSAGES_RECEIVED, &message);
             self.send(message).await?;
         }
         Ok(())
@@ -163,6 +180,40 @@ impl ToPeers {
     }
 }
 
+/// Shared view on the state of the connection of an [MqttActor] to the MQTT broker
+///
+/// The connection is marked as established on each connection acknowledgement from the broker,
+/// and as lost on each connection error or disconnection.
+#[derive(Clone, Debug, Default)]
+pub struct ConnectionStatus {
+    connected: Arc<AtomicBool>,
+}
+
+impl ConnectionStatus {
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    fn set_connected(&self, connected: bool) {
+        self.connected.store(connected, Ordering::Relaxed)
+    }
+}
+
+/// Consume the connection errors, which are already logged and reflected by the [ConnectionStatus]
+async fn ignore_errors(errors: &mut mpsc::UnboundedReceiver<MqttError>) {
+    while errors.next().await.is_some() {}
+    // The connection is closed: the actor will be stopped by the closure of the message streams
+    std::future::pending().await
+}
+
+fn count_message(family: &'static tedge_metrics::Family, message: &MqttMessage) {
+    if !tedge_metrics::is_enabled() {
+        return;
+    }
+    let topic_family = tedge_metrics::topic_family(&message.topic.name);
+    family.inc(&[("topic_family", topic_family)]);
+}
+
 #[async_trait]
 impl MessageReceiver<MqttMessage> for FromPeers {
     async fn try_recv(&mut self) -> Result<Option<MqttMessage>, RuntimeRequest> {
@@ -215,11 +266,14 @@ impl Actor for MqttActor {
             }
         };
 
-        tedge_utils::futures::select(
+        let relay_messages = tedge_utils::futures::select(
             self.from_peers
                 .relay_messages_to(&mut mqtt_client.published),
             self.to_peers.relay_messages_from(&mut mqtt_client.received),
-        )
-        .await
+        );
+        tokio::select! {
+            result = relay_messages => result,
+            _ = ignore_errors(&mut mqtt_client.errors) => unreachable!(),
+        }
     }
 }
@@ -0,0 +1,471 @@
+use crate::client::ServerSession;
+use crate::config::OpcUaConfig;
+use crate::config::OpcUaServers;
+use crate::config::ServerConfig;
+use crate::config::OPCUA_CONFIG_FILE_NAME;
+use crate::converter::NodeValue;
+use async_trait::async_trait;
+use camino::Utf8PathBuf;
+use serde_json::json;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::time::Duration;
+use tedge_actors::fan_in_message_type;
+use tedge_actors::Actor;
+use tedge_actors::Builder;
+use tedge_actors::ChannelError;
+use tedge_actors::DynSender;
+use tedge_actors::MessageReceiver;
+use tedge_actors::MessageSink;
+use tedge_actors::MessageSource;
+use tedge_actors::NoConfig;
+use tedge_actors::RuntimeError;
+use tedge_actors::RuntimeRequest;
+use tedge_actors::RuntimeRequestSink;
+use tedge_actors::Sender;
+use tedge_actors::SimpleMessageBox;
+use tedge_actors::SimpleMessageBoxBuilder;
+use tedge_api::entity::EntityType;
+use tedge_api::entity_store::EntityRegistrationMessage;
+use tedge_api::mqtt_topics::Channel;
+use tedge_api::mqtt_topics::ChannelFilter;
+use tedge_api::mqtt_topics::EntityFilter;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_api::mqtt_topics::OperationType;
+use tedge_api::workflow::GenericCommandState;
+use tedge_api::workflow::GenericStateUpdate;
+use tedge_file_system_ext::FsWatchEvent;
+use tedge_mqtt_ext::MqttMessage;
+use tedge_mqtt_ext::QoS;
+use tedge_mqtt_ext::TopicFilter;
+use tedge_utils::file::create_directory_with_defaults;
+use tedge_utils::file::FileError;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+fan_in_message_type!(OpcUaInput[MqttMessage, FsWatchEvent] : Debug);
+
+/// The delay before a new attempt to connect a server that cannot be reached
+const RECONNECT_DELAY: Duration = Duration::from_secs(10);
+
+/// Subscribe to the nodes of OPC UA servers, publishing their value changes
+/// as measurements and events of the server child devices,
+/// and call the methods of these servers on the matching custom operations
+///
+/// The servers are reloaded each time their definition file is updated.
+pub struct OpcUaActor {
+    config: OpcUaConfig,
+    servers: HashMap<EntityTopicId, ServerHandle>,
+    values: mpsc::UnboundedReceiver<NodeValue>,
+    value_sender: mpsc::UnboundedSender<NodeValue>,
+    commands: mpsc::UnboundedReceiver<GenericCommandState>,
+    command_sender: mpsc::UnboundedSender<GenericCommandState>,
+    messages: SimpleMessageBox<OpcUaInput, MqttMessage>,
+}
+
+/// A server connected in the background
+///
+/// The connection is closed when the handle is dropped.
+struct ServerHandle {
+    config: ServerConfig,
+    calls: mpsc::UnboundedSender<GenericCommandState>,
+    task: JoinHandle<()>,
+}
+
+impl Drop for ServerHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[async_trait]
+impl Actor for OpcUaActor {
+    fn name(&self) -> &str {
+        "OPC UA"
+    }
+
+    async fn run(mut self) -> Result<(), RuntimeError> {
+        self.reload_servers().await?;
+
+        loop {
+            tokio::select! {
+                input = self.messages.recv() => match input {
+                    Some(OpcUaInput::MqttMessage(message)) => self.process_command(message),
+                    Some(OpcUaInput::FsWatchEvent(event)) => self.process_file_watch_event(event).await?,
+                    None => break,
+                },
+                Some(value) = self.values.recv() => {
+                    if let Some(message) = value.into_mqtt_message(&self.config.mqtt_schema) {
+                        self.messages.send(message).await?
+                    }
+                },
+                Some(command) = self.commands.recv() => {
+                    self.messages.send(command.into_message()).await?
+                },
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl OpcUaActor {
+    async fn process_file_watch_event(&mut self, event: FsWatchEvent) -> Result<(), ChannelError> {
+        let path = match event {
+            FsWatchEvent::Modified(path) | FsWatchEvent::FileDeleted(path) => path,
+            // Creating a file also emits `FsWatchEvent::Modified`
+            FsWatchEvent::FileCreated(_)
+            | FsWatchEvent::DirectoryDeleted(_)
+            | FsWatchEvent::DirectoryCreated(_) => return Ok(()),
+        };
+
+        match path.file_name() {
+            Some(file_name) if file_name.eq(OPCUA_CONFIG_FILE_NAME) => self.reload_servers().await,
+            _ => Ok(()),
+        }
+    }
+
+    /// Connect the servers currently defined, keeping the current ones on error
+    ///
+    /// The servers which definition is unchanged are left connected.
+    async fn reload_servers(&mut self) -> Result<(), ChannelError> {
+        let config_file = &self.config.config_file;
+        let servers = match OpcUaServers::read(config_file) {
+            Ok(servers) => servers,
+            Err(err) => {
+                error!("{err}: the OPC UA servers are left unchanged");
+                return Ok(());
+            }
+        };
+        info!(
+            "Using the {} OPC UA servers defined in {config_file}",
+            servers.servers.len()
+        );
+
+        let mut previous_servers = std::mem::take(&mut self.servers);
+        for config in servers.servers {
+            let entity = config.name.entity().clone();
+            match previous_servers.remove(&entity) {
+                Some(server) if server.config == config => {
+                    self.servers.insert(entity, server);
+                }
+                previous_server => {
+                    if let Some(server) = previous_server {
+                        self.clear_capabilities(&server.config).await?;
+                    }
+                    self.register_server(&config).await?;
+                    let server = self.start_server(config);
+                    self.servers.insert(entity, server);
+                }
+            }
+        }
+        for server in previous_servers.into_values() {
+            info!("Disconnecting the OPC UA server {}", server.config.name);
+            self.clear_capabilities(&server.config).await?;
+        }
+        Ok(())
+    }
+
+    /// Register a server as a child device, declaring a capability for each of its methods
+    async fn register_server(&mut self, config: &ServerConfig) -> Result<(), ChannelError> {
+        let mqtt_schema = &self.config.mqtt_schema;
+        let entity = config.name.entity();
+        let registration =
+            EntityRegistrationMessage::new_custom(entity.clone(), EntityType::ChildDevice)
+                .with_other_fragment("name".to_string(), json!(config.name.as_str()))
+                .with_other_fragment("type".to_string(), json!("opcua"))
+                .to_mqtt_message(mqtt_schema);
+        self.messages.send(registration).await?;
+
+        for method in &config.methods {
+            let topic = mqtt_schema
+                .capability_topic_for(entity, OperationType::Custom(method.operation.clone()));
+            let capability = MqttMessage::new(&topic, "{}")
+                .with_retain()
+                .with_qos(QoS::AtLeastOnce);
+            self.messages.send(capability).await?;
+        }
+        Ok(())
+    }
+
+    /// Remove the capabilities of a server which is no longer defined or has been redefined
+    async fn clear_capabilities(&mut self, config: &ServerConfig) -> Result<(), ChannelError> {
+        let mqtt_schema = &self.config.mqtt_schema;
+        let entity = config.name.entity();
+        for method in &config.methods {
+            let topic = mqtt_schema
+                .capability_topic_for(entity, OperationType::Custom(method.operation.clone()));
+            let clear = MqttMessage::new(&topic, "")
+                .with_retain()
+                .with_qos(QoS::AtLeastOnce);
+            self.messages.send(clear).await?;
+        }
+        Ok(())
+    }
+
+    fn start_server(&self, config: ServerConfig) -> ServerHandle {
+        info!(
+            "Connecting the OPC UA server {} on {}",
+            config.name, config.endpoint
+        );
+        let (calls, call_receiver) = mpsc::unbounded_channel();
+        let task = tokio::spawn(run_server(
+            config.clone(),
+            self.config.pki_dir.clone(),
+            call_receiver,
+            self.value_sender.clone(),
+            self.command_sender.clone(),
+        ));
+        ServerHandle {
+            config,
+            calls,
+            task,
+        }
+    }
+
+    /// Forward to the target server the commands matching one of its methods
+    fn process_command(&mut self, message: MqttMessage) {
+        let Ok((entity, Channel::Command { operation, .. })) = self
+            .config
+            .mqtt_schema
+            .entity_channel_of(&message.topic)
+        else {
+            return;
+        };
+        let Some(server) = self.servers.get(&entity) else {
+            return;
+        };
+        if server.config.method(&operation.to_string()).is_none() {
+            return;
+        }
+
+        match GenericCommandState::from_command_message(&message) {
+            Ok(command) if command.is_init() => {
+                let _ = server.calls.send(command);
+            }
+            Ok(_) => {}
+            Err(err) => error!(
+                "Invalid {operation} command received on {}: {err}",
+                message.topic.name
+            ),
+        }
+    }
+}
+
+/// Connect a server, subscribe to its nodes and call its methods on request
+async fn run_server(
+    config: ServerConfig,
+    pki_dir: Utf8PathBuf,
+    mut calls: mpsc::UnboundedReceiver<GenericCommandState>,
+    values: mpsc::UnboundedSender<NodeValue>,
+    commands: mpsc::UnboundedSender<GenericCommandState>,
+) {
+    let session = loop {
+        match ServerSession::connect(&config, &pki_dir).await {
+            Ok(session) => break session,
+            Err(err) => {
+                warn!("{err}: retrying in {}s", RECONNECT_DELAY.as_secs());
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        }
+    };
+    info!("Connected to the OPC UA server {}", config.name);
+
+    if let Err(err) = session.subscribe(&config, values).await {
+        error!("{err}");
+    }
+
+    while let Some(command) = calls.recv().await {
+        let command = command.move_to(GenericStateUpdate::executing());
+        let _ = commands.send(command.clone());
+
+        let operation = command.operation().unwrap_or_default();
+        let Some(method) = config.method(&operation) else {
+            continue;
+        };
+        let arguments = match command.payload.get("arguments") {
+            None => vec![],
+            Some(serde_json::Value::Array(arguments)) => arguments.clone(),
+            Some(argument) => vec![argument.clone()],
+        };
+        let command = match session.call(method, &arguments).await {
+            Ok(result) => {
+                info!(
+                    "Called the method {} of the OPC UA server {}",
+                    method.method_id, config.name
+                );
+                command
+                    .update_with_json(json!({ "result": result }))
+                    .move_to(GenericStateUpdate::successful())
+            }
+            Err(err) => command.fail_with(format!(
+                "Failed to call the method {} of the OPC UA server {}: {err}",
+                method.method_id, config.name
+            )),
+        };
+        let _ = commands.send(command);
+    }
+}
+
+pub struct OpcUaActorBuilder {
+    config: OpcUaConfig,
+    box_builder: SimpleMessageBoxBuilder<OpcUaInput, MqttMessage>,
+}
+
+impl OpcUaActorBuilder {
+    pub fn new(
+        config: OpcUaConfig,
+        mqtt: &mut (impl MessageSource<MqttMessage, TopicFilter> + MessageSink<MqttMessage>),
+        fs_notify: &mut impl MessageSource<FsWatchEvent, std::path::PathBuf>,
+    ) -> Self {
+        let mut box_builder = SimpleMessageBoxBuilder::new("OPC UA", 16);
+        box_builder.connect_sink(NoConfig, mqtt);
+        mqtt.connect_sink(
+            Self::subscriptions(&config.mqtt_schema),
+            &box_builder.get_sender(),
+        );
+        fs_notify.connect_sink(
+            config.config_dir.clone().into_std_path_buf(),
+            &box_builder.get_sender(),
+        );
+
+        OpcUaActorBuilder {
+            config,
+            box_builder,
+        }
+    }
+
+    /// Create the directory watched for changes of the server definitions
+    pub async fn init(config: &OpcUaConfig) -> Result<(), FileError> {
+        create_directory_with_defaults(&config.config_dir).await
+    }
+
+    /// The methods being defined at runtime, the actor subscribes to all the commands,
+    /// ignoring those which don't match a method of the target server
+    fn subscriptions(mqtt_schema: &MqttSchema) -> TopicFilter {
+        mqtt_schema.topics(EntityFilter::AnyEntity, ChannelFilter::AnyCommand)
+    }
+}
+
+impl RuntimeRequestSink for OpcUaActorBuilder {
+    fn get_signal_sender(&self) -> DynSender<RuntimeRequest> {
+        self.box_builder.get_signal_sender()
+    }
+}
+
+impl Builder<OpcUaActor> for OpcUaActorBuilder {
+    type Error = Infallible;
+
+    fn try_build(self) -> Result<OpcUaActor, Self::Error> {
+        Ok(self.build())
+    }
+
+    fn build(self) -> OpcUaActor {
+        let (value_sender, values) = mpsc::unbounded_channel();
+        let (command_sender, commands) = mpsc::unbounded_channel();
+        OpcUaActor {
+            config: self.config,
+            servers: HashMap::new(),
+            values,
+            value_sender,
+            commands,
+            command_sender,
+            messages: self.box_builder.build(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tedge_actors::test_helpers::MessageReceiverExt;
+    use tedge_actors::test_helpers::TimedMessageBox;
+    use tedge_actors::NoMessage;
+    use tedge_test_utils::fs::TempTedgeDir;
+
+    const TEST_TIMEOUT: Duration = Duration::from_secs(3);
+
+    const BOILER: &str = r#"
+        [[server]]
+        name = "boiler"
+        endpoint = "opc.tcp://127.0.0.1:1"
+
+        [[server.method]]
+        operation = "restart_boiler"
+        object_id = "ns=2;s=Boiler"
+        method_id = "ns=2;s=Boiler.Restart"
+        "#;
+
+    #[tokio::test]
+    async fn servers_are_registered_as_child_devices_with_their_methods_as_operations() {
+        let ttd = TempTedgeDir::new();
+        let (mut mqtt, _fs) = spawn_opcua_actor(&ttd, BOILER).await;
+
+        let registration = mqtt.recv().await.unwrap();
+        assert_eq!(registration.topic.name, "te/device/boiler//");
+        let registration: serde_json::Value =
+            serde_json::from_str(registration.payload_str().unwrap()).unwrap();
+        assert_eq!(registration["@type"], "child-device");
+        assert_eq!(registration["type"], "opcua");
+
+        let capability = mqtt.recv().await.unwrap();
+        assert_eq!(
+            capability.topic.name,
+            "te/device/boiler///cmd/restart_boiler"
+        );
+        assert_eq!(capability.payload_str().unwrap(), "{}");
+    }
+
+    #[tokio::test]
+    async fn servers_are_reloaded_when_their_definition_is_updated() {
+        let ttd = TempTedgeDir::new();
+        let (mut mqtt, mut fs) = spawn_opcua_actor(&ttd, BOILER).await;
+        mqtt.skip(2).await;
+
+        let config_file = ttd.utf8_path().join("mappers/opcua").join(OPCUA_CONFIG_FILE_NAME);
+        std::fs::write(
+            &config_file,
+            r#"
+            [[server]]
+            name = "press"
+            endpoint = "opc.tcp://127.0.0.1:1"
+            "#,
+        )
+        .unwrap();
+        fs.send(FsWatchEvent::Modified(config_file.into_std_path_buf()))
+            .await
+            .unwrap();
+
+        let registration = mqtt.recv().await.unwrap();
+        assert_eq!(registration.topic.name, "te/device/press//");
+
+        let cleared = mqtt.recv().await.unwrap();
+        assert_eq!(cleared.topic.name, "te/device/boiler///cmd/restart_boiler");
+        assert!(cleared.payload_bytes().is_empty());
+    }
+
+    async fn spawn_opcua_actor(
+        ttd: &TempTedgeDir,
+        servers: &str,
+    ) -> (
+        TimedMessageBox<SimpleMessageBox<MqttMessage, MqttMessage>>,
+        SimpleMessageBox<NoMessage, FsWatchEvent>,
+    ) {
+        let config = OpcUaConfig::new(MqttSchema::default(), ttd.utf8_path());
+        OpcUaActorBuilder::init(&config).await.unwrap();
+        std::fs::write(&config.config_file, servers).unwrap();
+
+        let mut mqtt: SimpleMessageBoxBuilder<MqttMessage, MqttMessage> =
+            SimpleMessageBoxBuilder::new("MQTT", 16);
+        let mut fs: SimpleMessageBoxBuilder<NoMessage, FsWatchEvent> =
+            SimpleMessageBoxBuilder::new("FS", 16);
+        let actor = OpcUaActorBuilder::new(config, &mut mqtt, &mut fs).build();
+        tokio::spawn(async move { actor.run().await });
+
+        (mqtt.build().with_timeout(TEST_TIMEOUT), fs.build())
+    }
+}
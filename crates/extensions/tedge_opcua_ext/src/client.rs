@@ -0,0 +1,428 @@
+use crate::config::BrowsePath;
+use crate::config::MethodConfig;
+use crate::config::NodeConfig;
+use crate::config::NodeLocation;
+use crate::config::SecurityMode;
+use crate::config::SecurityPolicy;
+use crate::config::ServerConfig;
+use crate::converter::NodeValue;
+use crate::converter::Value;
+use crate::error::OpcUaError;
+use camino::Utf8Path;
+use opcua::client::ClientBuilder;
+use opcua::client::DataChangeCallback;
+use opcua::client::IdentityToken;
+use opcua::client::Session;
+use opcua::types::BrowsePath as UaBrowsePath;
+use opcua::types::CallMethodRequest;
+use opcua::types::DataValue;
+use opcua::types::MessageSecurityMode;
+use opcua::types::MonitoredItemCreateRequest;
+use opcua::types::NodeId;
+use opcua::types::ObjectId;
+use opcua::types::QualifiedName;
+use opcua::types::ReferenceTypeId;
+use opcua::types::RelativePath;
+use opcua::types::RelativePathElement;
+use opcua::types::StatusCode;
+use opcua::types::TimestampsToReturn;
+use opcua::types::UAString;
+use opcua::types::UserTokenPolicy;
+use opcua::types::UserTokenType;
+use opcua::types::Variant;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+const APPLICATION_NAME: &str = "thin-edge OPC UA client";
+const APPLICATION_URI: &str = "urn:thin-edge:opcua-client";
+
+/// The lifetime of a subscription and the keep-alive count, in number of publishing intervals
+const SUBSCRIPTION_LIFETIME_COUNT: u32 = 30;
+const SUBSCRIPTION_MAX_KEEP_ALIVE_COUNT: u32 = 10;
+
+/// A session opened on an OPC UA server
+///
+/// The session is re-established by the OPC UA client when the connection is lost,
+/// and closed when dropped.
+pub struct ServerSession {
+    server: String,
+    session: Arc<Session>,
+    event_loop: JoinHandle<StatusCode>,
+}
+
+impl Drop for ServerSession {
+    fn drop(&mut self) {
+        self.event_loop.abort();
+    }
+}
+
+impl ServerSession {
+    pub async fn connect(config: &ServerConfig, pki_dir: &Utf8Path) -> Result<Self, OpcUaError> {
+        let server = config.name.to_string();
+        let connection_error = |reason: String| OpcUaError::Connection {
+            server: server.clone(),
+            reason,
+        };
+
+        let mut client = ClientBuilder::new()
+            .application_name(APPLICATION_NAME)
+            .application_uri(APPLICATION_URI)
+            .product_uri(APPLICATION_URI)
+            .pki_dir(pki_dir.as_std_path())
+            .create_sample_keypair(true)
+            .trust_server_certs(config.trust_server_certs)
+            .session_retry_limit(-1)
+            .client()
+            .map_err(|errors| OpcUaError::ClientConfig(errors.join(", ")))?;
+
+        let (user_token_type, identity_token) = match (&config.username, &config.password) {
+            (Some(username), Some(password)) => (
+                UserTokenType::UserName,
+                IdentityToken::UserName(username.clone(), password.clone()),
+            ),
+            _ => (UserTokenType::Anonymous, IdentityToken::Anonymous),
+        };
+        let user_token_policy = UserTokenPolicy {
+            policy_id: UAString::null(),
+            token_type: user_token_type,
+            issued_token_type: UAString::null(),
+            issuer_endpoint_url: UAString::null(),
+            security_policy_uri: UAString::null(),
+        };
+        let endpoint = (
+            config.endpoint.as_str(),
+            security_policy(config.security_policy).to_str(),
+            security_mode(config.security_mode),
+            user_token_policy,
+        );
+
+        let (session, event_loop) = client
+            .connect_to_matching_endpoint(endpoint, identity_token)
+            .await
+            .map_err(|err| connection_error(err.to_string()))?;
+        let event_loop = event_loop.spawn();
+        if !session.wait_for_connection().await {
+            event_loop.abort();
+            return Err(connection_error("the session has been closed".to_string()));
+        }
+
+        Ok(ServerSession {
+            server,
+            session,
+            event_loop,
+        })
+    }
+
+    /// Subscribe to the value changes of the nodes of the server, sending them to the given channel
+    ///
+    /// The nodes that cannot be found are skipped.
+    pub async fn subscribe(
+        &self,
+        config: &ServerConfig,
+        values: mpsc::UnboundedSender<NodeValue>,
+    ) -> Result<(), OpcUaError> {
+        let mut nodes = HashMap::new();
+        for node in &config.nodes {
+            match self.resolve(node).await {
+                Ok(node_id) => {
+                    nodes.insert(node_id, node.clone());
+                }
+                Err(err) => warn!("{err}"),
+            }
+        }
+        if nodes.is_empty() {
+            return Ok(());
+        }
+
+        let entity = config.name.entity().clone();
+        let monitored_nodes = nodes.clone();
+        let callback = DataChangeCallback::new(move |data_value, item| {
+            let Some(node) = monitored_nodes.get(&item.item_to_monitor().node_id) else {
+                return;
+            };
+            if let Some(value) = node_value(&entity, node, &data_value) {
+                let _ = values.send(value);
+            }
+        });
+
+        let subscription_error = |reason: String| OpcUaError::Subscription {
+            server: self.server.clone(),
+            reason,
+        };
+        let subscription_id = self
+            .session
+            .create_subscription(
+                config.publishing_interval,
+                SUBSCRIPTION_LIFETIME_COUNT,
+                SUBSCRIPTION_MAX_KEEP_ALIVE_COUNT,
+                0,
+                0,
+                true,
+                callback,
+            )
+            .await
+            .map_err(|err| subscription_error(err.to_string()))?;
+
+        let items: Vec<MonitoredItemCreateRequest> =
+            nodes.into_keys().map(|node_id| node_id.into()).collect();
+        let results = self
+            .session
+            .create_monitored_items(subscription_id, TimestampsToReturn::Both, items)
+            .await
+            .map_err(|err| subscription_error(err.to_string()))?;
+        for result in results {
+            if !result.status_code.is_good() {
+                warn!(
+                    "Failed to monitor a node of the OPC UA server {}: {}",
+                    self.server, result.status_code
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Call a method with the given arguments, returning its output arguments
+    pub async fn call(
+        &self,
+        method: &MethodConfig,
+        arguments: &[serde_json::Value],
+    ) -> Result<Vec<serde_json::Value>, OpcUaError> {
+        let input_arguments = arguments
+            .iter()
+            .map(json_to_variant)
+            .collect::<Result<Vec<_>, _>>()?;
+        let request = CallMethodRequest {
+            object_id: parse_node_id(&method.object_id)?,
+            method_id: parse_node_id(&method.method_id)?,
+            input_arguments: Some(input_arguments),
+        };
+
+        let result = self
+            .session
+            .call_one(request)
+            .await
+            .map_err(|err| OpcUaError::MethodCall(err.to_string()))?;
+        if !result.status_code.is_good() {
+            return Err(OpcUaError::MethodCall(result.status_code.to_string()));
+        }
+        Ok(result
+            .output_arguments
+            .unwrap_or_default()
+            .iter()
+            .map(variant_to_json)
+            .collect())
+    }
+
+    /// Return the id of a node, browsing the server if the node is given by a path
+    async fn resolve(&self, node: &NodeConfig) -> Result<NodeId, OpcUaError> {
+        let unknown_node = |reason: String| OpcUaError::UnknownNode {
+            server: self.server.clone(),
+            node: node.name.clone(),
+            reason,
+        };
+        let path = match node.location().map_err(unknown_node)? {
+            NodeLocation::Id(node_id) => return Ok(node_id),
+            NodeLocation::Path(path) => path,
+        };
+
+        let results = self
+            .session
+            .translate_browse_paths_to_node_ids(&[ua_browse_path(&path)])
+            .await
+            .map_err(|err| unknown_node(err.to_string()))?;
+        let result = results
+            .into_iter()
+            .next()
+            .ok_or_else(|| unknown_node("no browse result".to_string()))?;
+        if !result.status_code.is_good() {
+            return Err(unknown_node(result.status_code.to_string()));
+        }
+        result
+            .targets
+            .unwrap_or_default()
+            .into_iter()
+            .next()
+            .map(|target| target.target_id.node_id)
+            .ok_or_else(|| unknown_node("no node matches the browse path".to_string()))
+    }
+}
+
+fn ua_browse_path(path: &BrowsePath) -> UaBrowsePath {
+    let elements = path
+        .elements
+        .iter()
+        .map(|(namespace, name)| RelativePathElement {
+            reference_type_id: ReferenceTypeId::HierarchicalReferences.into(),
+            is_inverse: false,
+            include_subtypes: true,
+            target_name: QualifiedName::new(*namespace, name.as_str()),
+        })
+        .collect();
+    UaBrowsePath {
+        starting_node: ObjectId::ObjectsFolder.into(),
+        relative_path: RelativePath {
+            elements: Some(elements),
+        },
+    }
+}
+
+fn parse_node_id(node_id: &str) -> Result<NodeId, OpcUaError> {
+    NodeId::from_str(node_id)
+        .map_err(|_| OpcUaError::InvalidArguments(format!("invalid node id {node_id:?}")))
+}
+
+fn security_policy(policy: SecurityPolicy) -> opcua::crypto::SecurityPolicy {
+    match policy {
+        SecurityPolicy::None => opcua::crypto::SecurityPolicy::None,
+        SecurityPolicy::Basic256Sha256 => opcua::crypto::SecurityPolicy::Basic256Sha256,
+        SecurityPolicy::Aes128Sha256RsaOaep => opcua::crypto::SecurityPolicy::Aes128Sha256RsaOaep,
+        SecurityPolicy::Aes256Sha256RsaPss => opcua::crypto::SecurityPolicy::Aes256Sha256RsaPss,
+    }
+}
+
+fn security_mode(mode: SecurityMode) -> MessageSecurityMode {
+    match mode {
+        SecurityMode::None => MessageSecurityMode::None,
+        SecurityMode::Sign => MessageSecurityMode::Sign,
+        SecurityMode::SignAndEncrypt => MessageSecurityMode::SignAndEncrypt,
+    }
+}
+
+/// Translate a value change of a node, ignoring values with a bad status or an unsupported type
+fn node_value(
+    entity: &tedge_api::mqtt_topics::EntityTopicId,
+    node: &NodeConfig,
+    data_value: &DataValue,
+) -> Option<NodeValue> {
+    if let Some(status) = data_value.status {
+        if !status.is_good() {
+            warn!("Ignoring a value of {} with status {status}", node.name);
+            return None;
+        }
+    }
+    let value = variant_to_value(data_value.value.as_ref()?)?;
+    let timestamp = data_value
+        .source_timestamp
+        .or(data_value.server_timestamp)
+        .and_then(|timestamp| {
+            let nanos = timestamp.as_chrono().timestamp_nanos_opt()?;
+            OffsetDateTime::from_unix_timestamp_nanos(nanos as i128).ok()
+        })
+        .unwrap_or_else(OffsetDateTime::now_utc);
+
+    Some(NodeValue {
+        entity: entity.clone(),
+        name: node.name.clone(),
+        kind: node.kind,
+        measurement_type: node.measurement_type.clone(),
+        value,
+        timestamp,
+    })
+}
+
+/// Numbers and booleans are published as numbers, strings and localized texts as texts
+fn variant_to_value(variant: &Variant) -> Option<Value> {
+    let number = match variant {
+        Variant::Boolean(value) => u8::from(*value) as f64,
+        Variant::SByte(value) => *value as f64,
+        Variant::Byte(value) => *value as f64,
+        Variant::Int16(value) => *value as f64,
+        Variant::UInt16(value) => *value as f64,
+        Variant::Int32(value) => *value as f64,
+        Variant::UInt32(value) => *value as f64,
+        Variant::Int64(value) => *value as f64,
+        Variant::UInt64(value) => *value as f64,
+        Variant::Float(value) => *value as f64,
+        Variant::Double(value) => *value,
+        Variant::String(value) => return value.value().clone().map(Value::Text),
+        Variant::LocalizedText(value) => return value.text.value().clone().map(Value::Text),
+        _ => return None,
+    };
+    Some(Value::Number(number))
+}
+
+fn json_to_variant(json: &serde_json::Value) -> Result<Variant, OpcUaError> {
+    match json {
+        serde_json::Value::Bool(value) => Ok(Variant::from(*value)),
+        serde_json::Value::Number(number) => match (number.as_i64(), number.as_f64()) {
+            (Some(value), _) => Ok(Variant::from(value)),
+            (None, Some(value)) => Ok(Variant::from(value)),
+            (None, None) => Err(OpcUaError::InvalidArguments(format!(
+                "unsupported number {number}"
+            ))),
+        },
+        serde_json::Value::String(value) => Ok(Variant::from(value.as_str())),
+        _ => Err(OpcUaError::InvalidArguments(format!(
+            "only booleans, numbers and strings are supported, not {json}"
+        ))),
+    }
+}
+
+fn variant_to_json(variant: &Variant) -> serde_json::Value {
+    match variant {
+        Variant::Empty => serde_json::Value::Null,
+        Variant::Boolean(value) => (*value).into(),
+        Variant::String(value) => value.value().clone().into(),
+        Variant::LocalizedText(value) => value.text.value().clone().into(),
+        _ => match variant_to_value(variant) {
+            Some(Value::Number(value)) => value.into(),
+            Some(Value::Text(value)) => value.into(),
+            None => variant.to_string().into(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn numeric_variants_are_published_as_numbers() {
+        assert_eq!(
+            variant_to_value(&Variant::Int16(-12)),
+            Some(Value::Number(-12.0))
+        );
+        assert_eq!(
+            variant_to_value(&Variant::Float(21.5)),
+            Some(Value::Number(21.5))
+        );
+        assert_eq!(
+            variant_to_value(&Variant::Boolean(true)),
+            Some(Value::Number(1.0))
+        );
+        assert_eq!(
+            variant_to_value(&Variant::from("running")),
+            Some(Value::Text("running".to_string()))
+        );
+        assert_eq!(variant_to_value(&Variant::Empty), None);
+    }
+
+    #[test]
+    fn json_arguments_are_translated_into_variants() {
+        assert_eq!(
+            json_to_variant(&json!(true)).unwrap(),
+            Variant::Boolean(true)
+        );
+        assert_eq!(json_to_variant(&json!(42)).unwrap(), Variant::Int64(42));
+        assert_eq!(json_to_variant(&json!(2.5)).unwrap(), Variant::Double(2.5));
+        assert_eq!(
+            json_to_variant(&json!("fast")).unwrap(),
+            Variant::from("fast")
+        );
+        assert!(json_to_variant(&json!({"speed": 1})).is_err());
+    }
+
+    #[test]
+    fn output_variants_are_translated_into_json() {
+        assert_eq!(variant_to_json(&Variant::Boolean(false)), json!(false));
+        assert_eq!(variant_to_json(&Variant::UInt32(7)), json!(7.0));
+        assert_eq!(variant_to_json(&Variant::from("done")), json!("done"));
+        assert_eq!(variant_to_json(&Variant::Empty), json!(null));
+    }
+}
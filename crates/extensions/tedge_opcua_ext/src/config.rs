@@ -0,0 +1,461 @@
+use crate::error::OpcUaConfigError;
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use opcua::types::NodeId;
+use serde::Deserialize;
+use serde::Deserializer;
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_api::mqtt_topics::TopicIdError;
+
+pub const OPCUA_CONFIG_FILE_NAME: &str = "opcua.toml";
+
+const DEFAULT_PUBLISHING_INTERVAL: Duration = Duration::from_secs(1);
+const DEFAULT_MEASUREMENT_TYPE: &str = "opcua";
+
+/// Configuration of the OPC UA extension
+#[derive(Clone, Debug)]
+pub struct OpcUaConfig {
+    pub mqtt_schema: MqttSchema,
+
+    /// The directory watched for changes of the server definitions
+    pub config_dir: Utf8PathBuf,
+
+    /// The TOML file defining the servers
+    pub config_file: Utf8PathBuf,
+
+    /// The directory where the client certificates and the trusted server certificates are stored
+    pub pki_dir: Utf8PathBuf,
+}
+
+impl OpcUaConfig {
+    /// The OPC UA servers are defined in `{config_dir}/mappers/opcua/opcua.toml`
+    pub fn new(mqtt_schema: MqttSchema, config_dir: &Utf8Path) -> Self {
+        let config_dir = config_dir.join("mappers").join("opcua");
+        let config_file = config_dir.join(OPCUA_CONFIG_FILE_NAME);
+        let pki_dir = config_dir.join("pki");
+        OpcUaConfig {
+            mqtt_schema,
+            config_dir,
+            config_file,
+            pki_dir,
+        }
+    }
+}
+
+/// The OPC UA servers, as defined in TOML
+///
+/// ```toml
+/// [[server]]
+/// name = "boiler"
+/// endpoint = "opc.tcp://192.168.1.20:4840"
+///
+/// [[server.node]]
+/// name = "temperature"
+/// node_id = "ns=2;s=Boiler.Temperature"
+///
+/// [[server.node]]
+/// name = "alert"
+/// browse_path = "2:Boiler/2:Alert"
+/// kind = "event"
+///
+/// [[server.method]]
+/// operation = "restart_boiler"
+/// object_id = "ns=2;s=Boiler"
+/// method_id = "ns=2;s=Boiler.Restart"
+/// ```
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct OpcUaServers {
+    #[serde(default, rename = "server")]
+    pub servers: Vec<ServerConfig>,
+}
+
+/// An OPC UA server, registered as a child device named after the server
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct ServerConfig {
+    pub name: ServerName,
+
+    /// The endpoint URL of the server, e.g. `opc.tcp://localhost:4840`
+    pub endpoint: String,
+
+    #[serde(default)]
+    pub security_policy: SecurityPolicy,
+
+    #[serde(default)]
+    pub security_mode: SecurityMode,
+
+    /// The user name to authenticate with, the session being anonymous if not set
+    pub username: Option<String>,
+
+    pub password: Option<String>,
+
+    /// Trust the certificate of the server without checking it has been added to the trusted certificates
+    #[serde(default)]
+    pub trust_server_certs: bool,
+
+    /// The interval at which the server publishes the value changes
+    #[serde(
+        default = "default_publishing_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub publishing_interval: Duration,
+
+    #[serde(default, rename = "node")]
+    pub nodes: Vec<NodeConfig>,
+
+    #[serde(default, rename = "method")]
+    pub methods: Vec<MethodConfig>,
+}
+
+/// The name of an OPC UA server, which is also the id of the child device it is registered as
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(try_from = "String")]
+pub struct ServerName {
+    name: String,
+    entity: EntityTopicId,
+}
+
+impl ServerName {
+    pub fn as_str(&self) -> &str {
+        &self.name
+    }
+
+    /// The child device this OPC UA server is registered as
+    pub fn entity(&self) -> &EntityTopicId {
+        &self.entity
+    }
+}
+
+impl TryFrom<String> for ServerName {
+    type Error = TopicIdError;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        let entity = EntityTopicId::default_child_device(&name)?;
+        Ok(ServerName { name, entity })
+    }
+}
+
+impl fmt::Display for ServerName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.name.fmt(f)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SecurityPolicy {
+    #[default]
+    None,
+    Basic256Sha256,
+    Aes128Sha256RsaOaep,
+    Aes256Sha256RsaPss,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SecurityMode {
+    #[default]
+    None,
+    Sign,
+    SignAndEncrypt,
+}
+
+/// A node which value changes are published as measurements or events
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct NodeConfig {
+    /// The name of the measurement or the type of the event
+    pub name: String,
+
+    /// The node id, e.g. `ns=2;s=Boiler.Temperature`
+    pub node_id: Option<String>,
+
+    /// The path of the node from the `Objects` folder, e.g. `2:Boiler/2:Temperature`
+    pub browse_path: Option<String>,
+
+    #[serde(default)]
+    pub kind: NodeKind,
+
+    /// The type of the measurement, for measurement nodes only
+    #[serde(default = "default_measurement_type")]
+    pub measurement_type: String,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeKind {
+    #[default]
+    Measurement,
+    Event,
+}
+
+/// Where to find a node on a server
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum NodeLocation {
+    Id(NodeId),
+    Path(BrowsePath),
+}
+
+/// A path of browse names from the `Objects` folder
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BrowsePath {
+    /// The namespace index and the name of each element of the path
+    pub elements: Vec<(u16, String)>,
+}
+
+impl FromStr for BrowsePath {
+    type Err = String;
+
+    /// Parse a path such as `2:Boiler/2:Temperature`, the namespace index defaulting to 0
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        let mut elements = Vec::new();
+        for element in path.trim_start_matches('/').split('/') {
+            let (namespace, name) = match element.split_once(':') {
+                Some((namespace, name)) => {
+                    let namespace = namespace
+                        .parse()
+                        .map_err(|_| format!("invalid namespace index in {element:?}"))?;
+                    (namespace, name)
+                }
+                None => (0, element),
+            };
+            if name.is_empty() {
+                return Err(format!("empty browse name in {path:?}"));
+            }
+            elements.push((namespace, name.to_string()));
+        }
+        Ok(BrowsePath { elements })
+    }
+}
+
+/// An OPC UA method exposed as a custom operation of the server child device
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct MethodConfig {
+    /// The name of the operation
+    pub operation: String,
+
+    /// The id of the object the method is called on
+    pub object_id: String,
+
+    /// The id of the method
+    pub method_id: String,
+}
+
+impl OpcUaServers {
+    /// Read the OPC UA servers, a missing file defining no servers
+    pub fn read(path: &Utf8Path) -> Result<Self, OpcUaConfigError> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => {
+                let servers: OpcUaServers =
+                    toml::from_str(&content).map_err(|source| OpcUaConfigError::InvalidConfig {
+                        path: path.to_owned(),
+                        source,
+                    })?;
+                servers.validate()?;
+                Ok(servers)
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(OpcUaServers::default()),
+            Err(source) => Err(OpcUaConfigError::ReadConfig {
+                path: path.to_owned(),
+                source,
+            }),
+        }
+    }
+
+    fn validate(&self) -> Result<(), OpcUaConfigError> {
+        let mut servers = HashSet::new();
+        for server in &self.servers {
+            if !servers.insert(server.name.as_str()) {
+                return Err(OpcUaConfigError::DuplicatedServer {
+                    server: server.name.to_string(),
+                });
+            }
+            server.validate()?;
+        }
+        Ok(())
+    }
+}
+
+impl ServerConfig {
+    /// Return the method exposed as the given operation
+    pub fn method(&self, operation: &str) -> Option<&MethodConfig> {
+        self.methods
+            .iter()
+            .find(|method| method.operation == operation)
+    }
+
+    fn validate(&self) -> Result<(), OpcUaConfigError> {
+        if self.username.is_some() != self.password.is_some() {
+            return Err(self.invalid("both a username and a password are required"));
+        }
+        for node in &self.nodes {
+            node.location()
+                .map_err(|reason| self.invalid(format!("node {:?}: {reason}", node.name)))?;
+        }
+        let mut operations = HashSet::new();
+        for method in &self.methods {
+            if !operations.insert(method.operation.as_str()) {
+                return Err(self.invalid(format!(
+                    "the operation {:?} is defined twice",
+                    method.operation
+                )));
+            }
+            for node_id in [&method.object_id, &method.method_id] {
+                NodeId::from_str(node_id).map_err(|_| {
+                    self.invalid(format!(
+                        "operation {:?}: invalid node id {node_id:?}",
+                        method.operation
+                    ))
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    fn invalid(&self, reason: impl Into<String>) -> OpcUaConfigError {
+        OpcUaConfigError::InvalidServer {
+            server: self.name.to_string(),
+            reason: reason.into(),
+        }
+    }
+}
+
+impl NodeConfig {
+    /// Where to find this node, given either by a node id or a browse path
+    pub fn location(&self) -> Result<NodeLocation, String> {
+        match (&self.node_id, &self.browse_path) {
+            (Some(node_id), None) => NodeId::from_str(node_id)
+                .map(NodeLocation::Id)
+                .map_err(|_| format!("invalid node id {node_id:?}")),
+            (None, Some(path)) => path.parse().map(NodeLocation::Path),
+            _ => Err("either a node_id or a browse_path is required".to_string()),
+        }
+    }
+}
+
+fn default_publishing_interval() -> Duration {
+    DEFAULT_PUBLISHING_INTERVAL
+}
+
+fn default_measurement_type() -> String {
+    DEFAULT_MEASUREMENT_TYPE.to_string()
+}
+
+fn deserialize_duration<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let duration = String::deserialize(deserializer)?;
+    humantime::parse_duration(&duration).map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tedge_test_utils::fs::TempTedgeDir;
+
+    #[test]
+    fn read_servers_with_nodes_and_methods() {
+        let servers: OpcUaServers = toml::from_str(
+            r#"
+            [[server]]
+            name = "boiler"
+            endpoint = "opc.tcp://192.168.1.20:4840"
+            security_policy = "basic256_sha256"
+            security_mode = "sign_and_encrypt"
+            username = "tedge"
+            password = "secret"
+
+            [[server.node]]
+            name = "temperature"
+            node_id = "ns=2;s=Boiler.Temperature"
+
+            [[server.node]]
+            name = "alert"
+            browse_path = "2:Boiler/2:Alert"
+            kind = "event"
+
+            [[server.method]]
+            operation = "restart_boiler"
+            object_id = "ns=2;s=Boiler"
+            method_id = "ns=2;s=Boiler.Restart"
+            "#,
+        )
+        .unwrap();
+        servers.validate().unwrap();
+
+        let boiler = &servers.servers[0];
+        assert_eq!(boiler.name.entity().as_str(), "device/boiler//");
+        assert_eq!(boiler.security_policy, SecurityPolicy::Basic256Sha256);
+        assert_eq!(boiler.security_mode, SecurityMode::SignAndEncrypt);
+        assert_eq!(boiler.publishing_interval, DEFAULT_PUBLISHING_INTERVAL);
+
+        let temperature = &boiler.nodes[0];
+        assert_eq!(temperature.kind, NodeKind::Measurement);
+        assert_eq!(temperature.measurement_type, "opcua");
+        assert_eq!(
+            temperature.location().unwrap(),
+            NodeLocation::Id(NodeId::new(2, "Boiler.Temperature"))
+        );
+
+        let alert = &boiler.nodes[1];
+        assert_eq!(alert.kind, NodeKind::Event);
+        assert_eq!(
+            alert.location().unwrap(),
+            NodeLocation::Path(BrowsePath {
+                elements: vec![(2, "Boiler".to_string()), (2, "Alert".to_string())]
+            })
+        );
+
+        assert!(boiler.method("restart_boiler").is_some());
+        assert!(boiler.method("unknown").is_none());
+    }
+
+    #[test]
+    fn parse_browse_paths() {
+        assert_eq!(
+            "/Server/0:ServerStatus/CurrentTime"
+                .parse::<BrowsePath>()
+                .unwrap()
+                .elements,
+            vec![
+                (0, "Server".to_string()),
+                (0, "ServerStatus".to_string()),
+                (0, "CurrentTime".to_string())
+            ]
+        );
+        assert!("2:Boiler//Temperature".parse::<BrowsePath>().is_err());
+        assert!("x:Boiler".parse::<BrowsePath>().is_err());
+    }
+
+    #[test]
+    fn reject_nodes_with_no_or_both_locations() {
+        let ttd = TempTedgeDir::new();
+        ttd.file(OPCUA_CONFIG_FILE_NAME).with_raw_content(
+            r#"
+            [[server]]
+            name = "boiler"
+            endpoint = "opc.tcp://localhost:4840"
+
+            [[server.node]]
+            name = "temperature"
+            "#,
+        );
+
+        let error = OpcUaServers::read(&ttd.utf8_path().join(OPCUA_CONFIG_FILE_NAME)).unwrap_err();
+
+        assert!(matches!(error, OpcUaConfigError::InvalidServer { .. }));
+    }
+
+    #[test]
+    fn a_missing_config_file_defines_no_servers() {
+        let ttd = TempTedgeDir::new();
+
+        let servers = OpcUaServers::read(&ttd.utf8_path().join(OPCUA_CONFIG_FILE_NAME)).unwrap();
+
+        assert!(servers.servers.is_empty());
+    }
+}
@@ -0,0 +1,150 @@
+use crate::config::NodeKind;
+use serde_json::json;
+use tedge_api::measurement::MeasurementVisitor;
+use tedge_api::measurement::ThinEdgeJsonSerializationError;
+use tedge_api::measurement::ThinEdgeJsonSerializer;
+use tedge_api::mqtt_topics::Channel;
+use tedge_api::mqtt_topics::EntityTopicId;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_mqtt_ext::MqttMessage;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use tracing::error;
+
+/// A value change notified by an OPC UA server
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodeValue {
+    /// The child device of the server
+    pub entity: EntityTopicId,
+
+    /// The name of the measurement or the type of the event
+    pub name: String,
+
+    pub kind: NodeKind,
+
+    pub measurement_type: String,
+
+    pub value: Value,
+
+    /// The source timestamp of the value, or the server timestamp if the source one is not known
+    pub timestamp: OffsetDateTime,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Number(f64),
+    Text(String),
+}
+
+impl NodeValue {
+    /// Translate a value change into a measurement or an event of the server child device
+    pub fn into_mqtt_message(self, mqtt_schema: &MqttSchema) -> Option<MqttMessage> {
+        match self.kind {
+            NodeKind::Measurement => self.into_measurement(mqtt_schema),
+            NodeKind::Event => self.into_event(mqtt_schema),
+        }
+    }
+
+    fn into_measurement(self, mqtt_schema: &MqttSchema) -> Option<MqttMessage> {
+        match self.thin_edge_json() {
+            Ok(payload) => {
+                let channel = Channel::Measurement {
+                    measurement_type: self.measurement_type,
+                };
+                let topic = mqtt_schema.topic_for(&self.entity, &channel);
+                Some(MqttMessage::new(&topic, payload))
+            }
+            Err(err) => {
+                error!("Error while encoding the {} measurement: {err}", self.name);
+                None
+            }
+        }
+    }
+
+    fn thin_edge_json(&self) -> Result<String, ThinEdgeJsonSerializationError> {
+        let mut serializer = ThinEdgeJsonSerializer::new();
+        serializer.visit_timestamp(self.timestamp)?;
+        match &self.value {
+            Value::Number(value) => serializer.visit_measurement(&self.name, *value)?,
+            Value::Text(value) => serializer.visit_text_property(&self.name, value)?,
+        }
+        serializer.into_string()
+    }
+
+    fn into_event(self, mqtt_schema: &MqttSchema) -> Option<MqttMessage> {
+        let time = match self.timestamp.format(&Rfc3339) {
+            Ok(time) => time,
+            Err(err) => {
+                error!("Error while encoding the {} event: {err}", self.name);
+                return None;
+            }
+        };
+        let (text, value) = match self.value {
+            Value::Number(value) => (value.to_string(), json!(value)),
+            Value::Text(text) => (text.clone(), json!(text)),
+        };
+        let payload = json!({
+            "text": text,
+            "time": time,
+            "value": value,
+        });
+
+        let channel = Channel::Event {
+            event_type: self.name,
+        };
+        let topic = mqtt_schema.topic_for(&self.entity, &channel);
+        Some(MqttMessage::new(&topic, payload.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    fn node_value(kind: NodeKind, value: Value) -> NodeValue {
+        NodeValue {
+            entity: EntityTopicId::default_child_device("boiler").unwrap(),
+            name: "temperature".to_string(),
+            kind,
+            measurement_type: "opcua".to_string(),
+            value,
+            timestamp: datetime!(2024-05-01 10:00:00 UTC),
+        }
+    }
+
+    fn topic_and_payload(message: MqttMessage) -> (String, serde_json::Value) {
+        let payload = serde_json::from_str(message.payload_str().unwrap()).unwrap();
+        (message.topic.name, payload)
+    }
+
+    #[test]
+    fn measurement_nodes_are_published_as_measurements() {
+        let message = node_value(NodeKind::Measurement, Value::Number(21.5))
+            .into_mqtt_message(&MqttSchema::default())
+            .unwrap();
+
+        assert_eq!(
+            topic_and_payload(message),
+            (
+                "te/device/boiler///m/opcua".to_string(),
+                json!({"time": "2024-05-01T10:00:00Z", "temperature": 21.5})
+            )
+        );
+    }
+
+    #[test]
+    fn event_nodes_are_published_as_events() {
+        let message = node_value(NodeKind::Event, Value::Text("overheat".to_string()))
+            .into_mqtt_message(&MqttSchema::default())
+            .unwrap();
+
+        assert_eq!(
+            topic_and_payload(message),
+            (
+                "te/device/boiler///e/temperature".to_string(),
+                json!({"text": "overheat", "time": "2024-05-01T10:00:00Z", "value": "overheat"})
+            )
+        );
+    }
+}
@@ -0,0 +1,47 @@
+use camino::Utf8PathBuf;
+
+#[derive(thiserror::Error, Debug)]
+pub enum OpcUaConfigError {
+    #[error("Failed to read {path}: {source}")]
+    ReadConfig {
+        path: Utf8PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("Invalid OPC UA configuration {path}: {source}")]
+    InvalidConfig {
+        path: Utf8PathBuf,
+        source: toml::de::Error,
+    },
+
+    #[error("The OPC UA server {server:?} is defined twice")]
+    DuplicatedServer { server: String },
+
+    #[error("Invalid OPC UA server {server:?}: {reason}")]
+    InvalidServer { server: String, reason: String },
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum OpcUaError {
+    #[error("Invalid OPC UA client configuration: {0}")]
+    ClientConfig(String),
+
+    #[error("Failed to connect the OPC UA server {server}: {reason}")]
+    Connection { server: String, reason: String },
+
+    #[error("Failed to subscribe to the nodes of the OPC UA server {server}: {reason}")]
+    Subscription { server: String, reason: String },
+
+    #[error("Cannot find the node {node} on the OPC UA server {server}: {reason}")]
+    UnknownNode {
+        server: String,
+        node: String,
+        reason: String,
+    },
+
+    #[error("Invalid method arguments: {0}")]
+    InvalidArguments(String),
+
+    #[error("The method call failed: {0}")]
+    MethodCall(String),
+}
@@ -0,0 +1,12 @@
+//! Read, subscribe to and call the nodes of OPC UA servers
+//!
+//! Each OPC UA server defined in `{config_dir}/mappers/opcua/opcua.toml` is registered as a child device.
+//! The value changes of the nodes configured for a server, given by node id or browse path,
+//! are published as measurements or events of this child device.
+//! The methods configured for a server are exposed as custom operations of the child device.
+//! The servers are reloaded each time the configuration file is updated.
+pub mod actor;
+pub mod client;
+pub mod config;
+pub mod converter;
+pub mod error;
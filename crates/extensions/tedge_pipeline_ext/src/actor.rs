@@ -0,0 +1,189 @@
+use crate::config::MeasurementPipelineConfig;
+use crate::config::PipelineStages;
+use crate::config::PIPELINE_CONFIG_FILE_NAME;
+use crate::error::PipelineError;
+use crate::pipeline::Pipeline;
+use async_trait::async_trait;
+use log::error;
+use log::info;
+use log::warn;
+use std::time::Duration;
+use tedge_actors::fan_in_message_type;
+use tedge_actors::Actor;
+use tedge_actors::ChannelError;
+use tedge_actors::DynSender;
+use tedge_actors::MessageReceiver;
+use tedge_actors::NoMessage;
+use tedge_actors::RuntimeError;
+use tedge_actors::Sender;
+use tedge_actors::SimpleMessageBox;
+use tedge_api::mqtt_topics::Channel;
+use tedge_file_system_ext::FsWatchEvent;
+use tedge_mqtt_ext::MqttMessage;
+use tedge_mqtt_ext::Topic;
+use tedge_mqtt_ext::TopicFilter;
+use tokio::time::Instant;
+use tokio::time::MissedTickBehavior;
+
+fan_in_message_type!(PipelineInput[MqttMessage, FsWatchEvent] : Debug);
+
+/// The period at which the pipeline checks for downsampling intervals that are over
+const FLUSH_PERIOD: Duration = Duration::from_secs(1);
+
+/// Pre-process the measurements received from MQTT before forwarding them to the peers
+///
+/// All the other messages are forwarded unchanged.
+pub struct MeasurementPipelineActor {
+    config: MeasurementPipelineConfig,
+    pipeline: Pipeline,
+    messages: SimpleMessageBox<PipelineInput, NoMessage>,
+    peers: Vec<(TopicFilter, DynSender<MqttMessage>)>,
+}
+
+#[async_trait]
+impl Actor for MeasurementPipelineActor {
+    fn name(&self) -> &str {
+        "MeasurementPipeline"
+    }
+
+    async fn run(mut self) -> Result<(), RuntimeError> {
+        self.reload_pipeline().await?;
+
+        let mut flush = tokio::time::interval(FLUSH_PERIOD);
+        flush.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            tokio::select! {
+                input = self.messages.recv() => match input {
+                    Some(PipelineInput::MqttMessage(message)) => self.process_message(message).await?,
+                    Some(PipelineInput::FsWatchEvent(event)) => self.process_file_watch_event(event).await?,
+                    None => break,
+                },
+                _ = flush.tick() => {
+                    let aggregated = self.pipeline.flush(Instant::now());
+                    self.publish_aggregated(aggregated).await?
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl MeasurementPipelineActor {
+    pub fn new(
+        config: MeasurementPipelineConfig,
+        messages: SimpleMessageBox<PipelineInput, NoMessage>,
+        peers: Vec<(TopicFilter, DynSender<MqttMessage>)>,
+    ) -> Self {
+        MeasurementPipelineActor {
+            config,
+            pipeline: Pipeline::default(),
+            messages,
+            peers,
+        }
+    }
+
+    async fn process_message(&mut self, message: MqttMessage) -> Result<(), ChannelError> {
+        let measurement_type = match self
+            .config
+            .mqtt_schema
+            .entity_channel_of(&message.topic.name)
+        {
+            Ok((_, Channel::Measurement { measurement_type })) if !self.pipeline.is_empty() => {
+                measurement_type
+            }
+            _ => return self.send(message).await,
+        };
+        let processed = self.pipeline.process(
+            &message.topic.name,
+            &measurement_type,
+            message.payload_bytes(),
+            Instant::now(),
+        );
+
+        match processed {
+            Ok(Some(payload)) => {
+                let processed = MqttMessage::new(&message.topic, payload)
+                    .with_qos(message.qos)
+                    .with_retain_flag(message.retain);
+                self.send(processed).await
+            }
+            Ok(None) => Ok(()),
+            Err(err) => {
+                // Let the converters report the invalid measurement
+                warn!(
+                    "Cannot pre-process the measurement received on {}: {err}",
+                    message.topic.name
+                );
+                self.send(message).await
+            }
+        }
+    }
+
+    async fn process_file_watch_event(&mut self, event: FsWatchEvent) -> Result<(), ChannelError> {
+        let path = match event {
+            FsWatchEvent::Modified(path) | FsWatchEvent::FileDeleted(path) => path,
+            // Creating a file also emits `FsWatchEvent::Modified`
+            FsWatchEvent::FileCreated(_)
+            | FsWatchEvent::DirectoryDeleted(_)
+            | FsWatchEvent::DirectoryCreated(_) => return Ok(()),
+        };
+
+        match path.file_name() {
+            Some(file_name) if file_name.eq(PIPELINE_CONFIG_FILE_NAME) => {
+                self.reload_pipeline().await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Replace the pipeline by the one currently defined, keeping the current one on error
+    ///
+    /// The values being downsampled by the former pipeline are aggregated and forwarded.
+    async fn reload_pipeline(&mut self) -> Result<(), ChannelError> {
+        let config_file = &self.config.config_file;
+        let stages = match PipelineStages::read(config_file) {
+            Ok(stages) => stages,
+            Err(err) => {
+                error!("{err}: the measurement pipeline is left unchanged");
+                return Ok(());
+            }
+        };
+        if stages.is_empty() {
+            info!("No measurement pipeline defined in {config_file}");
+        } else {
+            info!("Using the measurement pipeline defined in {config_file}");
+        }
+
+        let aggregated = self.pipeline.flush_all();
+        self.publish_aggregated(aggregated).await?;
+        self.pipeline = Pipeline::new(stages);
+        Ok(())
+    }
+
+    async fn publish_aggregated(
+        &mut self,
+        aggregated: Result<Vec<(String, String)>, PipelineError>,
+    ) -> Result<(), ChannelError> {
+        match aggregated {
+            Ok(aggregated) => {
+                for (topic, payload) in aggregated {
+                    self.send(MqttMessage::new(&Topic::new_unchecked(&topic), payload))
+                        .await?;
+                }
+            }
+            Err(err) => error!("Cannot aggregate the downsampled measurements: {err}"),
+        }
+        Ok(())
+    }
+
+    async fn send(&mut self, message: MqttMessage) -> Result<(), ChannelError> {
+        for (topic_filter, peer) in self.peers.iter_mut() {
+            if topic_filter.accept(&message) {
+                peer.send(message.clone()).await?;
+            }
+        }
+        Ok(())
+    }
+}
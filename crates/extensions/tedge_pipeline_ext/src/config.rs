@@ -0,0 +1,260 @@
+use crate::error::PipelineError;
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use serde::Deserialize;
+use serde::Deserializer;
+use std::time::Duration;
+use tedge_api::mqtt_topics::MqttSchema;
+
+pub const PIPELINE_CONFIG_FILE_NAME: &str = "pipeline.toml";
+
+/// Configuration of the measurement pipeline of a mapper
+#[derive(Clone, Debug)]
+pub struct MeasurementPipelineConfig {
+    pub mqtt_schema: MqttSchema,
+
+    /// The directory watched for changes of the pipeline definition
+    pub config_dir: Utf8PathBuf,
+
+    /// The TOML file defining the stages of the pipeline
+    pub config_file: Utf8PathBuf,
+}
+
+impl MeasurementPipelineConfig {
+    /// The pipeline of a mapper is defined in `{config_dir}/mappers/{mapper}/pipeline.toml`
+    pub fn new(mqtt_schema: MqttSchema, config_dir: &Utf8Path, mapper: &str) -> Self {
+        let config_dir = config_dir.join("mappers").join(mapper);
+        let config_file = config_dir.join(PIPELINE_CONFIG_FILE_NAME);
+        MeasurementPipelineConfig {
+            mqtt_schema,
+            config_dir,
+            config_file,
+        }
+    }
+}
+
+/// The stages of a measurement pipeline, as defined in TOML
+///
+/// Whatever their order in the file, the stages are applied in a fixed order:
+/// conversions first, then deadband filters, downsampling and finally renaming.
+/// All the stages select the measurements using their original names.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct PipelineStages {
+    #[serde(default)]
+    pub convert: Vec<ConvertStage>,
+
+    #[serde(default)]
+    pub deadband: Vec<DeadbandStage>,
+
+    #[serde(default)]
+    pub downsample: Vec<DownsampleStage>,
+
+    #[serde(default)]
+    pub rename: Vec<RenameStage>,
+}
+
+impl PipelineStages {
+    /// Read the stages of a pipeline, a missing file defining an empty pipeline
+    pub fn read(path: &Utf8Path) -> Result<Self, PipelineError> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => {
+                toml::from_str(&content).map_err(|source| PipelineError::InvalidConfig {
+                    path: path.to_owned(),
+                    source,
+                })
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(PipelineStages::default()),
+            Err(source) => Err(PipelineError::ReadConfig {
+                path: path.to_owned(),
+                source,
+            }),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.convert.is_empty()
+            && self.deadband.is_empty()
+            && self.downsample.is_empty()
+            && self.rename.is_empty()
+    }
+}
+
+/// The name of a measurement, prefixed by its group if any: `temperature` or `environment.temperature`
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Deserialize)]
+#[serde(from = "String")]
+pub struct MeasurementPath {
+    pub group: Option<String>,
+    pub name: String,
+}
+
+impl MeasurementPath {
+    pub fn new(group: Option<&str>, name: &str) -> Self {
+        MeasurementPath {
+            group: group.map(str::to_owned),
+            name: name.to_owned(),
+        }
+    }
+}
+
+impl From<String> for MeasurementPath {
+    fn from(path: String) -> Self {
+        match path.split_once('.') {
+            Some((group, name)) => MeasurementPath::new(Some(group), name),
+            None => MeasurementPath {
+                group: None,
+                name: path,
+            },
+        }
+    }
+}
+
+/// Select the measurements a stage applies to
+#[derive(Clone, Debug, Deserialize)]
+pub struct MeasurementSelector {
+    pub measurement: MeasurementPath,
+
+    /// The type of the measurements, as given by the last segment of `te/+/+/+/+/m/+`.
+    /// If not set, the measurements of all types are selected.
+    #[serde(rename = "type")]
+    pub measurement_type: Option<String>,
+}
+
+impl MeasurementSelector {
+    pub fn matches(&self, measurement_type: &str, measurement: &MeasurementPath) -> bool {
+        let type_matches = match &self.measurement_type {
+            Some(selected_type) => selected_type == measurement_type,
+            None => true,
+        };
+        type_matches && &self.measurement == measurement
+    }
+}
+
+/// Convert the unit of a measurement: `value * scale + offset`
+#[derive(Clone, Debug, Deserialize)]
+pub struct ConvertStage {
+    #[serde(flatten)]
+    pub selector: MeasurementSelector,
+
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+
+    #[serde(default)]
+    pub offset: f64,
+}
+
+impl ConvertStage {
+    pub fn apply(&self, value: f64) -> f64 {
+        value * self.scale + self.offset
+    }
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+/// Drop the values of a measurement that differ less than `threshold` from the last value forwarded
+#[derive(Clone, Debug, Deserialize)]
+pub struct DeadbandStage {
+    #[serde(flatten)]
+    pub selector: MeasurementSelector,
+
+    pub threshold: f64,
+}
+
+/// Replace the values of a measurement received over an interval by their aggregate
+#[derive(Clone, Debug, Deserialize)]
+pub struct DownsampleStage {
+    #[serde(flatten)]
+    pub selector: MeasurementSelector,
+
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+
+    #[serde(default)]
+    pub aggregate: Aggregate,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Aggregate {
+    #[default]
+    Average,
+    Min,
+    Max,
+}
+
+/// Rename a measurement, keeping it in its group if any
+#[derive(Clone, Debug, Deserialize)]
+pub struct RenameStage {
+    #[serde(flatten)]
+    pub selector: MeasurementSelector,
+
+    pub to: String,
+}
+
+fn deserialize_duration<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let duration = String::deserialize(deserializer)?;
+    humantime::parse_duration(&duration).map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_pipeline_stages() {
+        let stages: PipelineStages = toml::from_str(
+            r#"
+            [[convert]]
+            measurement = "environment.temperature"
+            scale = 1.8
+            offset = 32
+
+            [[deadband]]
+            measurement = "pressure"
+            type = "environment"
+            threshold = 0.5
+
+            [[downsample]]
+            measurement = "current"
+            interval = "1min"
+            aggregate = "max"
+
+            [[rename]]
+            measurement = "environment.temperature"
+            to = "temperature_f"
+            "#,
+        )
+        .unwrap();
+
+        let convert = &stages.convert[0];
+        assert_eq!(
+            convert.selector.measurement,
+            MeasurementPath::new(Some("environment"), "temperature")
+        );
+        assert_eq!(convert.apply(100.0), 212.0);
+
+        let deadband = &stages.deadband[0];
+        assert!(deadband
+            .selector
+            .matches("environment", &MeasurementPath::new(None, "pressure")));
+        assert!(!deadband
+            .selector
+            .matches("weather", &MeasurementPath::new(None, "pressure")));
+
+        let downsample = &stages.downsample[0];
+        assert_eq!(downsample.interval, Duration::from_secs(60));
+        assert_eq!(downsample.aggregate, Aggregate::Max);
+
+        assert_eq!(stages.rename[0].to, "temperature_f");
+    }
+
+    #[test]
+    fn a_missing_file_defines_an_empty_pipeline() {
+        let stages = PipelineStages::read(Utf8Path::new("/does/not/exist/pipeline.toml")).unwrap();
+        assert!(stages.is_empty());
+    }
+}
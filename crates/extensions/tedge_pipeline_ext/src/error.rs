@@ -0,0 +1,28 @@
+use camino::Utf8PathBuf;
+use tedge_api::measurement::MeasurementGrouperError;
+use tedge_api::measurement::ThinEdgeJsonSerializationError;
+use tedge_api::measurement::ThinEdgePayloadParserError;
+
+#[derive(thiserror::Error, Debug)]
+pub enum PipelineError {
+    #[error("Fail to read the measurement pipeline from {path}: {source}")]
+    ReadConfig {
+        path: Utf8PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("Invalid measurement pipeline {path}: {source}")]
+    InvalidConfig {
+        path: Utf8PathBuf,
+        source: toml::de::Error,
+    },
+
+    #[error(transparent)]
+    InvalidMeasurement(#[from] ThinEdgePayloadParserError),
+
+    #[error(transparent)]
+    Serialization(#[from] ThinEdgeJsonSerializationError),
+
+    #[error(transparent)]
+    Grouping(#[from] MeasurementGrouperError),
+}
@@ -0,0 +1,107 @@
+mod actor;
+mod config;
+mod error;
+mod pipeline;
+
+#[cfg(test)]
+mod tests;
+
+pub use actor::*;
+pub use config::*;
+pub use error::*;
+pub use pipeline::*;
+
+use std::convert::Infallible;
+use std::path::PathBuf;
+use tedge_actors::Builder;
+use tedge_actors::CloneSender;
+use tedge_actors::DynSender;
+use tedge_actors::MessageSink;
+use tedge_actors::MessageSource;
+use tedge_actors::NoMessage;
+use tedge_actors::RuntimeRequest;
+use tedge_actors::RuntimeRequestSink;
+use tedge_actors::SimpleMessageBoxBuilder;
+use tedge_file_system_ext::FsWatchEvent;
+use tedge_mqtt_ext::MqttMessage;
+use tedge_mqtt_ext::TopicFilter;
+use tedge_utils::file::create_directory_with_defaults;
+use tedge_utils::file::FileError;
+
+/// Builder of a [MeasurementPipelineActor], interposed between the MQTT actor and the converter of a mapper
+///
+/// The pipeline is connected to the converter in place of the MQTT actor:
+/// the messages published by the converter are sent directly to MQTT,
+/// while the messages the converter subscribes to are received from the pipeline.
+pub struct MeasurementPipelineBuilder {
+    config: MeasurementPipelineConfig,
+    box_builder: SimpleMessageBoxBuilder<PipelineInput, NoMessage>,
+    mqtt_publisher: DynSender<MqttMessage>,
+    subscriptions: TopicFilter,
+    peers: Vec<(TopicFilter, DynSender<MqttMessage>)>,
+}
+
+impl MeasurementPipelineBuilder {
+    pub fn new(
+        config: MeasurementPipelineConfig,
+        mqtt: &impl MessageSink<MqttMessage>,
+        fs_notify: &mut impl MessageSource<FsWatchEvent, PathBuf>,
+    ) -> Self {
+        let box_builder = SimpleMessageBoxBuilder::new("Measurement Pipeline", 16);
+        fs_notify.connect_sink(
+            config.config_dir.clone().into_std_path_buf(),
+            &box_builder.get_sender(),
+        );
+
+        MeasurementPipelineBuilder {
+            config,
+            box_builder,
+            mqtt_publisher: mqtt.get_sender(),
+            subscriptions: TopicFilter::empty(),
+            peers: Vec::new(),
+        }
+    }
+
+    /// Create the directory watched for changes of the pipeline definition
+    pub async fn init(config: &MeasurementPipelineConfig) -> Result<(), FileError> {
+        create_directory_with_defaults(&config.config_dir).await
+    }
+
+    /// Subscribe to the MQTT topics the peers of the pipeline are interested in
+    ///
+    /// This has to be called once all the peers have been connected to the pipeline.
+    pub fn connect_mqtt(&mut self, mqtt: &mut impl MessageSource<MqttMessage, TopicFilter>) {
+        mqtt.connect_sink(self.subscriptions.clone(), &self.box_builder.get_sender());
+    }
+}
+
+impl MessageSource<MqttMessage, TopicFilter> for MeasurementPipelineBuilder {
+    fn connect_sink(&mut self, subscriptions: TopicFilter, peer: &impl MessageSink<MqttMessage>) {
+        self.subscriptions.add_all(subscriptions.clone());
+        self.peers.push((subscriptions, peer.get_sender()));
+    }
+}
+
+impl MessageSink<MqttMessage> for MeasurementPipelineBuilder {
+    fn get_sender(&self) -> DynSender<MqttMessage> {
+        self.mqtt_publisher.sender_clone()
+    }
+}
+
+impl RuntimeRequestSink for MeasurementPipelineBuilder {
+    fn get_signal_sender(&self) -> DynSender<RuntimeRequest> {
+        self.box_builder.get_signal_sender()
+    }
+}
+
+impl Builder<MeasurementPipelineActor> for MeasurementPipelineBuilder {
+    type Error = Infallible;
+
+    fn try_build(self) -> Result<MeasurementPipelineActor, Self::Error> {
+        Ok(self.build())
+    }
+
+    fn build(self) -> MeasurementPipelineActor {
+        MeasurementPipelineActor::new(self.config, self.box_builder.build(), self.peers)
+    }
+}
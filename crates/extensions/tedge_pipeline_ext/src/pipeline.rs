@@ -0,0 +1,473 @@
+//! Apply the stages of a pipeline to thin-edge JSON measurements.
+//!
+//! A measurement is processed while being parsed, the values it contains being visited one after the other:
+//! each value is converted, filtered or kept for downsampling, then renamed and serialized.
+//! The values being downsampled are aggregated with a [MeasurementGrouper] when their interval ends.
+use crate::config::Aggregate;
+use crate::config::MeasurementPath;
+use crate::config::PipelineStages;
+use crate::error::PipelineError;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::time::Duration;
+use tedge_api::measurement::parse_payload;
+use tedge_api::measurement::MeasurementGrouper;
+use tedge_api::measurement::MeasurementVisitor;
+use tedge_api::measurement::ThinEdgeJsonSerializationError;
+use tedge_api::measurement::ThinEdgeJsonSerializer;
+use time::OffsetDateTime;
+use tokio::time::Instant;
+
+/// A measurement pipeline, along with the state of its stages
+#[derive(Default)]
+pub struct Pipeline {
+    stages: PipelineStages,
+
+    /// The last value forwarded by a deadband stage, per topic and measurement
+    deadbands: HashMap<(String, MeasurementPath), f64>,
+
+    /// The values being downsampled, per topic and interval
+    windows: HashMap<(String, Duration), Window>,
+}
+
+/// The values received on a topic over a downsampling interval
+struct Window {
+    measurement_type: String,
+    start: Instant,
+    values: BTreeMap<MeasurementPath, Aggregator>,
+}
+
+struct Aggregator {
+    aggregate: Aggregate,
+    count: u32,
+    sum: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Pipeline {
+    pub fn new(stages: PipelineStages) -> Self {
+        Pipeline {
+            stages,
+            ..Pipeline::default()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    /// Apply the pipeline to a thin-edge JSON or CBOR measurement received on some topic
+    ///
+    /// Return the measurement to be forwarded, if any:
+    /// a measurement is dropped when all its values have been filtered out or kept for downsampling.
+    pub fn process(
+        &mut self,
+        topic: &str,
+        measurement_type: &str,
+        payload: &[u8],
+        now: Instant,
+    ) -> Result<Option<String>, PipelineError> {
+        let mut visitor = PipelineVisitor {
+            pipeline: self,
+            topic,
+            measurement_type,
+            now,
+            output: ThinEdgeJsonSerializer::new(),
+            group: None,
+            group_started: false,
+            received: 0,
+            forwarded: 0,
+        };
+        parse_payload(payload, &mut visitor)?;
+
+        if visitor.received > 0 && visitor.forwarded == 0 {
+            return Ok(None);
+        }
+        Ok(Some(visitor.output.into_string()?))
+    }
+
+    /// Aggregate the values of the downsampling intervals that are over
+    ///
+    /// Return the aggregated measurements along with the topics they have been received on.
+    pub fn flush(&mut self, now: Instant) -> Result<Vec<(String, String)>, PipelineError> {
+        self.flush_windows(|(_, interval), window| window.start + *interval <= now)
+    }
+
+    /// Aggregate the values of all the downsampling intervals, whether over or not
+    pub fn flush_all(&mut self) -> Result<Vec<(String, String)>, PipelineError> {
+        self.flush_windows(|_, _| true)
+    }
+
+    fn flush_windows(
+        &mut self,
+        is_over: impl Fn(&(String, Duration), &Window) -> bool,
+    ) -> Result<Vec<(String, String)>, PipelineError> {
+        let ended: Vec<_> = self
+            .windows
+            .iter()
+            .filter(|(key, window)| is_over(key, window))
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        let timestamp = OffsetDateTime::now_utc();
+        let mut aggregated = Vec::new();
+        for key in ended {
+            if let Some(window) = self.windows.remove(&key) {
+                aggregated.push((key.0, self.aggregate(window, timestamp)?));
+            }
+        }
+        Ok(aggregated)
+    }
+
+    fn aggregate(
+        &self,
+        window: Window,
+        timestamp: OffsetDateTime,
+    ) -> Result<String, PipelineError> {
+        let mut grouper = MeasurementGrouper::new();
+        grouper.visit_timestamp(timestamp)?;
+        for (measurement, aggregator) in &window.values {
+            let name = self
+                .renamed(&window.measurement_type, measurement)
+                .unwrap_or(&measurement.name);
+            match &measurement.group {
+                Some(group) => {
+                    grouper.visit_grouped_measurement(group, name, aggregator.value())?
+                }
+                None => grouper.visit_measurement(name, aggregator.value())?,
+            }
+        }
+
+        let mut output = ThinEdgeJsonSerializer::new();
+        grouper.end()?.accept(&mut output)?;
+        Ok(output.into_string()?)
+    }
+
+    /// Apply the conversion, deadband and downsampling stages to a value
+    ///
+    /// Return the value to be forwarded, if not filtered out or kept for downsampling.
+    fn apply(
+        &mut self,
+        topic: &str,
+        measurement_type: &str,
+        measurement: &MeasurementPath,
+        value: f64,
+        now: Instant,
+    ) -> Option<f64> {
+        let mut value = value;
+        for stage in &self.stages.convert {
+            if stage.selector.matches(measurement_type, measurement) {
+                value = stage.apply(value);
+            }
+        }
+
+        for stage in &self.stages.deadband {
+            if stage.selector.matches(measurement_type, measurement) {
+                let key = (topic.to_string(), measurement.clone());
+                match self.deadbands.get(&key) {
+                    Some(last) if (value - last).abs() < stage.threshold => return None,
+                    _ => {
+                        self.deadbands.insert(key, value);
+                    }
+                }
+            }
+        }
+
+        for stage in &self.stages.downsample {
+            if stage.selector.matches(measurement_type, measurement) {
+                let window = self
+                    .windows
+                    .entry((topic.to_string(), stage.interval))
+                    .or_insert_with(|| Window {
+                        measurement_type: measurement_type.to_string(),
+                        start: now,
+                        values: BTreeMap::new(),
+                    });
+                window
+                    .values
+                    .entry(measurement.clone())
+                    .or_insert_with(|| Aggregator::new(stage.aggregate))
+                    .add(value);
+                return None;
+            }
+        }
+
+        Some(value)
+    }
+
+    /// The new name of a measurement, if renamed
+    fn renamed(&self, measurement_type: &str, measurement: &MeasurementPath) -> Option<&str> {
+        self.stages
+            .rename
+            .iter()
+            .find(|stage| stage.selector.matches(measurement_type, measurement))
+            .map(|stage| stage.to.as_str())
+    }
+}
+
+impl Aggregator {
+    fn new(aggregate: Aggregate) -> Self {
+        Aggregator {
+            aggregate,
+            count: 0,
+            sum: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    fn add(&mut self, value: f64) {
+        self.count += 1;
+        self.sum += value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    fn value(&self) -> f64 {
+        match self.aggregate {
+            Aggregate::Average => self.sum / self.count as f64,
+            Aggregate::Min => self.min,
+            Aggregate::Max => self.max,
+        }
+    }
+}
+
+/// Apply the pipeline to the values of a measurement as they are parsed,
+/// serializing the values that are forwarded.
+struct PipelineVisitor<'a> {
+    pipeline: &'a mut Pipeline,
+    topic: &'a str,
+    measurement_type: &'a str,
+    now: Instant,
+    output: ThinEdgeJsonSerializer,
+
+    /// The current group, which is serialized only if some of its values are forwarded
+    group: Option<String>,
+    group_started: bool,
+
+    received: usize,
+    forwarded: usize,
+}
+
+impl PipelineVisitor<'_> {
+    fn start_group(&mut self) -> Result<(), ThinEdgeJsonSerializationError> {
+        if let Some(group) = &self.group {
+            if !self.group_started {
+                self.output.visit_start_group(group)?;
+                self.group_started = true;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl MeasurementVisitor for PipelineVisitor<'_> {
+    type Error = ThinEdgeJsonSerializationError;
+
+    fn visit_timestamp(&mut self, value: OffsetDateTime) -> Result<(), Self::Error> {
+        self.output.visit_timestamp(value)
+    }
+
+    fn visit_measurement(&mut self, name: &str, value: f64) -> Result<(), Self::Error> {
+        self.received += 1;
+        let measurement = MeasurementPath::new(self.group.as_deref(), name);
+        let Some(value) = self.pipeline.apply(
+            self.topic,
+            self.measurement_type,
+            &measurement,
+            value,
+            self.now,
+        ) else {
+            return Ok(());
+        };
+
+        self.start_group()?;
+        let name = self
+            .pipeline
+            .renamed(self.measurement_type, &measurement)
+            .unwrap_or(name);
+        self.output.visit_measurement(name, value)?;
+        self.forwarded += 1;
+        Ok(())
+    }
+
+    fn visit_text_property(&mut self, name: &str, value: &str) -> Result<(), Self::Error> {
+        self.start_group()?;
+        self.output.visit_text_property(name, value)
+    }
+
+    fn visit_start_group(&mut self, group: &str) -> Result<(), Self::Error> {
+        self.group = Some(group.to_string());
+        self.group_started = false;
+        Ok(())
+    }
+
+    fn visit_end_group(&mut self) -> Result<(), Self::Error> {
+        if self.group_started {
+            self.output.visit_end_group()?;
+        }
+        self.group = None;
+        self.group_started = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use serde_json::Value;
+
+    const TOPIC: &str = "te/device/main///m/environment";
+
+    fn pipeline(stages: &str) -> Pipeline {
+        Pipeline::new(toml::from_str(stages).unwrap())
+    }
+
+    fn process(pipeline: &mut Pipeline, payload: Value, now: Instant) -> Option<Value> {
+        pipeline
+            .process(TOPIC, "environment", payload.to_string().as_bytes(), now)
+            .unwrap()
+            .map(|output| serde_json::from_str(&output).unwrap())
+    }
+
+    #[test]
+    fn convert_and_rename_measurements() {
+        let mut pipeline = pipeline(
+            r#"
+            [[convert]]
+            measurement = "env.temperature"
+            scale = 1.8
+            offset = 32
+
+            [[rename]]
+            measurement = "env.temperature"
+            to = "temperature_f"
+
+            [[rename]]
+            measurement = "temperature"
+            type = "weather"
+            to = "ignored"
+            "#,
+        );
+
+        let output = process(
+            &mut pipeline,
+            json!({"type": "lab", "temperature": 20, "env": {"temperature": 100, "humidity": 45}}),
+            Instant::now(),
+        );
+        assert_eq!(
+            output,
+            Some(json!({
+                "type": "lab",
+                "temperature": 20.0,
+                "env": {"temperature_f": 212.0, "humidity": 45.0}
+            }))
+        );
+    }
+
+    #[test]
+    fn deadband_drops_small_changes() {
+        let mut pipeline = pipeline(
+            r#"
+            [[deadband]]
+            measurement = "pressure"
+            threshold = 1.0
+            "#,
+        );
+        let now = Instant::now();
+
+        let forwarded: Vec<_> = [1000.0, 1000.5, 998.9, 1000.0, 999.5]
+            .into_iter()
+            .map(|pressure| process(&mut pipeline, json!({"pressure": pressure}), now))
+            .collect();
+        assert_eq!(
+            forwarded,
+            vec![
+                Some(json!({"pressure": 1000.0})),
+                None,
+                Some(json!({"pressure": 998.9})),
+                Some(json!({"pressure": 1000.0})),
+                None,
+            ]
+        );
+
+        // Only the values under a deadband are dropped
+        assert_eq!(
+            process(
+                &mut pipeline,
+                json!({"pressure": 1000.4, "humidity": 40}),
+                now
+            ),
+            Some(json!({"humidity": 40.0}))
+        );
+    }
+
+    #[test]
+    fn downsample_measurements_over_an_interval() {
+        let mut pipeline = pipeline(
+            r#"
+            [[downsample]]
+            measurement = "temperature"
+            interval = "10s"
+
+            [[downsample]]
+            measurement = "env.humidity"
+            interval = "10s"
+            aggregate = "max"
+
+            [[rename]]
+            measurement = "temperature"
+            to = "avg_temperature"
+            "#,
+        );
+        let start = Instant::now();
+
+        for (i, (temperature, humidity)) in [(20.0, 40.0), (21.0, 45.0), (25.0, 42.0)]
+            .into_iter()
+            .enumerate()
+        {
+            let now = start + Duration::from_secs(i as u64 * 3);
+            let output = process(
+                &mut pipeline,
+                json!({"temperature": temperature, "env": {"humidity": humidity}}),
+                now,
+            );
+            assert_eq!(output, None);
+        }
+
+        assert!(pipeline
+            .flush(start + Duration::from_secs(9))
+            .unwrap()
+            .is_empty());
+
+        let aggregated = pipeline.flush(start + Duration::from_secs(10)).unwrap();
+        assert_eq!(aggregated.len(), 1);
+        let (topic, payload) = &aggregated[0];
+        assert_eq!(topic, TOPIC);
+        let mut payload: Value = serde_json::from_str(payload).unwrap();
+        assert!(payload["time"].is_string());
+        payload.as_object_mut().unwrap().remove("time");
+        assert_eq!(
+            payload,
+            json!({"avg_temperature": 22.0, "env": {"humidity": 45.0}})
+        );
+
+        assert!(pipeline.flush_all().unwrap().is_empty());
+    }
+
+    #[test]
+    fn invalid_measurements_are_rejected() {
+        let mut pipeline = pipeline(
+            r#"
+            [[rename]]
+            measurement = "temperature"
+            to = "temp"
+            "#,
+        );
+        assert!(pipeline
+            .process(TOPIC, "environment", b"not a measurement", Instant::now())
+            .is_err());
+    }
+}
@@ -0,0 +1,172 @@
+use crate::MeasurementPipelineBuilder;
+use crate::MeasurementPipelineConfig;
+use serde_json::json;
+use serde_json::Value;
+use std::time::Duration;
+use tedge_actors::test_helpers::MessageReceiverExt;
+use tedge_actors::test_helpers::TimedMessageBox;
+use tedge_actors::Actor;
+use tedge_actors::Builder;
+use tedge_actors::MessageReceiver;
+use tedge_actors::MessageSource;
+use tedge_actors::NoMessage;
+use tedge_actors::Sender;
+use tedge_actors::SimpleMessageBox;
+use tedge_actors::SimpleMessageBoxBuilder;
+use tedge_api::mqtt_topics::MqttSchema;
+use tedge_file_system_ext::FsWatchEvent;
+use tedge_mqtt_ext::MqttMessage;
+use tedge_mqtt_ext::Topic;
+use tedge_mqtt_ext::TopicFilter;
+use tedge_test_utils::fs::TempTedgeDir;
+
+const TEST_TIMEOUT: Duration = Duration::from_secs(3);
+
+const RENAME_TEMPERATURE: &str = r#"
+[[rename]]
+measurement = "temperature"
+to = "temp"
+"#;
+
+/// Spawn a pipeline for the c8y mapper, returning the boxes used to interact with it:
+/// - an MQTT box, to send the messages received from MQTT
+/// - a converter box, to receive the messages forwarded to the converter
+/// - a file system box, to notify the pipeline of file changes
+async fn spawn_pipeline(
+    ttd: &TempTedgeDir,
+) -> (
+    SimpleMessageBox<MqttMessage, MqttMessage>,
+    TimedMessageBox<SimpleMessageBox<MqttMessage, NoMessage>>,
+    SimpleMessageBox<NoMessage, FsWatchEvent>,
+) {
+    let config = MeasurementPipelineConfig::new(MqttSchema::default(), ttd.utf8_path(), "c8y");
+    MeasurementPipelineBuilder::init(&config).await.unwrap();
+
+    let mut mqtt_builder: SimpleMessageBoxBuilder<MqttMessage, MqttMessage> =
+        SimpleMessageBoxBuilder::new("MQTT", 16);
+    let converter_builder: SimpleMessageBoxBuilder<MqttMessage, NoMessage> =
+        SimpleMessageBoxBuilder::new("Converter", 16);
+    let mut fs_builder: SimpleMessageBoxBuilder<NoMessage, FsWatchEvent> =
+        SimpleMessageBoxBuilder::new("FS", 16);
+
+    let mut pipeline_builder =
+        MeasurementPipelineBuilder::new(config, &mqtt_builder, &mut fs_builder);
+    let mut subscriptions = TopicFilter::new_unchecked("te/+/+/+/+/m/+");
+    subscriptions.add_unchecked("te/+/+/+/+/e/+");
+    pipeline_builder.connect_sink(subscriptions, &converter_builder);
+    pipeline_builder.connect_mqtt(&mut mqtt_builder);
+
+    let actor = pipeline_builder.build();
+    tokio::spawn(async move { actor.run().await });
+
+    (
+        mqtt_builder.build(),
+        converter_builder.build().with_timeout(TEST_TIMEOUT),
+        fs_builder.build(),
+    )
+}
+
+fn message(topic: &str, payload: Value) -> MqttMessage {
+    MqttMessage::new(&Topic::new_unchecked(topic), payload.to_string())
+}
+
+async fn assert_received(
+    converter: &mut TimedMessageBox<SimpleMessageBox<MqttMessage, NoMessage>>,
+    topic: &str,
+    payload: Value,
+) {
+    let received = converter.recv().await.expect("a message");
+    assert_eq!(received.topic.name, topic);
+    let received_payload: Value = serde_json::from_str(received.payload_str().unwrap()).unwrap();
+    assert_eq!(received_payload, payload);
+}
+
+#[tokio::test]
+async fn measurements_are_pre_processed() {
+    let ttd = TempTedgeDir::new();
+    ttd.dir("mappers")
+        .dir("c8y")
+        .file("pipeline.toml")
+        .with_raw_content(RENAME_TEMPERATURE);
+    let (mut mqtt, mut converter, _fs) = spawn_pipeline(&ttd).await;
+
+    mqtt.send(message(
+        "te/device/main///m/environment",
+        json!({"temperature": 21.5, "humidity": 45.0}),
+    ))
+    .await
+    .unwrap();
+
+    assert_received(
+        &mut converter,
+        "te/device/main///m/environment",
+        json!({"temp": 21.5, "humidity": 45.0}),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn other_messages_are_forwarded_unchanged() {
+    let ttd = TempTedgeDir::new();
+    ttd.dir("mappers")
+        .dir("c8y")
+        .file("pipeline.toml")
+        .with_raw_content(RENAME_TEMPERATURE);
+    let (mut mqtt, mut converter, _fs) = spawn_pipeline(&ttd).await;
+
+    let event = message(
+        "te/device/main///e/login",
+        json!({"text": "user logged in", "temperature": 21.5}),
+    );
+    mqtt.send(event.clone()).await.unwrap();
+    assert_eq!(converter.recv().await, Some(event));
+
+    // Invalid measurements are left to the converter to report
+    let invalid = MqttMessage::new(
+        &Topic::new_unchecked("te/device/main///m/environment"),
+        "not a measurement",
+    );
+    mqtt.send(invalid.clone()).await.unwrap();
+    assert_eq!(converter.recv().await, Some(invalid));
+}
+
+#[tokio::test]
+async fn pipeline_is_reloaded_on_change() {
+    let ttd = TempTedgeDir::new();
+    let (mut mqtt, mut converter, mut fs) = spawn_pipeline(&ttd).await;
+
+    mqtt.send(message(
+        "te/device/main///m/environment",
+        json!({"temperature": 21.5}),
+    ))
+    .await
+    .unwrap();
+    assert_received(
+        &mut converter,
+        "te/device/main///m/environment",
+        json!({"temperature": 21.5}),
+    )
+    .await;
+
+    let pipeline_file = ttd
+        .dir("mappers")
+        .dir("c8y")
+        .file("pipeline.toml")
+        .with_raw_content(RENAME_TEMPERATURE);
+    fs.send(FsWatchEvent::Modified(pipeline_file.to_path_buf()))
+        .await
+        .unwrap();
+
+    mqtt.send(message(
+        "te/device/main///m/environment",
+        json!({"temperature": 22.0}),
+    ))
+    .await
+    .unwrap();
+    assert_received(
+        &mut converter,
+        "te/device/main///m/environment",
+        json!({"temp": 22.0}),
+    )
+    .await;
+}
@@ -92,18 +92,58 @@ impl Server for ScriptActor {
     }
 }
 
-async fn kill_on_timeout(
+/// Send a SIGTERM to a process after a graceful timeout, then a SIGKILL after a forceful timeout
+///
+/// Return an error if the process is still running one second after the SIGKILL.
+/// This future is expected to be cancelled as soon as the process exits.
+pub async fn kill_on_timeout(
     pid: u32,
     graceful_timeout: Duration,
     forceful_timeout: Duration,
 ) -> std::io::Error {
     let pid = nix::unistd::Pid::from_raw(pid as nix::libc::pid_t);
+    signal_on_timeout(
+        |signal| nix::sys::signal::kill(pid, signal),
+        graceful_timeout,
+        forceful_timeout,
+    )
+    .await
+}
 
+/// Same as [kill_on_timeout], but signaling the whole process group led by the given process
+///
+/// The process must have been spawned in its own process group,
+/// so the sub-processes, notably those run by `sudo`, are killed along the leader.
+pub async fn kill_group_on_timeout(
+    pgid: u32,
+    graceful_timeout: Duration,
+    forceful_timeout: Duration,
+) -> std::io::Error {
+    let pgid = nix::unistd::Pid::from_raw(pgid as nix::libc::pid_t);
+    signal_on_timeout(
+        |signal| nix::sys::signal::killpg(pgid, signal),
+        graceful_timeout,
+        forceful_timeout,
+    )
+    .await
+}
+
+/// Kill immediately the process group led by the given process
+pub fn kill_group(pgid: u32) {
+    let pgid = nix::unistd::Pid::from_raw(pgid as nix::libc::pid_t);
+    let _ = nix::sys::signal::killpg(pgid, nix::sys::signal::SIGKILL);
+}
+
+async fn signal_on_timeout(
+    send: impl Fn(nix::sys::signal::Signal) -> nix::Result<()>,
+    graceful_timeout: Duration,
+    forceful_timeout: Duration,
+) -> std::io::Error {
     tokio::time::sleep(graceful_timeout).await;
-    let _ = nix::sys::signal::kill(pid, nix::sys::signal::SIGTERM);
+    let _ = send(nix::sys::signal::SIGTERM);
 
     tokio::time::sleep(forceful_timeout).await;
-    let _ = nix::sys::signal::kill(pid, nix::sys::signal::SIGKILL);
+    let _ = send(nix::sys::signal::SIGKILL);
 
     tokio::time::sleep(Duration::from_secs(1)).await;
     std::io::Error::new(
@@ -76,7 +76,7 @@ async fn run_with(
     let mut runtime = Runtime::new();
 
     // Create actor instances
-    let mqtt_config = tedge_config.mqtt_config()?;
+    let mqtt_config = tedge_config.service_mqtt_config(PLUGIN_NAME)?;
     let identity = tedge_config.http.client.auth.identity()?;
     let cloud_root_certs = tedge_config.cloud_root_certs();
     let mut downloader_actor = DownloaderActor::new(identity, cloud_root_certs).builder();